@@ -0,0 +1,423 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Meld staged changes into the commits in the stack they belong to
+///
+/// Each staged hunk is assigned to the commit that last touched the lines it changes and a
+/// `fixup!` commit is created for it, like `git absorb`.  Hunks that touch lines from more than one
+/// commit, or from outside of the stack, are left staged.
+///
+/// With `--and-rebase`, the fixups are squashed into their commits, rebasing descendants on top.
+#[derive(clap::Args)]
+pub(crate) struct AbsorbArgs {
+    /// Squash the fixups into their commits
+    #[arg(short = 'r', long)]
+    and_rebase: bool,
+
+    /// Don't actually switch
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
+}
+
+impl AbsorbArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let head_id = repo.head_commit().id;
+        let base = crate::ops::resolve_implicit_base(
+            &repo,
+            head_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let merge_base_oid = repo
+            .merge_base(base.id, head_id)
+            .ok_or_else(|| {
+                git2::Error::new(
+                    git2::ErrorCode::NotFound,
+                    git2::ErrorClass::Reference,
+                    format!("could not find base between {base} and HEAD"),
+                )
+            })
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_branches = branches.descendants(&repo, merge_base_oid);
+        let mut graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::protect_branches(&mut graph);
+        let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_ignored(&mut graph, &ignored);
+        git_stack::graph::mark_fixup(&mut graph, &repo);
+        git_stack::graph::mark_wip(&mut graph, &repo);
+        let anchors =
+            git_stack::git::Anchors::from_repo(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_anchors(&mut graph, &anchors);
+
+        if repo.raw().state() != git2::RepositoryState::Clean {
+            let message = format!("cannot walk commits, {:?} in progress", repo.raw().state());
+            if self.dry_run {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: {}",
+                    stderr_palette.error("error"),
+                    message
+                );
+            } else {
+                return Err(proc_exit::sysexits::USAGE_ERR.with_message(message));
+            }
+        }
+
+        // The commits fixups can target, newest first
+        let mut stack_ids = Vec::new();
+        let mut current_id = Some(head_id);
+        while let Some(id) = current_id {
+            let action = graph
+                .commit_get::<git_stack::graph::Action>(id)
+                .copied()
+                .unwrap_or_default();
+            if action.is_protected() {
+                break;
+            }
+            stack_ids.push(id);
+            current_id = graph.primary_parent_of(id);
+        }
+        if stack_ids.is_empty() {
+            return Err(
+                proc_exit::Code::FAILURE.with_message("cannot absorb into protected commits")
+            );
+        }
+
+        let absorbed =
+            assign_hunks(&repo, head_id, &stack_ids).with_code(proc_exit::Code::FAILURE)?;
+        if absorbed.assigned.is_empty() {
+            let _ = writeln!(
+                anstream::stderr(),
+                "{} nothing to absorb, no staged hunk belongs to a single commit in the stack",
+                stderr_palette.error("error:"),
+            );
+            return Err(proc_exit::Code::FAILURE.as_exit());
+        }
+
+        for (target_id, hunks) in &absorbed.assigned {
+            let target = repo.find_commit(*target_id).expect("stack commits exist");
+            let abbrev_id = repo
+                .raw()
+                .find_object(*target_id, None)
+                .unwrap_or_else(|e| panic!("Unexpected git2 error: {e}"))
+                .short_id()
+                .unwrap_or_else(|e| panic!("Unexpected git2 error: {e}"));
+            let _ = writeln!(
+                anstream::stderr(),
+                "{} {} {} into {}: {}",
+                stderr_palette.good("Absorbing"),
+                hunks.len(),
+                if hunks.len() == 1 { "hunk" } else { "hunks" },
+                stderr_palette.highlight(abbrev_id.as_str().unwrap()),
+                stderr_palette.hint(target.display_summary())
+            );
+        }
+        if absorbed.unassigned != 0 {
+            log::warn!(
+                "Leaving {} {} staged, they don't belong to a single commit in the stack",
+                absorbed.unassigned,
+                if absorbed.unassigned == 1 {
+                    "hunk"
+                } else {
+                    "hunks"
+                }
+            );
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "absorb",
+            None,
+            self.dry_run,
+        )?;
+
+        let fixup_ids = commit_fixups(&mut repo, &graph.branches, head_id, &absorbed)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut success = true;
+        if self.and_rebase {
+            let mut parent_id = head_id;
+            for fixup_id in fixup_ids {
+                graph.insert(git_stack::graph::Node::new(fixup_id), parent_id);
+                graph.commit_set(fixup_id, git_stack::graph::Fixup);
+                parent_id = fixup_id;
+            }
+            graph
+                .branches
+                .update(&repo)
+                .with_code(proc_exit::Code::FAILURE)?;
+
+            git_stack::graph::fixup(&mut graph, &repo, git_stack::config::Fixup::Squash);
+            let scripts = git_stack::graph::to_scripts(&graph, vec![]);
+
+            let stash_id = git_stack::git::stash_push(&mut repo, "absorb");
+
+            let head_branch = repo.head_branch();
+            let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
+            executor.set_run_hooks(!self.no_hooks);
+            success = crate::resume::run_scripts(
+                &mut repo,
+                &mut executor,
+                scripts,
+                head_branch.as_ref().and_then(|b| b.local_name()),
+                stash_id,
+            )?;
+
+            git_stack::git::stash_pop(&mut repo, stash_id);
+        }
+        if backed_up {
+            crate::ops::note_undo();
+        }
+
+        if success {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.as_exit())
+        }
+    }
+}
+
+/// Staged hunks, grouped by the commit they belong to
+struct Absorbed {
+    /// Oldest commit first
+    assigned: Vec<(git2::Oid, Vec<HunkId>)>,
+    unassigned: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct HunkId {
+    path: std::path::PathBuf,
+    old_start: u32,
+    old_lines: u32,
+    new_start: u32,
+    new_lines: u32,
+}
+
+impl HunkId {
+    fn new(path: &std::path::Path, hunk: &git2::DiffHunk<'_>) -> Self {
+        Self {
+            path: path.to_owned(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+        }
+    }
+}
+
+/// Find the commit in `stack_ids` that each staged hunk belongs to
+///
+/// A hunk belongs to the commit that last changed every line it removes or, for hunks that only
+/// add lines, the lines around it.
+fn assign_hunks(
+    repo: &git_stack::git::GitRepo,
+    head_id: git2::Oid,
+    stack_ids: &[git2::Oid],
+) -> Result<Absorbed, eyre::Error> {
+    let raw = repo.raw();
+    let head_tree = raw.find_commit(head_id)?.tree()?;
+    let diff = staged_diff(raw, &head_tree)?;
+    let bottom_id = *stack_ids.last().expect("stack is non-empty");
+
+    let mut assigned: std::collections::HashMap<git2::Oid, Vec<HunkId>> = Default::default();
+    let mut unassigned = 0;
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let Some(patch) = git2::Patch::from_diff(&diff, delta_idx)? else {
+            continue;
+        };
+        let path = delta.old_file().path();
+        let blame = match (delta.status(), path) {
+            (git2::Delta::Modified, Some(path)) => {
+                let mut options = git2::BlameOptions::new();
+                options.newest_commit(head_id);
+                if let Some(parent_id) = repo.parent_ids(bottom_id)?.first() {
+                    options.oldest_commit(*parent_id);
+                }
+                raw.blame_file(path, Some(&mut options)).ok()
+            }
+            _ => None,
+        };
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, _) = patch.hunk(hunk_idx)?;
+            let target_id = blame
+                .as_ref()
+                .and_then(|blame| blame_hunk(blame, &hunk))
+                .filter(|id| stack_ids.contains(id));
+            match (target_id, path) {
+                (Some(target_id), Some(path)) => {
+                    assigned
+                        .entry(target_id)
+                        .or_default()
+                        .push(HunkId::new(path, &hunk));
+                }
+                _ => {
+                    unassigned += 1;
+                }
+            }
+        }
+    }
+
+    let assigned = stack_ids
+        .iter()
+        .rev()
+        .filter_map(|id| assigned.remove(id).map(|hunks| (*id, hunks)))
+        .collect();
+    Ok(Absorbed {
+        assigned,
+        unassigned,
+    })
+}
+
+fn staged_diff<'r>(
+    repo: &'r git2::Repository,
+    head_tree: &git2::Tree<'_>,
+) -> Result<git2::Diff<'r>, git2::Error> {
+    let index = repo.index()?;
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(0);
+    repo.diff_tree_to_index(Some(head_tree), Some(&index), Some(&mut options))
+}
+
+fn blame_hunk(blame: &git2::Blame<'_>, hunk: &git2::DiffHunk<'_>) -> Option<git2::Oid> {
+    let lines = if hunk.old_lines() == 0 {
+        // Pure additions go after `old_start`, so look at the lines on either side
+        (hunk.old_start().max(1)..=hunk.old_start() + 1).collect::<Vec<_>>()
+    } else {
+        (hunk.old_start()..hunk.old_start() + hunk.old_lines()).collect()
+    };
+    let mut ids = lines
+        .into_iter()
+        .filter_map(|line| blame.get_line(line as usize))
+        .map(|blame_hunk| blame_hunk.final_commit_id());
+    let first = ids.next()?;
+    ids.all(|id| id == first).then_some(first)
+}
+
+/// Commit a `fixup!` for each assigned commit on top of `head_id`, moving its branches along
+///
+/// The index is left alone, so only the hunks that weren't absorbed remain staged.
+fn commit_fixups(
+    repo: &mut git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+    head_id: git2::Oid,
+    absorbed: &Absorbed,
+) -> Result<Vec<git2::Oid>, eyre::Error> {
+    let fixup_ids = {
+        let raw = repo.raw();
+        let head_commit = raw.find_commit(head_id)?;
+        let base_tree = head_commit.tree()?;
+        let diff = staged_diff(raw, &base_tree)?;
+
+        let mut fixup_ids = Vec::new();
+        let mut selected = std::collections::HashSet::new();
+        let mut parent_commit = head_commit.clone();
+        for (target_id, hunks) in &absorbed.assigned {
+            // Each tree is applied to HEAD's tree so the hunks' line numbers stay accurate
+            selected.extend(hunks.iter().cloned());
+            let current_path = std::cell::RefCell::new(None);
+            let mut options = git2::ApplyOptions::new();
+            options.delta_callback(|delta| {
+                let path = delta.and_then(|d| d.old_file().path().map(ToOwned::to_owned));
+                let keep = path
+                    .as_ref()
+                    .map(|path| selected.iter().any(|h: &HunkId| &h.path == path))
+                    .unwrap_or(false);
+                *current_path.borrow_mut() = path;
+                keep
+            });
+            options.hunk_callback(|hunk| {
+                let current_path = current_path.borrow();
+                match (hunk, current_path.as_deref()) {
+                    (Some(hunk), Some(path)) => selected.contains(&HunkId::new(path, &hunk)),
+                    _ => false,
+                }
+            });
+            let mut index = raw.apply_to_tree(&base_tree, &diff, Some(&mut options))?;
+            let tree_id = index.write_tree_to(raw)?;
+            let tree = raw.find_tree(tree_id)?;
+
+            let target_commit = repo.find_commit(*target_id).expect("stack commits exist");
+            let message = format!(
+                "fixup! {}",
+                target_commit
+                    .fixup_summary()
+                    .unwrap_or_else(|| target_commit.summary.as_ref())
+            );
+            let id = git2_ext::ops::commit(
+                raw,
+                &head_commit.author(),
+                &head_commit.committer(),
+                &message,
+                &tree,
+                &[&parent_commit],
+                None,
+            )?;
+            log::debug!("committed {} {}", id, message);
+            fixup_ids.push(id);
+            parent_commit = raw.find_commit(id)?;
+        }
+        fixup_ids
+    };
+
+    let fixup_id = *fixup_ids.last().expect("only called with assigned hunks");
+    let head_branch = repo.head_branch();
+    for branch in branches.get(head_id).into_iter().flatten() {
+        if let Some(name) = branch.local_name() {
+            if head_branch.as_ref().and_then(|b| b.local_name()) == Some(name) {
+                continue;
+            }
+            repo.branch(name, fixup_id)?;
+        }
+    }
+    // Move HEAD without a checkout, so the index and worktree keep what wasn't absorbed
+    let mut head = repo.raw().head()?;
+    if head.is_branch() {
+        head.set_target(fixup_id, "git-stack absorb")?;
+    } else {
+        repo.raw().set_head_detached(fixup_id)?;
+    }
+
+    Ok(fixup_ids)
+}
@@ -3,20 +3,36 @@ use std::io::Write;
 use proc_exit::prelude::*;
 
 #[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("action").multiple(false))]
 pub(crate) struct AliasArgs {
-    #[arg(long)]
+    #[arg(long, group = "action")]
     register: bool,
 
-    #[arg(long)]
+    #[arg(long, group = "action")]
     unregister: bool,
+
+    /// List the `stack.alias.<name>` commands
+    #[arg(long, group = "action")]
+    list: bool,
+
+    /// Remove the `stack.alias.<name>` command
+    #[arg(long, value_name = "NAME", group = "action")]
+    unset: Option<String>,
 }
 
 impl AliasArgs {
     pub(crate) fn exec(&self) -> proc_exit::ExitResult {
         if self.register {
+            crate::lock::require_writable(false)?;
             register()?;
         } else if self.unregister {
+            crate::lock::require_writable(false)?;
             unregister()?;
+        } else if self.list {
+            list()?;
+        } else if let Some(name) = self.unset.as_deref() {
+            crate::lock::require_writable(false)?;
+            unset(name)?;
         } else {
             status()?;
         }
@@ -234,6 +250,95 @@ fn status() -> proc_exit::ExitResult {
     Ok(())
 }
 
+fn list() -> proc_exit::ExitResult {
+    let repo_config = load_config().with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    let mut stdout = anstream::stdout().lock();
+    for (name, value) in repo_config.aliases() {
+        let _ = writeln!(stdout, "{name} = {value}");
+    }
+    Ok(())
+}
+
+fn unset(name: &str) -> proc_exit::ExitResult {
+    let config = if let Ok(config) = open_repo_config() {
+        config
+    } else {
+        git2::Config::open_default().with_code(proc_exit::Code::FAILURE)?
+    };
+    let key = format!("stack.alias.{name}");
+
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+
+    let mut removed = false;
+    for level in [
+        git2::ConfigLevel::Local,
+        git2::ConfigLevel::Global,
+        git2::ConfigLevel::XDG,
+    ] {
+        let Ok(mut level_config) = config.open_level(level) else {
+            continue;
+        };
+        let Ok(value) = level_config.get_string(&key) else {
+            continue;
+        };
+        let _ = writeln!(
+            stderr,
+            "{}: {}=\"{}\"",
+            stderr_palette.good("Unsetting"),
+            key,
+            value
+        );
+        level_config
+            .remove(&key)
+            .with_code(proc_exit::Code::FAILURE)?;
+        removed = true;
+    }
+
+    if removed {
+        Ok(())
+    } else {
+        Err(proc_exit::sysexits::USAGE_ERR.with_message(format!("`{key}` is not set")))
+    }
+}
+
+/// Replace a `stack.alias.<name>` command with its arguments
+///
+/// Built-in commands take precedence and aliases aren't expanded recursively, like `git`.
+pub(crate) fn expand(
+    args: Vec<std::ffi::OsString>,
+) -> Result<Vec<std::ffi::OsString>, proc_exit::Exit> {
+    let Some(name) = args.get(1).and_then(|a| a.to_str()) else {
+        return Ok(args);
+    };
+    if name.starts_with('-') {
+        return Ok(args);
+    }
+    let cmd = <crate::args::Args as clap::CommandFactory>::command();
+    if cmd.find_subcommand(name).is_some() {
+        return Ok(args);
+    }
+    let Ok(repo_config) = load_config() else {
+        return Ok(args);
+    };
+    let Some(value) = repo_config.alias(name) else {
+        return Ok(args);
+    };
+    let words = shlex::split(value).ok_or_else(|| {
+        proc_exit::sysexits::CONFIG_ERR.with_message(format!(
+            "could not split `stack.alias.{name}={value}` into arguments"
+        ))
+    })?;
+
+    let mut expanded = Vec::with_capacity(args.len() + words.len());
+    let mut args = args.into_iter();
+    expanded.extend(args.next());
+    args.next();
+    expanded.extend(words.into_iter().map(std::ffi::OsString::from));
+    expanded.extend(args);
+    Ok(expanded)
+}
+
 pub(crate) struct Alias {
     pub(crate) alias: &'static str,
     pub(crate) action: &'static str,
@@ -249,6 +354,15 @@ const ALIASES: &[Alias] = &[
     crate::run::RunArgs::alias(),
 ];
 
+fn load_config() -> Result<git_stack::config::RepoConfig, eyre::Error> {
+    let repo_config = match git2::Repository::discover(".") {
+        Ok(repo) => git_stack::config::RepoConfig::from_all(&repo)?,
+        Err(_) => git_stack::config::RepoConfig::from_defaults()
+            .update(git_stack::config::RepoConfig::from_env()),
+    };
+    Ok(repo_config)
+}
+
 fn open_repo_config() -> Result<git2::Config, eyre::Error> {
     let cwd = std::env::current_dir()?;
     let repo = git2::Repository::discover(cwd)?;
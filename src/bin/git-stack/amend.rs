@@ -11,7 +11,10 @@ use git_stack::git::Repo;
 /// `--edit` or `--message`.
 ///
 /// When you amend a commit that has descendants, those descendants are rebased on top of the
-/// amended version of the commit, unless doing so would result in merge conflicts.
+/// amended version of the commit, unless doing so would result in merge conflicts.  With
+/// `--edit-todo`, that rebase can be adjusted in your editor, like `git rebase --interactive`.
+/// With `--no-rebase`, the changes are left as a `fixup!` commit and the branches are marked as
+/// needing a restack, for the next `git stack --rebase` to do in one pass.
 #[derive(clap::Args)]
 pub(crate) struct AmendArgs {
     /// Commit to rewrite
@@ -40,9 +43,21 @@ pub(crate) struct AmendArgs {
     #[arg(short, long)]
     message: Option<String>,
 
+    /// Edit the todo list for rebasing the stack, to also reorder, squash, or drop commits
+    #[arg(long)]
+    edit_todo: bool,
+
+    /// Defer squashing the changes and restacking descendants to the next `git stack --rebase`
+    #[arg(long, conflicts_with_all = ["edit", "message", "edit_todo"])]
+    no_rebase: bool,
+
     /// Don't actually switch
     #[arg(short = 'n', long)]
     dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
 }
 
 impl AmendArgs {
@@ -61,26 +76,28 @@ impl AmendArgs {
 
         let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
         let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
         let mut repo = git_stack::git::GitRepo::new(repo);
 
         let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         repo.set_push_remote(repo_config.push_remote());
         repo.set_pull_remote(repo_config.pull_remote());
-        let config = repo
-            .raw()
-            .config()
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
-        repo.set_sign(
-            config
-                .get_bool("stack.gpgSign")
-                .or_else(|_| config.get_bool("commit.gpgSign"))
-                .unwrap_or_default(),
-        )
-        .with_code(proc_exit::Code::FAILURE)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
 
         let protected = git_stack::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
@@ -110,8 +127,14 @@ impl AmendArgs {
         let mut graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
             .with_code(proc_exit::Code::FAILURE)?;
         git_stack::graph::protect_branches(&mut graph);
+        let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_ignored(&mut graph, &ignored);
         git_stack::graph::mark_fixup(&mut graph, &repo);
         git_stack::graph::mark_wip(&mut graph, &repo);
+        let anchors =
+            git_stack::git::Anchors::from_repo(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_anchors(&mut graph, &anchors);
 
         if repo.raw().state() != git2::RepositoryState::Clean {
             let message = format!("cannot walk commits, {:?} in progress", repo.raw().state());
@@ -137,6 +160,10 @@ impl AmendArgs {
                 return Err(proc_exit::Code::FAILURE.with_message("cannot amend protected commits"));
             }
         }
+        if anchors.contains(head_id) {
+            return Err(proc_exit::Code::FAILURE
+                .with_message("cannot amend an anchor, run `git stack anchor --remove` first"));
+        }
 
         let index_tree = stage_fixup(
             &repo,
@@ -165,51 +192,42 @@ impl AmendArgs {
             .update(&repo)
             .with_code(proc_exit::Code::FAILURE)?;
 
-        let mut backed_up = false;
-        {
-            let stash_repo =
-                git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
-            let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
-            let mut snapshots =
-                git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
-            let snapshot_capacity = repo_config.capacity();
-            snapshots.capacity(snapshot_capacity);
-            let snapshot = git_branch_stash::Snapshot::from_repo(&stash_repo)
-                .with_code(proc_exit::Code::FAILURE)?;
-            if !self.dry_run {
-                snapshots.push(snapshot).to_sysexits()?;
-                backed_up = true;
-            }
+        if self.no_rebase {
+            return defer_restack(&repo, &graph, head_id, fixup_id, self.dry_run);
         }
 
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "amend",
+            None,
+            self.dry_run,
+        )?;
+
         let new_message = if let Some(message) = self.message.as_deref() {
             Some(message.trim().to_owned())
         } else if self.edit {
-            use std::fmt::Write;
-
             let raw_commit = repo
                 .raw()
                 .find_commit(head_id)
                 .expect("head_commit is always valid");
-            let existing = String::from_utf8_lossy(raw_commit.message_bytes());
-            let mut template = String::new();
-            writeln!(&mut template, "{existing}").unwrap();
-            writeln!(&mut template).unwrap();
-            writeln!(
-                &mut template,
-                "# Please enter the commit message for your changes. Lines starting"
-            )
-            .unwrap();
-            writeln!(
-                &mut template,
-                "# with '#' will be ignored, and an empty message aborts the commit."
-            )
-            .unwrap();
+            let existing = git_stack::git::decode_message(
+                raw_commit.message_bytes(),
+                raw_commit.message_encoding(),
+            );
             let head_branch = repo.head_branch();
-            if let Some(head_branch) = &head_branch {
-                writeln!(&mut template, "#").unwrap();
-                writeln!(&mut template, "# On branch {head_branch}").unwrap();
-            }
+            // The fixup only lines up with `head_id`'s changes when it sits directly on top
+            let amended_id = fixup_id
+                .filter(|id| {
+                    repo.parent_ids(*id).ok().and_then(|p| p.first().copied()) == Some(head_id)
+                })
+                .unwrap_or(head_id);
+            let template = crate::ops::commit_template(
+                repo.raw(),
+                &existing,
+                head_branch.as_ref().map(|b| b as &dyn std::fmt::Display),
+                Some((raw_commit.parent_id(0).ok(), amended_id)),
+            );
             let message = crate::ops::edit_commit(
                 repo.path()
                     .ok_or_else(|| eyre::format_err!("no `.git` path found"))
@@ -235,39 +253,46 @@ impl AmendArgs {
                 "{} nothing to amend to {}: {}",
                 stderr_palette.error("error:"),
                 stderr_palette.highlight(abbrev_id.as_str().unwrap()),
-                stderr_palette.hint(&head.summary)
+                stderr_palette.hint(head.display_summary())
             );
             return Err(proc_exit::Code::FAILURE.as_exit());
         }
 
-        let mut stash_id = None;
-        if !self.dry_run {
-            stash_id = git_stack::git::stash_push(&mut repo, "amend");
-        }
-
         git_stack::graph::fixup(&mut graph, &repo, git_stack::config::Fixup::Squash);
+        let mut messages = std::collections::HashMap::new();
         if let Some(new_message) = new_message {
+            messages.insert(head_id, new_message.clone());
             git_stack::graph::reword_commit(&mut graph, &repo, head_id, new_message)
                 .with_code(proc_exit::Code::FAILURE)?;
         }
 
-        let mut success = true;
-        let scripts = git_stack::graph::to_scripts(&graph, vec![]);
-        let head_branch = repo.head_branch();
-        let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
-        for script in scripts {
-            let results = executor.run(&mut repo, &script);
-            for (err, name, dependents) in results.iter() {
-                success = false;
-                log::error!("Failed to re-stack branch `{}`: {}", name, err);
-                if !dependents.is_empty() {
-                    log::error!("  Blocked dependents: {}", dependents.iter().join(", "));
+        let mut scripts = git_stack::graph::to_scripts(&graph, vec![]);
+        if self.edit_todo {
+            let edited = crate::ops::edit_todo(&repo, repo_config.editor(), &scripts, messages)
+                .with_code(proc_exit::Code::FAILURE)?;
+            match edited {
+                Some(script) => scripts = vec![script],
+                None => {
+                    return Err(proc_exit::Code::SUCCESS.with_message("Nothing to do."));
                 }
             }
         }
-        executor
-            .close(&mut repo, head_branch.as_ref().and_then(|b| b.local_name()))
-            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut stash_id = None;
+        if !self.dry_run {
+            stash_id = git_stack::git::stash_push(&mut repo, "amend");
+        }
+
+        let head_branch = repo.head_branch();
+        let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            &mut repo,
+            &mut executor,
+            scripts,
+            head_branch.as_ref().and_then(|b| b.local_name()),
+            stash_id,
+        )?;
 
         if success {
             let abbrev_id = repo
@@ -281,20 +306,13 @@ impl AmendArgs {
                 "{} to {}: {}",
                 stderr_palette.good("Amended"),
                 stderr_palette.highlight(abbrev_id.as_str().unwrap()),
-                stderr_palette.hint(&head.summary)
+                stderr_palette.hint(head.display_summary())
             );
         }
 
         git_stack::git::stash_pop(&mut repo, stash_id);
         if backed_up {
-            anstream::eprintln!(
-                "{}: to undo, run {}",
-                stderr_palette.info("note"),
-                stderr_palette.highlight(format_args!(
-                    "`git branch-stash pop {}`",
-                    crate::ops::STASH_STACK_NAME
-                ))
-            );
+            crate::ops::note_undo();
         }
 
         if success {
@@ -305,6 +323,63 @@ impl AmendArgs {
     }
 }
 
+/// Mark the branches on top of the amended commit as needing a restack, leaving them as is
+fn defer_restack(
+    repo: &git_stack::git::GitRepo,
+    graph: &git_stack::graph::Graph,
+    head_id: git2::Oid,
+    fixup_id: Option<git2::Oid>,
+    dry_run: bool,
+) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+    let head = repo.find_commit(head_id).expect("explicit bases exist");
+    let abbrev_id = &head_id.to_string()[..7];
+    if fixup_id.is_none() {
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} nothing to amend to {}: {}",
+            stderr_palette.error("error:"),
+            stderr_palette.highlight(abbrev_id),
+            stderr_palette.hint(head.display_summary())
+        );
+        return Err(proc_exit::Code::FAILURE.as_exit());
+    }
+
+    let mut names = std::collections::BTreeSet::new();
+    for id in graph.descendants_of(head_id) {
+        for branch in graph.branches.get(id).into_iter().flatten() {
+            if let Some(name) = branch.local_name() {
+                names.insert(name.to_owned());
+            }
+        }
+    }
+    if !dry_run {
+        for name in &names {
+            git_stack::git::mark_needs_restack(repo.raw(), name)
+                .with_code(proc_exit::Code::FAILURE)?;
+        }
+    }
+
+    let _ = writeln!(
+        anstream::stderr(),
+        "{} to {}: {}",
+        stderr_palette.good("Amended"),
+        stderr_palette.highlight(abbrev_id),
+        stderr_palette.hint(head.display_summary())
+    );
+    if !names.is_empty() {
+        let _ = writeln!(
+            anstream::stderr(),
+            "{}: {} {} restacking, run {}",
+            stderr_palette.info("note"),
+            names.iter().map(|n| format!("`{n}`")).join(", "),
+            if names.len() == 1 { "needs" } else { "need" },
+            stderr_palette.highlight("`git stack --rebase`")
+        );
+    }
+    Ok(())
+}
+
 fn stage_fixup(
     repo: &git_stack::git::GitRepo,
     all: bool,
@@ -0,0 +1,88 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Mark commits as anchors, or list the anchors
+///
+/// Anchors are for commits that something outside of the repo refers to by id, like a CI run or
+/// a deployment.  Fixups aren't moved across an anchor and `--repair` won't reorder stacks around
+/// one.  Anchors are stored as git notes under `refs/notes/stack-anchors`.
+#[derive(clap::Args)]
+pub(crate) struct AnchorArgs {
+    /// Commits to mark (default: list the anchors)
+    #[arg(value_name = "COMMIT")]
+    revs: Vec<String>,
+
+    /// Stop treating the commits as anchors
+    #[arg(long, requires = "revs")]
+    remove: bool,
+}
+
+impl AnchorArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        if self.revs.is_empty() {
+            let anchors =
+                git_stack::git::Anchors::from_repo(&repo).with_code(proc_exit::Code::FAILURE)?;
+            let mut stdout = anstream::stdout().lock();
+            for id in anchors.iter() {
+                let Ok(commit) = repo.find_commit(id) else {
+                    log::debug!("Anchor {id} is not in the repo");
+                    continue;
+                };
+                let _ = writeln!(
+                    stdout,
+                    "{} {}",
+                    &id.to_string()[..7],
+                    commit.summary().unwrap_or_default()
+                );
+            }
+            return Ok(());
+        }
+        crate::lock::require_writable(false)?;
+
+        let ids = self
+            .revs
+            .iter()
+            .map(|rev| {
+                repo.revparse_single(rev)
+                    .and_then(|o| o.peel_to_commit())
+                    .map(|c| c.id())
+                    .map_err(|_| {
+                        proc_exit::sysexits::USAGE_ERR
+                            .with_message(format!("could not find commit `{rev}`"))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rev, id) in self.revs.iter().zip(ids) {
+            let abbrev_id = &id.to_string()[..7];
+            if self.remove {
+                if git_stack::git::remove_anchor(&repo, id).with_code(proc_exit::Code::FAILURE)? {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} {}",
+                        stderr_palette.good("Removed anchor"),
+                        stderr_palette.highlight(abbrev_id),
+                    );
+                } else {
+                    log::warn!("`{rev}` is not an anchor");
+                }
+            } else if git_stack::git::add_anchor(&repo, id).with_code(proc_exit::Code::FAILURE)? {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{} {}",
+                    stderr_palette.good("Anchored"),
+                    stderr_palette.highlight(abbrev_id),
+                );
+            } else {
+                log::debug!("`{rev}` is already an anchor");
+            }
+        }
+
+        Ok(())
+    }
+}
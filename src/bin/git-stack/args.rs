@@ -2,6 +2,8 @@
 #[command(about, author, version)]
 #[command(group = clap::ArgGroup::new("mode").multiple(false))]
 #[command(args_conflicts_with_subcommands = true)]
+#[command(disable_help_subcommand = true)]
+#[command(after_long_help = crate::help::after_help(None))]
 pub(crate) struct Args {
     /// Rebase the selected stacks
     #[arg(short, long, group = "mode")]
@@ -11,41 +13,131 @@ pub(crate) struct Args {
     #[arg(long)]
     pub(crate) pull: bool,
 
+    /// Fetch the parent branch's remote when it is older than `stack.fresh-max-age`, rather than
+    /// warning, so squash-merged branches are noticed
+    #[arg(long)]
+    pub(crate) auto_fresh: bool,
+
     /// Push all ready branches
     #[arg(long)]
     pub(crate) push: bool,
 
-    /// Which branch stacks to include
-    #[arg(short, long, value_enum)]
-    pub(crate) stack: Option<git_stack::config::Stack>,
+    /// Create GitLab merge requests for pushed branches, targeting their parent branch
+    #[arg(long, requires = "push", conflicts_with = "push_style")]
+    pub(crate) create_mr: bool,
 
-    /// Branch to evaluate from (default: most-recent protected branch)
-    #[arg(long)]
+    /// How to send branches to the push remote
+    #[arg(long, value_enum, value_name = "STYLE", requires = "push")]
+    pub(crate) push_style: Option<git_stack::config::PushStyle>,
+
+    /// Also push draft branches, marking them as drafts where the push style supports it
+    #[arg(long, requires = "push")]
+    pub(crate) draft: bool,
+
+    /// Only push the current branch, up to this commit, leaving the local branch in place
+    #[arg(long, value_name = "REV", requires = "push")]
+    pub(crate) push_until_commit: Option<String>,
+
+    /// Which branch stacks to include: current, dependents, descendants, all, or a named stack
+    #[arg(short, long, value_name = "STACK", value_parser = StackArgParser)]
+    pub(crate) stack: Option<StackArg>,
+
+    /// Branch or other revision to evaluate from, like `origin/HEAD` or `@{u}` (default: most-recent protected branch)
+    #[arg(long, value_name = "REV")]
     pub(crate) base: Option<String>,
 
-    /// Branch to rebase onto (default: base)
-    #[arg(long)]
+    /// Branch or other revision to rebase onto (default: base)
+    #[arg(long, value_name = "REV")]
     pub(crate) onto: Option<String>,
 
     /// Action to perform with fixup-commits
     #[arg(long, value_enum)]
     pub(crate) fixup: Option<git_stack::config::Fixup>,
 
+    /// Only squash fixup commits (or apply `--fixup`), leaving each branch on its current base
+    /// without repairs
+    #[arg(long, requires = "rebase", conflicts_with_all = ["pull", "onto", "repair"])]
+    pub(crate) autosquash_only: bool,
+
+    /// Leave branches that would conflict in place, rather than failing on them
+    #[arg(long)]
+    pub(crate) skip_conflicts: bool,
+
+    /// Only re-stack this branch, leaving the rest of the stack in place
+    #[arg(long, value_name = "BRANCH")]
+    pub(crate) only: Option<String>,
+
+    /// Also re-stack the branches on top of `--only`
+    #[arg(long, requires = "only")]
+    pub(crate) with_descendants: bool,
+
+    /// Rewrite commits even when tags point to them, leaving the tags on the original commits
+    #[arg(long)]
+    pub(crate) force: bool,
+
+    /// Keep stacks on different protected bases apart, sectioning the output and confirming
+    /// pushes and prunes for each base
+    #[arg(long)]
+    pub(crate) separate_households: bool,
+
     /// Repair diverging branches.
     #[arg(long, overrides_with("no_repair"))]
     repair: bool,
     #[arg(long, overrides_with("repair"), hide = true)]
     no_repair: bool,
 
+    /// Pretend a development branch doesn't exist for this run
+    #[arg(long, value_name = "BRANCH")]
+    pub(crate) ignore_branch: Vec<String>,
+
+    /// Also ignore branches on top of the ignored branches
+    #[arg(long, requires = "ignore_branch")]
+    pub(crate) ignore_descendants: bool,
+
     #[arg(short = 'n', long)]
     pub(crate) dry_run: bool,
 
+    /// Skip the `reference-transaction`, `post-rewrite`, and `pre-push` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    pub(crate) no_hooks: bool,
+
+    /// Don't reuse or update the graph cache kept under `.git/git-stack`
+    #[arg(long)]
+    pub(crate) no_cache: bool,
+
     #[arg(long, value_enum)]
     pub(crate) format: Option<git_stack::config::Format>,
 
+    /// Markers for the branch graph
+    #[arg(long, value_enum, value_name = "THEME")]
+    pub(crate) icons: Option<git_stack::config::Icons>,
+
     #[arg(long, value_enum)]
     pub(crate) show_commits: Option<git_stack::config::ShowCommits>,
 
+    /// Show the last N protected commits beneath each stack
+    #[arg(long, value_name = "N")]
+    pub(crate) base_history: Option<usize>,
+
+    /// Truncate graph lines to this width (default: the terminal's width)
+    #[arg(long, value_name = "COLUMNS", conflicts_with = "full")]
+    pub(crate) max_width: Option<usize>,
+
+    /// Show graph lines in full, rather than truncating them to the terminal's width
+    #[arg(long)]
+    pub(crate) full: bool,
+
+    /// Mark whether each development commit's signature checks out
+    #[arg(long, overrides_with("no_show_signatures"))]
+    pub(crate) show_signatures: bool,
+    #[arg(long, overrides_with("show_signatures"), hide = true)]
+    pub(crate) no_show_signatures: bool,
+
+    /// Show the stacks as they were in an undo snapshot, by index (`0` is the most recent) or the
+    /// latest from before a time, like `2h` or `2024-01-31`
+    #[arg(long, value_name = "SNAPSHOT|TIME", group = "mode", conflicts_with_all = ["pull", "push", "fixup", "repair"])]
+    pub(crate) as_of: Option<String>,
+
     /// See what branches are protected
     #[arg(long, group = "mode")]
     pub(crate) protected: bool,
@@ -68,10 +160,32 @@ pub(crate) struct Args {
     #[arg(short = 'C', hide = true, value_name = "PATH")]
     pub(crate) current_dir: Option<Vec<std::path::PathBuf>>,
 
+    /// Layer the `stack-profile.<NAME>` config over the other config (default: `stack.profile`)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub(crate) profile: Option<String>,
+
+    /// Wait for another git-stack rewriting the repo to finish, rather than failing
+    #[arg(long, global = true, overrides_with("no_wait"))]
+    wait: bool,
+    #[arg(long, global = true, overrides_with("wait"), hide = true)]
+    no_wait: bool,
+
+    /// Refuse to run commands that change the repo, as if `stack.read-only` were set
+    #[arg(long, global = true)]
+    pub(crate) read_only: bool,
+
     /// Write the current configuration to file with `-` for stdout
     #[arg(long, group = "mode")]
     pub(crate) dump_config: Option<std::path::PathBuf>,
 
+    /// Finish a rewrite that stopped on a conflict, once it is resolved
+    #[arg(long = "continue", group = "mode")]
+    pub(crate) continue_: bool,
+
+    /// Give up on a rewrite that stopped on a conflict, restoring the branches
+    #[arg(long, group = "mode")]
+    pub(crate) abort: bool,
+
     #[command(flatten)]
     pub(crate) color: colorchoice_clap::Color,
 
@@ -85,13 +199,76 @@ pub(crate) struct Args {
 #[derive(clap::Subcommand)]
 pub(crate) enum Command {
     #[command(alias = "prev")]
+    #[command(after_long_help = crate::help::after_help(Some("previous")))]
     Previous(crate::prev::PrevArgs),
+    #[command(after_long_help = crate::help::after_help(Some("next")))]
     Next(crate::next::NextArgs),
+    #[command(after_long_help = crate::help::after_help(Some("reword")))]
     Reword(crate::reword::RewordArgs),
+    #[command(after_long_help = crate::help::after_help(Some("amend")))]
     Amend(crate::amend::AmendArgs),
+    #[command(after_long_help = crate::help::after_help(Some("edit")))]
+    Edit(crate::edit::EditArgs),
+    #[command(after_long_help = crate::help::after_help(Some("absorb")))]
+    Absorb(crate::absorb::AbsorbArgs),
+    #[command(after_long_help = crate::help::after_help(Some("move")))]
+    Move(crate::move_branch::MoveArgs),
+    #[command(after_long_help = crate::help::after_help(Some("squash")))]
+    Squash(crate::squash::SquashArgs),
+    #[command(after_long_help = crate::help::after_help(Some("sync")))]
     Sync(crate::sync::SyncArgs),
+    #[command(after_long_help = crate::help::after_help(Some("fetch")))]
+    Fetch(crate::fetch::FetchArgs),
+    #[command(after_long_help = crate::help::after_help(Some("run")))]
     Run(crate::run::RunArgs),
+    #[command(after_long_help = crate::help::after_help(Some("why")))]
+    Why(crate::why::WhyArgs),
+    #[command(after_long_help = crate::help::after_help(Some("status")))]
+    Status(crate::status::StatusArgs),
+    #[command(after_long_help = crate::help::after_help(Some("blame")))]
+    Blame(crate::blame::BlameArgs),
+    #[command(after_long_help = crate::help::after_help(Some("export-bundle")))]
+    ExportBundle(crate::bundle::ExportBundleArgs),
+    #[command(after_long_help = crate::help::after_help(Some("import-bundle")))]
+    ImportBundle(crate::bundle::ImportBundleArgs),
+    #[command(after_long_help = crate::help::after_help(Some("retarget")))]
+    Retarget(crate::retarget::RetargetArgs),
+    #[command(after_long_help = crate::help::after_help(Some("submit")))]
+    Submit(crate::submit::SubmitArgs),
+    #[command(after_long_help = crate::help::after_help(Some("regroup")))]
+    Regroup(crate::regroup::RegroupArgs),
+    #[command(after_long_help = crate::help::after_help(Some("rename-stack")))]
+    RenameStack(crate::regroup::RenameStackArgs),
+    #[command(after_long_help = crate::help::after_help(Some("select")))]
+    Select(crate::select::SelectArgs),
+    #[command(after_long_help = crate::help::after_help(Some("anchor")))]
+    Anchor(crate::anchor::AnchorArgs),
+    #[command(after_long_help = crate::help::after_help(Some("ignore-commit")))]
+    IgnoreCommit(crate::ignore_commit::IgnoreCommitArgs),
+    #[command(after_long_help = crate::help::after_help(Some("rename")))]
+    Rename(crate::rename::RenameArgs),
+    #[command(after_long_help = crate::help::after_help(Some("worktree")))]
+    Worktree(crate::worktree::WorktreeArgs),
+    #[command(after_long_help = crate::help::after_help(Some("config")))]
+    Config(crate::config::ConfigArgs),
+    #[command(after_long_help = crate::help::after_help(Some("doctor")))]
+    Doctor(crate::doctor::DoctorArgs),
     Alias(crate::alias::AliasArgs),
+    Maintenance(crate::maintenance::MaintenanceArgs),
+    #[command(alias = "prune")]
+    #[command(after_long_help = crate::help::after_help(Some("gc")))]
+    Gc(crate::gc::GcArgs),
+    #[command(after_long_help = crate::help::after_help(Some("tidy")))]
+    Tidy(crate::tidy::TidyArgs),
+    #[command(after_long_help = crate::help::after_help(Some("snapshot")))]
+    Snapshot(crate::snapshot::SnapshotArgs),
+    #[command(after_long_help = crate::help::after_help(Some("undo")))]
+    Undo(crate::undo::UndoArgs),
+    SelfTest(crate::self_test::SelfTestArgs),
+    Help(crate::help::HelpArgs),
+    Completions(crate::completions::CompletionsArgs),
+    #[command(name = "__complete", hide = true)]
+    Complete(crate::completions::CompleteArgs),
 }
 
 impl Args {
@@ -101,11 +278,45 @@ impl Args {
             Some(Command::Next(c)) => c.exec(),
             Some(Command::Reword(c)) => c.exec(),
             Some(Command::Amend(c)) => c.exec(),
+            Some(Command::Edit(c)) => c.exec(),
+            Some(Command::Absorb(c)) => c.exec(),
+            Some(Command::Move(c)) => c.exec(),
+            Some(Command::Squash(c)) => c.exec(),
             Some(Command::Sync(c)) => c.exec(),
+            Some(Command::Fetch(c)) => c.exec(),
             Some(Command::Run(c)) => c.exec(),
+            Some(Command::Why(c)) => c.exec(),
+            Some(Command::Status(c)) => c.exec(),
+            Some(Command::Blame(c)) => c.exec(),
+            Some(Command::ExportBundle(c)) => c.exec(),
+            Some(Command::ImportBundle(c)) => c.exec(),
+            Some(Command::Retarget(c)) => c.exec(),
+            Some(Command::Submit(c)) => c.exec(),
+            Some(Command::Regroup(c)) => c.exec(),
+            Some(Command::RenameStack(c)) => c.exec(),
+            Some(Command::Select(c)) => c.exec(),
+            Some(Command::Anchor(c)) => c.exec(),
+            Some(Command::IgnoreCommit(c)) => c.exec(),
+            Some(Command::Rename(c)) => c.exec(),
+            Some(Command::Worktree(c)) => c.exec(),
+            Some(Command::Config(c)) => c.exec(),
+            Some(Command::Doctor(c)) => c.exec(),
             Some(Command::Alias(c)) => c.exec(),
+            Some(Command::Maintenance(c)) => c.exec(),
+            Some(Command::Gc(c)) => c.exec(),
+            Some(Command::Tidy(c)) => c.exec(),
+            Some(Command::Snapshot(c)) => c.exec(),
+            Some(Command::Undo(c)) => c.exec(),
+            Some(Command::SelfTest(c)) => c.exec(),
+            Some(Command::Help(c)) => c.exec(),
+            Some(Command::Completions(c)) => c.exec(),
+            Some(Command::Complete(c)) => c.exec(),
             None => {
-                if let Some(output_path) = self.dump_config.as_deref() {
+                if self.continue_ {
+                    crate::resume::continue_rewrite(self)
+                } else if self.abort {
+                    crate::resume::abort_rewrite(self)
+                } else if let Some(output_path) = self.dump_config.as_deref() {
                     crate::config::dump_config(self, output_path)
                 } else if let Some(ignore) = self.protect.as_deref() {
                     crate::config::protect(self, ignore)
@@ -118,32 +329,168 @@ impl Args {
         }
     }
 
+    /// Whether to warn about settings naming remotes or branches that don't exist
+    ///
+    /// Skipped for commands run behind the user's back, like completions and prompts.
+    pub(crate) fn checks_config(&self) -> bool {
+        !matches!(
+            self.command,
+            Some(
+                Command::Status(_)
+                    | Command::Doctor(_)
+                    | Command::Completions(_)
+                    | Command::Complete(_)
+            )
+        )
+    }
+
+    /// Whether flags that only change how the stacks are shown were passed
+    ///
+    /// These opt out of `stack.default-command`, so looking never rewrites or pushes.
+    pub(crate) fn shows_only(&self) -> bool {
+        self.format.is_some()
+            || self.icons.is_some()
+            || self.show_commits.is_some()
+            || self.base_history.is_some()
+            || self.max_width.is_some()
+            || self.full
+            || self.show_signatures
+            || self.no_show_signatures
+    }
+
     pub(crate) fn to_config(&self) -> git_stack::config::RepoConfig {
         git_stack::config::RepoConfig {
             editor: None,
             protected_branches: None,
+            protect_remote_default_branch: None,
             protect_commit_count: None,
             protect_commit_age: None,
             auto_base_commit_count: None,
-            stack: self.stack,
+            stack: self.stack.as_ref().and_then(StackArg::mode),
             push_remote: None,
             pull_remote: None,
+            protected_remotes: None,
             show_format: self.format,
+            show_icons: self.icons,
+            palette: None,
+            colors: Default::default(),
             show_commits: self.show_commits,
             show_stacked: None,
+            show_base_history: self.base_history,
+            show_max_width: if self.full { Some(0) } else { self.max_width },
+            show_signatures: resolve_bool_arg(self.show_signatures, self.no_show_signatures),
             auto_fixup: None,
             auto_repair: None,
+            preserve_order: None,
+            drop_by_patch_id: None,
+            resign: None,
+            sign: None,
+            gitlab_mr: self.create_mr.then_some(true),
+            push_style: self.push_style.or_else(|| {
+                self.create_mr
+                    .then_some(git_stack::config::PushStyle::GitlabMr)
+            }),
+            forge: None,
+            github_token: None,
+            default_command: None,
+            network_retries: None,
+            network_retry_delay: None,
+            fresh_max_age: None,
+            profile: None,
+            worktree_root: None,
+            read_only: None,
+            auto_stash: None,
 
             capacity: None,
+            max_age: None,
+            stashes: Default::default(),
+            branches: Default::default(),
+            aliases: Default::default(),
         }
     }
 
     pub(crate) fn repair(&self) -> Option<bool> {
         resolve_bool_arg(self.repair, self.no_repair)
     }
+
+    pub(crate) fn wait(&self) -> bool {
+        resolve_bool_arg(self.wait, self.no_wait).unwrap_or(false)
+    }
+}
+
+/// `--stack`: either which stacks to include relative to `HEAD` or a named stack
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StackArg {
+    Mode(git_stack::config::Stack),
+    Named(String),
+}
+
+impl StackArg {
+    pub(crate) fn mode(&self) -> Option<git_stack::config::Stack> {
+        match self {
+            Self::Mode(mode) => Some(*mode),
+            Self::Named(_) => None,
+        }
+    }
+
+    pub(crate) fn named(&self) -> Option<&str> {
+        match self {
+            Self::Mode(_) => None,
+            Self::Named(name) => Some(name),
+        }
+    }
+}
+
+impl std::fmt::Display for StackArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mode(mode) => mode.fmt(f),
+            Self::Named(name) => name.fmt(f),
+        }
+    }
+}
+
+impl std::str::FromStr for StackArg {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stack = <git_stack::config::Stack as clap::ValueEnum>::from_str(s, false)
+            .map(Self::Mode)
+            .unwrap_or_else(|_| Self::Named(s.to_owned()));
+        Ok(stack)
+    }
+}
+
+/// Accept any stack name while still offering the built-in selections in help and completions
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StackArgParser;
+
+impl clap::builder::TypedValueParser for StackArgParser {
+    type Value = StackArg;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let value = clap::builder::NonEmptyStringValueParser::new().parse_ref(cmd, arg, value)?;
+        let Ok(stack) = value.parse();
+        Ok(stack)
+    }
+
+    fn possible_values(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        Some(Box::new(
+            <git_stack::config::Stack as clap::ValueEnum>::value_variants()
+                .iter()
+                .filter_map(clap::ValueEnum::to_possible_value),
+        ))
+    }
 }
 
-fn resolve_bool_arg(yes: bool, no: bool) -> Option<bool> {
+pub(crate) fn resolve_bool_arg(yes: bool, no: bool) -> Option<bool> {
     match (yes, no) {
         (true, false) => Some(true),
         (false, true) => Some(false),
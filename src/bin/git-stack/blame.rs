@@ -0,0 +1,145 @@
+use std::io::Write;
+
+use bstr::ByteSlice;
+use proc_exit::prelude::*;
+
+/// Show which lines of a file the stack changes
+///
+/// Lines last changed by the stack's development commits are annotated with that commit; the
+/// rest are dimmed as upstream.
+#[derive(clap::Args)]
+pub(crate) struct BlameArgs {
+    /// File to blame, relative to the current directory
+    #[arg(value_name = "PATH")]
+    path: std::path::PathBuf,
+
+    /// Commit to blame the file as of
+    #[arg(long, value_name = "REV", default_value = "HEAD")]
+    rev: String,
+}
+
+impl BlameArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stdout_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        let path = repo_path(&repo, &cwd, &self.path).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let target = repo
+            .resolve(&self.rev)
+            .ok_or_else(|| eyre::format_err!("could not find `{}`", self.rev))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let target_id = target.id;
+
+        let onto = crate::ops::resolve_implicit_base(
+            &repo,
+            target_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let base = crate::ops::resolve_base_from_onto(&repo, &onto);
+        let merge_base_oid = repo
+            .merge_base(base.id, target_id)
+            .ok_or_else(|| eyre::format_err!("could not find base between {base} and {target_id}"))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_ids: std::collections::HashSet<_> = repo
+            .commit_range(
+                std::ops::Bound::Excluded(&merge_base_oid),
+                std::ops::Bound::Included(&target_id),
+            )
+            .with_code(proc_exit::Code::FAILURE)?
+            .into_iter()
+            .collect();
+
+        let lines = repo
+            .blame(&path, target_id)
+            .map_err(|err| {
+                eyre::format_err!(
+                    "could not blame `{}` in {}: {}",
+                    path.display(),
+                    self.rev,
+                    err
+                )
+            })
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        let width = lines.len().to_string().len();
+        let mut stdout = anstream::stdout().lock();
+        for (index, line) in lines.iter().enumerate() {
+            let number = index + 1;
+            let content = line.content.to_str_lossy();
+            if stack_ids.contains(&line.id) {
+                let id = line.id.to_string();
+                let _ = writeln!(
+                    stdout,
+                    "{} {number:>width$}) {content}",
+                    stdout_palette.highlight(format_args!("{:<8}", &id[..7])),
+                );
+            } else {
+                let _ = writeln!(
+                    stdout,
+                    "{}",
+                    stdout_palette.hint(format_args!(
+                        "{:<8} {number:>width$}) {content}",
+                        "upstream"
+                    ))
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve `path`, relative to `cwd`, to a path relative to the root of the repo
+fn repo_path(
+    repo: &git2::Repository,
+    cwd: &std::path::Path,
+    path: &std::path::Path,
+) -> eyre::Result<std::path::PathBuf> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| eyre::format_err!("cannot blame in a bare repository"))?;
+    let workdir = std::fs::canonicalize(workdir)?;
+    let mut full = if path.is_absolute() {
+        std::path::PathBuf::new()
+    } else {
+        std::fs::canonicalize(cwd)?
+    };
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                full.pop();
+            }
+            std::path::Component::Normal(_)
+            | std::path::Component::RootDir
+            | std::path::Component::Prefix(_) => full.push(component),
+        }
+    }
+    let relative = full
+        .strip_prefix(&workdir)
+        .map_err(|_| eyre::format_err!("`{}` is outside the repository", path.display()))?;
+    Ok(relative.to_owned())
+}
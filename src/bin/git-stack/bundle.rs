@@ -0,0 +1,294 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Write the current stack to a git bundle
+///
+/// The bundle holds the stack's development branches and requires only its protected base, so it
+/// can be moved to another machine or attached to an issue and restored with `import-bundle`.
+#[derive(clap::Args)]
+pub(crate) struct ExportBundleArgs {
+    /// Where to write the bundle
+    #[arg(value_name = "PATH")]
+    path: std::path::PathBuf,
+}
+
+impl ExportBundleArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let head_id = repo.head_commit().id;
+        let base = crate::ops::resolve_implicit_base(
+            &repo,
+            head_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let merge_base_oid = repo
+            .merge_base(base.id, head_id)
+            .ok_or_else(|| eyre::format_err!("could not find base between {base} and HEAD"))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_branches = branches.dependents(&repo, merge_base_oid, head_id);
+
+        let mut names: Vec<_> = stack_branches
+            .iter()
+            .flat_map(|(_, b)| b.iter())
+            .filter(|b| b.kind() != git_stack::graph::BranchKind::Protected)
+            .filter_map(|b| b.local_name().map(|n| n.to_owned()))
+            .collect();
+        names.sort_unstable();
+        if names.is_empty() {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message("no development branches in the current stack"));
+        }
+
+        let mut cmd = std::process::Command::new("git");
+        cmd.arg("bundle")
+            .arg("create")
+            .arg(&self.path)
+            .args(names.iter().map(|n| format!("refs/heads/{n}")))
+            .arg(format!("^{merge_base_oid}"));
+        log::debug!("{cmd:?}");
+        let output = cmd.output().with_code(proc_exit::Code::FAILURE)?;
+        if !output.status.success() {
+            let _ = anstream::stderr().write_all(&output.stderr);
+            return Err(proc_exit::Code::FAILURE.with_message("`git bundle create` failed"));
+        }
+
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {} onto {} to {}",
+            stderr_palette.good("Bundled"),
+            names.join(", "),
+            stderr_palette.highlight(&base),
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Restore a stack from a bundle written by `export-bundle`
+#[derive(clap::Args)]
+pub(crate) struct ImportBundleArgs {
+    /// Bundle to read
+    #[arg(value_name = "PATH")]
+    path: std::path::PathBuf,
+
+    /// Overwrite existing branches of the same name
+    #[arg(short, long)]
+    force: bool,
+}
+
+impl ImportBundleArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(false)?;
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git_stack::git::GitRepo::new(repo);
+
+        let header = BundleHeader::read(&self.path)
+            .map_err(|err| eyre::format_err!("could not read {}: {err}", self.path.display()))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let header = BundleHeader::parse(&header)
+            .ok_or_else(|| eyre::format_err!("{} is not a git bundle", self.path.display()))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        let missing: Vec<_> = header
+            .prerequisites
+            .iter()
+            .filter(|id| repo.find_commit(**id).is_none())
+            .collect();
+        if !missing.is_empty() {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "the stack's base is missing, fetch it first: {}",
+                missing
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let existing: Vec<_> = repo.local_branches().map(|b| b.name).collect();
+        let mut refspecs = Vec::new();
+        let mut imported = Vec::new();
+        for (id, name) in &header.branches {
+            git_stack::git::check_branch_case(name, existing.iter().map(|n| n.as_str()))
+                .map_err(|err| eyre::format_err!("{}", err.message()))
+                .with_code(proc_exit::sysexits::USAGE_ERR)?;
+            if let Some(existing) = repo.find_local_branch(name) {
+                if existing.id == *id {
+                    continue;
+                }
+                if !self.force {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: skipping {}, it already exists (use `--force` to overwrite)",
+                        stderr_palette.warn("warning"),
+                        stderr_palette.highlight(name)
+                    );
+                    continue;
+                }
+            }
+            let force = if self.force { "+" } else { "" };
+            refspecs.push(format!("{force}refs/heads/{name}:refs/heads/{name}"));
+            imported.push(name.as_str());
+        }
+
+        if !refspecs.is_empty() {
+            let mut cmd = std::process::Command::new("git");
+            cmd.arg("fetch")
+                .arg("--quiet")
+                .arg(&self.path)
+                .args(&refspecs);
+            log::debug!("{cmd:?}");
+            let output = cmd.output().with_code(proc_exit::Code::FAILURE)?;
+            if !output.status.success() {
+                let _ = anstream::stderr().write_all(&output.stderr);
+                return Err(proc_exit::Code::FAILURE.with_message("`git fetch` from bundle failed"));
+            }
+        }
+
+        let bases = header
+            .prerequisites
+            .iter()
+            .map(|id| {
+                let commit = repo.find_commit(*id).expect("prerequisites checked above");
+                format!(
+                    "{} ({})",
+                    stderr_palette.highlight(&id.to_string()[..7]),
+                    stderr_palette.hint(commit.display_summary())
+                )
+            })
+            .collect::<Vec<_>>();
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {} onto {}",
+            stderr_palette.good("Imported"),
+            if imported.is_empty() {
+                "no branches".to_owned()
+            } else {
+                imported.join(", ")
+            },
+            bases.join(", ")
+        );
+
+        Ok(())
+    }
+}
+
+/// The refs and boundary commits listed at the start of a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BundleHeader {
+    prerequisites: Vec<git2::Oid>,
+    branches: Vec<(git2::Oid, String)>,
+}
+
+impl BundleHeader {
+    /// Read up to the end of the header, leaving the packfile alone
+    fn read(path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+        use std::io::BufRead as _;
+
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut header = Vec::new();
+        loop {
+            let start = header.len();
+            if reader.read_until(b'\n', &mut header)? == 0 || header[start..] == *b"\n" {
+                return Ok(header);
+            }
+        }
+    }
+
+    fn parse(content: &[u8]) -> Option<Self> {
+        let mut lines = content.split(|b| *b == b'\n');
+        let signature = lines.next()?;
+        if signature != b"# v2 git bundle" && signature != b"# v3 git bundle" {
+            return None;
+        }
+
+        let mut prerequisites = Vec::new();
+        let mut branches = Vec::new();
+        for line in lines {
+            if line.is_empty() {
+                return Some(Self {
+                    prerequisites,
+                    branches,
+                });
+            }
+            let line = std::str::from_utf8(line).ok()?;
+            if line.starts_with('@') {
+                // v3 capability
+            } else if let Some(prerequisite) = line.strip_prefix('-') {
+                let id = prerequisite
+                    .split_once(' ')
+                    .map(|(id, _)| id)
+                    .unwrap_or(prerequisite);
+                prerequisites.push(git2::Oid::from_str(id).ok()?);
+            } else {
+                let (id, name) = line.split_once(' ')?;
+                if let Some(name) = name.strip_prefix("refs/heads/") {
+                    branches.push((git2::Oid::from_str(id).ok()?, name.to_owned()));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_header() {
+        let content = b"# v2 git bundle
+-1111111111111111111111111111111111111111 Base
+2222222222222222222222222222222222222222 refs/heads/feature
+3333333333333333333333333333333333333333 refs/tags/v1
+
+PACK";
+        let header = BundleHeader::parse(content).unwrap();
+        assert_eq!(
+            header.prerequisites,
+            vec![git2::Oid::from_str("1111111111111111111111111111111111111111").unwrap()]
+        );
+        assert_eq!(
+            header.branches,
+            vec![(
+                git2::Oid::from_str("2222222222222222222222222222222222222222").unwrap(),
+                "feature".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_not_bundle() {
+        assert_eq!(BundleHeader::parse(b"PACK"), None);
+    }
+}
@@ -0,0 +1,321 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Generate shell completions
+///
+/// For example, for bash: `source <(git-stack completions bash)`
+#[derive(clap::Args)]
+pub(crate) struct CompletionsArgs {
+    #[arg(value_enum)]
+    shell: Shell,
+
+    /// Ask `git-stack` for candidates while completing, rather than embedding them in the script
+    ///
+    /// This allows completing branch names and stays accurate across upgrades.
+    #[arg(long)]
+    dynamic: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl CompletionsArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let mut stdout = anstream::stdout().lock();
+        if self.dynamic {
+            let script = match self.shell {
+                Shell::Bash => BASH_DYNAMIC,
+                Shell::Zsh => ZSH_DYNAMIC,
+                Shell::Fish => FISH_DYNAMIC,
+            };
+            stdout
+                .write_all(script.as_bytes())
+                .with_code(proc_exit::Code::FAILURE)?;
+        } else {
+            let mut cmd = <crate::args::Args as clap::CommandFactory>::command();
+            let generator = match self.shell {
+                Shell::Bash => clap_complete::Shell::Bash,
+                Shell::Zsh => clap_complete::Shell::Zsh,
+                Shell::Fish => clap_complete::Shell::Fish,
+            };
+            let mut script = Vec::new();
+            clap_complete::generate(generator, &mut cmd, "git-stack", &mut script);
+            stdout
+                .write_all(&script)
+                .with_code(proc_exit::Code::FAILURE)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report completion candidates for the words of a command-line
+///
+/// The last word is the one being completed.  Candidates are written one per line.
+#[derive(clap::Args)]
+pub(crate) struct CompleteArgs {
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    words: Vec<String>,
+}
+
+impl CompleteArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let mut stdout = anstream::stdout().lock();
+        for candidate in candidates(&self.words) {
+            let _ = writeln!(stdout, "{candidate}");
+        }
+        Ok(())
+    }
+}
+
+fn candidates(words: &[String]) -> Vec<String> {
+    let (partial, preceding) = match words.split_last() {
+        Some((partial, preceding)) => (partial.as_str(), preceding),
+        None => ("", words),
+    };
+
+    let mut cmd = <crate::args::Args as clap::CommandFactory>::command();
+    cmd.build();
+    let mut current = &cmd;
+    let mut positional_index = 0;
+    let mut pending: Option<&clap::Arg> = None;
+    for word in preceding {
+        if pending.take().is_some() {
+            continue;
+        }
+        if let Some(long) = word.strip_prefix("--") {
+            if long.is_empty() || long.contains('=') {
+                continue;
+            }
+            pending = current
+                .get_arguments()
+                .find(|a| {
+                    a.get_long_and_visible_aliases()
+                        .unwrap_or_default()
+                        .contains(&long)
+                })
+                .filter(|a| a.get_action().takes_values());
+        } else if let Some(short) = word.strip_prefix('-').filter(|s| s.chars().count() == 1) {
+            let short = short.chars().next().unwrap();
+            pending = current
+                .get_arguments()
+                .find(|a| a.get_short() == Some(short))
+                .filter(|a| a.get_action().takes_values());
+        } else if let Some(subcommand) = current
+            .find_subcommand(word)
+            .filter(|_| positional_index == 0)
+        {
+            current = subcommand;
+        } else {
+            positional_index += 1;
+        }
+    }
+
+    let mut candidates = Vec::new();
+    if let Some(arg) = pending {
+        candidates.extend(values(current, arg));
+    } else if partial.starts_with('-') {
+        candidates.extend(
+            current
+                .get_arguments()
+                .filter(|a| !a.is_hide_set())
+                .filter_map(|a| a.get_long())
+                .map(|l| format!("--{l}")),
+        );
+    } else {
+        if positional_index == 0 {
+            candidates.extend(
+                current
+                    .get_subcommands()
+                    .filter(|s| !s.is_hide_set())
+                    .map(|s| s.get_name().to_owned()),
+            );
+        }
+        // The last positional may take any number of values
+        let arg = current.get_positionals().nth(positional_index).or_else(|| {
+            current
+                .get_positionals()
+                .last()
+                .filter(|a| matches!(a.get_action(), clap::ArgAction::Append))
+        });
+        if let Some(arg) = arg {
+            candidates.extend(values(current, arg));
+        }
+    }
+    candidates.retain(|c| c.starts_with(partial));
+    candidates
+}
+
+fn values(cmd: &clap::Command, arg: &clap::Arg) -> Vec<String> {
+    let possible_values = arg.get_possible_values();
+    if !possible_values.is_empty() {
+        let mut values: Vec<_> = possible_values
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| v.get_name().to_owned())
+            .collect();
+        if arg.get_id() == "stack" {
+            values.extend(stack_names());
+        }
+        return values;
+    }
+
+    match (cmd.get_name(), arg.get_id().as_str()) {
+        (_, "base" | "onto" | "rev") => branch_names(),
+        ("regroup", "stack") | ("rename-stack", "old") => stack_names(),
+        ("regroup", "branches") => branch_names(),
+        ("config", "keys") => config_keys(),
+        ("help", "topic") => {
+            let mut topics = vec![crate::help::WORKFLOWS_TOPIC.to_owned()];
+            let root = <crate::args::Args as clap::CommandFactory>::command();
+            topics.extend(
+                root.get_subcommands()
+                    .filter(|s| !s.is_hide_set())
+                    .map(|s| s.get_name().to_owned()),
+            );
+            topics
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn stack_names() -> Vec<String> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return Vec::new();
+    };
+    let Ok(repo) = git2::Repository::discover(cwd) else {
+        return Vec::new();
+    };
+    let Ok(named_stacks) = git_stack::config::NamedStacks::from_repo(&repo) else {
+        return Vec::new();
+    };
+    named_stacks
+        .stacks()
+        .into_keys()
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+/// The `stack.*` keys in [`git_stack::config::FIELDS`] that can be named as-is
+fn config_keys() -> Vec<String> {
+    git_stack::config::FIELDS
+        .iter()
+        .map(|f| f.key)
+        .filter(|k| k.starts_with("stack.") && !k.contains('<'))
+        .map(|k| k.to_owned())
+        .collect()
+}
+
+fn branch_names() -> Vec<String> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return Vec::new();
+    };
+    let Ok(repo) = git2::Repository::discover(cwd) else {
+        return Vec::new();
+    };
+    let Ok(branches) = repo.branches(None) else {
+        return Vec::new();
+    };
+    let mut names: Vec<_> = branches
+        .filter_map(|b| b.ok())
+        .filter_map(|(b, _)| b.name().ok().flatten().map(|n| n.to_owned()))
+        .filter(|n| !n.ends_with("/HEAD"))
+        .collect();
+    names.sort();
+    names
+}
+
+const BASH_DYNAMIC: &str = r#"_git_stack() {
+    local i start=1
+    for ((i = 0; i < COMP_CWORD; i++)); do
+        if [[ "${COMP_WORDS[i]}" == "stack" || "${COMP_WORDS[i]}" == *git-stack ]]; then
+            start=$((i + 1))
+            break
+        fi
+    done
+    local IFS=$'\n'
+    COMPREPLY=($(git-stack __complete -- "${COMP_WORDS[@]:start:COMP_CWORD-start+1}" 2>/dev/null))
+}
+complete -o default -F _git_stack git-stack
+"#;
+
+const ZSH_DYNAMIC: &str = r#"#compdef git-stack
+_git-stack() {
+    local -a candidates
+    candidates=("${(@f)$(git-stack __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    compadd -a candidates
+}
+compdef _git-stack git-stack
+"#;
+
+const FISH_DYNAMIC: &str = r#"function __git_stack_complete
+    set -l tokens (commandline -opc) (commandline -ct)
+    set -l start 2
+    if test "$tokens[1]" = git
+        set start 3
+    end
+    git-stack __complete -- $tokens[$start..-1] 2>/dev/null
+end
+complete -c git-stack -f -a '(__git_stack_complete)'
+complete -c git -n '__fish_seen_subcommand_from stack' -f -a '(__git_stack_complete)'
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn complete(words: &[&str]) -> Vec<String> {
+        let words: Vec<_> = words.iter().map(|w| (*w).to_owned()).collect();
+        candidates(&words)
+    }
+
+    #[test]
+    fn subcommands() {
+        let actual = complete(&["sy"]);
+        assert_eq!(actual, ["sync"]);
+    }
+
+    #[test]
+    fn flags() {
+        let actual = complete(&["sync", "--dr"]);
+        assert_eq!(actual, ["--dry-run"]);
+    }
+
+    #[test]
+    fn value_enum() {
+        let actual = complete(&["--stack", "d"]);
+        assert_eq!(actual, ["dependents", "descendants"]);
+    }
+
+    #[test]
+    fn help_topic() {
+        let actual = complete(&["help", "wo"]);
+        assert_eq!(actual, ["workflows", "worktree"]);
+    }
+
+    #[test]
+    fn config_key() {
+        let actual = complete(&["config", "stack.auto-f"]);
+        assert_eq!(actual, ["stack.auto-fixup"]);
+    }
+
+    #[test]
+    fn config_keys_are_concrete() {
+        let actual = complete(&["config", "stack.protected", "stack."]);
+        assert!(
+            actual.contains(&"stack.push-remote".to_owned()),
+            "{actual:?}"
+        );
+        assert!(actual.iter().all(|k| !k.contains('<')), "{actual:?}");
+    }
+
+    #[test]
+    fn hidden_is_skipped() {
+        let actual = complete(&["__"]);
+        assert!(actual.is_empty(), "{actual:?}");
+    }
+}
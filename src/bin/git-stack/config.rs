@@ -2,6 +2,318 @@ use std::io::Write;
 
 use proc_exit::prelude::*;
 
+/// Show the settings `git stack` uses and where each was set
+///
+/// By default, only settings set by a gitconfig, profile, or the environment are shown.
+#[derive(clap::Args)]
+pub(crate) struct ConfigArgs {
+    /// Only show these settings, even if left at their default
+    #[arg(value_name = "KEY", conflicts_with_all = ["schema", "validate"])]
+    keys: Vec<String>,
+
+    /// Show every setting, including defaults
+    #[arg(long)]
+    effective: bool,
+
+    /// Print as JSON, for scripts
+    #[arg(long)]
+    json: bool,
+
+    /// Print a JSON Schema of the `git config` keys `git stack` reads
+    #[arg(long, conflicts_with_all = ["effective", "json", "validate"])]
+    schema: bool,
+
+    /// Check each config file for unknown keys and invalid values
+    #[arg(long, conflicts_with_all = ["effective", "json"])]
+    validate: bool,
+}
+
+#[derive(serde::Serialize)]
+struct Setting {
+    key: String,
+    value: String,
+    source: String,
+}
+
+impl ConfigArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        if self.schema {
+            let output =
+                serde_json::to_string_pretty(&schema()).with_code(proc_exit::Code::FAILURE)?;
+            writeln!(anstream::stdout(), "{output}").to_sysexits()?;
+            return Ok(());
+        }
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        if self.validate {
+            return validate(&repo);
+        }
+
+        let layers = git_stack::config::RepoConfig::layers(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let settings: Vec<_> = git_stack::config::RepoConfig::effective_sources(&layers)
+            .into_iter()
+            .filter(|(key, _, source)| {
+                if self.keys.is_empty() {
+                    self.effective || *source != git_stack::config::ConfigSource::Default
+                } else {
+                    self.keys.iter().any(|k| k.eq_ignore_ascii_case(key))
+                }
+            })
+            .map(|(key, value, source)| Setting {
+                key,
+                value,
+                source: source.to_string(),
+            })
+            .collect();
+
+        let mut stdout = anstream::stdout().lock();
+        if self.json {
+            let output =
+                serde_json::to_string_pretty(&settings).with_code(proc_exit::Code::FAILURE)?;
+            writeln!(stdout, "{output}").to_sysexits()?;
+        } else {
+            let palette = crate::ops::Palette::colored();
+            for setting in &settings {
+                writeln!(
+                    stdout,
+                    "{}={} {}",
+                    setting.key,
+                    setting.value,
+                    palette.hint(format_args!("({})", setting.source))
+                )
+                .to_sysexits()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn schema() -> serde_json::Value {
+    use git_stack::config::FieldKind;
+
+    let mut properties = serde_json::Map::new();
+    let mut pattern_properties = serde_json::Map::new();
+    for field in git_stack::config::FIELDS {
+        let (mut value, format) = match field.kind {
+            FieldKind::String => (serde_json::json!({"type": "string"}), None),
+            FieldKind::Bool => (serde_json::json!({"type": "boolean"}), None),
+            FieldKind::Integer => (serde_json::json!({"type": "integer", "minimum": 0}), None),
+            FieldKind::Duration => (
+                serde_json::json!({"type": "string"}),
+                Some("a time delta, like `10days`"),
+            ),
+            FieldKind::Choice(choices) => (
+                serde_json::json!({"type": "string", "enum": choices()}),
+                None,
+            ),
+            FieldKind::Color => (
+                serde_json::json!({"type": "string"}),
+                Some("a git color, like `blue bold`"),
+            ),
+            FieldKind::DefaultCommand => (
+                serde_json::json!({
+                    "type": "string",
+                    "pattern": r"^(show|sync|(pull|rebase|push)(\+(pull|rebase|push))*)$",
+                }),
+                None,
+            ),
+        };
+        if field.key.starts_with("stack-profile.") {
+            value = serde_json::json!({});
+        }
+        if field.multivar {
+            value = serde_json::json!({"type": "array", "items": value});
+        }
+        let description = match format {
+            Some(format) => format!("{} ({})", field.description, format),
+            None => field.description.to_owned(),
+        };
+        value["description"] = description.into();
+
+        if field.key.contains('<') {
+            let mut pattern = String::from("^");
+            let mut rest = field.key;
+            while let Some((literal, placeholder)) = rest.split_once('<') {
+                pattern.push_str(&literal.replace('.', r"\."));
+                pattern.push_str(".+");
+                rest = placeholder.split_once('>').map(|(_, r)| r).unwrap_or("");
+            }
+            pattern.push_str(&rest.replace('.', r"\."));
+            pattern.push('$');
+            pattern_properties.insert(pattern, value);
+        } else {
+            properties.insert(field.key.to_owned(), value);
+        }
+    }
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "git-stack configuration",
+        "description": "The `git config` keys git-stack reads, as `<section>.<key>`",
+        "type": "object",
+        "properties": properties,
+        "patternProperties": pattern_properties,
+        "additionalProperties": false,
+    })
+}
+
+/// Report unknown `git stack` keys and invalid values in each config file and the environment
+fn validate(repo: &git2::Repository) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+
+    let mut paths: Vec<std::path::PathBuf> = [
+        git2::Config::find_system(),
+        git2::Config::find_xdg(),
+        git2::Config::find_global(),
+    ]
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    if let Some(workdir) = repo.workdir() {
+        paths.push(workdir.join(".gitconfig"));
+    }
+    paths.push(repo.path().join("config"));
+
+    let mut problems = Vec::new();
+    for path in paths {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let config = git2::Config::open(&path).with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let mut entries = config
+            .entries(None)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let mut occurrences = std::collections::HashMap::<String, usize>::new();
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else { continue };
+            // Included files are only checked when their includer is
+            if entry.include_depth() != 0 {
+                continue;
+            }
+            let Some(key) = entry.name() else { continue };
+            let occurrence = occurrences.entry(key.to_owned()).or_default();
+            let line = locate(&content, key, *occurrence);
+            *occurrence += 1;
+            if let Err(message) = check_entry(key, entry.value()) {
+                let location = match line {
+                    Some(line) => format!("{}:{}", path.display(), line),
+                    None => path.display().to_string(),
+                };
+                problems.push(format!("{location}: {message}"));
+            }
+        }
+    }
+
+    let params = git_config_env::ConfigParameters::new();
+    let env = git_config_env::ConfigEnv::new();
+    let env_entries = params
+        .iter()
+        .chain(env.iter().map(|(key, value)| (key, Some(value))));
+    for (key, value) in env_entries {
+        if let Err(message) = check_entry(&key, value.as_deref()) {
+            problems.push(format!("env: {message}"));
+        }
+    }
+
+    let mut stderr = anstream::stderr().lock();
+    for problem in &problems {
+        let _ = writeln!(stderr, "{}: {}", stderr_palette.error("error"), problem);
+    }
+    if problems.is_empty() {
+        let _ = writeln!(stderr, "{}: no problems found", stderr_palette.info("note"));
+        Ok(())
+    } else {
+        Err(proc_exit::sysexits::CONFIG_ERR.as_exit())
+    }
+}
+
+fn check_entry(key: &str, value: Option<&str>) -> Result<(), String> {
+    use git_stack::config::FieldKind;
+
+    if !git_stack::config::is_stack_key(key) {
+        return Ok(());
+    }
+    let Some(field) = git_stack::config::find_field(key) else {
+        return Err(match git_stack::config::suggest_field(key) {
+            Some(suggestion) => format!("unknown key `{key}`, did you mean `{suggestion}`?"),
+            None => format!("unknown key `{key}`"),
+        });
+    };
+    let Some(value) = value else {
+        return match field.kind {
+            FieldKind::Bool => Ok(()),
+            _ => Err(format!("`{key}` needs a value")),
+        };
+    };
+    let expected = match field.kind {
+        FieldKind::String => return Ok(()),
+        FieldKind::Bool => git2::Config::parse_bool(value)
+            .map(|_| ())
+            .map_err(|_| "a bool, like `true` or `false`".to_owned()),
+        FieldKind::Integer => git2::Config::parse_i64(value)
+            .ok()
+            .filter(|i| 0 <= *i)
+            .map(|_| ())
+            .ok_or_else(|| "a non-negative integer".to_owned()),
+        FieldKind::Duration => humantime::parse_duration(value)
+            .map(|_| ())
+            .map_err(|_| "a time delta, like `10days`".to_owned()),
+        FieldKind::Choice(choices) => {
+            let choices = choices();
+            if choices.iter().any(|c| c == value) {
+                Ok(())
+            } else {
+                Err(format!("one of {}", choices.join(", ")))
+            }
+        }
+        FieldKind::Color => crate::ops::parse_git_color(value).map(|_| ()),
+        FieldKind::DefaultCommand => {
+            <git_stack::config::DefaultCommand as std::str::FromStr>::from_str(value).map(|_| ())
+        }
+    };
+    expected.map_err(|expected| format!("invalid `{key}={value}`, expected {expected}"))
+}
+
+/// The line, counting from 1, of the `occurrence`th (from 0) setting of `key` in a gitconfig file
+fn locate(content: &str, key: &str, occurrence: usize) -> Option<usize> {
+    let mut section = String::new();
+    let mut seen = 0;
+    for (i, line) in content.lines().enumerate() {
+        let mut line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let Some((header, rest)) = header.split_once(']') else {
+                continue;
+            };
+            section = match header.split_once('"') {
+                Some((name, subsection)) => format!(
+                    "{}.{}",
+                    name.trim().to_ascii_lowercase(),
+                    subsection.trim_end_matches('"')
+                ),
+                None => header.trim().to_ascii_lowercase(),
+            };
+            line = rest.trim();
+        }
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let name = line
+            .split(|c: char| c == '=' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        if format!("{section}.{name}").eq_ignore_ascii_case(key) {
+            if seen == occurrence {
+                return Some(i + 1);
+            }
+            seen += 1;
+        }
+    }
+    None
+}
+
 pub(crate) fn dump_config(
     args: &crate::args::Args,
     output_path: &std::path::Path,
@@ -28,6 +340,7 @@ pub(crate) fn dump_config(
 }
 
 pub(crate) fn protect(args: &crate::args::Args, ignore: &str) -> proc_exit::ExitResult {
+    crate::lock::require_writable(false)?;
     log::trace!("Initializing");
     let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
     let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
@@ -56,7 +369,7 @@ pub(crate) fn protected(args: &crate::args::Args) -> proc_exit::ExitResult {
         .with_code(proc_exit::sysexits::CONFIG_ERR)?
         .update(args.to_config());
     let protected = git_stack::legacy::git::ProtectedBranches::new(
-        repo_config.protected_branches().iter().map(|s| s.as_str()),
+        repo_config.protected_patterns().iter().map(|s| s.as_str()),
     )
     .with_code(proc_exit::sysexits::CONFIG_ERR)?;
 
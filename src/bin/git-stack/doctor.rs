@@ -0,0 +1,289 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Check for setup problems that get in the way of `git stack`
+///
+/// Each problem is reported with how to fix it.
+#[derive(clap::Args)]
+pub(crate) struct DoctorArgs {}
+
+impl DoctorArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let layers = git_stack::config::RepoConfig::layers(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let repo_config = git_stack::config::RepoConfig::from_layers(layers.iter().cloned());
+
+        let mut findings = Vec::new();
+        findings.extend(check_git_version());
+        findings.extend(check_head(&repo));
+        findings.extend(
+            git_stack::config::RepoConfig::problems(&repo, &layers)
+                .into_iter()
+                .map(|problem| Finding {
+                    message: problem,
+                    fix: "correct or `git config --unset` it, `git stack config --effective` shows where it is set".to_owned(),
+                }),
+        );
+        findings.extend(check_protected(&repo, &repo_config).with_code(proc_exit::Code::FAILURE)?);
+        findings.extend(check_upstreams(&repo).with_code(proc_exit::Code::FAILURE)?);
+        findings.extend(check_fetched(&repo, &repo_config).with_code(proc_exit::Code::FAILURE)?);
+        findings.extend(check_hooks(&repo).with_code(proc_exit::Code::FAILURE)?);
+        findings.extend(check_snapshots(&repo, &repo_config).with_code(proc_exit::Code::FAILURE)?);
+
+        let mut stderr = anstream::stderr().lock();
+        for finding in &findings {
+            let _ = writeln!(
+                stderr,
+                "{}: {}",
+                stderr_palette.warn("warning"),
+                finding.message
+            );
+            let _ = writeln!(stderr, "  {}: {}", stderr_palette.hint("fix"), finding.fix);
+        }
+        if findings.is_empty() {
+            let _ = writeln!(stderr, "{}: no problems found", stderr_palette.info("note"));
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.with_message(format!(
+                "found {} {}",
+                findings.len(),
+                if findings.len() == 1 {
+                    "problem"
+                } else {
+                    "problems"
+                }
+            )))
+        }
+    }
+}
+
+/// A problem and how to fix it
+struct Finding {
+    message: String,
+    fix: String,
+}
+
+/// What `git-stack` leaves to `git`, and the oldest version that supports it
+const GIT_FEATURES: &[(git_stack::git::GitVersion, &str)] = &[
+    (
+        git_stack::git::GitVersion::new(2, 4, 0),
+        "`git stack --push`, which pushes with `--atomic`",
+    ),
+    (
+        git_stack::git::GitVersion::new(2, 10, 0),
+        "`stack.push-style=gitlab-mr`, which needs push options",
+    ),
+    (
+        git_stack::git::GitVersion::new(2, 34, 0),
+        "SSH-signed commits in `--show-signatures`",
+    ),
+];
+
+fn check_git_version() -> Option<Finding> {
+    let Some(version) = git_stack::git::GitVersion::from_cli() else {
+        return Some(Finding {
+            message: "could not run `git`".to_owned(),
+            fix: "install `git` and make sure it is on your `PATH`".to_owned(),
+        });
+    };
+    let unsupported: Vec<_> = GIT_FEATURES
+        .iter()
+        .filter(|(min, _)| version < *min)
+        .collect();
+    let (newest, _) = unsupported.last()?;
+    Some(Finding {
+        message: format!(
+            "git {version} is too old for {}",
+            unsupported
+                .iter()
+                .map(|(_, feature)| *feature)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        fix: format!("upgrade `git` to {newest} or newer"),
+    })
+}
+
+fn check_head(repo: &git2::Repository) -> Option<Finding> {
+    if repo.state() != git2::RepositoryState::Clean {
+        return Some(Finding {
+            message: format!("a {:?} is in progress", repo.state()),
+            fix: "finish or abort it, like with `git rebase --continue` or `git rebase --abort`"
+                .to_owned(),
+        });
+    }
+    if crate::ops::is_head_unborn(repo) {
+        return Some(Finding {
+            message: "`HEAD` has no commits yet".to_owned(),
+            fix: "make your first commit with `git commit`".to_owned(),
+        });
+    }
+    if repo.head_detached().unwrap_or(false) {
+        let head = repo.head().ok()?.peel_to_commit().ok()?;
+        let short_id = head.as_object().short_id().ok()?;
+        return Some(Finding {
+            message: format!(
+                "`HEAD` is detached at {}, so `git stack` can't tell which stack you are on",
+                short_id.as_str().unwrap_or_default()
+            ),
+            fix: "`git switch <branch>`, or `git switch -c <branch>` to keep the commits you made"
+                .to_owned(),
+        });
+    }
+    None
+}
+
+fn check_protected(
+    repo: &git2::Repository,
+    repo_config: &git_stack::config::RepoConfig,
+) -> Result<Option<Finding>, eyre::Error> {
+    let protected = git_stack::git::ProtectedBranches::new(
+        repo_config.protected_patterns().iter().map(|s| s.as_str()),
+    )?;
+    for entry in repo.branches(None)? {
+        let (branch, kind) = entry?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        let name = match kind {
+            git2::BranchType::Local => name,
+            git2::BranchType::Remote => match name.split_once('/') {
+                Some((_, name)) => name,
+                None => continue,
+            },
+        };
+        if name != "HEAD" && protected.is_protected(name) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(Finding {
+        message: "no branches are protected, so `git stack` treats every branch as yours to rewrite"
+            .to_owned(),
+        fix: "`git stack --protect <branch>` for the branch you merge into, like `git stack --protect main`"
+            .to_owned(),
+    }))
+}
+
+fn check_upstreams(repo: &git2::Repository) -> Result<Option<Finding>, git2::Error> {
+    let mut gone = Vec::new();
+    for entry in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = entry?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        if let Some(upstream) = git_stack::git::gone_upstream(repo, name)? {
+            gone.push(format!("`{name}` (`{upstream}`)"));
+        }
+    }
+    if gone.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Finding {
+        message: format!("upstreams are gone for {}", gone.join(", ")),
+        fix: "`git stack tidy` to stop tracking them".to_owned(),
+    }))
+}
+
+fn check_fetched(
+    repo: &git2::Repository,
+    repo_config: &git_stack::config::RepoConfig,
+) -> Result<Option<Finding>, eyre::Error> {
+    let remote = repo_config.pull_remote();
+    let protected = git_stack::git::ProtectedBranches::new(
+        repo_config.protected_patterns().iter().map(|s| s.as_str()),
+    )?;
+    let mut newest = None;
+    for entry in repo.branches(Some(git2::BranchType::Remote))? {
+        let (branch, _) = entry?;
+        let Some((branch_remote, name)) = branch.name()?.and_then(|n| n.split_once('/')) else {
+            continue;
+        };
+        if branch_remote != remote || name == "HEAD" || !protected.is_protected(name) {
+            continue;
+        }
+        if let Some(age) = crate::ops::remote_branch_age(repo, remote, name) {
+            newest = Some(
+                newest
+                    .map(|newest: std::time::Duration| newest.min(age))
+                    .unwrap_or(age),
+            );
+        }
+    }
+    let Some(age) = newest else {
+        return Ok(None);
+    };
+    if age < repo_config.fresh_max_age() {
+        return Ok(None);
+    }
+    let age = humantime::format_duration(std::time::Duration::from_secs(age.as_secs()));
+    Ok(Some(Finding {
+        message: format!(
+            "`{remote}` was last fetched {age} ago, so branches merged since then aren't cleaned up"
+        ),
+        fix: "`git stack fetch --all-stacks`".to_owned(),
+    }))
+}
+
+/// Hooks `git stack` runs, or leaves to `git push`
+const HOOKS: &[&str] = &["pre-push", "post-rewrite", "reference-transaction"];
+
+fn check_hooks(repo: &git2::Repository) -> Result<Vec<Finding>, git2::Error> {
+    let hooks = git_stack::git::hooks(repo)?;
+    let mut findings = Vec::new();
+    if !hooks.root().exists() {
+        if let Ok(hooks_path) = repo.config()?.get_string("core.hooksPath") {
+            findings.push(Finding {
+                message: format!(
+                    "`core.hooksPath` is `{hooks_path}`, which doesn't exist, so no hooks run"
+                ),
+                fix: "create it, or `git config --unset core.hooksPath`".to_owned(),
+            });
+        }
+        return Ok(findings);
+    }
+    for name in HOOKS {
+        let path = hooks.root().join(name);
+        if path.exists() && hooks.find_hook(repo, name).is_none() {
+            findings.push(Finding {
+                message: format!("the `{name}` hook isn't executable, so it is skipped"),
+                fix: format!("`chmod +x {}`", path.display()),
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// How many snapshots a stack without a limit can have before it is called out
+const MANY_SNAPSHOTS: usize = 100;
+
+fn check_snapshots(
+    repo: &git2::Repository,
+    repo_config: &git_stack::config::RepoConfig,
+) -> Result<Vec<Finding>, git2::Error> {
+    let stash_repo = git_branch_stash::GitRepo::new(git2::Repository::open(repo.path())?);
+    let mut findings = Vec::new();
+    for stack in git_branch_stash::Stack::all(&stash_repo) {
+        if repo_config.stash_capacity(&stack.name).is_some()
+            || repo_config.stash_max_age(&stack.name).is_some()
+        {
+            continue;
+        }
+        let count = stack.iter().count();
+        if count <= MANY_SNAPSHOTS {
+            continue;
+        }
+        let name = &stack.name;
+        findings.push(Finding {
+            message: format!(
+                "the `{name}` snapshot stack has {count} snapshots and no capacity or max-age, so it grows without limit"
+            ),
+            fix: format!("`git config branch-stash.{name}.capacity 30` or `git config branch-stash.{name}.max-age 30days`"),
+        });
+    }
+    Ok(findings)
+}
@@ -0,0 +1,336 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Stop at a commit in the stack to change it, like `edit` in `git rebase --interactive`
+///
+/// `HEAD` is detached at the commit so you can change it however you like, whether by staging
+/// changes or with `git commit --amend`, or add commits on top of it.  `--continue` amends in any
+/// staged changes, rebases everything that was stacked on the commit onto the new `HEAD`, and
+/// switches back to your branch.
+#[derive(clap::Args)]
+pub(crate) struct EditArgs {
+    /// Commit to edit
+    #[arg(required_unless_present_any = ["continue_", "abort"])]
+    rev: Option<String>,
+
+    /// Finish the edit, rebasing the descendants of the commit onto `HEAD`
+    #[arg(long = "continue", conflicts_with_all = ["rev", "abort"])]
+    continue_: bool,
+
+    /// Give up on the edit, switching back to the branch as it was
+    #[arg(long, conflicts_with = "rev")]
+    abort: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
+}
+
+impl EditArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, false)?;
+        let stop = git_stack::git::EditStop::load(&repo).with_code(proc_exit::Code::FAILURE)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        match (self.rev.as_deref(), stop) {
+            (Some(_), Some(_)) => Err(proc_exit::sysexits::USAGE_ERR.with_message(
+                "already editing a commit, run `git stack edit --continue` or `git stack edit --abort`",
+            )),
+            (Some(rev), None) => self.start(&mut repo, rev),
+            (None, Some(stop)) if self.continue_ => self.finish(&cwd, &mut repo, &stop),
+            (None, Some(stop)) => self.abort(&mut repo, &stop),
+            (None, None) => {
+                Err(proc_exit::sysexits::USAGE_ERR.with_message("no edit in progress"))
+            }
+        }
+    }
+
+    fn start(&self, repo: &mut git_stack::git::GitRepo, rev: &str) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let Some(branch) = repo.head_branch() else {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message("`HEAD` is detached, switch to the branch to edit first"));
+        };
+        let branch = branch.name.clone();
+        if repo.raw().state() != git2::RepositoryState::Clean {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "cannot edit commits, {:?} in progress",
+                repo.raw().state()
+            )));
+        }
+        if repo.is_dirty() {
+            return Err(
+                proc_exit::sysexits::USAGE_ERR.with_message("Working tree is dirty, aborting")
+            );
+        }
+
+        let edit_id = crate::ops::resolve_explicit_base(repo, rev)
+            .with_code(proc_exit::Code::FAILURE)?
+            .id;
+        let head_id = repo.head_commit().id;
+        if repo.merge_base(edit_id, head_id) != Some(edit_id) {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message(format!("`{rev}` isn't part of `{branch}`")));
+        }
+        let graph = stack_graph(repo, &repo_config, edit_id)?;
+        let action = graph
+            .commit_get::<git_stack::graph::Action>(edit_id)
+            .copied()
+            .unwrap_or_default();
+        if action.is_protected() {
+            return Err(proc_exit::Code::FAILURE.with_message("cannot edit protected commits"));
+        }
+        let anchors =
+            git_stack::git::Anchors::from_repo(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        if anchors.contains(edit_id) {
+            return Err(proc_exit::Code::FAILURE
+                .with_message("cannot edit an anchor, run `git stack anchor --remove` first"));
+        }
+
+        git_stack::git::EditStop::new(edit_id, branch)
+            .save(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        repo.switch_commit(edit_id)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let commit = repo.find_commit(edit_id).expect("explicit bases exist");
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} at {}: {}",
+            stderr_palette.good("Stopped"),
+            stderr_palette.highlight(&edit_id.to_string()[..7]),
+            stderr_palette.hint(commit.display_summary())
+        );
+        let _ = writeln!(
+            anstream::stderr(),
+            "{}: stage your changes or `git commit --amend`, then run `git stack edit --continue`",
+            stderr_palette.info("note"),
+        );
+        Ok(())
+    }
+
+    fn finish(
+        &self,
+        cwd: &std::path::Path,
+        repo: &mut git_stack::git::GitRepo,
+        stop: &git_stack::git::EditStop,
+    ) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
+        if repo.raw().state() != git2::RepositoryState::Clean {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "cannot continue, {:?} in progress",
+                repo.raw().state()
+            )));
+        }
+        if !repo.raw().head_detached().unwrap_or(false) {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(
+                "`HEAD` is no longer detached, `git switch --detach` back to the edited commit or run `git stack edit --abort`",
+            ));
+        }
+
+        let edit_id = stop.commit_id().with_code(proc_exit::Code::FAILURE)?;
+        let tip_id = amend_staged(repo).with_code(proc_exit::Code::FAILURE)?;
+
+        if tip_id == edit_id {
+            let _ = writeln!(
+                anstream::stderr(),
+                "{}: nothing changed, switching back to `{}`",
+                stderr_palette.info("note"),
+                stop.branch
+            );
+            return self.abort(repo, stop);
+        }
+
+        let mut graph = stack_graph(repo, &repo_config, edit_id)?;
+        if !graph.contains_id(edit_id) {
+            return Err(proc_exit::Code::FAILURE.with_message(format!(
+                "`{}` is no longer part of a stack, run `git stack edit --abort`",
+                &edit_id.to_string()[..7]
+            )));
+        }
+        rebase_onto_edit(&mut graph, repo, edit_id, tip_id).with_code(proc_exit::Code::FAILURE)?;
+        let scripts = git_stack::graph::to_scripts(&graph, vec![]);
+
+        let backed_up = crate::ops::backup_branches(
+            cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "edit",
+            None,
+            false,
+        )?;
+        git_stack::git::EditStop::clear(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+
+        let stash_id = git_stack::git::stash_push(repo, "edit");
+        let mut executor = git_stack::rewrite::Executor::new(false);
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            repo,
+            &mut executor,
+            scripts,
+            Some(stop.branch.as_str()),
+            stash_id,
+        )?;
+
+        if success {
+            let commit = repo.find_commit(tip_id).expect("`HEAD` exists");
+            let _ = writeln!(
+                anstream::stderr(),
+                "{} {}: {}",
+                stderr_palette.good("Edited"),
+                stderr_palette.highlight(&tip_id.to_string()[..7]),
+                stderr_palette.hint(commit.display_summary())
+            );
+        }
+
+        git_stack::git::stash_pop(repo, stash_id);
+        if backed_up {
+            crate::ops::note_undo();
+        }
+
+        if success {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.as_exit())
+        }
+    }
+
+    fn abort(
+        &self,
+        repo: &mut git_stack::git::GitRepo,
+        stop: &git_stack::git::EditStop,
+    ) -> proc_exit::ExitResult {
+        if repo.is_dirty() {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(
+                "Working tree is dirty, commit or `git stash` your changes before aborting",
+            ));
+        }
+        repo.switch_branch(&stop.branch)
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::git::EditStop::clear(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        Ok(())
+    }
+}
+
+/// The stack `edit_id` is in, as `git stack amend` sees it
+fn stack_graph(
+    repo: &git_stack::git::GitRepo,
+    repo_config: &git_stack::config::RepoConfig,
+    edit_id: git2::Oid,
+) -> Result<git_stack::graph::Graph, proc_exit::Exit> {
+    let protected = git_stack::git::ProtectedBranches::new(
+        repo_config.protected_patterns().iter().map(|s| s.as_str()),
+    )
+    .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    let branches = git_stack::graph::BranchSet::from_repo(repo, &protected)
+        .with_code(proc_exit::Code::FAILURE)?;
+    let base = crate::ops::resolve_implicit_base(
+        repo,
+        edit_id,
+        &branches,
+        repo_config.auto_base_commit_count(),
+    );
+    let merge_base_oid = repo
+        .merge_base(base.id, edit_id)
+        .ok_or_else(|| {
+            git2::Error::new(
+                git2::ErrorCode::NotFound,
+                git2::ErrorClass::Reference,
+                format!("could not find base between {base} and {edit_id}"),
+            )
+        })
+        .with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let stack_branches = branches.descendants(repo, merge_base_oid);
+    let mut graph = git_stack::graph::Graph::from_branches(repo, stack_branches)
+        .with_code(proc_exit::Code::FAILURE)?;
+    git_stack::graph::protect_branches(&mut graph);
+    Ok(graph)
+}
+
+/// Amend the staged changes into `HEAD`, returning the new `HEAD`
+fn amend_staged(repo: &mut git_stack::git::GitRepo) -> Result<git2::Oid, git2::Error> {
+    let head_id = repo.raw().head()?.peel_to_commit()?.id();
+    let tree_id = repo.raw().index()?.write_tree()?;
+    let id = repo.amend_tree(head_id, tree_id)?;
+    if id != head_id {
+        log::debug!("amended {} into {}", head_id, id);
+        repo.raw().set_head_detached(id)?;
+    }
+    Ok(id)
+}
+
+/// Re-parent everything stacked on `edit_id` onto `tip_id`, what it was edited into
+///
+/// Like [`git_stack::graph::move_branch`], everything else is protected so only what is stacked on
+/// the edit is rewritten.
+fn rebase_onto_edit(
+    graph: &mut git_stack::graph::Graph,
+    repo: &git_stack::git::GitRepo,
+    edit_id: git2::Oid,
+    tip_id: git2::Oid,
+) -> eyre::Result<()> {
+    let edit_parent_id = graph.primary_parent_of(edit_id);
+    let mut added = Vec::new();
+    let mut current_id = tip_id;
+    while !graph.contains_id(current_id) {
+        added.push(current_id);
+        current_id = repo
+            .parent_ids(current_id)?
+            .first()
+            .copied()
+            .ok_or_else(|| eyre::eyre!("`HEAD` isn't based on the edited commit"))?;
+    }
+    eyre::ensure!(
+        current_id == edit_id || Some(current_id) == edit_parent_id,
+        "`HEAD` isn't based on the edited commit or its parent, run `git stack edit --abort`"
+    );
+    let mut parent_id = current_id;
+    for id in added.into_iter().rev() {
+        graph.insert(git_stack::graph::Node::new(id), parent_id);
+        parent_id = id;
+    }
+
+    let children = graph
+        .children_of(edit_id)
+        .filter(|id| !graph.ancestors_of(tip_id).any(|a| a == *id))
+        .collect::<Vec<_>>();
+    for child_id in children {
+        graph.rebase(child_id, edit_id, tip_id);
+    }
+    for mut branch in graph.branches.remove(edit_id).into_iter().flatten() {
+        branch.set_id(tip_id);
+        graph.branches.insert(branch);
+    }
+
+    let moved = graph
+        .descendants_of(tip_id)
+        .filter(|id| *id != tip_id)
+        .collect::<std::collections::HashSet<_>>();
+    let others = graph
+        .descendants()
+        .filter(|id| !moved.contains(id))
+        .collect::<Vec<_>>();
+    for id in others {
+        graph.commit_set(id, git_stack::graph::Action::Protected);
+        // Leave their branches be, rather than re-creating them where they are
+        if id != tip_id {
+            graph.branches.remove(id);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Fetch the remotes your stacks are pushed to and pulled from
+///
+/// By default, this covers the remotes of the current stack and the branch it is on top of.  Each
+/// remote is fetched in parallel, pruning remote-tracking branches that are gone.
+#[derive(clap::Args)]
+pub(crate) struct FetchArgs {
+    /// Fetch the remotes of every local branch, not just those of the current stack
+    #[arg(long)]
+    all_stacks: bool,
+
+    /// How many remotes to fetch at once [default: all of them]
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<std::num::NonZeroUsize>,
+
+    /// Only report which remotes would be fetched
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl FetchArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        if crate::ops::is_head_unborn(&repo) {
+            crate::ops::note_unborn_head();
+            return Ok(());
+        }
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        for (branch, remote) in repo_config.branch_push_remotes() {
+            repo.set_branch_push_remote(branch, remote);
+        }
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut remotes = Vec::new();
+        let branches = if self.all_stacks {
+            branches.all()
+        } else {
+            let head_id = repo.head_commit().id;
+            let onto = crate::ops::resolve_implicit_base(
+                &repo,
+                head_id,
+                &branches,
+                repo_config.auto_base_commit_count(),
+            );
+            if let Some(remote) = onto.branch.as_ref().and_then(|b| b.remote.as_deref()) {
+                remotes.push(remote.to_owned());
+            }
+            let base = crate::ops::resolve_base_from_onto(&repo, &onto);
+            match repo.merge_base(base.id, head_id) {
+                Some(merge_base_oid) => branches.dependents(&repo, merge_base_oid, head_id),
+                None => branches.all(),
+            }
+        };
+        remotes.extend(branch_remotes(&repo, &branches));
+        let existing = repo.raw().remotes().ok();
+        let existing: Vec<_> = existing.iter().flat_map(|r| r.iter().flatten()).collect();
+        remotes.sort_unstable();
+        remotes.dedup();
+        remotes.retain(|r| {
+            let exists = existing.contains(&r.as_str());
+            if !exists {
+                log::debug!("Skipping `{}`, there is no such remote", r);
+            }
+            exists
+        });
+
+        let mut stderr = anstream::stderr().lock();
+        if remotes.is_empty() {
+            let _ = writeln!(
+                stderr,
+                "{}: no remotes to fetch",
+                stderr_palette.info("note"),
+            );
+            return Ok(());
+        }
+        if self.dry_run {
+            for remote in &remotes {
+                let _ = writeln!(
+                    stderr,
+                    "{} `{}`",
+                    stderr_palette.good("Would fetch and prune"),
+                    remote
+                );
+            }
+            return Ok(());
+        }
+        drop(stderr);
+
+        let retry = crate::ops::Retry::from_config(&repo_config);
+        let jobs = self
+            .jobs
+            .map(|jobs| jobs.get())
+            .unwrap_or(remotes.len())
+            .min(remotes.len());
+        let total = remotes.len();
+        let mut failed = 0;
+        fetch_prune_parallel(&remotes, jobs, retry, |done, remote, result| {
+            let mut stderr = anstream::stderr().lock();
+            match result {
+                Ok(()) => {
+                    let _ = writeln!(
+                        stderr,
+                        "{} `{}` ({}/{})",
+                        stderr_palette.good("Fetched"),
+                        remote,
+                        done,
+                        total
+                    );
+                }
+                Err(err) => {
+                    failed += 1;
+                    log::error!("Could not fetch `{}`, {}", remote, err);
+                }
+            }
+        });
+
+        if 0 < failed {
+            return Err(proc_exit::Code::FAILURE
+                .with_message(format!("could not fetch {failed} of {total} remotes")));
+        }
+
+        Ok(())
+    }
+}
+
+/// The remotes `branches` are pushed to, for development branches, or pulled from, for protected
+/// branches
+fn branch_remotes(
+    repo: &git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+) -> Vec<String> {
+    branches
+        .iter()
+        .flat_map(|(_, b)| b.iter())
+        .map(|b| {
+            if let Some(remote) = b.remote() {
+                return remote.to_owned();
+            }
+            match b.kind() {
+                git_stack::graph::BranchKind::Mutable | git_stack::graph::BranchKind::Deleted => {
+                    repo.push_remote_for(b.base_name()).to_owned()
+                }
+                git_stack::graph::BranchKind::Mixed | git_stack::graph::BranchKind::Protected => {
+                    repo.pull_remote_for(b.base_name()).to_owned()
+                }
+            }
+        })
+        .collect()
+}
+
+/// Run `git fetch --prune` for each remote, `jobs` at a time
+///
+/// `report` is called as each fetch finishes, with how many have finished so far.
+fn fetch_prune_parallel(
+    remotes: &[String],
+    jobs: usize,
+    retry: crate::ops::Retry,
+    mut report: impl FnMut(usize, &str, eyre::Result<()>),
+) {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(remote) = remotes.get(i) else {
+                    break;
+                };
+                let result = crate::ops::git_fetch_prune(remote, retry);
+                if tx.send((remote.as_str(), result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        for (done, (remote, result)) in rx.into_iter().enumerate() {
+            report(done + 1, remote, result);
+        }
+    });
+}
@@ -0,0 +1,77 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Clean up old undo snapshots
+///
+/// Snapshots taken before rewriting branches are removed once they are older than `--expire`,
+/// keeping the most recent one so the last operation can still be undone.  Snapshots also expire
+/// as new ones are taken when `branch-stash.git-stack.max-age` or `branch-stash.max-age` is set.
+#[derive(clap::Args)]
+pub(crate) struct GcArgs {
+    /// Remove snapshots older than this, e.g. `30d` or `now` [default: `branch-stash.git-stack.max-age`, `branch-stash.max-age`, or 30d]
+    #[arg(long, value_name = "AGE", value_parser = parse_expire)]
+    expire: Option<std::time::Duration>,
+
+    /// Only report what would be removed
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+pub(crate) const DEFAULT_EXPIRE: std::time::Duration =
+    std::time::Duration::from_secs(30 * 24 * 60 * 60);
+
+impl GcArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(self.dry_run)?;
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo_config = git_stack::config::RepoConfig::from_all(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let expire = self
+            .expire
+            .or_else(|| repo_config.stash_max_age(crate::ops::STASH_STACK_NAME))
+            .unwrap_or(DEFAULT_EXPIRE);
+
+        let stash_repo = git_branch_stash::GitRepo::new(repo);
+        let mut snapshots = git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
+        let Some(latest) = snapshots.peek() else {
+            let _ = writeln!(
+                anstream::stderr(),
+                "{}: no snapshots to clean up",
+                stderr_palette.info("note"),
+            );
+            return Ok(());
+        };
+        let expired = crate::ops::expire_snapshots(&snapshots, &latest, expire, self.dry_run);
+
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {} {} older than {}",
+            stderr_palette.good(if self.dry_run {
+                "Would remove"
+            } else {
+                "Removed"
+            }),
+            expired,
+            if expired == 1 {
+                "snapshot"
+            } else {
+                "snapshots"
+            },
+            humantime::format_duration(expire)
+        );
+
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_expire(value: &str) -> Result<std::time::Duration, humantime::DurationError> {
+    if value == "now" {
+        Ok(std::time::Duration::ZERO)
+    } else {
+        humantime::parse_duration(value)
+    }
+}
@@ -0,0 +1,162 @@
+/// GitHub, through the `gh` CLI so we reuse its authentication
+pub(crate) struct Github {
+    token: Option<String>,
+}
+
+/// Where a local branch is pushed, for finding and opening its pull requests
+pub(crate) struct Head {
+    /// Owner of the repository the branch is pushed to, if the remote is on GitHub
+    pub(crate) owner: Option<String>,
+    /// Name of the branch in that repository
+    pub(crate) name: String,
+}
+
+pub(crate) struct PullRequest {
+    pub(crate) number: u64,
+    pub(crate) base: String,
+    pub(crate) url: String,
+}
+
+impl Github {
+    /// Talk to GitHub as `gh` is logged in, unless a `token` is configured
+    pub(crate) fn new(token: Option<&str>) -> Self {
+        Self {
+            token: token.map(|t| t.to_owned()),
+        }
+    }
+
+    /// Open pull requests from `head`
+    ///
+    /// `gh pr list --head` only matches the branch name, so pull requests from same-named branches
+    /// in other forks are filtered out by owner.
+    pub(crate) fn open_pull_requests(&self, head: &Head) -> eyre::Result<Vec<PullRequest>> {
+        let output = self.gh(&[
+            "pr",
+            "list",
+            "--state",
+            "open",
+            "--head",
+            &head.name,
+            "--json",
+            "headRepositoryOwner,number,baseRefName,url",
+            "--template",
+            "{{range .}}{{.headRepositoryOwner.login}} {{.number}} {{.baseRefName}} {{.url}}\n{{end}}",
+        ])?;
+        let mut pull_requests = Vec::new();
+        for line in output.lines().filter(|l| !l.is_empty()) {
+            let Some((owner, line)) = line.split_once(' ') else {
+                eyre::bail!("unexpected `gh` output: {line}");
+            };
+            if head
+                .owner
+                .as_deref()
+                .is_some_and(|o| !o.eq_ignore_ascii_case(owner))
+            {
+                log::trace!("Ignoring pull request from {owner}:{}", head.name);
+                continue;
+            }
+            pull_requests.push(PullRequest::parse(line)?);
+        }
+        Ok(pull_requests)
+    }
+
+    /// Open a pull request with the REST API
+    pub(crate) fn create_pull_request(
+        &self,
+        head: &Head,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> eyre::Result<PullRequest> {
+        let head = match head.owner.as_deref() {
+            Some(owner) => format!("{owner}:{}", head.name),
+            None => head.name.clone(),
+        };
+        let output = self.gh(&[
+            "api",
+            "--method",
+            "POST",
+            "repos/{owner}/{repo}/pulls",
+            "-f",
+            &format!("head={head}"),
+            "-f",
+            &format!("base={base}"),
+            "-f",
+            &format!("title={title}"),
+            "-f",
+            &format!("body={body}"),
+            "--template",
+            "{{.number}} {{.base.ref}} {{.html_url}}\n",
+        ])?;
+        PullRequest::parse(output.trim_end())
+    }
+
+    pub(crate) fn set_base(&self, number: u64, base: &str) -> eyre::Result<()> {
+        self.gh(&["pr", "edit", &number.to_string(), "--base", base])?;
+        Ok(())
+    }
+
+    fn gh(&self, args: &[&str]) -> eyre::Result<String> {
+        log::trace!("gh {}", args.join(" "));
+        let mut cmd = std::process::Command::new("gh");
+        cmd.args(args).stderr(std::process::Stdio::inherit());
+        if let Some(token) = self.token.as_deref() {
+            cmd.env("GH_TOKEN", token);
+        }
+        let output = cmd
+            .output()
+            .map_err(|err| eyre::format_err!("could not run `gh`: {err}"))?;
+        if !output.status.success() {
+            eyre::bail!("`gh {}` failed", args.join(" "));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+impl Head {
+    /// Find where `branch` is pushed on `remote`, `None` if pushing it is not configured
+    pub(crate) fn new(repo: &git2::Repository, remote: &str, branch: &str) -> Option<Self> {
+        let name = git_stack::git::push_target(repo, remote, branch)?;
+        let owner = repo.find_remote(remote).ok().and_then(|r| {
+            let url = r.pushurl().or_else(|| r.url())?;
+            github_owner(url).map(|o| o.to_owned())
+        });
+        Some(Self { owner, name })
+    }
+}
+
+/// The owner in a GitHub remote URL, like `git@github.com:<owner>/<repo>.git`
+fn github_owner(url: &str) -> Option<&str> {
+    let (_, path) = url.split_once("github.com")?;
+    let path = path.strip_prefix([':', '/'])?;
+    let (owner, repo) = path.split_once('/')?;
+    (!owner.is_empty() && !repo.is_empty()).then_some(owner)
+}
+
+impl PullRequest {
+    fn parse(line: &str) -> eyre::Result<Self> {
+        let mut fields = line.splitn(3, ' ');
+        let (Some(number), Some(base)) = (fields.next(), fields.next()) else {
+            eyre::bail!("unexpected `gh` output: {line}");
+        };
+        Ok(Self {
+            number: number.parse()?,
+            base: base.to_owned(),
+            url: fields.next().unwrap_or_default().to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn owner_from_url() {
+        assert_eq!(github_owner("git@github.com:me/repo.git"), Some("me"));
+        assert_eq!(github_owner("https://github.com/me/repo"), Some("me"));
+        assert_eq!(github_owner("ssh://git@github.com/me/repo.git"), Some("me"));
+        assert_eq!(github_owner("https://example.com/me/repo.git"), None);
+        assert_eq!(github_owner("/tmp/remote.git"), None);
+    }
+}
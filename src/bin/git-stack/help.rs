@@ -0,0 +1,345 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use proc_exit::prelude::*;
+
+/// Print help for a command or topic
+#[derive(clap::Args)]
+pub(crate) struct HelpArgs {
+    /// Command to print help for or `workflows` for common tasks
+    #[arg(value_name = "COMMAND|TOPIC")]
+    topic: Vec<String>,
+}
+
+impl HelpArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        if self.topic.iter().map(|s| s.as_str()).eq([WORKFLOWS_TOPIC]) {
+            let _ = write!(anstream::stdout(), "{}", render_workflows());
+            return Ok(());
+        }
+
+        let mut cmd = <crate::args::Args as clap::CommandFactory>::command();
+        cmd.build();
+        let mut current = &mut cmd;
+        for name in &self.topic {
+            current = current
+                .find_subcommand_mut(name)
+                .ok_or_else(|| {
+                    eyre::format_err!(
+                        "unrecognized command or topic `{name}`, see `git stack help`"
+                    )
+                })
+                .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        }
+        current
+            .print_long_help()
+            .with_code(proc_exit::Code::FAILURE)?;
+        Ok(())
+    }
+}
+
+pub(crate) const WORKFLOWS_TOPIC: &str = "workflows";
+
+/// A task-oriented grouping of examples
+pub(crate) struct Workflow {
+    pub(crate) name: &'static str,
+    pub(crate) about: &'static str,
+    pub(crate) examples: &'static [Example],
+}
+
+/// An invocation of `git stack`, verified against the command model in tests
+pub(crate) struct Example {
+    pub(crate) about: &'static str,
+    pub(crate) args: &'static [&'static str],
+}
+
+impl Example {
+    fn subcommand(&self) -> Option<&'static str> {
+        self.args.first().copied().filter(|a| !a.starts_with('-'))
+    }
+
+    fn render(&self) -> String {
+        let mut line = "git stack".to_owned();
+        for arg in self.args {
+            line.push(' ');
+            line.push_str(&shlex::try_quote(arg).expect("examples have no nul bytes"));
+        }
+        line
+    }
+}
+
+pub(crate) const WORKFLOWS: &[Workflow] = &[
+    Workflow {
+        name: "Reviewing your stacks",
+        about: "See how your development branches relate to each other and what is ready",
+        examples: &[
+            Example {
+                about: "Show all development branches on top of their protected bases",
+                args: &[],
+            },
+            Example {
+                about: "Only show the stack you are working on",
+                args: &["--stack", "current"],
+            },
+            Example {
+                about: "List the branches in each stack",
+                args: &["--format", "list"],
+            },
+            Example {
+                about: "Group the current branch into the `ui` stack",
+                args: &["regroup", "ui"],
+            },
+            Example {
+                about: "Only show the branches grouped into the `ui` stack",
+                args: &["--stack", "ui"],
+            },
+            Example {
+                about: "Rename the `ui` stack",
+                args: &["rename-stack", "ui", "frontend"],
+            },
+            Example {
+                about: "Work on the `ui` stack by default, rebasing it onto `release`",
+                args: &["select", "--stack", "ui", "--onto", "release"],
+            },
+            Example {
+                about: "Summarize the current stack for a shell prompt",
+                args: &["status", "--porcelain"],
+            },
+            Example {
+                about: "Explain why a branch is protected or not ready to push",
+                args: &["why", "feature"],
+            },
+            Example {
+                about: "See which lines of a file your stack changes",
+                args: &["blame", "src/lib.rs"],
+            },
+            Example {
+                about: "Show every setting and where it was set, to attach to a bug report",
+                args: &["config", "--effective", "--json"],
+            },
+        ],
+    },
+    Workflow {
+        name: "Staying up-to-date",
+        about: "Catch conflicts early by keeping your branches on top of upstream",
+        examples: &[
+            Example {
+                about: "Pull the protected branches and rebase your development branches on them",
+                args: &["sync"],
+            },
+            Example {
+                about: "Preview what a sync would do",
+                args: &["sync", "--dry-run"],
+            },
+            Example {
+                about: "Fetch every remote your branches are pushed to or pulled from, in parallel",
+                args: &["fetch", "--all-stacks"],
+            },
+            Example {
+                about: "Rebase, processing `fixup!` commits and repairing split stacks",
+                args: &["--rebase", "--repair"],
+            },
+            Example {
+                about:
+                    "Finish a sync that stopped on a conflict, once the resolution is `git add`ed",
+                args: &["--continue"],
+            },
+        ],
+    },
+    Workflow {
+        name: "Editing history",
+        about: "Change commits in the middle of a stack, rebasing everything on top of them",
+        examples: &[
+            Example {
+                about: "Move to the parent commit",
+                args: &["previous"],
+            },
+            Example {
+                about: "Move to the next branch",
+                args: &["next", "--branch"],
+            },
+            Example {
+                about: "Squash staged changes into the current commit",
+                args: &["amend"],
+            },
+            Example {
+                about: "Stop at an older commit to change it, then rebase the stack on the result",
+                args: &["edit", "HEAD~2"],
+            },
+            Example {
+                about: "Finish the edit, once the changes are staged or committed",
+                args: &["edit", "--continue"],
+            },
+            Example {
+                about: "Squash staged changes into the commits in the stack they belong to",
+                args: &["absorb", "--and-rebase"],
+            },
+            Example {
+                about: "Squash the current branch down to one commit",
+                args: &["squash", "--message", "Add the frobnicator"],
+            },
+            Example {
+                about: "Stack the current branch on `feature` instead",
+                args: &["move", "--onto", "feature"],
+            },
+            Example {
+                about: "Edit the current commit's message",
+                args: &["reword", "--message", "Fix the frobnicator"],
+            },
+            Example {
+                about: "Reorder, drop, or reword commits in the stack with your editor",
+                args: &["reword", "--interactive"],
+            },
+            Example {
+                about: "Keep commits from being reordered around one that CI refers to by id",
+                args: &["anchor", "HEAD~2"],
+            },
+            Example {
+                about: "Keep a commit starting with `WIP` or `fixup!` from being treated as one",
+                args: &["ignore-commit", "HEAD"],
+            },
+            Example {
+                about: "Rename a branch, keeping undo snapshots and its upstream in step",
+                args: &["rename", "feature", "frobnicator"],
+            },
+            Example {
+                about: "Check out a branch of the stack next to this one to compare them",
+                args: &["worktree", "feature"],
+            },
+            Example {
+                about: "Verify each commit in the stack still builds",
+                args: &["run", "cargo", "check"],
+            },
+            Example {
+                about: "Drop undo snapshots older than a month",
+                args: &["gc", "--expire=30d"],
+            },
+            Example {
+                about: "See what a weekly cleanup would remove",
+                args: &["tidy", "--dry-run"],
+            },
+            Example {
+                about: "Check for setup problems, like no protected branches or upstreams that are gone",
+                args: &["doctor"],
+            },
+            Example {
+                about: "See what undoing the last operation would change",
+                args: &["snapshot", "--diff=0"],
+            },
+            Example {
+                about: "Undo the last reword",
+                args: &["snapshot", "--apply=reword"],
+            },
+            Example {
+                about: "Undo the last operation",
+                args: &["undo"],
+            },
+        ],
+    },
+    Workflow {
+        name: "Sharing your work",
+        about: "Publish branches for review",
+        examples: &[
+            Example {
+                about: "Push all branches that are ready for review",
+                args: &["--push"],
+            },
+            Example {
+                about: "Rebase onto the latest upstream before pushing",
+                args: &["--pull", "--push"],
+            },
+            Example {
+                about: "Open a pull request for each pushed branch, targeting its parent",
+                args: &["submit"],
+            },
+            Example {
+                about: "Point each pull request at its parent branch after the bottom one landed",
+                args: &["retarget"],
+            },
+            Example {
+                about: "Save the current stack to attach to an issue or move to another machine",
+                args: &["export-bundle", "stack.bundle"],
+            },
+            Example {
+                about: "Restore a stack saved with `export-bundle`",
+                args: &["import-bundle", "stack.bundle"],
+            },
+        ],
+    },
+];
+
+/// Render `git stack help workflows`
+pub(crate) fn render_workflows() -> String {
+    let mut out = String::new();
+    for (i, workflow) in WORKFLOWS.iter().enumerate() {
+        if i != 0 {
+            writeln!(&mut out).unwrap();
+        }
+        writeln!(&mut out, "{}", workflow.name).unwrap();
+        writeln!(&mut out, "  {}", workflow.about).unwrap();
+        for example in workflow.examples {
+            writeln!(&mut out).unwrap();
+            writeln!(&mut out, "  # {}", example.about).unwrap();
+            writeln!(&mut out, "  $ {}", example.render()).unwrap();
+        }
+    }
+    out
+}
+
+/// Examples for a command, for use with `after_long_help`
+pub(crate) fn after_help(subcommand: Option<&str>) -> String {
+    let mut out = String::new();
+    let examples = WORKFLOWS
+        .iter()
+        .flat_map(|w| w.examples.iter())
+        .filter(|e| e.subcommand() == subcommand);
+    for example in examples {
+        if out.is_empty() {
+            writeln!(&mut out, "Examples:").unwrap();
+        }
+        writeln!(&mut out, "  # {}", example.about).unwrap();
+        writeln!(&mut out, "  $ {}", example.render()).unwrap();
+    }
+    if subcommand.is_none() {
+        if !out.is_empty() {
+            writeln!(&mut out).unwrap();
+        }
+        write!(
+            &mut out,
+            "See `git stack help {WORKFLOWS_TOPIC}` for examples of common tasks"
+        )
+        .unwrap();
+    }
+    out.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn examples_parse() {
+        use clap::Parser;
+        for example in WORKFLOWS.iter().flat_map(|w| w.examples.iter()) {
+            let args = std::iter::once("git-stack").chain(example.args.iter().copied());
+            if let Err(err) = crate::args::Args::try_parse_from(args) {
+                panic!("`{}` is invalid: {}", example.render(), err);
+            }
+        }
+    }
+
+    #[test]
+    fn examples_use_full_names() {
+        use clap::CommandFactory;
+        let cmd = crate::args::Args::command();
+        for example in WORKFLOWS.iter().flat_map(|w| w.examples.iter()) {
+            if let Some(name) = example.subcommand() {
+                assert!(
+                    cmd.get_subcommands().any(|s| s.get_name() == name),
+                    "`{}` should use the command's name, not an alias",
+                    example.render()
+                );
+            }
+        }
+    }
+}
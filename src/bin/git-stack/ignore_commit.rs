@@ -0,0 +1,90 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Exempt commits from the WIP and fixup heuristics, or list the exempt commits
+///
+/// For commits whose summary legitimately starts with `WIP` or `fixup!`.  They aren't kept from
+/// being pushed and aren't squashed into other commits.  Exempt commits are stored as git notes
+/// under `refs/notes/stack-ignored`.
+#[derive(clap::Args)]
+pub(crate) struct IgnoreCommitArgs {
+    /// Commits to exempt (default: list the exempt commits)
+    #[arg(value_name = "COMMIT")]
+    revs: Vec<String>,
+
+    /// Apply the heuristics to the commits again
+    #[arg(long, requires = "revs")]
+    remove: bool,
+}
+
+impl IgnoreCommitArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        if self.revs.is_empty() {
+            let ignored = git_stack::git::IgnoredCommits::from_repo(&repo)
+                .with_code(proc_exit::Code::FAILURE)?;
+            let mut stdout = anstream::stdout().lock();
+            for id in ignored.iter() {
+                let Ok(commit) = repo.find_commit(id) else {
+                    log::debug!("Ignored commit {id} is not in the repo");
+                    continue;
+                };
+                let _ = writeln!(
+                    stdout,
+                    "{} {}",
+                    &id.to_string()[..7],
+                    commit.summary().unwrap_or_default()
+                );
+            }
+            return Ok(());
+        }
+        crate::lock::require_writable(false)?;
+
+        let ids = self
+            .revs
+            .iter()
+            .map(|rev| {
+                repo.revparse_single(rev)
+                    .and_then(|o| o.peel_to_commit())
+                    .map(|c| c.id())
+                    .map_err(|_| {
+                        proc_exit::sysexits::USAGE_ERR
+                            .with_message(format!("could not find commit `{rev}`"))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rev, id) in self.revs.iter().zip(ids) {
+            let abbrev_id = &id.to_string()[..7];
+            if self.remove {
+                if git_stack::git::unignore_commit(&repo, id).with_code(proc_exit::Code::FAILURE)? {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} {}",
+                        stderr_palette.good("Unignored"),
+                        stderr_palette.highlight(abbrev_id),
+                    );
+                } else {
+                    log::warn!("`{rev}` is not ignored");
+                }
+            } else if git_stack::git::ignore_commit(&repo, id)
+                .with_code(proc_exit::Code::FAILURE)?
+            {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{} {}",
+                    stderr_palette.good("Ignored"),
+                    stderr_palette.highlight(abbrev_id),
+                );
+            } else {
+                log::debug!("`{rev}` is already ignored");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+static WAIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static READ_ONLY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Whether [`lock_repo`] waits for another `git-stack` to finish, rather than failing
+pub(crate) fn set_wait(wait: bool) {
+    WAIT.store(wait, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Whether [`require_writable`] refuses, from `stack.read-only` or `--read-only`
+pub(crate) fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Refuse to change the repo, its config, or the forge when running read-only
+///
+/// Dry-runs are always allowed.  Commands that rewrite get this from [`lock_repo`]; everything
+/// else that changes something calls it before doing so.
+pub(crate) fn require_writable(dry_run: bool) -> Result<(), proc_exit::Exit> {
+    if dry_run || !READ_ONLY.load(std::sync::atomic::Ordering::Relaxed) {
+        return Ok(());
+    }
+    Err(proc_exit::Code::FAILURE.with_message("refusing to make changes, `stack.read-only` is set"))
+}
+
+/// Keep other `git-stack` processes from rewriting the repo until the lock is dropped
+///
+/// Nothing is locked for a dry-run.
+pub(crate) fn lock_repo(
+    repo: &git2::Repository,
+    dry_run: bool,
+) -> Result<Option<git_stack::git::RepoLock>, proc_exit::Exit> {
+    require_writable(dry_run)?;
+    if dry_run {
+        return Ok(None);
+    }
+
+    let wait = WAIT.load(std::sync::atomic::Ordering::Relaxed);
+    let mut waiting = false;
+    loop {
+        match git_stack::git::RepoLock::try_acquire(repo) {
+            Ok(lock) => return Ok(Some(lock)),
+            Err(git_stack::git::LockError::Held { holder, .. }) if wait => {
+                if !waiting {
+                    let stderr_palette = crate::ops::Palette::colored();
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} for git-stack (pid {}) to finish",
+                        stderr_palette.info("Waiting"),
+                        holder.pid
+                    );
+                    waiting = true;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(err @ git_stack::git::LockError::Held { .. }) => {
+                return Err(proc_exit::sysexits::TEMP_FAIL
+                    .with_message(format!("{err}, pass `--wait` to wait for it")));
+            }
+            Err(err) => {
+                return Err(err).with_code(proc_exit::sysexits::IO_ERR);
+            }
+        }
+    }
+}
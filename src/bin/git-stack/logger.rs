@@ -25,17 +25,17 @@ pub(crate) fn init_logging(
         } else {
             builder.format(move |f, record| match record.level() {
                 log::Level::Error => {
-                    writeln!(f, "{}: {}", palette.error(record.level()), record.args())
+                    writeln!(f, "{:#}: {}", palette.error(record.level()), record.args())
                 }
                 log::Level::Warn => {
-                    writeln!(f, "{}: {}", palette.warn(record.level()), record.args())
+                    writeln!(f, "{:#}: {}", palette.warn(record.level()), record.args())
                 }
                 log::Level::Info => writeln!(f, "{}", record.args()),
                 log::Level::Debug => {
-                    writeln!(f, "{}: {}", palette.debug(record.level()), record.args())
+                    writeln!(f, "{:#}: {}", palette.debug(record.level()), record.args())
                 }
                 log::Level::Trace => {
-                    writeln!(f, "{}: {}", palette.trace(record.level()), record.args())
+                    writeln!(f, "{:#}: {}", palette.trace(record.level()), record.args())
                 }
             });
         }
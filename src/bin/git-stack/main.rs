@@ -3,18 +3,47 @@
 use clap::Parser;
 use proc_exit::WithCodeResultExt;
 
+mod absorb;
 mod alias;
 mod amend;
+mod anchor;
 mod args;
+mod blame;
+mod bundle;
+mod completions;
 mod config;
+mod doctor;
+mod edit;
+mod fetch;
+mod gc;
+mod github;
+mod help;
+mod ignore_commit;
+mod lock;
 mod logger;
+mod maintenance;
+mod move_branch;
 mod next;
 mod ops;
 mod prev;
+mod regroup;
+mod rename;
+mod resume;
+mod retarget;
 mod reword;
 mod run;
+mod select;
+mod self_test;
+mod snapshot;
+mod squash;
 mod stack;
+mod status;
+mod submit;
 mod sync;
+mod tidy;
+mod undo;
+mod why;
+mod worktree;
 
 fn main() {
     human_panic::setup_panic!();
@@ -24,7 +53,8 @@ fn main() {
 
 fn run() -> proc_exit::ExitResult {
     // clap's `get_matches` uses Failure rather than Usage, so bypass it for `get_matches_safe`.
-    let args = match args::Args::try_parse() {
+    let args = alias::expand(std::env::args_os().collect())?;
+    let args = match args::Args::try_parse_from(args) {
         Ok(args) => args,
         Err(e) if e.use_stderr() => {
             let _ = e.print();
@@ -53,6 +83,15 @@ fn run() -> proc_exit::ExitResult {
         log::trace!("CWD={}", current_dir.display());
         std::env::set_current_dir(current_dir).with_code(proc_exit::sysexits::USAGE_ERR)?;
     }
+    if let Some(profile) = args.profile.as_deref() {
+        // Every command loads its config separately, so select it for all of them
+        std::env::set_var(git_stack::config::PROFILE_ENV, profile);
+    }
+    lock::set_wait(args.wait());
+    ops::init_from_config(args.checks_config());
+    if args.read_only {
+        lock::set_read_only(true);
+    }
 
     args.exec()
 }
@@ -0,0 +1,419 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Keep stacks fresh in the background
+///
+/// Registering a repository schedules `git stack maintenance run` with `cron` (or the Task
+/// Scheduler on Windows) to sync every registered repository daily.  Syncs run non-interactively
+/// and fail if any repository fails.  Without a subcommand, lists the registered repositories.
+#[derive(clap::Args)]
+pub(crate) struct MaintenanceArgs {
+    #[command(subcommand)]
+    command: Option<MaintenanceCommand>,
+}
+
+#[derive(clap::Subcommand)]
+enum MaintenanceCommand {
+    /// Add the current repository to the set of repositories to keep synced, scheduling the syncs
+    Register,
+    /// Remove the current repository, unscheduling the syncs once none are left
+    Unregister,
+    /// Sync all registered repositories
+    Run {
+        /// Only report what would be synced
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+}
+
+impl MaintenanceArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        match self.command {
+            Some(MaintenanceCommand::Register) => {
+                crate::lock::require_writable(false)?;
+                register()
+            }
+            Some(MaintenanceCommand::Unregister) => {
+                crate::lock::require_writable(false)?;
+                unregister()
+            }
+            Some(MaintenanceCommand::Run { dry_run }) => {
+                crate::lock::require_writable(dry_run)?;
+                run(dry_run)
+            }
+            None => status(),
+        }
+    }
+}
+
+const MAINTENANCE_REPO_FIELD: &str = "stack.maintenance-repo";
+const REPORT_NAME: &str = "git-stack-maintenance.log";
+
+fn register() -> proc_exit::ExitResult {
+    let workdir = current_workdir()?;
+    let mut user_config = open_user_config()?;
+
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+
+    let registered = registered_repos(&user_config)?;
+    if registered.contains(&workdir) {
+        let _ = writeln!(
+            stderr,
+            "{}: {} is already registered",
+            stderr_palette.info("note"),
+            workdir.display()
+        );
+    } else {
+        let _ = writeln!(
+            stderr,
+            "{}: {}",
+            stderr_palette.good("Registering"),
+            workdir.display()
+        );
+        let value = workdir
+            .to_str()
+            .ok_or_else(|| eyre::format_err!("non-UTF8 path `{}`", workdir.display()))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        user_config
+            .set_multivar(MAINTENANCE_REPO_FIELD, "^$", value)
+            .with_code(proc_exit::Code::FAILURE)?;
+    }
+
+    let exe = std::env::current_exe().with_code(proc_exit::Code::FAILURE)?;
+    if scheduler::schedule(&exe).with_code(proc_exit::Code::FAILURE)? {
+        let _ = writeln!(
+            stderr,
+            "{}: daily {} with {}",
+            stderr_palette.good("Scheduled"),
+            stderr_palette.highlight("`git stack maintenance run`"),
+            scheduler::NAME
+        );
+    }
+
+    Ok(())
+}
+
+fn unregister() -> proc_exit::ExitResult {
+    let workdir = current_workdir()?;
+    let mut user_config = open_user_config()?;
+
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+
+    let registered = registered_repos(&user_config)?;
+    if registered.contains(&workdir) {
+        let _ = writeln!(
+            stderr,
+            "{}: {}",
+            stderr_palette.good("Unregistering"),
+            workdir.display()
+        );
+        let remaining: Vec<_> = registered.into_iter().filter(|r| *r != workdir).collect();
+        user_config
+            .remove_multivar(MAINTENANCE_REPO_FIELD, ".*")
+            .with_code(proc_exit::Code::FAILURE)?;
+        for repo in &remaining {
+            let value = repo.to_str().expect("only UTF8 paths are registered");
+            user_config
+                .set_multivar(MAINTENANCE_REPO_FIELD, "^$", value)
+                .with_code(proc_exit::Code::FAILURE)?;
+        }
+        if remaining.is_empty() && scheduler::unschedule().with_code(proc_exit::Code::FAILURE)? {
+            let _ = writeln!(
+                stderr,
+                "{}: {} from {}",
+                stderr_palette.good("Unscheduled"),
+                stderr_palette.highlight("`git stack maintenance run`"),
+                scheduler::NAME
+            );
+        }
+    } else {
+        let _ = writeln!(
+            stderr,
+            "{}: {} is not registered",
+            stderr_palette.info("note"),
+            workdir.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn status() -> proc_exit::ExitResult {
+    let config = git2::Config::open_default().with_code(proc_exit::Code::FAILURE)?;
+    let registered = registered_repos(&config)?;
+
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stdout = anstream::stdout().lock();
+    let mut stderr = anstream::stderr().lock();
+
+    for repo in &registered {
+        let _ = writeln!(stdout, "{}", repo.display());
+    }
+    if registered.is_empty() {
+        let _ = writeln!(
+            stderr,
+            "{}: To register, run {}",
+            stderr_palette.info("note"),
+            stderr_palette.good("`git stack maintenance register`")
+        );
+    } else {
+        let _ = writeln!(
+            stderr,
+            "{}: To sync now, run {}",
+            stderr_palette.info("note"),
+            stderr_palette.good("`git stack maintenance run`")
+        );
+    }
+
+    Ok(())
+}
+
+fn run(dry_run: bool) -> proc_exit::ExitResult {
+    let config = git2::Config::open_default().with_code(proc_exit::Code::FAILURE)?;
+    let registered = registered_repos(&config)?;
+    let exe = std::env::current_exe().with_code(proc_exit::Code::FAILURE)?;
+
+    let mut failed = Vec::new();
+    for workdir in &registered {
+        match sync_repo(&exe, workdir, dry_run) {
+            Ok(true) => {
+                log::info!("Synced {}", workdir.display());
+            }
+            Ok(false) => {
+                log::error!("Failed to sync {}", workdir.display());
+                failed.push(workdir);
+            }
+            Err(err) => {
+                log::error!("Failed to sync {}: {}", workdir.display(), err);
+                failed.push(workdir);
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(proc_exit::Code::FAILURE.as_exit())
+    }
+}
+
+/// Run `git stack sync` without any chance of prompting the user, recording the output
+fn sync_repo(
+    exe: &std::path::Path,
+    workdir: &std::path::Path,
+    dry_run: bool,
+) -> eyre::Result<bool> {
+    let repo = git2::Repository::open(workdir)?;
+    if repo.state() != git2::RepositoryState::Clean {
+        eyre::bail!("{:?} in progress", repo.state());
+    }
+
+    let mut cmd = std::process::Command::new(exe);
+    cmd.arg("sync")
+        .arg("--color=never")
+        .current_dir(workdir)
+        .stdin(std::process::Stdio::null())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_EDITOR", "false")
+        .env("GIT_SEQUENCE_EDITOR", "false")
+        .env("GIT_ASKPASS", "false")
+        .env("SSH_ASKPASS", "false");
+    if dry_run {
+        cmd.arg("--dry-run");
+    }
+    log::trace!("{:?}", cmd);
+    let output = cmd.output()?;
+
+    let report_path = repo.path().join(REPORT_NAME);
+    let mut report = std::fs::File::create(&report_path)?;
+    writeln!(
+        report,
+        "# {} {}",
+        humantime::format_rfc3339_seconds(std::time::SystemTime::now()),
+        if dry_run { "dry-run" } else { "sync" }
+    )?;
+    writeln!(report, "# status: {}", output.status)?;
+    report.write_all(&output.stdout)?;
+    report.write_all(&output.stderr)?;
+    log::debug!("Wrote report to {}", report_path.display());
+
+    Ok(output.status.success())
+}
+
+fn registered_repos(config: &git2::Config) -> Result<Vec<std::path::PathBuf>, proc_exit::Exit> {
+    let mut repos = Vec::new();
+    let mut entries = config
+        .multivar(MAINTENANCE_REPO_FIELD, None)
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    while let Some(entry) = entries.next() {
+        let entry = entry.with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let Some(value) = entry.value() else { continue };
+        repos.push(std::path::PathBuf::from(value));
+    }
+    Ok(repos)
+}
+
+fn current_workdir() -> Result<std::path::PathBuf, proc_exit::Exit> {
+    let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| eyre::format_err!("bare repositories are not supported"))
+        .with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let workdir = workdir
+        .canonicalize()
+        .with_code(proc_exit::sysexits::USAGE_ERR)?;
+    Ok(workdir)
+}
+
+fn open_user_config() -> Result<git2::Config, proc_exit::Exit> {
+    git2::Config::open_default()
+        .with_code(proc_exit::Code::FAILURE)?
+        .open_global()
+        .with_code(proc_exit::Code::FAILURE)
+}
+
+/// Runs `git stack maintenance run` daily through the platform's job scheduler
+#[cfg(not(windows))]
+mod scheduler {
+    use std::io::Write as _;
+
+    pub(super) const NAME: &str = "`crontab`";
+
+    /// Marks the line this manages, so the rest of the crontab is left alone
+    const MARKER: &str = "# git-stack maintenance";
+
+    /// Add the crontab entry, returning whether it was missing
+    pub(super) fn schedule(exe: &std::path::Path) -> eyre::Result<bool> {
+        let crontab = read()?;
+        if crontab.lines().any(|line| line.ends_with(MARKER)) {
+            return Ok(false);
+        }
+        let exe = shlex::try_quote(
+            exe.to_str()
+                .ok_or_else(|| eyre::eyre!("non-UTF8 path `{}`", exe.display()))?,
+        )?
+        .into_owned();
+        let mut crontab = crontab;
+        if !crontab.is_empty() && !crontab.ends_with('\n') {
+            crontab.push('\n');
+        }
+        crontab.push_str(&format!("0 4 * * * {exe} maintenance run {MARKER}\n"));
+        write(&crontab)?;
+        Ok(true)
+    }
+
+    /// Remove the crontab entry, returning whether there was one
+    pub(super) fn unschedule() -> eyre::Result<bool> {
+        let crontab = read()?;
+        let kept: Vec<_> = crontab
+            .lines()
+            .filter(|line| !line.ends_with(MARKER))
+            .collect();
+        if kept.len() == crontab.lines().count() {
+            return Ok(false);
+        }
+        let mut crontab = kept.join("\n");
+        if !crontab.is_empty() {
+            crontab.push('\n');
+        }
+        write(&crontab)?;
+        Ok(true)
+    }
+
+    fn read() -> eyre::Result<String> {
+        let output = std::process::Command::new("crontab")
+            .arg("-l")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .map_err(|err| eyre::eyre!("could not run `crontab`: {}", err))?;
+        if output.status.success() {
+            return Ok(String::from_utf8(output.stdout)?);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("no crontab for") {
+            // No crontab yet
+            log::debug!("crontab -l: {}", stderr.trim());
+            Ok(String::new())
+        } else {
+            // Writing on top of a crontab we couldn't read would clobber it
+            eyre::bail!(
+                "`crontab -l` failed with {}: {}",
+                output.status,
+                stderr.trim()
+            );
+        }
+    }
+
+    fn write(crontab: &str) -> eyre::Result<()> {
+        let mut child = std::process::Command::new("crontab")
+            .arg("-")
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|err| eyre::eyre!("could not run `crontab`: {}", err))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(crontab.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            eyre::bail!("`crontab -` failed with {}", status);
+        }
+        Ok(())
+    }
+}
+
+/// Runs `git stack maintenance run` daily through the platform's job scheduler
+#[cfg(windows)]
+mod scheduler {
+    pub(super) const NAME: &str = "the Task Scheduler";
+
+    const TASK: &str = "git-stack maintenance";
+
+    /// Add the scheduled task, returning whether it was missing
+    pub(super) fn schedule(exe: &std::path::Path) -> eyre::Result<bool> {
+        if exists()? {
+            return Ok(false);
+        }
+        let run = format!("\"{}\" maintenance run", exe.display());
+        schtasks(&[
+            "/Create", "/TN", TASK, "/SC", "DAILY", "/ST", "04:00", "/TR", &run,
+        ])?;
+        Ok(true)
+    }
+
+    /// Remove the scheduled task, returning whether there was one
+    pub(super) fn unschedule() -> eyre::Result<bool> {
+        if !exists()? {
+            return Ok(false);
+        }
+        schtasks(&["/Delete", "/F", "/TN", TASK])?;
+        Ok(true)
+    }
+
+    fn exists() -> eyre::Result<bool> {
+        let status = std::process::Command::new("schtasks")
+            .args(["/Query", "/TN", TASK])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map_err(|err| eyre::eyre!("could not run `schtasks`: {}", err))?;
+        Ok(status.success())
+    }
+
+    fn schtasks(args: &[&str]) -> eyre::Result<()> {
+        let status = std::process::Command::new("schtasks")
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .map_err(|err| eyre::eyre!("could not run `schtasks`: {}", err))?;
+        if !status.success() {
+            eyre::bail!("`schtasks {}` failed with {}", args[0], status);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Move a branch onto a different parent branch
+///
+/// The commits unique to the branch, back to its parent branch or the protected base, are rebased
+/// onto `--onto`, bringing along the branches stacked on it.  The rest of the repo is left alone.
+#[derive(clap::Args)]
+pub(crate) struct MoveArgs {
+    /// Branch to move onto
+    #[arg(long, value_name = "BRANCH")]
+    onto: String,
+
+    /// Branch to move, defaulting to the current branch
+    #[arg(long, value_name = "NAME")]
+    branch: Option<String>,
+
+    /// Don't actually move
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
+}
+
+impl MoveArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let head_branch = repo.head_branch();
+        let selected_branch = match self.branch.as_deref() {
+            Some(name) => repo.find_local_branch(name).ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR.with_message(format!("no branch `{name}`"))
+            })?,
+            None => head_branch.clone().ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR
+                    .with_message("HEAD is detached, pass the branch to move with `--branch`")
+            })?,
+        };
+        let onto_branch = repo
+            .find_local_branch(&self.onto)
+            .or_else(|| repo.find_remote_branch(repo.pull_remote(), &self.onto))
+            .ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR.with_message(format!("no branch `{}`", self.onto))
+            })?;
+        let selected_id = selected_branch.id;
+        let onto_id = onto_branch.id;
+        let merge_base_oid = repo
+            .merge_base(onto_id, selected_id)
+            .ok_or_else(|| {
+                git2::Error::new(
+                    git2::ErrorCode::NotFound,
+                    git2::ErrorClass::Reference,
+                    format!("could not find base between {onto_branch} and {selected_branch}"),
+                )
+            })
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_branches = branches.descendants(&repo, merge_base_oid);
+        let mut graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
+            .with_code(proc_exit::Code::FAILURE)?;
+        if !graph.contains_id(onto_id) {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "cannot move onto `{onto_branch}`, it isn't a local or protected branch"
+            )));
+        }
+        git_stack::graph::protect_branches(&mut graph);
+
+        if repo.raw().state() != git2::RepositoryState::Clean {
+            let message = format!("cannot walk commits, {:?} in progress", repo.raw().state());
+            if self.dry_run {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: {}",
+                    stderr_palette.error("error"),
+                    message
+                );
+            } else {
+                return Err(proc_exit::sysexits::USAGE_ERR.with_message(message));
+            }
+        }
+        let action = graph
+            .commit_get::<git_stack::graph::Action>(selected_id)
+            .copied()
+            .unwrap_or_default();
+        if action.is_protected() {
+            return Err(proc_exit::Code::FAILURE
+                .with_message(format!("cannot move protected branch `{selected_branch}`")));
+        }
+
+        let first_id = git_stack::graph::move_branch(&mut graph, selected_id, onto_id)
+            .with_code(proc_exit::Code::FAILURE)?;
+        let scripts = git_stack::graph::to_scripts(&graph, vec![]);
+        let parent_id = repo
+            .raw()
+            .find_commit(first_id)
+            .and_then(|c| c.parent_id(0))
+            .with_code(proc_exit::Code::FAILURE)?;
+        if parent_id == onto_id {
+            return Err(proc_exit::Code::SUCCESS
+                .with_message(format!("`{selected_branch}` is already on `{onto_branch}`")));
+        }
+
+        let mut stash_id = None;
+        if !self.dry_run {
+            stash_id = git_stack::git::stash_push(&mut repo, "move");
+        }
+
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "move",
+            stash_id,
+            self.dry_run,
+        )?;
+
+        let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            &mut repo,
+            &mut executor,
+            scripts,
+            head_branch.as_ref().and_then(|b| b.local_name()),
+            stash_id,
+        )?;
+
+        if success {
+            let _ = writeln!(
+                anstream::stderr(),
+                "{} {} onto {}",
+                stderr_palette.good("Moved"),
+                stderr_palette.highlight(&selected_branch),
+                stderr_palette.highlight(&onto_branch)
+            );
+        }
+
+        git_stack::git::stash_pop(&mut repo, stash_id);
+        if backed_up {
+            crate::ops::note_undo();
+        }
+
+        if success {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.as_exit())
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use bstr::ByteSlice;
 use eyre::WrapErr;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -48,35 +47,176 @@ pub(crate) fn resolve_explicit_base(
     repo: &git_stack::git::GitRepo,
     base: &str,
 ) -> eyre::Result<AnnotatedOid> {
-    let (obj, r) = repo.raw().revparse_ext(base)?;
-    if let Some(r) = r {
-        if r.is_tag() {
-            return Ok(AnnotatedOid::new(obj.id()));
-        }
-
-        let branch = if r.is_remote() {
-            let shorthand = r
-                .shorthand()
-                .ok_or_else(|| eyre::eyre!("Expected branch, got `{}`", base))?;
-            let (remote, name) = shorthand
-                .split_once('/')
-                .expect("removes should always have at least one `/`");
-            repo.find_remote_branch(remote, name)
-                .ok_or_else(|| eyre::eyre!("Could not find branch {:?}", r.shorthand()))
+    let rev = resolve_rev(repo.raw(), base)?;
+    let branch = match (rev.remote.as_deref(), rev.branch.as_deref()) {
+        (Some(remote), Some(name)) => repo.find_remote_branch(remote, name),
+        (None, Some(name)) => repo.find_local_branch(name),
+        _ => None,
+    };
+    match branch {
+        Some(branch) if branch.id == rev.id => Ok(AnnotatedOid::with_branch(branch)),
+        _ => Ok(AnnotatedOid::new(rev.id)),
+    }
+}
+
+/// Which rewritten commits to sign
+///
+/// `stack.sign` wins.  Otherwise, all of them are signed with `stack.gpgSign`, `commit.gpgSign`,
+/// or `stack.resign=resign-mine`.
+pub(crate) fn sign_policy(
+    repo: &git2::Repository,
+    repo_config: &git_stack::config::RepoConfig,
+) -> Result<git_stack::config::Sign, git2::Error> {
+    if let Some(sign) = repo_config.sign() {
+        return Ok(sign);
+    }
+    let config = repo.config()?;
+    let gpg_sign = config
+        .get_bool("stack.gpgSign")
+        .or_else(|_| config.get_bool("commit.gpgSign"))
+        .unwrap_or_default();
+    if gpg_sign || repo_config.resign() == git_stack::config::Resign::ResignMine {
+        Ok(git_stack::config::Sign::Always)
+    } else {
+        Ok(git_stack::config::Sign::Never)
+    }
+}
+
+/// Whether a commit's signature checks out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SignatureStatus {
+    Good,
+    /// Signed, but the key isn't known or trusted, so it can't be checked
+    UnknownKey,
+    Bad,
+    Unsigned,
+}
+
+/// Check the signatures of `ids`
+///
+/// Signatures are found with libgit2 but checked by `git`, so `gpg.program`,
+/// `gpg.ssh.allowedSignersFile`, and the like are respected.
+pub(crate) fn verify_signatures(
+    repo: &git2::Repository,
+    ids: impl IntoIterator<Item = git2::Oid>,
+) -> std::collections::HashMap<git2::Oid, SignatureStatus> {
+    let mut statuses = std::collections::HashMap::new();
+    let mut signed = Vec::new();
+    for id in ids {
+        if repo.extract_signature(&id, None).is_ok() {
+            statuses.insert(id, SignatureStatus::UnknownKey);
+            signed.push(id.to_string());
         } else {
-            let shorthand = r
-                .shorthand()
-                .ok_or_else(|| eyre::eyre!("Expected branch, got `{}`", base))?;
-            if shorthand == "HEAD" {
-                return Ok(AnnotatedOid::new(obj.id()));
+            statuses.insert(id, SignatureStatus::Unsigned);
+        }
+    }
+    if signed.is_empty() {
+        return statuses;
+    }
+
+    let output = std::process::Command::new("git")
+        .arg("--git-dir")
+        .arg(repo.path())
+        .args(["log", "--no-walk=unsorted", "--format=%H %G?"])
+        .args(&signed)
+        .output();
+    let stdout = match output {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            log::debug!(
+                "Could not verify signatures: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return statuses;
+        }
+        Err(err) => {
+            log::debug!("Could not verify signatures: {}", err);
+            return statuses;
+        }
+    };
+    for line in String::from_utf8_lossy(&stdout).lines() {
+        let Some((id, code)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(id) = git2::Oid::from_str(id) else {
+            continue;
+        };
+        // `U` is a good signature from a key that isn't trusted or, for SSH, allowed
+        let status = match code {
+            "G" => SignatureStatus::Good,
+            "B" | "R" => SignatureStatus::Bad,
+            _ => SignatureStatus::UnknownKey,
+        };
+        statuses.insert(id, status);
+    }
+    statuses
+}
+
+/// A commit named by a revspec, along with the branch the revspec refers to, if any
+pub(crate) struct ResolvedRev {
+    pub(crate) id: git2::Oid,
+    pub(crate) remote: Option<String>,
+    pub(crate) branch: Option<String>,
+}
+
+/// Resolve any revspec git understands, like `origin/HEAD`, `@{u}`, `v1.0`, or `:/fix`
+///
+/// The branch is only kept when the revspec names the branch's commit, so `main~2` and tags
+/// resolve to a detached commit.  `HEAD` is also left detached.
+pub(crate) fn resolve_rev(repo: &git2::Repository, rev: &str) -> eyre::Result<ResolvedRev> {
+    let (obj, r) = repo
+        .revparse_ext(rev)
+        .map_err(|err| eyre::eyre!("could not find `{}`: {}", rev, err.message()))?;
+    let id = obj
+        .peel_to_commit()
+        .map_err(|_| eyre::eyre!("`{}` is not a commit", rev))?
+        .id();
+    let mut resolved = ResolvedRev {
+        id,
+        remote: None,
+        branch: None,
+    };
+
+    let Some(r) = r.filter(|r| r.name() != Some("HEAD")) else {
+        return Ok(resolved);
+    };
+    // Remote `HEAD`s point to the default branch
+    let r = r.resolve()?;
+    if r.target() != Some(id) {
+        return Ok(resolved);
+    }
+    let Some(ref_name) = r.name() else {
+        return Ok(resolved);
+    };
+    if r.is_branch() {
+        resolved.branch = ref_name.strip_prefix("refs/heads/").map(ToOwned::to_owned);
+    } else if r.is_remote() {
+        let remote = repo
+            .branch_remote_name(ref_name)
+            .ok()
+            .and_then(|r| r.as_str().map(ToOwned::to_owned));
+        let shorthand = ref_name
+            .strip_prefix("refs/remotes/")
+            .expect("remote branches are under `refs/remotes`");
+        // Without a configured remote, assume its name has no `/`
+        let (remote, name) = match remote {
+            Some(remote) => {
+                let name = shorthand
+                    .strip_prefix(&format!("{remote}/"))
+                    .map(ToOwned::to_owned);
+                (Some(remote), name)
             }
-            repo.find_local_branch(shorthand)
-                .ok_or_else(|| eyre::eyre!("Could not find branch {:?}", shorthand))
-        }?;
-        Ok(AnnotatedOid::with_branch(branch))
-    } else {
-        Ok(AnnotatedOid::new(obj.id()))
+            None => match shorthand.split_once('/') {
+                Some((remote, name)) => (Some(remote.to_owned()), Some(name.to_owned())),
+                None => (None, None),
+            },
+        };
+        if name.is_some() {
+            resolved.remote = remote;
+            resolved.branch = name;
+        }
     }
+    Ok(resolved)
 }
 
 pub(crate) fn resolve_implicit_base(
@@ -106,11 +246,8 @@ pub(crate) fn resolve_implicit_base(
                             .get(head_oid)
                             .map(|b| b[0].name())
                             .or_else(|| {
-                                repo.find_commit(head_oid)?
-                                    .summary
-                                    .to_str()
-                                    .ok()
-                                    .map(ToOwned::to_owned)
+                                let commit = repo.find_commit(head_oid)?;
+                                Some(commit.display_summary().into_owned())
                             })
                             .unwrap_or_else(|| "target".to_owned()),
                         ahead_count,
@@ -129,11 +266,8 @@ pub(crate) fn resolve_implicit_base(
                     .get(head_oid)
                     .map(|b| b[0].name())
                     .or_else(|| {
-                        repo.find_commit(head_oid)?
-                            .summary
-                            .to_str()
-                            .ok()
-                            .map(ToOwned::to_owned)
+                        let commit = repo.find_commit(head_oid)?;
+                        Some(commit.display_summary().into_owned())
                     })
                     .unwrap_or_else(|| "target".to_owned())
             );
@@ -167,72 +301,478 @@ pub(crate) fn resolve_base_from_onto(
 pub(crate) fn git_prune_development(
     repo: &mut git_stack::git::GitRepo,
     branches: &[&str],
+    retry: Retry,
     dry_run: bool,
 ) -> eyre::Result<()> {
     if branches.is_empty() {
         return Ok(());
     }
 
-    let remote = repo.push_remote();
-    let output = std::process::Command::new("git")
-        .arg("ls-remote")
-        .arg("--heads")
-        .arg(remote)
-        .args(branches)
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .wrap_err("Could not run `git fetch`")?
-        .wait_with_output()?;
-    if !output.status.success() {
-        eyre::bail!("Could not run `git fetch`");
-    }
-    let stdout = String::from_utf8(output.stdout).wrap_err("Could not run `git fetch`")?;
-    #[allow(clippy::needless_collect)]
-    let remote_branches: Vec<_> = stdout
-        .lines()
-        .filter_map(|l| l.split_once('\t').map(|s| s.1))
-        .filter_map(|l| l.strip_prefix("refs/heads/"))
-        .collect();
-
     if !branches.is_empty() {
         log::trace!("Local branches:\n  {}", branches.join("\n  "));
     }
-    if !remote_branches.is_empty() {
-        log::trace!("Remote branches:\n  {}", remote_branches.join("\n  "));
-    }
-    for branch in branches {
-        if !remote_branches.contains(branch) {
-            let remote_branch = format!("{remote}/{branch}");
-            log::info!("Pruning {}", remote_branch);
-            if !dry_run {
-                let mut branch = repo
-                    .raw()
-                    .find_branch(&remote_branch, git2::BranchType::Remote)?;
-                branch.delete()?;
-            }
+    let gone = gone_from_push_remotes(branches, |b| repo.push_remote_for(b).to_owned(), retry)?;
+    for remote_branch in gone {
+        log::info!("Pruning {}", remote_branch);
+        if !dry_run {
+            let mut branch = repo
+                .raw()
+                .find_branch(&remote_branch, git2::BranchType::Remote)?;
+            branch.delete()?;
         }
     }
 
     Ok(())
 }
 
-pub(crate) fn git_fetch_upstream(remote: &str, branch_name: &str) -> eyre::Result<()> {
+/// The remote-tracking branches, as `<remote>/<branch>`, of `branches` that their push remote no
+/// longer has
+pub(crate) fn gone_from_push_remotes(
+    branches: &[&str],
+    push_remote_for: impl Fn(&str) -> String,
+    retry: Retry,
+) -> eyre::Result<Vec<String>> {
+    let mut by_remote = std::collections::BTreeMap::<String, Vec<&str>>::new();
+    for branch in branches {
+        by_remote
+            .entry(push_remote_for(branch))
+            .or_default()
+            .push(branch);
+    }
+
+    let mut gone = Vec::new();
+    for (remote, branches) in by_remote {
+        let mut cmd = std::process::Command::new("git");
+        cmd.arg("ls-remote")
+            .arg("--heads")
+            .arg(&remote)
+            .args(&branches);
+        let output = git_remote(&mut cmd, retry).wrap_err("Could not run `git fetch`")?;
+        if !output.status.success() {
+            eyre::bail!("Could not run `git fetch`");
+        }
+        let stdout = String::from_utf8(output.stdout).wrap_err("Could not run `git fetch`")?;
+        #[allow(clippy::needless_collect)]
+        let remote_branches: Vec<_> = stdout
+            .lines()
+            .filter_map(|l| l.split_once('\t').map(|s| s.1))
+            .filter_map(|l| l.strip_prefix("refs/heads/"))
+            .collect();
+        if !remote_branches.is_empty() {
+            log::trace!("Remote branches:\n  {}", remote_branches.join("\n  "));
+        }
+        gone.extend(
+            branches
+                .into_iter()
+                .filter(|branch| !remote_branches.contains(branch))
+                .map(|branch| format!("{remote}/{branch}")),
+        );
+    }
+    Ok(gone)
+}
+
+pub(crate) fn git_fetch_upstream(
+    remote: &str,
+    branch_name: &str,
+    retry: Retry,
+) -> eyre::Result<()> {
     log::debug!("git fetch {} {}", remote, branch_name);
     // A little uncertain about some of the weirder authentication needs, just deferring to `git`
     // instead of using `libgit2`
-    let status = std::process::Command::new("git")
-        .arg("fetch")
-        .arg(remote)
-        .arg(branch_name)
-        .status()
-        .wrap_err("Could not run `git fetch`")?;
-    if !status.success() {
+    let mut cmd = std::process::Command::new("git");
+    cmd.arg("fetch").arg(remote).arg(branch_name);
+    let output = git_remote(&mut cmd, retry).wrap_err("Could not run `git fetch`")?;
+    if !output.status.success() {
         eyre::bail!("`git fetch {} {}` failed", remote, branch_name,);
     }
 
     Ok(())
 }
 
+pub(crate) fn git_fetch_prune(remote: &str, retry: Retry) -> eyre::Result<()> {
+    log::debug!("git fetch --prune {}", remote);
+    let mut cmd = std::process::Command::new("git");
+    cmd.arg("fetch").arg("--prune").arg(remote);
+    let output = git_remote(&mut cmd, retry).wrap_err("Could not run `git fetch`")?;
+    if !output.status.success() {
+        eyre::bail!("`git fetch --prune {}` failed", remote);
+    }
+
+    Ok(())
+}
+
+/// How long ago `remote/branch_name` was last updated from the remote
+///
+/// This goes by the remote-tracking branch's reflog, falling back to when `FETCH_HEAD` was
+/// written.
+pub(crate) fn remote_branch_age(
+    repo: &git2::Repository,
+    remote: &str,
+    branch_name: &str,
+) -> Option<std::time::Duration> {
+    let now = std::time::SystemTime::now();
+    let refname = format!("refs/remotes/{remote}/{branch_name}");
+    let reflog_time = repo
+        .reflog(&refname)
+        .ok()
+        .and_then(|reflog| {
+            reflog
+                .get(0)
+                .map(|entry| entry.committer().when().seconds())
+        })
+        .and_then(|seconds| u64::try_from(seconds).ok())
+        .map(|seconds| std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+    let updated = reflog_time.or_else(|| {
+        std::fs::metadata(repo.path().join("FETCH_HEAD"))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    })?;
+    Some(now.duration_since(updated).unwrap_or_default())
+}
+
+/// How to retry remote operations that fail for transient reasons
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Retry {
+    pub(crate) retries: usize,
+    pub(crate) delay: std::time::Duration,
+}
+
+impl Retry {
+    pub(crate) fn from_config(config: &git_stack::config::RepoConfig) -> Self {
+        Self {
+            retries: config.network_retries(),
+            delay: config.network_retry_delay(),
+        }
+    }
+}
+
+/// Run a `git` command that talks to a remote, retrying on transient network failures
+///
+/// Each operation is retried on its own, so work that already reached the remote is not redone.
+/// `stdout` is captured for the caller while `stderr` is shown as it comes, and captured too.
+///
+/// A `--force-with-lease` push is only retried if it never reached the remote.  Otherwise the
+/// remote may have taken some of it, and the retry would be checked against a stale lease.
+pub(crate) fn git_remote(
+    cmd: &mut std::process::Command,
+    retry: Retry,
+) -> std::io::Result<std::process::Output> {
+    cmd.stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let leased = cmd.get_args().any(|a| {
+        a.to_str()
+            .is_some_and(|a| a.starts_with("--force-with-lease"))
+    });
+    let mut delay = retry.delay;
+    let mut attempt = 0;
+    loop {
+        log::trace!("{:?}", cmd);
+        let output = output_tee_stderr(cmd)?;
+        if output.status.success() || !is_transient(&output.stderr) || retry.retries <= attempt {
+            return Ok(output);
+        }
+        if leased && !is_unsent(&output.stderr) {
+            log::warn!(
+                "Not retrying, the remote may have taken part of the push; fetch and push again"
+            );
+            return Ok(output);
+        }
+        attempt += 1;
+        log::warn!(
+            "Transient failure talking to remote, retrying in {} ({}/{})",
+            humantime::format_duration(delay),
+            attempt,
+            retry.retries
+        );
+        std::thread::sleep(delay);
+        delay = delay.saturating_mul(2);
+    }
+}
+
+/// Like [`std::process::Command::output`], but forwarding `stderr` while it is captured
+///
+/// This keeps progress and prompts from a slow remote visible.
+fn output_tee_stderr(cmd: &mut std::process::Command) -> std::io::Result<std::process::Output> {
+    use std::io::Read as _;
+    use std::io::Write as _;
+
+    let mut child = cmd.spawn()?;
+    let mut child_stderr = child.stderr.take().expect("stderr is piped");
+    let tee = std::thread::spawn(move || {
+        let mut captured = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            match child_stderr.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => {
+                    let _ = anstream::stderr().write_all(&buffer[..len]);
+                    captured.extend_from_slice(&buffer[..len]);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        captured
+    });
+    let mut stdout = Vec::new();
+    let read = child
+        .stdout
+        .take()
+        .expect("stdout is piped")
+        .read_to_end(&mut stdout);
+    let status = child.wait()?;
+    read?;
+    let stderr = tee.join().unwrap_or_default();
+    Ok(std::process::Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// What `git push` did with a ref
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PushStatus {
+    Pushed,
+    UpToDate,
+    /// With the reason, like `stale info` or `atomic push failed`
+    Rejected(String),
+}
+
+/// What `git push --porcelain` reported for each remote ref, like `refs/heads/feature`
+pub(crate) fn parse_push_porcelain(stdout: &str) -> Vec<(String, PushStatus)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let flag = fields.next()?;
+            let (_, dst) = fields.next()?.rsplit_once(':')?;
+            let summary = fields.next().unwrap_or_default();
+            let status = match flag {
+                "=" => PushStatus::UpToDate,
+                "!" => {
+                    let reason = summary
+                        .rsplit_once(" (")
+                        .and_then(|(_, reason)| reason.strip_suffix(')'))
+                        .unwrap_or(summary);
+                    PushStatus::Rejected(reason.to_owned())
+                }
+                " " | "+" | "-" | "*" => PushStatus::Pushed,
+                _ => return None,
+            };
+            Some((dst.to_owned(), status))
+        })
+        .collect()
+}
+
+/// Whether `git push --atomic` failed because the remote can't do atomic pushes
+pub(crate) fn refuses_atomic(stderr: &[u8]) -> bool {
+    String::from_utf8_lossy(stderr).contains("does not support --atomic push")
+}
+
+fn is_transient(stderr: &[u8]) -> bool {
+    const TRANSIENT: &[&str] = &[
+        "connection reset",
+        "connection refused",
+        "connection timed out",
+        "operation timed out",
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "the remote end hung up unexpectedly",
+        "unexpected disconnect",
+        "early eof",
+        "rpc failed",
+        "returned error: 429",
+        "returned error: 5",
+        "http 429",
+        "http 5",
+    ];
+    // Rejected refs can end with the remote hanging up, like with `--atomic`
+    const PERMANENT: &[&str] = &["atomic push failed", "[rejected]", "[remote rejected]"];
+    let stderr = String::from_utf8_lossy(stderr).to_lowercase();
+    TRANSIENT.iter().any(|t| stderr.contains(t)) && !PERMANENT.iter().any(|p| stderr.contains(p))
+}
+
+/// Whether a transient failure happened before anything reached the remote
+fn is_unsent(stderr: &[u8]) -> bool {
+    const UNSENT: &[&str] = &[
+        "connection refused",
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "failed to connect",
+    ];
+    let stderr = String::from_utf8_lossy(stderr).to_lowercase();
+    UNSENT.iter().any(|t| stderr.contains(t))
+}
+
+/// The width of the terminal `stdout` is shown on, if it is one
+pub(crate) fn terminal_width() -> Option<usize> {
+    use std::io::IsTerminal as _;
+
+    let stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    if let Ok(size) = rustix::termios::tcgetwinsize(&stdout) {
+        if size.ws_col != 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|columns| *columns != 0)
+}
+
+/// Cut `line` down to `max_width` columns, ending it with `ellipsis`
+///
+/// ANSI escape codes don't count towards the width and the style is reset after a cut.
+pub(crate) fn truncate_line<'l>(
+    line: &'l str,
+    max_width: usize,
+    ellipsis: &str,
+) -> std::borrow::Cow<'l, str> {
+    fn visible_width(text: &str) -> usize {
+        let mut width = 0;
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                skip_escape(&mut chars, &mut String::new());
+            } else {
+                width += char_width(c);
+            }
+        }
+        width
+    }
+
+    fn skip_escape(chars: &mut std::str::Chars<'_>, escape: &mut String) {
+        escape.push('\x1b');
+        if let Some(c) = chars.next() {
+            escape.push(c);
+            if c != '[' {
+                return;
+            }
+        }
+        for c in chars.by_ref() {
+            escape.push(c);
+            if ('@'..='~').contains(&c) {
+                break;
+            }
+        }
+    }
+
+    if visible_width(line) <= max_width {
+        return std::borrow::Cow::Borrowed(line);
+    }
+
+    let budget = max_width.saturating_sub(visible_width(ellipsis));
+    let mut truncated = String::with_capacity(line.len());
+    let mut width = 0;
+    let mut styled = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            skip_escape(&mut chars, &mut truncated);
+            styled = true;
+            continue;
+        }
+        width += char_width(c);
+        if budget < width {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push_str(ellipsis);
+    if styled {
+        truncated.push_str("\x1b[0m");
+    }
+    std::borrow::Cow::Owned(truncated)
+}
+
+/// Columns `c` takes up in a terminal, approximating East Asian Width for wide characters and
+/// emoji
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        // Combining marks, zero-width joiners, and variation selectors
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2600..=0x27BF
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F000..=0x1FAFF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// [`switch`], with `stash` setting aside a dirty working tree and restoring it after
+///
+/// When the changes don't apply cleanly, they are left in `git stash list` to resolve by hand.
+pub(crate) fn switch_stashed(
+    repo: &mut git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+    current_id: git2::Oid,
+    stderr_palette: Palette,
+    stash: bool,
+    dry_run: bool,
+) -> proc_exit::ExitResult {
+    use proc_exit::prelude::*;
+    use std::io::Write;
+
+    let stash_id = if stash && !dry_run && repo.is_dirty() {
+        git_stack::git::stash_push(repo, "branch-stash")
+    } else {
+        None
+    };
+    if !dry_run && repo.is_dirty() {
+        git_stack::git::stash_pop(repo, stash_id);
+        return Err(proc_exit::sysexits::USAGE_ERR.with_message("Working tree is dirty, aborting"));
+    }
+
+    let switched = switch(repo, branches, current_id, stderr_palette, dry_run);
+
+    let mut restored = true;
+    if let Some(stash_id) = stash_id {
+        if let Err(err) = repo.stash_pop(stash_id) {
+            restored = false;
+            let _ = writeln!(
+                anstream::stderr(),
+                "{}: could not restore your changes, {}",
+                stderr_palette.error("error"),
+                err.message()
+            );
+            let abbrev_id = &stash_id.to_string()[..7];
+            if err.code() == git2::ErrorCode::Conflict {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: resolve the conflicts, then drop {} from `git stash list`",
+                    stderr_palette.info("note"),
+                    stderr_palette.highlight(abbrev_id)
+                );
+            } else {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: they are kept as {} in `git stash list`, resolve with `git stash pop`",
+                    stderr_palette.info("note"),
+                    stderr_palette.highlight(abbrev_id)
+                );
+            }
+        }
+    }
+
+    switched.with_code(proc_exit::Code::FAILURE)?;
+    if restored {
+        Ok(())
+    } else {
+        Err(proc_exit::Code::FAILURE.as_exit())
+    }
+}
+
 /// Switch to the best-guess branch
 ///
 /// # Panic
@@ -259,7 +799,7 @@ pub(crate) fn switch(
             "{} to {}: {}",
             stderr_palette.good("Switching"),
             stderr_palette.highlight(current_branch.display_name()),
-            stderr_palette.hint(&current_commit.summary)
+            stderr_palette.hint(current_commit.display_summary())
         );
         if !dry_run {
             repo.switch_branch(
@@ -280,7 +820,7 @@ pub(crate) fn switch(
             "{} to {}: {}",
             stderr_palette.good("Switching"),
             stderr_palette.highlight(abbrev_id.as_str().unwrap()),
-            stderr_palette.hint(&current_commit.summary)
+            stderr_palette.hint(current_commit.display_summary())
         );
         if !dry_run {
             repo.switch_commit(current_id)?;
@@ -313,27 +853,344 @@ pub(crate) fn render_id(
     }
 }
 
+/// Ask which side of a fork in the stack to take, when attached to a terminal
+///
+/// `ids` are in order of preference, with the first being the default.  Returns `None` when not
+/// interactive.
+pub(crate) fn pick_fork(
+    repo: &git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+    ids: &[git2::Oid],
+) -> Option<git2::Oid> {
+    use std::io::IsTerminal as _;
+    use std::io::Write as _;
+
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let stderr_palette = Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+    let _ = writeln!(stderr, "The stack forks, which side?");
+    for (i, id) in ids.iter().enumerate() {
+        let default = if i == 0 { " (default)" } else { "" };
+        let _ = writeln!(
+            stderr,
+            "  {}) {}{}",
+            i + 1,
+            stderr_palette.highlight(render_id(repo, branches, *id)),
+            stderr_palette.hint(default)
+        );
+    }
+    loop {
+        let _ = write!(stderr, "> ");
+        let _ = stderr.flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return ids.first().copied();
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return ids.first().copied();
+        }
+        match answer.parse::<usize>() {
+            Ok(i) if 1 <= i && i <= ids.len() => return Some(ids[i - 1]),
+            _ => {
+                let _ = writeln!(stderr, "Pick a number from 1 to {}", ids.len());
+            }
+        }
+    }
+}
+
+/// Ask to go ahead with `action`, when attached to a terminal
+///
+/// Going ahead is the default, including when not interactive.
+pub(crate) fn confirm(action: impl std::fmt::Display) -> bool {
+    use std::io::IsTerminal as _;
+    use std::io::Write as _;
+
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return true;
+    }
+    let stderr_palette = Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+    loop {
+        let _ = write!(stderr, "{}? {} ", action, stderr_palette.hint("[Y/n]"));
+        let _ = stderr.flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return true;
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "" | "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => {
+                let _ = writeln!(stderr, "Answer `y` or `n`");
+            }
+        }
+    }
+}
+
 pub(crate) fn edit_commit(
     git_path: &std::path::Path,
     editor: &str,
-    initial: &str,
+    template: &CommitTemplate,
 ) -> eyre::Result<Option<String>> {
     let edit_path = git_path.join("COMMIT_EDITMSG");
-    std::fs::write(&edit_path, initial)?;
-    let start = std::fs::metadata(&edit_path)?.modified()?;
+    let Some(edited) = edit_file(&edit_path, editor, &template.text)? else {
+        return Ok(None);
+    };
+
+    let sanitized = sanitize_message(&edited, &template.comment);
+    if sanitized.is_empty() {
+        eyre::bail!("Aborting commit due to empty commit message.")
+    }
+    if template
+        .template
+        .as_deref()
+        .is_some_and(|t| sanitize_message(t, &template.comment) == sanitized)
+    {
+        eyre::bail!("Aborting commit; you did not edit the message.")
+    }
+
+    Ok(Some(sanitized))
+}
+
+/// A commit message to edit, see [`commit_template`]
+pub(crate) struct CommitTemplate {
+    text: String,
+    /// What comment lines start with, from `core.commentChar`
+    comment: String,
+    /// `commit.template`, when the message started from it
+    template: Option<String>,
+}
+
+/// Message template for editing `existing`, like `git commit --amend`
+///
+/// Like `git commit`, this follows:
+/// - `commit.template`, for starting a commit without a message
+/// - `commit.verbose`, showing the changes of `diff`, as `(parent, commit)`, below a scissors line
+/// - `core.commentChar`, including `auto`
+pub(crate) fn commit_template(
+    repo: &git2::Repository,
+    existing: &str,
+    branch: Option<&dyn std::fmt::Display>,
+    diff: Option<(Option<git2::Oid>, git2::Oid)>,
+) -> CommitTemplate {
+    use std::fmt::Write;
+
+    let config = repo.config().ok();
+    let config = config.as_ref();
+    let template = (existing.trim().is_empty())
+        .then(|| config?.get_path("commit.template").ok())
+        .flatten()
+        .and_then(|path| match std::fs::read_to_string(&path) {
+            Ok(template) => Some(template),
+            Err(err) => {
+                log::warn!(
+                    "Could not read `commit.template` `{}`, {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        });
+    let message = template.as_deref().unwrap_or(existing);
+    let comment = match config.and_then(|c| c.get_string("core.commentChar").ok()) {
+        Some(comment) if comment == "auto" => auto_comment(message),
+        Some(comment) if !comment.is_empty() => comment,
+        _ => "#".to_owned(),
+    };
+    let verbose = config
+        .and_then(|c| {
+            c.get_bool("commit.verbose")
+                .ok()
+                .or_else(|| c.get_i32("commit.verbose").ok().map(|v| 0 < v))
+        })
+        .unwrap_or(false);
+
+    let mut text = String::new();
+    writeln!(&mut text, "{}", message.trim_end()).unwrap();
+    writeln!(&mut text).unwrap();
+    writeln!(
+        &mut text,
+        "{comment} Please enter the commit message for your changes. Lines starting"
+    )
+    .unwrap();
+    writeln!(
+        &mut text,
+        "{comment} with '{comment}' will be ignored, and an empty message aborts the commit."
+    )
+    .unwrap();
+    if let Some(branch) = branch {
+        writeln!(&mut text, "{comment}").unwrap();
+        writeln!(&mut text, "{comment} On branch {branch}").unwrap();
+    }
+    if let Some((parent_id, id)) = diff.filter(|_| verbose) {
+        match commit_diff(repo, parent_id, id) {
+            Ok(patch) => {
+                writeln!(&mut text, "{comment} {SCISSORS}").unwrap();
+                writeln!(
+                    &mut text,
+                    "{comment} Do not modify or remove the line above."
+                )
+                .unwrap();
+                writeln!(&mut text, "{comment} Everything below it will be ignored.").unwrap();
+                text.push_str(&patch);
+            }
+            Err(err) => log::debug!("Could not diff {}: {}", id, err),
+        }
+    }
+    CommitTemplate {
+        text,
+        comment,
+        template,
+    }
+}
+
+/// Everything after this comment is cut from a commit message, like with `git commit --verbose`
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// A comment character no line of `message` starts with, for `core.commentChar=auto`
+fn auto_comment(message: &str) -> String {
+    "#;@!$%^&|:"
+        .chars()
+        .find(|c| !message.lines().any(|l| l.starts_with(*c)))
+        .unwrap_or('#')
+        .to_string()
+}
+
+/// The patch `id` makes on top of `parent_id`, like `git diff`
+fn commit_diff(
+    repo: &git2::Repository,
+    parent_id: Option<git2::Oid>,
+    id: git2::Oid,
+) -> Result<String, git2::Error> {
+    let tree = repo.find_commit(id)?.tree()?;
+    let parent_tree = parent_id
+        .map(|id| repo.find_commit(id).and_then(|c| c.tree()))
+        .transpose()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    let mut patch = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })?;
+    Ok(String::from_utf8_lossy(&patch).into_owned())
+}
+
+/// Let the user reorder, drop, reword, and squash commits in `scripts` with their editor
+///
+/// `messages` are used for commits that were already reworded, everything else marked `reword`
+/// gets an editor of its own.  Returns `None` if the user emptied the todo list.
+pub(crate) fn edit_todo(
+    repo: &git_stack::git::GitRepo,
+    editor: &str,
+    scripts: &[git_stack::rewrite::Script],
+    messages: std::collections::HashMap<git2::Oid, String>,
+) -> eyre::Result<Option<git_stack::rewrite::Script>> {
+    use std::fmt::Write;
+
+    let git_path = git_stack::git::Repo::path(repo)
+        .ok_or_else(|| eyre::format_err!("no `.git` path found"))?;
+    let mut context = TodoContext {
+        repo,
+        editor,
+        messages,
+    };
+
+    let mut todo = String::new();
+    for script in scripts {
+        if !todo.is_empty() {
+            writeln!(&mut todo).unwrap();
+        }
+        write!(&mut todo, "{}", script.todo(&context)).unwrap();
+    }
+    todo.push_str(TODO_HELP);
+
+    let edit_path = git_path.join("git-stack-todo");
+    let edited = edit_file(&edit_path, editor, &todo)?.unwrap_or(todo);
+    let script = git_stack::rewrite::Script::parse_todo(&edited, &mut context)?;
+    Ok((!script.is_empty()).then_some(script))
+}
+
+const TODO_HELP: &str = "
+# Commands:
+# p, pick <commit> = use commit
+# r, reword <commit> = use commit, but edit the commit message
+# f, fixup <commit> = meld into the previous commit, keeping its message
+# d, drop <commit> = remove commit
+# l, label <label> = label the current commit
+# t, reset <label> = start again from a label or commit
+# m, merge -C <commit> <label>... = re-create a merge commit, `-c` to edit its message
+# x, exec git switch --force-create <branch> = point a branch at the current commit
+# x, exec git branch -D <branch> = delete a branch
+#
+# These lines can be re-ordered; they are executed from top to bottom.
+# If you remove everything, nothing will be changed.
+";
+
+struct TodoContext<'r> {
+    repo: &'r git_stack::git::GitRepo,
+    editor: &'r str,
+    messages: std::collections::HashMap<git2::Oid, String>,
+}
+
+impl git_stack::rewrite::TodoContext for TodoContext<'_> {
+    fn resolve(&self, rev: &str) -> Option<git2::Oid> {
+        let object = self.repo.raw().revparse_single(rev).ok()?;
+        object.peel_to_commit().ok().map(|c| c.id())
+    }
+
+    fn summary(&self, id: git2::Oid) -> Option<String> {
+        self.repo
+            .find_commit(id)
+            .map(|c| c.display_summary().into_owned())
+    }
+
+    fn reword(&mut self, id: git2::Oid) -> eyre::Result<String> {
+        if let Some(message) = self.messages.get(&id) {
+            return Ok(message.clone());
+        }
+
+        let raw_commit = self.repo.raw().find_commit(id)?;
+        let existing = git_stack::git::decode_message(
+            raw_commit.message_bytes(),
+            raw_commit.message_encoding(),
+        );
+        let git_path = git_stack::git::Repo::path(self.repo)
+            .ok_or_else(|| eyre::format_err!("no `.git` path found"))?;
+        let template = commit_template(
+            self.repo.raw(),
+            &existing,
+            None,
+            Some((raw_commit.parent_id(0).ok(), id)),
+        );
+        let message = edit_commit(git_path, self.editor, &template)?
+            .unwrap_or_else(|| existing.trim().to_owned());
+        self.messages.insert(id, message.clone());
+        Ok(message)
+    }
+}
+
+/// Open `path` in the user's editor, returning the edited content if it changed
+fn edit_file(path: &std::path::Path, editor: &str, initial: &str) -> eyre::Result<Option<String>> {
+    std::fs::write(path, initial)?;
 
     let mut args = shlex::Shlex::new(editor);
     let cmd = args.next().unwrap_or_else(|| "vi".to_owned());
 
     let status = std::process::Command::new(cmd)
         .args(args)
-        .arg(&edit_path)
+        .arg(path)
         .spawn()?
         .wait()?;
     if !status.success() {
         eyre::bail!(
             "failed to edit `{}` with `{}`: code {}",
-            edit_path.display(),
+            path.display(),
             editor,
             status
                 .code()
@@ -342,27 +1199,23 @@ pub(crate) fn edit_commit(
         );
     }
 
-    let end = std::fs::metadata(&edit_path)?.modified()?;
-    if start == end {
-        return Ok(None);
-    }
-
-    let edited = std::fs::read_to_string(&edit_path)?;
+    let edited = std::fs::read_to_string(path)?;
     if edited == initial {
         return Ok(None);
     }
 
-    let sanitized = sanitize_message(&edited);
-    if sanitized.is_empty() {
-        eyre::bail!("Aborting commit due to empty commit message.")
-    }
-
-    Ok(Some(sanitized))
+    Ok(Some(edited))
 }
 
-pub(crate) fn sanitize_message(message: &str) -> String {
-    let mut lines = LinesWithTerminator::new(message).collect::<Vec<_>>();
-    lines.retain(|l| !l.starts_with('#'));
+/// Clean up an edited commit message like `git commit --cleanup=strip`
+///
+/// Lines starting with `comment` are dropped, along with everything from a scissors line on.
+pub(crate) fn sanitize_message(message: &str, comment: &str) -> String {
+    let scissors = format!("{comment} {SCISSORS}");
+    let mut lines = LinesWithTerminator::new(message)
+        .take_while(|l| l.trim_end() != scissors)
+        .collect::<Vec<_>>();
+    lines.retain(|l| !l.starts_with(comment));
     while !lines.is_empty() {
         if lines.first().unwrap().trim().is_empty() {
             lines.remove(0);
@@ -424,18 +1277,113 @@ pub(crate) struct Palette {
     pub(crate) hint: anstyle::Style,
 }
 
+static PALETTE: std::sync::OnceLock<Palette> = std::sync::OnceLock::new();
+
+/// Apply the settings every command shares, like the [`Palette::colored`] and `stack.read-only`
+///
+/// With `check`, also warn about settings naming remotes or branches that don't exist, so this
+/// is reported once per run rather than by each place the config is loaded.
+pub(crate) fn init_from_config(check: bool) {
+    // Keep config warnings from being reported twice
+    let level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let (config, problems) = match git2::Repository::discover(".") {
+        Ok(repo) => match git_stack::config::RepoConfig::layers(&repo) {
+            Ok(layers) => {
+                let problems = if check {
+                    git_stack::config::RepoConfig::problems(&repo, &layers)
+                } else {
+                    Vec::new()
+                };
+                (
+                    Some(git_stack::config::RepoConfig::from_layers(layers)),
+                    problems,
+                )
+            }
+            Err(_) => (None, Vec::new()),
+        },
+        Err(_) => (
+            Some(
+                git_stack::config::RepoConfig::from_defaults()
+                    .update(git_stack::config::RepoConfig::from_env()),
+            ),
+            Vec::new(),
+        ),
+    };
+    log::set_max_level(level);
+
+    if let Some(config) = config {
+        let _ = PALETTE.set(Palette::from_config(&config));
+        crate::lock::set_read_only(config.read_only());
+    }
+    for problem in problems {
+        log::warn!("{}", problem);
+    }
+}
+
 impl Palette {
+    /// The palette selected by [`init_from_config`]
     pub(crate) fn colored() -> Self {
-        Self {
-            error: anstyle::AnsiColor::Red.on_default() | anstyle::Effects::BOLD,
-            warn: anstyle::AnsiColor::Yellow.on_default() | anstyle::Effects::BOLD,
-            info: anstyle::AnsiColor::Blue.on_default() | anstyle::Effects::BOLD,
-            good: anstyle::AnsiColor::Cyan.on_default() | anstyle::Effects::BOLD,
-            highlight: anstyle::AnsiColor::Green.on_default() | anstyle::Effects::BOLD,
-            hint: anstyle::Effects::DIMMED.into(),
+        PALETTE
+            .get()
+            .copied()
+            .unwrap_or_else(|| Self::named(git_stack::config::ColorPalette::default()))
+    }
+
+    pub(crate) fn named(name: git_stack::config::ColorPalette) -> Self {
+        use anstyle::AnsiColor;
+        use anstyle::Effects;
+
+        match name {
+            git_stack::config::ColorPalette::Default => Self {
+                error: AnsiColor::Red.on_default() | Effects::BOLD,
+                warn: AnsiColor::Yellow.on_default() | Effects::BOLD,
+                info: AnsiColor::Blue.on_default() | Effects::BOLD,
+                good: AnsiColor::Cyan.on_default() | Effects::BOLD,
+                highlight: AnsiColor::Green.on_default() | Effects::BOLD,
+                hint: Effects::DIMMED.into(),
+            },
+            git_stack::config::ColorPalette::Colorblind => Self {
+                error: AnsiColor::Magenta.on_default() | Effects::BOLD,
+                warn: AnsiColor::Yellow.on_default() | Effects::BOLD,
+                info: AnsiColor::Blue.on_default() | Effects::BOLD,
+                good: AnsiColor::Cyan.on_default() | Effects::BOLD,
+                highlight: (Effects::BOLD | Effects::UNDERLINE).into(),
+                hint: Effects::DIMMED.into(),
+            },
+            git_stack::config::ColorPalette::MonochromeBold => Self {
+                error: (Effects::BOLD | Effects::INVERT).into(),
+                warn: Effects::BOLD.into(),
+                info: anstyle::Style::new(),
+                good: anstyle::Style::new(),
+                highlight: (Effects::BOLD | Effects::UNDERLINE).into(),
+                hint: Effects::DIMMED.into(),
+            },
         }
     }
 
+    /// [`Palette::named`] with the `color.stack.<role>` overrides applied
+    pub(crate) fn from_config(config: &git_stack::config::RepoConfig) -> Self {
+        let mut palette = Self::named(config.palette());
+        for (role, style) in [
+            ("error", &mut palette.error),
+            ("warn", &mut palette.warn),
+            ("info", &mut palette.info),
+            ("good", &mut palette.good),
+            ("highlight", &mut palette.highlight),
+            ("hint", &mut palette.hint),
+        ] {
+            let Some(color) = config.color(role) else {
+                continue;
+            };
+            match parse_git_color(color) {
+                Ok(parsed) => *style = parsed,
+                Err(err) => log::warn!("Invalid `color.stack.{}={}`: {}", role, color, err),
+            }
+        }
+        palette
+    }
+
     pub(crate) fn error<D: std::fmt::Display>(self, display: D) -> Styled<D> {
         Styled::new(display, self.error)
     }
@@ -461,6 +1409,90 @@ impl Palette {
     }
 }
 
+/// Parse a color in `git config` syntax, like `red bold` or `#ff8700 black ul`
+///
+/// The first color is the foreground, the second the background.
+pub(crate) fn parse_git_color(spec: &str) -> Result<anstyle::Style, String> {
+    let mut style = anstyle::Style::new();
+    let mut colors = 0;
+    for word in spec.split_whitespace() {
+        let word = word.to_ascii_lowercase();
+        let (remove, name) = match word.strip_prefix("no-").or_else(|| word.strip_prefix("no")) {
+            Some(name) if parse_git_effect(name).is_some() => (true, name),
+            _ => (false, word.as_str()),
+        };
+        if let Some(effect) = parse_git_effect(name) {
+            style = if remove {
+                style.effects(style.get_effects().remove(effect))
+            } else {
+                style.effects(style.get_effects().insert(effect))
+            };
+            continue;
+        }
+        if name == "reset" {
+            continue;
+        }
+        let color = parse_git_color_name(name).ok_or_else(|| format!("unknown color `{word}`"))?;
+        match colors {
+            0 => style = style.fg_color(color),
+            1 => style = style.bg_color(color),
+            _ => return Err(format!("too many colors at `{word}`")),
+        }
+        colors += 1;
+    }
+    Ok(style)
+}
+
+fn parse_git_effect(name: &str) -> Option<anstyle::Effects> {
+    let effect = match name {
+        "bold" => anstyle::Effects::BOLD,
+        "dim" => anstyle::Effects::DIMMED,
+        "italic" => anstyle::Effects::ITALIC,
+        "ul" | "underline" => anstyle::Effects::UNDERLINE,
+        "blink" => anstyle::Effects::BLINK,
+        "reverse" => anstyle::Effects::INVERT,
+        "strike" => anstyle::Effects::STRIKETHROUGH,
+        _ => return None,
+    };
+    Some(effect)
+}
+
+/// `None` within `Some` is git's `normal` and `default`, keeping the terminal's color
+fn parse_git_color_name(name: &str) -> Option<Option<anstyle::Color>> {
+    use anstyle::AnsiColor;
+
+    if name == "normal" || name == "default" {
+        return Some(None);
+    }
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        let rgb = anstyle::RgbColor(channel(0)?, channel(2)?, channel(4)?);
+        return Some(Some(rgb.into()));
+    }
+    if let Ok(index) = name.parse::<u8>() {
+        return Some(Some(anstyle::Ansi256Color(index).into()));
+    }
+    let (bright, base) = match name.strip_prefix("bright") {
+        Some(base) => (true, base),
+        None => (false, name),
+    };
+    let color = match base {
+        "black" => AnsiColor::Black,
+        "red" => AnsiColor::Red,
+        "green" => AnsiColor::Green,
+        "yellow" => AnsiColor::Yellow,
+        "blue" => AnsiColor::Blue,
+        "magenta" => AnsiColor::Magenta,
+        "cyan" => AnsiColor::Cyan,
+        "white" => AnsiColor::White,
+        _ => return None,
+    };
+    Some(Some(color.bright(bright).into()))
+}
+
 #[derive(Debug)]
 pub(crate) struct Styled<D> {
     display: D,
@@ -484,3 +1516,529 @@ impl<D: std::fmt::Display> std::fmt::Display for Styled<D> {
 }
 
 pub(crate) const STASH_STACK_NAME: &str = "git-stack";
+
+/// How many `git-branch-stash` snapshots to keep, from `branch-stash.<name>.*`
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SnapshotRetention {
+    capacity: Option<usize>,
+    max_age: Option<std::time::Duration>,
+}
+
+impl SnapshotRetention {
+    pub(crate) fn from_config(config: &git_stack::config::RepoConfig) -> Self {
+        Self {
+            capacity: config.stash_capacity(STASH_STACK_NAME),
+            max_age: config.stash_max_age(STASH_STACK_NAME),
+        }
+    }
+}
+
+/// Snapshot all branches so the operation can be undone with `git stack undo`
+///
+/// `message` names the operation for `git stack snapshot` and the undo log.  `HEAD` is recorded in the snapshot's
+/// metadata, see [`git_stack::git::HeadState`], counting changes already stashed as `stash_id` as
+/// uncommitted.  Returns whether a snapshot was recorded.
+pub(crate) fn backup_branches(
+    cwd: &std::path::Path,
+    retention: SnapshotRetention,
+    message: &str,
+    stash_id: Option<git2::Oid>,
+    dry_run: bool,
+) -> Result<bool, proc_exit::Exit> {
+    let Some(pushed) = push_snapshot(cwd, retention, message, stash_id, dry_run)? else {
+        return Ok(false);
+    };
+    let repo = pushed.repo;
+
+    match git_stack::git::OpLog::new(&repo).and_then(|log| {
+        let mut operation = git_stack::git::Operation::from_repo(&repo, message)?;
+        operation.snapshot = Some(pushed.path);
+        operation.head = pushed.head;
+        Ok((log, operation))
+    }) {
+        Ok((log, operation)) => {
+            if let Err(err) = log.push(&operation) {
+                log::warn!("Could not record `{}` for undo: {}", message, err);
+            }
+        }
+        Err(err) => log::warn!("Could not record `{}` for undo: {}", message, err),
+    }
+    Ok(true)
+}
+
+/// Snapshot all branches without recording an operation to undo
+///
+/// For undoing itself, so the next `git stack undo` goes further back rather than redoing.
+pub(crate) fn backup_branches_for_undo(
+    cwd: &std::path::Path,
+    retention: SnapshotRetention,
+) -> Result<bool, proc_exit::Exit> {
+    push_snapshot(cwd, retention, "undo", None, false).map(|pushed| pushed.is_some())
+}
+
+struct PushedSnapshot {
+    repo: git2::Repository,
+    path: std::path::PathBuf,
+    head: Option<git_stack::git::HeadState>,
+}
+
+fn push_snapshot(
+    cwd: &std::path::Path,
+    retention: SnapshotRetention,
+    message: &str,
+    stash_id: Option<git2::Oid>,
+    dry_run: bool,
+) -> Result<Option<PushedSnapshot>, proc_exit::Exit> {
+    use proc_exit::prelude::*;
+
+    let stash_repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
+    let mut snapshots = git_branch_stash::Stack::new(STASH_STACK_NAME, &stash_repo);
+    snapshots.capacity(retention.capacity);
+    let mut snapshot =
+        git_branch_stash::Snapshot::from_repo(&stash_repo).with_code(proc_exit::Code::FAILURE)?;
+    snapshot.insert_message(message);
+    let head = match git_stack::git::HeadState::from_repo(stash_repo.raw()) {
+        Ok(mut head) => {
+            head.dirty |= stash_id.is_some();
+            head.insert_metadata(&mut snapshot.metadata);
+            Some(head)
+        }
+        Err(err) => {
+            log::debug!("Not recording HEAD in the snapshot: {}", err);
+            None
+        }
+    };
+    if dry_run {
+        return Ok(None);
+    }
+
+    let latest = snapshots.push(snapshot).to_sysexits()?;
+    if let Some(max_age) = retention.max_age {
+        expire_snapshots(&snapshots, &latest, max_age, false);
+    }
+    let repo =
+        git2::Repository::open(stash_repo.raw().path()).with_code(proc_exit::Code::FAILURE)?;
+    Ok(Some(PushedSnapshot {
+        repo,
+        path: latest,
+        head,
+    }))
+}
+
+/// Warn about a branch that won't re-stack cleanly, before anything is rewritten
+pub(crate) fn warn_conflict(
+    repo: &git2::Repository,
+    branch: Option<&str>,
+    commit_id: git2::Oid,
+    dependents: &[String],
+    skip: bool,
+) {
+    let branch = branch.unwrap_or("detached");
+    let summary = repo
+        .find_commit(commit_id)
+        .ok()
+        .and_then(|c| c.summary().map(ToOwned::to_owned))
+        .unwrap_or_default();
+    let abbrev_id = &commit_id.to_string()[..7];
+    if skip {
+        log::warn!(
+            "Leaving `{}` in place, it will conflict on {} ({})",
+            branch,
+            abbrev_id,
+            summary
+        );
+    } else {
+        log::warn!(
+            "Branch `{}` will conflict on {} ({})",
+            branch,
+            abbrev_id,
+            summary
+        );
+    }
+    if !dependents.is_empty() {
+        log::warn!("  Blocked dependents: {}", dependents.join(", "));
+    }
+}
+
+/// Report rewritten commits that tags point to, as the tags would be left on the original commits
+///
+/// Returns whether to stop, which is unless `force`d.
+pub(crate) fn warn_tagged_rewrites(
+    repo: &git2::Repository,
+    rewritten: &[git2::Oid],
+    force: bool,
+) -> Result<bool, git2::Error> {
+    if rewritten.is_empty() {
+        return Ok(false);
+    }
+    let tags = git_stack::git::tagged_commits(repo)?;
+    let mut found = false;
+    for id in rewritten {
+        let Some(names) = tags.get(id) else {
+            continue;
+        };
+        found = true;
+        let summary = repo
+            .find_commit(*id)
+            .ok()
+            .and_then(|c| c.summary().map(ToOwned::to_owned))
+            .unwrap_or_default();
+        let abbrev_id = &id.to_string()[..7];
+        let names = names
+            .iter()
+            .map(|n| format!("`{n}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if force {
+            log::warn!(
+                "Rewriting {} ({}), leaving {} on the original commit",
+                abbrev_id,
+                summary,
+                names
+            );
+        } else {
+            log::error!(
+                "Cannot rewrite {} ({}), tagged {}",
+                abbrev_id,
+                summary,
+                names
+            );
+        }
+    }
+    if found && !force {
+        log::error!("  Use `--force` to rewrite anyway, leaving the tags on the original commits");
+    }
+    Ok(found && !force)
+}
+
+/// Report protected branches whose copies on different remotes have diverged
+///
+/// Whichever remote we pull from wins, so the user should know the other one disagrees.
+pub(crate) fn warn_diverged_remotes(
+    repo: &git2::Repository,
+    protected: impl Fn(&str) -> bool,
+    pull_remote: impl Fn(&str) -> String,
+) {
+    let diverged = match git_stack::git::find_diverged_remotes(repo, protected, pull_remote) {
+        Ok(diverged) => diverged,
+        Err(err) => {
+            log::debug!("Could not compare protected remote branches: {}", err);
+            return;
+        }
+    };
+    for divergence in diverged {
+        log::warn!(
+            "`{other}/{name}` has diverged from `{remote}/{name}` ({} ahead, {} behind), using `{remote}/{name}`",
+            divergence.behind,
+            divergence.ahead,
+            other = divergence.other_remote,
+            remote = divergence.remote,
+            name = divergence.name,
+        );
+        log::warn!(
+            "  To pick the remote, set `stack.protected-remote` to `{}={}`",
+            divergence.name,
+            divergence.other_remote
+        );
+    }
+}
+
+/// Tell the user how to restore the snapshot taken by [`backup_branches`]
+///
+/// Logged at info so `-q` silences it
+pub(crate) fn note_undo() {
+    let stderr_palette = Palette::colored();
+    log::info!(
+        "{}: to undo, run {}",
+        stderr_palette.info("note"),
+        stderr_palette.highlight("`git stack undo`")
+    );
+}
+
+/// Report the branches that were left alone as re-stacking them would change nothing
+pub(crate) fn note_up_to_date(branches: &[String]) {
+    if branches.is_empty() {
+        return;
+    }
+    let stderr_palette = Palette::colored();
+    let noun = if branches.len() == 1 {
+        "branch"
+    } else {
+        "branches"
+    };
+    anstream::eprintln!(
+        "{} {noun} already up to date",
+        stderr_palette.good(branches.len())
+    );
+    log::debug!("Up to date: {}", branches.join(", "));
+}
+
+/// Preview how `--dry-run` would change the stacks
+pub(crate) fn note_plan(
+    repo: &git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+    diff: &git_stack::graph::GraphDiff,
+) {
+    let stderr_palette = Palette::colored();
+    let no_branches = git_stack::graph::BranchSet::new();
+    // Sorted by name, as sibling commits have no natural order
+    let mut moved = diff
+        .moved
+        .iter()
+        .filter_map(|moved| {
+            let old_parent = moved.old_parents.first()?;
+            let new_parent = moved.new_parents.first()?;
+            Some((
+                render_id(repo, branches, moved.id),
+                render_id(repo, branches, *old_parent),
+                render_id(repo, branches, *new_parent),
+            ))
+        })
+        .collect::<Vec<_>>();
+    moved.sort();
+    for (name, old_parent, new_parent) in moved {
+        anstream::eprintln!(
+            "{} {} from {} onto {}",
+            stderr_palette.info("Would move"),
+            stderr_palette.highlight(name),
+            old_parent,
+            new_parent,
+        );
+    }
+    for retarget in &diff.retargeted {
+        let name = stderr_palette.highlight(&retarget.name);
+        match (retarget.old_id, retarget.new_id) {
+            (_, None) => {
+                anstream::eprintln!("{} {}", stderr_palette.info("Would delete"), name);
+            }
+            (Some(old_id), Some(new_id)) => {
+                anstream::eprintln!(
+                    "{} {} from {} to {}",
+                    stderr_palette.info("Would move"),
+                    name,
+                    render_id(repo, &no_branches, old_id),
+                    render_id(repo, &no_branches, new_id),
+                );
+            }
+            (None, Some(new_id)) => {
+                anstream::eprintln!(
+                    "{} {} at {}",
+                    stderr_palette.info("Would create"),
+                    name,
+                    render_id(repo, &no_branches, new_id),
+                );
+            }
+        }
+    }
+}
+
+/// The branch the stack containing `branch_id` is built on top of
+pub(crate) fn parent_branch(
+    graph: &git_stack::graph::Graph,
+    branch_id: git2::Oid,
+) -> Option<String> {
+    let mut current_id = branch_id;
+    while let Some(parent_id) = graph.primary_parent_of(current_id) {
+        if let Some(branches) = graph.branches.get(parent_id) {
+            if let Some(name) = branches.iter().map(|b| b.base_name()).min() {
+                return Some(name.to_owned());
+            }
+        }
+        current_id = parent_id;
+    }
+    None
+}
+
+/// Whether `HEAD` is on a branch without any commits, like right after `git init`
+pub(crate) fn is_head_unborn(repo: &git2::Repository) -> bool {
+    repo.head()
+        .err()
+        .map(|err| err.code() == git2::ErrorCode::UnbornBranch)
+        .unwrap_or(false)
+}
+
+/// Fail commands that need a commit to work from
+pub(crate) fn require_born_head(repo: &git2::Repository) -> proc_exit::ExitResult {
+    if is_head_unborn(repo) {
+        Err(proc_exit::sysexits::USAGE_ERR
+            .with_message("no commits yet, create one with `git commit` first"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Tell the user there is nothing to do until the first commit
+pub(crate) fn note_unborn_head() {
+    let stderr_palette = Palette::colored();
+    anstream::eprintln!(
+        "{}: no commits yet, nothing to do",
+        stderr_palette.info("note"),
+    );
+}
+
+/// Remove snapshots older than `max_age`, always keeping `latest`
+///
+/// Returns how many snapshots were (or, with `dry_run`, would be) removed.
+pub(crate) fn expire_snapshots(
+    snapshots: &git_branch_stash::Stack,
+    latest: &std::path::Path,
+    max_age: std::time::Duration,
+    dry_run: bool,
+) -> usize {
+    let Some(cutoff) = std::time::SystemTime::now().checked_sub(max_age) else {
+        return 0;
+    };
+    let mut expired = 0;
+    for snapshot_path in snapshots.iter().filter(|p| p != latest) {
+        let modified = std::fs::metadata(&snapshot_path).and_then(|m| m.modified());
+        match modified {
+            Ok(modified) if modified <= cutoff => {
+                if dry_run {
+                    log::trace!("Would expire {}", snapshot_path.display());
+                    expired += 1;
+                } else if let Err(err) = std::fs::remove_file(&snapshot_path) {
+                    log::debug!("Failed to remove {}: {}", snapshot_path.display(), err);
+                } else {
+                    log::trace!("Expired {}", snapshot_path.display());
+                    expired += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                log::debug!(
+                    "Failed to check age of {}: {}",
+                    snapshot_path.display(),
+                    err
+                );
+            }
+        }
+    }
+    expired
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transient_failures() {
+        assert!(is_transient(
+            b"error: RPC failed; HTTP 503 curl 22 The requested URL returned error: 503\n"
+        ));
+        assert!(is_transient(
+            b"fatal: unable to access 'https://example.com/': Could not resolve host: example.com\n"
+        ));
+        assert!(is_transient(
+            b"fatal: the remote end hung up unexpectedly\n"
+        ));
+    }
+
+    #[test]
+    fn permanent_failures() {
+        assert!(!is_transient(
+            b"fatal: Authentication failed for 'https://example.com/'\n"
+        ));
+        assert!(!is_transient(
+            b" ! [rejected]        main -> main (stale info)\n"
+        ));
+        assert!(!is_transient(b"fatal: couldn't find remote ref missing\n"));
+        assert!(!is_transient(
+            b"error: atomic push failed for ref refs/heads/other. status: 7\n\nfatal: the remote end hung up unexpectedly\n"
+        ));
+    }
+
+    #[test]
+    fn unsent_failures() {
+        assert!(is_unsent(
+            b"fatal: unable to access 'https://example.com/': Could not resolve host: example.com\n"
+        ));
+        assert!(is_unsent(
+            b"ssh: connect to host example.com port 22: Connection refused\n"
+        ));
+        assert!(!is_unsent(b"fatal: the remote end hung up unexpectedly\n"));
+        assert!(!is_unsent(
+            b"error: RPC failed; HTTP 503 curl 22 The requested URL returned error: 503\n"
+        ));
+    }
+
+    #[test]
+    fn atomic_refused() {
+        assert!(refuses_atomic(
+            b"fatal: the receiving end does not support --atomic push\n"
+        ));
+        assert!(!refuses_atomic(
+            b"error: atomic push failed for ref refs/heads/other. status: 7\n"
+        ));
+    }
+
+    #[test]
+    fn push_porcelain() {
+        let stdout = "To ../remote.git
+*\trefs/heads/feature:refs/heads/feature\t[new branch]
++\trefs/heads/fix:refs/heads/me/fix\t1234567...89abcde (forced update)
+=\trefs/heads/done:refs/heads/done\t[up to date]
+!\trefs/heads/other:refs/heads/other\t[rejected] (stale info)
+Done
+";
+        assert_eq!(
+            parse_push_porcelain(stdout),
+            [
+                ("refs/heads/feature".to_owned(), PushStatus::Pushed),
+                ("refs/heads/me/fix".to_owned(), PushStatus::Pushed),
+                ("refs/heads/done".to_owned(), PushStatus::UpToDate),
+                (
+                    "refs/heads/other".to_owned(),
+                    PushStatus::Rejected("stale info".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sanitize_comments() {
+        assert_eq!(
+            sanitize_message("\nSubject\n\n# comment\nBody\n\n", "#"),
+            "Subject\n\nBody"
+        );
+        assert_eq!(
+            sanitize_message("Subject\n# kept\n; dropped\n", ";"),
+            "Subject\n# kept"
+        );
+        assert_eq!(
+            sanitize_message(
+                "Subject\n# ------------------------ >8 ------------------------\n+diff\n",
+                "#"
+            ),
+            "Subject"
+        );
+    }
+
+    #[test]
+    fn auto_comment_char() {
+        assert_eq!(auto_comment("Subject\n\nBody"), "#");
+        assert_eq!(auto_comment("#1 Subject\n;x"), "@");
+    }
+
+    #[test]
+    fn git_colors() {
+        assert_eq!(
+            parse_git_color("red bold"),
+            Ok(anstyle::AnsiColor::Red.on_default() | anstyle::Effects::BOLD)
+        );
+        assert_eq!(
+            parse_git_color("normal brightblue ul"),
+            Ok(anstyle::Style::new()
+                .bg_color(Some(anstyle::AnsiColor::BrightBlue.into()))
+                .underline())
+        );
+        assert_eq!(
+            parse_git_color("#ff8700 208 nobold"),
+            Ok(anstyle::Style::new()
+                .fg_color(Some(anstyle::RgbColor(0xff, 0x87, 0x00).into()))
+                .bg_color(Some(anstyle::Ansi256Color(208).into())))
+        );
+        assert!(parse_git_color("red green blue").is_err());
+        assert!(parse_git_color("reddish").is_err());
+    }
+}
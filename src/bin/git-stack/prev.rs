@@ -9,15 +9,23 @@ pub(crate) struct PrevArgs {
     #[arg(default_value = "1")]
     num_commits: usize,
 
-    /// Jump directly to the previous branch
-    #[arg(short, long)]
-    branch: bool,
+    /// Jump directly to the previous branch, taking the side of each merge that leads to `NAME`
+    #[arg(
+        short,
+        long,
+        value_name = "NAME",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    branch: Option<Option<String>>,
 
-    /// Stash prior to switch
-    #[arg(long)]
+    /// Stash a dirty working tree while switching, restoring it after
+    #[arg(long, overrides_with("no_stash"))]
     stash: bool,
+    #[arg(long, overrides_with("stash"), hide = true)]
+    no_stash: bool,
 
-    /// On ambiguity, select the oldest commit
+    /// On ambiguity, select the oldest commit, rather than asking
     #[arg(long)]
     oldest: bool,
 
@@ -42,23 +50,49 @@ impl PrevArgs {
     }
 
     pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(self.dry_run)?;
         let stderr_palette = crate::ops::Palette::colored();
 
         let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
         let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
         let mut repo = git_stack::git::GitRepo::new(repo);
 
         let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         repo.set_push_remote(repo_config.push_remote());
         repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
 
         let protected = git_stack::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
             .with_code(proc_exit::Code::FAILURE)?;
+        let by_branch = self.branch.is_some();
+        let toward = self
+            .branch
+            .as_ref()
+            .and_then(|b| b.as_deref())
+            .map(|name| {
+                let branch = repo.find_local_branch(name).ok_or_else(|| {
+                    proc_exit::sysexits::USAGE_ERR
+                        .with_message(format!("could not find branch `{name}`"))
+                })?;
+                let head_id = repo.head_commit().id;
+                if branch.id == head_id || repo.merge_base(head_id, branch.id) != Some(branch.id) {
+                    return Err(proc_exit::sysexits::USAGE_ERR
+                        .with_message(format!("`{name}` is not beneath `HEAD`")));
+                }
+                Ok(branch.id)
+            })
+            .transpose()?;
 
         if repo.raw().state() != git2::RepositoryState::Clean {
             let message = format!(
@@ -77,10 +111,9 @@ impl PrevArgs {
             }
         }
 
-        if self.stash && !self.dry_run {
-            git_stack::git::stash_push(&mut repo, "branch-stash");
-        }
-        if repo.is_dirty() {
+        let stash = crate::args::resolve_bool_arg(self.stash, self.no_stash)
+            .unwrap_or_else(|| repo_config.auto_stash());
+        if !stash && repo.is_dirty() {
             let message = "Working tree is dirty, aborting";
             if self.dry_run {
                 let _ = writeln!(
@@ -119,7 +152,7 @@ impl PrevArgs {
                         anstream::stderr(),
                         "{}: not enough unprotected parent {}, only able to go back {}; to traverse protected commits, pass `--protected`",
                         stderr_palette.info("note"),
-                        if self.branch { "branches" } else { "commits" },
+                        if by_branch { "branches" } else { "commits" },
                         self.num_commits
                     );
                 }
@@ -141,7 +174,7 @@ impl PrevArgs {
                         anstream::stderr(),
                         "{}: not enough parent {}, only able to go forward {}",
                         stderr_palette.info("note"),
-                        if self.branch { "branches" } else { "commits" },
+                        if by_branch { "branches" } else { "commits" },
                         self.num_commits
                     );
                 }
@@ -167,20 +200,34 @@ impl PrevArgs {
                     (branch_kind, std::cmp::Reverse(commit_time))
                 });
             }
-            current_id = *next_ids.first().expect("next_ids.is_empty checked");
+            // Past `toward`, there is nothing to steer by
+            let toward_id = toward.and_then(|toward_id| {
+                next_ids
+                    .iter()
+                    .copied()
+                    .find(|id| repo.merge_base(*id, toward_id) == Some(toward_id))
+            });
+            current_id = if let Some(toward_id) = toward_id {
+                toward_id
+            } else if 1 < next_ids.len() && !self.oldest {
+                crate::ops::pick_fork(&repo, &branches, &next_ids)
+                    .unwrap_or_else(|| *next_ids.first().expect("next_ids.is_empty checked"))
+            } else {
+                *next_ids.first().expect("next_ids.is_empty checked")
+            };
             if 1 < next_ids.len() {
                 log::debug!(
                     "selected {} over {}",
                     crate::ops::render_id(&repo, &branches, current_id),
                     next_ids
                         .iter()
-                        .skip(1)
+                        .filter(|id| **id != current_id)
                         .map(|id| crate::ops::render_id(&repo, &branches, *id))
                         .collect::<Vec<_>>()
                         .join(", ")
                 );
             }
-            if self.branch {
+            if by_branch {
                 if let Some(current) = branches.get(current_id) {
                     log::debug!(
                         "Traversing {}",
@@ -198,14 +245,14 @@ impl PrevArgs {
         }
 
         if current_id != head_id {
-            crate::ops::switch(
+            crate::ops::switch_stashed(
                 &mut repo,
                 &branches,
                 current_id,
                 stderr_palette,
+                stash,
                 self.dry_run,
-            )
-            .with_code(proc_exit::Code::FAILURE)?;
+            )?;
         }
 
         Ok(())
@@ -0,0 +1,159 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Move branches into a named stack, or list the named stacks
+///
+/// Named stacks group related development branches so they can be worked on together with
+/// `git stack --stack <NAME>`, independent of how their commits are laid out.  A branch belongs
+/// to at most one named stack.
+#[derive(clap::Args)]
+pub(crate) struct RegroupArgs {
+    /// Named stack to move the branches into (default: list the named stacks)
+    #[arg(value_name = "STACK")]
+    stack: Option<String>,
+
+    /// Branches to move (default: current branch)
+    #[arg(value_name = "BRANCH", requires = "stack")]
+    branches: Vec<String>,
+}
+
+impl RegroupArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let mut named_stacks = git_stack::config::NamedStacks::from_repo(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+
+        let Some(stack) = self.stack.as_deref() else {
+            let mut stdout = anstream::stdout().lock();
+            for (stack, branches) in named_stacks.stacks() {
+                let _ = writeln!(stdout, "{stack}");
+                for branch in branches {
+                    let _ = writeln!(stdout, "  {branch}");
+                }
+            }
+            return Ok(());
+        };
+        validate_stack_name(stack)?;
+        crate::lock::require_writable(false)?;
+
+        let branches = if self.branches.is_empty() {
+            let head = repo.head().with_code(proc_exit::sysexits::USAGE_ERR)?;
+            let branch = head
+                .is_branch()
+                .then(|| head.shorthand())
+                .flatten()
+                .ok_or_else(|| {
+                    proc_exit::sysexits::USAGE_ERR
+                        .with_message("HEAD is detached, pass the branches to move")
+                })?;
+            vec![branch.to_owned()]
+        } else {
+            self.branches.clone()
+        };
+        for branch in &branches {
+            if repo.find_branch(branch, git2::BranchType::Local).is_err() {
+                return Err(proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("could not find branch `{branch}`")));
+            }
+        }
+
+        for branch in &branches {
+            match named_stacks.stack_of(branch) {
+                Some(current) if current == stack => {
+                    log::debug!("`{branch}` is already in `{stack}`");
+                    continue;
+                }
+                Some(current) => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} {} from {} to {}",
+                        stderr_palette.good("Moving"),
+                        stderr_palette.highlight(branch),
+                        current,
+                        stderr_palette.highlight(stack),
+                    );
+                }
+                None => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} {} to {}",
+                        stderr_palette.good("Adding"),
+                        stderr_palette.highlight(branch),
+                        stderr_palette.highlight(stack),
+                    );
+                }
+            }
+            named_stacks
+                .assign(&repo, branch, stack)
+                .with_code(proc_exit::Code::FAILURE)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rename a named stack
+#[derive(clap::Args)]
+pub(crate) struct RenameStackArgs {
+    /// Named stack to rename
+    #[arg(value_name = "OLD")]
+    old: String,
+
+    /// New name for the stack
+    #[arg(value_name = "NEW")]
+    new: String,
+}
+
+impl RenameStackArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(false)?;
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let mut named_stacks = git_stack::config::NamedStacks::from_repo(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+
+        if !named_stacks.contains(&self.old) {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message(format!("no stack named `{}`", self.old)));
+        }
+        validate_stack_name(&self.new)?;
+        if named_stacks.contains(&self.new) {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "stack `{}` already exists, use `git stack regroup` to move branches into it",
+                self.new
+            )));
+        }
+
+        named_stacks
+            .rename(&repo, &self.old, &self.new)
+            .with_code(proc_exit::Code::FAILURE)?;
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {} to {}",
+            stderr_palette.good("Renamed"),
+            self.old,
+            stderr_palette.highlight(&self.new),
+        );
+
+        Ok(())
+    }
+}
+
+/// Named stacks share `--stack` with the built-in selections, so those names are taken
+fn validate_stack_name(name: &str) -> proc_exit::ExitResult {
+    if name.is_empty() {
+        return Err(proc_exit::sysexits::USAGE_ERR.with_message("stack name cannot be empty"));
+    }
+    if <git_stack::config::Stack as clap::ValueEnum>::from_str(name, false).is_ok() {
+        return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+            "`{name}` is reserved for `--stack {name}`, pick another name"
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Rename a development branch, carrying over what refers to it
+///
+/// Unlike `git branch -m`, the `git branch-stash` snapshots are updated so undoing an earlier
+/// operation restores the branch under its new name, and a branch tracking its own pushed copy
+/// is pointed at the new name on the remote.
+#[derive(clap::Args)]
+pub(crate) struct RenameArgs {
+    /// Branch to rename
+    #[arg(value_name = "OLD")]
+    old: String,
+
+    /// New name for the branch
+    #[arg(value_name = "NEW")]
+    new: String,
+}
+
+impl RenameArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, false)?;
+
+        let mut branch = repo
+            .find_branch(&self.old, git2::BranchType::Local)
+            .map_err(|_| {
+                proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("could not find branch `{}`", self.old))
+            })?;
+        if !git2::Branch::name_is_valid(&self.new).unwrap_or(false) {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message(format!("`{}` is not a valid branch name", self.new)));
+        }
+        if repo.find_branch(&self.new, git2::BranchType::Local).is_ok() {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message(format!("branch `{}` already exists", self.new)));
+        }
+        let existing = repo
+            .branches(Some(git2::BranchType::Local))
+            .with_code(proc_exit::Code::FAILURE)?
+            .filter_map(|b| b.ok())
+            .filter_map(|(b, _)| b.name().ok().flatten().map(ToOwned::to_owned))
+            .filter(|name| *name != self.old)
+            .collect::<Vec<_>>();
+        git_stack::git::check_branch_case(&self.new, existing.iter().map(|s| s.as_str()))
+            .map_err(|err| proc_exit::sysexits::USAGE_ERR.with_message(err.message().to_owned()))?;
+
+        // The `branch.<name>.*` section, including the upstream and named stack, moves with it
+        branch
+            .rename(&self.new, false)
+            .with_code(proc_exit::Code::FAILURE)?;
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {} to {}",
+            stderr_palette.good("Renamed"),
+            self.old,
+            stderr_palette.highlight(&self.new),
+        );
+
+        if let Some(remote) = retrack_upstream(&repo, &self.old, &self.new)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?
+        {
+            log::info!(
+                "`{remote}/{}` is left on the remote, the next push creates `{remote}/{}`",
+                self.old,
+                self.new
+            );
+        }
+
+        let updated = rename_in_snapshots(&repo, &self.old, &self.new);
+        if updated != 0 {
+            log::debug!("Renamed `{}` in {} snapshots", self.old, updated);
+        }
+
+        Ok(())
+    }
+}
+
+/// Point `new` at its own name on the remote, if `old` was tracking its own pushed copy
+///
+/// Returns the remote, if the upstream was updated.
+fn retrack_upstream(
+    repo: &git2::Repository,
+    old: &str,
+    new: &str,
+) -> Result<Option<String>, git2::Error> {
+    let mut config = repo.config()?;
+    let merge_key = format!("branch.{new}.merge");
+    let Ok(merge) = config.get_string(&merge_key) else {
+        return Ok(None);
+    };
+    if merge != format!("refs/heads/{old}") {
+        return Ok(None);
+    }
+    let Ok(remote) = config.get_string(&format!("branch.{new}.remote")) else {
+        return Ok(None);
+    };
+    config.set_str(&merge_key, &format!("refs/heads/{new}"))?;
+    Ok(Some(remote))
+}
+
+/// Rename the branch in every `git branch-stash` snapshot, returning how many changed
+///
+/// A snapshot that can't be updated is skipped, as undo still works for the other branches.
+fn rename_in_snapshots(repo: &git2::Repository, old: &str, new: &str) -> usize {
+    let Ok(stash_repo) = git2::Repository::open(repo.path()) else {
+        return 0;
+    };
+    let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
+    let mut updated = 0;
+    for stack in git_branch_stash::Stack::all(&stash_repo) {
+        for path in stack.iter() {
+            let mut snapshot = match git_branch_stash::Snapshot::load(&path) {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    log::debug!("Skipping {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            let mut changed = false;
+            for branch in snapshot.branches.iter_mut() {
+                if branch.name == old {
+                    new.clone_into(&mut branch.name);
+                    changed = true;
+                }
+            }
+            if !changed {
+                continue;
+            }
+            snapshot
+                .branches
+                .sort_unstable_by(|a, b| a.name.cmp(&b.name));
+            match snapshot.save(&path) {
+                Ok(()) => updated += 1,
+                Err(err) => log::warn!("Could not rename `{}` in {}: {}", old, path.display(), err),
+            }
+        }
+    }
+    updated
+}
@@ -0,0 +1,211 @@
+use std::io::Write;
+
+use itertools::Itertools;
+use proc_exit::prelude::*;
+
+/// Finish a rewrite that stopped on a conflict, after the user resolved it
+pub(crate) fn continue_rewrite(_args: &crate::args::Args) -> proc_exit::ExitResult {
+    log::trace!("Initializing");
+    let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let _lock = crate::lock::lock_repo(&repo, false)?;
+    let mut repo = git_stack::git::GitRepo::new(repo);
+
+    let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    repo.set_sign_policy(sign)
+        .with_code(proc_exit::Code::FAILURE)?;
+
+    let git_dir = repo.raw().path().to_owned();
+    let resume = load(&git_dir)?;
+
+    let resolved_id = repo
+        .commit_conflict(resume.onto_id(), resume.conflict_id())
+        .map_err(|err| {
+            proc_exit::sysexits::USAGE_ERR.with_message(format!(
+                "{}, resolve the conflict or run `git stack --abort`",
+                err.message()
+            ))
+        })?;
+
+    let mut executor = git_stack::rewrite::Executor::new(false);
+    executor.set_resumable(true);
+    executor.register(ReportFailures);
+    let results = executor.resume(&mut repo, &resume, resolved_id);
+    let success = results.is_empty();
+    if let Some(mut suspended) = executor.take_suspended() {
+        suspended.set_restore_branch(resume.restore_branch());
+        suspended.set_stash_id(resume.stash_id());
+        return Err(suspend(&mut repo, &suspended));
+    }
+    executor
+        .close(&mut repo, resume.restore_branch())
+        .with_code(proc_exit::Code::FAILURE)?;
+    git_stack::rewrite::Resume::clear(&git_dir).with_code(proc_exit::Code::FAILURE)?;
+
+    git_stack::git::stash_pop(&mut repo, resume.stash_id());
+
+    if success {
+        Ok(())
+    } else {
+        Err(proc_exit::Code::FAILURE.as_exit())
+    }
+}
+
+/// Give up on a rewrite that stopped on a conflict, putting the branches back
+pub(crate) fn abort_rewrite(_args: &crate::args::Args) -> proc_exit::ExitResult {
+    log::trace!("Initializing");
+    let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+    let _lock = crate::lock::lock_repo(&repo, false)?;
+    let mut repo = git_stack::git::GitRepo::new(repo);
+
+    let git_dir = repo.raw().path().to_owned();
+    let resume = load(&git_dir)?;
+
+    repo.abort_conflict().with_code(proc_exit::Code::FAILURE)?;
+    for (name, id) in resume.original_branches() {
+        match id {
+            Some(id) => {
+                log::trace!("git branch --force {} {}", name, id);
+                repo.branch(name, id).with_code(proc_exit::Code::FAILURE)?;
+            }
+            None => {
+                if repo.find_local_branch(name).is_some() {
+                    log::trace!("git branch -D {}", name);
+                    repo.delete_branch(name)
+                        .with_code(proc_exit::Code::FAILURE)?;
+                }
+            }
+        }
+    }
+    if let Some(branch) = resume.restore_branch() {
+        repo.switch_branch(branch)
+            .with_code(proc_exit::Code::FAILURE)?;
+    } else {
+        repo.switch_commit(resume.original_head_id())
+            .with_code(proc_exit::Code::FAILURE)?;
+    }
+    git_stack::rewrite::Resume::clear(&git_dir).with_code(proc_exit::Code::FAILURE)?;
+
+    git_stack::git::stash_pop(&mut repo, resume.stash_id());
+
+    Ok(())
+}
+
+/// Refuse to start a rewrite while another one is stopped on a conflict
+pub(crate) fn require_no_rewrite(repo: &git2::Repository) -> proc_exit::ExitResult {
+    if git_stack::rewrite::Resume::path(repo.path()).exists() {
+        Err(proc_exit::sysexits::USAGE_ERR.with_message(
+            "a rewrite is stopped on a conflict, run `git stack --continue` or `git stack --abort`",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Run `scripts`, stopping at the first conflicting cherry-pick for `git stack --continue`
+///
+/// Returns whether every branch was re-stacked.
+pub(crate) fn run_scripts(
+    repo: &mut git_stack::git::GitRepo,
+    executor: &mut git_stack::rewrite::Executor,
+    scripts: Vec<git_stack::rewrite::Script>,
+    restore_branch: Option<&str>,
+    stash_id: Option<git2::Oid>,
+) -> Result<bool, proc_exit::Exit> {
+    let mut success = true;
+    executor.set_resumable(true);
+    executor.register(ReportFailures);
+    let mut scripts = scripts.into_iter();
+    while let Some(script) = scripts.next() {
+        let results = executor.run(repo, &script);
+        success &= results.is_empty();
+        if let Some(mut suspended) = executor.take_suspended() {
+            for script in scripts.by_ref() {
+                suspended.push_script(script);
+            }
+            suspended.set_restore_branch(restore_branch);
+            suspended.set_stash_id(stash_id);
+            return Err(suspend(repo, &suspended));
+        }
+    }
+    executor
+        .close(repo, restore_branch)
+        .with_code(proc_exit::Code::FAILURE)?;
+
+    Ok(success)
+}
+
+/// Report branches that couldn't be re-stacked, leaving conflicts the rewrite stopped at to
+/// [`suspend`]
+struct ReportFailures;
+
+impl git_stack::rewrite::Events for ReportFailures {
+    fn on_conflict(&mut self, failure: &git_stack::rewrite::BatchFailure<'_>) {
+        if failure.suspended {
+            return;
+        }
+        log::error!(
+            "Failed to re-stack branch `{}`: {}",
+            failure.branch,
+            failure.error
+        );
+        if !failure.dependents.is_empty() {
+            log::error!(
+                "  Blocked dependents: {}",
+                failure.dependents.iter().join(", ")
+            );
+        }
+    }
+}
+
+fn load(git_dir: &std::path::Path) -> Result<git_stack::rewrite::Resume, proc_exit::Exit> {
+    git_stack::rewrite::Resume::load(git_dir)
+        .with_code(proc_exit::Code::FAILURE)?
+        .ok_or_else(|| proc_exit::sysexits::USAGE_ERR.with_message("no rewrite in progress"))
+}
+
+/// Save the progress and leave the conflict in the working tree for the user to resolve
+fn suspend(
+    repo: &mut git_stack::git::GitRepo,
+    resume: &git_stack::rewrite::Resume,
+) -> proc_exit::Exit {
+    let stderr_palette = crate::ops::Palette::colored();
+
+    if let Err(err) = resume.save(repo.raw().path()) {
+        return proc_exit::Code::FAILURE.with_message(err);
+    }
+    if let Err(err) = repo.checkout_conflict(resume.onto_id(), resume.conflict_id()) {
+        return proc_exit::Code::FAILURE.with_message(err);
+    }
+
+    let conflict_id = resume.conflict_id();
+    let summary = repo
+        .find_commit(conflict_id)
+        .map(|c| c.display_summary().into_owned())
+        .unwrap_or_default();
+    let _ = writeln!(
+        anstream::stderr(),
+        "{}: could not apply {} ({}): {}",
+        stderr_palette.error("error"),
+        stderr_palette.highlight(&conflict_id.to_string()[..7]),
+        resume.branch().unwrap_or("detached"),
+        stderr_palette.hint(summary)
+    );
+    let _ = writeln!(
+        anstream::stderr(),
+        "{}: resolve the conflicts and `git add` them, then run {}",
+        stderr_palette.info("note"),
+        stderr_palette.highlight("`git stack --continue`")
+    );
+    let _ = writeln!(
+        anstream::stderr(),
+        "{}: to put the branches back, run {}",
+        stderr_palette.info("note"),
+        stderr_palette.highlight("`git stack --abort`")
+    );
+    proc_exit::Code::FAILURE.as_exit()
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Point each open pull request at its parent branch in the stack
+///
+/// After the bottom branch of a stack lands and is deleted, the pull requests stacked on top of it
+/// still target it.  This updates their base branch to match the current stacks.  Requires
+/// `stack.forge`.
+#[derive(clap::Args)]
+pub(crate) struct RetargetArgs {
+    /// Don't actually update pull requests
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl RetargetArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(self.dry_run)?;
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let forge = match repo_config.forge() {
+            git_stack::config::Forge::None => {
+                return Err(proc_exit::sysexits::CONFIG_ERR
+                    .with_message("no forge configured, set `stack.forge=github`"));
+            }
+            git_stack::config::Forge::Github => {
+                crate::github::Github::new(repo_config.github_token())
+            }
+        };
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+        let graph = git_stack::graph::Graph::from_branches(&repo, branches.all())
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut failed = Vec::new();
+        for (branch_id, branch) in branches
+            .iter()
+            .flat_map(|(id, b)| b.iter().map(move |b| (id, b)))
+        {
+            if branch.kind() == git_stack::graph::BranchKind::Protected {
+                continue;
+            }
+            let Some(name) = branch.local_name() else {
+                continue;
+            };
+            let Some(parent) = crate::ops::parent_branch(&graph, branch_id) else {
+                log::debug!("Skipping `{name}`, no parent branch");
+                continue;
+            };
+
+            let Some(head) = crate::github::Head::new(repo.raw(), repo.push_remote_for(name), name)
+            else {
+                log::debug!("Skipping `{name}`, not pushed");
+                continue;
+            };
+
+            let pull_requests = match forge.open_pull_requests(&head) {
+                Ok(pull_requests) => pull_requests,
+                Err(err) => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: could not look up pull requests for {}: {}",
+                        stderr_palette.error("error"),
+                        stderr_palette.highlight(name),
+                        err
+                    );
+                    failed.push(name.to_owned());
+                    continue;
+                }
+            };
+            for pull_request in pull_requests {
+                if pull_request.base == parent {
+                    log::debug!("#{} already targets `{}`", pull_request.number, parent);
+                    continue;
+                }
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{} #{} ({}) from {} to {}",
+                    stderr_palette.good("Retargeting"),
+                    pull_request.number,
+                    stderr_palette.highlight(name),
+                    pull_request.base,
+                    stderr_palette.highlight(&parent)
+                );
+                if self.dry_run {
+                    continue;
+                }
+                if let Err(err) = forge.set_base(pull_request.number, &parent) {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: could not retarget #{}: {}",
+                        stderr_palette.error("error"),
+                        pull_request.number,
+                        err
+                    );
+                    failed.push(name.to_owned());
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.with_message(format!(
+                "could not retarget pull requests for {}",
+                failed.join(", ")
+            )))
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use std::io::Write;
 
-use itertools::Itertools;
 use proc_exit::prelude::*;
 
 use git_stack::git::Repo;
@@ -9,6 +8,13 @@ use git_stack::git::Repo;
 ///
 /// When you reword a commit that has descendants, those descendants are rebased on top of the
 /// reworded version of the commit.
+///
+/// With `--interactive`, the stack's rebase plan is opened in your editor, like
+/// `git rebase --interactive`, so commits can also be reordered, squashed, or dropped.
+///
+/// With `--file`, many commits are reworded at once from a file with a `<rev> <message>` line per
+/// commit, where `\n` in a message starts a new line.  Every commit is checked before any are
+/// rewritten.
 #[derive(clap::Args)]
 pub(crate) struct RewordArgs {
     /// Commit to rewrite
@@ -19,9 +25,26 @@ pub(crate) struct RewordArgs {
     #[arg(short, long)]
     message: Option<String>,
 
+    /// Edit a todo list for the whole stack to reword, reorder, squash, or drop commits
+    #[arg(short, long, alias = "edit-todo", conflicts_with = "message")]
+    interactive: bool,
+
+    /// Read `<rev> <message>` lines to reword, `-` for stdin
+    #[arg(
+        short = 'F',
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["rev", "message", "interactive"]
+    )]
+    file: Option<std::path::PathBuf>,
+
     /// Don't actually switch
     #[arg(short = 'n', long)]
     dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
 }
 
 impl RewordArgs {
@@ -40,26 +63,28 @@ impl RewordArgs {
 
         let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
         let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
         let mut repo = git_stack::git::GitRepo::new(repo);
 
         let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         repo.set_push_remote(repo_config.push_remote());
         repo.set_pull_remote(repo_config.pull_remote());
-        let config = repo
-            .raw()
-            .config()
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
-        repo.set_sign(
-            config
-                .get_bool("stack.gpgSign")
-                .or_else(|_| config.get_bool("commit.gpgSign"))
-                .unwrap_or_default(),
-        )
-        .with_code(proc_exit::Code::FAILURE)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
 
         let protected = git_stack::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
@@ -69,6 +94,15 @@ impl RewordArgs {
             crate::ops::resolve_explicit_base(&repo, "HEAD").with_code(proc_exit::Code::FAILURE)?;
         let head_branch = head_ann_id.branch.as_ref();
 
+        let rewords = self
+            .file
+            .as_deref()
+            .map(|path| read_rewords(&repo, path))
+            .transpose()?;
+        if rewords.as_ref().is_some_and(|r| r.is_empty()) {
+            return Err(proc_exit::Code::SUCCESS.with_message("Nothing to do."));
+        }
+
         let selected_ann_id = crate::ops::resolve_explicit_base(&repo, &self.rev)
             .with_code(proc_exit::Code::FAILURE)?;
         let selected_id = selected_ann_id.id;
@@ -76,26 +110,42 @@ impl RewordArgs {
             .find_commit(selected_id)
             .expect("resolve found a commit");
         let selected_branch = selected_ann_id.branch.as_ref();
-        let base = crate::ops::resolve_implicit_base(
-            &repo,
-            selected_id,
-            &branches,
-            repo_config.auto_base_commit_count(),
-        );
-        let merge_base_oid = repo
-            .merge_base(base.id, selected_id)
-            .ok_or_else(|| {
-                git2::Error::new(
-                    git2::ErrorCode::NotFound,
-                    git2::ErrorClass::Reference,
-                    format!("could not find base between {base} and HEAD"),
-                )
-            })
-            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let target_ids = match rewords.as_deref() {
+            Some(rewords) => rewords.iter().map(|r| r.id).collect::<Vec<_>>(),
+            None => vec![selected_id],
+        };
+        let mut stack_base_oid: Option<git2::Oid> = None;
+        for &target_id in &target_ids {
+            let base = crate::ops::resolve_implicit_base(
+                &repo,
+                target_id,
+                &branches,
+                repo_config.auto_base_commit_count(),
+            );
+            let merge_base_oid = repo
+                .merge_base(base.id, target_id)
+                .and_then(|oid| match stack_base_oid {
+                    Some(stack_base_oid) => repo.merge_base(stack_base_oid, oid),
+                    None => Some(oid),
+                })
+                .ok_or_else(|| {
+                    git2::Error::new(
+                        git2::ErrorCode::NotFound,
+                        git2::ErrorClass::Reference,
+                        format!("could not find base between {base} and {target_id}"),
+                    )
+                })
+                .with_code(proc_exit::sysexits::USAGE_ERR)?;
+            stack_base_oid = Some(merge_base_oid);
+        }
+        let merge_base_oid = stack_base_oid.expect("always at least one target");
         let stack_branches = branches.descendants(&repo, merge_base_oid);
         let mut graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
             .with_code(proc_exit::Code::FAILURE)?;
         git_stack::graph::protect_branches(&mut graph);
+        let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_ignored(&mut graph, &ignored);
         git_stack::graph::mark_fixup(&mut graph, &repo);
         git_stack::graph::mark_wip(&mut graph, &repo);
 
@@ -112,118 +162,112 @@ impl RewordArgs {
                 return Err(proc_exit::sysexits::USAGE_ERR.with_message(message));
             }
         }
-        let action = graph
-            .commit_get::<git_stack::graph::Action>(selected_id)
-            .copied()
-            .unwrap_or_default();
-        match action {
-            git_stack::graph::Action::Pick => {}
-            git_stack::graph::Action::Fixup => {
-                return Err(proc_exit::Code::FAILURE.with_message("cannot reword fixup commits"));
+
+        if let Some(rewords) = rewords {
+            // Check every commit before rewording any so a bad line leaves the stack untouched
+            let mut errors = Vec::new();
+            for reword in rewords {
+                let result = check_rewordable(&graph, reword.id)
+                    .map_err(eyre::Error::msg)
+                    .and_then(|()| {
+                        git_stack::graph::reword_commit(
+                            &mut graph,
+                            &repo,
+                            reword.id,
+                            reword.message,
+                        )
+                    });
+                if let Err(err) = result {
+                    errors.push(format!("line {}: `{}`: {}", reword.line, reword.rev, err));
+                }
             }
-            git_stack::graph::Action::Protected => {
-                return Err(
-                    proc_exit::Code::FAILURE.with_message("cannot reword protected commits")
-                );
+            if !errors.is_empty() {
+                return Err(proc_exit::Code::FAILURE.with_message(errors.join("\n")));
             }
+        } else {
+            check_rewordable(&graph, selected_id)
+                .map_err(|err| proc_exit::Code::FAILURE.with_message(err))?;
         }
 
-        let new_message = if let Some(message) = self.message.as_deref() {
-            message.trim().to_owned()
-        } else {
-            use std::fmt::Write;
-
-            let raw_commit = repo
-                .raw()
-                .find_commit(head.id)
-                .expect("selected_commit is always valid");
-            let existing = String::from_utf8_lossy(raw_commit.message_bytes());
-            let mut template = String::new();
-            writeln!(&mut template, "{existing}").unwrap();
-            writeln!(&mut template).unwrap();
-            writeln!(
-                &mut template,
-                "# Please enter the commit message for your changes. Lines starting"
-            )
-            .unwrap();
-            writeln!(
-                &mut template,
-                "# with '#' will be ignored, and an empty message aborts the commit."
-            )
-            .unwrap();
-            if let Some(selected_branch) = &selected_branch {
-                writeln!(&mut template, "#").unwrap();
-                writeln!(&mut template, "# On branch {selected_branch}").unwrap();
-            }
-            let message = crate::ops::edit_commit(
-                repo.path()
-                    .ok_or_else(|| eyre::format_err!("no `.git` path found"))
-                    .with_code(proc_exit::Code::FAILURE)?,
-                repo_config.editor(),
-                &template,
-            )
-            .with_code(proc_exit::Code::FAILURE)?;
-            let message = match message {
-                Some(message) => message,
+        let scripts = if self.file.is_some() {
+            git_stack::graph::to_scripts(&graph, vec![])
+        } else if self.interactive {
+            let scripts = git_stack::graph::to_scripts(&graph, vec![]);
+            let edited =
+                crate::ops::edit_todo(&repo, repo_config.editor(), &scripts, Default::default())
+                    .with_code(proc_exit::Code::FAILURE)?;
+            match edited {
+                Some(script) => vec![script],
                 None => {
                     return Err(proc_exit::Code::SUCCESS.with_message("Nothing to do."));
                 }
+            }
+        } else {
+            let new_message = if let Some(message) = self.message.as_deref() {
+                message.trim().to_owned()
+            } else {
+                let raw_commit = repo
+                    .raw()
+                    .find_commit(head.id)
+                    .expect("selected_commit is always valid");
+                let existing = git_stack::git::decode_message(
+                    raw_commit.message_bytes(),
+                    raw_commit.message_encoding(),
+                );
+                let template = crate::ops::commit_template(
+                    repo.raw(),
+                    &existing,
+                    selected_branch.map(|b| b as &dyn std::fmt::Display),
+                    Some((raw_commit.parent_id(0).ok(), head.id)),
+                );
+                let message = crate::ops::edit_commit(
+                    repo.path()
+                        .ok_or_else(|| eyre::format_err!("no `.git` path found"))
+                        .with_code(proc_exit::Code::FAILURE)?,
+                    repo_config.editor(),
+                    &template,
+                )
+                .with_code(proc_exit::Code::FAILURE)?;
+                let message = match message {
+                    Some(message) => message,
+                    None => {
+                        return Err(proc_exit::Code::SUCCESS.with_message("Nothing to do."));
+                    }
+                };
+                message
             };
-            message
-        };
 
-        git_stack::graph::reword_commit(&mut graph, &repo, selected_id, new_message)
-            .with_code(proc_exit::Code::FAILURE)?;
+            git_stack::graph::reword_commit(&mut graph, &repo, selected_id, new_message)
+                .with_code(proc_exit::Code::FAILURE)?;
+            git_stack::graph::to_scripts(&graph, vec![])
+        };
 
         let mut stash_id = None;
         if !self.dry_run {
             stash_id = git_stack::git::stash_push(&mut repo, "reword");
         }
 
-        let mut backed_up = false;
-        {
-            let stash_repo =
-                git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
-            let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
-            let mut snapshots =
-                git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
-            let snapshot_capacity = repo_config.capacity();
-            snapshots.capacity(snapshot_capacity);
-            let snapshot = git_branch_stash::Snapshot::from_repo(&stash_repo)
-                .with_code(proc_exit::Code::FAILURE)?;
-            if !self.dry_run {
-                snapshots.push(snapshot).to_sysexits()?;
-                backed_up = true;
-            }
-        }
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "reword",
+            stash_id,
+            self.dry_run,
+        )?;
 
-        let mut success = true;
-        let scripts = git_stack::graph::to_scripts(&graph, vec![]);
         let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
-        for script in scripts {
-            let results = executor.run(&mut repo, &script);
-            for (err, name, dependents) in results.iter() {
-                success = false;
-                log::error!("Failed to re-stack branch `{}`: {}", name, err);
-                if !dependents.is_empty() {
-                    log::error!("  Blocked dependents: {}", dependents.iter().join(", "));
-                }
-            }
-        }
-        executor
-            .close(&mut repo, head_branch.as_ref().and_then(|b| b.local_name()))
-            .with_code(proc_exit::Code::FAILURE)?;
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            &mut repo,
+            &mut executor,
+            scripts,
+            head_branch.as_ref().and_then(|b| b.local_name()),
+            stash_id,
+        )?;
 
         git_stack::git::stash_pop(&mut repo, stash_id);
         if backed_up {
-            anstream::eprintln!(
-                "{}: to undo, run {}",
-                stderr_palette.info("note"),
-                stderr_palette.highlight(format_args!(
-                    "`git branch-stash pop {}`",
-                    crate::ops::STASH_STACK_NAME
-                ))
-            );
+            crate::ops::note_undo();
         }
 
         if success {
@@ -233,3 +277,144 @@ impl RewordArgs {
         }
     }
 }
+
+fn check_rewordable(graph: &git_stack::graph::Graph, id: git2::Oid) -> Result<(), &'static str> {
+    let action = graph
+        .commit_get::<git_stack::graph::Action>(id)
+        .copied()
+        .unwrap_or_default();
+    match action {
+        git_stack::graph::Action::Pick => Ok(()),
+        git_stack::graph::Action::Fixup => Err("cannot reword fixup commits"),
+        git_stack::graph::Action::Protected => Err("cannot reword protected commits"),
+    }
+}
+
+/// A commit to reword from `--file`
+struct Reword {
+    line: usize,
+    rev: String,
+    id: git2::Oid,
+    message: String,
+}
+
+/// Read and resolve the `--file` map, reporting every bad line at once
+fn read_rewords(
+    repo: &git_stack::git::GitRepo,
+    path: &std::path::Path,
+) -> Result<Vec<Reword>, proc_exit::Exit> {
+    let content = if path == std::path::Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    }
+    .map_err(|err| {
+        proc_exit::sysexits::USAGE_ERR.with_message(format!(
+            "could not read `{}`: {}",
+            path.display(),
+            err
+        ))
+    })?;
+
+    let mut errors = Vec::new();
+    let mut rewords: Vec<Reword> = Vec::new();
+    for entry in parse_rewords(&content) {
+        let (line, rev, message) = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        let id = match crate::ops::resolve_rev(repo.raw(), rev) {
+            Ok(resolved) => resolved.id,
+            Err(err) => {
+                errors.push(format!("line {line}: {err}"));
+                continue;
+            }
+        };
+        if let Some(existing) = rewords.iter().find(|r| r.id == id) {
+            errors.push(format!(
+                "line {line}: `{rev}` is the same commit as `{}` on line {}",
+                existing.rev, existing.line
+            ));
+            continue;
+        }
+        rewords.push(Reword {
+            line,
+            rev: rev.to_owned(),
+            id,
+            message,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(rewords)
+    } else {
+        Err(proc_exit::sysexits::USAGE_ERR.with_message(errors.join("\n")))
+    }
+}
+
+/// Parse `<rev> <message>` lines, skipping blank lines and `#` comments
+///
+/// In messages, `\n` is a line break and `\\` a backslash.
+fn parse_rewords(content: &str) -> impl Iterator<Item = Result<(usize, &str, String), String>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_num, line)| {
+            let (rev, message) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let message = unescape_message(message.trim());
+            if message.is_empty() {
+                return Err(format!("line {line_num}: missing message for `{rev}`"));
+            }
+            Ok((line_num, rev, message))
+        })
+}
+
+fn unescape_message(escaped: &str) -> String {
+    let mut message = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => message.push('\n'),
+                Some(other) => message.push(other),
+                None => message.push('\\'),
+            }
+        } else {
+            message.push(c);
+        }
+    }
+    message.trim().to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_map() {
+        let content = "# release notes
+abc123 feat: Add widgets
+
+feature~2   fix: Handle empty input\\n\\nCloses #12
+main
+";
+        let rewords = parse_rewords(content).collect::<Vec<_>>();
+        assert_eq!(
+            rewords,
+            vec![
+                Ok((2, "abc123", "feat: Add widgets".to_owned())),
+                Ok((
+                    4,
+                    "feature~2",
+                    "fix: Handle empty input\n\nCloses #12".to_owned()
+                )),
+                Err("line 5: missing message for `main`".to_owned()),
+            ]
+        );
+    }
+}
@@ -11,16 +11,33 @@ pub(crate) struct RunArgs {
     /// Keep going on failure
     #[arg(long, alias = "no-ff")]
     no_fail_fast: bool,
-    #[arg(long, alias = "ff", hide = true, overrides_with = "no_fail_fast")]
+    /// Skip what is stacked on a failure (default)
+    #[arg(long, alias = "ff", overrides_with = "no_fail_fast")]
     fail_fast: bool,
 
+    /// Only run at this branch or commit and what is stacked on it
+    #[arg(long, value_name = "BRANCH|COMMIT")]
+    from: Option<String>,
+
+    /// Only run at this branch or commit and what it is stacked on
+    #[arg(long, value_name = "BRANCH|COMMIT")]
+    to: Option<String>,
+
     /// Switch to the first commit that failed
     #[arg(short, long)]
     switch: bool,
 
+    /// Only run at branches, bottom-to-top, reporting each branch's result at the end
+    #[arg(short, long)]
+    branch: bool,
+
     /// Don't actually switch
     #[arg(short = 'n', long)]
     dry_run: bool,
+
+    /// Write the results to stdout as JSON
+    #[arg(long)]
+    json: bool,
 }
 
 impl RunArgs {
@@ -35,19 +52,28 @@ impl RunArgs {
     }
 
     pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        // Commits are only checked out while running, unless leaving the user at a failure
+        crate::lock::require_writable(self.dry_run || !self.switch)?;
         let stderr_palette = crate::ops::Palette::colored();
 
         let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
         let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
         let mut repo = git_stack::git::GitRepo::new(repo);
 
         let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         repo.set_push_remote(repo_config.push_remote());
         repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
 
         let protected = git_stack::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
@@ -107,11 +133,41 @@ impl RunArgs {
         let graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
             .with_code(proc_exit::Code::FAILURE)?;
 
+        let from_id = self
+            .from
+            .as_deref()
+            .map(|rev| resolve_in_stack(&repo, &graph, rev))
+            .transpose()?;
+        let to_id = self
+            .to
+            .as_deref()
+            .map(|rev| resolve_in_stack(&repo, &graph, rev))
+            .transpose()?;
+        let selected = graph
+            .descendants_of(merge_base_oid)
+            .filter(|id| !self.branch || has_development_branch(&graph, *id))
+            .filter(|id| {
+                from_id
+                    .map(|from_id| repo.contains_commit(*id, from_id).unwrap_or(false))
+                    .unwrap_or(true)
+            })
+            .filter(|id| {
+                to_id
+                    .map(|to_id| repo.contains_commit(to_id, *id).unwrap_or(false))
+                    .unwrap_or(true)
+            })
+            .collect::<std::collections::HashSet<_>>();
+
         let mut first_failure = None;
+        let mut results = Vec::new();
+        let mut skipped = Vec::new();
 
         let mut success = true;
         let mut cursor = graph.descendants_of(merge_base_oid).into_cursor();
         while let Some(current_id) = cursor.next(&graph) {
+            if !selected.contains(&current_id) {
+                continue;
+            }
             let current_commit = repo
                 .find_commit(current_id)
                 .expect("children/head are always present");
@@ -120,15 +176,18 @@ impl RunArgs {
                 "{} to {}: {}",
                 stderr_palette.good("Switching"),
                 stderr_palette.highlight(crate::ops::render_id(&repo, &branches, current_id)),
-                stderr_palette.hint(&current_commit.summary)
+                stderr_palette.hint(current_commit.display_summary())
             );
             if !self.dry_run {
                 repo.switch_commit(current_id)
                     .with_code(proc_exit::Code::FAILURE)?;
             }
+            let start = std::time::Instant::now();
             let status = std::process::Command::new(&self.command[0])
                 .args(&self.command[1..])
                 .status();
+            let duration = start.elapsed();
+            let exit_code = status.as_ref().ok().and_then(|s| s.code());
             let mut current_success = true;
             match status {
                 Ok(status) if status.success() => {
@@ -175,14 +234,68 @@ impl RunArgs {
                     current_success = false;
                 }
             }
+            let status = if current_success {
+                Status::Passed
+            } else {
+                Status::Failed
+            };
+            results.push(RunResult {
+                id: current_id,
+                status,
+                duration: Some(duration),
+                exit_code,
+            });
             if !current_success {
                 first_failure.get_or_insert(current_id);
                 if self.fail_fast() {
                     cursor.stop();
+                    skipped.extend(
+                        graph
+                            .descendants_of(current_id)
+                            .filter(|id| *id != current_id && selected.contains(id)),
+                    );
                 }
                 success = false;
             }
         }
+        for id in skipped {
+            if results.iter().all(|r| r.id != id) {
+                results.push(RunResult {
+                    id,
+                    status: Status::Skipped,
+                    duration: None,
+                    exit_code: None,
+                });
+            }
+        }
+
+        for result in &results {
+            let status = format!("{:<7}", result.status);
+            let status = match result.status {
+                Status::Passed => stderr_palette.good(status),
+                Status::Failed => stderr_palette.error(status),
+                Status::Skipped => stderr_palette.warn(status),
+            };
+            let duration = result
+                .duration
+                .map(|d| format!("{:.2}s", d.as_secs_f64()))
+                .unwrap_or_default();
+            let _ = writeln!(
+                anstream::stderr(),
+                "  {} {:>8}  {}",
+                status,
+                duration,
+                stderr_palette.highlight(crate::ops::render_id(&repo, &branches, result.id))
+            );
+        }
+        if self.json {
+            let json = results
+                .iter()
+                .map(|r| r.to_json(&repo, &graph))
+                .collect::<Vec<_>>();
+            let json = serde_json::to_string_pretty(&json).with_code(proc_exit::Code::FAILURE)?;
+            let _ = writeln!(anstream::stdout(), "{json}");
+        }
 
         if !success && self.switch && first_failure != Some(head_id) {
             assert!(
@@ -244,6 +357,89 @@ impl RunArgs {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Status {
+    Passed,
+    Failed,
+    /// Stacked on a failure with `--fail-fast`
+    Skipped,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        };
+        // Pass through `{:<7}` and friends
+        status.fmt(f)
+    }
+}
+
+struct RunResult {
+    id: git2::Oid,
+    status: Status,
+    duration: Option<std::time::Duration>,
+    exit_code: Option<i32>,
+}
+
+impl RunResult {
+    fn to_json(
+        &self,
+        repo: &git_stack::git::GitRepo,
+        graph: &git_stack::graph::Graph,
+    ) -> serde_json::Value {
+        let branches = graph
+            .branches
+            .get(self.id)
+            .into_iter()
+            .flatten()
+            .map(|b| b.display_name().to_string())
+            .collect::<Vec<_>>();
+        let summary = repo
+            .find_commit(self.id)
+            .map(|c| c.summary.to_string())
+            .unwrap_or_default();
+        serde_json::json!({
+            "commit": self.id.to_string(),
+            "branches": branches,
+            "summary": summary,
+            "status": self.status.to_string(),
+            "duration_secs": self.duration.map(|d| d.as_secs_f64()),
+            "exit_code": self.exit_code,
+        })
+    }
+}
+
+/// Only commits in the stack being run make sense as end points
+fn resolve_in_stack(
+    repo: &git_stack::git::GitRepo,
+    graph: &git_stack::graph::Graph,
+    rev: &str,
+) -> Result<git2::Oid, proc_exit::Exit> {
+    let id = repo
+        .resolve(rev)
+        .ok_or_else(|| {
+            proc_exit::sysexits::USAGE_ERR.with_message(format!("could not find `{rev}`"))
+        })?
+        .id;
+    if !graph.contains_id(id) {
+        return Err(proc_exit::sysexits::USAGE_ERR
+            .with_message(format!("`{rev}` is not in the current stack")));
+    }
+    Ok(id)
+}
+
+fn has_development_branch(graph: &git_stack::graph::Graph, id: git2::Oid) -> bool {
+    graph
+        .branches
+        .get(id)
+        .into_iter()
+        .flatten()
+        .any(|b| b.kind() != git_stack::graph::BranchKind::Protected)
+}
+
 fn resolve_bool_arg(yes: bool, no: bool) -> Option<bool> {
     match (yes, no) {
         (true, false) => Some(true),
@@ -0,0 +1,160 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Select the stack later commands work on, or show the selection
+///
+/// `git stack`, including `--push`, and `git stack next` use the selection when `--base`,
+/// `--onto`, or `--stack` aren't given.  The selection is kept per worktree.
+#[derive(clap::Args)]
+pub(crate) struct SelectArgs {
+    /// Branch or other revision to evaluate from, like `origin/HEAD` or `@{u}`
+    #[arg(long, value_name = "REV")]
+    base: Option<String>,
+
+    /// Branch or other revision to rebase onto
+    #[arg(long, value_name = "REV")]
+    onto: Option<String>,
+
+    /// Which branch stacks to include: current, dependents, descendants, all, or a named stack
+    #[arg(short, long, value_name = "STACK", value_parser = crate::args::StackArgParser)]
+    stack: Option<crate::args::StackArg>,
+
+    /// Forget the selection, going back to the defaults
+    #[arg(long, conflicts_with_all = ["base", "onto", "stack"])]
+    clear: bool,
+}
+
+impl SelectArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        if self.clear {
+            if git_stack::git::StackSelection::clear(&repo).with_code(proc_exit::Code::FAILURE)? {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}",
+                    stderr_palette.good("Cleared the selected stack")
+                );
+            } else {
+                log::warn!("No stack is selected");
+            }
+            return Ok(());
+        }
+
+        let selection = git_stack::git::StackSelection {
+            base: self.base.clone(),
+            onto: self.onto.clone(),
+            stack: self.stack.as_ref().map(|stack| stack.to_string()),
+        };
+        if selection.is_empty() {
+            let selection = git_stack::git::StackSelection::load(&repo)
+                .with_code(proc_exit::sysexits::CONFIG_ERR)?
+                .filter(|selection| !selection.is_empty());
+            match selection {
+                Some(selection) => {
+                    let _ = writeln!(anstream::stdout(), "{selection}");
+                    if let Err(err) = validate(&repo, &selection) {
+                        log::warn!("{err}");
+                    }
+                }
+                None => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: no stack is selected",
+                        stderr_palette.info("note"),
+                    );
+                }
+            }
+            return Ok(());
+        }
+
+        validate(&repo, &selection).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        selection.save(&repo).with_code(proc_exit::Code::FAILURE)?;
+        let _ = writeln!(
+            anstream::stderr(),
+            "{} {}",
+            stderr_palette.good("Selected"),
+            stderr_palette.highlight(&selection),
+        );
+
+        Ok(())
+    }
+}
+
+/// Load the selected stack, checking it still names revisions and stacks that exist
+pub(crate) fn load(repo: &git2::Repository) -> Result<Selection, proc_exit::Exit> {
+    let Some(selection) = git_stack::git::StackSelection::load(repo)
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?
+        .filter(|selection| !selection.is_empty())
+    else {
+        return Ok(Selection::default());
+    };
+    validate(repo, &selection).map_err(|err| {
+        proc_exit::sysexits::USAGE_ERR.with_message(format!(
+            "selected stack is invalid, {err}; update it with `git stack select` or reset it with `git stack select --clear`"
+        ))
+    })?;
+    log::debug!("Using selected stack {selection}");
+    let stack = selection.stack.as_deref().map(|stack| {
+        let Ok(stack) = stack.parse();
+        stack
+    });
+    Ok(Selection {
+        base: selection.base,
+        onto: selection.onto,
+        stack,
+    })
+}
+
+fn validate(
+    repo: &git2::Repository,
+    selection: &git_stack::git::StackSelection,
+) -> eyre::Result<()> {
+    for rev in [&selection.base, &selection.onto].into_iter().flatten() {
+        crate::ops::resolve_rev(repo, rev)?;
+    }
+    if let Some(name) = selection.stack.as_deref() {
+        let Ok(stack) = name.parse::<crate::args::StackArg>();
+        if let Some(name) = stack.named() {
+            let named_stacks = git_stack::config::NamedStacks::from_repo(repo)?;
+            if !named_stacks.contains(name) {
+                eyre::bail!("no stack named `{}`", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The selected stack, to fall back to when `--base`, `--onto`, and `--stack` aren't given
+#[derive(Clone, Default, Debug)]
+pub(crate) struct Selection {
+    pub(crate) base: Option<String>,
+    pub(crate) onto: Option<String>,
+    pub(crate) stack: Option<crate::args::StackArg>,
+}
+
+impl Selection {
+    /// The `--base` and `--onto` to use, only falling back to the selection when neither is given
+    pub(crate) fn base_onto<'s>(
+        &'s self,
+        base: Option<&'s str>,
+        onto: Option<&'s str>,
+    ) -> (Option<&'s str>, Option<&'s str>) {
+        if base.is_some() || onto.is_some() {
+            (base, onto)
+        } else {
+            (self.base.as_deref(), self.onto.as_deref())
+        }
+    }
+
+    pub(crate) fn stack<'s>(
+        &'s self,
+        stack: Option<&'s crate::args::StackArg>,
+    ) -> Option<&'s crate::args::StackArg> {
+        stack.or(self.stack.as_ref())
+    }
+}
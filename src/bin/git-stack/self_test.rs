@@ -0,0 +1,205 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Rehearse a `git stack` operation in a scratch clone before applying it
+///
+/// The branches are copied into a temporary clone where the operation is run first.  Only if that
+/// succeeds is the operation run against this repository.
+#[derive(clap::Args)]
+pub(crate) struct SelfTestArgs {
+    /// Arguments for the `git stack` operation to rehearse
+    #[arg(
+        value_name = "ARG",
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        default_value = "--rebase"
+    )]
+    args: Vec<std::ffi::OsString>,
+
+    /// Only rehearse, don't apply to this repository
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl SelfTestArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        if self.args.iter().any(|a| a == "--push") {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message("`--push` cannot be rehearsed, push after the operation succeeds"));
+        }
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        if repo.state() != git2::RepositoryState::Clean {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message(format!("cannot rehearse, {:?} in progress", repo.state())));
+        }
+        let exe = std::env::current_exe().with_code(proc_exit::Code::FAILURE)?;
+
+        let scratch = tempfile::Builder::new()
+            .prefix("git-stack-self-test-")
+            .tempdir()
+            .with_code(proc_exit::Code::FAILURE)?;
+        let rehearsal = rehearse(&repo, &exe, scratch.path(), &self.args);
+        if let Err(err) = scratch.close() {
+            log::debug!("Could not clean up rehearsal: {}", err);
+        }
+        match rehearsal {
+            Ok(true) => {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: rehearsal succeeded",
+                    stderr_palette.good("Success")
+                );
+            }
+            Ok(false) => {
+                return Err(proc_exit::Code::FAILURE
+                    .with_message("rehearsal failed, leaving this repository untouched"));
+            }
+            Err(err) => {
+                return Err(
+                    proc_exit::Code::FAILURE.with_message(format!("could not rehearse: {err}"))
+                );
+            }
+        }
+
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let status = std::process::Command::new(&exe)
+            .args(&self.args)
+            .status()
+            .with_code(proc_exit::Code::FAILURE)?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.as_exit())
+        }
+    }
+}
+
+/// Run `git stack` in a clone of `repo`, reporting whether it succeeded
+fn rehearse(
+    repo: &git2::Repository,
+    exe: &std::path::Path,
+    scratch: &std::path::Path,
+    args: &[std::ffi::OsString],
+) -> eyre::Result<bool> {
+    let source = repo.path();
+    log::debug!("Cloning {} into {}", source.display(), scratch.display());
+    git(
+        None,
+        &[
+            "clone".as_ref(),
+            "--quiet".as_ref(),
+            "--shared".as_ref(),
+            "--no-checkout".as_ref(),
+            source.as_os_str(),
+            scratch.as_os_str(),
+        ],
+    )?;
+    // The clone's own `origin/HEAD` points at our checked out branch, protecting it
+    git(
+        Some(scratch),
+        &[
+            "update-ref".as_ref(),
+            "--no-deref".as_ref(),
+            "-d".as_ref(),
+            "refs/remotes/origin/HEAD".as_ref(),
+        ],
+    )?;
+    // Mirror the branches as-is so protected branches and upstreams match
+    std::fs::copy(source.join("config"), scratch.join(".git").join("config"))?;
+    isolate(scratch)?;
+    git(
+        Some(scratch),
+        &[
+            "fetch".as_ref(),
+            "--quiet".as_ref(),
+            "--no-tags".as_ref(),
+            "--update-head-ok".as_ref(),
+            source.as_os_str(),
+            "+refs/heads/*:refs/heads/*".as_ref(),
+            "+refs/remotes/*:refs/remotes/*".as_ref(),
+        ],
+    )?;
+    let head = repo.head()?;
+    let head = if head.is_branch() {
+        head.shorthand().unwrap_or("HEAD").to_owned()
+    } else {
+        head.peel_to_commit()?.id().to_string()
+    };
+    git(
+        Some(scratch),
+        &["checkout".as_ref(), "--quiet".as_ref(), head.as_ref()],
+    )?;
+
+    let status = std::process::Command::new(exe)
+        .args(args)
+        .current_dir(scratch)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .stdin(std::process::Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Ok(false);
+    }
+
+    let scratch_repo = git2::Repository::open(scratch)?;
+    Ok(scratch_repo.state() == git2::RepositoryState::Clean)
+}
+
+/// Keep the rehearsal from reaching the real remotes or forge
+///
+/// Fetching is harmless but every push is pointed at a path that doesn't exist.
+fn isolate(scratch: &std::path::Path) -> eyre::Result<()> {
+    let scratch_repo = git2::Repository::open(scratch)?;
+    let mut config = scratch_repo
+        .config()?
+        .open_level(git2::ConfigLevel::Local)?;
+    let no_push = scratch.join(".git").join("no-push");
+    let no_push = no_push
+        .to_str()
+        .ok_or_else(|| eyre::eyre!("non-UTF-8 path `{}`", scratch.display()))?;
+    for remote in scratch_repo.remotes()?.iter().flatten() {
+        let key = format!("remote.{remote}.pushurl");
+        config
+            .remove_multivar(&key, ".*")
+            .or_else(ignore_not_found)?;
+        config.set_str(&key, no_push)?;
+    }
+    // Override rather than remove, in case it is set globally
+    config
+        .remove_multivar("stack.forge", ".*")
+        .or_else(ignore_not_found)?;
+    config.set_str("stack.forge", "none")?;
+    Ok(())
+}
+
+fn ignore_not_found(err: git2::Error) -> Result<(), git2::Error> {
+    if err.code() == git2::ErrorCode::NotFound {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+fn git(cwd: Option<&std::path::Path>, args: &[&std::ffi::OsStr]) -> eyre::Result<()> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(args)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE");
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
+    log::trace!("{:?}", cmd);
+    let status = cmd.status()?;
+    if !status.success() {
+        eyre::bail!("`{:?}` failed", cmd);
+    }
+    Ok(())
+}
@@ -0,0 +1,338 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Inspect the undo snapshots
+///
+/// Lists the snapshots taken before rewriting branches, most recent first.  A snapshot is
+/// selected by its index in that list (`0` being the most recent), its path, or text from its
+/// message.
+#[derive(clap::Args)]
+pub(crate) struct SnapshotArgs {
+    /// Show which branches moved, were created, or were deleted since the snapshot
+    #[arg(long, value_name = "SNAPSHOT")]
+    diff: Option<String>,
+
+    /// Restore the branches and `HEAD` to the snapshot, picking one when attached to a terminal
+    #[arg(long, value_name = "SNAPSHOT", num_args = 0..=1, conflicts_with = "diff")]
+    apply: Option<Option<String>>,
+}
+
+impl SnapshotArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let mut stash_repo = git_branch_stash::GitRepo::new(repo);
+        let snapshots = git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
+
+        if let Some(selector) = self.apply.as_ref() {
+            crate::lock::require_writable(false)?;
+            let path = match selector.as_deref() {
+                Some(selector) => find_snapshot(&snapshots, selector)?,
+                None => pick_snapshot(&snapshots)?,
+            };
+            apply(&cwd, &mut stash_repo, &path)
+        } else if let Some(selector) = self.diff.as_deref() {
+            let path = find_snapshot(&snapshots, selector)?;
+            let snapshot =
+                git_branch_stash::Snapshot::load(&path).with_code(proc_exit::Code::FAILURE)?;
+            diff(stash_repo.raw(), &snapshot)
+        } else {
+            list(&snapshots)
+        }
+    }
+}
+
+fn list(snapshots: &git_branch_stash::Stack) -> proc_exit::ExitResult {
+    let now = std::time::SystemTime::now();
+    let mut stdout = anstream::stdout().lock();
+    for (index, path) in snapshots.iter().rev().enumerate() {
+        if let Some(line) = describe(index, &path, now) {
+            let _ = writeln!(stdout, "{line}");
+        }
+    }
+    Ok(())
+}
+
+/// Summarize a snapshot for [`list`], `None` if it can't be read
+fn describe(index: usize, path: &std::path::Path, now: std::time::SystemTime) -> Option<String> {
+    let snapshot = match git_branch_stash::Snapshot::load(path) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            log::debug!("Skipping {}: {}", path.display(), err);
+            return None;
+        }
+    };
+    let age = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .map(|age| std::time::Duration::from_secs(age.as_secs()));
+    let mut line = format!(
+        "{index}: {} {}",
+        snapshot.branches.len(),
+        if snapshot.branches.len() == 1 {
+            "branch"
+        } else {
+            "branches"
+        }
+    );
+    if let Some(head) = git_stack::git::HeadState::from_metadata(&snapshot.metadata) {
+        match head.branch.as_deref() {
+            Some(branch) => line.push_str(&format!(", on `{branch}`")),
+            None => line.push_str(&format!(", detached at {}", &head.id.to_string()[..7])),
+        }
+    }
+    if let Some(age) = age {
+        line.push_str(&format!(", {} ago", humantime::format_duration(age)));
+    }
+    if let Some(message) = snapshot_message(&snapshot) {
+        line.push_str(&format!(" ({message})"));
+    }
+    Some(line)
+}
+
+fn snapshot_message(snapshot: &git_branch_stash::Snapshot) -> Option<&str> {
+    snapshot.metadata.get("message").and_then(|m| m.as_str())
+}
+
+/// Resolve a `SNAPSHOT` argument to the snapshot's path
+pub(crate) fn find_snapshot(
+    snapshots: &git_branch_stash::Stack,
+    selector: &str,
+) -> Result<std::path::PathBuf, proc_exit::Exit> {
+    if let Ok(index) = selector.parse::<usize>() {
+        return snapshots.iter().rev().nth(index).ok_or_else(|| {
+            proc_exit::sysexits::USAGE_ERR.with_message(format!("no snapshot at index {index}"))
+        });
+    }
+    let path = std::path::Path::new(selector);
+    if path.is_file() {
+        return Ok(path.to_owned());
+    }
+    snapshots
+        .iter()
+        .rev()
+        .find(|path| {
+            git_branch_stash::Snapshot::load(path).is_ok_and(|snapshot| {
+                snapshot_message(&snapshot).is_some_and(|message| message.contains(selector))
+            })
+        })
+        .ok_or_else(|| {
+            proc_exit::sysexits::USAGE_ERR.with_message(format!("no snapshot `{selector}`"))
+        })
+}
+
+/// Ask which snapshot to use when attached to a terminal, otherwise take the most recent
+fn pick_snapshot(
+    snapshots: &git_branch_stash::Stack,
+) -> Result<std::path::PathBuf, proc_exit::Exit> {
+    use std::io::IsTerminal as _;
+
+    let paths: Vec<_> = snapshots.iter().rev().collect();
+    if paths.is_empty() {
+        return Err(proc_exit::sysexits::USAGE_ERR.with_message("no snapshots"));
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok(paths[0].clone());
+    }
+
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+    let now = std::time::SystemTime::now();
+    let _ = writeln!(stderr, "Which snapshot?");
+    for (index, path) in paths.iter().enumerate() {
+        if let Some(line) = describe(index, path, now) {
+            let default = if index == 0 { " (default)" } else { "" };
+            let _ = writeln!(stderr, "  {}{}", line, stderr_palette.hint(default));
+        }
+    }
+    loop {
+        let _ = write!(stderr, "> ");
+        let _ = stderr.flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return Ok(paths[0].clone());
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(paths[0].clone());
+        }
+        match answer.parse::<usize>() {
+            Ok(index) if index < paths.len() => return Ok(paths[index].clone()),
+            _ => {
+                let _ = writeln!(stderr, "Pick a number from 0 to {}", paths.len() - 1);
+            }
+        }
+    }
+}
+
+/// Move the branches back to where the snapshot recorded them, then `HEAD`
+///
+/// The current branches are snapshotted first so this can be undone in turn.
+fn apply(
+    cwd: &std::path::Path,
+    stash_repo: &mut git_branch_stash::GitRepo,
+    path: &std::path::Path,
+) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+
+    let snapshot = git_branch_stash::Snapshot::load(path).with_code(proc_exit::Code::FAILURE)?;
+    let repo =
+        git2::Repository::open(stash_repo.raw().path()).with_code(proc_exit::Code::FAILURE)?;
+    let mut repo = git_stack::git::GitRepo::new(repo);
+    if repo.is_dirty() {
+        return Err(proc_exit::sysexits::USAGE_ERR
+            .with_message("cannot apply a snapshot with uncommitted changes"));
+    }
+
+    let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    crate::ops::backup_branches(
+        cwd,
+        crate::ops::SnapshotRetention::from_config(&repo_config),
+        "snapshot --apply",
+        None,
+        false,
+    )?;
+
+    snapshot
+        .apply(stash_repo)
+        .with_code(proc_exit::Code::FAILURE)?;
+    if let Some(head) = git_stack::git::HeadState::from_metadata(&snapshot.metadata) {
+        head.restore(&mut repo)
+            .with_code(proc_exit::Code::FAILURE)?;
+    }
+
+    let _ = writeln!(
+        anstream::stderr(),
+        "{} {} {}",
+        stderr_palette.good("Restored"),
+        snapshot.branches.len(),
+        if snapshot.branches.len() == 1 {
+            "branch"
+        } else {
+            "branches"
+        }
+    );
+    Ok(())
+}
+
+/// Resolve a `--as-of` argument, either a `SNAPSHOT` or a time to take the latest snapshot before
+///
+/// Times are either ago, like `2h`, or a date, like `2024-01-31 12:00:00` (UTC).
+pub(crate) fn find_snapshot_as_of(
+    repo: &git2::Repository,
+    value: &str,
+) -> Result<git_branch_stash::Snapshot, proc_exit::Exit> {
+    let stash_repo = git2::Repository::open(repo.path()).with_code(proc_exit::Code::FAILURE)?;
+    let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
+    let snapshots = git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
+
+    let path = if value.parse::<usize>().is_ok() || std::path::Path::new(value).is_file() {
+        find_snapshot(&snapshots, value)?
+    } else {
+        let time = humantime::parse_duration(value)
+            .ok()
+            .and_then(|ago| std::time::SystemTime::now().checked_sub(ago))
+            .or_else(|| humantime::parse_rfc3339_weak(value).ok())
+            .ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("expected a snapshot or time, got `{value}`"))
+            })?;
+        snapshots
+            .iter()
+            .rev()
+            .find(|path| {
+                std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| modified <= time)
+            })
+            .ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("no snapshot from before `{value}`"))
+            })?
+    };
+    log::debug!("Showing branches as of {}", path.display());
+    git_branch_stash::Snapshot::load(&path).with_code(proc_exit::Code::FAILURE)
+}
+
+fn diff(repo: &git2::Repository, snapshot: &git_branch_stash::Snapshot) -> proc_exit::ExitResult {
+    let old: std::collections::BTreeMap<&str, git2::Oid> = snapshot
+        .branches
+        .iter()
+        .map(|b| (b.name.as_str(), b.id))
+        .collect();
+    let mut current = std::collections::BTreeMap::new();
+    for branch in repo
+        .branches(Some(git2::BranchType::Local))
+        .with_code(proc_exit::Code::FAILURE)?
+    {
+        let (branch, _) = branch.with_code(proc_exit::Code::FAILURE)?;
+        let Some(name) = branch.name().ok().flatten() else {
+            continue;
+        };
+        let Some(id) = branch.get().target() else {
+            continue;
+        };
+        current.insert(name.to_owned(), id);
+    }
+
+    let mut stdout = anstream::stdout().lock();
+    let names: std::collections::BTreeSet<&str> = old
+        .keys()
+        .copied()
+        .chain(current.keys().map(|n| n.as_str()))
+        .collect();
+    for name in names {
+        match (old.get(name), current.get(name)) {
+            (Some(old_id), Some(new_id)) if old_id == new_id => {}
+            (Some(old_id), Some(new_id)) => {
+                let counts = match repo.graph_ahead_behind(*new_id, *old_id) {
+                    Ok((ahead, behind)) => format!(" (+{ahead} -{behind})"),
+                    Err(err) => {
+                        log::debug!("Could not compare {} to {}: {}", new_id, old_id, err);
+                        String::new()
+                    }
+                };
+                let _ = writeln!(
+                    stdout,
+                    "moved {name} {} -> {}{counts}",
+                    &old_id.to_string()[..7],
+                    &new_id.to_string()[..7],
+                );
+            }
+            (Some(old_id), None) => {
+                let _ = writeln!(stdout, "deleted {name} {}", &old_id.to_string()[..7]);
+            }
+            (None, Some(new_id)) => {
+                let _ = writeln!(stdout, "created {name} {}", &new_id.to_string()[..7]);
+            }
+            (None, None) => unreachable!("names come from either side"),
+        }
+    }
+
+    if let Some(head) = git_stack::git::HeadState::from_metadata(&snapshot.metadata) {
+        if let Ok(current_head) = git_stack::git::HeadState::from_repo(repo) {
+            if head.branch != current_head.branch
+                || (head.branch.is_none() && head.id != current_head.id)
+            {
+                let _ = writeln!(
+                    stdout,
+                    "HEAD {} -> {}",
+                    describe_head(&head),
+                    describe_head(&current_head)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_head(head: &git_stack::git::HeadState) -> String {
+    match head.branch.as_deref() {
+        Some(branch) => branch.to_owned(),
+        None => head.id.to_string()[..7].to_owned(),
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Squash a branch down to one commit
+///
+/// The commits unique to the branch, back to its parent branch or the protected base, are
+/// combined into one.  Branches stacked on it are rebased on top of the squashed commit.
+#[derive(clap::Args)]
+pub(crate) struct SquashArgs {
+    /// Branch to squash, defaulting to the current branch
+    branch: Option<String>,
+
+    /// Commit message, instead of combining the messages of the squashed commits
+    #[arg(short, long)]
+    message: Option<String>,
+
+    /// Don't actually switch
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
+}
+
+impl SquashArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let head_branch = repo.head_branch();
+        let selected_branch = match self.branch.as_deref() {
+            Some(name) => repo.find_local_branch(name).ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR.with_message(format!("no branch `{name}`"))
+            })?,
+            None => head_branch.clone().ok_or_else(|| {
+                proc_exit::sysexits::USAGE_ERR
+                    .with_message("HEAD is detached, pass the branch to squash")
+            })?,
+        };
+        let selected_id = selected_branch.id;
+        let base = crate::ops::resolve_implicit_base(
+            &repo,
+            selected_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let merge_base_oid = repo
+            .merge_base(base.id, selected_id)
+            .ok_or_else(|| {
+                git2::Error::new(
+                    git2::ErrorCode::NotFound,
+                    git2::ErrorClass::Reference,
+                    format!("could not find base between {base} and {selected_branch}"),
+                )
+            })
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_branches = branches.descendants(&repo, merge_base_oid);
+        let mut graph = git_stack::graph::Graph::from_branches(&repo, stack_branches)
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::protect_branches(&mut graph);
+        let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_ignored(&mut graph, &ignored);
+        git_stack::graph::mark_fixup(&mut graph, &repo);
+        git_stack::graph::mark_wip(&mut graph, &repo);
+        let anchors =
+            git_stack::git::Anchors::from_repo(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        git_stack::graph::mark_anchors(&mut graph, &anchors);
+
+        if repo.raw().state() != git2::RepositoryState::Clean {
+            let message = format!("cannot walk commits, {:?} in progress", repo.raw().state());
+            if self.dry_run {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: {}",
+                    stderr_palette.error("error"),
+                    message
+                );
+            } else {
+                return Err(proc_exit::sysexits::USAGE_ERR.with_message(message));
+            }
+        }
+        let action = graph
+            .commit_get::<git_stack::graph::Action>(selected_id)
+            .copied()
+            .unwrap_or_default();
+        if action.is_protected() {
+            return Err(proc_exit::Code::FAILURE.with_message(format!(
+                "cannot squash protected branch `{selected_branch}`"
+            )));
+        }
+
+        let squashed = git_stack::graph::squash_branch(&mut graph, selected_id)
+            .with_code(proc_exit::Code::FAILURE)?;
+        if squashed.len() < 2 {
+            return Err(proc_exit::Code::SUCCESS.with_message("Nothing to squash."));
+        }
+        if let Some(anchor_id) = squashed.iter().find(|id| anchors.contains(**id)) {
+            return Err(proc_exit::Code::FAILURE.with_message(format!(
+                "cannot squash anchor {anchor_id}, run `git stack anchor --remove` first"
+            )));
+        }
+
+        let new_message = if let Some(message) = self.message.as_deref() {
+            message.trim().to_owned()
+        } else {
+            let mut messages = Vec::with_capacity(squashed.len());
+            for id in &squashed {
+                let raw_commit = repo
+                    .raw()
+                    .find_commit(*id)
+                    .with_code(proc_exit::Code::FAILURE)?;
+                let message = git_stack::git::decode_message(
+                    raw_commit.message_bytes(),
+                    raw_commit.message_encoding(),
+                );
+                messages.push(message.trim().to_owned());
+            }
+            messages.join("\n\n")
+        };
+        git_stack::graph::reword_commit(&mut graph, &repo, squashed[0], new_message)
+            .with_code(proc_exit::Code::FAILURE)?;
+        let scripts = git_stack::graph::to_scripts(&graph, vec![]);
+
+        let mut stash_id = None;
+        if !self.dry_run {
+            stash_id = git_stack::git::stash_push(&mut repo, "squash");
+        }
+
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "squash",
+            stash_id,
+            self.dry_run,
+        )?;
+
+        let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            &mut repo,
+            &mut executor,
+            scripts,
+            head_branch.as_ref().and_then(|b| b.local_name()),
+            stash_id,
+        )?;
+
+        git_stack::git::stash_pop(&mut repo, stash_id);
+        if backed_up {
+            crate::ops::note_undo();
+        }
+
+        if success {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE.as_exit())
+        }
+    }
+}
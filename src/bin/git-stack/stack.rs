@@ -1,7 +1,6 @@
 use std::collections::VecDeque;
 use std::io::Write;
 
-use bstr::ByteSlice;
 use eyre::WrapErr;
 use itertools::Itertools;
 use proc_exit::prelude::*;
@@ -12,45 +11,105 @@ struct State {
     repo: git_stack::legacy::git::GitRepo,
     branches: git_stack::legacy::git::Branches,
     protected_branches: git_stack::legacy::git::Branches,
+    anchors: git_stack::git::Anchors,
+    ignored: git_stack::git::IgnoredCommits,
+    drafts: git_stack::git::DraftBranches,
+    needs_restack: git_stack::git::PendingRestack,
+    checked_out: std::collections::BTreeMap<String, std::path::PathBuf>,
     head_commit: std::rc::Rc<git_stack::legacy::git::Commit>,
+    /// `HEAD` as recorded in the `--as-of` snapshot
+    as_of_head: Option<git_stack::legacy::git::Branch>,
     stacks: Vec<StackState>,
 
     rebase: bool,
+    /// Only process fixups as part of `rebase`, leaving branches on their current base
+    autosquash_only: bool,
     pull: bool,
+    auto_fresh: bool,
+    fresh_max_age: std::time::Duration,
     push: bool,
+    push_until_commit: Option<String>,
+    push_drafts: bool,
+    separate_households: bool,
     fixup: git_stack::config::Fixup,
+    /// `branch.<name>.stack-auto-fixup`, when the `fixup` is from `stack.auto-fixup`
+    fixup_overrides: std::collections::BTreeMap<String, git_stack::config::Fixup>,
     repair: bool,
+    preserve_order: bool,
+    drop_by_patch_id: bool,
+    skip_conflicts: bool,
+    only: Option<String>,
+    with_descendants: bool,
+    force: bool,
     dry_run: bool,
-    snapshot_capacity: Option<usize>,
+    run_hooks: bool,
+    snapshot_retention: crate::ops::SnapshotRetention,
+    resign: git_stack::config::Resign,
+    sign: git_stack::config::Sign,
+    push_style: git_stack::config::PushStyle,
+    retry: crate::ops::Retry,
     protect_commit_count: Option<usize>,
     protect_commit_age: std::time::Duration,
     protect_commit_time: std::time::SystemTime,
 
     show_format: git_stack::config::Format,
+    show_icons: git_stack::config::Icons,
     show_commits: git_stack::config::ShowCommits,
     show_stacked: bool,
+    show_base_history: usize,
+    show_max_width: Option<usize>,
+    show_signatures: bool,
+}
+
+/// The steps `stack.default-command` runs when none are passed on the command line
+#[derive(Copy, Clone, Default, Debug)]
+struct DefaultSteps {
+    pull: bool,
+    rebase: bool,
+    push: bool,
 }
 
 impl State {
     fn new(
         mut repo: git_stack::legacy::git::GitRepo,
+        repo_config: git_stack::config::RepoConfig,
         args: &crate::args::Args,
+        selection: &crate::select::Selection,
+        default_steps: DefaultSteps,
     ) -> Result<Self, proc_exit::Exit> {
-        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
-            .with_code(proc_exit::sysexits::CONFIG_ERR)?
-            .update(args.to_config());
-
-        let mut rebase = args.rebase;
-        let pull = args.pull;
+        let DefaultSteps {
+            pull: default_pull,
+            rebase: default_rebase,
+            push: default_push,
+        } = default_steps;
+        let explicit_rebase = args.rebase || default_rebase;
+
+        let mut rebase = explicit_rebase;
+        let pull = args.pull || default_pull;
         if pull {
             log::trace!("`--pull` implies `--rebase`");
             rebase = true;
         }
         let rebase = rebase;
+        let autosquash_only = args.autosquash_only;
 
-        let fixup = match (args.fixup, args.rebase) {
+        let needs_restack = git_stack::git::PendingRestack::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        let mut fixup_overrides = Default::default();
+        let fixup = match (args.fixup, explicit_rebase) {
             (Some(fixup), _) => fixup,
-            (_, true) => repo_config.auto_fixup(),
+            _ if autosquash_only => {
+                fixup_overrides = repo_config.branch_auto_fixups();
+                git_stack::config::Fixup::Squash
+            }
+            _ if rebase && !needs_restack.is_empty() => {
+                log::trace!("Squashing fixups deferred by `git stack amend --no-rebase`");
+                git_stack::config::Fixup::Squash
+            }
+            (_, true) => {
+                fixup_overrides = repo_config.branch_auto_fixups();
+                repo_config.auto_fixup()
+            }
             _ => {
                 // Assume the user is only wanting to show the tree and not modify it.
                 let no_op = git_stack::config::Fixup::Ignore;
@@ -63,7 +122,8 @@ impl State {
                 no_op
             }
         };
-        let repair = match (args.repair(), args.rebase) {
+        let repair = match (args.repair(), explicit_rebase) {
+            _ if autosquash_only => false,
             (Some(repair), _) => repair,
             (_, true) => repo_config.auto_repair(),
             _ => {
@@ -77,40 +137,103 @@ impl State {
                 false
             }
         };
-        let push = args.push;
+        let push = args.push || default_push;
+        let push_until_commit = args.push_until_commit.clone();
+        let push_drafts = args.draft;
+        let separate_households = args.separate_households;
         let protected = git_stack::legacy::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let preserve_order = repo_config.preserve_order();
+        let drop_by_patch_id = repo_config.drop_by_patch_id();
+        let skip_conflicts = args.skip_conflicts;
+        let only = args.only.clone();
+        if let Some(only) = only.as_deref() {
+            if repo.find_local_branch(only).is_none() {
+                return Err(proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("could not find branch `{only}`")));
+            }
+        }
+        let with_descendants = args.with_descendants;
+        let force = args.force;
         let dry_run = args.dry_run;
-        let snapshot_capacity = repo_config.capacity();
+        let run_hooks = !args.no_hooks;
+        let snapshot_retention = crate::ops::SnapshotRetention::from_config(&repo_config);
+        let resign = repo_config.resign();
+        let push_style = repo_config.push_style();
+        let retry = crate::ops::Retry::from_config(&repo_config);
+        let auto_fresh = args.auto_fresh;
+        let fresh_max_age = repo_config.fresh_max_age();
         let protect_commit_count = repo_config.protect_commit_count();
         let protect_commit_age = repo_config.protect_commit_age();
         let protect_commit_time = std::time::SystemTime::now() - protect_commit_age;
         let show_format = repo_config.show_format();
+        let show_icons = repo_config.show_icons();
         let show_commits = repo_config.show_commits();
         let show_stacked = repo_config.show_stacked();
+        let show_base_history = repo_config.show_base_history();
+        let show_signatures = repo_config.show_signatures();
+        let show_max_width = match repo_config.show_max_width() {
+            Some(0) => None,
+            Some(max_width) => Some(max_width),
+            None => crate::ops::terminal_width(),
+        };
 
         repo.set_push_remote(repo_config.push_remote());
+        for (branch, remote) in repo_config.branch_push_remotes() {
+            repo.set_branch_push_remote(branch, remote);
+        }
         repo.set_pull_remote(repo_config.pull_remote());
-        let config = repo
-            .raw()
-            .config()
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
-        repo.set_sign(
-            config
-                .get_bool("stack.gpgSign")
-                .or_else(|_| config.get_bool("commit.gpgSign"))
-                .unwrap_or_default(),
-        )
-        .with_code(proc_exit::Code::FAILURE)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        crate::ops::warn_diverged_remotes(
+            repo.raw(),
+            |name| protected.is_protected(name),
+            |name| repo.pull_remote_for(name).to_owned(),
+        );
+
+        let as_of = args
+            .as_of
+            .as_deref()
+            .map(|value| crate::snapshot::find_snapshot_as_of(repo.raw(), value))
+            .transpose()?;
+        let local_branches: Vec<_> = match as_of.as_ref() {
+            Some(snapshot) => snapshot_branches(&repo, snapshot),
+            None => repo.local_branches().collect(),
+        };
 
         let mut branches = git_stack::legacy::git::Branches::new([]);
         let mut protected_branches = git_stack::legacy::git::Branches::new([]);
-        for branch in repo.local_branches() {
+        for branch in local_branches {
+            if repo.find_commit(branch.id).is_none() {
+                // Like after an interrupted fetch or a pruned object database
+                log::warn!(
+                    "Skipping `{}`, its commit {} is missing (try `git fetch`)",
+                    branch,
+                    branch.id
+                );
+                continue;
+            }
             if protected.is_protected(&branch.name) {
                 log::trace!("Branch {} is protected", branch);
-                if let Some(remote) = repo.find_remote_branch(repo.pull_remote(), &branch.name) {
+                // The remote's state at the time of the snapshot is unknown
+                let remote = as_of.is_none().then(|| {
+                    repo.find_remote_branch(repo.pull_remote_for(&branch.name), &branch.name)
+                });
+                if let Some(remote) = remote
+                    .flatten()
+                    .filter(|remote| repo.find_commit(remote.id).is_some())
+                {
                     protected_branches.insert(remote.clone());
                     branches.insert(remote);
                 } else {
@@ -119,21 +242,53 @@ impl State {
             }
             branches.insert(branch);
         }
-        let head_commit = repo.head_commit();
-        let base = args
-            .base
-            .as_deref()
+        if !args.ignore_branch.is_empty() {
+            branches = ignore_branches(
+                &repo,
+                branches,
+                &protected_branches,
+                &args.ignore_branch,
+                args.ignore_descendants,
+            )
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        }
+        let named_stack = selection.stack(args.stack.as_ref()).and_then(|s| s.named());
+        if let Some(name) = named_stack {
+            branches = named_stack_branches(&repo, branches, &protected_branches, name)
+                .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        }
+        let as_of_head = as_of
+            .as_ref()
+            .and_then(|snapshot| git_stack::git::HeadState::from_metadata(&snapshot.metadata))
+            .filter(|head| repo.find_commit(head.id).is_some())
+            .map(|head| git_stack::legacy::git::Branch {
+                remote: None,
+                name: head.branch.unwrap_or_else(|| "HEAD".to_owned()),
+                id: head.id,
+                push_id: None,
+                pull_id: None,
+            });
+        let head_commit = as_of_head
+            .as_ref()
+            .and_then(|head| repo.find_commit(head.id))
+            .unwrap_or_else(|| repo.head_commit());
+        let (base, onto) = selection.base_onto(args.base.as_deref(), args.onto.as_deref());
+        let base = base
             .map(|name| resolve_explicit_base(&repo, name))
             .transpose()
             .with_code(proc_exit::sysexits::USAGE_ERR)?;
-        let onto = args
-            .onto
-            .as_deref()
+        let onto = onto
             .map(|name| resolve_explicit_base(&repo, name))
             .transpose()
             .with_code(proc_exit::sysexits::USAGE_ERR)?;
 
-        let stacks = match (base, onto, repo_config.stack()) {
+        // A named stack already narrowed `branches`, so show it whole
+        let stack = if named_stack.is_some() {
+            git_stack::config::Stack::All
+        } else {
+            repo_config.stack()
+        };
+        let stacks = match (base, onto, stack) {
             (Some(base), Some(onto), git_stack::config::Stack::All) => {
                 vec![StackState::new(base, onto, branches.all())]
             }
@@ -237,27 +392,64 @@ impl State {
             }
         }
 
+        let anchors =
+            git_stack::git::Anchors::from_repo(repo.raw()).with_code(proc_exit::Code::FAILURE)?;
+        let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        let drafts = git_stack::git::DraftBranches::from_repo(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+        let checked_out = git_stack::git::branches_checked_out_elsewhere(repo.raw())
+            .with_code(proc_exit::Code::FAILURE)?;
+
         Ok(Self {
             repo,
             branches,
             protected_branches,
+            anchors,
+            ignored,
+            drafts,
+            needs_restack,
+            checked_out,
             head_commit,
+            as_of_head,
             stacks,
 
             rebase,
+            autosquash_only,
             pull,
+            auto_fresh,
+            fresh_max_age,
             push,
+            push_until_commit,
+            push_drafts,
+            separate_households,
             fixup,
+            fixup_overrides,
             repair,
+            preserve_order,
+            drop_by_patch_id,
+            skip_conflicts,
+            only,
+            with_descendants,
+            force,
             dry_run,
-            snapshot_capacity,
+            run_hooks,
+            snapshot_retention,
+            resign,
+            sign,
+            push_style,
+            retry,
             protect_commit_count,
             protect_commit_age,
             protect_commit_time,
 
             show_format,
+            show_icons,
             show_commits,
             show_stacked,
+            show_base_history,
+            show_max_width,
+            show_signatures,
         })
     }
 
@@ -274,6 +466,186 @@ impl State {
     }
 }
 
+/// Check the remote-tracking branches being rebased onto are recent enough to detect squash-merges
+///
+/// Merged branches are only dropped once the base they landed in has been fetched, so a stale base
+/// silently leaves them in the stack.
+fn refresh_stale_bases(state: &mut State) -> eyre::Result<()> {
+    let mut fetched = std::collections::BTreeSet::new();
+    for stack in state.stacks.iter() {
+        let Some(branch) = &stack.onto.branch else {
+            continue;
+        };
+        let Some(remote) = &branch.remote else {
+            continue;
+        };
+        if fetched.contains(remote) {
+            continue;
+        }
+        let Some(age) = crate::ops::remote_branch_age(state.repo.raw(), remote, &branch.name)
+        else {
+            continue;
+        };
+        if age < state.fresh_max_age {
+            continue;
+        }
+        let age = humantime::format_duration(std::time::Duration::from_secs(age.as_secs()));
+        if state.auto_fresh {
+            log::debug!("`{}` was last fetched {} ago", branch, age);
+            match crate::ops::git_fetch_prune(remote, state.retry) {
+                Ok(_) => {
+                    fetched.insert(remote.clone());
+                }
+                Err(err) => {
+                    log::warn!("Skipping fetch of `{}`, {}", remote, err);
+                }
+            }
+        } else {
+            log::warn!(
+                "`{}` was last fetched {} ago, squash-merged branches may be missed; run `git fetch --prune {}` or pass `--auto-fresh`",
+                branch,
+                age,
+                remote
+            );
+        }
+    }
+    if !fetched.is_empty() {
+        state.update()?;
+    }
+    Ok(())
+}
+
+/// Forget the deferred restacks of the branches that were just restacked
+fn clear_restacked(state: &mut State) -> Result<(), git2::Error> {
+    let restacked: Vec<_> = state
+        .needs_restack
+        .iter()
+        .filter(|name| {
+            state.stacks.iter().any(|stack| {
+                stack
+                    .branches
+                    .iter()
+                    .flat_map(|(_, branches)| branches.iter())
+                    .any(|b| b.remote.is_none() && b.name == *name)
+            })
+        })
+        .map(ToOwned::to_owned)
+        .collect();
+    for name in &restacked {
+        git_stack::git::clear_needs_restack(state.repo.raw(), name)?;
+    }
+    state.needs_restack = git_stack::git::PendingRestack::from_repo(state.repo.raw())?;
+    Ok(())
+}
+
+/// The local branches recorded in `snapshot`, skipping those whose commits are gone
+fn snapshot_branches(
+    repo: &git_stack::legacy::git::GitRepo,
+    snapshot: &git_branch_stash::Snapshot,
+) -> Vec<git_stack::legacy::git::Branch> {
+    snapshot
+        .branches
+        .iter()
+        .filter_map(|branch| {
+            if repo.find_commit(branch.id).is_none() {
+                log::warn!(
+                    "Skipping `{}`, its commit {} is no longer in the repo",
+                    branch.name,
+                    branch.id
+                );
+                return None;
+            }
+            Some(git_stack::legacy::git::Branch {
+                remote: None,
+                name: branch.name.clone(),
+                id: branch.id,
+                push_id: None,
+                pull_id: None,
+            })
+        })
+        .collect()
+}
+
+/// Remove `ignored` branches (and optionally their descendants) from consideration
+fn ignore_branches(
+    repo: &git_stack::legacy::git::GitRepo,
+    branches: git_stack::legacy::git::Branches,
+    protected_branches: &git_stack::legacy::git::Branches,
+    ignored: &[String],
+    descendants: bool,
+) -> eyre::Result<git_stack::legacy::git::Branches> {
+    let mut ignored_ids = Vec::new();
+    for name in ignored {
+        let branch = repo
+            .find_local_branch(name)
+            .ok_or_else(|| eyre::eyre!("could not find branch `{}`", name))?;
+        if protected_branches
+            .get(branch.id)
+            .into_iter()
+            .flatten()
+            .any(|b| b.name == branch.name)
+        {
+            eyre::bail!("cannot ignore protected branch `{}`", name);
+        }
+        ignored_ids.push(branch.id);
+    }
+
+    let is_ignored = |branch: &git_stack::legacy::git::Branch| {
+        if branch.remote.is_some() {
+            return false;
+        }
+        if ignored.contains(&branch.name) {
+            return true;
+        }
+        let is_protected = protected_branches
+            .get(branch.id)
+            .into_iter()
+            .flatten()
+            .any(|b| b.name == branch.name);
+        descendants
+            && !is_protected
+            && ignored_ids
+                .iter()
+                .any(|id| repo.merge_base(*id, branch.id) == Some(*id))
+    };
+    let remaining = branches.into_iter().flat_map(|(_, b)| b).filter(|b| {
+        let ignore = is_ignored(b);
+        if ignore {
+            log::debug!("Ignoring branch {}", b);
+        }
+        !ignore
+    });
+    Ok(git_stack::legacy::git::Branches::new(remaining))
+}
+
+/// Only keep protected branches and the development branches in the named stack `name`
+fn named_stack_branches(
+    repo: &git_stack::legacy::git::GitRepo,
+    branches: git_stack::legacy::git::Branches,
+    protected_branches: &git_stack::legacy::git::Branches,
+    name: &str,
+) -> eyre::Result<git_stack::legacy::git::Branches> {
+    let named_stacks = git_stack::config::NamedStacks::from_repo(repo.raw())?;
+    if !named_stacks.contains(name) {
+        eyre::bail!("no stack named `{}`, see `git stack regroup`", name);
+    }
+
+    let remaining = branches.into_iter().flat_map(|(_, b)| b).filter(|b| {
+        let is_protected = b.remote.is_some()
+            || protected_branches
+                .get(b.id)
+                .into_iter()
+                .flatten()
+                .any(|p| p.name == b.name);
+        let keep = is_protected || named_stacks.stack_of(&b.name) == Some(name);
+        if !keep {
+            log::debug!("Excluding branch {} from stack `{}`", b, name);
+        }
+        keep
+    });
+    Ok(git_stack::legacy::git::Branches::new(remaining))
+}
+
 #[derive(Debug)]
 struct StackState {
     base: AnnotatedOid,
@@ -312,22 +684,85 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
     log::trace!("Initializing");
     let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
     let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
-    let repo = git_stack::legacy::git::GitRepo::new(repo);
-    let mut state = State::new(repo, args)?;
+    if crate::ops::is_head_unborn(&repo) {
+        crate::ops::note_unborn_head();
+        return Ok(());
+    }
+    let selection = crate::select::load(&repo)?;
+    let mut repo = git_stack::legacy::git::GitRepo::new(repo);
+    let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?
+        .update(git_stack::config::RepoConfig {
+            stack: selection
+                .stack
+                .as_ref()
+                .and_then(crate::args::StackArg::mode),
+            ..Default::default()
+        })
+        .update(args.to_config());
+    let default_command = repo_config
+        .default_command()
+        .map_err(|err| eyre::format_err!("{err}"))
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    let default_command =
+        if args.rebase || args.pull || args.push || args.as_of.is_some() || args.shows_only() {
+            git_stack::config::DefaultCommand::Show
+        } else {
+            log::trace!("Running `stack.default-command={}`", default_command);
+            default_command
+        };
+    let default_steps = match default_command {
+        git_stack::config::DefaultCommand::Show => DefaultSteps::default(),
+        git_stack::config::DefaultCommand::Sync => {
+            return crate::sync::SyncArgs::from_args(args).exec();
+        }
+        git_stack::config::DefaultCommand::Pipeline { pull, rebase, push } => {
+            DefaultSteps { pull, rebase, push }
+        }
+    };
+    if !args.no_cache {
+        repo.load_cache();
+    }
+    let mut state = State::new(repo, repo_config, args, &selection, default_steps)?;
+    if state.rebase {
+        crate::resume::require_no_rewrite(state.repo.raw())?;
+    }
+    let _lock = if state.rebase || state.pull || state.push {
+        crate::lock::lock_repo(state.repo.raw(), state.dry_run)?
+    } else {
+        None
+    };
 
     if state.pull {
         // Update status of remote unprotected branches
-        let mut push_branches: Vec<_> = state
-            .stacks
-            .iter()
-            .flat_map(|stack| stack.branches.iter())
-            .filter(|(oid, _)| !state.protected_branches.contains_oid(*oid))
-            .flat_map(|(_, b)| b.iter())
-            .filter_map(|b| b.push_id.and_then(|_| b.local_name()))
-            .collect();
-        push_branches.sort_unstable();
-        if !push_branches.is_empty() {
-            match git_prune_development(&mut state.repo, &push_branches, state.dry_run) {
+        let households: Vec<_> = if state.separate_households {
+            state
+                .stacks
+                .iter()
+                .map(|stack| {
+                    (
+                        Some(stack.onto.to_string()),
+                        pushed_branches(&state.protected_branches, std::slice::from_ref(stack)),
+                    )
+                })
+                .collect()
+        } else {
+            vec![(
+                None,
+                pushed_branches(&state.protected_branches, &state.stacks),
+            )]
+        };
+        for (household, push_branches) in households {
+            if push_branches.is_empty() {
+                continue;
+            }
+            match git_prune_development(
+                &mut state.repo,
+                &push_branches,
+                household.as_deref(),
+                state.retry,
+                state.dry_run,
+            ) {
                 Ok(_) => (),
                 Err(err) => {
                     log::warn!("Skipping fetch of `{}`, {}", state.repo.push_remote(), err);
@@ -335,11 +770,18 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
             }
         }
 
+        let mut pulled = std::collections::BTreeSet::new();
         for stack in state.stacks.iter() {
             if let Some(branch) = &stack.onto.branch {
                 if let Some(remote) = &branch.remote {
-                    match git_fetch_upstream(remote, branch.name.as_str()) {
-                        Ok(_) => (),
+                    if !pulled.insert(branch.to_string()) {
+                        continue;
+                    }
+                    match crate::ops::git_fetch_upstream(remote, branch.name.as_str(), state.retry)
+                    {
+                        Ok(_) => {
+                            fast_forward_protected(state.repo.raw(), branch, state.dry_run);
+                        }
                         Err(err) => {
                             log::warn!("Skipping pull of `{}`, {}", branch, err);
                         }
@@ -350,6 +792,8 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
             }
         }
         state.update().with_code(proc_exit::Code::FAILURE)?;
+    } else if state.rebase && !state.autosquash_only {
+        refresh_stale_bases(&mut state).with_code(proc_exit::Code::FAILURE)?;
     }
 
     let mut success = true;
@@ -369,20 +813,13 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
             }
         }
 
-        {
-            let stash_repo =
-                git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
-            let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
-            let mut snapshots =
-                git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
-            snapshots.capacity(state.snapshot_capacity);
-            let snapshot = git_branch_stash::Snapshot::from_repo(&stash_repo)
-                .with_code(proc_exit::Code::FAILURE)?;
-            if !state.dry_run {
-                snapshots.push(snapshot).to_sysexits()?;
-                backed_up = true;
-            }
-        }
+        backed_up = crate::ops::backup_branches(
+            &cwd,
+            state.snapshot_retention,
+            "stack",
+            stash_id,
+            state.dry_run,
+        )?;
 
         let mut head_branch = state
             .repo
@@ -412,9 +849,77 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
                 Ok(script)
             })
             .collect();
-        let scripts = scripts?;
+        let mut scripts = scripts?;
+        if let Some(only) = state.only.as_deref() {
+            if !scripts.iter().any(|s| s.creates_branch(only)) {
+                git_stack::legacy::git::stash_pop(&mut state.repo, stash_id);
+                return Err(proc_exit::sysexits::USAGE_ERR
+                    .with_message(format!("`{only}` is not in the selected stacks")));
+            }
+            for script in &mut scripts {
+                if script.creates_branch(only) {
+                    script.retain_branch(only, state.with_descendants);
+                } else {
+                    *script = git_stack::legacy::git::Script::new();
+                }
+            }
+        }
+        // A real run without `--skip-conflicts` reports conflicts as it hits them
+        if state.skip_conflicts || state.dry_run {
+            if state.dry_run {
+                state
+                    .repo
+                    .keep_checks_in_memory()
+                    .with_code(proc_exit::Code::FAILURE)?;
+            }
+            for script in &mut scripts {
+                let conflicts = script
+                    .dry_run_check(&state.repo)
+                    .with_code(proc_exit::Code::FAILURE)?;
+                for conflict in &conflicts {
+                    crate::ops::warn_conflict(
+                        state.repo.raw(),
+                        conflict.branch(),
+                        conflict.commit_id(),
+                        conflict.dependents(),
+                        state.skip_conflicts,
+                    );
+                }
+                if state.skip_conflicts {
+                    script.skip_conflicts(&conflicts);
+                }
+            }
+        }
+        let mut up_to_date = Vec::new();
+        for script in &mut scripts {
+            if let Some(branches) = script
+                .up_to_date_branches(&state.repo)
+                .with_code(proc_exit::Code::FAILURE)?
+            {
+                up_to_date.extend(branches);
+                *script = git_stack::legacy::git::Script::new();
+            }
+        }
+        crate::ops::note_up_to_date(&up_to_date);
+        scripts.retain(|s| !s.is_empty());
+        let mut rewritten = Vec::new();
+        for script in &scripts {
+            rewritten.extend(
+                script
+                    .rewritten_commits(&state.repo)
+                    .with_code(proc_exit::Code::FAILURE)?,
+            );
+        }
+        if crate::ops::warn_tagged_rewrites(state.repo.raw(), &rewritten, state.force)
+            .with_code(proc_exit::Code::FAILURE)?
+            && !state.dry_run
+        {
+            git_stack::legacy::git::stash_pop(&mut state.repo, stash_id);
+            return Err(proc_exit::Code::FAILURE.with_message("refusing to rewrite tagged commits"));
+        }
 
         let mut executor = git_stack::legacy::git::Executor::new(&state.repo, state.dry_run);
+        executor.set_run_hooks(state.run_hooks);
         for script in scripts {
             let results = executor.run_script(&mut state.repo, &script);
             for (err, name, dependents) in results.iter() {
@@ -429,26 +934,33 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
             .close(&mut state.repo, &head_branch)
             .with_code(proc_exit::Code::FAILURE)?;
         state.update().with_code(proc_exit::Code::FAILURE)?;
+        if success
+            && !state.dry_run
+            && state.only.is_none()
+            && state.fixup == git_stack::config::Fixup::Squash
+        {
+            clear_restacked(&mut state).with_code(proc_exit::Code::FAILURE)?;
+        }
     }
 
-    if state.push {
+    if let Some(rev) = state.push_until_commit.clone() {
+        // Resolved after any rebase, so `HEAD~` refers to the re-stacked commits
+        push_until_commit(&mut state, &rev).with_code(proc_exit::Code::FAILURE)?;
+        state.update().with_code(proc_exit::Code::FAILURE)?;
+    } else if state.push {
         push(&mut state).with_code(proc_exit::Code::FAILURE)?;
         state.update().with_code(proc_exit::Code::FAILURE)?;
     }
 
     show(&state).with_code(proc_exit::Code::FAILURE)?;
+    if let Err(err) = state.repo.save_cache() {
+        log::debug!("Could not save the graph cache: {}", err);
+    }
 
     git_stack::legacy::git::stash_pop(&mut state.repo, stash_id);
 
     if backed_up {
-        let palette_stderr = crate::ops::Palette::colored();
-        log::info!(
-            "{}",
-            palette_stderr.hint(format_args!(
-                "To undo, run `git branch-stash pop {}`",
-                crate::ops::STASH_STACK_NAME
-            ))
-        );
+        crate::ops::note_undo();
     }
 
     if !success {
@@ -458,6 +970,22 @@ pub(crate) fn stack(args: &crate::args::Args) -> proc_exit::ExitResult {
     Ok(())
 }
 
+/// Local branches in `stacks` with a copy on the push remote
+fn pushed_branches<'s>(
+    protected_branches: &git_stack::legacy::git::Branches,
+    stacks: &'s [StackState],
+) -> Vec<&'s str> {
+    let mut push_branches: Vec<_> = stacks
+        .iter()
+        .flat_map(|stack| stack.branches.iter())
+        .filter(|(oid, _)| !protected_branches.contains_oid(*oid))
+        .flat_map(|(_, b)| b.iter())
+        .filter_map(|b| b.push_id.and_then(|_| b.local_name()))
+        .collect();
+    push_branches.sort_unstable();
+    push_branches
+}
+
 fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::legacy::git::Script> {
     log::trace!("Planning stack changes with base={}", stack.base,);
     let graphed_branches = stack.branches.clone();
@@ -485,6 +1013,8 @@ fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::le
         .collect();
     protected_oids.insert(stack.onto.id);
     git_stack::legacy::graph::protect_commits(&mut graph, &state.repo, protected_oids);
+    git_stack::legacy::graph::mark_anchors(&mut graph, &state.anchors);
+    git_stack::legacy::graph::mark_ignored(&mut graph, &state.ignored);
     if let Some(protect_commit_count) = state.protect_commit_count {
         git_stack::legacy::graph::protect_large_branches(&mut graph, protect_commit_count);
     }
@@ -495,10 +1025,58 @@ fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::le
     );
     if let Some(user) = state.repo.user() {
         git_stack::legacy::graph::protect_foreign_branches(&mut graph, &user, &[]);
+        match state.resign {
+            git_stack::config::Resign::Strip => {
+                let signed = git_stack::legacy::graph::find_foreign_signed_commits(
+                    &graph,
+                    &state.repo,
+                    &user,
+                );
+                if !signed.is_empty() {
+                    log::warn!(
+                        "Rewriting may drop signatures by others (set `stack.resign=block` to keep them): {}",
+                        signed.iter().map(|id| id.to_string()[..7].to_owned()).join(", ")
+                    );
+                }
+            }
+            git_stack::config::Resign::Block | git_stack::config::Resign::ResignMine => {
+                let signed = git_stack::legacy::graph::protect_foreign_signed_branches(
+                    &mut graph,
+                    &state.repo,
+                    &user,
+                );
+                if !signed.is_empty() {
+                    log::warn!(
+                        "Leaving branches with commits signed by others in place (`stack.resign={}`): {}",
+                        state.resign,
+                        signed.join(", ")
+                    );
+                }
+            }
+        }
+        if state.sign == git_stack::config::Sign::Never {
+            let signed =
+                git_stack::legacy::graph::find_own_signed_commits(&graph, &state.repo, &user);
+            if !signed.is_empty() {
+                log::warn!(
+                    "Rewriting may drop your signatures (set `stack.sign=if-signed` to re-sign them): {}",
+                    signed.iter().map(|id| id.to_string()[..7].to_owned()).join(", ")
+                );
+            }
+        }
+    }
+    for (name, path) in
+        git_stack::legacy::graph::protect_checked_out_branches(&mut graph, &state.checked_out)
+    {
+        log::warn!(
+            "Leaving the stack with `{}` in place, it is checked out in `{}`",
+            name,
+            path.display()
+        );
     }
 
     let mut dropped_branches = Vec::new();
-    if state.rebase {
+    if state.rebase && !state.autosquash_only {
         log::trace!("Rebasing onto {}", stack.onto);
         let onto_id = stack.onto.id;
         let pull_start_id = stack.base.id;
@@ -508,7 +1086,12 @@ fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::le
             .unwrap_or(onto_id);
 
         git_stack::legacy::graph::rebase_development_branches(&mut graph, onto_id);
-        git_stack::legacy::graph::fast_forward_pulled_branches(&mut graph, pull_start_id, onto_id);
+        git_stack::legacy::graph::fast_forward_pulled_branches(
+            &mut graph,
+            pull_start_id,
+            onto_id,
+            &state.checked_out,
+        );
 
         let pull_range: Vec<_> =
             git_stack::legacy::git::commit_range(&state.repo, onto_id..pull_start_id)?
@@ -519,17 +1102,35 @@ fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::le
             &mut graph,
             pull_range.iter().map(|c| c.tree_id),
         );
+        if state.drop_by_patch_id {
+            git_stack::legacy::graph::drop_by_patch_id(
+                &mut graph,
+                state.repo.raw(),
+                pull_range.iter().map(|c| c.id),
+            );
+        }
         dropped_branches.extend(git_stack::legacy::graph::drop_merged_branches(
             &mut graph,
             pull_range.iter().map(|c| c.id),
             &state.protected_branches,
         ));
     }
-    git_stack::legacy::graph::fixup(&mut graph, state.fixup);
+    git_stack::legacy::graph::fixup_branches(&mut graph, state.fixup, &state.fixup_overrides);
     if state.repair {
         log::trace!("Repairing");
         git_stack::legacy::graph::merge_stacks(&mut graph);
-        git_stack::legacy::graph::realign_stacks(&mut graph);
+        git_stack::legacy::graph::merge_stacks_by_patch_id(&mut graph, state.repo.raw());
+        if state.preserve_order {
+            let misaligned = git_stack::legacy::graph::find_misaligned_branches(&graph);
+            if !misaligned.is_empty() {
+                log::warn!(
+                    "Leaving diverged stacks in place (`stack.preserve-order=true`): {}",
+                    misaligned.join(", ")
+                );
+            }
+        } else {
+            git_stack::legacy::graph::realign_stacks(&mut graph);
+        }
     }
 
     let mut script = git_stack::legacy::graph::to_script(&graph);
@@ -543,8 +1144,60 @@ fn plan_changes(state: &State, stack: &StackState) -> eyre::Result<git_stack::le
 }
 
 fn push(state: &mut State) -> eyre::Result<()> {
+    if !state.separate_households {
+        let graph = push_graph(state, &state.stacks)?;
+        return git_push(
+            &mut state.repo,
+            &graph,
+            state.push_style,
+            state.run_hooks,
+            state.retry,
+            state.dry_run,
+        );
+    }
+
+    for i in 0..state.stacks.len() {
+        let household = state.stacks[i].onto.to_string();
+        let graph = push_graph(state, std::slice::from_ref(&state.stacks[i]))?;
+        let pushable: Vec<_> = graph
+            .breadth_first_iter()
+            .filter(|node| node.pushable)
+            .flat_map(|node| node.branches.iter())
+            .filter_map(|b| b.local_name())
+            .collect();
+        if pushable.is_empty() {
+            continue;
+        }
+        if !state.dry_run
+            && !crate::ops::confirm(format_args!(
+                "Push {} onto `{}`",
+                pushable.join(", "),
+                household
+            ))
+        {
+            log::info!("Skipping push of stacks onto `{}`", household);
+            continue;
+        }
+        git_push(
+            &mut state.repo,
+            &graph,
+            state.push_style,
+            state.run_hooks,
+            state.retry,
+            state.dry_run,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The graph of `stacks` with the branches to push marked
+fn push_graph(
+    state: &State,
+    stacks: &[StackState],
+) -> eyre::Result<git_stack::legacy::graph::Graph> {
     let mut graphed_branches = git_stack::legacy::git::Branches::new(None);
-    for stack in state.stacks.iter() {
+    for stack in stacks.iter() {
         let stack_graphed_branches = stack.branches.clone();
         graphed_branches.extend(stack_graphed_branches.into_iter().flat_map(|(_, b)| b));
     }
@@ -555,6 +1208,7 @@ fn push(state: &mut State) -> eyre::Result<()> {
     )?;
 
     git_stack::legacy::graph::protect_branches(&mut graph, &state.repo, &state.protected_branches);
+    git_stack::legacy::graph::mark_ignored(&mut graph, &state.ignored);
     if let Some(protect_commit_count) = state.protect_commit_count {
         git_stack::legacy::graph::protect_large_branches(&mut graph, protect_commit_count);
     }
@@ -567,9 +1221,194 @@ fn push(state: &mut State) -> eyre::Result<()> {
         git_stack::legacy::graph::protect_foreign_branches(&mut graph, &user, &[]);
     }
 
-    git_stack::legacy::graph::pushable(&mut graph);
+    if state.push_drafts {
+        // Flagged after `pushable` so drafts are pushed, but still marked as drafts
+        git_stack::legacy::graph::pushable(&mut graph);
+        git_stack::legacy::graph::mark_draft(&mut graph, state.repo.raw(), &state.drafts);
+    } else {
+        git_stack::legacy::graph::mark_draft(&mut graph, state.repo.raw(), &state.drafts);
+        git_stack::legacy::graph::pushable(&mut graph);
+    }
+
+    Ok(graph)
+}
+
+/// Push an ancestor of the current branch to its copy on the remote, leaving the local branch
+/// in place
+fn push_until_commit(state: &mut State, rev: &str) -> eyre::Result<()> {
+    let palette_stderr = crate::ops::Palette::colored();
+
+    let head_branch = state
+        .repo
+        .head_branch()
+        .ok_or_else(|| eyre::eyre!("must be on a branch to push `{}` to", rev))?;
+    let local_branch = head_branch.name.clone();
+    if state
+        .protected_branches
+        .get(head_branch.id)
+        .into_iter()
+        .flatten()
+        .any(|b| b.name == local_branch)
+    {
+        eyre::bail!("cannot push part of protected branch `{}`", local_branch);
+    }
+
+    let until = crate::ops::resolve_rev(state.repo.raw(), rev)?;
+    if state.repo.merge_base(until.id, head_branch.id) != Some(until.id) {
+        eyre::bail!("`{}` is not on `{}`", rev, local_branch);
+    }
+    let protected_ids: Vec<_> = state
+        .protected_branches
+        .iter()
+        .flat_map(|(_, branches)| branches.iter())
+        .map(|b| (b.id, b.to_string()))
+        .collect();
+    if let Some((_, protected)) = protected_ids
+        .iter()
+        .find(|(id, _)| state.repo.merge_base(until.id, *id) == Some(until.id))
+    {
+        eyre::bail!("`{}` is already on protected branch `{}`", rev, protected);
+    }
+    // Like `git_push`, the branch is a draft only because of the commits since the branch it is
+    // stacked on, which is what a merge request targets
+    let mut parent_branch = None;
+    let mut draft = state.drafts.contains(&local_branch);
+    let mut revwalk = state.repo.raw().revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+    revwalk.push(until.id)?;
+    for (id, _) in &protected_ids {
+        revwalk.hide(*id)?;
+    }
+    for id in revwalk {
+        let commit = state
+            .repo
+            .find_commit(id?)
+            .expect("revwalk only returns commits");
+        if parent_branch.is_none() && commit.id != until.id {
+            parent_branch = state
+                .branches
+                .get(commit.id)
+                .into_iter()
+                .flatten()
+                .filter(|b| b.remote.is_none())
+                .map(|b| b.name.clone())
+                .min();
+        }
+        if parent_branch.is_none() {
+            draft |= git_stack::git::is_draft_commit(state.repo.raw(), commit.id);
+        }
+        if commit.wip_summary().is_some() && !state.ignored.contains(commit.id) {
+            let abbrev_id = state.repo.raw().find_object(commit.id, None)?.short_id()?;
+            eyre::bail!(
+                "cannot push WIP commit {} ({})",
+                abbrev_id.as_str().unwrap_or_default(),
+                commit.display_summary()
+            );
+        }
+    }
+
+    if draft && !state.push_drafts {
+        eyre::bail!(
+            "cannot push draft `{}`, pass `--draft` to push it anyway",
+            local_branch
+        );
+    }
 
-    git_push(&mut state.repo, &graph, state.dry_run)?;
+    let raw_branch = state
+        .repo
+        .raw()
+        .find_branch(&local_branch, git2::BranchType::Local)?;
+    let upstream_set = raw_branch.upstream().is_ok();
+    let remote = state.repo.push_remote_for(&local_branch).to_owned();
+    let protected_branch = git_stack::legacy::git::find_protected_base(
+        &state.repo,
+        &state.protected_branches,
+        until.id,
+    )
+    .map(|b| b.name.clone());
+    let target = match state.push_style {
+        git_stack::config::PushStyle::Plain => PushTarget::Plain,
+        git_stack::config::PushStyle::GitlabMr => {
+            PushTarget::MergeRequest(parent_branch.as_deref().or(protected_branch.as_deref()))
+        }
+        git_stack::config::PushStyle::GerritRefsFor => {
+            PushTarget::RefsFor(protected_branch.as_deref())
+        }
+    };
+    let dst = push_dst(state.repo.raw(), &remote, &local_branch, target, draft)
+        .wrap_err_with(|| format!("cannot push `{rev}`"))?
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "`{}` is not pushed to `{}` per `push.default` or `remote.{}.push`",
+                local_branch,
+                remote,
+                remote
+            )
+        })?;
+
+    let refspec = format!("{}:{}", until.id, dst.0);
+    let mut args = vec!["push"];
+    let refs_for = matches!(target, PushTarget::RefsFor(_));
+    if !refs_for {
+        args.push("--force-with-lease");
+    }
+    if !state.run_hooks {
+        args.push("--no-verify");
+    }
+    args.extend(dst.1.iter().map(|flag| flag.as_str()));
+    args.push(remote.as_str());
+    args.push(refspec.as_str());
+    log::trace!("git {}", args.join(" "),);
+    if state.dry_run {
+        return Ok(());
+    }
+
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(&args);
+    let output = crate::ops::git_remote(&mut cmd, state.retry)
+        .wrap_err_with(|| format!("Could not push {local_branch}"))?;
+    if !output.status.success() {
+        eyre::bail!("Could not push {}", local_branch);
+    }
+    let pushed_to = match dst.0.strip_prefix("refs/heads/") {
+        Some(push_target) => {
+            if !upstream_set {
+                // `--set-upstream` only applies when pushing a local branch
+                let mut raw_branch = state
+                    .repo
+                    .raw()
+                    .find_branch(&local_branch, git2::BranchType::Local)?;
+                let tracking =
+                    git_stack::git::tracking_branch(state.repo.raw(), &remote, push_target)
+                        .unwrap_or_else(|| format!("{remote}/{push_target}"));
+                raw_branch.set_upstream(Some(&tracking))?;
+            }
+            format!("{remote}/{push_target}")
+        }
+        None => format!("{remote} {}", dst.0),
+    };
+
+    let behind = state
+        .repo
+        .commit_count(until.id, head_branch.id)
+        .unwrap_or_default();
+    let abbrev_id = state.repo.raw().find_object(until.id, None)?.short_id()?;
+    let summary = state
+        .repo
+        .find_commit(until.id)
+        .map(|c| c.display_summary().to_string())
+        .unwrap_or_default();
+    let _ = writeln!(
+        anstream::stderr(),
+        "{} {} ({}) to {}, leaving {} {} on `{}` unpushed",
+        palette_stderr.good("Pushed"),
+        palette_stderr.highlight(abbrev_id.as_str().unwrap_or_default()),
+        summary,
+        palette_stderr.highlight(pushed_to),
+        behind,
+        if behind == 1 { "commit" } else { "commits" },
+        local_branch,
+    );
 
     Ok(())
 }
@@ -583,10 +1422,18 @@ fn show(state: &State) -> eyre::Result<()> {
     let abbrev_graph = match state.show_format {
         git_stack::config::Format::Silent => false,
         git_stack::config::Format::List => false,
+        git_stack::config::Format::ListVerbose => false,
         git_stack::config::Format::Graph => true,
         git_stack::config::Format::Debug => true,
     };
 
+    if state.show_format == git_stack::config::Format::Silent
+        && state.protect_commit_count.is_none()
+    {
+        // Nothing would be shown
+        return Ok(());
+    }
+
     let mut graphs = Vec::with_capacity(state.stacks.len());
     for stack in state.stacks.iter() {
         let graphed_branches = stack.branches.clone();
@@ -624,6 +1471,10 @@ fn show(state: &State) -> eyre::Result<()> {
             .collect();
         protected_oids.insert(stack.onto.id);
         git_stack::legacy::graph::protect_commits(&mut graph, &state.repo, protected_oids);
+        git_stack::legacy::graph::mark_anchors(&mut graph, &state.anchors);
+        git_stack::legacy::graph::mark_ignored(&mut graph, &state.ignored);
+        git_stack::legacy::graph::mark_draft(&mut graph, state.repo.raw(), &state.drafts);
+        git_stack::legacy::graph::mark_needs_restack(&mut graph, &state.needs_restack);
         if let Some(protect_commit_count) = state.protect_commit_count {
             let protected =
                 git_stack::legacy::graph::protect_large_branches(&mut graph, protect_commit_count);
@@ -635,33 +1486,34 @@ fn show(state: &State) -> eyre::Result<()> {
                 );
             }
         }
+        if state.show_format == git_stack::config::Format::Silent {
+            continue;
+        }
         if abbrev_graph {
+            let user = state.repo.user();
+            let inactive = git_stack::legacy::graph::trim_inactive_branches(
+                &mut graph,
+                state.protect_commit_time,
+                user.as_deref(),
+                &[state.head_commit.id],
+            );
             old_stacks.extend(
-                git_stack::legacy::graph::trim_old_branches(
-                    &mut graph,
-                    state.protect_commit_time,
-                    &[state.head_commit.id],
-                )
-                .into_iter()
-                .map(|b| format!("{}", palette_stderr.warn(b))),
+                inactive
+                    .old
+                    .into_iter()
+                    .map(|b| format!("{}", palette_stderr.warn(b))),
             );
-            if let Some(user) = state.repo.user() {
-                foreign_stacks.extend(
-                    git_stack::legacy::graph::trim_foreign_branches(
-                        &mut graph,
-                        &user,
-                        &[state.head_commit.id],
-                    )
+            foreign_stacks.extend(
+                inactive
+                    .foreign
                     .into_iter()
                     .map(|b| format!("{}", palette_stderr.warn(b))),
-                );
-                git_stack::legacy::graph::protect_foreign_branches(&mut graph, &user, &[]);
-            }
+            );
         }
-
         if state.dry_run {
             // Show as-if we performed all mutations
-            if state.rebase {
+            git_stack::legacy::graph::protect_checked_out_branches(&mut graph, &state.checked_out);
+            if state.rebase && !state.autosquash_only {
                 log::trace!("Rebasing onto {}", stack.onto);
                 let onto_id = stack.onto.id;
                 let pull_start_id = stack.base.id;
@@ -675,6 +1527,7 @@ fn show(state: &State) -> eyre::Result<()> {
                     &mut graph,
                     pull_start_id,
                     onto_id,
+                    &state.checked_out,
                 );
 
                 let pull_range: Vec<_> =
@@ -686,32 +1539,49 @@ fn show(state: &State) -> eyre::Result<()> {
                     &mut graph,
                     pull_range.iter().map(|c| c.tree_id),
                 );
+                if state.drop_by_patch_id {
+                    git_stack::legacy::graph::drop_by_patch_id(
+                        &mut graph,
+                        state.repo.raw(),
+                        pull_range.iter().map(|c| c.id),
+                    );
+                }
                 git_stack::legacy::graph::drop_merged_branches(
                     &mut graph,
                     pull_range.iter().map(|c| c.id),
                     &state.protected_branches,
                 );
             }
-            git_stack::legacy::graph::fixup(&mut graph, state.fixup);
+            git_stack::legacy::graph::fixup_branches(
+                &mut graph,
+                state.fixup,
+                &state.fixup_overrides,
+            );
             if state.repair {
                 log::trace!("Repairing");
                 git_stack::legacy::graph::merge_stacks(&mut graph);
-                git_stack::legacy::graph::realign_stacks(&mut graph);
+                git_stack::legacy::graph::merge_stacks_by_patch_id(&mut graph, state.repo.raw());
+                if !state.preserve_order {
+                    git_stack::legacy::graph::realign_stacks(&mut graph);
+                }
             }
         }
 
-        git_stack::legacy::graph::pushable(&mut graph);
+        if abbrev_graph {
+            // Only shown in the status of each branch
+            git_stack::legacy::graph::pushable(&mut graph);
+        }
 
-        graphs.push(graph);
+        graphs.push((Some(stack.onto.to_string()), graph));
     }
     if graphs.is_empty() {
         log::trace!("Rendering empty stack base={}", state.head_commit.id);
         let graph = git_stack::legacy::graph::Graph::new(git_stack::legacy::graph::Node::new(
             state.head_commit.clone(),
         ));
-        graphs.push(graph);
+        graphs.push((None, graph));
     }
-    graphs.sort_by_key(|g| {
+    graphs.sort_by_key(|(_, g)| {
         let mut revwalk = state
             .repo
             .raw()
@@ -727,14 +1597,50 @@ fn show(state: &State) -> eyre::Result<()> {
         revwalk.count()
     });
 
-    for graph in graphs {
+    for (i, (household, graph)) in graphs.into_iter().enumerate() {
+        let sectioned = matches!(
+            state.show_format,
+            git_stack::config::Format::List
+                | git_stack::config::Format::ListVerbose
+                | git_stack::config::Format::Graph
+        );
+        if let Some(household) = household.filter(|_| state.separate_households && sectioned) {
+            let palette = crate::ops::Palette::colored();
+            if i != 0 {
+                writeln!(anstream::stdout())?;
+            }
+            writeln!(
+                anstream::stdout(),
+                "{}",
+                palette.highlight(format_args!("Stacks onto {household}:"))
+            )?;
+        }
         match state.show_format {
             git_stack::config::Format::Silent => {}
             git_stack::config::Format::List => {
                 let palette = crate::ops::Palette::colored();
-                list(
+                let head_branch = state
+                    .as_of_head
+                    .clone()
+                    .unwrap_or_else(|| state.repo.head_branch().unwrap());
+                list(
+                    &mut anstream::stdout(),
+                    &head_branch,
+                    &graph,
+                    &state.protected_branches,
+                    &palette,
+                )?;
+            }
+            git_stack::config::Format::ListVerbose => {
+                let palette = crate::ops::Palette::colored();
+                let head_branch = state
+                    .as_of_head
+                    .clone()
+                    .unwrap_or_else(|| state.repo.head_branch().unwrap());
+                list_verbose(
                     &mut anstream::stdout(),
                     &state.repo,
+                    &head_branch,
                     &graph,
                     &state.protected_branches,
                     &palette,
@@ -745,8 +1651,13 @@ fn show(state: &State) -> eyre::Result<()> {
                     anstream::stdout(),
                     "{}",
                     DisplayTree::new(&state.repo, &graph)
+                        .head_branch(state.as_of_head.clone())
                         .show(state.show_commits)
+                        .icons(state.show_icons)
                         .stacked(state.show_stacked)
+                        .base_history(state.show_base_history)
+                        .max_width(state.show_max_width)
+                        .signatures(state.show_signatures)
                         .protected_branches(&state.protected_branches)
                 )?;
             }
@@ -820,27 +1731,15 @@ fn resolve_explicit_base(
     repo: &git_stack::legacy::git::GitRepo,
     base: &str,
 ) -> eyre::Result<AnnotatedOid> {
-    let (obj, r) = repo.raw().revparse_ext(base)?;
-    if let Some(r) = r {
-        if r.is_tag() {
-            return Ok(AnnotatedOid::new(obj.id()));
-        }
-
-        let branch = if r.is_remote() {
-            let (remote, name) = r
-                .shorthand()
-                .ok_or_else(|| eyre::eyre!("Expected branch, got `{}`", base))?
-                .split_once('/')
-                .expect("removes should always have at least one `/`");
-            repo.find_remote_branch(remote, name)
-                .ok_or_else(|| eyre::eyre!("Could not find branch {:?}", r.shorthand()))
-        } else {
-            repo.find_local_branch(base)
-                .ok_or_else(|| eyre::eyre!("Could not find branch {:?}", base))
-        }?;
-        Ok(AnnotatedOid::with_branch(branch))
-    } else {
-        Ok(AnnotatedOid::new(obj.id()))
+    let rev = crate::ops::resolve_rev(repo.raw(), base)?;
+    let branch = match (rev.remote.as_deref(), rev.branch.as_deref()) {
+        (Some(remote), Some(name)) => repo.find_remote_branch(remote, name),
+        (None, Some(name)) => repo.find_local_branch(name),
+        _ => None,
+    };
+    match branch {
+        Some(branch) if branch.id == rev.id => Ok(AnnotatedOid::with_branch(branch)),
+        _ => Ok(AnnotatedOid::new(rev.id)),
     }
 }
 
@@ -872,11 +1771,8 @@ fn resolve_implicit_base(
                             .get(head_oid)
                             .map(|b| b[0].to_string())
                             .or_else(|| {
-                                repo.find_commit(head_oid)?
-                                    .summary
-                                    .to_str()
-                                    .ok()
-                                    .map(ToOwned::to_owned)
+                                let commit = repo.find_commit(head_oid)?;
+                                Some(commit.display_summary().into_owned())
                             })
                             .unwrap_or_else(|| "target".to_owned()),
                         ahead_count,
@@ -895,11 +1791,8 @@ fn resolve_implicit_base(
                     .get(head_oid)
                     .map(|b| b[0].to_string())
                     .or_else(|| {
-                        repo.find_commit(head_oid)?
-                            .summary
-                            .to_str()
-                            .ok()
-                            .map(ToOwned::to_owned)
+                        let commit = repo.find_commit(head_oid)?;
+                        Some(commit.display_summary().into_owned())
                     })
                     .unwrap_or_else(|| "target".to_owned())
             );
@@ -939,69 +1832,106 @@ fn resolve_onto_from_base(
     base.branch
         .as_ref()
         .filter(|b| b.remote.is_none())
-        .and_then(|b| repo.find_remote_branch(repo.pull_remote(), &b.name))
+        .and_then(|b| repo.find_remote_branch(repo.pull_remote_for(&b.name), &b.name))
         .map(AnnotatedOid::with_branch)
         .unwrap_or_else(|| base.clone())
 }
 
+/// Fast-forward the local copy of the just-fetched protected `branch`, if there is one
+fn fast_forward_protected(
+    repo: &git2::Repository,
+    branch: &git_stack::legacy::git::Branch,
+    dry_run: bool,
+) {
+    if repo
+        .find_branch(&branch.name, git2::BranchType::Local)
+        .is_err()
+    {
+        return;
+    }
+    let Some(target) = repo
+        .find_reference(&format!("refs/remotes/{branch}"))
+        .ok()
+        .and_then(|r| r.target())
+    else {
+        return;
+    };
+    let message = format!("git-stack: fast-forward to {branch}");
+    match git_stack::git::fast_forward_branch(repo, &branch.name, target, &message, dry_run) {
+        Ok(git_stack::git::FastForward::UpToDate) => {}
+        Ok(git_stack::git::FastForward::Updated) => {
+            log::info!("Fast-forwarding `{}` to `{}`", branch.name, branch);
+        }
+        Ok(git_stack::git::FastForward::Diverged) => {
+            log::warn!(
+                "Leaving `{}` in place, it has diverged from `{}`",
+                branch.name,
+                branch
+            );
+        }
+        Ok(git_stack::git::FastForward::Dirty(path)) => {
+            let canonical =
+                |path: &std::path::Path| std::fs::canonicalize(path).unwrap_or(path.to_owned());
+            if repo.workdir().map(canonical) == Some(canonical(&path)) {
+                // Re-stacking stashes the changes and moves the branch along with the rest
+                log::debug!(
+                    "Leaving `{}` to the rebase, the working tree is dirty",
+                    branch.name
+                );
+                return;
+            }
+            log::warn!(
+                "Leaving `{}` in place, it is checked out in `{}` with uncommitted changes",
+                branch.name,
+                path.display()
+            );
+        }
+        Err(err) => {
+            log::warn!("Could not fast-forward `{}`: {}", branch.name, err);
+        }
+    }
+}
+
+/// Remove the remote-tracking branches of `branches` that are gone from their push remote
+///
+/// With a `household`, the base of the stacks `branches` are in, this is confirmed first.
 fn git_prune_development(
     repo: &mut git_stack::legacy::git::GitRepo,
     branches: &[&str],
+    household: Option<&str>,
+    retry: crate::ops::Retry,
     dry_run: bool,
 ) -> eyre::Result<()> {
     if branches.is_empty() {
         return Ok(());
     }
 
-    let remote = repo.push_remote();
-    let output = std::process::Command::new("git")
-        .arg("ls-remote")
-        .arg("--heads")
-        .arg(remote)
-        .args(branches)
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .wrap_err("Could not run `git fetch`")?
-        .wait_with_output()?;
-    if !output.status.success() {
-        eyre::bail!("Could not run `git fetch`");
-    }
-    let stdout = String::from_utf8(output.stdout).wrap_err("Could not run `git fetch`")?;
-    #[allow(clippy::needless_collect)]
-    let remote_branches: Vec<_> = stdout
-        .lines()
-        .filter_map(|l| l.split_once('\t').map(|s| s.1))
-        .filter_map(|l| l.strip_prefix("refs/heads/"))
-        .collect();
-
-    for branch in branches {
-        if !remote_branches.contains(branch) {
-            let remote_branch = format!("{remote}/{branch}");
-            log::info!("Pruning {}", remote_branch);
-            if !dry_run {
-                let mut branch = repo
-                    .raw()
-                    .find_branch(&remote_branch, git2::BranchType::Remote)?;
-                branch.delete()?;
-            }
+    let gone = crate::ops::gone_from_push_remotes(
+        branches,
+        |b| repo.push_remote_for(b).to_owned(),
+        retry,
+    )?;
+    if let Some(household) = household {
+        if !gone.is_empty()
+            && !dry_run
+            && !crate::ops::confirm(format_args!(
+                "Prune {} from stacks onto `{}`",
+                gone.join(", "),
+                household
+            ))
+        {
+            log::info!("Skipping prune of stacks onto `{}`", household);
+            return Ok(());
         }
     }
-
-    Ok(())
-}
-
-fn git_fetch_upstream(remote: &str, branch_name: &str) -> eyre::Result<()> {
-    log::debug!("git fetch {} {}", remote, branch_name);
-    // A little uncertain about some of the weirder authentication needs, just deferring to `git`
-    // instead of using `libgit2`
-    let status = std::process::Command::new("git")
-        .arg("fetch")
-        .arg(remote)
-        .arg(branch_name)
-        .status()
-        .wrap_err("Could not run `git fetch`")?;
-    if !status.success() {
-        eyre::bail!("`git fetch {} {}` failed", remote, branch_name,);
+    for remote_branch in gone {
+        log::info!("Pruning {}", remote_branch);
+        if !dry_run {
+            let mut branch = repo
+                .raw()
+                .find_branch(&remote_branch, git2::BranchType::Remote)?;
+            branch.delete()?;
+        }
     }
 
     Ok(())
@@ -1010,19 +1940,123 @@ fn git_fetch_upstream(remote: &str, branch_name: &str) -> eyre::Result<()> {
 fn git_push(
     repo: &mut git_stack::legacy::git::GitRepo,
     graph: &git_stack::legacy::graph::Graph,
+    push_style: git_stack::config::PushStyle,
+    run_hooks: bool,
+    retry: crate::ops::Retry,
     dry_run: bool,
 ) -> eyre::Result<()> {
     let mut failed = Vec::new();
+    let mut planned = Vec::new();
 
     let mut node_queue = VecDeque::new();
-    node_queue.push_back(graph.root_id());
-    while let Some(current_id) = node_queue.pop_front() {
+    node_queue.push_back((graph.root_id(), None, None, false));
+    while let Some((current_id, parent_branch, protected_branch, draft)) = node_queue.pop_front() {
         let current = graph.get(current_id).expect("all children exist");
+        let draft = !current.action.is_protected() && (draft || current.draft);
 
-        failed.extend(git_push_node(repo, current, dry_run));
+        let target = match push_style {
+            git_stack::config::PushStyle::Plain => PushTarget::Plain,
+            git_stack::config::PushStyle::GitlabMr => PushTarget::MergeRequest(parent_branch),
+            git_stack::config::PushStyle::GerritRefsFor => PushTarget::RefsFor(protected_branch),
+        };
+        failed.extend(git_push_node(
+            repo,
+            current,
+            target,
+            draft,
+            run_hooks,
+            &mut planned,
+        ));
 
+        let current_branch = current.branches.iter().map(|b| b.name.as_str()).min();
+        let parent_branch = current_branch.or(parent_branch);
+        let protected_branch = current_branch
+            .filter(|_| current.action.is_protected())
+            .or(protected_branch);
+        // Each branch is only a draft because of its own commits
+        let draft = draft && current.branches.is_empty();
         for child_id in current.children.iter().copied() {
-            node_queue.push_back(child_id);
+            node_queue.push_back((child_id, parent_branch, protected_branch, draft));
+        }
+    }
+
+    // libgit2 doesn't run hooks and `git push` would run `pre-push` once for the whole batch, so
+    // run it for each branch ahead of either
+    let pre_push = run_hooks
+        .then(|| git_stack::git::hooks(repo.raw()).ok())
+        .flatten()
+        .filter(|hooks| hooks.find_hook(repo.raw(), "pre-push").is_some());
+    let mut by_remote = indexmap::IndexMap::<String, Vec<PlannedPush>>::new();
+    for push in planned {
+        by_remote.entry(push.remote.clone()).or_default().push(push);
+    }
+    for (remote, pushes) in by_remote {
+        if dry_run {
+            for (flags, pushes) in group_by_flags(pushes) {
+                log::trace!("git {}", push_args(&remote, &flags, &pushes).join(" "));
+            }
+            continue;
+        }
+
+        let pushes = match pre_push.as_ref() {
+            Some(hooks) => {
+                let mut passed = Vec::new();
+                for mut push in pushes {
+                    match git_stack::git::run_pre_push(repo.raw(), hooks, &remote, &push.push_ref) {
+                        Ok(true) => {
+                            push.flags.insert(0, "--no-verify".to_owned());
+                            passed.push(push);
+                        }
+                        Ok(false) => {
+                            log::warn!(
+                                "`{}` was rejected by the `pre-push` hook",
+                                push.push_ref.branch
+                            );
+                            failed.push(push.push_ref.branch);
+                        }
+                        Err(err) => {
+                            log::warn!(
+                                "Could not run the `pre-push` hook for `{}`, {}",
+                                push.push_ref.branch,
+                                err
+                            );
+                            failed.push(push.push_ref.branch);
+                        }
+                    }
+                }
+                passed
+            }
+            None => pushes,
+        };
+        let (batch, mut cli): (Vec<_>, Vec<_>) = pushes.into_iter().partition(|push| push.libgit2);
+        let mut batches = group_by_flags(batch).into_iter();
+        while let Some((_, batch)) = batches.next() {
+            let refs: Vec<_> = batch.iter().map(|push| push.push_ref.clone()).collect();
+            match repo.push_refs(&remote, &refs) {
+                Ok(report) => {
+                    report_push(repo.raw(), &remote, &batch, &report);
+                    failed.extend(report.rejected.into_iter().map(|r| r.branch));
+                }
+                Err(git_stack::git::PushError::Connect(err)) => {
+                    log::debug!(
+                        "Falling back to `git push` for `{}`, libgit2 could not connect: {}",
+                        remote,
+                        err.message()
+                    );
+                    cli.extend(batch);
+                    cli.extend(batches.by_ref().flat_map(|(_, batch)| batch));
+                }
+                Err(err) => {
+                    log::warn!("Could not push to `{}`, {}", remote, err);
+                    failed.extend(batch.into_iter().map(|push| push.push_ref.branch));
+                }
+            }
+        }
+
+        for (flags, pushes) in group_by_flags(cli) {
+            let report = git_push_batch(&remote, &flags, &pushes, retry);
+            report_push(repo.raw(), &remote, &pushes, &report);
+            failed.extend(report.rejected.into_iter().map(|r| r.branch));
         }
     }
 
@@ -1033,10 +2067,168 @@ fn git_push(
     }
 }
 
+/// Branches that can go in one `git push`, as they need the same flags
+///
+/// One push can't update a ref twice, so branches sent to the same ref, like sibling stacks
+/// to `refs/for/<protected branch>`, are split across pushes.
+fn group_by_flags(pushes: Vec<PlannedPush>) -> Vec<(Vec<String>, Vec<PlannedPush>)> {
+    let mut groups = Vec::<(Vec<String>, Vec<PlannedPush>)>::new();
+    for push in pushes {
+        let group = groups.iter_mut().find(|(flags, group)| {
+            *flags == push.flags && group.iter().all(|p| p.push_ref.dst != push.push_ref.dst)
+        });
+        match group {
+            Some((_, group)) => group.push(push),
+            None => groups.push((push.flags.clone(), vec![push])),
+        }
+    }
+    groups
+}
+
+fn push_args(remote: &str, flags: &[String], pushes: &[PlannedPush]) -> Vec<String> {
+    let mut args = vec!["push".to_owned(), "--porcelain".to_owned()];
+    args.extend(flags.iter().cloned());
+    args.push(remote.to_owned());
+    args.extend(
+        pushes
+            .iter()
+            .map(|push| format!("{}:{}", push.push_ref.branch, push.push_ref.dst)),
+    );
+    args
+}
+
+/// Push `pushes` with one `git push`, reading what happened to each branch from its output
+fn git_push_batch(
+    remote: &str,
+    flags: &[String],
+    pushes: &[PlannedPush],
+    retry: crate::ops::Retry,
+) -> git_stack::git::PushReport {
+    let mut report = git_stack::git::PushReport::default();
+    let args = push_args(remote, flags, pushes);
+    log::trace!("git {}", args.join(" "));
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(&args);
+    let statuses = match crate::ops::git_remote(&mut cmd, retry) {
+        Ok(output)
+            if !output.status.success()
+                && crate::ops::refuses_atomic(&output.stderr)
+                && flags.iter().any(|f| f == "--atomic") =>
+        {
+            log::debug!(
+                "`{}` can't push atomically, pushing each branch on its own",
+                remote
+            );
+            let flags: Vec<_> = flags.iter().filter(|f| *f != "--atomic").cloned().collect();
+            return git_push_batch(remote, &flags, pushes, retry);
+        }
+        Ok(output) => crate::ops::parse_push_porcelain(&String::from_utf8_lossy(&output.stdout)),
+        Err(err) => {
+            log::debug!("`git push` failed with {}", err);
+            Vec::new()
+        }
+    };
+    for push in pushes {
+        let branch = push.push_ref.branch.clone();
+        match statuses.iter().find(|(dst, _)| *dst == push.push_ref.dst) {
+            Some((_, crate::ops::PushStatus::Pushed)) => report.pushed.push(branch),
+            Some((_, crate::ops::PushStatus::UpToDate)) => report.up_to_date.push(branch),
+            Some((_, crate::ops::PushStatus::Rejected(reason))) => {
+                report.rejected.push(git_stack::git::PushRejection {
+                    branch,
+                    reason: reason.clone(),
+                });
+            }
+            None => report.rejected.push(git_stack::git::PushRejection {
+                branch,
+                reason: "`git push` failed".to_owned(),
+            }),
+        }
+    }
+    report
+}
+
+/// Report what was pushed, setting upstreams like `git push --set-upstream`
+fn report_push(
+    repo: &git2::Repository,
+    remote: &str,
+    pushes: &[PlannedPush],
+    report: &git_stack::git::PushReport,
+) {
+    let palette_stderr = crate::ops::Palette::colored();
+    for rejection in &report.rejected {
+        log::warn!(
+            "`{}` was rejected by `{}`, {}",
+            rejection.branch,
+            remote,
+            rejection.reason
+        );
+    }
+    for push in pushes {
+        let push_ref = &push.push_ref;
+        if report.pushed.contains(&push_ref.branch) {
+            let dst = match push_ref.dst.strip_prefix("refs/heads/") {
+                Some(dst) => format!("{remote}/{dst}"),
+                None => format!("{remote} {}", push_ref.dst),
+            };
+            let _ = writeln!(
+                anstream::stderr(),
+                "{} {} to {}",
+                palette_stderr.good("Pushed"),
+                palette_stderr.highlight(&push_ref.branch),
+                dst
+            );
+        } else if report.up_to_date.contains(&push_ref.branch) {
+            log::debug!("`{}` is up to date on `{}`", push_ref.branch, remote);
+        } else {
+            continue;
+        }
+        if push.set_upstream {
+            let upstream = repo.config().and_then(|mut config| {
+                config.set_str(&format!("branch.{}.remote", push_ref.branch), remote)?;
+                config.set_str(&format!("branch.{}.merge", push_ref.branch), &push_ref.dst)
+            });
+            if let Err(err) = upstream {
+                log::warn!(
+                    "Could not set the upstream of `{}`, {}",
+                    push_ref.branch,
+                    err.message()
+                );
+            }
+        }
+    }
+}
+
+/// Where [`git_push_node`] sends a branch, per [`git_stack::config::PushStyle`]
+#[derive(Copy, Clone, Debug)]
+enum PushTarget<'b> {
+    Plain,
+    /// Ask GitLab to open a merge request, targeting the parent branch if known
+    MergeRequest(Option<&'b str>),
+    /// Push for review against the protected branch the node is stacked on
+    RefsFor(Option<&'b str>),
+}
+
+/// A branch [`git_push`] will push
+struct PlannedPush {
+    remote: String,
+    push_ref: git_stack::git::PushRef,
+    /// The `git push` flags, for when libgit2 can't push it
+    flags: Vec<String>,
+    /// Whether libgit2 can push it, which it can't with push options or a lease
+    libgit2: bool,
+    /// Make the pushed branch the upstream, like `--set-upstream`
+    set_upstream: bool,
+}
+
+/// Plan pushing the branches on `node`, marking them as drafts for review when `draft`
 fn git_push_node(
-    repo: &mut git_stack::legacy::git::GitRepo,
+    repo: &git_stack::legacy::git::GitRepo,
     node: &git_stack::legacy::graph::Node,
-    dry_run: bool,
+    target: PushTarget<'_>,
+    draft: bool,
+    run_hooks: bool,
+    planned: &mut Vec<PlannedPush>,
 ) -> Vec<String> {
     let mut failed = Vec::new();
     for branch in node.branches.iter() {
@@ -1053,30 +2245,46 @@ fn git_push_node(
                 .expect("all referenced branches exist");
             let upstream_set = raw_branch.upstream().is_ok();
 
-            let remote = repo.push_remote();
-            let mut args = vec!["push", "--force-with-lease"];
-            if !upstream_set {
-                args.push("--set-upstream");
-            }
-            args.push(remote);
-            args.push(local_branch);
-            log::trace!("git {}", args.join(" "),);
-            if !dry_run {
-                let status = std::process::Command::new("git").args(&args).status();
-                match status {
-                    Ok(status) => {
-                        if !status.success() {
-                            failed.push(local_branch.to_owned());
-                        }
-                    }
-                    Err(err) => {
-                        log::debug!("`git push` failed with {}", err);
-                        failed.push(local_branch.to_owned());
+            let remote = repo.push_remote_for(local_branch);
+            let mut flags = Vec::new();
+            if !run_hooks {
+                flags.push("--no-verify".to_owned());
+            }
+            match push_dst(repo.raw(), remote, local_branch, target, draft) {
+                Ok(Some((dst, target_flags))) => {
+                    let refs_for = matches!(target, PushTarget::RefsFor(_));
+                    if !refs_for {
+                        flags.extend(["--force-with-lease".to_owned(), "--atomic".to_owned()]);
                     }
+                    flags.extend(target_flags);
+                    planned.push(PlannedPush {
+                        remote: remote.to_owned(),
+                        push_ref: git_stack::git::PushRef {
+                            branch: local_branch.to_owned(),
+                            dst,
+                        },
+                        flags,
+                        libgit2: refs_for,
+                        set_upstream: !refs_for && !upstream_set,
+                    });
+                }
+                Ok(None) => {
+                    log::debug!(
+                        "Skipping push of `{}`, not pushed to `{}` per `push.default` or `remote.{}.push`",
+                        branch,
+                        remote,
+                        remote
+                    );
+                }
+                Err(err) => {
+                    log::warn!("Skipping push of `{}`, {}", branch, err);
+                    failed.push(local_branch.to_owned());
                 }
             }
         } else if node.action.is_protected() {
             log::debug!("Skipping push of `{}`, protected", branch);
+        } else if draft {
+            log::debug!("Skipping push of `{}`, draft", branch);
         } else {
             log::debug!("Skipping push of `{}`", branch);
         }
@@ -1085,14 +2293,58 @@ fn git_push_node(
     failed
 }
 
+/// The ref `local_branch` updates on `remote` for `target`, with the push options that go with it
+///
+/// `None` when `push.default` or `remote.<name>.push` don't push `local_branch` to `remote`.
+fn push_dst(
+    repo: &git2::Repository,
+    remote: &str,
+    local_branch: &str,
+    target: PushTarget<'_>,
+    draft: bool,
+) -> eyre::Result<Option<(String, Vec<String>)>> {
+    match target {
+        PushTarget::Plain | PushTarget::MergeRequest(_) => {
+            let Some(push_target) = git_stack::git::push_target(repo, remote, local_branch) else {
+                return Ok(None);
+            };
+            let mut flags = Vec::new();
+            if let PushTarget::MergeRequest(parent_branch) = target {
+                flags.extend(["-o".to_owned(), "merge_request.create".to_owned()]);
+                if let Some(parent_branch) = parent_branch {
+                    flags.extend([
+                        "-o".to_owned(),
+                        format!("merge_request.target={parent_branch}"),
+                    ]);
+                }
+                if draft {
+                    flags.extend(["-o".to_owned(), "merge_request.draft".to_owned()]);
+                }
+            }
+            Ok(Some((format!("refs/heads/{push_target}"), flags)))
+        }
+        PushTarget::RefsFor(protected_branch) => {
+            let Some(protected_branch) = protected_branch else {
+                eyre::bail!("not stacked on a protected branch to review against");
+            };
+            // Gerrit tracks changes by `Change-Id`, so nothing is force-pushed
+            // Drafts are sent as work-in-progress changes
+            let wip = if draft { "%wip" } else { "" };
+            Ok(Some((
+                format!("refs/for/{protected_branch}{wip}"),
+                Vec::new(),
+            )))
+        }
+    }
+}
+
 fn list(
     writer: &mut dyn Write,
-    repo: &git_stack::legacy::git::GitRepo,
+    head_branch: &git_stack::legacy::git::Branch,
     graph: &git_stack::legacy::graph::Graph,
     protected_branches: &git_stack::legacy::git::Branches,
     palette: &crate::ops::Palette,
 ) -> Result<(), std::io::Error> {
-    let head_branch = repo.head_branch().unwrap();
     for node in graph.breadth_first_iter() {
         let protected = protected_branches.get(node.commit.id);
         let mut branches: Vec<_> = node.branches.iter().collect();
@@ -1106,7 +2358,59 @@ fn list(
             writeln!(
                 writer,
                 "{}",
-                format_branch_name(b, node, &head_branch, protected_branches, palette)
+                format_branch_name(b, node, head_branch, protected_branches, palette)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`list`] with `<branch>\t<parent>\t<ahead>\t<behind>\t<status>` columns, `-` where a
+/// column doesn't apply
+///
+/// Ahead and behind are relative to the branch on the push remote.  The status is one of `drop`,
+/// `merge-commit`, `needs-restack`, `pushed`, `ahead`, `behind`, `diverged`, `draft`, or `ready`.
+fn list_verbose(
+    writer: &mut dyn Write,
+    repo: &git_stack::legacy::git::GitRepo,
+    head_branch: &git_stack::legacy::git::Branch,
+    graph: &git_stack::legacy::graph::Graph,
+    protected_branches: &git_stack::legacy::git::Branches,
+    palette: &crate::ops::Palette,
+) -> Result<(), std::io::Error> {
+    // The closest branch beneath each commit, preferring local branches
+    let mut parents: std::collections::HashMap<git2::Oid, String> = Default::default();
+    for node in graph.breadth_first_iter() {
+        let parent = parents.get(&node.commit.id).cloned();
+        let mut branches: Vec<_> = node.branches.iter().collect();
+        branches.sort_by_key(|b| (b.remote.is_some(), b.to_string()));
+        if let Some(owner) = branches.first().map(|b| b.to_string()).or(parent.clone()) {
+            for child_id in &node.children {
+                parents.insert(*child_id, owner.clone());
+            }
+        }
+
+        let protected = protected_branches.get(node.commit.id);
+        branches.sort();
+        for b in branches {
+            if b.remote.is_some() || protected.into_iter().flatten().contains(&b) {
+                // See `list`
+                continue;
+            }
+            let relation = commit_relation(repo, b.id, b.push_id);
+            let (ahead, behind) = match relation {
+                Some((ahead, behind)) => (ahead.to_string(), behind.to_string()),
+                None => ("-".to_owned(), "-".to_owned()),
+            };
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                format_branch_name(b, node, head_branch, protected_branches, palette),
+                parent.as_deref().unwrap_or("-"),
+                ahead,
+                behind,
+                branch_status_word(repo, node, relation),
             )?;
         }
     }
@@ -1114,12 +2418,47 @@ fn list(
     Ok(())
 }
 
+/// [`format_branch_status`] and [`format_commit_status`] as a single word for scripts
+fn branch_status_word(
+    repo: &git_stack::legacy::git::GitRepo,
+    node: &git_stack::legacy::graph::Node,
+    relation: Option<(usize, usize)>,
+) -> &'static str {
+    if node.action.is_delete() {
+        "drop"
+    } else if 1 < repo
+        .raw()
+        .find_commit(node.commit.id)
+        .unwrap_or_else(|e| panic!("Unexpected git2 error: {e}"))
+        .parent_count()
+    {
+        "merge-commit"
+    } else if node.needs_restack {
+        "needs-restack"
+    } else {
+        match relation {
+            Some((0, 0)) => "pushed",
+            Some((_, 0)) => "ahead",
+            Some((0, _)) => "behind",
+            Some(_) => "diverged",
+            None if node.draft => "draft",
+            None if node.pushable => "ready",
+            None => "-",
+        }
+    }
+}
+
 struct DisplayTree<'r> {
     repo: &'r git_stack::legacy::git::GitRepo,
     graph: &'r git_stack::legacy::graph::Graph,
+    head_branch: Option<git_stack::legacy::git::Branch>,
     protected_branches: git_stack::legacy::git::Branches,
     show: git_stack::config::ShowCommits,
+    icons: git_stack::config::Icons,
     stacked: bool,
+    base_history: usize,
+    max_width: Option<usize>,
+    signatures: bool,
 }
 
 impl<'r> DisplayTree<'r> {
@@ -1130,22 +2469,58 @@ impl<'r> DisplayTree<'r> {
         Self {
             repo,
             graph,
+            head_branch: Default::default(),
             protected_branches: Default::default(),
             show: Default::default(),
+            icons: Default::default(),
             stacked: Default::default(),
+            base_history: Default::default(),
+            max_width: Default::default(),
+            signatures: Default::default(),
         }
     }
 
+    /// Mark this branch as checked out, rather than the repo's `HEAD`
+    pub(crate) fn head_branch(
+        mut self,
+        head_branch: Option<git_stack::legacy::git::Branch>,
+    ) -> Self {
+        self.head_branch = head_branch;
+        self
+    }
+
     pub(crate) fn show(mut self, show: git_stack::config::ShowCommits) -> Self {
         self.show = show;
         self
     }
 
+    pub(crate) fn icons(mut self, icons: git_stack::config::Icons) -> Self {
+        self.icons = icons;
+        self
+    }
+
     pub(crate) fn stacked(mut self, stacked: bool) -> Self {
         self.stacked = stacked;
         self
     }
 
+    pub(crate) fn base_history(mut self, base_history: usize) -> Self {
+        self.base_history = base_history;
+        self
+    }
+
+    /// Truncate lines wider than this, rather than letting the terminal wrap them
+    pub(crate) fn max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Mark whether each development commit's signature checks out
+    pub(crate) fn signatures(mut self, signatures: bool) -> Self {
+        self.signatures = signatures;
+        self
+    }
+
     pub(crate) fn protected_branches(
         mut self,
         protected_branches: &git_stack::legacy::git::Branches,
@@ -1157,7 +2532,10 @@ impl<'r> DisplayTree<'r> {
 
 impl std::fmt::Display for DisplayTree<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        let head_branch = self.repo.head_branch().unwrap();
+        let head_branch = self
+            .head_branch
+            .clone()
+            .unwrap_or_else(|| self.repo.head_branch().unwrap());
 
         let is_visible: Box<dyn Fn(&git_stack::legacy::graph::Node) -> bool> = match self.show {
             git_stack::config::ShowCommits::All => Box::new(|_| true),
@@ -1190,8 +2568,76 @@ impl std::fmt::Display for DisplayTree<'_> {
         } else {
             tree.sort();
         }
-        let tree = tree.into_display(self.repo, &head_branch, &self.protected_branches);
-        tree.fmt(f)
+        let signatures = self.signatures.then(|| {
+            let ids = self
+                .graph
+                .breadth_first_iter()
+                .filter(|node| !node.action.is_protected())
+                .map(|node| node.commit.id);
+            crate::ops::verify_signatures(self.repo.raw(), ids)
+        });
+        let tree = tree.into_display(
+            self.repo,
+            &head_branch,
+            &self.protected_branches,
+            self.icons,
+            signatures.as_ref(),
+        );
+        let Some(max_width) = self.max_width else {
+            tree.fmt(f)?;
+            return self.fmt_base_history(f);
+        };
+
+        let mut rendered = String::new();
+        std::fmt::Write::write_fmt(&mut rendered, format_args!("{tree}"))?;
+        self.fmt_base_history(&mut rendered)?;
+        let ellipsis = glyphs(self.icons).ellipsis;
+        for line in rendered.lines() {
+            writeln!(
+                f,
+                "{}",
+                crate::ops::truncate_line(line, max_width, ellipsis)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl DisplayTree<'_> {
+    /// Recently landed protected commits, newest first, to give context for the stack
+    fn fmt_base_history(&self, f: &mut dyn std::fmt::Write) -> Result<(), std::fmt::Error> {
+        if self.base_history == 0 {
+            return Ok(());
+        }
+
+        let palette = crate::ops::Palette::colored();
+        let mut current = self.repo.raw().find_commit(self.graph.root_id()).ok();
+        for _ in 0..self.base_history {
+            let Some(parent) = current.and_then(|c| c.parent(0).ok()) else {
+                break;
+            };
+            let abbrev_id = parent
+                .as_object()
+                .short_id()
+                .unwrap_or_else(|e| panic!("Unexpected git2 error: {e}"));
+            let summary = self
+                .repo
+                .find_commit(parent.id())
+                .map(|c| c.display_summary().to_string())
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "{}",
+                palette.hint(format_args!(
+                    "{} {} {}",
+                    glyphs(self.icons).history,
+                    abbrev_id.as_str().unwrap_or_default(),
+                    summary
+                ))
+            )?;
+            current = Some(parent);
+        }
+        Ok(())
     }
 }
 
@@ -1268,7 +2714,6 @@ fn append_children<'r>(
                             tree.stacks[0].push(child_tree);
                         }
                         parent_node = node;
-                        continue;
                     }
                     _ => {
                         let child_tree =
@@ -1315,7 +2760,7 @@ struct Tree<'r> {
 
 impl<'r> Tree<'r> {
     fn sort(&mut self) {
-        self.stacks.sort_by_key(|s| s[0].weight);
+        self.sort_stacks();
         for stack in self.stacks.iter_mut() {
             for child in stack.iter_mut() {
                 child.sort();
@@ -1324,7 +2769,7 @@ impl<'r> Tree<'r> {
     }
 
     fn linearize(&mut self) {
-        self.stacks.sort_by_key(|s| s[0].weight);
+        self.sort_stacks();
         for stack in self.stacks.iter_mut() {
             for child in stack.iter_mut() {
                 child.linearize();
@@ -1341,61 +2786,96 @@ impl<'r> Tree<'r> {
         }
     }
 
+    /// Order sibling stacks by weight
+    ///
+    /// Ties are broken by branch name and then by the oldest commit so the output doesn't shuffle
+    /// as commit ids change across rebases.
+    fn sort_stacks(&mut self) {
+        self.stacks.sort_by(|a, b| {
+            let a_key = (a[0].weight, stack_branch_name(a), a[0].root.commit.time);
+            let b_key = (b[0].weight, stack_branch_name(b), b[0].root.commit.time);
+            a_key.cmp(&b_key)
+        });
+    }
+
+    /// The first branch name, in display order
+    fn branch_name(&self) -> Option<&'r str> {
+        let root_name = self.root.branches.iter().map(|b| b.name.as_str()).min();
+        root_name.or_else(|| self.stacks.iter().find_map(|s| stack_branch_name(s)))
+    }
+
     fn into_display(
         self,
         repo: &'r git_stack::legacy::git::GitRepo,
         head_branch: &'r git_stack::legacy::git::Branch,
         protected_branches: &'r git_stack::legacy::git::Branches,
+        icons: git_stack::config::Icons,
+        signatures: Option<&'r Signatures>,
     ) -> termtree::Tree<RenderNode<'r>> {
+        let glyphs = glyphs(icons);
         let root = RenderNode {
             repo,
             head_branch,
             protected_branches,
+            icons,
+            signatures,
             node: Some(self.root),
         };
-        let mut tree = termtree::Tree::new(root).with_glyphs(GLYPHS);
+        let mut tree = termtree::Tree::new(root).with_glyphs(glyphs.item);
         let joint = RenderNode {
             repo,
             head_branch,
             protected_branches,
+            icons,
+            signatures,
             node: None,
         };
         let stacks_len = self.stacks.len();
         for (i, stack) in self.stacks.into_iter().enumerate() {
             if i < stacks_len - 1 {
-                let mut stack_tree = termtree::Tree::new(joint).with_glyphs(JOINT_GLYPHS);
+                let mut stack_tree = termtree::Tree::new(joint).with_glyphs(glyphs.joint);
                 for child_tree in stack {
-                    stack_tree.push(child_tree.into_display(repo, head_branch, protected_branches));
+                    stack_tree.push(child_tree.into_display(
+                        repo,
+                        head_branch,
+                        protected_branches,
+                        icons,
+                        signatures,
+                    ));
                 }
                 tree.push(stack_tree);
             } else {
                 let stack_len = stack.len();
                 for (j, child_tree) in stack.into_iter().enumerate() {
                     if i != 0 && j == 0 {
-                        tree.push(termtree::Tree::new(joint).with_glyphs(SPACE_GLYPHS));
+                        tree.push(termtree::Tree::new(joint).with_glyphs(glyphs.space));
                     }
                     let child = RenderNode {
                         repo,
                         head_branch,
                         protected_branches,
+                        icons,
+                        signatures,
                         node: Some(child_tree.root),
                     };
-                    tree.push(termtree::Tree::new(child).with_glyphs(GLYPHS));
+                    tree.push(termtree::Tree::new(child).with_glyphs(glyphs.item));
                     if !child_tree.stacks.is_empty() {
                         for child_stack in child_tree.stacks {
                             let mut stack_tree =
-                                termtree::Tree::new(joint).with_glyphs(JOINT_GLYPHS);
+                                termtree::Tree::new(joint).with_glyphs(glyphs.joint);
                             for child_tree in child_stack {
                                 stack_tree.push(child_tree.into_display(
                                     repo,
                                     head_branch,
                                     protected_branches,
+                                    icons,
+                                    signatures,
                                 ));
                             }
                             tree.push(stack_tree);
                         }
                         if j < stack_len {
-                            tree.push(termtree::Tree::new(joint).with_glyphs(SPACE_GLYPHS));
+                            tree.push(termtree::Tree::new(joint).with_glyphs(glyphs.space));
                         }
                     }
                 }
@@ -1405,6 +2885,10 @@ impl<'r> Tree<'r> {
     }
 }
 
+fn stack_branch_name<'r>(stack: &[Tree<'r>]) -> Option<&'r str> {
+    stack.iter().find_map(|t| t.branch_name())
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum Weight {
     Commit(usize),
@@ -1444,36 +2928,87 @@ impl std::ops::AddAssign<usize> for Weight {
     }
 }
 
+type Signatures = std::collections::HashMap<git2::Oid, crate::ops::SignatureStatus>;
+
 #[derive(Copy, Clone, Debug)]
 struct RenderNode<'r> {
     repo: &'r git_stack::legacy::git::GitRepo,
     head_branch: &'r git_stack::legacy::git::Branch,
     protected_branches: &'r git_stack::legacy::git::Branches,
+    icons: git_stack::config::Icons,
+    signatures: Option<&'r Signatures>,
     node: Option<&'r git_stack::legacy::graph::Node>,
 }
 
-const GLYPHS: termtree::GlyphPalette = termtree::GlyphPalette {
-    middle_item: "⌽",
-    last_item: "⌽",
-    item_indent: " ",
-    skip_indent: " ",
-    ..termtree::GlyphPalette::new()
-};
+/// Tree glyphs for an icon theme
+struct Glyphs {
+    item: termtree::GlyphPalette,
+    space: termtree::GlyphPalette,
+    joint: termtree::GlyphPalette,
+    history: &'static str,
+    ellipsis: &'static str,
+}
 
-const SPACE_GLYPHS: termtree::GlyphPalette = termtree::GlyphPalette {
-    middle_item: "│",
-    last_item: " ",
-    item_indent: " ",
-    skip_indent: " ",
-    ..termtree::GlyphPalette::new()
+const UNICODE_GLYPHS: Glyphs = Glyphs {
+    item: termtree::GlyphPalette {
+        middle_item: "⌽",
+        last_item: "⌽",
+        item_indent: " ",
+        skip_indent: " ",
+        ..termtree::GlyphPalette::new()
+    },
+    space: termtree::GlyphPalette {
+        middle_item: "│",
+        last_item: " ",
+        item_indent: " ",
+        skip_indent: " ",
+        ..termtree::GlyphPalette::new()
+    },
+    joint: termtree::GlyphPalette {
+        item_indent: "─┐",
+        skip_indent: " ",
+        ..termtree::GlyphPalette::new()
+    },
+    history: "┊",
+    ellipsis: "…",
 };
 
-const JOINT_GLYPHS: termtree::GlyphPalette = termtree::GlyphPalette {
-    item_indent: "─┐",
-    skip_indent: " ",
-    ..termtree::GlyphPalette::new()
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    item: termtree::GlyphPalette {
+        middle_item: "*",
+        last_item: "*",
+        item_indent: " ",
+        middle_skip: "|",
+        last_skip: " ",
+        skip_indent: " ",
+    },
+    space: termtree::GlyphPalette {
+        middle_item: "|",
+        last_item: " ",
+        item_indent: " ",
+        middle_skip: "|",
+        last_skip: " ",
+        skip_indent: " ",
+    },
+    joint: termtree::GlyphPalette {
+        middle_item: "|",
+        last_item: "`",
+        item_indent: "-+",
+        middle_skip: "|",
+        last_skip: " ",
+        skip_indent: " ",
+    },
+    history: ":",
+    ellipsis: "...",
 };
 
+fn glyphs(icons: git_stack::config::Icons) -> &'static Glyphs {
+    match icons {
+        git_stack::config::Icons::Unicode | git_stack::config::Icons::Emoji => &UNICODE_GLYPHS,
+        git_stack::config::Icons::Ascii => &ASCII_GLYPHS,
+    }
+}
+
 // Shared implementation doesn't mean shared requirements, we want to track according to
 // requirements
 #[allow(clippy::if_same_then_else)]
@@ -1534,18 +3069,28 @@ impl std::fmt::Display for RenderNode<'_> {
                                     self.protected_branches,
                                     &palette
                                 ),
-                                format_branch_status(b, self.repo, node, &palette),
+                                format_branch_status(b, self.repo, node, self.icons, &palette),
                             )
                         })
                         .join(", ")
                 )?;
             }
 
-            write!(f, "{} ", format_commit_status(self.repo, node, &palette))?;
+            write!(
+                f,
+                "{}",
+                format_commit_status(self.repo, node, self.icons, &palette)
+            )?;
+            if let Some(signature) = self.signatures.and_then(|s| s.get(&node.commit.id)) {
+                write!(f, " {}", format_signature(*signature, self.icons, &palette))?;
+            }
+            write!(f, " ")?;
 
-            let summary = String::from_utf8_lossy(&node.commit.summary);
+            let summary = node.commit.display_summary();
             if node.action.is_protected() {
                 write!(f, "{}", palette.hint(summary))?;
+            } else if node.ignored {
+                write!(f, "{summary}")?;
             } else if node.commit.fixup_summary().is_some() {
                 // Needs to be squashed
                 write!(f, "{}", palette.warn(summary))?;
@@ -1591,12 +3136,13 @@ fn format_branch_status<'d>(
     branch: &'d git_stack::legacy::git::Branch,
     repo: &'d git_stack::legacy::git::GitRepo,
     node: &'d git_stack::legacy::graph::Node,
+    icons: git_stack::config::Icons,
     palette: &'d crate::ops::Palette,
 ) -> String {
     // See format_commit_status
     if node.action.is_protected() {
         if branch.pull_id.is_none() {
-            format!(" {}", palette.warn("(no remote)"))
+            format!(" {}", palette.warn(status(icons, "🔌", "no remote")))
         } else {
             String::new()
         }
@@ -1609,29 +3155,61 @@ fn format_branch_status<'d>(
         .parent_count()
     {
         String::new()
+    } else if node.needs_restack {
+        format!(" {}", palette.warn(status(icons, "🧱", "needs restack")))
     } else {
         if node.branches.is_empty() {
             String::new()
         } else {
+            // Only call out the remote when it isn't the one every other branch goes to
+            let push_remote = branch
+                .local_name()
+                .map(|name| repo.push_remote_for(name))
+                .filter(|remote| *remote != repo.push_remote());
+            let to = push_remote
+                .map(|remote| format!(" to {remote}"))
+                .unwrap_or_default();
+            let of = push_remote
+                .map(|remote| format!(" of {remote}"))
+                .unwrap_or_default();
+            let than = push_remote
+                .map(|remote| format!(" {remote}"))
+                .unwrap_or_default();
             match commit_relation(repo, branch.id, branch.push_id) {
                 Some((0, 0)) => {
-                    format!(" {}", palette.good("(pushed)"))
+                    format!(
+                        " {}",
+                        palette.good(status(icons, "✅", format_args!("pushed{to}")))
+                    )
                 }
                 Some((local, 0)) => {
-                    format!(" {}", palette.info(format!("({local} ahead)")))
+                    format!(
+                        " {}",
+                        palette.info(status(icons, "🔼", format_args!("{local} ahead{of}")))
+                    )
                 }
                 Some((0, remote)) => {
-                    format!(" {}", palette.warn(format!("({remote} behind)")))
+                    format!(
+                        " {}",
+                        palette.warn(status(icons, "🔽", format_args!("{remote} behind{than}")))
+                    )
                 }
                 Some((local, remote)) => {
                     format!(
                         " {}",
-                        palette.warn(format!("({local} ahead, {remote} behind)")),
+                        palette.warn(status(
+                            icons,
+                            "🔃",
+                            format_args!("{local} ahead, {remote} behind{than}")
+                        )),
                     )
                 }
                 None => {
                     if node.pushable {
-                        format!(" {}", palette.info("(ready)"))
+                        format!(
+                            " {}",
+                            palette.info(status(icons, "🚀", format_args!("ready{to}")))
+                        )
                     } else {
                         String::new()
                     }
@@ -1644,25 +3222,63 @@ fn format_branch_status<'d>(
 fn format_commit_status<'d>(
     repo: &'d git_stack::legacy::git::GitRepo,
     node: &'d git_stack::legacy::graph::Node,
+    icons: git_stack::config::Icons,
     palette: &'d crate::ops::Palette,
 ) -> String {
     // See format_branch_status
     if node.action.is_protected() {
         String::new()
     } else if node.action.is_delete() {
-        format!(" {}", palette.error("(drop)"))
+        format!(" {}", palette.error(status(icons, "❌", "drop")))
     } else if 1 < repo
         .raw()
         .find_commit(node.commit.id)
         .unwrap_or_else(|e| panic!("Unexpected git2 error: {e}"))
         .parent_count()
     {
-        format!(" {}", palette.error("(merge commit)"))
+        format!(" {}", palette.error(status(icons, "🔀", "merge commit")))
+    } else if node.draft {
+        format!(" {}", palette.warn(status(icons, "📝", "draft")))
     } else {
         String::new()
     }
 }
 
+fn format_signature(
+    signature: crate::ops::SignatureStatus,
+    icons: git_stack::config::Icons,
+    palette: &crate::ops::Palette,
+) -> String {
+    match signature {
+        crate::ops::SignatureStatus::Good => {
+            palette.good(status(icons, "🔏", "signed")).to_string()
+        }
+        crate::ops::SignatureStatus::UnknownKey => {
+            palette.warn(status(icons, "❔", "unknown key")).to_string()
+        }
+        crate::ops::SignatureStatus::Bad => palette
+            .error(status(icons, "⛔", "bad signature"))
+            .to_string(),
+        crate::ops::SignatureStatus::Unsigned => {
+            palette.hint(status(icons, "🔓", "unsigned")).to_string()
+        }
+    }
+}
+
+/// A status marker, with `emoji` standing in for the parentheses in the emoji theme
+fn status(
+    icons: git_stack::config::Icons,
+    emoji: &str,
+    description: impl std::fmt::Display,
+) -> String {
+    match icons {
+        git_stack::config::Icons::Unicode | git_stack::config::Icons::Ascii => {
+            format!("({description})")
+        }
+        git_stack::config::Icons::Emoji => format!("{emoji} {description}"),
+    }
+}
+
 fn commit_relation(
     repo: &git_stack::legacy::git::GitRepo,
     local: git2::Oid,
@@ -0,0 +1,194 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Summarize the current stack on one line, for shell prompts
+///
+/// Only the branches around `HEAD` are looked at, skipping the planning `git stack` does, so this
+/// stays fast in large repositories.
+#[derive(clap::Args)]
+pub(crate) struct StatusArgs {
+    /// Print `<branch> <position>/<count> <ahead> <behind> <clean|dirty> <base>`
+    ///
+    /// `HEAD` stands in for a detached branch and `-` for anything without a protected base.
+    #[arg(long)]
+    porcelain: bool,
+}
+
+impl StatusArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stdout_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+
+        let repo_config = git_stack::config::RepoConfig::from_all(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+
+        let status = Status::from_repo(&repo, &protected).with_code(proc_exit::Code::FAILURE)?;
+
+        let mut stdout = anstream::stdout().lock();
+        if self.porcelain {
+            let (ahead, behind, base) = match status.base.as_ref() {
+                Some(base) => (
+                    base.ahead.to_string(),
+                    base.behind.to_string(),
+                    base.name.as_str(),
+                ),
+                None => ("-".to_owned(), "-".to_owned(), "-"),
+            };
+            let _ = writeln!(
+                stdout,
+                "{} {}/{} {} {} {} {}",
+                status.branch.as_deref().unwrap_or("HEAD"),
+                status.position,
+                status.count,
+                ahead,
+                behind,
+                if status.dirty { "dirty" } else { "clean" },
+                base,
+            );
+        } else {
+            let branch = match status.branch.as_deref() {
+                Some(branch) => branch.to_owned(),
+                None => format!(
+                    "HEAD ({})",
+                    repo.head()
+                        .ok()
+                        .and_then(|h| h.peel_to_commit().ok())
+                        .and_then(|c| c.as_object().short_id().ok())
+                        .and_then(|id| id.as_str().map(ToOwned::to_owned))
+                        .unwrap_or_default()
+                ),
+            };
+            let _ = write!(
+                stdout,
+                "{} {}",
+                stdout_palette.highlight(branch),
+                stdout_palette.hint(format_args!("{}/{}", status.position, status.count))
+            );
+            if let Some(base) = status.base.as_ref() {
+                let _ = write!(
+                    stdout,
+                    " on {} ({} ahead, {} behind)",
+                    stdout_palette.info(&base.name),
+                    base.ahead,
+                    base.behind
+                );
+            }
+            if status.dirty {
+                let _ = write!(stdout, " {}", stdout_palette.warn("dirty"));
+            }
+            let _ = writeln!(stdout);
+        }
+
+        Ok(())
+    }
+}
+
+struct Status {
+    branch: Option<String>,
+    /// How many branches of the stack are at or beneath `HEAD`
+    position: usize,
+    /// How many branches are in the stack, from the base up through `HEAD` and its descendants
+    count: usize,
+    dirty: bool,
+    base: Option<StatusBase>,
+}
+
+struct StatusBase {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+impl Status {
+    fn from_repo(
+        repo: &git2::Repository,
+        protected: &git_stack::git::ProtectedBranches,
+    ) -> Result<Self, git2::Error> {
+        let head = repo.head()?;
+        let head_id = head.peel_to_commit()?.id();
+        let branch = head
+            .is_branch()
+            .then(|| head.shorthand().map(ToOwned::to_owned))
+            .flatten();
+
+        let mut protected_branches = Vec::new();
+        let mut stack_branches = Vec::new();
+        for entry in repo.branches(Some(git2::BranchType::Local))? {
+            let (local, _) = entry?;
+            let (Some(name), Some(id)) = (local.name()?, local.get().target()) else {
+                continue;
+            };
+            if protected.is_protected(name) {
+                protected_branches.push((name.to_owned(), id));
+            } else {
+                stack_branches.push(id);
+            }
+        }
+
+        // The protected branch `HEAD` forked from most recently
+        let mut base: Option<(String, git2::Oid, usize)> = None;
+        protected_branches.sort();
+        for (name, id) in protected_branches {
+            let Ok(merge_base) = repo.merge_base(head_id, id) else {
+                continue;
+            };
+            let (distance, _) = repo.graph_ahead_behind(head_id, merge_base)?;
+            if base.as_ref().map(|(_, _, d)| distance < *d).unwrap_or(true) {
+                base = Some((name, id, distance));
+            }
+        }
+
+        let mut position = 0;
+        let mut count = 0;
+        if let Some((_, base_id, _)) = base.as_ref() {
+            let fork_id = repo.merge_base(head_id, *base_id)?;
+            for id in stack_branches {
+                if id == fork_id || !repo.graph_descendant_of(id, fork_id)? {
+                    continue;
+                }
+                if id == head_id || repo.graph_descendant_of(head_id, id)? {
+                    position += 1;
+                    count += 1;
+                } else if repo.graph_descendant_of(id, head_id)? {
+                    count += 1;
+                }
+            }
+        }
+
+        let dirty = !repo
+            .statuses(Some(
+                git2::StatusOptions::new()
+                    .include_untracked(false)
+                    .include_ignored(false),
+            ))?
+            .is_empty();
+
+        let base = match base {
+            Some((name, base_id, _)) => {
+                let (ahead, behind) = repo.graph_ahead_behind(head_id, base_id)?;
+                Some(StatusBase {
+                    name,
+                    ahead,
+                    behind,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            branch,
+            position,
+            count,
+            dirty,
+            base,
+        })
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Open a pull request for each branch in the current stack
+///
+/// Each pull request targets the branch below it in the stack, retargeting existing pull requests
+/// whose parent changed.  Branches need to be pushed first, e.g. with `git stack --push`.
+/// Requires `stack.forge`.
+#[derive(clap::Args)]
+pub(crate) struct SubmitArgs {
+    /// Don't actually create or update pull requests
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl SubmitArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        crate::lock::require_writable(self.dry_run)?;
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        for (branch, remote) in repo_config.branch_push_remotes() {
+            repo.set_branch_push_remote(branch, remote);
+        }
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let forge = match repo_config.forge() {
+            git_stack::config::Forge::None => {
+                return Err(proc_exit::sysexits::CONFIG_ERR
+                    .with_message("no forge configured, set `stack.forge=github`"));
+            }
+            git_stack::config::Forge::Github => {
+                crate::github::Github::new(repo_config.github_token())
+            }
+        };
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let head_id = repo.head_commit().id;
+        let base = crate::ops::resolve_implicit_base(
+            &repo,
+            head_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let merge_base_oid = repo
+            .merge_base(base.id, head_id)
+            .ok_or_else(|| eyre::format_err!("could not find base between {base} and HEAD"))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let stack_branches = branches.dependents(&repo, merge_base_oid, head_id);
+        let graph = git_stack::graph::Graph::from_branches(&repo, branches.all())
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut stack: Vec<_> = stack_branches
+            .iter()
+            .flat_map(|(id, b)| b.iter().map(move |b| (id, b)))
+            .filter(|(_, b)| b.kind() != git_stack::graph::BranchKind::Protected)
+            .filter_map(|(id, b)| b.local_name().map(|n| (id, n.to_owned())))
+            .collect();
+        stack.sort_by_key(|(id, name)| (repo.commit_count(merge_base_oid, *id), name.clone()));
+        if stack.is_empty() {
+            return Err(proc_exit::sysexits::USAGE_ERR
+                .with_message("no development branches in the current stack"));
+        }
+
+        let mut submitted = Vec::new();
+        let mut failed = Vec::new();
+        for (branch_id, name) in stack {
+            let Some(parent) = crate::ops::parent_branch(&graph, branch_id) else {
+                log::debug!("Skipping `{name}`, no parent branch");
+                continue;
+            };
+
+            let remote = repo.push_remote_for(&name);
+            let head = crate::github::Head::new(repo.raw(), remote, &name);
+            let pushed = head
+                .as_ref()
+                .and_then(|head| repo.find_remote_branch(remote, &head.name))
+                .map(|b| b.id == branch_id)
+                .unwrap_or(false);
+            let (true, Some(head)) = (pushed, head) else {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{}: skipping {}, push it first with `git stack --push`",
+                    stderr_palette.warn("warning"),
+                    stderr_palette.highlight(&name),
+                );
+                submitted.push((parent, name, "(not pushed)".to_owned()));
+                continue;
+            };
+
+            let pull_requests = match forge.open_pull_requests(&head) {
+                Ok(pull_requests) => pull_requests,
+                Err(err) => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: could not look up pull requests for {}: {}",
+                        stderr_palette.error("error"),
+                        stderr_palette.highlight(&name),
+                        err
+                    );
+                    failed.push(name);
+                    continue;
+                }
+            };
+
+            let result = if let Some(pull_request) = pull_requests.into_iter().next() {
+                if pull_request.base != parent {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{} #{} ({}) from {} to {}",
+                        stderr_palette.good("Retargeting"),
+                        pull_request.number,
+                        stderr_palette.highlight(&name),
+                        pull_request.base,
+                        stderr_palette.highlight(&parent)
+                    );
+                    if !self.dry_run {
+                        if let Err(err) = forge.set_base(pull_request.number, &parent) {
+                            let _ = writeln!(
+                                anstream::stderr(),
+                                "{}: could not retarget #{}: {}",
+                                stderr_palette.error("error"),
+                                pull_request.number,
+                                err
+                            );
+                            failed.push(name.clone());
+                        }
+                    }
+                }
+                Ok(pull_request.url)
+            } else {
+                let _ = writeln!(
+                    anstream::stderr(),
+                    "{} pull request for {} onto {}",
+                    stderr_palette.good("Creating"),
+                    stderr_palette.highlight(&name),
+                    stderr_palette.highlight(&parent)
+                );
+                if self.dry_run {
+                    Ok("(new)".to_owned())
+                } else {
+                    let (title, body) = describe(&repo, &graph, branch_id, &name);
+                    forge
+                        .create_pull_request(&head, &parent, &title, &body)
+                        .map(|pull_request| pull_request.url)
+                }
+            };
+            match result {
+                Ok(url) => {
+                    submitted.push((parent, name, url));
+                }
+                Err(err) => {
+                    let _ = writeln!(
+                        anstream::stderr(),
+                        "{}: could not open a pull request for {}: {}",
+                        stderr_palette.error("error"),
+                        stderr_palette.highlight(&name),
+                        err
+                    );
+                    failed.push(name);
+                }
+            }
+        }
+
+        let _ = write!(anstream::stdout(), "{}", render(&submitted));
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(proc_exit::Code::FAILURE
+                .with_message(format!("could not submit {}", failed.join(", "))))
+        }
+    }
+}
+
+/// Title and body for a new pull request, like `gh pr create --fill`
+fn describe(
+    repo: &git_stack::git::GitRepo,
+    graph: &git_stack::graph::Graph,
+    branch_id: git2::Oid,
+    name: &str,
+) -> (String, String) {
+    let mut commit_ids = vec![branch_id];
+    let mut current_id = branch_id;
+    while let Some(parent_id) = graph.primary_parent_of(current_id) {
+        if graph.branches.get(parent_id).is_some() {
+            break;
+        }
+        commit_ids.push(parent_id);
+        current_id = parent_id;
+    }
+
+    if let [commit_id] = commit_ids.as_slice() {
+        let commit = repo.raw().find_commit(*commit_id).ok();
+        let message = commit
+            .as_ref()
+            .map(|c| git_stack::git::decode_message(c.message_bytes(), c.message_encoding()))
+            .unwrap_or_default();
+        let (title, body) = message.split_once('\n').unwrap_or((&message, ""));
+        (title.trim().to_owned(), body.trim().to_owned())
+    } else {
+        let body = commit_ids
+            .iter()
+            .rev()
+            .filter_map(|id| repo.find_commit(*id))
+            .map(|c| format!("- {}\n", c.summary))
+            .collect();
+        (name.to_owned(), body)
+    }
+}
+
+/// Each submitted branch beneath its parent, with its pull request
+fn render(submitted: &[(String, String, String)]) -> String {
+    fn subtree(
+        submitted: &[(String, String, String)],
+        name: &str,
+        url: &str,
+    ) -> termtree::Tree<String> {
+        termtree::Tree::new(format!("{name} {url}")).with_leaves(
+            submitted
+                .iter()
+                .filter(|(parent, _, _)| parent == name)
+                .map(|(_, child, url)| subtree(submitted, child, url)),
+        )
+    }
+
+    let mut roots: Vec<&str> = submitted
+        .iter()
+        .map(|(parent, _, _)| parent.as_str())
+        .filter(|parent| !submitted.iter().any(|(_, name, _)| name == parent))
+        .collect();
+    roots.dedup();
+    roots
+        .into_iter()
+        .map(|root| {
+            termtree::Tree::new(root.to_owned())
+                .with_leaves(
+                    submitted
+                        .iter()
+                        .filter(|(parent, _, _)| parent == root)
+                        .map(|(_, child, url)| subtree(submitted, child, url)),
+                )
+                .to_string()
+        })
+        .collect()
+}
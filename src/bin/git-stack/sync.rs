@@ -7,9 +7,31 @@ pub(crate) struct SyncArgs {
     /// Don't actually switch
     #[arg(short = 'n', long)]
     dry_run: bool,
+
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    #[arg(long, visible_alias = "no-verify")]
+    no_hooks: bool,
+
+    /// Leave branches that would conflict in place, rather than stopping on them
+    #[arg(long)]
+    skip_conflicts: bool,
+
+    /// Rewrite commits even when tags point to them, leaving the tags on the original commits
+    #[arg(long)]
+    force: bool,
 }
 
 impl SyncArgs {
+    /// For `stack.default-command=sync`, taking the flags shared with `git stack`
+    pub(crate) fn from_args(args: &crate::args::Args) -> Self {
+        Self {
+            dry_run: args.dry_run,
+            no_hooks: args.no_hooks,
+            skip_conflicts: args.skip_conflicts,
+            force: args.force,
+        }
+    }
+
     pub(crate) const fn alias() -> crate::alias::Alias {
         let alias = "sync";
         let action = "stack sync";
@@ -21,32 +43,43 @@ impl SyncArgs {
     }
 
     pub(crate) fn exec(&self) -> proc_exit::ExitResult {
-        let stderr_palette = crate::ops::Palette::colored();
-
         let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
         let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        if crate::ops::is_head_unborn(&repo) {
+            crate::ops::note_unborn_head();
+            return Ok(());
+        }
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
         let mut repo = git_stack::git::GitRepo::new(repo);
 
         let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
         repo.set_push_remote(repo_config.push_remote());
+        for (branch, remote) in repo_config.branch_push_remotes() {
+            repo.set_branch_push_remote(branch, remote);
+        }
         repo.set_pull_remote(repo_config.pull_remote());
-        let config = repo
-            .raw()
-            .config()
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+        let sign = crate::ops::sign_policy(repo.raw(), &repo_config)
             .with_code(proc_exit::sysexits::CONFIG_ERR)?;
-        repo.set_sign(
-            config
-                .get_bool("stack.gpgSign")
-                .or_else(|_| config.get_bool("commit.gpgSign"))
-                .unwrap_or_default(),
-        )
-        .with_code(proc_exit::Code::FAILURE)?;
+        repo.set_sign_policy(sign)
+            .with_code(proc_exit::Code::FAILURE)?;
 
         let protected = git_stack::git::ProtectedBranches::new(
-            repo_config.protected_branches().iter().map(|s| s.as_str()),
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
         )
         .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        crate::ops::warn_diverged_remotes(
+            repo.raw(),
+            |name| protected.is_protected(name),
+            |name| repo.pull_remote_for(name).to_owned(),
+        );
         let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
             .with_code(proc_exit::Code::FAILURE)?;
 
@@ -77,24 +110,16 @@ impl SyncArgs {
             stash_id = git_stack::git::stash_push(&mut repo, "reword");
         }
 
-        let mut backed_up = false;
-        {
-            let stash_repo =
-                git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
-            let stash_repo = git_branch_stash::GitRepo::new(stash_repo);
-            let mut snapshots =
-                git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
-            let snapshot_capacity = repo_config.capacity();
-            snapshots.capacity(snapshot_capacity);
-            let snapshot = git_branch_stash::Snapshot::from_repo(&stash_repo)
-                .with_code(proc_exit::Code::FAILURE)?;
-            if !self.dry_run {
-                snapshots.push(snapshot).to_sysexits()?;
-                backed_up = true;
-            }
-        }
+        let backed_up = crate::ops::backup_branches(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+            "sync",
+            stash_id,
+            self.dry_run,
+        )?;
 
         // Update status of remote unprotected branches
+        let retry = crate::ops::Retry::from_config(&repo_config);
         let mut update_branches = false;
         let mut push_branches: Vec<_> = branches
             .iter()
@@ -109,7 +134,8 @@ impl SyncArgs {
             .collect();
         push_branches.sort_unstable();
         if !push_branches.is_empty() {
-            match crate::ops::git_prune_development(&mut repo, &push_branches, self.dry_run) {
+            match crate::ops::git_prune_development(&mut repo, &push_branches, retry, self.dry_run)
+            {
                 Ok(_) => update_branches = true,
                 Err(err) => {
                     log::warn!("Skipping fetch of `{}`, {}", repo.push_remote(), err);
@@ -118,7 +144,7 @@ impl SyncArgs {
         }
         if let Some(branch) = &onto.branch {
             if let Some(remote) = &branch.remote {
-                match crate::ops::git_fetch_upstream(remote, branch.name.as_str()) {
+                match crate::ops::git_fetch_upstream(remote, branch.name.as_str(), retry) {
                     Ok(_) => update_branches = true,
                     Err(err) => {
                         log::warn!("Skipping pull of `{}`, {}", branch, err);
@@ -135,15 +161,69 @@ impl SyncArgs {
         let protect_commit_count = repo_config.protect_commit_count();
         let protect_commit_age = repo_config.protect_commit_age();
         let protect_commit_time = std::time::SystemTime::now() - protect_commit_age;
-        let scripts = plan_changes(
+        let (mut scripts, planned) = plan_changes(
             &repo,
             &base,
             &onto,
             &branches,
             protect_commit_count,
             protect_commit_time,
+            repo_config.resign(),
+            sign,
         )
         .with_code(proc_exit::Code::FAILURE)?;
+        if self.dry_run {
+            crate::ops::note_plan(&repo, &branches, &planned);
+        }
+        // A real run without `--skip-conflicts` reports conflicts as it hits them
+        if self.skip_conflicts || self.dry_run {
+            if self.dry_run {
+                repo.keep_checks_in_memory()
+                    .with_code(proc_exit::Code::FAILURE)?;
+            }
+            for script in &mut scripts {
+                let conflicts = script
+                    .dry_run_check(&repo)
+                    .with_code(proc_exit::Code::FAILURE)?;
+                for conflict in &conflicts {
+                    crate::ops::warn_conflict(
+                        repo.raw(),
+                        conflict.branch(),
+                        conflict.commit_id(),
+                        conflict.dependents(),
+                        self.skip_conflicts,
+                    );
+                }
+                if self.skip_conflicts {
+                    script.skip_conflicts(&conflicts);
+                }
+            }
+        }
+        let mut up_to_date = Vec::new();
+        for script in &mut scripts {
+            up_to_date.extend(
+                script
+                    .skip_up_to_date(&repo)
+                    .with_code(proc_exit::Code::FAILURE)?,
+            );
+        }
+        crate::ops::note_up_to_date(&up_to_date);
+        scripts.retain(|s| !s.is_empty());
+        let mut rewritten = Vec::new();
+        for script in &scripts {
+            rewritten.extend(
+                script
+                    .rewritten_commits(&repo)
+                    .with_code(proc_exit::Code::FAILURE)?,
+            );
+        }
+        if crate::ops::warn_tagged_rewrites(repo.raw(), &rewritten, self.force)
+            .with_code(proc_exit::Code::FAILURE)?
+            && !self.dry_run
+        {
+            git_stack::git::stash_pop(&mut repo, stash_id);
+            return Err(proc_exit::Code::FAILURE.with_message("refusing to rewrite tagged commits"));
+        }
         let head_local_branch = head_branch.clone();
         if let Some(head_local_branch) = head_local_branch.as_ref().and_then(|b| b.local_name()) {
             for script in &scripts {
@@ -162,32 +242,19 @@ impl SyncArgs {
             }
         }
 
-        let mut success = true;
         let mut executor = git_stack::rewrite::Executor::new(self.dry_run);
-        for script in scripts {
-            let results = executor.run(&mut repo, &script);
-            for (err, name, dependents) in results.iter() {
-                success = false;
-                log::error!("Failed to re-stack branch `{}`: {}", name, err);
-                if !dependents.is_empty() {
-                    log::error!("  Blocked dependents: {}", dependents.iter().join(", "));
-                }
-            }
-        }
-        executor
-            .close(&mut repo, head_branch.as_ref().and_then(|b| b.local_name()))
-            .with_code(proc_exit::Code::FAILURE)?;
+        executor.set_run_hooks(!self.no_hooks);
+        let success = crate::resume::run_scripts(
+            &mut repo,
+            &mut executor,
+            scripts,
+            head_branch.as_ref().and_then(|b| b.local_name()),
+            stash_id,
+        )?;
 
         git_stack::git::stash_pop(&mut repo, stash_id);
         if backed_up {
-            anstream::eprintln!(
-                "{}: to undo, run {}",
-                stderr_palette.info("note"),
-                stderr_palette.highlight(format_args!(
-                    "`git branch-stash pop {}`",
-                    crate::ops::STASH_STACK_NAME
-                ))
-            );
+            crate::ops::note_undo();
         }
 
         if success {
@@ -198,14 +265,17 @@ impl SyncArgs {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn plan_changes(
-    repo: &dyn git_stack::git::Repo,
+    repo: &git_stack::git::GitRepo,
     base: &crate::ops::AnnotatedOid,
     onto: &crate::ops::AnnotatedOid,
     branches: &git_stack::graph::BranchSet,
     protect_commit_count: Option<usize>,
     protect_commit_time: std::time::SystemTime,
-) -> eyre::Result<Vec<git_stack::rewrite::Script>> {
+    resign: git_stack::config::Resign,
+    sign: git_stack::config::Sign,
+) -> eyre::Result<(Vec<git_stack::rewrite::Script>, git_stack::graph::GraphDiff)> {
     log::trace!("Planning stack changes with base={}, onto={}", base, onto);
     let graphed_branches = branches.clone();
     let mut graph = git_stack::graph::Graph::from_branches(repo, graphed_branches)?;
@@ -217,9 +287,49 @@ fn plan_changes(
     git_stack::graph::protect_stale_branches(&mut graph, repo, protect_commit_time, &[head_id]);
     if let Some(user) = repo.user() {
         git_stack::graph::protect_foreign_branches(&mut graph, repo, &user, &[]);
+        match resign {
+            git_stack::config::Resign::Strip => {
+                let signed = git_stack::graph::find_foreign_signed_commits(&graph, repo, &user);
+                if !signed.is_empty() {
+                    log::warn!(
+                        "Rewriting may drop signatures by others (set `stack.resign=block` to keep them): {}",
+                        signed.iter().map(|id| id.to_string()[..7].to_owned()).join(", ")
+                    );
+                }
+            }
+            git_stack::config::Resign::Block | git_stack::config::Resign::ResignMine => {
+                let signed =
+                    git_stack::graph::protect_foreign_signed_branches(&mut graph, repo, &user);
+                if !signed.is_empty() {
+                    log::warn!(
+                        "Leaving branches with commits signed by others in place (`stack.resign={}`): {}",
+                        resign,
+                        signed.join(", ")
+                    );
+                }
+            }
+        }
+        if sign == git_stack::config::Sign::Never {
+            let signed = git_stack::graph::find_own_signed_commits(&graph, repo, &user);
+            if !signed.is_empty() {
+                log::warn!(
+                    "Rewriting may drop your signatures (set `stack.sign=if-signed` to re-sign them): {}",
+                    signed.iter().map(|id| id.to_string()[..7].to_owned()).join(", ")
+                );
+            }
+        }
+    }
+    let checked_out = repo.branches_checked_out_elsewhere()?;
+    for (name, path) in git_stack::graph::protect_checked_out_branches(&mut graph, &checked_out) {
+        log::warn!(
+            "Leaving the stack with `{}` in place, it is checked out in `{}`",
+            name,
+            path.display()
+        );
     }
 
     let mut dropped_branches = Vec::new();
+    let unplanned = graph.clone();
 
     let onto_id = onto.id;
     let pull_start_id = base.id;
@@ -244,5 +354,6 @@ fn plan_changes(
 
     log::trace!("Generating script");
     let scripts = git_stack::graph::to_scripts(&graph, dropped_branches);
-    Ok(scripts)
+    let planned = git_stack::graph::diff(&unplanned, &graph);
+    Ok((scripts, planned))
 }
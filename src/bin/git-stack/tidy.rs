@@ -0,0 +1,426 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Clean up the repository in one go
+///
+/// Fetches and prunes the remotes, deletes branches that were merged or squashed into a protected
+/// branch, expires old undo snapshots and records, and fixes up which remote branch each local
+/// branch tracks.  Everything is planned and confirmed up front, and deleted branches can be
+/// restored with `git stack undo`.
+#[derive(clap::Args)]
+pub(crate) struct TidyArgs {
+    /// Expire snapshots and undo records older than this, e.g. `30d` or `now` [default: `branch-stash.git-stack.max-age`, `branch-stash.max-age`, or 30d]
+    #[arg(long, value_name = "AGE", value_parser = crate::gc::parse_expire)]
+    expire: Option<std::time::Duration>,
+
+    /// Plan with the remote-tracking branches as they are, rather than fetching first
+    #[arg(long)]
+    no_fetch: bool,
+
+    /// Only report what would be cleaned up
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl TidyArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
+
+        let repo_config = git_stack::config::RepoConfig::from_all(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let expire = self
+            .expire
+            .or_else(|| repo_config.stash_max_age(crate::ops::STASH_STACK_NAME))
+            .unwrap_or(crate::gc::DEFAULT_EXPIRE);
+
+        let remotes = fetch_remotes(&repo, &repo_config);
+        if !self.no_fetch && !self.dry_run {
+            let retry = crate::ops::Retry::from_config(&repo_config);
+            for remote in &remotes {
+                if let Err(err) = crate::ops::git_fetch_prune(remote, retry) {
+                    log::warn!("Skipping fetch of `{}`, {}", remote, err);
+                }
+            }
+        }
+
+        let plan = Plan::from_repo(&repo, &repo_config, &protected, expire)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut stderr = anstream::stderr().lock();
+        if self.dry_run && !self.no_fetch {
+            for remote in &remotes {
+                let _ = writeln!(
+                    stderr,
+                    "{} `{}`",
+                    stderr_palette.good("Would fetch and prune"),
+                    remote
+                );
+            }
+        }
+        if plan.is_empty() {
+            let _ = writeln!(
+                stderr,
+                "{}: nothing to tidy up",
+                stderr_palette.info("note"),
+            );
+            return Ok(());
+        }
+        let verb = |action: &'static str| {
+            if self.dry_run {
+                format!("Would {}", action.to_ascii_lowercase())
+            } else {
+                action.to_owned()
+            }
+        };
+        for (name, reason) in &plan.delete {
+            let _ = writeln!(
+                stderr,
+                "{} {} ({})",
+                stderr_palette.good(verb("Delete")),
+                stderr_palette.highlight(name),
+                reason
+            );
+        }
+        if 0 < plan.snapshots {
+            let _ = writeln!(
+                stderr,
+                "{} {} {} older than {}",
+                stderr_palette.good(verb("Expire")),
+                plan.snapshots,
+                if plan.snapshots == 1 {
+                    "snapshot"
+                } else {
+                    "snapshots"
+                },
+                humantime::format_duration(expire)
+            );
+        }
+        if !plan.operations.is_empty() {
+            let _ = writeln!(
+                stderr,
+                "{} {} undo {} older than {}",
+                stderr_palette.good(verb("Expire")),
+                plan.operations.len(),
+                if plan.operations.len() == 1 {
+                    "record"
+                } else {
+                    "records"
+                },
+                humantime::format_duration(expire)
+            );
+        }
+        for (name, upstream) in &plan.untrack {
+            let _ = writeln!(
+                stderr,
+                "{} `{}` for {}, it is gone",
+                stderr_palette.good(verb("Untrack")),
+                upstream,
+                stderr_palette.highlight(name)
+            );
+        }
+        for (name, upstream) in &plan.track {
+            let _ = writeln!(
+                stderr,
+                "{} `{}` for {}",
+                stderr_palette.good(verb("Track")),
+                upstream,
+                stderr_palette.highlight(name)
+            );
+        }
+        drop(stderr);
+        if self.dry_run || !crate::ops::confirm("Tidy up") {
+            return Ok(());
+        }
+
+        let backed_up = if plan.delete.is_empty() {
+            false
+        } else {
+            crate::ops::backup_branches(
+                &cwd,
+                crate::ops::SnapshotRetention::from_config(&repo_config),
+                "tidy",
+                None,
+                false,
+            )?
+        };
+        let summary = plan.apply(&repo, expire);
+        let _ = writeln!(
+            anstream::stderr(),
+            "{}: {}",
+            stderr_palette.good("Tidied"),
+            summary.join(", ")
+        );
+        if backed_up {
+            crate::ops::note_undo();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Plan {
+    /// Local branches to delete, with why
+    delete: Vec<(String, String)>,
+    snapshots: usize,
+    operations: Vec<std::path::PathBuf>,
+    /// Local branches whose upstream is gone
+    untrack: Vec<(String, String)>,
+    /// Local branches to track their push remote branch
+    track: Vec<(String, String)>,
+}
+
+impl Plan {
+    fn from_repo(
+        repo: &git2::Repository,
+        repo_config: &git_stack::config::RepoConfig,
+        protected: &git_stack::git::ProtectedBranches,
+        expire: std::time::Duration,
+    ) -> Result<Self, git2::Error> {
+        let mut plan = Self::default();
+
+        let head_branch = repo
+            .head()
+            .ok()
+            .filter(|h| h.is_branch())
+            .and_then(|h| h.shorthand().map(ToOwned::to_owned));
+        let checked_out = git_stack::git::branches_checked_out_elsewhere(repo)?;
+
+        let mut protected_tips = Vec::new();
+        let mut development = Vec::new();
+        for entry in repo.branches(None)? {
+            let (branch, kind) = entry?;
+            let (Some(name), Some(id)) = (branch.name()?, branch.get().target()) else {
+                continue;
+            };
+            let short_name = match kind {
+                git2::BranchType::Local => name,
+                git2::BranchType::Remote => match name.split_once('/') {
+                    Some((_, short_name)) => short_name,
+                    None => continue,
+                },
+            };
+            if short_name == "HEAD" {
+                continue;
+            }
+            if protected.is_protected(short_name) {
+                protected_tips.push((name.to_owned(), id));
+            } else if kind == git2::BranchType::Local {
+                development.push((name.to_owned(), id));
+            }
+        }
+        protected_tips.sort();
+        development.sort();
+
+        let mut deleted = std::collections::BTreeSet::new();
+        for (name, id) in &development {
+            if head_branch.as_deref() == Some(name.as_str()) || checked_out.contains_key(name) {
+                continue;
+            }
+            if let Some(reason) = merged_into(repo, *id, &protected_tips)? {
+                deleted.insert(name.clone());
+                plan.delete.push((name.clone(), reason));
+            }
+        }
+
+        let stash_repo = git_branch_stash::GitRepo::new(git2::Repository::open(repo.path())?);
+        let mut snapshots = git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
+        if let Some(latest) = snapshots.peek() {
+            plan.snapshots = crate::ops::expire_snapshots(&snapshots, &latest, expire, true);
+        }
+        plan.operations = git_stack::git::OpLog::new(repo)?.expired(expire);
+
+        for (name, _) in &development {
+            if deleted.contains(name) {
+                continue;
+            }
+            if let Some(upstream) = git_stack::git::gone_upstream(repo, name)? {
+                plan.untrack.push((name.clone(), upstream));
+                continue;
+            }
+            let branch = repo.find_branch(name, git2::BranchType::Local)?;
+            match branch.upstream() {
+                Ok(_) => {}
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                    let push_remote = repo_config.push_remote_for(name);
+                    let upstream = format!("{push_remote}/{name}");
+                    if repo
+                        .find_branch(&upstream, git2::BranchType::Remote)
+                        .is_ok()
+                    {
+                        plan.track.push((name.clone(), upstream));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(plan)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.delete.is_empty()
+            && self.snapshots == 0
+            && self.operations.is_empty()
+            && self.untrack.is_empty()
+            && self.track.is_empty()
+    }
+
+    /// Carry out the plan, reporting what was done
+    fn apply(&self, repo: &git2::Repository, expire: std::time::Duration) -> Vec<String> {
+        let mut summary = Vec::new();
+
+        let mut deleted = 0;
+        for (name, _) in &self.delete {
+            match repo
+                .find_branch(name, git2::BranchType::Local)
+                .and_then(|mut b| b.delete())
+            {
+                Ok(()) => deleted += 1,
+                Err(err) => log::warn!("Could not delete `{}`: {}", name, err),
+            }
+        }
+        if !self.delete.is_empty() {
+            summary.push(format!("deleted {}", plural(deleted, "branch", "branches")));
+        }
+
+        if 0 < self.snapshots {
+            let expired = git2::Repository::open(repo.path())
+                .map(git_branch_stash::GitRepo::new)
+                .map(|stash_repo| {
+                    let mut snapshots =
+                        git_branch_stash::Stack::new(crate::ops::STASH_STACK_NAME, &stash_repo);
+                    match snapshots.peek() {
+                        Some(latest) => {
+                            crate::ops::expire_snapshots(&snapshots, &latest, expire, false)
+                        }
+                        None => 0,
+                    }
+                })
+                .unwrap_or_default();
+            summary.push(format!(
+                "expired {}",
+                plural(expired, "snapshot", "snapshots")
+            ));
+        }
+
+        if !self.operations.is_empty() {
+            let mut expired = 0;
+            if let Ok(oplog) = git_stack::git::OpLog::new(repo) {
+                for path in &self.operations {
+                    match oplog.remove(path) {
+                        Ok(()) => expired += 1,
+                        Err(err) => log::warn!("{}", err),
+                    }
+                }
+            }
+            summary.push(format!(
+                "expired {}",
+                plural(expired, "undo record", "undo records")
+            ));
+        }
+
+        let mut updated = 0;
+        for (name, upstream) in self
+            .untrack
+            .iter()
+            .map(|(name, _)| (name, None))
+            .chain(self.track.iter().map(|(name, u)| (name, Some(u.as_str()))))
+        {
+            match repo
+                .find_branch(name, git2::BranchType::Local)
+                .and_then(|mut b| b.set_upstream(upstream))
+            {
+                Ok(()) => updated += 1,
+                Err(err) => log::warn!("Could not update the upstream of `{}`: {}", name, err),
+            }
+        }
+        if !self.untrack.is_empty() || !self.track.is_empty() {
+            summary.push(format!(
+                "updated {}",
+                plural(updated, "upstream", "upstreams")
+            ));
+        }
+
+        summary
+    }
+}
+
+/// Why the development branch at `id` is no longer needed, if it isn't
+///
+/// A branch is merged when a protected branch contains it, and squashed when a protected branch
+/// has a commit since they forked with the same tree as the branch.
+fn merged_into(
+    repo: &git2::Repository,
+    id: git2::Oid,
+    protected_tips: &[(String, git2::Oid)],
+) -> Result<Option<String>, git2::Error> {
+    for (name, tip) in protected_tips {
+        if id == *tip || repo.graph_descendant_of(*tip, id)? {
+            return Ok(Some(format!("merged into `{name}`")));
+        }
+    }
+
+    let tree_id = repo.find_commit(id)?.tree_id();
+    for (name, tip) in protected_tips {
+        let Ok(merge_base) = repo.merge_base(id, *tip) else {
+            continue;
+        };
+        if merge_base == id {
+            continue;
+        }
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(*tip)?;
+        revwalk.hide(merge_base)?;
+        for commit_id in revwalk {
+            if repo.find_commit(commit_id?)?.tree_id() == tree_id {
+                return Ok(Some(format!("squashed into `{name}`")));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// The remotes worth fetching: the push and pull remotes, including per-branch push remotes, and
+/// those protected branches come from
+fn fetch_remotes(
+    repo: &git2::Repository,
+    repo_config: &git_stack::config::RepoConfig,
+) -> Vec<String> {
+    let existing = repo.remotes().ok();
+    let existing: Vec<_> = existing.iter().flat_map(|r| r.iter().flatten()).collect();
+    let mut remotes = vec![
+        repo_config.push_remote().to_owned(),
+        repo_config.pull_remote().to_owned(),
+    ];
+    remotes.extend(
+        repo_config
+            .protected_remotes()
+            .iter()
+            .filter_map(|entry| entry.rsplit_once('=').map(|(_, r)| r.to_owned())),
+    );
+    remotes.extend(
+        repo_config
+            .branch_push_remotes()
+            .map(|(_, remote)| remote.to_owned()),
+    );
+    let mut seen = std::collections::BTreeSet::new();
+    remotes.retain(|r| existing.contains(&r.as_str()) && seen.insert(r.clone()));
+    remotes
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Undo the most recent `git-stack` operation
+///
+/// Each operation that rewrites branches is recorded, along with where the branches and `HEAD`
+/// were beforehand, under `.git/git-stack/ops/`.  Undoing moves them back and drops the record,
+/// so running it again undoes the operation before that.  Branches the operation created are left
+/// alone.
+#[derive(clap::Args)]
+pub(crate) struct UndoArgs {
+    /// Only report what would be restored
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl UndoArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stderr_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::resume::require_no_rewrite(&repo)?;
+        let _lock = crate::lock::lock_repo(&repo, self.dry_run)?;
+
+        let oplog = git_stack::git::OpLog::new(&repo).with_code(proc_exit::Code::FAILURE)?;
+        let Some((path, operation)) = oplog.peek().with_code(proc_exit::Code::FAILURE)? else {
+            return Err(proc_exit::sysexits::USAGE_ERR.with_message("nothing to undo"));
+        };
+
+        let repo_config = git_stack::config::RepoConfig::from_all(&repo)
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+        if repo.is_dirty() {
+            return Err(
+                proc_exit::sysexits::USAGE_ERR.with_message("cannot undo with uncommitted changes")
+            );
+        }
+
+        let head_branch = repo
+            .head_branch()
+            .and_then(|b| b.local_name().map(ToOwned::to_owned));
+        let mut moved = Vec::new();
+        for branch in &operation.branches {
+            let current = repo.find_local_branch(&branch.name).map(|b| b.id);
+            if current == Some(branch.id) {
+                continue;
+            }
+            if repo.find_commit(branch.id).is_none() {
+                log::warn!(
+                    "Leaving `{}` in place, its commit {} is gone",
+                    branch.name,
+                    branch.id
+                );
+                continue;
+            }
+            moved.push((branch.name.as_str(), current, branch.id));
+        }
+
+        let mut stderr = anstream::stderr().lock();
+        for (name, current, id) in &moved {
+            let current = current
+                .map(|id| id.to_string()[..7].to_owned())
+                .unwrap_or_else(|| "(deleted)".to_owned());
+            let _ = writeln!(
+                stderr,
+                "{} {name} {current} -> {}",
+                if self.dry_run { "Would move" } else { "Moving" },
+                &id.to_string()[..7]
+            );
+        }
+        if self.dry_run {
+            let _ = writeln!(
+                stderr,
+                "{} would be undone",
+                stderr_palette.highlight(format_args!("`{}`", operation.name))
+            );
+            return Ok(());
+        }
+
+        crate::ops::backup_branches_for_undo(
+            &cwd,
+            crate::ops::SnapshotRetention::from_config(&repo_config),
+        )?;
+        for (name, _, id) in &moved {
+            if head_branch.as_deref() == Some(*name) {
+                repo.detach().with_code(proc_exit::Code::FAILURE)?;
+            }
+            repo.branch(name, *id).with_code(proc_exit::Code::FAILURE)?;
+        }
+        match operation.head.as_ref() {
+            Some(head) => head.restore(&mut repo),
+            None => match head_branch.as_deref() {
+                Some(name) if repo.head_branch().is_none() => repo.switch_branch(name),
+                _ => Ok(()),
+            },
+        }
+        .with_code(proc_exit::Code::FAILURE)?;
+        oplog.remove(&path).with_code(proc_exit::Code::FAILURE)?;
+
+        let _ = writeln!(
+            stderr,
+            "{} {}",
+            stderr_palette.good("Undid"),
+            stderr_palette.highlight(format_args!("`{}`", operation.name))
+        );
+        Ok(())
+    }
+}
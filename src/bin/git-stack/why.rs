@@ -0,0 +1,263 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Explain how `git stack` plans to treat a branch or commit
+///
+/// This reports which protection rule matched, why a branch isn't pushable, which commit a fixup
+/// applies to, and whether a branch looks merged upstream.
+#[derive(clap::Args)]
+pub(crate) struct WhyArgs {
+    /// Branch or commit to explain
+    #[arg(value_name = "BRANCH|COMMIT", default_value = "HEAD")]
+    rev: String,
+}
+
+impl WhyArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let stdout_palette = crate::ops::Palette::colored();
+
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        crate::ops::require_born_head(&repo)?;
+        let mut repo = git_stack::git::GitRepo::new(repo);
+
+        let repo_config = git_stack::config::RepoConfig::from_all(repo.raw())
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        repo.set_push_remote(repo_config.push_remote());
+        repo.set_pull_remote(repo_config.pull_remote());
+        repo.set_protected_remotes(
+            git_stack::git::ProtectedRemotes::new(
+                repo_config.protected_remotes().iter().map(|s| s.as_str()),
+            )
+            .with_code(proc_exit::sysexits::CONFIG_ERR)?,
+        );
+
+        let protected = git_stack::git::ProtectedBranches::new(
+            repo_config.protected_patterns().iter().map(|s| s.as_str()),
+        )
+        .with_code(proc_exit::sysexits::CONFIG_ERR)?;
+        let branches = git_stack::graph::BranchSet::from_repo(&repo, &protected)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let target = repo
+            .resolve(&self.rev)
+            .ok_or_else(|| eyre::format_err!("could not find `{}`", self.rev))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let target_id = target.id;
+
+        let onto = crate::ops::resolve_implicit_base(
+            &repo,
+            target_id,
+            &branches,
+            repo_config.auto_base_commit_count(),
+        );
+        let base = crate::ops::resolve_base_from_onto(&repo, &onto);
+        let merge_base_oid = repo
+            .merge_base(base.id, target_id)
+            .ok_or_else(|| eyre::format_err!("could not find base between {base} and {target_id}"))
+            .with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let branches = branches.descendants(&repo, merge_base_oid);
+
+        let graph = plan(&repo, &base, &onto, &branches, &repo_config)
+            .with_code(proc_exit::Code::FAILURE)?;
+
+        let mut stdout = anstream::stdout().lock();
+        let _ = writeln!(
+            stdout,
+            "{}: {}",
+            stdout_palette.highlight(crate::ops::render_id(&repo, &branches, target_id)),
+            stdout_palette.hint(target.display_summary())
+        );
+        let _ = writeln!(
+            stdout,
+            "  planned against {} onto {}",
+            stdout_palette.highlight(&base),
+            stdout_palette.highlight(&onto)
+        );
+
+        if !graph.contains_id(target_id) {
+            let _ = writeln!(
+                stdout,
+                "  not part of any development stack, it is upstream of {}",
+                stdout_palette.highlight(&base)
+            );
+            return Ok(());
+        }
+
+        for branch in graph.branches.get(target_id).into_iter().flatten() {
+            let kind = match branch.kind() {
+                git_stack::graph::BranchKind::Protected => "protected",
+                git_stack::graph::BranchKind::Mutable => "development",
+                git_stack::graph::BranchKind::Mixed => "development with protected commits",
+                git_stack::graph::BranchKind::Deleted => "to be deleted",
+            };
+            let _ = writeln!(
+                stdout,
+                "  branch {} is {}",
+                stdout_palette.highlight(branch.display_name()),
+                kind
+            );
+        }
+
+        let decisions = graph.commit_get::<git_stack::graph::Decisions>(target_id);
+        let mut explained = false;
+        for decision in decisions.into_iter().flat_map(|d| d.iter()) {
+            explained = true;
+            let _ = writeln!(
+                stdout,
+                "  {}",
+                render_decision(&repo, &branches, &repo_config, decision)
+            );
+        }
+
+        if graph.branches.contains_oid(target_id) {
+            match graph.commit_get::<git_stack::graph::PushStatus>(target_id) {
+                Some(git_stack::graph::PushStatus::Pushable) => {
+                    let _ = writeln!(stdout, "  {}", stdout_palette.good("ready to push"));
+                }
+                Some(git_stack::graph::PushStatus::Pushed) => {
+                    let _ = writeln!(stdout, "  already pushed");
+                }
+                Some(git_stack::graph::PushStatus::Blocked(_))
+                | Some(git_stack::graph::PushStatus::Draft)
+                | None => {}
+            }
+        }
+
+        let action = graph
+            .commit_get::<git_stack::graph::Action>(target_id)
+            .copied()
+            .unwrap_or_default();
+        if !explained && action.is_pick() {
+            let _ = writeln!(
+                stdout,
+                "  development commit, rebased onto {}",
+                stdout_palette.highlight(&onto)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Run the planning passes, keeping the graph so its decision records can be reported
+fn plan(
+    repo: &git_stack::git::GitRepo,
+    base: &crate::ops::AnnotatedOid,
+    onto: &crate::ops::AnnotatedOid,
+    branches: &git_stack::graph::BranchSet,
+    repo_config: &git_stack::config::RepoConfig,
+) -> eyre::Result<git_stack::graph::Graph> {
+    let mut graph = git_stack::graph::Graph::from_branches(repo, branches.clone())?;
+    git_stack::graph::protect_branches(&mut graph);
+    if let Some(protect_commit_count) = repo_config.protect_commit_count() {
+        git_stack::graph::protect_large_branches(&mut graph, protect_commit_count);
+    }
+    let head_id = repo.head_commit().id;
+    let protect_commit_time = std::time::SystemTime::now() - repo_config.protect_commit_age();
+    git_stack::graph::protect_stale_branches(&mut graph, repo, protect_commit_time, &[head_id]);
+    if let Some(user) = repo.user() {
+        git_stack::graph::protect_foreign_branches(&mut graph, repo, &user, &[]);
+        if repo_config.resign() != git_stack::config::Resign::Strip {
+            git_stack::graph::protect_foreign_signed_branches(&mut graph, repo, &user);
+        }
+    }
+    let checked_out = repo.branches_checked_out_elsewhere()?;
+    git_stack::graph::protect_checked_out_branches(&mut graph, &checked_out);
+
+    let onto_id = onto.id;
+    let pull_start_id = repo.merge_base(base.id, onto_id).unwrap_or(onto_id);
+    git_stack::graph::rebase_development_branches(&mut graph, onto_id);
+    git_stack::graph::fast_forward_pulled_branches(&mut graph, pull_start_id, onto_id);
+
+    let pull_range: Vec<_> = git_stack::git::commit_range(repo, onto_id..pull_start_id)?
+        .into_iter()
+        .filter_map(|id| repo.find_commit(id))
+        .collect();
+    git_stack::graph::delete_squashed_branches_by_tree_id(
+        &mut graph,
+        repo,
+        pull_start_id,
+        pull_range.iter().map(|c| c.tree_id),
+    );
+    git_stack::graph::delete_merged_branches(&mut graph, pull_range.iter().map(|c| c.id));
+
+    let anchors = git_stack::git::Anchors::from_repo(repo.raw())?;
+    git_stack::graph::mark_anchors(&mut graph, &anchors);
+    let ignored = git_stack::git::IgnoredCommits::from_repo(repo.raw())?;
+    git_stack::graph::mark_ignored(&mut graph, &ignored);
+    git_stack::graph::fixup_branches(
+        &mut graph,
+        repo,
+        repo_config.auto_fixup(),
+        &repo_config.branch_auto_fixups(),
+    );
+    git_stack::graph::mark_wip(&mut graph, repo);
+    let drafts = git_stack::git::DraftBranches::from_repo(repo.raw())?;
+    git_stack::graph::mark_draft(&mut graph, repo.raw(), &drafts);
+    git_stack::graph::pushable(&mut graph);
+
+    Ok(graph)
+}
+
+fn render_decision(
+    repo: &git_stack::git::GitRepo,
+    branches: &git_stack::graph::BranchSet,
+    repo_config: &git_stack::config::RepoConfig,
+    decision: &git_stack::graph::Decision,
+) -> String {
+    use git_stack::graph::Decision;
+
+    match decision {
+        Decision::ProtectedBranch(name) => {
+            format!("protected, reachable from protected branch `{name}`")
+        }
+        Decision::LargeBranch(count) => format!(
+            "protected, the branch has {count} commits (`stack.protect-commit-count={}`)",
+            repo_config.protect_commit_count().unwrap_or(0)
+        ),
+        Decision::StaleBranch => format!(
+            "protected, every commit on the branch is older than `stack.protect-commit-age={}`",
+            humantime::format_duration(repo_config.protect_commit_age())
+        ),
+        Decision::ForeignBranch => {
+            "protected, no commit on the branch is by you (`user.name`)".to_owned()
+        }
+        Decision::ForeignSignature(signed_id) => format!(
+            "protected, {} is signed by someone else (`stack.resign={}`)",
+            crate::ops::render_id(repo, branches, *signed_id),
+            repo_config.resign()
+        ),
+        Decision::CheckedOut(path) => format!(
+            "protected, the stack has a branch checked out in `{}`",
+            path.display()
+        ),
+        Decision::Tagged(tag) => format!("protected, the branch is tagged {tag}"),
+        Decision::Wip => "work-in-progress commit".to_owned(),
+        Decision::Draft => "draft, the branch isn't ready to push".to_owned(),
+        Decision::NotPushable { reason, blocked_by } => format!(
+            "not pushable, {reason} ({})",
+            crate::ops::render_id(repo, branches, *blocked_by)
+        ),
+        Decision::Fixup(target) => format!(
+            "fixup, applied to {} (`stack.auto-fixup={}`)",
+            crate::ops::render_id(repo, branches, *target),
+            repo_config.auto_fixup()
+        ),
+        Decision::NoFixupTarget => {
+            "fixup, left in place as no matching commit is in the stack".to_owned()
+        }
+        Decision::AnchoredFixup(anchor_id) => format!(
+            "fixup, left in place as applying it would reorder around anchor {}",
+            crate::ops::render_id(repo, branches, *anchor_id)
+        ),
+        Decision::Anchor => "anchor, commits aren't reordered around it".to_owned(),
+        Decision::Ignored => "ignored, not treated as WIP or a fixup".to_owned(),
+        Decision::SquashMerged => {
+            "squash-merged upstream, its tree matches a pulled commit".to_owned()
+        }
+        Decision::Merged => "merged upstream".to_owned(),
+        _ => format!("{decision:?}"),
+    }
+}
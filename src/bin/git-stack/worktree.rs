@@ -0,0 +1,216 @@
+use std::io::Write;
+
+use proc_exit::prelude::*;
+
+/// Check out a stack branch in its own worktree, to review it side-by-side
+///
+/// Worktrees are created under `stack.worktree-root` and recorded so they can be listed and
+/// cleaned up.  Without arguments, lists the worktrees created for the stack.
+#[derive(clap::Args)]
+pub(crate) struct WorktreeArgs {
+    /// Branch to check out
+    #[arg(value_name = "BRANCH")]
+    branch: Option<String>,
+
+    /// Where to create the worktree (default: `<stack.worktree-root>/<BRANCH>`)
+    #[arg(value_name = "PATH", requires = "branch")]
+    path: Option<std::path::PathBuf>,
+
+    /// Remove the worktrees created for the stack that have no uncommitted changes
+    #[arg(long, conflicts_with = "branch")]
+    clean: bool,
+}
+
+impl WorktreeArgs {
+    pub(crate) fn exec(&self) -> proc_exit::ExitResult {
+        let cwd = std::env::current_dir().with_code(proc_exit::sysexits::USAGE_ERR)?;
+        let repo = git2::Repository::discover(&cwd).with_code(proc_exit::sysexits::USAGE_ERR)?;
+        // Worktrees are tracked by the repo they were created from
+        let repo = git_stack::git::open_main(&repo).with_code(proc_exit::sysexits::USAGE_ERR)?;
+
+        if let Some(branch) = self.branch.as_deref() {
+            crate::lock::require_writable(false)?;
+            add(&repo, branch, self.path.as_deref())
+        } else if self.clean {
+            crate::lock::require_writable(false)?;
+            clean(&repo)
+        } else {
+            list(&repo)
+        }
+    }
+}
+
+fn add(
+    repo: &git2::Repository,
+    branch: &str,
+    path: Option<&std::path::Path>,
+) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+
+    let reference = repo
+        .find_branch(branch, git2::BranchType::Local)
+        .map_err(|_| {
+            proc_exit::sysexits::USAGE_ERR.with_message(format!("could not find branch `{branch}`"))
+        })?
+        .into_reference();
+    let checked_out =
+        git_stack::git::checked_out_branches(repo).with_code(proc_exit::Code::FAILURE)?;
+    if let Some((_, path)) = checked_out.iter().find(|(name, _)| name == branch) {
+        return Err(proc_exit::sysexits::USAGE_ERR.with_message(format!(
+            "`{branch}` is already checked out at `{}`",
+            path.display()
+        )));
+    }
+
+    let name = worktree_name(branch);
+    if repo.find_worktree(&name).is_ok() {
+        return Err(proc_exit::sysexits::USAGE_ERR
+            .with_message(format!("worktree `{name}` already exists")));
+    }
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => worktree_root(repo)?.join(branch),
+    };
+    if path.exists() {
+        return Err(proc_exit::sysexits::USAGE_ERR
+            .with_message(format!("`{}` already exists", path.display())));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_code(proc_exit::Code::FAILURE)?;
+    }
+
+    let mut options = git2::WorktreeAddOptions::new();
+    options.reference(Some(&reference));
+    repo.worktree(&name, &path, Some(&options))
+        .map_err(|err| proc_exit::Code::FAILURE.with_message(err.message().to_owned()))?;
+    git_stack::git::register_worktree(repo, &name).with_code(proc_exit::Code::FAILURE)?;
+
+    let _ = writeln!(
+        anstream::stderr(),
+        "{} `{}` at {}",
+        stderr_palette.good("Checked out"),
+        branch,
+        stderr_palette.highlight(path.display()),
+    );
+    // For `cd "$(git stack worktree <branch>)"`
+    let _ = writeln!(anstream::stdout(), "{}", path.display());
+
+    Ok(())
+}
+
+fn list(repo: &git2::Repository) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stdout = anstream::stdout().lock();
+
+    for name in git_stack::git::stack_worktrees(repo).with_code(proc_exit::Code::FAILURE)? {
+        let Ok(worktree) = repo.find_worktree(&name) else {
+            let _ = writeln!(
+                stdout,
+                "{} {}",
+                name,
+                stderr_palette.warn("(removed outside of git-stack)")
+            );
+            continue;
+        };
+        let branch = git2::Repository::open_from_worktree(&worktree)
+            .ok()
+            .and_then(|r| {
+                r.head()
+                    .ok()
+                    .and_then(|h| h.shorthand().map(ToOwned::to_owned))
+            })
+            .unwrap_or_else(|| "detached".to_owned());
+        if worktree.validate().is_ok() {
+            let _ = writeln!(stdout, "{} {}", worktree.path().display(), branch);
+        } else {
+            let _ = writeln!(
+                stdout,
+                "{} {} {}",
+                worktree.path().display(),
+                branch,
+                stderr_palette.warn("(missing)")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn clean(repo: &git2::Repository) -> proc_exit::ExitResult {
+    let stderr_palette = crate::ops::Palette::colored();
+    let mut stderr = anstream::stderr().lock();
+
+    for name in git_stack::git::stack_worktrees(repo).with_code(proc_exit::Code::FAILURE)? {
+        if let Ok(worktree) = repo.find_worktree(&name) {
+            if worktree
+                .is_locked()
+                .map(|l| l != git2::WorktreeLockStatus::Unlocked)
+                == Ok(true)
+            {
+                log::warn!("Leaving `{}`, it is locked", worktree.path().display());
+                continue;
+            }
+            if worktree.validate().is_ok() {
+                let dirty = git2::Repository::open_from_worktree(&worktree)
+                    .and_then(|r| has_changes(&r))
+                    .unwrap_or(true);
+                if dirty {
+                    log::warn!(
+                        "Leaving `{}`, it has uncommitted changes",
+                        worktree.path().display()
+                    );
+                    continue;
+                }
+            }
+            let mut options = git2::WorktreePruneOptions::new();
+            options.valid(true).working_tree(true);
+            worktree
+                .prune(Some(&mut options))
+                .with_code(proc_exit::Code::FAILURE)?;
+            let _ = writeln!(
+                stderr,
+                "{} {}",
+                stderr_palette.good("Removed"),
+                worktree.path().display()
+            );
+        }
+        git_stack::git::unregister_worktree(repo, &name).with_code(proc_exit::Code::FAILURE)?;
+    }
+
+    Ok(())
+}
+
+/// Worktree names can't contain `/`, unlike branches
+fn worktree_name(branch: &str) -> String {
+    branch.replace('/', "-")
+}
+
+fn worktree_root(repo: &git2::Repository) -> Result<std::path::PathBuf, proc_exit::Exit> {
+    let workdir = repo.workdir().ok_or_else(|| {
+        proc_exit::sysexits::USAGE_ERR.with_message("cannot add a worktree to a bare repository")
+    })?;
+    let repo_config =
+        git_stack::config::RepoConfig::from_all(repo).with_code(proc_exit::sysexits::CONFIG_ERR)?;
+    let root = match repo_config.worktree_root() {
+        Some(root) => workdir.join(root),
+        None => {
+            let workdir = workdir.components().as_path();
+            let name = workdir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            workdir
+                .parent()
+                .unwrap_or(workdir)
+                .join(format!("{name}.worktrees"))
+        }
+    };
+    Ok(root)
+}
+
+fn has_changes(repo: &git2::Repository) -> Result<bool, git2::Error> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(!statuses.is_empty())
+}
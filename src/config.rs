@@ -5,35 +5,180 @@ pub struct RepoConfig {
     pub editor: Option<String>,
 
     pub protected_branches: Option<Vec<String>>,
+    pub protect_remote_default_branch: Option<bool>,
     pub protect_commit_count: Option<usize>,
     pub protect_commit_age: Option<std::time::Duration>,
     pub auto_base_commit_count: Option<usize>,
     pub stack: Option<Stack>,
     pub push_remote: Option<String>,
     pub pull_remote: Option<String>,
+    pub protected_remotes: Option<Vec<String>>,
     pub show_format: Option<Format>,
+    pub show_icons: Option<Icons>,
+    pub palette: Option<ColorPalette>,
+    pub colors: std::collections::BTreeMap<String, String>,
     pub show_commits: Option<ShowCommits>,
     pub show_stacked: Option<bool>,
+    pub show_base_history: Option<usize>,
+    pub show_max_width: Option<usize>,
+    pub show_signatures: Option<bool>,
     pub auto_fixup: Option<Fixup>,
     pub auto_repair: Option<bool>,
+    pub preserve_order: Option<bool>,
+    pub drop_by_patch_id: Option<bool>,
+    pub resign: Option<Resign>,
+    pub sign: Option<Sign>,
+    pub gitlab_mr: Option<bool>,
+    pub push_style: Option<PushStyle>,
+    pub forge: Option<Forge>,
+    pub github_token: Option<String>,
+    pub default_command: Option<String>,
+    pub network_retries: Option<usize>,
+    pub network_retry_delay: Option<std::time::Duration>,
+    pub fresh_max_age: Option<std::time::Duration>,
+    pub profile: Option<String>,
+    pub worktree_root: Option<String>,
+    pub read_only: Option<bool>,
+    pub auto_stash: Option<bool>,
 
     pub capacity: Option<usize>,
+    pub max_age: Option<std::time::Duration>,
+    pub stashes: std::collections::BTreeMap<String, StashConfig>,
+    pub branches: std::collections::BTreeMap<String, BranchConfig>,
+    pub aliases: std::collections::BTreeMap<String, String>,
+}
+
+/// Retention for a named `git-branch-stash` stack, from `branch-stash.<name>.*`
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct StashConfig {
+    pub capacity: Option<usize>,
+    pub max_age: Option<std::time::Duration>,
+}
+
+impl StashConfig {
+    fn update(mut self, other: Self) -> Self {
+        self.capacity = other.capacity.or(self.capacity);
+        self.max_age = other.max_age.or(self.max_age);
+        self
+    }
+}
+
+/// Overrides for a single branch, from `branch.<name>.stack-*`
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct BranchConfig {
+    pub push_remote: Option<String>,
+    pub auto_fixup: Option<Fixup>,
+    pub protected: Option<bool>,
+}
+
+impl BranchConfig {
+    fn update(mut self, other: Self) -> Self {
+        self.push_remote = other.push_remote.or(self.push_remote);
+        self.auto_fixup = other.auto_fixup.or(self.auto_fixup);
+        self.protected = other.protected.or(self.protected);
+        self
+    }
+
+    fn set(&mut self, branch_key: &str, value: Option<&str>) {
+        if branch_key == BRANCH_PUSH_REMOTE_KEY {
+            self.push_remote = value.map(ToOwned::to_owned);
+        } else if branch_key == BRANCH_AUTO_FIXUP_KEY {
+            self.auto_fixup = value.and_then(|v| FromStr::from_str(v).ok());
+        } else {
+            self.protected = match value {
+                Some(value) => git2::Config::parse_bool(value).ok(),
+                None => Some(true),
+            };
+        }
+    }
+}
+
+/// Where a [`RepoConfig`] layer was read from, see [`RepoConfig::layers`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Built-in defaults
+    Default,
+    /// The pull remote's default branch, from `refs/remotes/<remote>/HEAD`
+    Remote,
+    /// System, global, and XDG gitconfig
+    User,
+    /// `.gitconfig` in the working directory
+    Workdir,
+    /// The repository's `.git/config`
+    Repo,
+    /// The selected `stack-profile.<name>`
+    Profile,
+    /// `GIT_CONFIG_PARAMETERS`, `GIT_CONFIG_COUNT`, and `GIT_EDITOR`
+    Env,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::Remote => "remote",
+            Self::User => "user",
+            Self::Workdir => "workdir",
+            Self::Repo => "repo",
+            Self::Profile => "profile",
+            Self::Env => "env",
+        };
+        name.fmt(f)
+    }
 }
 
 static CORE_EDITOR: &str = "core.editor";
 static PROTECTED_STACK_FIELD: &str = "stack.protected-branch";
+static PROTECT_REMOTE_DEFAULT_FIELD: &str = "stack.protect-remote-default-branch";
 static PROTECT_COMMIT_COUNT: &str = "stack.protect-commit-count";
 static PROTECT_COMMIT_AGE: &str = "stack.protect-commit-age";
 static AUTO_BASE_COMMIT_COUNT: &str = "stack.auto-base-commit-count";
 static STACK_FIELD: &str = "stack.stack";
 static PUSH_REMOTE_FIELD: &str = "stack.push-remote";
 static PULL_REMOTE_FIELD: &str = "stack.pull-remote";
+static PROTECTED_REMOTE_FIELD: &str = "stack.protected-remote";
 static FORMAT_FIELD: &str = "stack.show-format";
+static ICONS_FIELD: &str = "stack.show-icons";
+static PALETTE_FIELD: &str = "stack.palette";
+static COLOR_SECTION: &str = "color.stack";
+static COLOR_ROLES: [&str; 6] = ["error", "warn", "info", "good", "highlight", "hint"];
 static SHOW_COMMITS_FIELD: &str = "stack.show-commits";
 static STACKED_FIELD: &str = "stack.show-stacked";
+static BASE_HISTORY_FIELD: &str = "stack.show-base-history";
+static MAX_WIDTH_FIELD: &str = "stack.show-max-width";
+static SIGNATURES_FIELD: &str = "stack.show-signatures";
 static AUTO_FIXUP_FIELD: &str = "stack.auto-fixup";
 static AUTO_REPAIR_FIELD: &str = "stack.auto-repair";
+static PRESERVE_ORDER_FIELD: &str = "stack.preserve-order";
+static DROP_BY_PATCH_ID_FIELD: &str = "stack.dropByPatchId";
+static RESIGN_FIELD: &str = "stack.resign";
+static SIGN_FIELD: &str = "stack.sign";
+static GITLAB_MR_FIELD: &str = "stack.gitlab-mr";
+static PUSH_STYLE_FIELD: &str = "stack.pushStyle";
+static FORGE_FIELD: &str = "stack.forge";
+static GITHUB_TOKEN_FIELD: &str = "stack.github-token";
+static DEFAULT_COMMAND_FIELD: &str = "stack.default-command";
+static NETWORK_RETRIES_FIELD: &str = "stack.network-retries";
+static NETWORK_RETRY_DELAY_FIELD: &str = "stack.network-retry-delay";
+static FRESH_MAX_AGE_FIELD: &str = "stack.fresh-max-age";
+static PROFILE_FIELD: &str = "stack.profile";
+static WORKTREE_ROOT_FIELD: &str = "stack.worktree-root";
+static READ_ONLY_FIELD: &str = "stack.read-only";
+static AUTO_STASH_FIELD: &str = "stack.auto-stash";
+static PROFILE_SECTION: &str = "stack-profile";
+static ALIAS_SECTION: &str = "stack.alias";
+/// Overrides `stack.profile`, for `git stack --profile`
+pub const PROFILE_ENV: &str = "GIT_STACK_PROFILE";
 static BACKUP_CAPACITY_FIELD: &str = "branch-stash.capacity";
+static BACKUP_MAX_AGE_FIELD: &str = "branch-stash.max-age";
+static STASH_SECTION: &str = "branch-stash";
+static STASH_CAPACITY_KEY: &str = "capacity";
+static STASH_MAX_AGE_KEY: &str = "max-age";
+static BRANCH_SECTION: &str = "branch";
+static NAMED_STACK_KEY: &str = "stack";
+static BRANCH_PUSH_REMOTE_KEY: &str = "stack-push-remote";
+static BRANCH_AUTO_FIXUP_KEY: &str = "stack-auto-fixup";
+static BRANCH_PROTECTED_KEY: &str = "stack-protected";
 
 #[cfg(windows)]
 static DEFAULT_CORE_EDITOR: &str = "notepad.exe";
@@ -44,10 +189,18 @@ static DEFAULT_PROTECT_COMMIT_COUNT: usize = 50;
 static DEFAULT_PROTECT_COMMIT_AGE: std::time::Duration =
     std::time::Duration::from_secs(60 * 60 * 24 * 14);
 static DEFAULT_AUTO_BASE_COMMIT_COUNT: usize = 500;
+static DEFAULT_NETWORK_RETRIES: usize = 3;
+static DEFAULT_NETWORK_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+static DEFAULT_FRESH_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 24);
 const DEFAULT_CAPACITY: usize = 30;
 
 impl RepoConfig {
     pub fn from_all(repo: &git2::Repository) -> eyre::Result<Self> {
+        Ok(Self::from_layers(Self::layers(repo)?))
+    }
+
+    /// Each source [`RepoConfig::from_all`] reads, from lowest to highest precedence
+    pub fn layers(repo: &git2::Repository) -> eyre::Result<Vec<(ConfigSource, Self)>> {
         log::trace!("Loading gitconfig");
         let default_config = match git2::Config::open_default() {
             Ok(config) => Some(config),
@@ -56,16 +209,187 @@ impl RepoConfig {
                 None
             }
         };
-        let config = Self::from_defaults_internal(default_config.as_ref());
-        let config = if let Some(default_config) = default_config.as_ref() {
-            config.update(Self::from_gitconfig(default_config))
-        } else {
-            config
-        };
-        let config = config.update(Self::from_workdir(repo)?);
-        let config = config.update(Self::from_repo(repo)?);
-        let config = config.update(Self::from_env());
-        Ok(config)
+        let mut layers = vec![(
+            ConfigSource::Default,
+            Self::from_defaults_internal(default_config.as_ref()),
+        )];
+        if let Some(default_config) = default_config.as_ref() {
+            layers.push((ConfigSource::User, Self::from_gitconfig(default_config)));
+        }
+        layers.push((ConfigSource::Workdir, Self::from_workdir(repo)?));
+        layers.push((ConfigSource::Repo, Self::from_repo(repo)?));
+        let env = Self::from_env();
+
+        let profile = std::env::var(PROFILE_ENV)
+            .ok()
+            .filter(|p| !p.is_empty())
+            .or_else(|| env.profile.clone())
+            .or_else(|| layers.iter().rev().find_map(|(_, c)| c.profile.clone()));
+        if let Some(profile) = profile {
+            let gitconfig = repo.config()?;
+            let mut profile_config = Self::from_profile(&gitconfig, &profile)
+                .ok_or_else(|| eyre::eyre!("unknown profile `{profile}`"))?;
+            log::trace!("Using profile `{}`", profile);
+            profile_config.profile = Some(profile);
+            layers.push((ConfigSource::Profile, profile_config));
+        }
+
+        layers.push((ConfigSource::Env, env));
+
+        let combined = Self::from_layers(layers.iter().cloned());
+        if combined.protect_remote_default_branch() {
+            if let Some(branch) = remote_default_branch(repo, combined.pull_remote()) {
+                log::trace!("Protecting `{}`, the remote's default branch", branch);
+                let remote = Self {
+                    protected_branches: Some(vec![branch]),
+                    ..Default::default()
+                };
+                layers.insert(1, (ConfigSource::Remote, remote));
+            }
+        }
+        Ok(layers)
+    }
+
+    /// Combine [`RepoConfig::layers`], later layers taking precedence
+    pub fn from_layers(layers: impl IntoIterator<Item = (ConfigSource, Self)>) -> Self {
+        layers
+            .into_iter()
+            .fold(Self::default(), |config, (source, layer)| match source {
+                ConfigSource::Profile => config.apply_profile(layer),
+                _ => config.update(layer),
+            })
+    }
+
+    /// Settings in [`RepoConfig::layers`] that name remotes or branches `repo` doesn't have
+    ///
+    /// Built-in defaults are only checked when they can't work, like `origin` in a repository
+    /// whose only remote is `upstream`.
+    pub fn problems(repo: &git2::Repository, layers: &[(ConfigSource, Self)]) -> Vec<String> {
+        let mut problems = Vec::new();
+        // Remote-tracking branches count, in case they were fetched without configuring a remote
+        let mut remotes = std::collections::BTreeSet::new();
+        if let Ok(configured) = repo.remotes() {
+            remotes.extend(configured.iter().flatten().map(ToOwned::to_owned));
+        }
+        let mut branches = Vec::new();
+        if let Ok(entries) = repo.branches(None) {
+            for (branch, kind) in entries.flatten() {
+                let Ok(Some(name)) = branch.name() else {
+                    continue;
+                };
+                let name = match kind {
+                    git2::BranchType::Local => name,
+                    git2::BranchType::Remote => match name.split_once('/') {
+                        Some((remote, name)) => {
+                            remotes.insert(remote.to_owned());
+                            name
+                        }
+                        None => name,
+                    },
+                };
+                branches.push(name.to_owned());
+            }
+        }
+        let remotes = remotes.into_iter().collect::<Vec<_>>();
+        let missing_remote = |name: &str| !remotes.iter().any(|r| r == name);
+
+        let combined = Self::from_layers(layers.iter().cloned());
+        if !remotes.is_empty() {
+            let push_remote = combined.push_remote();
+            if missing_remote(push_remote) {
+                problems.push(format!(
+                    "`{}` is `{}` but there is no such remote (have {})",
+                    PUSH_REMOTE_FIELD,
+                    push_remote,
+                    remotes.join(", ")
+                ));
+            }
+            let pull_remote = combined.pull_remote();
+            if pull_remote != push_remote && missing_remote(pull_remote) {
+                problems.push(format!(
+                    "`{}` is `{}` but there is no such remote (have {})",
+                    PULL_REMOTE_FIELD,
+                    pull_remote,
+                    remotes.join(", ")
+                ));
+            }
+        }
+        if !remotes.is_empty() {
+            for (name, remote) in combined.branch_push_remotes() {
+                if missing_remote(remote) {
+                    problems.push(format!(
+                        "`{BRANCH_SECTION}.{name}.{BRANCH_PUSH_REMOTE_KEY}` is `{remote}` but there is no such remote"
+                    ));
+                }
+            }
+        }
+        for entry in combined.protected_remotes() {
+            if let Some((_, remote)) = entry.rsplit_once('=') {
+                if !remote.is_empty() && missing_remote(remote) {
+                    problems.push(format!(
+                        "`{PROTECTED_REMOTE_FIELD}={entry}` names `{remote}` but there is no such remote"
+                    ));
+                }
+            }
+        }
+
+        let patterns = layers
+            .iter()
+            .filter(|(source, _)| !matches!(source, ConfigSource::Default | ConfigSource::Remote))
+            .flat_map(|(_, layer)| layer.protected_branches())
+            .filter(|p| !p.starts_with('!'));
+        let mut checked = std::collections::BTreeSet::new();
+        for pattern in patterns {
+            if !checked.insert(pattern.as_str()) {
+                continue;
+            }
+            let Ok(protected) = crate::git::ProtectedBranches::new([pattern.as_str()]) else {
+                continue;
+            };
+            if !branches.iter().any(|b| protected.is_protected(b)) {
+                problems.push(format!(
+                    "`{PROTECTED_STACK_FIELD}={pattern}` doesn't match any branch"
+                ));
+            }
+        }
+
+        problems
+    }
+
+    /// Read `stack-profile.<profile>.<key>`, as if each were `stack.<key>`
+    ///
+    /// Returns `None` if the profile has no keys set.
+    pub fn from_profile(config: &git2::Config, profile: &str) -> Option<Self> {
+        let mut entries = Vec::new();
+        let mut profile_entries = config.entries(Some(r"^stack-profile\..+\.")).ok()?;
+        while let Some(entry) = profile_entries.next() {
+            let Ok(entry) = entry else { continue };
+            let Some((name, key)) = entry.name().and_then(parse_profile_key) else {
+                continue;
+            };
+            if name == profile {
+                entries.push((
+                    std::borrow::Cow::Owned(format!("stack.{key}")),
+                    entry.value().map(|v| std::borrow::Cow::Owned(v.to_owned())),
+                ));
+            }
+        }
+        if entries.is_empty() {
+            return None;
+        }
+        Some(Self::from_env_iter(entries.into_iter()))
+    }
+
+    /// Like [`RepoConfig::update`] but lists set by the profile replace, rather than extend, the
+    /// lists from other sources
+    fn apply_profile(mut self, profile: Self) -> Self {
+        if profile.protected_branches.is_some() {
+            self.protected_branches = None;
+        }
+        if profile.protected_remotes.is_some() {
+            self.protected_remotes = None;
+        }
+        self.update(profile)
     }
 
     pub fn from_repo(repo: &git2::Repository) -> eyre::Result<Self> {
@@ -137,6 +461,9 @@ impl RepoConfig {
                         .get_or_insert_with(Vec::new)
                         .push(value.into_owned());
                 }
+            } else if key == PROTECT_REMOTE_DEFAULT_FIELD {
+                config.protect_remote_default_branch =
+                    Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
             } else if key == PROTECT_COMMIT_COUNT {
                 if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
                     config.protect_commit_count = Some(value);
@@ -164,24 +491,138 @@ impl RepoConfig {
                 if let Some(value) = value {
                     config.pull_remote = Some(value.into_owned());
                 }
+            } else if key == PROTECTED_REMOTE_FIELD {
+                if let Some(value) = value {
+                    config
+                        .protected_remotes
+                        .get_or_insert_with(Vec::new)
+                        .push(value.into_owned());
+                }
             } else if key == FORMAT_FIELD {
                 if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
                     config.show_format = Some(value);
                 }
+            } else if key == ICONS_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.show_icons = Some(value);
+                }
+            } else if key == PALETTE_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.palette = Some(value);
+                }
+            } else if let Some(role) = parse_color_key(&key) {
+                if let Some(value) = value {
+                    config.colors.insert(role.to_owned(), value.into_owned());
+                }
+            } else if let Some(name) = parse_alias_key(&key) {
+                if let Some(value) = value {
+                    config.aliases.insert(name.to_owned(), value.into_owned());
+                }
             } else if key == SHOW_COMMITS_FIELD {
                 if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
                     config.show_commits = Some(value);
                 }
             } else if key == STACKED_FIELD {
                 config.show_stacked = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key == SIGNATURES_FIELD {
+                config.show_signatures = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key == BASE_HISTORY_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.show_base_history = Some(value);
+                }
+            } else if key == MAX_WIDTH_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.show_max_width = Some(value);
+                }
             } else if key == AUTO_FIXUP_FIELD {
                 if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
                     config.auto_fixup = Some(value);
                 }
             } else if key == AUTO_REPAIR_FIELD {
                 config.auto_repair = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key == PRESERVE_ORDER_FIELD {
+                config.preserve_order = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key.eq_ignore_ascii_case(DROP_BY_PATCH_ID_FIELD) {
+                config.drop_by_patch_id = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key == RESIGN_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.resign = Some(value);
+                }
+            } else if key == SIGN_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.sign = Some(value);
+                }
+            } else if key == GITLAB_MR_FIELD {
+                config.gitlab_mr = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key.eq_ignore_ascii_case(PUSH_STYLE_FIELD) {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.push_style = Some(value);
+                }
+            } else if key == FORGE_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.forge = Some(value);
+                }
+            } else if key == GITHUB_TOKEN_FIELD {
+                if let Some(value) = value {
+                    config.github_token = Some(value.into_owned());
+                }
+            } else if key == DEFAULT_COMMAND_FIELD {
+                if let Some(value) = value {
+                    config.default_command = Some(value.into_owned());
+                }
+            } else if key == NETWORK_RETRIES_FIELD {
+                if let Some(value) = value.as_ref().and_then(|v| FromStr::from_str(v).ok()) {
+                    config.network_retries = Some(value);
+                }
+            } else if key == NETWORK_RETRY_DELAY_FIELD {
+                if let Some(value) = value
+                    .as_ref()
+                    .and_then(|v| humantime::parse_duration(v).ok())
+                {
+                    config.network_retry_delay = Some(value);
+                }
+            } else if key == FRESH_MAX_AGE_FIELD {
+                if let Some(value) = value
+                    .as_ref()
+                    .and_then(|v| humantime::parse_duration(v).ok())
+                {
+                    config.fresh_max_age = Some(value);
+                }
+            } else if key == PROFILE_FIELD {
+                if let Some(value) = value {
+                    config.profile = Some(value.into_owned());
+                }
+            } else if key == WORKTREE_ROOT_FIELD {
+                if let Some(value) = value {
+                    config.worktree_root = Some(value.into_owned());
+                }
+            } else if key == READ_ONLY_FIELD {
+                config.read_only = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if key == AUTO_STASH_FIELD {
+                config.auto_stash = Some(value.as_ref().map(|v| v == "true").unwrap_or(true));
+            } else if parse_profile_key(&key).is_some() {
+                // Only read when the profile is selected
             } else if key == BACKUP_CAPACITY_FIELD {
                 config.capacity = value.as_deref().and_then(|s| s.parse::<usize>().ok());
+            } else if key == BACKUP_MAX_AGE_FIELD {
+                config.max_age = value
+                    .as_deref()
+                    .and_then(|v| humantime::parse_duration(v).ok());
+            } else if let Some((name, stash_key)) = parse_stash_key(&key) {
+                let stash = config.stashes.entry(name.to_owned()).or_default();
+                if stash_key == STASH_CAPACITY_KEY {
+                    stash.capacity = value.as_deref().and_then(|s| s.parse::<usize>().ok());
+                } else {
+                    stash.max_age = value
+                        .as_deref()
+                        .and_then(|v| humantime::parse_duration(v).ok());
+                }
+            } else if let Some((name, branch_key)) = parse_branch_key(&key) {
+                config
+                    .branches
+                    .entry(name.to_owned())
+                    .or_default()
+                    .set(branch_key, value.as_deref());
             } else {
                 log::warn!(
                     "Unsupported config: {}={}",
@@ -211,16 +652,33 @@ impl RepoConfig {
         conf.editor = std::env::var("VISUAL")
             .or_else(|_err| std::env::var("EDITOR"))
             .ok();
+        conf.protect_remote_default_branch = Some(conf.protect_remote_default_branch());
         conf.protect_commit_count = Some(conf.protect_commit_count().unwrap_or(0));
         conf.protect_commit_age = Some(conf.protect_commit_age());
         conf.auto_base_commit_count = Some(conf.auto_base_commit_count().unwrap_or(0));
         conf.stack = Some(conf.stack());
         conf.push_remote = Some(conf.push_remote().to_owned());
         conf.pull_remote = Some(conf.pull_remote().to_owned());
+        conf.protected_remotes = Some(Vec::new());
         conf.show_format = Some(conf.show_format());
+        conf.show_icons = Some(conf.show_icons());
+        conf.palette = Some(conf.palette());
         conf.show_commits = Some(conf.show_commits());
         conf.show_stacked = Some(conf.show_stacked());
+        conf.show_signatures = Some(conf.show_signatures());
+        conf.show_base_history = Some(conf.show_base_history());
         conf.auto_fixup = Some(conf.auto_fixup());
+        conf.preserve_order = Some(conf.preserve_order());
+        conf.drop_by_patch_id = Some(conf.drop_by_patch_id());
+        conf.resign = Some(conf.resign());
+        conf.gitlab_mr = Some(conf.gitlab_mr());
+        // `stack.pushStyle` is left unset so it can fall back to `stack.gitlab-mr`
+        conf.forge = Some(conf.forge());
+        conf.network_retries = Some(conf.network_retries());
+        conf.network_retry_delay = Some(conf.network_retry_delay());
+        conf.fresh_max_age = Some(conf.fresh_max_age());
+        conf.read_only = Some(conf.read_only());
+        conf.auto_stash = Some(conf.auto_stash());
         conf.capacity = Some(DEFAULT_CAPACITY);
 
         let mut protected_branches: Vec<String> = Vec::new();
@@ -259,6 +717,7 @@ impl RepoConfig {
                 }
             })
             .unwrap_or(None);
+        let protect_remote_default_branch = config.get_bool(PROTECT_REMOTE_DEFAULT_FIELD).ok();
 
         let protect_commit_count = config
             .get_i64(PROTECT_COMMIT_COUNT)
@@ -279,6 +738,24 @@ impl RepoConfig {
             .ok()
             .or_else(|| config.get_string("remote.pushDefault").ok());
         let pull_remote = config.get_string(PULL_REMOTE_FIELD).ok();
+        let protected_remotes = config
+            .multivar(PROTECTED_REMOTE_FIELD, None)
+            .map(|entries| {
+                let mut protected_remotes = Vec::new();
+                entries
+                    .for_each(|entry| {
+                        if let Some(value) = entry.value() {
+                            protected_remotes.push(value.to_owned());
+                        }
+                    })
+                    .unwrap();
+                if protected_remotes.is_empty() {
+                    None
+                } else {
+                    Some(protected_remotes)
+                }
+            })
+            .unwrap_or(None);
 
         let stack = config
             .get_string(STACK_FIELD)
@@ -290,12 +767,58 @@ impl RepoConfig {
             .ok()
             .and_then(|s| FromStr::from_str(&s).ok());
 
+        let show_icons = config
+            .get_string(ICONS_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+
+        let palette = config
+            .get_string(PALETTE_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+
+        let mut colors = std::collections::BTreeMap::new();
+        if let Ok(mut entries) = config.entries(Some(r"^color\.stack\.")) {
+            while let Some(entry) = entries.next() {
+                let Ok(entry) = entry else { continue };
+                let (Some(key), Some(value)) = (entry.name(), entry.value()) else {
+                    continue;
+                };
+                if let Some(role) = parse_color_key(key) {
+                    colors.insert(role.to_owned(), value.to_owned());
+                }
+            }
+        }
+
+        let mut aliases = std::collections::BTreeMap::new();
+        if let Ok(mut entries) = config.entries(Some(r"^stack\.alias\.")) {
+            while let Some(entry) = entries.next() {
+                let Ok(entry) = entry else { continue };
+                let (Some(key), Some(value)) = (entry.name(), entry.value()) else {
+                    continue;
+                };
+                if let Some(name) = parse_alias_key(key) {
+                    aliases.insert(name.to_owned(), value.to_owned());
+                }
+            }
+        }
+
         let show_commits = config
             .get_string(SHOW_COMMITS_FIELD)
             .ok()
             .and_then(|s| FromStr::from_str(&s).ok());
 
         let show_stacked = config.get_bool(STACKED_FIELD).ok();
+        let show_signatures = config.get_bool(SIGNATURES_FIELD).ok();
+
+        let show_base_history = config
+            .get_i64(BASE_HISTORY_FIELD)
+            .ok()
+            .map(|i| i.max(0) as usize);
+        let show_max_width = config
+            .get_i64(MAX_WIDTH_FIELD)
+            .ok()
+            .map(|i| i.max(0) as usize);
 
         let auto_fixup = config
             .get_string(AUTO_FIXUP_FIELD)
@@ -304,26 +827,162 @@ impl RepoConfig {
 
         let auto_repair = config.get_bool(AUTO_REPAIR_FIELD).ok();
 
+        let preserve_order = config.get_bool(PRESERVE_ORDER_FIELD).ok();
+        let drop_by_patch_id = config.get_bool(DROP_BY_PATCH_ID_FIELD).ok();
+
+        let resign = config
+            .get_string(RESIGN_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+        let sign = config
+            .get_string(SIGN_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+
+        let gitlab_mr = config.get_bool(GITLAB_MR_FIELD).ok();
+
+        let push_style = config
+            .get_string(PUSH_STYLE_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+
+        let forge = config
+            .get_string(FORGE_FIELD)
+            .ok()
+            .and_then(|s| FromStr::from_str(&s).ok());
+
+        let github_token = config.get_string(GITHUB_TOKEN_FIELD).ok();
+
+        let default_command = config.get_string(DEFAULT_COMMAND_FIELD).ok();
+
+        let network_retries = config
+            .get_i64(NETWORK_RETRIES_FIELD)
+            .ok()
+            .map(|i| i.max(0) as usize);
+        let network_retry_delay = config
+            .get_string(NETWORK_RETRY_DELAY_FIELD)
+            .ok()
+            .and_then(|s| humantime::parse_duration(&s).ok());
+        let fresh_max_age = config
+            .get_string(FRESH_MAX_AGE_FIELD)
+            .ok()
+            .and_then(|s| humantime::parse_duration(&s).ok());
+        let profile = config.get_string(PROFILE_FIELD).ok();
+
+        let worktree_root = config.get_string(WORKTREE_ROOT_FIELD).ok();
+        let read_only = config.get_bool(READ_ONLY_FIELD).ok();
+        let auto_stash = config.get_bool(AUTO_STASH_FIELD).ok();
+
         let capacity = config
             .get_i64(BACKUP_CAPACITY_FIELD)
             .map(|i| i as usize)
             .ok();
+        let max_age = config
+            .get_string(BACKUP_MAX_AGE_FIELD)
+            .ok()
+            .and_then(|s| humantime::parse_duration(&s).ok());
+
+        let mut stashes = std::collections::BTreeMap::<String, StashConfig>::new();
+        if let Ok(mut entries) = config.entries(Some(r"^branch-stash\..+\.")) {
+            while let Some(entry) = entries.next() {
+                let Ok(entry) = entry else { continue };
+                let (Some(key), Some(value)) = (entry.name(), entry.value()) else {
+                    continue;
+                };
+                let Some((name, stash_key)) = parse_stash_key(key) else {
+                    continue;
+                };
+                let stash = stashes.entry(name.to_owned()).or_default();
+                if stash_key == STASH_CAPACITY_KEY {
+                    stash.capacity = value.parse::<i64>().ok().map(|i| i.max(0) as usize);
+                } else {
+                    stash.max_age = humantime::parse_duration(value).ok();
+                }
+            }
+        }
+
+        let mut branches = std::collections::BTreeMap::<String, BranchConfig>::new();
+        if let Ok(mut entries) = config.entries(Some(r"^branch\..+\.stack-")) {
+            while let Some(entry) = entries.next() {
+                let Ok(entry) = entry else { continue };
+                let Some(key) = entry.name() else {
+                    continue;
+                };
+                let Some((name, branch_key)) = parse_branch_key(key) else {
+                    continue;
+                };
+                branches
+                    .entry(name.to_owned())
+                    .or_default()
+                    .set(branch_key, entry.value());
+            }
+        }
+        let mut git_push_remotes = std::collections::BTreeMap::<String, String>::new();
+        if let Ok(mut entries) = config.entries(Some(r"^branch\..+\.pushremote$")) {
+            while let Some(entry) = entries.next() {
+                let Ok(entry) = entry else { continue };
+                let (Some(key), Some(value)) = (entry.name(), entry.value()) else {
+                    continue;
+                };
+                let Some((name, _)) = key
+                    .strip_prefix(BRANCH_SECTION)
+                    .and_then(|k| k.strip_prefix('.'))
+                    .and_then(|k| k.rsplit_once('.'))
+                    .filter(|(name, _)| !name.is_empty())
+                else {
+                    continue;
+                };
+                git_push_remotes.insert(name.to_owned(), value.to_owned());
+            }
+        }
+        for (name, remote) in git_push_remotes {
+            let branch = branches.entry(name).or_default();
+            branch.push_remote = branch.push_remote.take().or(Some(remote));
+        }
 
         Self {
             editor,
             protected_branches,
+            protect_remote_default_branch,
             protect_commit_count,
             protect_commit_age,
             auto_base_commit_count,
             stack,
             push_remote,
             pull_remote,
+            protected_remotes,
             show_format,
+            show_icons,
+            palette,
+            colors,
             show_commits,
             show_stacked,
+            show_base_history,
+            show_max_width,
+            show_signatures,
             auto_fixup,
             auto_repair,
+            preserve_order,
+            drop_by_patch_id,
+            resign,
+            sign,
+            gitlab_mr,
+            push_style,
+            forge,
+            github_token,
+            default_command,
+            network_retries,
+            network_retry_delay,
+            fresh_max_age,
+            profile,
+            worktree_root,
+            read_only,
+            auto_stash,
             capacity,
+            max_age,
+            stashes,
+            branches,
+            aliases,
         }
     }
 
@@ -354,22 +1013,328 @@ impl RepoConfig {
             (None, Some(rhs)) => self.protected_branches = Some(rhs),
             (_, _) => (),
         }
+        self.protect_remote_default_branch = other
+            .protect_remote_default_branch
+            .or(self.protect_remote_default_branch);
         self.protect_commit_count = other.protect_commit_count.or(self.protect_commit_count);
         self.protect_commit_age = other.protect_commit_age.or(self.protect_commit_age);
         self.auto_base_commit_count = other.auto_base_commit_count.or(self.auto_base_commit_count);
         self.push_remote = other.push_remote.or(self.push_remote);
         self.pull_remote = other.pull_remote.or(self.pull_remote);
+        match (&mut self.protected_remotes, other.protected_remotes) {
+            (Some(lhs), Some(rhs)) => lhs.extend(rhs),
+            (None, Some(rhs)) => self.protected_remotes = Some(rhs),
+            (_, _) => (),
+        }
         self.stack = other.stack.or(self.stack);
         self.show_format = other.show_format.or(self.show_format);
+        self.show_icons = other.show_icons.or(self.show_icons);
+        self.palette = other.palette.or(self.palette);
+        self.colors.extend(other.colors);
+        self.aliases.extend(other.aliases);
         self.show_commits = other.show_commits.or(self.show_commits);
         self.show_stacked = other.show_stacked.or(self.show_stacked);
+        self.show_signatures = other.show_signatures.or(self.show_signatures);
+        self.show_base_history = other.show_base_history.or(self.show_base_history);
+        self.show_max_width = other.show_max_width.or(self.show_max_width);
         self.auto_fixup = other.auto_fixup.or(self.auto_fixup);
         self.auto_repair = other.auto_repair.or(self.auto_repair);
+        self.preserve_order = other.preserve_order.or(self.preserve_order);
+        self.drop_by_patch_id = other.drop_by_patch_id.or(self.drop_by_patch_id);
+        self.resign = other.resign.or(self.resign);
+        self.sign = other.sign.or(self.sign);
+        self.gitlab_mr = other.gitlab_mr.or(self.gitlab_mr);
+        self.push_style = other.push_style.or(self.push_style);
+        self.forge = other.forge.or(self.forge);
+        self.github_token = other.github_token.or(self.github_token);
+        self.default_command = other.default_command.or(self.default_command);
+        self.network_retries = other.network_retries.or(self.network_retries);
+        self.network_retry_delay = other.network_retry_delay.or(self.network_retry_delay);
+        self.fresh_max_age = other.fresh_max_age.or(self.fresh_max_age);
+        self.profile = other.profile.or(self.profile);
+        self.worktree_root = other.worktree_root.or(self.worktree_root);
+        self.read_only = other.read_only.or(self.read_only);
+        self.auto_stash = other.auto_stash.or(self.auto_stash);
         self.capacity = other.capacity.or(self.capacity);
+        self.max_age = other.max_age.or(self.max_age);
+        for (name, stash) in other.stashes {
+            let merged = self.stashes.remove(&name).unwrap_or_default().update(stash);
+            self.stashes.insert(name, merged);
+        }
+        for (name, branch) in other.branches {
+            let merged = self
+                .branches
+                .remove(&name)
+                .unwrap_or_default()
+                .update(branch);
+            self.branches.insert(name, merged);
+        }
 
         self
     }
 
+    /// Every setting, as `(key, value)`, with defaults filled in
+    ///
+    /// Lists have an entry per item.  `stack.github-token` is left out so it isn't leaked.
+    pub fn effective(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut push = |key: &str, value: String| entries.push((key.to_owned(), value));
+        push(CORE_EDITOR, self.editor().to_owned());
+        for branch in self.protected_branches() {
+            push(PROTECTED_STACK_FIELD, branch.clone());
+        }
+        push(
+            PROTECT_REMOTE_DEFAULT_FIELD,
+            self.protect_remote_default_branch().to_string(),
+        );
+        push(
+            PROTECT_COMMIT_COUNT,
+            self.protect_commit_count().unwrap_or(0).to_string(),
+        );
+        push(
+            PROTECT_COMMIT_AGE,
+            humantime::format_duration(self.protect_commit_age()).to_string(),
+        );
+        push(
+            AUTO_BASE_COMMIT_COUNT,
+            self.auto_base_commit_count().unwrap_or(0).to_string(),
+        );
+        push(STACK_FIELD, self.stack().to_string());
+        push(PUSH_REMOTE_FIELD, self.push_remote().to_owned());
+        push(PULL_REMOTE_FIELD, self.pull_remote().to_owned());
+        for remote in self.protected_remotes() {
+            push(PROTECTED_REMOTE_FIELD, remote.clone());
+        }
+        push(FORMAT_FIELD, self.show_format().to_string());
+        push(ICONS_FIELD, self.show_icons().to_string());
+        push(PALETTE_FIELD, self.palette().to_string());
+        push(SHOW_COMMITS_FIELD, self.show_commits().to_string());
+        push(STACKED_FIELD, self.show_stacked().to_string());
+        push(SIGNATURES_FIELD, self.show_signatures().to_string());
+        push(BASE_HISTORY_FIELD, self.show_base_history().to_string());
+        if let Some(max_width) = self.show_max_width() {
+            push(MAX_WIDTH_FIELD, max_width.to_string());
+        }
+        push(AUTO_FIXUP_FIELD, self.auto_fixup().to_string());
+        push(AUTO_REPAIR_FIELD, self.auto_repair().to_string());
+        push(PRESERVE_ORDER_FIELD, self.preserve_order().to_string());
+        push(DROP_BY_PATCH_ID_FIELD, self.drop_by_patch_id().to_string());
+        push(RESIGN_FIELD, self.resign().to_string());
+        if let Some(sign) = self.sign {
+            push(SIGN_FIELD, sign.to_string());
+        }
+        push(GITLAB_MR_FIELD, self.gitlab_mr().to_string());
+        push(PUSH_STYLE_FIELD, self.push_style().to_string());
+        push(FORGE_FIELD, self.forge().to_string());
+        push(
+            DEFAULT_COMMAND_FIELD,
+            self.default_command
+                .clone()
+                .unwrap_or_else(|| DefaultCommand::default().to_string()),
+        );
+        push(NETWORK_RETRIES_FIELD, self.network_retries().to_string());
+        push(
+            NETWORK_RETRY_DELAY_FIELD,
+            humantime::format_duration(self.network_retry_delay()).to_string(),
+        );
+        push(
+            FRESH_MAX_AGE_FIELD,
+            humantime::format_duration(self.fresh_max_age()).to_string(),
+        );
+        if let Some(profile) = self.profile() {
+            push(PROFILE_FIELD, profile.to_owned());
+        }
+        if let Some(worktree_root) = self.worktree_root() {
+            push(WORKTREE_ROOT_FIELD, worktree_root.to_owned());
+        }
+        push(READ_ONLY_FIELD, self.read_only().to_string());
+        push(AUTO_STASH_FIELD, self.auto_stash().to_string());
+        push(
+            BACKUP_CAPACITY_FIELD,
+            self.capacity().unwrap_or(0).to_string(),
+        );
+        if let Some(max_age) = self.max_age {
+            push(
+                BACKUP_MAX_AGE_FIELD,
+                humantime::format_duration(max_age).to_string(),
+            );
+        }
+        entries.extend(self.color_entries());
+        entries.extend(self.stash_entries());
+        entries.extend(self.branch_entries());
+        entries.extend(self.alias_entries());
+        entries
+    }
+
+    /// Only the settings this config sets, as `(key, value)`, formatted like
+    /// [`RepoConfig::effective`]
+    fn explicit(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut push = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                entries.push((key.to_owned(), value));
+            }
+        };
+        let duration = |value: std::time::Duration| humantime::format_duration(value).to_string();
+        push(CORE_EDITOR, self.editor.clone());
+        for branch in self.protected_branches() {
+            push(PROTECTED_STACK_FIELD, Some(branch.clone()));
+        }
+        push(
+            PROTECT_REMOTE_DEFAULT_FIELD,
+            self.protect_remote_default_branch.map(|v| v.to_string()),
+        );
+        push(
+            PROTECT_COMMIT_COUNT,
+            self.protect_commit_count.map(|v| v.to_string()),
+        );
+        push(PROTECT_COMMIT_AGE, self.protect_commit_age.map(duration));
+        push(
+            AUTO_BASE_COMMIT_COUNT,
+            self.auto_base_commit_count.map(|v| v.to_string()),
+        );
+        push(STACK_FIELD, self.stack.map(|v| v.to_string()));
+        push(PUSH_REMOTE_FIELD, self.push_remote.clone());
+        push(PULL_REMOTE_FIELD, self.pull_remote.clone());
+        for remote in self.protected_remotes() {
+            push(PROTECTED_REMOTE_FIELD, Some(remote.clone()));
+        }
+        push(FORMAT_FIELD, self.show_format.map(|v| v.to_string()));
+        push(ICONS_FIELD, self.show_icons.map(|v| v.to_string()));
+        push(PALETTE_FIELD, self.palette.map(|v| v.to_string()));
+        push(SHOW_COMMITS_FIELD, self.show_commits.map(|v| v.to_string()));
+        push(STACKED_FIELD, self.show_stacked.map(|v| v.to_string()));
+        push(
+            SIGNATURES_FIELD,
+            self.show_signatures.map(|v| v.to_string()),
+        );
+        push(
+            BASE_HISTORY_FIELD,
+            self.show_base_history.map(|v| v.to_string()),
+        );
+        push(MAX_WIDTH_FIELD, self.show_max_width.map(|v| v.to_string()));
+        push(AUTO_FIXUP_FIELD, self.auto_fixup.map(|v| v.to_string()));
+        push(AUTO_REPAIR_FIELD, self.auto_repair.map(|v| v.to_string()));
+        push(
+            PRESERVE_ORDER_FIELD,
+            self.preserve_order.map(|v| v.to_string()),
+        );
+        push(
+            DROP_BY_PATCH_ID_FIELD,
+            self.drop_by_patch_id.map(|v| v.to_string()),
+        );
+        push(RESIGN_FIELD, self.resign.map(|v| v.to_string()));
+        push(SIGN_FIELD, self.sign.map(|v| v.to_string()));
+        push(GITLAB_MR_FIELD, self.gitlab_mr.map(|v| v.to_string()));
+        push(PUSH_STYLE_FIELD, self.push_style.map(|v| v.to_string()));
+        push(FORGE_FIELD, self.forge.map(|v| v.to_string()));
+        push(DEFAULT_COMMAND_FIELD, self.default_command.clone());
+        push(
+            NETWORK_RETRIES_FIELD,
+            self.network_retries.map(|v| v.to_string()),
+        );
+        push(
+            NETWORK_RETRY_DELAY_FIELD,
+            self.network_retry_delay.map(duration),
+        );
+        push(FRESH_MAX_AGE_FIELD, self.fresh_max_age.map(duration));
+        push(PROFILE_FIELD, self.profile.clone());
+        push(WORKTREE_ROOT_FIELD, self.worktree_root.clone());
+        push(READ_ONLY_FIELD, self.read_only.map(|v| v.to_string()));
+        push(AUTO_STASH_FIELD, self.auto_stash.map(|v| v.to_string()));
+        push(BACKUP_CAPACITY_FIELD, self.capacity.map(|v| v.to_string()));
+        push(BACKUP_MAX_AGE_FIELD, self.max_age.map(duration));
+        entries.extend(self.color_entries());
+        entries.extend(self.stash_entries());
+        entries.extend(self.branch_entries());
+        entries.extend(self.alias_entries());
+        entries
+    }
+
+    fn color_entries(&self) -> Vec<(String, String)> {
+        self.colors
+            .iter()
+            .map(|(role, color)| (format!("{COLOR_SECTION}.{role}"), color.clone()))
+            .collect()
+    }
+
+    fn alias_entries(&self) -> Vec<(String, String)> {
+        self.aliases
+            .iter()
+            .map(|(name, value)| (format!("{ALIAS_SECTION}.{name}"), value.clone()))
+            .collect()
+    }
+
+    fn stash_entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        for (name, stash) in &self.stashes {
+            if let Some(capacity) = stash.capacity {
+                entries.push((
+                    format!("{STASH_SECTION}.{name}.{STASH_CAPACITY_KEY}"),
+                    capacity.to_string(),
+                ));
+            }
+            if let Some(max_age) = stash.max_age {
+                entries.push((
+                    format!("{STASH_SECTION}.{name}.{STASH_MAX_AGE_KEY}"),
+                    humantime::format_duration(max_age).to_string(),
+                ));
+            }
+        }
+        entries
+    }
+
+    fn branch_entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        for (name, branch) in &self.branches {
+            let mut push = |key: &str, value: String| {
+                entries.push((format!("{BRANCH_SECTION}.{name}.{key}"), value));
+            };
+            if let Some(remote) = branch.push_remote.as_ref() {
+                push(BRANCH_PUSH_REMOTE_KEY, remote.clone());
+            }
+            if let Some(fixup) = branch.auto_fixup {
+                push(BRANCH_AUTO_FIXUP_KEY, fixup.to_string());
+            }
+            if let Some(protected) = branch.protected {
+                push(BRANCH_PROTECTED_KEY, protected.to_string());
+            }
+        }
+        entries
+    }
+
+    /// [`RepoConfig::effective`] settings of the combined `layers`, with the layer that set each
+    ///
+    /// Settings no layer sets, like those falling back to another setting, are attributed to
+    /// [`ConfigSource::Default`].
+    pub fn effective_sources(
+        layers: &[(ConfigSource, Self)],
+    ) -> Vec<(String, String, ConfigSource)> {
+        let combined = Self::from_layers(layers.iter().cloned());
+        let explicit: Vec<_> = layers
+            .iter()
+            .map(|(source, layer)| (*source, layer.explicit()))
+            .collect();
+        combined
+            .effective()
+            .into_iter()
+            .map(|(key, value)| {
+                let set_by = |exact: bool| {
+                    explicit.iter().rev().find_map(|(source, entries)| {
+                        entries
+                            .iter()
+                            .any(|(k, v)| *k == key && (!exact || *v == value))
+                            .then_some(*source)
+                    })
+                };
+                let source = set_by(true)
+                    .or_else(|| set_by(false))
+                    .unwrap_or(ConfigSource::Default);
+                (key, value, source)
+            })
+            .collect()
+    }
+
     pub fn editor(&self) -> &str {
         self.editor.as_deref().unwrap_or(DEFAULT_CORE_EDITOR)
     }
@@ -378,6 +1343,25 @@ impl RepoConfig {
         self.protected_branches.as_deref().unwrap_or(&[])
     }
 
+    /// [`RepoConfig::protected_branches`] with each `branch.<name>.stack-protected` applied last,
+    /// so it wins over the patterns
+    pub fn protected_patterns(&self) -> Vec<String> {
+        let mut patterns = self.protected_branches().to_vec();
+        for (name, branch) in &self.branches {
+            match branch.protected {
+                Some(true) => patterns.push(format!("/{name}")),
+                Some(false) => patterns.push(format!("!/{name}")),
+                None => {}
+            }
+        }
+        patterns
+    }
+
+    /// Protect the pull remote's default branch, as recorded by `refs/remotes/<remote>/HEAD`
+    pub fn protect_remote_default_branch(&self) -> bool {
+        self.protect_remote_default_branch.unwrap_or(true)
+    }
+
     pub fn protect_commit_count(&self) -> Option<usize> {
         let protect_commit_count = self
             .protect_commit_count
@@ -407,6 +1391,30 @@ impl RepoConfig {
             .unwrap_or_else(|| self.push_remote())
     }
 
+    /// The remote to push `branch` to, from `branch.<name>.stack-push-remote` or
+    /// `branch.<name>.pushRemote`
+    pub fn push_remote_for(&self, branch: &str) -> &str {
+        self.branches
+            .get(branch)
+            .and_then(|b| b.push_remote.as_deref())
+            .unwrap_or_else(|| self.push_remote())
+    }
+
+    /// Branches pushed somewhere other than [`RepoConfig::push_remote`], with their remote
+    pub fn branch_push_remotes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.branches.iter().filter_map(|(name, branch)| {
+            branch
+                .push_remote
+                .as_deref()
+                .map(|remote| (name.as_str(), remote))
+        })
+    }
+
+    /// `<pattern>=<remote>` overrides of [`RepoConfig::pull_remote`] for protected branches
+    pub fn protected_remotes(&self) -> &[String] {
+        self.protected_remotes.as_deref().unwrap_or(&[])
+    }
+
     pub fn stack(&self) -> Stack {
         self.stack.unwrap_or_default()
     }
@@ -415,6 +1423,31 @@ impl RepoConfig {
         self.show_format.unwrap_or_default()
     }
 
+    pub fn show_icons(&self) -> Icons {
+        self.show_icons.unwrap_or_default()
+    }
+
+    pub fn palette(&self) -> ColorPalette {
+        self.palette.unwrap_or_default()
+    }
+
+    /// The `color.stack.<role>` override of the [`RepoConfig::palette`], in `git config` color
+    /// syntax
+    pub fn color(&self, role: &str) -> Option<&str> {
+        self.colors.get(role).map(|s| s.as_str())
+    }
+
+    /// User-defined `stack.alias.<name>` commands, like `next --move`
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(|s| s.as_str())
+    }
+
     pub fn show_commits(&self) -> ShowCommits {
         self.show_commits.unwrap_or_default()
     }
@@ -423,115 +1456,740 @@ impl RepoConfig {
         self.show_stacked.unwrap_or(true)
     }
 
-    pub fn auto_fixup(&self) -> Fixup {
-        self.auto_fixup.unwrap_or_default()
+    /// Mark whether each development commit's signature checks out
+    pub fn show_signatures(&self) -> bool {
+        self.show_signatures.unwrap_or(false)
     }
 
-    pub fn auto_repair(&self) -> bool {
-        self.auto_repair.unwrap_or(true)
+    /// How many protected commits to show beneath each stack's root
+    pub fn show_base_history(&self) -> usize {
+        self.show_base_history.unwrap_or(0)
     }
 
-    pub fn capacity(&self) -> Option<usize> {
-        let capacity = self.capacity.unwrap_or(DEFAULT_CAPACITY);
+    /// How wide to show the graph, truncating longer lines
+    ///
+    /// Unset means the terminal's width and `0` means no limit.
+    pub fn show_max_width(&self) -> Option<usize> {
+        self.show_max_width
+    }
+
+    /// The `auto-fixup` for the fixups in `branch`, from `branch.<name>.stack-auto-fixup`
+    pub fn auto_fixup_for(&self, branch: &str) -> Fixup {
+        self.branches
+            .get(branch)
+            .and_then(|b| b.auto_fixup)
+            .unwrap_or_else(|| self.auto_fixup())
+    }
+
+    /// Branches whose fixups are handled differently than [`RepoConfig::auto_fixup`]
+    pub fn branch_auto_fixups(&self) -> std::collections::BTreeMap<String, Fixup> {
+        self.branches
+            .iter()
+            .filter_map(|(name, branch)| branch.auto_fixup.map(|fixup| (name.clone(), fixup)))
+            .collect()
+    }
+
+    pub fn auto_fixup(&self) -> Fixup {
+        self.auto_fixup.unwrap_or_default()
+    }
+
+    pub fn auto_repair(&self) -> bool {
+        self.auto_repair.unwrap_or(true)
+    }
+
+    /// Limit repairs to changes that keep each branch's commits in order
+    pub fn preserve_order(&self) -> bool {
+        self.preserve_order.unwrap_or(false)
+    }
+
+    /// Also drop commits whose changes landed upstream under a different tree
+    pub fn drop_by_patch_id(&self) -> bool {
+        self.drop_by_patch_id.unwrap_or(false)
+    }
+
+    pub fn resign(&self) -> Resign {
+        self.resign.unwrap_or_default()
+    }
+
+    /// Which rewritten commits to sign, if set rather than left to `stack.gpgSign`
+    pub fn sign(&self) -> Option<Sign> {
+        self.sign
+    }
+
+    /// Create GitLab merge requests, targeting each branch's parent, when pushing
+    pub fn gitlab_mr(&self) -> bool {
+        self.gitlab_mr.unwrap_or(false)
+    }
+
+    /// How `--push` sends branches to the push remote
+    pub fn push_style(&self) -> PushStyle {
+        match self.push_style {
+            Some(push_style) => push_style,
+            None if self.gitlab_mr() => PushStyle::GitlabMr,
+            None => PushStyle::default(),
+        }
+    }
+
+    /// Where pull requests are hosted, enabling commands that talk to it
+    pub fn forge(&self) -> Forge {
+        self.forge.unwrap_or_default()
+    }
+
+    /// Token for the GitHub API, falling back to `gh`'s own login
+    pub fn github_token(&self) -> Option<&str> {
+        self.github_token.as_deref()
+    }
+
+    /// What a bare `git stack` does, failing if `stack.default-command` is invalid
+    pub fn default_command(&self) -> Result<DefaultCommand, String> {
+        self.default_command
+            .as_deref()
+            .map(|s| {
+                DefaultCommand::from_str(s)
+                    .map_err(|err| format!("invalid `{DEFAULT_COMMAND_FIELD}={s}`: {err}"))
+            })
+            .unwrap_or(Ok(DefaultCommand::default()))
+    }
+
+    /// Number of times to retry a remote operation that failed for a transient reason
+    pub fn network_retries(&self) -> usize {
+        self.network_retries.unwrap_or(DEFAULT_NETWORK_RETRIES)
+    }
+
+    /// Delay before the first retry, doubling with each attempt
+    pub fn network_retry_delay(&self) -> std::time::Duration {
+        self.network_retry_delay
+            .unwrap_or(DEFAULT_NETWORK_RETRY_DELAY)
+    }
+
+    /// How old the remote-tracking branches may get before planning warns they are stale
+    pub fn fresh_max_age(&self) -> std::time::Duration {
+        self.fresh_max_age.unwrap_or(DEFAULT_FRESH_MAX_AGE)
+    }
+
+    /// The `stack-profile.<name>` layered over the other config files
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Where `git stack worktree` creates worktrees, relative to the working directory
+    ///
+    /// Defaults to a `<workdir>.worktrees` directory next to the working directory.
+    pub fn worktree_root(&self) -> Option<&str> {
+        self.worktree_root.as_deref()
+    }
+
+    /// Whether commands that would change the repo, its config, or the forge refuse to run
+    pub fn read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+
+    /// Whether `git stack next` and `git stack previous` stash a dirty working tree while
+    /// switching
+    pub fn auto_stash(&self) -> bool {
+        self.auto_stash.unwrap_or(false)
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        let capacity = self.capacity.unwrap_or(DEFAULT_CAPACITY);
         (capacity != 0).then_some(capacity)
     }
+
+    /// Capacity of the named snapshot stack, falling back to [`RepoConfig::capacity`]
+    pub fn stash_capacity(&self, name: &str) -> Option<usize> {
+        match self.stashes.get(name).and_then(|s| s.capacity) {
+            Some(capacity) => (capacity != 0).then_some(capacity),
+            None => self.capacity(),
+        }
+    }
+
+    /// How long to keep snapshots in the named stack, falling back to `branch-stash.max-age`, if
+    /// they expire
+    pub fn stash_max_age(&self, name: &str) -> Option<std::time::Duration> {
+        self.stashes
+            .get(name)
+            .and_then(|s| s.max_age)
+            .or(self.max_age)
+    }
 }
 
 impl std::fmt::Display for RepoConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "[{}]", CORE_EDITOR.split_once('.').unwrap().0)?;
-        writeln!(
-            f,
-            "\t{}={}",
-            CORE_EDITOR.split_once('.').unwrap().1,
-            self.editor()
-        )?;
-        writeln!(f, "[{}]", STACK_FIELD.split_once('.').unwrap().0)?;
-        for branch in self.protected_branches() {
-            writeln!(
-                f,
-                "\t{}={}",
-                PROTECTED_STACK_FIELD.split_once('.').unwrap().1,
-                branch
-            )?;
-        }
-        writeln!(
-            f,
-            "\t{}={}",
-            PROTECT_COMMIT_COUNT.split_once('.').unwrap().1,
-            self.protect_commit_count().unwrap_or(0)
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            PROTECT_COMMIT_AGE.split_once('.').unwrap().1,
-            humantime::format_duration(self.protect_commit_age())
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            AUTO_BASE_COMMIT_COUNT.split_once('.').unwrap().1,
-            self.auto_base_commit_count().unwrap_or(0)
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            STACK_FIELD.split_once('.').unwrap().1,
-            self.stack()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            PUSH_REMOTE_FIELD.split_once('.').unwrap().1,
-            self.push_remote()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            PULL_REMOTE_FIELD.split_once('.').unwrap().1,
-            self.pull_remote()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            FORMAT_FIELD.split_once('.').unwrap().1,
-            self.show_format()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            SHOW_COMMITS_FIELD.split_once('.').unwrap().1,
-            self.show_commits()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            STACKED_FIELD.split_once('.').unwrap().1,
-            self.show_stacked()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            AUTO_FIXUP_FIELD.split_once('.').unwrap().1,
-            self.auto_fixup()
-        )?;
-        writeln!(
-            f,
-            "\t{}={}",
-            AUTO_REPAIR_FIELD.split_once('.').unwrap().1,
-            self.auto_repair()
-        )?;
-        writeln!(f, "[{}]", BACKUP_CAPACITY_FIELD.split_once('.').unwrap().0)?;
-        writeln!(
-            f,
-            "\t{}={}",
-            BACKUP_CAPACITY_FIELD.split_once('.').unwrap().1,
-            self.capacity().unwrap_or(0)
-        )?;
+        let mut current_section = None;
+        for (key, value) in self.effective() {
+            let (section, rest) = key.split_once('.').expect("all keys have a section");
+            let (section, name) = match rest.rsplit_once('.') {
+                Some((subsection, name)) => (format!("{section} \"{subsection}\""), name),
+                None => (section.to_owned(), rest),
+            };
+            if current_section.as_ref() != Some(&section) {
+                writeln!(f, "[{section}]")?;
+                current_section = Some(section);
+            }
+            writeln!(f, "\t{name}={value}")?;
+        }
         Ok(())
     }
 }
 
+/// Branches grouped into named stacks
+///
+/// Membership is stored per branch as `branch.<name>.stack` in the repo config, so it follows the
+/// branch through `git branch -m` and is dropped with `git branch -D`.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct NamedStacks {
+    branches: std::collections::BTreeMap<String, String>,
+}
+
+impl NamedStacks {
+    pub fn from_repo(repo: &git2::Repository) -> eyre::Result<Self> {
+        let config = repo.config()?;
+        let mut branches = std::collections::BTreeMap::new();
+        let mut entries = config.entries(Some(r"^branch\..*\.stack$"))?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let (Some(key), Some(value)) = (entry.name(), entry.value()) else {
+                continue;
+            };
+            let Some(branch) = parse_named_stack_key(key) else {
+                continue;
+            };
+            branches.insert(branch.to_owned(), value.to_owned());
+        }
+        Ok(Self { branches })
+    }
+
+    /// The named stack `branch` belongs to
+    pub fn stack_of(&self, branch: &str) -> Option<&str> {
+        self.branches.get(branch).map(|s| s.as_str())
+    }
+
+    pub fn contains(&self, stack: &str) -> bool {
+        self.branches.values().any(|s| s == stack)
+    }
+
+    /// Each named stack with its branches
+    pub fn stacks(&self) -> std::collections::BTreeMap<&str, Vec<&str>> {
+        let mut stacks = std::collections::BTreeMap::<&str, Vec<&str>>::new();
+        for (branch, stack) in &self.branches {
+            stacks
+                .entry(stack.as_str())
+                .or_default()
+                .push(branch.as_str());
+        }
+        stacks
+    }
+
+    /// Move `branch` into `stack`, persisting it to the repo config
+    pub fn assign(
+        &mut self,
+        repo: &git2::Repository,
+        branch: &str,
+        stack: &str,
+    ) -> eyre::Result<()> {
+        let mut config = git2::Config::open(&git_dir_config(repo))?;
+        config.set_str(&named_stack_key(branch), stack)?;
+        self.branches.insert(branch.to_owned(), stack.to_owned());
+        Ok(())
+    }
+
+    /// Rename `old` to `new`, persisting it to the repo config
+    pub fn rename(&mut self, repo: &git2::Repository, old: &str, new: &str) -> eyre::Result<()> {
+        let mut config = git2::Config::open(&git_dir_config(repo))?;
+        for (branch, stack) in self.branches.iter_mut() {
+            if stack == old {
+                config.set_str(&named_stack_key(branch), new)?;
+                *stack = new.to_owned();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn named_stack_key(branch: &str) -> String {
+    format!("{BRANCH_SECTION}.{branch}.{NAMED_STACK_KEY}")
+}
+
+/// Extract `name` from `branch.<name>.stack`
+fn parse_named_stack_key(key: &str) -> Option<&str> {
+    key.strip_prefix(BRANCH_SECTION)?
+        .strip_prefix('.')?
+        .strip_suffix(NAMED_STACK_KEY)?
+        .strip_suffix('.')
+        .filter(|b| !b.is_empty())
+}
+
+/// A `git config` key `git-stack` reads, for `git stack config --schema` and `--validate`
+#[derive(Copy, Clone, Debug)]
+pub struct Field {
+    /// The key, with placeholders like `<name>` for the parts the user picks
+    pub key: &'static str,
+    pub kind: FieldKind,
+    /// Whether each occurrence of the key adds to a list, rather than overriding the last
+    pub multivar: bool,
+    pub description: &'static str,
+}
+
+/// The values a [`Field`] accepts
+#[derive(Copy, Clone, Debug)]
+pub enum FieldKind {
+    String,
+    Bool,
+    /// A non-negative integer
+    Integer,
+    /// A time delta, like `10days`
+    Duration,
+    /// One of a fixed set of names
+    Choice(fn() -> Vec<String>),
+    /// `git config` color syntax, like `blue bold`
+    Color,
+    /// See [`DefaultCommand`]
+    DefaultCommand,
+}
+
+fn choices<E: clap::ValueEnum>() -> Vec<String> {
+    E::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_owned())
+        .collect()
+}
+
+const fn field(
+    key: &'static str,
+    kind: FieldKind,
+    multivar: bool,
+    description: &'static str,
+) -> Field {
+    Field {
+        key,
+        kind,
+        multivar,
+        description,
+    }
+}
+
+/// Every key `git-stack` reads, besides git's own like `core.editor`
+pub static FIELDS: &[Field] = &[
+    field(
+        PROTECTED_STACK_FIELD,
+        FieldKind::String,
+        true,
+        "Branch names matching these globs (`.gitignore` syntax) are protected",
+    ),
+    field(
+        PROTECT_REMOTE_DEFAULT_FIELD,
+        FieldKind::Bool,
+        false,
+        "Protect the pull remote's default branch",
+    ),
+    field(
+        PROTECT_COMMIT_COUNT,
+        FieldKind::Integer,
+        false,
+        "Protect commits on a branch with this many or more commits",
+    ),
+    field(
+        PROTECT_COMMIT_AGE,
+        FieldKind::Duration,
+        false,
+        "Protect commits older than this",
+    ),
+    field(
+        AUTO_BASE_COMMIT_COUNT,
+        FieldKind::Integer,
+        false,
+        "Split off branches more than this many commits from the implied base",
+    ),
+    field(
+        STACK_FIELD,
+        FieldKind::Choice(choices::<Stack>),
+        false,
+        "Which development branch-stacks to operate on",
+    ),
+    field(
+        PUSH_REMOTE_FIELD,
+        FieldKind::String,
+        false,
+        "Development remote for pushing local branches",
+    ),
+    field(
+        PULL_REMOTE_FIELD,
+        FieldKind::String,
+        false,
+        "Upstream remote for pulling protected branches",
+    ),
+    field(
+        PROTECTED_REMOTE_FIELD,
+        FieldKind::String,
+        true,
+        "`<glob>=<remote>` to pull matching protected branches from",
+    ),
+    field(
+        FORMAT_FIELD,
+        FieldKind::Choice(choices::<Format>),
+        false,
+        "How to show the stacks",
+    ),
+    field(
+        ICONS_FIELD,
+        FieldKind::Choice(choices::<Icons>),
+        false,
+        "Markers for the graph",
+    ),
+    field(
+        PALETTE_FIELD,
+        FieldKind::Choice(choices::<ColorPalette>),
+        false,
+        "Colors used for all output",
+    ),
+    field(
+        "color.stack.<role>",
+        FieldKind::Color,
+        false,
+        "Override the palette's color for `error`, `warn`, `info`, `good`, `highlight`, or `hint`",
+    ),
+    field(
+        SHOW_COMMITS_FIELD,
+        FieldKind::Choice(choices::<ShowCommits>),
+        false,
+        "Which commits to show",
+    ),
+    field(
+        STACKED_FIELD,
+        FieldKind::Bool,
+        false,
+        "Show branches as stacked on top of each other, where possible",
+    ),
+    field(
+        SIGNATURES_FIELD,
+        FieldKind::Bool,
+        false,
+        "Mark each development commit as signed, signed with an unknown key, or unsigned",
+    ),
+    field(
+        BASE_HISTORY_FIELD,
+        FieldKind::Integer,
+        false,
+        "Show this many protected commits beneath each stack",
+    ),
+    field(
+        MAX_WIDTH_FIELD,
+        FieldKind::Integer,
+        false,
+        "Truncate graph lines to this width (default: the terminal's width, 0 for no limit)",
+    ),
+    field(
+        AUTO_FIXUP_FIELD,
+        FieldKind::Choice(choices::<Fixup>),
+        false,
+        "Default fixup operation with `--rebase`",
+    ),
+    field(
+        AUTO_REPAIR_FIELD,
+        FieldKind::Bool,
+        false,
+        "Perform branch repair with `--rebase`",
+    ),
+    field(
+        PRESERVE_ORDER_FIELD,
+        FieldKind::Bool,
+        false,
+        "Only repair in ways that keep each branch's commits in order",
+    ),
+    field(
+        DROP_BY_PATCH_ID_FIELD,
+        FieldKind::Bool,
+        false,
+        "With `--rebase`, drop commits whose patch-id matches a pulled commit",
+    ),
+    field(
+        RESIGN_FIELD,
+        FieldKind::Choice(choices::<Resign>),
+        false,
+        "How to rewrite commits signed by other people",
+    ),
+    field(
+        SIGN_FIELD,
+        FieldKind::Choice(choices::<Sign>),
+        false,
+        "Which rewritten commits to sign (default: all with `stack.gpgSign`, otherwise none)",
+    ),
+    field(
+        GITLAB_MR_FIELD,
+        FieldKind::Bool,
+        false,
+        "With `--push`, ask GitLab to create merge requests",
+    ),
+    field(
+        PUSH_STYLE_FIELD,
+        FieldKind::Choice(choices::<PushStyle>),
+        false,
+        "How `--push` sends branches to the push remote",
+    ),
+    field(
+        FORGE_FIELD,
+        FieldKind::Choice(choices::<Forge>),
+        false,
+        "Where pull requests are hosted",
+    ),
+    field(
+        GITHUB_TOKEN_FIELD,
+        FieldKind::String,
+        false,
+        "GitHub token for `stack.forge=github`",
+    ),
+    field(
+        DEFAULT_COMMAND_FIELD,
+        FieldKind::DefaultCommand,
+        false,
+        "What a bare `git stack` does",
+    ),
+    field(
+        NETWORK_RETRIES_FIELD,
+        FieldKind::Integer,
+        false,
+        "Retry fetches and pushes this many times on transient failures",
+    ),
+    field(
+        NETWORK_RETRY_DELAY_FIELD,
+        FieldKind::Duration,
+        false,
+        "Delay before the first retry, doubling with each attempt",
+    ),
+    field(
+        FRESH_MAX_AGE_FIELD,
+        FieldKind::Duration,
+        false,
+        "How old the base's remote-tracking branch may be before warning",
+    ),
+    field(
+        PROFILE_FIELD,
+        FieldKind::String,
+        false,
+        "Profile to layer over the config files",
+    ),
+    field(
+        "stack-profile.<name>.<field>",
+        FieldKind::String,
+        false,
+        "`stack.<field>` when the `<name>` profile is selected",
+    ),
+    field(
+        "stack.gpgSign",
+        FieldKind::Bool,
+        false,
+        "Sign commits, falling back to `commit.gpgSign`",
+    ),
+    field(
+        WORKTREE_ROOT_FIELD,
+        FieldKind::String,
+        false,
+        "Where `git stack worktree` creates worktrees",
+    ),
+    field(
+        READ_ONLY_FIELD,
+        FieldKind::Bool,
+        false,
+        "Refuse to run commands that change the repo, only allowing reports and dry-runs",
+    ),
+    field(
+        AUTO_STASH_FIELD,
+        FieldKind::Bool,
+        false,
+        "Stash a dirty working tree while `git stack next` and `previous` switch, restoring it after",
+    ),
+    field(
+        "stack.alias.<name>",
+        FieldKind::String,
+        false,
+        "Run `git stack <name>` as these arguments, like `next --move`",
+    ),
+    field(
+        crate::git::STACK_WORKTREE_FIELD,
+        FieldKind::String,
+        true,
+        "Worktrees created by `git stack worktree`",
+    ),
+    field(
+        "stack.maintenance-repo",
+        FieldKind::String,
+        true,
+        "Repositories synced by `git stack maintenance run`",
+    ),
+    field(
+        "branch.<name>.stack",
+        FieldKind::String,
+        false,
+        "Named stack the branch belongs to",
+    ),
+    field(
+        "branch.<name>.stack-push-remote",
+        FieldKind::String,
+        false,
+        "Override `stack.push-remote` for the branch",
+    ),
+    field(
+        "branch.<name>.stack-auto-fixup",
+        FieldKind::Choice(choices::<Fixup>),
+        false,
+        "Override `stack.auto-fixup` for the fixups in the branch",
+    ),
+    field(
+        "branch.<name>.stack-protected",
+        FieldKind::Bool,
+        false,
+        "Protect or unprotect the branch, overriding `stack.protected-branch`",
+    ),
+    field(
+        BACKUP_CAPACITY_FIELD,
+        FieldKind::Integer,
+        false,
+        "How many `git branch-stash` snapshots to keep (0 for unlimited)",
+    ),
+    field(
+        BACKUP_MAX_AGE_FIELD,
+        FieldKind::Duration,
+        false,
+        "Drop `git branch-stash` snapshots older than this",
+    ),
+    field(
+        "branch-stash.<name>.capacity",
+        FieldKind::Integer,
+        false,
+        "Override `branch-stash.capacity` for the named snapshot stack",
+    ),
+    field(
+        "branch-stash.<name>.max-age",
+        FieldKind::Duration,
+        false,
+        "Override `branch-stash.max-age` for the named snapshot stack",
+    ),
+];
+
+/// Whether `key` is in a namespace `git-stack` owns, so any key there should be a [`Field`]
+pub fn is_stack_key(key: &str) -> bool {
+    let section = key.split_once('.').map(|(s, _)| s).unwrap_or(key);
+    ["stack", PROFILE_SECTION, STASH_SECTION].contains(&section.to_ascii_lowercase().as_str())
+        || key
+            .to_ascii_lowercase()
+            .starts_with(&format!("{COLOR_SECTION}."))
+        || key
+            .strip_prefix(BRANCH_SECTION)
+            .and_then(|k| k.strip_prefix('.'))
+            .and_then(|k| k.rsplit_once('.'))
+            .is_some_and(|(_, k)| k.starts_with("stack-"))
+        || find_field(key).is_some()
+}
+
+/// The [`Field`] for `key`, as `git config` reports it
+///
+/// For `stack-profile.<name>.<field>`, this is the `stack.<field>` it stands in for.
+pub fn find_field(key: &str) -> Option<&'static Field> {
+    if let Some((_, field)) = parse_profile_key(key) {
+        if field.eq_ignore_ascii_case("profile") {
+            return None;
+        }
+        return FIELDS
+            .iter()
+            .find(|f| f.key.eq_ignore_ascii_case(&format!("stack.{field}")));
+    }
+    let pattern = if parse_color_key(key).is_some() {
+        "color.stack.<role>".to_owned()
+    } else if parse_alias_key(key).is_some() {
+        format!("{ALIAS_SECTION}.<name>")
+    } else if let Some((_, stash_key)) = parse_stash_key(key) {
+        format!("{STASH_SECTION}.<name>.{stash_key}")
+    } else if key
+        .strip_prefix(BRANCH_SECTION)
+        .and_then(|k| k.strip_prefix('.'))
+        .and_then(|k| k.rsplit_once('.'))
+        .is_some_and(|(name, k)| !name.is_empty() && k == NAMED_STACK_KEY)
+    {
+        format!("{BRANCH_SECTION}.<name>.{NAMED_STACK_KEY}")
+    } else if let Some((_, branch_key)) = parse_branch_key(key) {
+        format!("{BRANCH_SECTION}.<name>.{branch_key}")
+    } else {
+        key.to_owned()
+    };
+    FIELDS
+        .iter()
+        .filter(|f| f.key != "stack-profile.<name>.<field>")
+        .find(|f| f.key.eq_ignore_ascii_case(&pattern))
+}
+
+/// The known key closest to the unknown `key`, for suggesting fixes to typos
+pub fn suggest_field(key: &str) -> Option<&'static str> {
+    let key = key.to_ascii_lowercase();
+    FIELDS
+        .iter()
+        .filter(|f| !f.key.contains('<'))
+        .map(|f| (edit_distance(&key, &f.key.to_ascii_lowercase()), f.key))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, key)| key)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut row: Vec<_> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if a == *b {
+                diagonal
+            } else {
+                1 + diagonal.min(row[j]).min(current)
+            };
+            diagonal = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Split `branch.<name>.stack-<key>` into `name` and `stack-<key>`
+fn parse_branch_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix(BRANCH_SECTION)?.strip_prefix('.')?;
+    let (name, branch_key) = rest.rsplit_once('.')?;
+    (!name.is_empty()
+        && [
+            BRANCH_PUSH_REMOTE_KEY,
+            BRANCH_AUTO_FIXUP_KEY,
+            BRANCH_PROTECTED_KEY,
+        ]
+        .contains(&branch_key))
+    .then_some((name, branch_key))
+}
+
+/// Split `branch-stash.<name>.<key>` into `name` and `key`
+fn parse_stash_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix(STASH_SECTION)?.strip_prefix('.')?;
+    let (name, stash_key) = rest.rsplit_once('.')?;
+    [STASH_CAPACITY_KEY, STASH_MAX_AGE_KEY]
+        .contains(&stash_key)
+        .then_some((name, stash_key))
+}
+
+/// Extract `name` from `stack.alias.<name>`
+fn parse_alias_key(key: &str) -> Option<&str> {
+    let name = key.strip_prefix(ALIAS_SECTION)?.strip_prefix('.')?;
+    (!name.is_empty() && !name.contains('.')).then_some(name)
+}
+
+/// Extract `role` from `color.stack.<role>`
+fn parse_color_key(key: &str) -> Option<&str> {
+    let role = key.strip_prefix(COLOR_SECTION)?.strip_prefix('.')?;
+    COLOR_ROLES.contains(&role).then_some(role)
+}
+
+/// Split `stack-profile.<name>.<key>` into `name` and `key`
+fn parse_profile_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix(PROFILE_SECTION)?.strip_prefix('.')?;
+    rest.rsplit_once('.').filter(|(name, _)| !name.is_empty())
+}
+
 fn git_dir_config(repo: &git2::Repository) -> std::path::PathBuf {
     repo.path().join("config")
 }
@@ -540,13 +2198,28 @@ fn default_branch(config: &git2::Config) -> &str {
     config.get_str("init.defaultBranch").ok().unwrap_or("main")
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+/// The branch `refs/remotes/<remote>/HEAD` points to, as set by `git clone` or
+/// `git remote set-head`
+fn remote_default_branch(repo: &git2::Repository, remote: &str) -> Option<String> {
+    let head = repo
+        .find_reference(&format!("refs/remotes/{remote}/HEAD"))
+        .ok()?;
+    let target = head.symbolic_target()?;
+    let branch = target.strip_prefix(&format!("refs/remotes/{remote}/"))?;
+    (!branch.is_empty() && branch != "HEAD").then(|| branch.to_owned())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
 pub enum Format {
     /// No output
     Silent,
     /// List branches in selected stacks
     List,
+    /// List branches with their parent branch, commits ahead and behind the push remote, and
+    /// status, tab-separated
+    ListVerbose,
     /// Render a branch branch
+    #[default]
     Graph,
     /// Internal data for debugging
     Debug,
@@ -576,15 +2249,82 @@ impl FromStr for Format {
     }
 }
 
-impl Default for Format {
-    fn default() -> Self {
-        Self::Graph
+/// Markers used when rendering the branch graph
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum Icons {
+    /// Box-drawing glyphs with text status
+    #[default]
+    Unicode,
+    /// Plain ASCII, for terminals and fonts without box-drawing support
+    Ascii,
+    /// Emoji status markers
+    Emoji,
+}
+
+impl std::fmt::Display for Icons {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+impl FromStr for Icons {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+/// Colors used for all output, before `color.stack.<role>` overrides
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum ColorPalette {
+    /// Red, yellow, blue, cyan, and green
+    #[default]
+    Default,
+    /// Avoids telling roles apart by red and green
+    Colorblind,
+    /// No colors, only bold, underline, and dim
+    MonochromeBold,
+}
+
+impl std::fmt::Display for ColorPalette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl FromStr for ColorPalette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
 pub enum ShowCommits {
     None,
+    #[default]
     Unprotected,
     All,
 }
@@ -613,13 +2353,7 @@ impl FromStr for ShowCommits {
     }
 }
 
-impl Default for ShowCommits {
-    fn default() -> Self {
-        Self::Unprotected
-    }
-}
-
-#[derive(clap::ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(clap::ValueEnum, Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum Stack {
     /// Branches in BASE..HEAD
     Current,
@@ -628,6 +2362,7 @@ pub enum Stack {
     /// Branches in BASE..
     Descendants,
     /// Show all branches
+    #[default]
     All,
 }
 
@@ -655,17 +2390,215 @@ impl FromStr for Stack {
     }
 }
 
-impl Default for Stack {
-    fn default() -> Self {
-        Self::All
+/// How to treat commits signed by other people when rewriting them
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum Resign {
+    /// Rewrite them, dropping their signatures
+    #[default]
+    Strip,
+    /// Leave branches with them in place
+    Block,
+    /// Leave branches with them in place and sign the commits that are rewritten
+    ResignMine,
+}
+
+impl std::fmt::Display for Resign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
     }
 }
 
+impl FromStr for Resign {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+/// Which commits to sign when rewriting them
 #[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sign {
+    /// Sign every rewritten commit
+    Always,
+    /// Leave rewritten commits unsigned, dropping any signatures
+    Never,
+    /// Only sign rewritten commits that were signed before
+    IfSigned,
+}
+
+impl std::fmt::Display for Sign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl FromStr for Sign {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+/// Where pull requests are hosted
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum Forge {
+    /// Don't talk to a forge
+    #[default]
+    None,
+    /// GitHub, through the `gh` CLI
+    Github,
+}
+
+impl std::fmt::Display for Forge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl FromStr for Forge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+/// How `--push` sends branches to the push remote
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum PushStyle {
+    /// Force-push each branch to the branch of the same name
+    #[default]
+    Plain,
+    /// Like `plain`, asking GitLab to create a merge request targeting the parent branch
+    GitlabMr,
+    /// Push each branch for review to `refs/for/<protected branch>`, as Gerrit expects
+    GerritRefsFor,
+}
+
+impl std::fmt::Display for PushStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl FromStr for PushStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use clap::ValueEnum;
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {s}"))
+    }
+}
+
+/// What a bare `git stack` does
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DefaultCommand {
+    /// Only show the stacks
+    #[default]
+    Show,
+    /// Run `git stack sync`
+    Sync,
+    /// Run a combination of `--pull`, `--rebase`, and `--push`, like `pull+rebase+push`
+    Pipeline {
+        pull: bool,
+        rebase: bool,
+        push: bool,
+    },
+}
+
+impl std::fmt::Display for DefaultCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Show => "show".fmt(f),
+            Self::Sync => "sync".fmt(f),
+            Self::Pipeline { pull, rebase, push } => {
+                let steps = [(*pull, "pull"), (*rebase, "rebase"), (*push, "push")];
+                let steps: Vec<_> = steps
+                    .into_iter()
+                    .filter_map(|(enabled, step)| enabled.then_some(step))
+                    .collect();
+                steps.join("+").fmt(f)
+            }
+        }
+    }
+}
+
+impl FromStr for DefaultCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "show" => return Ok(Self::Show),
+            "sync" => return Ok(Self::Sync),
+            _ => {}
+        }
+
+        let mut pull = false;
+        let mut rebase = false;
+        let mut push = false;
+        for step in s.split('+').map(|s| s.trim()) {
+            match step {
+                "pull" => pull = true,
+                "rebase" => rebase = true,
+                "push" => push = true,
+                _ => {
+                    return Err(format!(
+                        "expected `show`, `sync`, or `+`-separated `pull`, `rebase`, `push`, got `{step}`"
+                    ))
+                }
+            }
+        }
+        Ok(Self::Pipeline { pull, rebase, push })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
 pub enum Fixup {
     /// No special processing
     Ignore,
     /// Move them to after the commit they fix
+    #[default]
     Move,
     /// Squash into the commit they fix
     Squash,
@@ -700,9 +2633,3 @@ impl FromStr for Fixup {
         Err(format!("Invalid variant: {s}"))
     }
 }
-
-impl Default for Fixup {
-    fn default() -> Self {
-        Self::Move
-    }
-}
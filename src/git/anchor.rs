@@ -0,0 +1,77 @@
+/// Notes ref holding the commits marked as anchors
+pub const ANCHORS_REF: &str = "refs/notes/stack-anchors";
+
+/// Commits that planning must not reorder around
+///
+/// Anchors are for commits that something outside of the repo refers to by id.  Fixups aren't
+/// moved across them and stacks aren't realigned or merged around them.  They are kept as git
+/// notes under [`ANCHORS_REF`] so they can be shared like any other notes.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Anchors {
+    ids: std::collections::BTreeSet<git2::Oid>,
+}
+
+impl Anchors {
+    pub fn new(ids: impl IntoIterator<Item = git2::Oid>) -> Self {
+        Self {
+            ids: ids.into_iter().collect(),
+        }
+    }
+
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let notes = match repo.notes(Some(ANCHORS_REF)) {
+            Ok(notes) => notes,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        let mut ids = std::collections::BTreeSet::new();
+        for note in notes {
+            let (_, annotated_id) = note?;
+            ids.insert(annotated_id);
+        }
+        Ok(Self { ids })
+    }
+
+    pub fn contains(&self, id: git2::Oid) -> bool {
+        self.ids.contains(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = git2::Oid> + '_ {
+        self.ids.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Mark `id` as an anchor
+///
+/// Returns `false` if it already was one.
+pub fn add_anchor(repo: &git2::Repository, id: git2::Oid) -> Result<bool, git2::Error> {
+    if repo.find_note(Some(ANCHORS_REF), id).is_ok() {
+        return Ok(false);
+    }
+    let signature = repo.signature()?;
+    repo.note(
+        &signature,
+        &signature,
+        Some(ANCHORS_REF),
+        id,
+        "git-stack anchor\n",
+        false,
+    )?;
+    Ok(true)
+}
+
+/// Stop treating `id` as an anchor
+///
+/// Returns `false` if it wasn't one.
+pub fn remove_anchor(repo: &git2::Repository, id: git2::Oid) -> Result<bool, git2::Error> {
+    if repo.find_note(Some(ANCHORS_REF), id).is_err() {
+        return Ok(false);
+    }
+    let signature = repo.signature()?;
+    repo.note_delete(id, Some(ANCHORS_REF), &signature, &signature)?;
+    Ok(true)
+}
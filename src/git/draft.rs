@@ -0,0 +1,81 @@
+/// Commit trailer marking the branch the commit is on as a draft, like `Stack-Draft: true`
+pub const DRAFT_TRAILER: &str = "Stack-Draft";
+
+/// Branch config marking the branch as a draft, `branch.<name>.stack-draft`
+pub const DRAFT_BRANCH_KEY: &str = "stack-draft";
+
+/// Branches that aren't ready to push
+///
+/// A branch is a draft when `branch.<name>.stack-draft` is set or a commit on it has a
+/// [`DRAFT_TRAILER`].  Drafts are only pushed with `git stack --push --draft`.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct DraftBranches {
+    names: std::collections::BTreeSet<String>,
+}
+
+impl DraftBranches {
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+        }
+    }
+
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let config = repo.config()?;
+        let mut names = std::collections::BTreeSet::new();
+        let mut entries = config.entries(Some(r"^branch\..+\.stack-draft$"))?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let Some(name) = entry
+                .name()
+                .and_then(|key| key.strip_prefix("branch."))
+                .and_then(|key| key.strip_suffix(DRAFT_BRANCH_KEY))
+                .and_then(|key| key.strip_suffix('.'))
+            else {
+                continue;
+            };
+            if is_true(entry.value().unwrap_or_default()) {
+                names.insert(name.to_owned());
+            } else {
+                // Later entries override earlier ones
+                names.remove(name);
+            }
+        }
+        Ok(Self { names })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names.iter().map(|s| s.as_str())
+    }
+}
+
+/// Whether the commit's message has a [`DRAFT_TRAILER`] set to true
+pub fn is_draft_commit(repo: &git2::Repository, id: git2::Oid) -> bool {
+    repo.find_commit(id)
+        .map(|commit| is_draft_message(commit.message_bytes()))
+        .unwrap_or(false)
+}
+
+/// Whether `message` has a [`DRAFT_TRAILER`] set to true
+pub fn is_draft_message(message: &[u8]) -> bool {
+    let Ok(trailers) = git2::message_trailers_bytes(message) else {
+        return false;
+    };
+    // Later trailers override earlier ones
+    let draft = trailers
+        .iter()
+        .rfind(|(key, _)| key.eq_ignore_ascii_case(DRAFT_TRAILER.as_bytes()))
+        .map(|(_, value)| is_true(&String::from_utf8_lossy(value)));
+    draft.unwrap_or(false)
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "" | "true" | "yes" | "on" | "1"
+    )
+}
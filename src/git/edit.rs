@@ -0,0 +1,62 @@
+/// The commit `git stack edit` stopped at, for `git stack edit --continue` to rebase onto
+///
+/// This is kept per worktree, under `.git/git-stack/edit.json`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EditStop {
+    /// The commit being edited, as it was before the edit
+    pub commit: String,
+    /// The branch to switch back to when done
+    pub branch: String,
+}
+
+impl EditStop {
+    pub fn new(commit: git2::Oid, branch: String) -> Self {
+        Self {
+            commit: commit.to_string(),
+            branch,
+        }
+    }
+
+    /// Where the stop is kept within the worktree's `.git` directory
+    pub fn path(git_dir: &std::path::Path) -> std::path::PathBuf {
+        git_dir.join("git-stack").join("edit.json")
+    }
+
+    pub fn commit_id(&self) -> eyre::Result<git2::Oid> {
+        git2::Oid::from_str(&self.commit)
+            .map_err(|err| eyre::eyre!("invalid commit `{}`: {}", self.commit, err.message()))
+    }
+
+    pub fn load(repo: &git2::Repository) -> eyre::Result<Option<Self>> {
+        let path = Self::path(repo.path());
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => eyre::bail!("could not read `{}`: {}", path.display(), err),
+        };
+        let stop = serde_json::from_str(&data)
+            .map_err(|err| eyre::eyre!("could not parse `{}`: {}", path.display(), err))?;
+        Ok(Some(stop))
+    }
+
+    pub fn save(&self, repo: &git2::Repository) -> eyre::Result<()> {
+        let path = Self::path(repo.path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, data)
+            .map_err(|err| eyre::eyre!("could not write `{}`: {}", path.display(), err))?;
+        Ok(())
+    }
+
+    /// Forget the stop once the edit is finished or aborted
+    pub fn clear(repo: &git2::Repository) -> eyre::Result<()> {
+        let path = Self::path(repo.path());
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => eyre::bail!("could not remove `{}`: {}", path.display(), err),
+        }
+    }
+}
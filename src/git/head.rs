@@ -0,0 +1,89 @@
+/// `git branch-stash` snapshot metadata recording where `HEAD` was, see [`HeadState`]
+pub const HEAD_METADATA_KEY: &str = "head";
+
+/// Where `HEAD` pointed when a snapshot was taken
+///
+/// Snapshots only record branches, so this is kept in their metadata under
+/// [`HEAD_METADATA_KEY`] to return to the same branch, or detached commit, when undoing.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HeadState {
+    /// The checked out branch, unset when `HEAD` is detached
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The commit `HEAD` resolved to
+    #[serde(with = "crate::rewrite::oid")]
+    pub id: git2::Oid,
+    /// Whether the index or worktree had uncommitted changes
+    #[serde(default)]
+    pub dirty: bool,
+}
+
+impl HeadState {
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let head = repo.head()?;
+        let branch = if repo.head_detached()? {
+            None
+        } else {
+            head.shorthand().map(ToOwned::to_owned)
+        };
+        let id = head.peel_to_commit()?.id();
+        let statuses = repo.statuses(Some(
+            git2::StatusOptions::new()
+                .include_ignored(false)
+                .include_untracked(false),
+        ))?;
+        let dirty = !statuses.is_empty();
+        Ok(Self { branch, id, dirty })
+    }
+
+    /// Read the state recorded by [`HeadState::insert_metadata`], if any
+    pub fn from_metadata(
+        metadata: &std::collections::BTreeMap<String, serde_json::Value>,
+    ) -> Option<Self> {
+        let value = metadata.get(HEAD_METADATA_KEY)?;
+        match serde_json::from_value(value.clone()) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                log::debug!(
+                    "Ignoring invalid `{}` snapshot metadata: {}",
+                    HEAD_METADATA_KEY,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn insert_metadata(
+        &self,
+        metadata: &mut std::collections::BTreeMap<String, serde_json::Value>,
+    ) {
+        let value = serde_json::to_value(self).expect("always serializable");
+        metadata.insert(HEAD_METADATA_KEY.to_owned(), value);
+    }
+
+    /// Check out the recorded branch, or detach at the recorded commit when there was no branch
+    ///
+    /// Run this after the branches are restored so the branch points to its recorded commit.  If
+    /// the branch no longer exists, `HEAD` is detached at the recorded commit instead.  This
+    /// doesn't bring back uncommitted changes, those were stashed by the operation.
+    pub fn restore(&self, repo: &mut dyn crate::git::Repo) -> Result<(), git2::Error> {
+        let current = repo.head_branch();
+        match self.branch.as_deref() {
+            Some(name) if repo.find_local_branch(name).is_some() => {
+                if current.as_ref().and_then(|b| b.local_name()) != Some(name) {
+                    log::debug!("Restoring HEAD to {}", name);
+                    repo.switch_branch(name)?;
+                }
+            }
+            _ => {
+                if current.is_some() || repo.head_commit().id != self.id {
+                    log::debug!("Restoring HEAD to {}", self.id);
+                    repo.switch_commit(self.id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
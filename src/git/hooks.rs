@@ -0,0 +1,15 @@
+/// The hooks `git` would run for `repo`, honoring `core.hooksPath`
+///
+/// Unlike [`git2_ext::hooks::Hooks::with_repo`], a relative `core.hooksPath` is resolved like `git`
+/// does, against the top of the worktree (or the git dir of a bare repo), and linked worktrees
+/// share the hooks of the repo they belong to.
+pub fn hooks(repo: &git2::Repository) -> Result<git2_ext::hooks::Hooks, git2::Error> {
+    let config = repo.config()?;
+    let root = match config.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => path,
+        Ok(path) => repo.workdir().unwrap_or_else(|| repo.path()).join(path),
+        Err(_) => crate::git::common_dir(repo)?.join("hooks"),
+    };
+    log::trace!("Looking for hooks in {}", root.display());
+    Ok(git2_ext::hooks::Hooks::new(root))
+}
@@ -0,0 +1,77 @@
+/// Notes ref holding the commits exempt from the WIP and fixup heuristics
+pub const IGNORED_REF: &str = "refs/notes/stack-ignored";
+
+/// Commits whose summary shouldn't be read as WIP or `fixup!`
+///
+/// Some commits legitimately start with `WIP` or `fixup!`.  These aren't kept from being pushed
+/// or squashed into other commits.  They are kept as git notes under [`IGNORED_REF`] so they can
+/// be shared like any other notes.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct IgnoredCommits {
+    ids: std::collections::BTreeSet<git2::Oid>,
+}
+
+impl IgnoredCommits {
+    pub fn new(ids: impl IntoIterator<Item = git2::Oid>) -> Self {
+        Self {
+            ids: ids.into_iter().collect(),
+        }
+    }
+
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let notes = match repo.notes(Some(IGNORED_REF)) {
+            Ok(notes) => notes,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        let mut ids = std::collections::BTreeSet::new();
+        for note in notes {
+            let (_, annotated_id) = note?;
+            ids.insert(annotated_id);
+        }
+        Ok(Self { ids })
+    }
+
+    pub fn contains(&self, id: git2::Oid) -> bool {
+        self.ids.contains(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = git2::Oid> + '_ {
+        self.ids.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Exempt `id` from the WIP and fixup heuristics
+///
+/// Returns `false` if it already was.
+pub fn ignore_commit(repo: &git2::Repository, id: git2::Oid) -> Result<bool, git2::Error> {
+    if repo.find_note(Some(IGNORED_REF), id).is_ok() {
+        return Ok(false);
+    }
+    let signature = repo.signature()?;
+    repo.note(
+        &signature,
+        &signature,
+        Some(IGNORED_REF),
+        id,
+        "git-stack ignore-commit\n",
+        false,
+    )?;
+    Ok(true)
+}
+
+/// Apply the WIP and fixup heuristics to `id` again
+///
+/// Returns `false` if it wasn't exempt.
+pub fn unignore_commit(repo: &git2::Repository, id: git2::Oid) -> Result<bool, git2::Error> {
+    if repo.find_note(Some(IGNORED_REF), id).is_err() {
+        return Ok(false);
+    }
+    let signature = repo.signature()?;
+    repo.note_delete(id, Some(IGNORED_REF), &signature, &signature)?;
+    Ok(true)
+}
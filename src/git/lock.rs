@@ -0,0 +1,218 @@
+use std::io::Write;
+
+/// Keeps other `git-stack` processes from rewriting the repo at the same time
+///
+/// The lock is a file under the repo's common `.git` directory, so it is shared by all worktrees.
+/// It is released when dropped.
+#[derive(Debug)]
+pub struct RepoLock {
+    path: std::path::PathBuf,
+}
+
+impl RepoLock {
+    /// Where the lock is kept within the repo's common `.git` directory
+    pub fn path(common_dir: &std::path::Path) -> std::path::PathBuf {
+        common_dir.join("git-stack").join("lock")
+    }
+
+    /// Take the lock for this process
+    ///
+    /// A lock left behind by a process that is no longer running is taken over.
+    pub fn try_acquire(repo: &git2::Repository) -> Result<Self, LockError> {
+        let main =
+            super::open_main(repo).map_err(|err| LockError::Io(std::io::Error::other(err)))?;
+        let path = Self::path(main.path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(LockError::Io)?;
+        }
+
+        // Linking a complete file into place keeps others from seeing a lock without a holder
+        let pid = std::process::id();
+        let since = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let staged_path = path.with_extension(pid.to_string());
+        let mut staged = std::fs::File::create(&staged_path).map_err(LockError::Io)?;
+        let written = writeln!(staged, "{pid}\n{since}");
+        drop(staged);
+        let linked = written
+            .map_err(LockError::Io)
+            .and_then(|()| Self::link(&staged_path, &path));
+        let _ = std::fs::remove_file(&staged_path);
+        linked
+    }
+
+    fn link(staged_path: &std::path::Path, path: &std::path::Path) -> Result<Self, LockError> {
+        loop {
+            match std::fs::hard_link(staged_path, path) {
+                Ok(()) => {
+                    return Ok(Self {
+                        path: path.to_owned(),
+                    });
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    match LockHolder::read(path) {
+                        Some(holder) if holder.is_running() => {
+                            return Err(LockError::Held {
+                                holder,
+                                path: path.to_owned(),
+                            });
+                        }
+                        holder => Self::take_over(staged_path, path, holder)?,
+                    }
+                }
+                Err(err) => return Err(LockError::Io(err)),
+            }
+        }
+    }
+
+    /// Move the stale lock left by `stale` out of the way
+    ///
+    /// Renaming only succeeds for one of the processes racing to take it over.  If the lock
+    /// changed hands since `stale` was read, the live holder's lock is put back.
+    fn take_over(
+        staged_path: &std::path::Path,
+        path: &std::path::Path,
+        stale: Option<LockHolder>,
+    ) -> Result<(), LockError> {
+        log::debug!(
+            "Taking over stale lock `{}` from {:?}",
+            path.display(),
+            stale
+        );
+        let mut stale_path = staged_path.as_os_str().to_owned();
+        stale_path.push(".stale");
+        let stale_path = std::path::PathBuf::from(stale_path);
+        match std::fs::rename(path, &stale_path) {
+            Ok(()) => {}
+            // Another process took it over first
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(LockError::Io(err)),
+        }
+        let moved = LockHolder::read(&stale_path);
+        let result = match moved {
+            Some(holder) if moved != stale && holder.is_running() => {
+                log::debug!(
+                    "Lock `{}` was taken by {:?}, restoring it",
+                    path.display(),
+                    holder
+                );
+                match std::fs::hard_link(&stale_path, path) {
+                    Ok(()) => Err(LockError::Held {
+                        holder,
+                        path: path.to_owned(),
+                    }),
+                    // Leave it to the loop to check the new holder
+                    Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+                    Err(err) => Err(LockError::Io(err)),
+                }
+            }
+            _ => Ok(()),
+        };
+        let _ = std::fs::remove_file(&stale_path);
+        result
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::debug!("Could not remove `{}`: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// The process holding a [`RepoLock`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub since: std::time::SystemTime,
+}
+
+impl LockHolder {
+    fn read(path: &std::path::Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut lines = content.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let since = lines.next()?.trim().parse().ok()?;
+        Some(Self {
+            pid,
+            since: std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(since),
+        })
+    }
+
+    /// Whether the holder is still around
+    ///
+    /// Where this can't be checked, the holder is assumed to have exited.
+    pub fn is_running(&self) -> bool {
+        if self.pid == std::process::id() {
+            return true;
+        }
+        is_running(self.pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    std::path::Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_running(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains(&format!("\"{pid}\"")))
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: u32) -> bool {
+    // Without a way to check, a lock that outlived its run would block every later one
+    false
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process has the lock
+    Held {
+        holder: LockHolder,
+        path: std::path::PathBuf,
+    },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Held { holder, path } => {
+                let since = holder.since.elapsed().unwrap_or_default();
+                let since = std::time::Duration::from_secs(since.as_secs());
+                write!(
+                    f,
+                    "another git-stack (pid {}) has been running for {}, holding `{}`",
+                    holder.pid,
+                    humantime::format_duration(since),
+                    path.display()
+                )
+            }
+            Self::Io(err) => write!(f, "could not lock the repo: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
@@ -1,5 +1,33 @@
+mod anchor;
+mod draft;
+mod edit;
+mod head;
+mod hooks;
+mod ignore;
+mod lock;
+mod oplog;
+mod ops;
 mod protect;
+mod push;
 mod repo;
+mod restack;
+mod selection;
+mod version;
+mod worktree;
 
+pub use anchor::*;
+pub use draft::*;
+pub use edit::*;
+pub use head::*;
+pub use hooks::*;
+pub use ignore::*;
+pub use lock::*;
+pub use oplog::*;
+pub use ops::*;
 pub use protect::*;
+pub use push::*;
 pub use repo::*;
+pub use restack::*;
+pub use selection::*;
+pub use version::*;
+pub use worktree::*;
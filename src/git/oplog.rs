@@ -0,0 +1,176 @@
+/// A `git-stack` operation that rewrote branches, as recorded for `git stack undo`
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Operation {
+    /// What was run, like `reword` or `sync`
+    pub name: String,
+    /// Seconds since the epoch when the operation started
+    pub time: u64,
+    /// The `git branch-stash` snapshot taken before the operation, if it was kept
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<std::path::PathBuf>,
+    /// Where each branch pointed before the operation
+    pub branches: Vec<OperationBranch>,
+    /// Where `HEAD` was before the operation
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<super::HeadState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OperationBranch {
+    pub name: String,
+    #[serde(with = "crate::rewrite::oid")]
+    pub id: git2::Oid,
+}
+
+impl Operation {
+    /// Record the branches and `HEAD` as they are before running `name`
+    pub fn from_repo(repo: &git2::Repository, name: &str) -> Result<Self, git2::Error> {
+        let mut branches = Vec::new();
+        for branch in repo.branches(Some(git2::BranchType::Local))? {
+            let (branch, _) = branch?;
+            let Some(branch_name) = branch.name().ok().flatten() else {
+                continue;
+            };
+            let Some(id) = branch.get().target() else {
+                continue;
+            };
+            branches.push(OperationBranch {
+                name: branch_name.to_owned(),
+                id,
+            });
+        }
+        let head = super::HeadState::from_repo(repo)
+            .map_err(|err| log::debug!("Not recording HEAD for undo: {}", err))
+            .ok();
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Ok(Self {
+            name: name.to_owned(),
+            time,
+            snapshot: None,
+            branches,
+            head,
+        })
+    }
+}
+
+/// The log of [`Operation`]s, kept as numbered files under the repo's `.git/git-stack/ops/`
+///
+/// This lives in the common `.git` directory so undo works from any worktree.
+#[derive(Clone, Debug)]
+pub struct OpLog {
+    dir: std::path::PathBuf,
+}
+
+impl OpLog {
+    /// How many operations are kept, dropping the oldest
+    pub const CAPACITY: usize = 100;
+
+    /// Where the log is kept within the repo's common `.git` directory
+    pub fn path(common_dir: &std::path::Path) -> std::path::PathBuf {
+        common_dir.join("git-stack").join("ops")
+    }
+
+    pub fn new(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let main = super::open_main(repo)?;
+        Ok(Self {
+            dir: Self::path(main.path()),
+        })
+    }
+
+    /// Recorded operations, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = std::path::PathBuf> {
+        let mut entries: Vec<(u64, std::path::PathBuf)> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let index = path.file_stem()?.to_str()?.parse().ok()?;
+                Some((index, path))
+            })
+            .collect();
+        entries.sort_unstable();
+        entries.into_iter().map(|(_, path)| path)
+    }
+
+    pub fn push(&self, operation: &Operation) -> eyre::Result<std::path::PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        let next = self
+            .iter()
+            .next_back()
+            .and_then(|path| path.file_stem()?.to_str()?.parse::<u64>().ok())
+            .map(|index| index + 1)
+            .unwrap_or_default();
+        let path = self.dir.join(format!("{next}.json"));
+        let data = serde_json::to_string_pretty(operation)?;
+        std::fs::write(&path, data)
+            .map_err(|err| eyre::eyre!("could not write `{}`: {}", path.display(), err))?;
+
+        let paths: Vec<_> = self.iter().collect();
+        let excess = paths.len().saturating_sub(Self::CAPACITY);
+        for old in &paths[..excess] {
+            if let Err(err) = std::fs::remove_file(old) {
+                log::debug!("Could not remove {}: {}", old.display(), err);
+            }
+        }
+        Ok(path)
+    }
+
+    /// The most recent operation and where it is recorded
+    pub fn peek(&self) -> eyre::Result<Option<(std::path::PathBuf, Operation)>> {
+        let Some(path) = self.iter().next_back() else {
+            return Ok(None);
+        };
+        let data = std::fs::read_to_string(&path)
+            .map_err(|err| eyre::eyre!("could not read `{}`: {}", path.display(), err))?;
+        let operation = serde_json::from_str(&data)
+            .map_err(|err| eyre::eyre!("could not parse `{}`: {}", path.display(), err))?;
+        Ok(Some((path, operation)))
+    }
+
+    /// Operations recorded more than `max_age` ago, always keeping the most recent one
+    ///
+    /// Operations that can't be read are left alone.
+    pub fn expired(&self, max_age: std::time::Duration) -> Vec<std::path::PathBuf> {
+        let Some(cutoff) = std::time::SystemTime::now()
+            .checked_sub(max_age)
+            .and_then(|cutoff| {
+                cutoff
+                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                    .ok()
+            })
+        else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = self.iter().collect();
+        paths.pop();
+        paths
+            .into_iter()
+            .filter(|path| {
+                let operation = std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|data| serde_json::from_str::<Operation>(&data).ok());
+                match operation {
+                    Some(operation) => operation.time <= cutoff.as_secs(),
+                    None => {
+                        log::debug!("Could not read {}", path.display());
+                        false
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Forget an operation once it is undone
+    pub fn remove(&self, path: &std::path::Path) -> eyre::Result<()> {
+        std::fs::remove_file(path)
+            .map_err(|err| eyre::eyre!("could not remove `{}`: {}", path.display(), err))
+    }
+}
@@ -0,0 +1,503 @@
+use bstr::ByteSlice;
+
+/// Cherry-pick `cherry_id` onto `head_id`, preserving the message bytes and `encoding` header
+///
+/// See [`git2_ext::ops::cherry_pick`]
+pub fn cherry_pick(
+    repo: &git2::Repository,
+    head_id: git2::Oid,
+    cherry_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let cherry_commit = repo.find_commit(cherry_id)?;
+    if is_plain_message(&cherry_commit) {
+        return git2_ext::ops::cherry_pick(repo, head_id, cherry_id, sign);
+    }
+
+    // Pick the same base as `git2_ext::ops::cherry_pick`, so merges diff against the parent
+    // that is being rebased onto, or their first parent otherwise
+    let (base_id, mainline) = match cherry_commit.parent_count() {
+        0 => (cherry_id, 0),
+        1 => (cherry_commit.parent_id(0)?, 0),
+        _ => cherry_commit
+            .parent_ids()
+            .position(|id| id == head_id)
+            .map(|i| (head_id, i as u32 + 1))
+            .unwrap_or((cherry_commit.parent_id(0)?, 1)),
+    };
+    if base_id == head_id {
+        // Already on top of the intended base
+        return Ok(cherry_id);
+    }
+
+    let head_commit = repo.find_commit(head_id)?;
+    let mut index = repo.cherrypick_commit(&cherry_commit, &head_commit, mainline, None)?;
+    if index.has_conflicts() {
+        return Err(git2::Error::new(
+            git2::ErrorCode::Unmerged,
+            git2::ErrorClass::Index,
+            format!("cherry-pick conflicts with {cherry_id}"),
+        ));
+    }
+    let tree_id = index.write_tree_to(repo)?;
+    commit_picked(repo, &head_commit, &cherry_commit, tree_id, sign)
+}
+
+/// Commit `tree_id`, from [`apply_to_tree`] on `head_id`'s tree, as cherry-picking `cherry_id`
+///
+/// This is [`cherry_pick`] for a non-merge commit without redoing the merge a check already did.
+pub fn cherry_pick_tree(
+    repo: &git2::Repository,
+    head_id: git2::Oid,
+    cherry_id: git2::Oid,
+    tree_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let cherry_commit = repo.find_commit(cherry_id)?;
+    if cherry_commit.parent_ids().eq([head_id]) {
+        // Already on top of the intended base
+        return Ok(cherry_id);
+    }
+    let head_commit = repo.find_commit(head_id)?;
+    commit_picked(repo, &head_commit, &cherry_commit, tree_id, sign)
+}
+
+fn commit_picked(
+    repo: &git2::Repository,
+    head_commit: &git2::Commit<'_>,
+    cherry_commit: &git2::Commit<'_>,
+    tree_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let head_id = head_commit.id();
+    let cherry_id = cherry_commit.id();
+    if tree_id == head_commit.tree_id() {
+        log::trace!("Skipping {}, already applied to {}", cherry_id, head_id);
+        return Ok(head_id);
+    }
+
+    let committer = git2_ext::ops::commit_signature(repo)?;
+    let committer = match (committer.name(), committer.email()) {
+        // For simple rebases, preserve the original commit time
+        (Some(name), Some(email)) => git2::Signature::new(name, email, &cherry_commit.time())?,
+        _ => committer,
+    };
+    let author = cherry_commit.author();
+    commit_raw(
+        repo,
+        &author,
+        &committer,
+        cherry_commit.message_encoding(),
+        cherry_commit.message_raw_bytes(),
+        tree_id,
+        &[head_id],
+        sign,
+    )
+}
+
+/// Commit `tree_id` on `head_id` as the user's resolution of cherry-picking `cherry_id`
+///
+/// The message and author come from `cherry_id`, like `git cherry-pick --continue`.
+pub fn cherry_pick_resolved(
+    repo: &git2::Repository,
+    head_id: git2::Oid,
+    cherry_id: git2::Oid,
+    tree_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let head_commit = repo.find_commit(head_id)?;
+    if tree_id == head_commit.tree_id() {
+        log::trace!("Skipping {}, resolved to {}", cherry_id, head_id);
+        return Ok(head_id);
+    }
+
+    let cherry_commit = repo.find_commit(cherry_id)?;
+    let committer = git2_ext::ops::commit_signature(repo)?;
+    let author = cherry_commit.author();
+    commit_raw(
+        repo,
+        &author,
+        &committer,
+        cherry_commit.message_encoding(),
+        cherry_commit.message_raw_bytes(),
+        tree_id,
+        &[head_id],
+        sign,
+    )
+}
+
+/// Replace the tree of `id`, like `git commit --amend --no-edit`
+///
+/// The message bytes and `encoding` header are kept as they are.
+pub fn amend_tree(
+    repo: &git2::Repository,
+    id: git2::Oid,
+    tree_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let commit = repo.find_commit(id)?;
+    if tree_id == commit.tree_id() {
+        return Ok(id);
+    }
+
+    let committer = git2_ext::ops::commit_signature(repo)?;
+    let author = commit.author();
+    let parents: Vec<_> = commit.parent_ids().collect();
+    commit_raw(
+        repo,
+        &author,
+        &committer,
+        commit.message_encoding(),
+        commit.message_raw_bytes(),
+        tree_id,
+        &parents,
+        sign,
+    )
+}
+
+/// Apply the changes of `cherry_id` to `tree_id` in memory, `None` if they conflict
+///
+/// Nothing is committed, so this can check whether a rebase will go through before running it.
+/// Merge commits are compared against their first parent, like `git cherry-pick -m 1`.
+pub fn apply_to_tree(
+    repo: &git2::Repository,
+    tree_id: git2::Oid,
+    cherry_id: git2::Oid,
+) -> Result<Option<git2::Oid>, git2::Error> {
+    let cherry_commit = repo.find_commit(cherry_id)?;
+    let base_tree = if 0 < cherry_commit.parent_count() {
+        cherry_commit.parent(0)?.tree()?
+    } else {
+        let empty_id = repo.treebuilder(None)?.write()?;
+        repo.find_tree(empty_id)?
+    };
+    let mut index = repo.merge_trees(
+        &base_tree,
+        &repo.find_tree(tree_id)?,
+        &cherry_commit.tree()?,
+        None,
+    )?;
+    if index.has_conflicts() {
+        return Ok(None);
+    }
+    index.write_tree_to(repo).map(Some)
+}
+
+/// The patch-id of the changes `id` makes to its parent, like `git patch-id`
+///
+/// This stays the same when a commit is rebased or reworded, so the rewritten commit can be matched
+/// with its original.  Merge commits and commits that change nothing have none.
+pub fn patch_id(repo: &git2::Repository, id: git2::Oid) -> Result<Option<git2::Oid>, git2::Error> {
+    let commit = repo.find_commit(id)?;
+    if 1 < commit.parent_count() {
+        return Ok(None);
+    }
+    let parent_tree = if commit.parent_count() == 0 {
+        None
+    } else {
+        Some(commit.parent(0)?.tree()?)
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+    diff.patchid(None).map(Some)
+}
+
+/// A line of a file and the commit that last changed it, see [`blame`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameLine {
+    /// The commit that introduced the line, as of the blamed commit
+    pub id: git2::Oid,
+    /// The line's content, without its line ending
+    pub content: Vec<u8>,
+}
+
+/// Attribute each line of `path`, as of `head_id`, to the commit that last changed it
+///
+/// `path` is relative to the root of the repo.
+pub fn blame(
+    repo: &git2::Repository,
+    path: &std::path::Path,
+    head_id: git2::Oid,
+) -> Result<Vec<BlameLine>, git2::Error> {
+    let tree = repo.find_commit(head_id)?.tree()?;
+    let blob = tree.get_path(path)?.to_object(repo)?.peel_to_blob()?;
+    let mut opts = git2::BlameOptions::new();
+    opts.newest_commit(head_id);
+    let blame = repo.blame_file(path, Some(&mut opts))?;
+    blob.content()
+        .lines()
+        .enumerate()
+        .map(|(index, content)| {
+            let hunk = blame.get_line(index + 1).ok_or_else(|| {
+                git2::Error::from_str(&format!("no blame for line {}", index + 1))
+            })?;
+            Ok(BlameLine {
+                id: hunk.final_commit_id(),
+                content: content.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Re-create the merge commit `merge_id` on `head_id`, merging in `parent_ids`
+///
+/// Like `git rebase --rebase-merges`, the merge is redone rather than replayed, so conflicts the
+/// original merge resolved will fail.
+pub fn merge(
+    repo: &git2::Repository,
+    head_id: git2::Oid,
+    merge_id: git2::Oid,
+    parent_ids: &[git2::Oid],
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let merge_commit = repo.find_commit(merge_id)?;
+    let original_ids: Vec<_> = merge_commit.parent_ids().collect();
+    let new_ids: Vec<_> = std::iter::once(head_id)
+        .chain(parent_ids.iter().copied())
+        .collect();
+    if original_ids == new_ids {
+        log::trace!("Keeping {}, parents are unchanged", merge_id);
+        return Ok(merge_id);
+    }
+
+    let mut tree_id = repo.find_commit(head_id)?.tree_id();
+    for (i, parent_id) in parent_ids.iter().enumerate() {
+        let mut merged_ids = vec![*parent_id];
+        merged_ids.extend_from_slice(&new_ids[..=i]);
+        let base_id = repo.merge_base_many(&merged_ids)?;
+        let mut index = repo.merge_trees(
+            &repo.find_commit(base_id)?.tree()?,
+            &repo.find_tree(tree_id)?,
+            &repo.find_commit(*parent_id)?.tree()?,
+            None,
+        )?;
+        if index.has_conflicts() {
+            return Err(git2::Error::new(
+                git2::ErrorCode::Unmerged,
+                git2::ErrorClass::Index,
+                format!("merge {merge_id} conflicts with {parent_id}"),
+            ));
+        }
+        tree_id = index.write_tree_to(repo)?;
+    }
+
+    let committer = git2_ext::ops::commit_signature(repo)?;
+    let committer = match (committer.name(), committer.email()) {
+        // For simple rebases, preserve the original commit time
+        (Some(name), Some(email)) => git2::Signature::new(name, email, &merge_commit.time())?,
+        _ => committer,
+    };
+    let author = merge_commit.author();
+    commit_raw(
+        repo,
+        &author,
+        &committer,
+        merge_commit.message_encoding(),
+        merge_commit.message_raw_bytes(),
+        tree_id,
+        &new_ids,
+        sign,
+    )
+}
+
+/// Squash `head_id` into `into_id`, preserving the message bytes and `encoding` header
+///
+/// See [`git2_ext::ops::squash`]
+pub fn squash(
+    repo: &git2::Repository,
+    head_id: git2::Oid,
+    into_id: git2::Oid,
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let into_commit = repo.find_commit(into_id)?;
+    if is_plain_message(&into_commit) {
+        return git2_ext::ops::squash(repo, head_id, into_id, sign);
+    }
+
+    let head_commit = repo.find_commit(head_id)?;
+    let base_tree = if 0 < head_commit.parent_count() {
+        head_commit.parent(0)?.tree()?
+    } else {
+        head_commit.tree()?
+    };
+    let mut index =
+        repo.merge_trees(&base_tree, &into_commit.tree()?, &head_commit.tree()?, None)?;
+    if index.has_conflicts() {
+        return Err(git2::Error::new(
+            git2::ErrorCode::Unmerged,
+            git2::ErrorClass::Index,
+            format!("squash conflicts with {into_id}"),
+        ));
+    }
+    let tree_id = index.write_tree_to(repo)?;
+    let parents: Vec<_> = into_commit.parent_ids().take(1).collect();
+    let author = into_commit.author();
+    let committer = into_commit.committer();
+    commit_raw(
+        repo,
+        &author,
+        &committer,
+        into_commit.message_encoding(),
+        into_commit.message_raw_bytes(),
+        tree_id,
+        &parents,
+        sign,
+    )
+}
+
+/// Decode a commit message for display according to its `encoding` header
+pub fn decode_message<'m>(message: &'m [u8], encoding: Option<&str>) -> std::borrow::Cow<'m, str> {
+    let encoding = encoding
+        .and_then(|e| encoding_rs::Encoding::for_label(e.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode_without_bom_handling(message).0
+}
+
+/// Whether `git2_ext` can round-trip this commit's message
+fn is_plain_message(commit: &git2::Commit<'_>) -> bool {
+    let encoding = commit.message_encoding();
+    let is_utf8 = encoding
+        .map(|e| e.eq_ignore_ascii_case("utf-8") || e.eq_ignore_ascii_case("utf8"))
+        .unwrap_or(true);
+    is_utf8 && commit.message_raw().is_some()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn commit_raw(
+    repo: &git2::Repository,
+    author: &git2::Signature<'_>,
+    committer: &git2::Signature<'_>,
+    encoding: Option<&str>,
+    message: &[u8],
+    tree_id: git2::Oid,
+    parent_ids: &[git2::Oid],
+    sign: Option<&dyn git2_ext::ops::Sign>,
+) -> Result<git2::Oid, git2::Error> {
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(format!("tree {tree_id}\n").as_bytes());
+    for parent_id in parent_ids {
+        buffer.extend_from_slice(format!("parent {parent_id}\n").as_bytes());
+    }
+    buffer.extend_from_slice(b"author ");
+    write_signature(&mut buffer, author);
+    buffer.extend_from_slice(b"committer ");
+    write_signature(&mut buffer, committer);
+    if let Some(encoding) = encoding {
+        buffer.extend_from_slice(format!("encoding {encoding}\n").as_bytes());
+    }
+    buffer.push(b'\n');
+    buffer.extend_from_slice(message);
+
+    if let Some(sign) = sign {
+        // Signing only works on UTF-8, so rather than abort the whole rewrite, leave this one
+        // commit unsigned
+        if let Ok(content) = buffer.to_str() {
+            let signed = sign.sign(content)?;
+            return repo.commit_signed(content, &signed, None);
+        }
+        log::warn!(
+            "Not signing `{}`, its message is not UTF-8",
+            String::from_utf8_lossy(message.lines().next().unwrap_or_default())
+        );
+    }
+    repo.odb()?.write(git2::ObjectType::Commit, &buffer)
+}
+
+fn write_signature(buffer: &mut Vec<u8>, signature: &git2::Signature<'_>) {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    let (sign, offset) = if offset < 0 {
+        ('-', -offset)
+    } else {
+        ('+', offset)
+    };
+    buffer.extend_from_slice(signature.name_bytes());
+    buffer.extend_from_slice(b" <");
+    buffer.extend_from_slice(signature.email_bytes());
+    buffer.extend_from_slice(
+        format!(
+            "> {} {}{:02}{:02}\n",
+            when.seconds(),
+            sign,
+            offset / 60,
+            offset % 60
+        )
+        .as_bytes(),
+    );
+}
+
+/// Whether two branch names would map to the same ref file on a case-insensitive filesystem
+///
+/// Loose refs are stored as files, so `Feature-X` and `feature-x`, or `Team/a` and `team/b`,
+/// overwrite each other.
+pub fn is_case_conflict(name: &str, other: &str) -> bool {
+    name.split('/')
+        .zip(other.split('/'))
+        .find(|(c, o)| c != o)
+        .map(|(c, o)| c.to_lowercase() == o.to_lowercase())
+        .unwrap_or(false)
+}
+
+/// Reject creating `name` when an existing branch differs from it only by case
+pub fn check_branch_case<'b>(
+    name: &str,
+    existing: impl IntoIterator<Item = &'b str>,
+) -> Result<(), git2::Error> {
+    if let Some(other) = existing.into_iter().find(|o| is_case_conflict(name, o)) {
+        return Err(git2::Error::new(
+            git2::ErrorCode::Exists,
+            git2::ErrorClass::Reference,
+            format!(
+                "cannot create branch `{name}`, it differs from `{other}` only by case which corrupts refs on case-insensitive filesystems"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Tag names, by the commit they point to
+///
+/// Annotated tags are peeled to their commit; tags of trees or blobs are left out.
+pub fn tagged_commits(
+    repo: &git2::Repository,
+) -> Result<std::collections::HashMap<git2::Oid, Vec<String>>, git2::Error> {
+    let mut tags = std::collections::HashMap::<git2::Oid, Vec<String>>::new();
+    for reference in repo.references_glob("refs/tags/*")? {
+        let reference = reference?;
+        let Some(name) = reference.shorthand() else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        tags.entry(commit.id()).or_default().push(name.to_owned());
+    }
+    Ok(tags)
+}
+
+/// The upstream local `branch` tracks, as `<remote>/<branch>`, when it no longer exists
+///
+/// This is what `git status` reports as gone, usually after the remote branch was deleted and
+/// pruned.
+pub fn gone_upstream(repo: &git2::Repository, branch: &str) -> Result<Option<String>, git2::Error> {
+    match repo
+        .find_branch(branch, git2::BranchType::Local)?
+        .upstream()
+    {
+        Ok(_) => Ok(None),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            let config = repo.config()?;
+            let Ok(merge) = config.get_string(&format!("branch.{branch}.merge")) else {
+                return Ok(None);
+            };
+            let remote = config
+                .get_string(&format!("branch.{branch}.remote"))
+                .unwrap_or_else(|_| ".".to_owned());
+            let merge = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+            Ok(Some(format!("{remote}/{merge}")))
+        }
+        Err(err) => Err(err),
+    }
+}
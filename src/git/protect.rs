@@ -29,6 +29,107 @@ impl ProtectedBranches {
     }
 }
 
+/// Which remote each protected branch is pulled from, when it isn't the `pull-remote`
+///
+/// Each entry is `<pattern>=<remote>`, with `.gitignore`-style patterns like
+/// [`ProtectedBranches`].  The first matching entry wins.
+#[derive(Clone, Debug, Default)]
+pub struct ProtectedRemotes {
+    remotes: Vec<(ProtectedBranches, String)>,
+}
+
+impl ProtectedRemotes {
+    pub fn new<'p>(entries: impl IntoIterator<Item = &'p str>) -> eyre::Result<Self> {
+        let mut remotes = Vec::new();
+        for entry in entries {
+            let (pattern, remote) = entry
+                .rsplit_once('=')
+                .filter(|(p, r)| !p.is_empty() && !r.is_empty())
+                .ok_or_else(|| eyre::eyre!("expected `<pattern>=<remote>`, got `{entry}`"))?;
+            remotes.push((ProtectedBranches::new([pattern])?, remote.to_owned()));
+        }
+        Ok(Self { remotes })
+    }
+
+    /// The remote to pull `name` from, if overridden
+    pub fn remote(&self, name: &str) -> Option<&str> {
+        self.remotes
+            .iter()
+            .find(|(pattern, _)| pattern.is_protected(name))
+            .map(|(_, remote)| remote.as_str())
+    }
+}
+
+/// Same-named protected branches on two remotes that both have commits the other doesn't
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Branch name, without the remote
+    pub name: String,
+    /// The remote the branch is pulled from
+    pub remote: String,
+    /// The remote that disagrees
+    pub other_remote: String,
+    /// Commits only on `remote`
+    pub ahead: usize,
+    /// Commits only on `other_remote`
+    pub behind: usize,
+}
+
+/// Compare each protected remote branch against its copies on other remotes
+///
+/// Copies that are only behind or ahead are normal, from fetching at different times, so just
+/// the ones that have diverged are reported.
+pub fn find_diverged_remotes(
+    repo: &git2::Repository,
+    protected: impl Fn(&str) -> bool,
+    pull_remote: impl Fn(&str) -> String,
+) -> Result<Vec<Divergence>, git2::Error> {
+    let mut by_name = std::collections::BTreeMap::<String, Vec<(String, git2::Oid)>>::new();
+    for branch in repo.branches(Some(git2::BranchType::Remote))? {
+        let (branch, _) = branch?;
+        if branch.get().symbolic_target().is_some() {
+            // `origin/HEAD`
+            continue;
+        }
+        let (Some(shorthand), Some(id)) = (branch.get().shorthand(), branch.get().target()) else {
+            continue;
+        };
+        let Some((remote, name)) = shorthand.split_once('/') else {
+            continue;
+        };
+        if protected(name) {
+            by_name
+                .entry(name.to_owned())
+                .or_default()
+                .push((remote.to_owned(), id));
+        }
+    }
+
+    let mut diverged = Vec::new();
+    for (name, remotes) in by_name {
+        let remote = pull_remote(&name);
+        let Some((_, id)) = remotes.iter().find(|(r, _)| *r == remote) else {
+            continue;
+        };
+        for (other_remote, other_id) in &remotes {
+            if *other_remote == remote || other_id == id {
+                continue;
+            }
+            let (ahead, behind) = repo.graph_ahead_behind(*id, *other_id)?;
+            if ahead != 0 && behind != 0 {
+                diverged.push(Divergence {
+                    name: name.clone(),
+                    remote: remote.clone(),
+                    other_remote: other_remote.clone(),
+                    ahead,
+                    behind,
+                });
+            }
+        }
+    }
+    Ok(diverged)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -54,6 +155,20 @@ mod test {
         assert!(!protect.is_protected("feature"));
     }
 
+    #[test]
+    fn protected_remote_patterns() {
+        let remotes = ProtectedRemotes::new(vec!["release/*=upstream", "main=origin"]).unwrap();
+        assert_eq!(remotes.remote("release/v1.0.0"), Some("upstream"));
+        assert_eq!(remotes.remote("main"), Some("origin"));
+        assert_eq!(remotes.remote("dev"), None);
+    }
+
+    #[test]
+    fn protected_remote_invalid() {
+        assert!(ProtectedRemotes::new(vec!["main"]).is_err());
+        assert!(ProtectedRemotes::new(vec!["main="]).is_err());
+    }
+
     #[test]
     fn folders() {
         let protect = ProtectedBranches::new(vec!["release/"]).unwrap();
@@ -0,0 +1,279 @@
+/// How `git push` picks the branch to update on the remote, from `push.default`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PushDefault {
+    /// Only push explicit refspecs
+    Nothing,
+    /// Push to the branch of the same name
+    Current,
+    /// Push to the branch's upstream
+    Upstream,
+    /// Push to the branch of the same name
+    #[default]
+    Simple,
+    /// Push to the branch of the same name, if the remote already has it
+    Matching,
+}
+
+impl PushDefault {
+    pub fn from_config(config: &git2::Config) -> Self {
+        match config.get_string("push.default").ok().as_deref() {
+            Some("nothing") => Self::Nothing,
+            Some("current") => Self::Current,
+            Some("upstream") | Some("tracking") => Self::Upstream,
+            Some("matching") => Self::Matching,
+            _ => Self::Simple,
+        }
+    }
+}
+
+/// The branch on `remote` that pushing the local `branch` updates, like `git push <remote>`
+///
+/// Matching `remote.<remote>.push` refspecs take precedence over `push.default`.  As
+/// `git stack --push` always names the branch, `push.default=nothing` pushes to the branch of the
+/// same name.  Returns `None` when the branch wouldn't be pushed.
+pub fn push_target(repo: &git2::Repository, remote: &str, branch: &str) -> Option<String> {
+    let local_ref = format!("refs/heads/{branch}");
+    if let Ok(raw_remote) = repo.find_remote(remote) {
+        let mut push_refspecs = raw_remote
+            .refspecs()
+            .filter(|r| r.direction() == git2::Direction::Push)
+            .peekable();
+        if push_refspecs.peek().is_some() {
+            return push_refspecs
+                .filter(|r| r.src_matches(&local_ref))
+                .find_map(|r| {
+                    let dst = r.transform(&local_ref).ok()?;
+                    let dst = dst.as_str()?.strip_prefix("refs/heads/")?;
+                    Some(dst.to_owned())
+                });
+        }
+    }
+
+    let config = repo.config().ok()?;
+    match PushDefault::from_config(&config) {
+        PushDefault::Nothing | PushDefault::Current | PushDefault::Simple => {
+            Some(branch.to_owned())
+        }
+        PushDefault::Upstream => {
+            let upstream = config
+                .get_string(&format!("branch.{branch}.remote"))
+                .ok()
+                .filter(|r| r == remote)
+                .and_then(|_| config.get_string(&format!("branch.{branch}.merge")).ok())
+                .and_then(|merge| merge.strip_prefix("refs/heads/").map(ToOwned::to_owned));
+            // Not yet pushed, so `--set-upstream` makes the branch of the same name its upstream
+            Some(upstream.unwrap_or_else(|| branch.to_owned()))
+        }
+        PushDefault::Matching => {
+            let tracking = tracking_branch(repo, remote, branch)?;
+            repo.find_branch(&tracking, git2::BranchType::Remote)
+                .is_ok()
+                .then(|| branch.to_owned())
+        }
+    }
+}
+
+/// The remote-tracking branch, like `origin/main`, that fetching `branch` from `remote` updates
+///
+/// This follows `remote.<remote>.fetch`, falling back to `<remote>/<branch>` without any.
+pub fn tracking_branch(repo: &git2::Repository, remote: &str, branch: &str) -> Option<String> {
+    let Ok(raw_remote) = repo.find_remote(remote) else {
+        return Some(format!("{remote}/{branch}"));
+    };
+    let remote_ref = format!("refs/heads/{branch}");
+    let mut fetch_refspecs = raw_remote
+        .refspecs()
+        .filter(|r| r.direction() == git2::Direction::Fetch)
+        .peekable();
+    if fetch_refspecs.peek().is_none() {
+        return Some(format!("{remote}/{branch}"));
+    }
+    fetch_refspecs
+        .filter(|r| r.src_matches(&remote_ref))
+        .find_map(|r| {
+            let dst = r.transform(&remote_ref).ok()?;
+            let dst = dst.as_str()?.strip_prefix("refs/remotes/")?;
+            Some(dst.to_owned())
+        })
+}
+
+/// A branch to update on a remote with [`push`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushRef {
+    /// The local branch to push
+    pub branch: String,
+    /// The ref to update on the remote, like `refs/heads/feature` or `refs/for/main`
+    pub dst: String,
+}
+
+/// What [`push`] did with each branch
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PushReport {
+    /// Branches the remote was updated with
+    pub pushed: Vec<String>,
+    /// Branches the remote already had
+    pub up_to_date: Vec<String>,
+    pub rejected: Vec<PushRejection>,
+}
+
+/// A branch [`push`] didn't update
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushRejection {
+    pub branch: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    /// Nothing was pushed, like when libgit2 can't authenticate or doesn't support the protocol
+    Connect(git2::Error),
+    /// Some refs may have been pushed
+    Push(git2::Error),
+}
+
+impl std::fmt::Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(err) => write!(f, "could not connect: {}", err.message()),
+            Self::Push(err) => err.message().fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PushError {}
+
+/// Push `refs` to `remote` in one go with libgit2
+///
+/// Credentials come from the SSH agent, `credential.helper`, and then the platform's defaults.
+/// Refs the remote rejects are reported rather than failing the push.  Unlike `git push`, hooks
+/// aren't run, see [`run_pre_push`].
+///
+/// Nothing is force-pushed: libgit2 can't make the remote check a lease as part of the push, so
+/// overwriting a branch is left to `git push --force-with-lease`.
+pub fn push(
+    repo: &git2::Repository,
+    remote: &str,
+    refs: &[PushRef],
+) -> Result<PushReport, PushError> {
+    let config = repo.config().map_err(PushError::Connect)?;
+    let mut raw_remote = repo
+        .find_remote(remote)
+        .or_else(|_| repo.remote_anonymous(remote))
+        .map_err(PushError::Connect)?;
+
+    let mut report = PushReport::default();
+    if refs.is_empty() {
+        return Ok(report);
+    }
+    let refspecs: Vec<_> = refs
+        .iter()
+        .map(|push_ref| format!("refs/heads/{}:{}", push_ref.branch, push_ref.dst))
+        .collect();
+
+    let mut connection = raw_remote
+        .connect_auth(git2::Direction::Push, Some(callbacks(&config)), None)
+        .map_err(PushError::Connect)?;
+    let mut statuses = Vec::new();
+    let mut callbacks = callbacks(&config);
+    callbacks.push_update_reference(|dst, status| {
+        if let Some(status) = status {
+            statuses.push((dst.to_owned(), status.to_owned()));
+        }
+        Ok(())
+    });
+    let mut options = git2::PushOptions::new();
+    options.remote_callbacks(callbacks);
+    log::trace!("libgit2 push {} {}", remote, refspecs.join(" "));
+    connection
+        .remote()
+        .push(&refspecs, Some(&mut options))
+        .map_err(PushError::Push)?;
+    drop(connection);
+    drop(options);
+    for push_ref in refs {
+        match statuses.iter().find(|(dst, _)| *dst == push_ref.dst) {
+            Some((_, reason)) => report.rejected.push(PushRejection {
+                branch: push_ref.branch.clone(),
+                reason: reason.clone(),
+            }),
+            None => report.pushed.push(push_ref.branch.clone()),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Run the `pre-push` hook for pushing `push_ref` to `remote`, like `git push` does
+///
+/// The hook is passed the remote's name and URL, and fed
+/// `<local ref> <local oid> <remote ref> <remote oid>` with the remote oid from the remote-tracking
+/// branch, or zeros when the remote isn't known to have the branch.  Returns whether the push may
+/// go ahead, which it may when there is no hook or the remote already has the branch.
+pub fn run_pre_push(
+    repo: &git2::Repository,
+    hooks: &git2_ext::hooks::Hooks,
+    remote: &str,
+    push_ref: &PushRef,
+) -> Result<bool, std::io::Error> {
+    let local_ref = format!("refs/heads/{}", push_ref.branch);
+    let local_id = repo
+        .refname_to_id(&local_ref)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::NotFound, err.message()))?;
+    let remote_id = push_ref
+        .dst
+        .strip_prefix("refs/heads/")
+        .and_then(|dst| tracking_branch(repo, remote, dst))
+        .and_then(|tracking| {
+            repo.find_branch(&tracking, git2::BranchType::Remote)
+                .ok()?
+                .get()
+                .target()
+        })
+        .unwrap_or_else(git2::Oid::zero);
+    if local_id == remote_id {
+        return Ok(true);
+    }
+    let url = repo
+        .find_remote(remote)
+        .ok()
+        .and_then(|r| r.pushurl().or_else(|| r.url()).map(ToOwned::to_owned))
+        .unwrap_or_else(|| remote.to_owned());
+    let stdin = format!("{local_ref} {local_id} {} {remote_id}\n", push_ref.dst);
+    log::trace!("Running pre-push hook for `{}`", push_ref.branch);
+    let code = hooks.run_hook(
+        repo,
+        "pre-push",
+        &[remote, &url],
+        Some(stdin.as_bytes()),
+        &[],
+    )?;
+    Ok(code == 0)
+}
+
+fn callbacks(config: &git2::Config) -> git2::RemoteCallbacks<'_> {
+    let mut tried = git2::CredentialType::empty();
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        let untried = allowed - tried;
+        if untried.contains(git2::CredentialType::SSH_KEY) {
+            tried |= git2::CredentialType::SSH_KEY;
+            git2::Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if untried.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            tried |= git2::CredentialType::USER_PASS_PLAINTEXT;
+            git2::Cred::credential_helper(config, url, username)
+        } else if untried.contains(git2::CredentialType::DEFAULT) {
+            tried |= git2::CredentialType::DEFAULT;
+            git2::Cred::default()
+        } else if untried.contains(git2::CredentialType::USERNAME) {
+            tried |= git2::CredentialType::USERNAME;
+            git2::Cred::username(username.unwrap_or("git"))
+        } else {
+            Err(git2::Error::new(
+                git2::ErrorCode::Auth,
+                git2::ErrorClass::Callback,
+                format!("no credentials for `{url}`"),
+            ))
+        }
+    });
+    callbacks
+}
@@ -9,11 +9,14 @@ pub trait Repo {
     fn user(&self) -> Option<std::rc::Rc<str>>;
     fn push_remote(&self) -> &str;
     fn pull_remote(&self) -> &str;
+    fn pull_remote_for(&self, name: &str) -> &str;
 
     fn is_dirty(&self) -> bool;
     fn merge_base(&self, one: git2::Oid, two: git2::Oid) -> Option<git2::Oid>;
 
     fn find_commit(&self, id: git2::Oid) -> Option<std::rc::Rc<Commit>>;
+    /// See [`GitRepo::signed_by_committer`]
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>>;
     fn head_commit(&self) -> std::rc::Rc<Commit>;
     fn head_branch(&self) -> Option<Branch>;
     fn resolve(&self, revspec: &str) -> Option<std::rc::Rc<Commit>>;
@@ -24,8 +27,26 @@ pub trait Repo {
         base_bound: std::ops::Bound<&git2::Oid>,
         head_bound: std::ops::Bound<&git2::Oid>,
     ) -> Result<Vec<git2::Oid>>;
+    /// Every commit reachable from `head_ids` but not `base_id`, with its parents
+    ///
+    /// Unlike a [`Repo::commit_range`] per head, shared history is only walked once.
+    fn parents_between(
+        &self,
+        head_ids: &[git2::Oid],
+        base_id: git2::Oid,
+    ) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>>;
     fn contains_commit(&self, haystack_id: git2::Oid, needle_id: git2::Oid) -> Result<bool>;
+    /// See [`crate::git::apply_to_tree`]
+    fn apply_to_tree(&self, tree_id: git2::Oid, cherry_id: git2::Oid) -> Result<Option<git2::Oid>>;
+    /// See [`crate::git::patch_id`]
+    fn patch_id(&self, id: git2::Oid) -> Option<git2::Oid>;
     fn cherry_pick(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<git2::Oid>;
+    fn merge(
+        &mut self,
+        head_id: git2::Oid,
+        merge_id: git2::Oid,
+        parent_ids: &[git2::Oid],
+    ) -> Result<git2::Oid>;
     fn reword(&mut self, head_oid: git2::Oid, msg: &str) -> Result<git2::Oid>;
     fn squash(&mut self, head_id: git2::Oid, into_id: git2::Oid) -> Result<git2::Oid>;
 
@@ -36,6 +57,10 @@ pub trait Repo {
     fn delete_branch(&mut self, name: &str) -> Result<()>;
     fn find_local_branch(&self, name: &str) -> Option<Branch>;
     fn find_remote_branch(&self, remote: &str, name: &str) -> Option<Branch>;
+    /// The branch on the push remote that pushing the local branch `name` updates
+    ///
+    /// See [`crate::git::push_target`].
+    fn find_push_branch(&self, name: &str) -> Option<Branch>;
     fn local_branches(&self) -> Box<dyn Iterator<Item = Branch> + '_>;
     fn remote_branches(&self) -> Box<dyn Iterator<Item = Branch> + '_>;
     fn detach(&mut self) -> Result<()>;
@@ -71,12 +96,18 @@ pub struct Commit {
     pub id: git2::Oid,
     pub tree_id: git2::Oid,
     pub summary: bstr::BString,
+    pub encoding: Option<std::rc::Rc<str>>,
     pub time: std::time::SystemTime,
     pub author: Option<std::rc::Rc<str>>,
     pub committer: Option<std::rc::Rc<str>>,
 }
 
 impl Commit {
+    /// The summary, decoded according to the commit's `encoding`
+    pub fn display_summary(&self) -> std::borrow::Cow<'_, str> {
+        crate::git::decode_message(&self.summary, self.encoding.as_deref())
+    }
+
     pub fn fixup_summary(&self) -> Option<&bstr::BStr> {
         self.summary
             .strip_prefix(b"fixup! ")
@@ -114,12 +145,21 @@ impl Commit {
 pub struct GitRepo {
     repo: git2::Repository,
     sign: Option<git2_ext::ops::UserSign>,
+    sign_policy: crate::config::Sign,
     push_remote: Option<String>,
+    branch_push_remotes: std::collections::HashMap<String, String>,
     pull_remote: Option<String>,
+    protected_remotes: crate::git::ProtectedRemotes,
     commits: std::cell::RefCell<std::collections::HashMap<git2::Oid, std::rc::Rc<Commit>>>,
     interned_strings: std::cell::RefCell<std::collections::HashSet<std::rc::Rc<str>>>,
     bases: std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<git2::Oid>>>,
     counts: std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<usize>>>,
+    signers: std::cell::RefCell<std::collections::HashMap<git2::Oid, Option<std::rc::Rc<str>>>>,
+    /// Results of [`GitRepo::apply_to_tree`], so cherry-picks after a check don't merge again
+    applied:
+        std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<git2::Oid>>>,
+    /// Where checks write their objects, see [`GitRepo::keep_checks_in_memory`]
+    check_repo: Option<git2::Repository>,
 }
 
 impl GitRepo {
@@ -127,30 +167,64 @@ impl GitRepo {
         Self {
             repo,
             sign: None,
+            sign_policy: crate::config::Sign::Never,
             push_remote: None,
+            branch_push_remotes: Default::default(),
             pull_remote: None,
+            protected_remotes: Default::default(),
             commits: Default::default(),
             interned_strings: Default::default(),
             bases: Default::default(),
             counts: Default::default(),
+            signers: Default::default(),
+            applied: Default::default(),
+            check_repo: None,
         }
     }
 
     pub fn set_sign(&mut self, yes: bool) -> Result<(), git2::Error> {
-        if yes {
-            let config = self.repo.config()?;
-            let sign = git2_ext::ops::UserSign::from_config(&self.repo, &config)?;
-            self.sign = Some(sign);
+        let policy = if yes {
+            crate::config::Sign::Always
         } else {
-            self.sign = None;
-        }
+            crate::config::Sign::Never
+        };
+        self.set_sign_policy(policy)
+    }
+
+    /// Choose which rewritten commits get signed
+    pub fn set_sign_policy(&mut self, policy: crate::config::Sign) -> Result<(), git2::Error> {
+        self.sign = match policy {
+            crate::config::Sign::Never => None,
+            crate::config::Sign::Always | crate::config::Sign::IfSigned => {
+                let config = self.repo.config()?;
+                Some(git2_ext::ops::UserSign::from_config(&self.repo, &config)?)
+            }
+        };
+        self.sign_policy = policy;
         Ok(())
     }
 
+    /// How to sign the commit rewritten from `original_id`, if at all
+    fn sign_for(&self, original_id: git2::Oid) -> Option<&dyn git2_ext::ops::Sign> {
+        let sign = self.sign.as_ref()?;
+        if self.sign_policy == crate::config::Sign::IfSigned
+            && self.repo.extract_signature(&original_id, None).is_err()
+        {
+            return None;
+        }
+        Some(sign)
+    }
+
     pub fn set_push_remote(&mut self, remote: &str) {
         self.push_remote = Some(remote.to_owned());
     }
 
+    /// Push `branch` to `remote`, rather than the [`GitRepo::push_remote`]
+    pub fn set_branch_push_remote(&mut self, branch: &str, remote: &str) {
+        self.branch_push_remotes
+            .insert(branch.to_owned(), remote.to_owned());
+    }
+
     pub fn set_pull_remote(&mut self, remote: &str) {
         self.pull_remote = Some(remote.to_owned());
     }
@@ -163,6 +237,34 @@ impl GitRepo {
         self.pull_remote.as_deref().unwrap_or("origin")
     }
 
+    pub fn set_protected_remotes(&mut self, remotes: crate::git::ProtectedRemotes) {
+        self.protected_remotes = remotes;
+    }
+
+    /// The remote to push the branch `name` to
+    pub fn push_remote_for(&self, name: &str) -> &str {
+        self.branch_push_remotes
+            .get(name)
+            .map(|r| r.as_str())
+            .unwrap_or_else(|| self.push_remote())
+    }
+
+    /// Push `refs` to `remote` in one go with libgit2, see [`crate::git::push`]
+    pub fn push_refs(
+        &self,
+        remote: &str,
+        refs: &[crate::git::PushRef],
+    ) -> Result<crate::git::PushReport, crate::git::PushError> {
+        crate::git::push(&self.repo, remote, refs)
+    }
+
+    /// The remote to pull the protected branch `name` from
+    pub fn pull_remote_for(&self, name: &str) -> &str {
+        self.protected_remotes
+            .remote(name)
+            .unwrap_or_else(|| self.pull_remote())
+    }
+
     pub fn raw(&self) -> &git2::Repository {
         &self.repo
     }
@@ -216,7 +318,8 @@ impl GitRepo {
             Some(std::rc::Rc::clone(commit))
         } else {
             let commit = self.repo.find_commit(id).ok()?;
-            let summary: bstr::BString = commit.summary_bytes().unwrap().into();
+            let summary: bstr::BString = commit.summary_bytes().unwrap_or_default().into();
+            let encoding = commit.message_encoding().map(|e| self.intern_string(e));
             let time = std::time::SystemTime::UNIX_EPOCH
                 + std::time::Duration::from_secs(commit.time().seconds().max(0) as u64);
 
@@ -226,6 +329,7 @@ impl GitRepo {
                 id: commit.id(),
                 tree_id: commit.tree_id(),
                 summary,
+                encoding,
                 time,
                 author,
                 committer,
@@ -235,6 +339,24 @@ impl GitRepo {
         }
     }
 
+    /// The committer of `id`, if the commit carries a signature
+    ///
+    /// The signature isn't verified, the committer is assumed to have made it.  Reading it means
+    /// parsing the commit again, so this is only looked up when signatures matter.
+    pub fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        if let Some(signer) = self.signers.borrow().get(&id) {
+            return signer.clone();
+        }
+        let signer = self
+            .repo
+            .extract_signature(&id, None)
+            .ok()
+            .and_then(|_| self.repo.find_commit(id).ok())
+            .and_then(|c| c.committer().name().map(|n| self.intern_string(n)));
+        self.signers.borrow_mut().insert(id, signer.clone());
+        signer
+    }
+
     pub fn head_commit(&self) -> std::rc::Rc<Commit> {
         let head_id = self
             .repo
@@ -268,6 +390,22 @@ impl GitRepo {
         })
     }
 
+    /// Local branches checked out in any worktree, including this one, with the worktree's path
+    pub fn worktree_branches(
+        &self,
+    ) -> Result<std::collections::BTreeMap<String, std::path::PathBuf>> {
+        Ok(crate::git::checked_out_branches(&self.repo)?
+            .into_iter()
+            .collect())
+    }
+
+    /// Local branches checked out in other worktrees, which can't be updated from this one
+    pub fn branches_checked_out_elsewhere(
+        &self,
+    ) -> Result<std::collections::BTreeMap<String, std::path::PathBuf>> {
+        crate::git::branches_checked_out_elsewhere(&self.repo)
+    }
+
     pub fn resolve(&self, revspec: &str) -> Option<std::rc::Rc<Commit>> {
         let id = self.repo.revparse_single(revspec).ok()?.id();
         self.find_commit(id)
@@ -348,6 +486,56 @@ impl GitRepo {
         Ok(result)
     }
 
+    pub fn parents_between(
+        &self,
+        head_ids: &[git2::Oid],
+        base_id: git2::Oid,
+    ) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        let mut revwalk = self.repo.revwalk()?;
+        for head_id in head_ids {
+            revwalk.push(*head_id)?;
+        }
+        revwalk.hide(base_id)?;
+        let ids = revwalk.collect::<Result<Vec<_>>>()?;
+        self.parents_of_each(&ids)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn parents_of_each(&self, ids: &[git2::Oid]) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        ids.iter()
+            .map(|id| Ok((*id, self.parent_ids(*id)?)))
+            .collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    fn parents_of_each(&self, ids: &[git2::Oid]) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        use rayon::prelude::*;
+
+        // Opening the repo per thread isn't worth it for a handful of commits
+        const MIN_CHUNK_SIZE: usize = 256;
+
+        let chunk_size = ids
+            .len()
+            .div_ceil(rayon::current_num_threads())
+            .max(MIN_CHUNK_SIZE);
+        let path = self.repo.path();
+        let chunks = ids
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                // `git2::Repository` can't be shared between threads
+                let repo = git2::Repository::open(path)?;
+                chunk
+                    .iter()
+                    .map(|id| {
+                        let commit = repo.find_commit(*id)?;
+                        Ok((*id, commit.parent_ids().collect()))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
     pub fn contains_commit(&self, haystack_id: git2::Oid, needle_id: git2::Oid) -> Result<bool> {
         let needle_commit = self.repo.find_commit(needle_id)?;
         let needle_ann_commit = self.repo.find_annotated_commit(needle_id)?;
@@ -406,31 +594,164 @@ impl GitRepo {
         }
     }
 
+    /// Write the objects [`GitRepo::apply_to_tree`] creates to memory rather than the repository
+    ///
+    /// For checks in a dry run, which must not leave anything behind.
+    pub fn keep_checks_in_memory(&mut self) -> Result<(), git2::Error> {
+        let check_repo = git2::Repository::open(self.repo.path())?;
+        check_repo.odb()?.add_new_mempack_backend(1000)?;
+        self.check_repo = Some(check_repo);
+        self.applied.borrow_mut().clear();
+        Ok(())
+    }
+
+    pub fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>> {
+        if let Some(applied) = self.applied.borrow().get(&(tree_id, cherry_id)) {
+            return Ok(*applied);
+        }
+        let repo = self.check_repo.as_ref().unwrap_or(&self.repo);
+        let applied = crate::git::apply_to_tree(repo, tree_id, cherry_id)?;
+        self.applied
+            .borrow_mut()
+            .insert((tree_id, cherry_id), applied);
+        Ok(applied)
+    }
+
+    /// The tree an earlier check found cherry-picking `cherry_id` onto `head_id` gives
+    fn checked_tree(&self, head_id: git2::Oid, cherry_id: git2::Oid) -> Option<git2::Oid> {
+        if self.check_repo.is_some() {
+            return None;
+        }
+        let cherry_commit = self.repo.find_commit(cherry_id).ok()?;
+        if cherry_commit.parent_count() != 1 {
+            return None;
+        }
+        let head_tree_id = self.repo.find_commit(head_id).ok()?.tree_id();
+        self.applied
+            .borrow()
+            .get(&(head_tree_id, cherry_id))
+            .copied()?
+    }
+
+    pub fn patch_id(&self, id: git2::Oid) -> Option<git2::Oid> {
+        crate::git::patch_id(&self.repo, id)
+            .map_err(|err| log::debug!("Could not diff {}: {}", id, err))
+            .ok()
+            .flatten()
+    }
+
+    /// See [`crate::git::blame`]
+    pub fn blame(
+        &self,
+        path: &std::path::Path,
+        head_id: git2::Oid,
+    ) -> Result<Vec<crate::git::BlameLine>> {
+        crate::git::blame(&self.repo, path, head_id)
+    }
+
     pub fn cherry_pick(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<git2::Oid> {
-        git2_ext::ops::cherry_pick(
+        let sign = self.sign_for(cherry_id);
+        match self.checked_tree(head_id, cherry_id) {
+            Some(tree_id) => {
+                crate::git::cherry_pick_tree(&self.repo, head_id, cherry_id, tree_id, sign)
+            }
+            None => crate::git::cherry_pick(&self.repo, head_id, cherry_id, sign),
+        }
+    }
+
+    pub fn merge(
+        &mut self,
+        head_id: git2::Oid,
+        merge_id: git2::Oid,
+        parent_ids: &[git2::Oid],
+    ) -> Result<git2::Oid> {
+        crate::git::merge(
             &self.repo,
             head_id,
-            cherry_id,
-            self.sign.as_ref().map(|s| s as &dyn git2_ext::ops::Sign),
+            merge_id,
+            parent_ids,
+            self.sign_for(merge_id),
         )
     }
 
     pub fn reword(&mut self, head_oid: git2::Oid, msg: &str) -> Result<git2::Oid> {
-        git2_ext::ops::reword(
-            &self.repo,
-            head_oid,
-            msg,
-            self.sign.as_ref().map(|s| s as &dyn git2_ext::ops::Sign),
-        )
+        git2_ext::ops::reword(&self.repo, head_oid, msg, self.sign_for(head_oid))
     }
 
     pub fn squash(&mut self, head_id: git2::Oid, into_id: git2::Oid) -> Result<git2::Oid> {
-        git2_ext::ops::squash(
+        crate::git::squash(&self.repo, head_id, into_id, self.sign_for(into_id))
+    }
+
+    /// Replace the tree of `id`, see [`crate::git::amend_tree`]
+    pub fn amend_tree(&mut self, id: git2::Oid, tree_id: git2::Oid) -> Result<git2::Oid> {
+        crate::git::amend_tree(&self.repo, id, tree_id, self.sign_for(id))
+    }
+
+    /// Cherry-pick `cherry_id` onto `head_id` in the working tree, leaving conflicts for the user
+    pub fn checkout_conflict(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<()> {
+        self.switch_commit(head_id)?;
+        let cherry_commit = self.repo.find_commit(cherry_id)?;
+        let mut options = git2::CherrypickOptions::new();
+        if 1 < cherry_commit.parent_count() {
+            options.mainline(1);
+        }
+        self.repo.cherrypick(&cherry_commit, Some(&mut options))
+    }
+
+    /// Commit the user's resolution of [`GitRepo::checkout_conflict`]
+    ///
+    /// If the user already committed it, e.g. with `git cherry-pick --continue`, that commit is
+    /// used instead.
+    pub fn commit_conflict(
+        &mut self,
+        head_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<git2::Oid> {
+        let current_id = self.head_commit().id;
+        if self.repo.state() != git2::RepositoryState::CherryPick {
+            if current_id != head_id {
+                return Ok(current_id);
+            }
+            return Err(Error::new(
+                git2::ErrorCode::NotFound,
+                git2::ErrorClass::CherryPick,
+                "no cherry-pick in progress",
+            ));
+        }
+
+        let mut index = self.repo.index()?;
+        if index.has_conflicts() {
+            return Err(Error::new(
+                git2::ErrorCode::Unmerged,
+                git2::ErrorClass::Index,
+                "conflicts are unresolved, `git add` the resolved files",
+            ));
+        }
+        let tree_id = index.write_tree()?;
+        let resolved_id = crate::git::cherry_pick_resolved(
             &self.repo,
-            head_id,
-            into_id,
-            self.sign.as_ref().map(|s| s as &dyn git2_ext::ops::Sign),
-        )
+            current_id,
+            cherry_id,
+            tree_id,
+            self.sign_for(cherry_id),
+        )?;
+        self.repo.cleanup_state()?;
+        self.repo.set_head_detached(resolved_id)?;
+        Ok(resolved_id)
+    }
+
+    /// Throw away an in-progress [`GitRepo::checkout_conflict`]
+    pub fn abort_conflict(&mut self) -> Result<()> {
+        self.repo.cleanup_state()?;
+        let head = self.repo.head()?.peel_to_commit()?;
+        let mut builder = git2::build::CheckoutBuilder::new();
+        builder.force();
+        self.repo
+            .reset(head.as_object(), git2::ResetType::Hard, Some(&mut builder))
     }
 
     pub fn stash_push(&mut self, message: Option<&str>) -> Result<git2::Oid> {
@@ -455,7 +776,16 @@ impl GitRepo {
                 "stash ID not found",
             )
         })?;
-        self.repo.stash_pop(index, None)
+        self.repo.stash_apply(index, None)?;
+        if self.repo.index()?.has_conflicts() {
+            // Keep the stash to recover from resolving the conflicts wrong
+            return Err(Error::new(
+                git2::ErrorCode::Conflict,
+                git2::ErrorClass::Stash,
+                "the stashed changes conflict",
+            ));
+        }
+        self.repo.stash_drop(index)
     }
 
     pub fn branch(&mut self, name: &str, id: git2::Oid) -> Result<()> {
@@ -484,6 +814,17 @@ impl GitRepo {
         self.load_remote_branch(&branch, remote, name).ok()
     }
 
+    pub fn find_push_branch(&self, name: &str) -> Option<Branch> {
+        let remote = self.push_remote_for(name);
+        let target = crate::git::push_target(&self.repo, remote, name)?;
+        let tracking = crate::git::tracking_branch(&self.repo, remote, &target)?;
+        let branch = self
+            .repo
+            .find_branch(&tracking, git2::BranchType::Remote)
+            .ok()?;
+        self.load_remote_branch(&branch, remote, &target).ok()
+    }
+
     pub fn local_branches(&self) -> impl Iterator<Item = Branch> + '_ {
         log::trace!("Loading local branches");
         self.repo
@@ -637,6 +978,9 @@ impl Repo for GitRepo {
     fn pull_remote(&self) -> &str {
         self.pull_remote()
     }
+    fn pull_remote_for(&self, name: &str) -> &str {
+        self.pull_remote_for(name)
+    }
 
     fn is_dirty(&self) -> bool {
         self.is_dirty()
@@ -650,6 +994,10 @@ impl Repo for GitRepo {
         self.find_commit(id)
     }
 
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        self.signed_by_committer(id)
+    }
+
     fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.head_commit()
     }
@@ -678,14 +1026,39 @@ impl Repo for GitRepo {
         self.commit_range(base_bound, head_bound)
     }
 
+    fn parents_between(
+        &self,
+        head_ids: &[git2::Oid],
+        base_id: git2::Oid,
+    ) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        self.parents_between(head_ids, base_id)
+    }
+
     fn contains_commit(&self, haystack_id: git2::Oid, needle_id: git2::Oid) -> Result<bool> {
         self.contains_commit(haystack_id, needle_id)
     }
 
+    fn apply_to_tree(&self, tree_id: git2::Oid, cherry_id: git2::Oid) -> Result<Option<git2::Oid>> {
+        self.apply_to_tree(tree_id, cherry_id)
+    }
+
+    fn patch_id(&self, id: git2::Oid) -> Option<git2::Oid> {
+        self.patch_id(id)
+    }
+
     fn cherry_pick(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<git2::Oid> {
         self.cherry_pick(head_id, cherry_id)
     }
 
+    fn merge(
+        &mut self,
+        head_id: git2::Oid,
+        merge_id: git2::Oid,
+        parent_ids: &[git2::Oid],
+    ) -> Result<git2::Oid> {
+        self.merge(head_id, merge_id, parent_ids)
+    }
+
     fn reword(&mut self, head_oid: git2::Oid, msg: &str) -> Result<git2::Oid> {
         self.reword(head_oid, msg)
     }
@@ -718,6 +1091,10 @@ impl Repo for GitRepo {
         self.find_remote_branch(remote, name)
     }
 
+    fn find_push_branch(&self, name: &str) -> Option<Branch> {
+        self.find_push_branch(name)
+    }
+
     fn local_branches(&self) -> Box<dyn Iterator<Item = Branch> + '_> {
         Box::new(self.local_branches())
     }
@@ -801,6 +1178,10 @@ impl InMemoryRepo {
         "origin"
     }
 
+    pub fn pull_remote_for(&self, _name: &str) -> &str {
+        self.pull_remote()
+    }
+
     fn user(&self) -> Option<std::rc::Rc<str>> {
         None
     }
@@ -821,6 +1202,11 @@ impl InMemoryRepo {
         self.commits.get(&id).map(|c| c.1.clone())
     }
 
+    /// In-memory commits are never signed
+    pub fn signed_by_committer(&self, _id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        None
+    }
+
     pub fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.commits.get(&self.head_id.unwrap()).cloned().unwrap().1
     }
@@ -897,6 +1283,28 @@ impl InMemoryRepo {
         Ok(result)
     }
 
+    pub fn parents_between(
+        &self,
+        head_ids: &[git2::Oid],
+        base_id: git2::Oid,
+    ) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        let mut seen = std::collections::HashSet::new();
+        let mut parents = Vec::new();
+        for head_id in head_ids {
+            for commit in self
+                .commits_from(*head_id)
+                .take_while(|commit| commit.id != base_id)
+            {
+                if !seen.insert(commit.id) {
+                    // The rest of the history was already walked from another head
+                    break;
+                }
+                parents.push((commit.id, self.parent_ids(commit.id)?));
+            }
+        }
+        Ok(parents)
+    }
+
     pub fn contains_commit(&self, haystack_id: git2::Oid, needle_id: git2::Oid) -> Result<bool> {
         // Because we don't have the information for likeness matches, just checking for Oid
         let mut next = Some(haystack_id);
@@ -909,6 +1317,27 @@ impl InMemoryRepo {
         Ok(false)
     }
 
+    /// Without file contents, there are no changes to compare
+    pub fn patch_id(&self, _id: git2::Oid) -> Option<git2::Oid> {
+        None
+    }
+
+    /// Without file contents, nothing conflicts
+    pub fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>> {
+        self.find_commit(cherry_id).ok_or_else(|| {
+            Error::new(
+                git2::ErrorCode::NotFound,
+                git2::ErrorClass::Reference,
+                format!("could not find commit {cherry_id:?}"),
+            )
+        })?;
+        Ok(Some(tree_id))
+    }
+
     pub fn cherry_pick(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<git2::Oid> {
         let cherry_commit = self.find_commit(cherry_id).ok_or_else(|| {
             Error::new(
@@ -925,6 +1354,16 @@ impl InMemoryRepo {
         Ok(new_id)
     }
 
+    /// Only the first parent is tracked, like the rest of the in-memory history
+    pub fn merge(
+        &mut self,
+        head_id: git2::Oid,
+        merge_id: git2::Oid,
+        _parent_ids: &[git2::Oid],
+    ) -> Result<git2::Oid> {
+        self.cherry_pick(head_id, merge_id)
+    }
+
     pub fn reword(&mut self, head_id: git2::Oid, msg: &str) -> Result<git2::Oid> {
         let (head_parent, head_commit) = self.commits.get(&head_id).cloned().ok_or_else(|| {
             git2::Error::new(
@@ -938,6 +1377,7 @@ impl InMemoryRepo {
         let new_id = self.gen_id();
         reworded_commit.id = new_id;
         reworded_commit.summary = msg.into();
+        reworded_commit.encoding = None;
         self.commits
             .insert(new_id, (head_parent, std::rc::Rc::new(reworded_commit)));
         Ok(new_id)
@@ -1013,6 +1453,10 @@ impl InMemoryRepo {
         None
     }
 
+    pub fn find_push_branch(&self, name: &str) -> Option<Branch> {
+        self.find_remote_branch(self.push_remote(), name)
+    }
+
     pub fn local_branches(&self) -> impl Iterator<Item = Branch> + '_ {
         self.branches.values().cloned()
     }
@@ -1081,6 +1525,9 @@ impl Repo for InMemoryRepo {
     fn pull_remote(&self) -> &str {
         self.pull_remote()
     }
+    fn pull_remote_for(&self, name: &str) -> &str {
+        self.pull_remote_for(name)
+    }
 
     fn is_dirty(&self) -> bool {
         self.is_dirty()
@@ -1094,6 +1541,10 @@ impl Repo for InMemoryRepo {
         self.find_commit(id)
     }
 
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        self.signed_by_committer(id)
+    }
+
     fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.head_commit()
     }
@@ -1118,14 +1569,39 @@ impl Repo for InMemoryRepo {
         self.commit_range(base_bound, head_bound)
     }
 
+    fn parents_between(
+        &self,
+        head_ids: &[git2::Oid],
+        base_id: git2::Oid,
+    ) -> Result<Vec<(git2::Oid, Vec<git2::Oid>)>> {
+        self.parents_between(head_ids, base_id)
+    }
+
     fn contains_commit(&self, haystack_id: git2::Oid, needle_id: git2::Oid) -> Result<bool> {
         self.contains_commit(haystack_id, needle_id)
     }
 
+    fn apply_to_tree(&self, tree_id: git2::Oid, cherry_id: git2::Oid) -> Result<Option<git2::Oid>> {
+        self.apply_to_tree(tree_id, cherry_id)
+    }
+
+    fn patch_id(&self, id: git2::Oid) -> Option<git2::Oid> {
+        self.patch_id(id)
+    }
+
     fn cherry_pick(&mut self, head_id: git2::Oid, cherry_id: git2::Oid) -> Result<git2::Oid> {
         self.cherry_pick(head_id, cherry_id)
     }
 
+    fn merge(
+        &mut self,
+        head_id: git2::Oid,
+        merge_id: git2::Oid,
+        parent_ids: &[git2::Oid],
+    ) -> Result<git2::Oid> {
+        self.merge(head_id, merge_id, parent_ids)
+    }
+
     fn reword(&mut self, head_oid: git2::Oid, msg: &str) -> Result<git2::Oid> {
         self.reword(head_oid, msg)
     }
@@ -1162,6 +1638,10 @@ impl Repo for InMemoryRepo {
         self.find_remote_branch(remote, name)
     }
 
+    fn find_push_branch(&self, name: &str) -> Option<Branch> {
+        self.find_push_branch(name)
+    }
+
     fn local_branches(&self) -> Box<dyn Iterator<Item = Branch> + '_> {
         Box::new(self.local_branches())
     }
@@ -0,0 +1,73 @@
+/// Branch config marking the branch as waiting on a restack, `branch.<name>.stack-needs-restack`
+pub const NEEDS_RESTACK_BRANCH_KEY: &str = "stack-needs-restack";
+
+/// Branches whose restacking was deferred, like by `git stack amend --no-rebase`
+///
+/// These carry a `fixup!` commit that the next `git stack --rebase` squashes, restacking the
+/// branches in one pass.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct PendingRestack {
+    names: std::collections::BTreeSet<String>,
+}
+
+impl PendingRestack {
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+        }
+    }
+
+    pub fn from_repo(repo: &git2::Repository) -> Result<Self, git2::Error> {
+        let config = repo.config()?;
+        let mut names = std::collections::BTreeSet::new();
+        let mut entries = config.entries(Some(r"^branch\..+\.stack-needs-restack$"))?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let Some(name) = entry
+                .name()
+                .and_then(|key| key.strip_prefix("branch."))
+                .and_then(|key| key.strip_suffix(NEEDS_RESTACK_BRANCH_KEY))
+                .and_then(|key| key.strip_suffix('.'))
+            else {
+                continue;
+            };
+            if git2::Config::parse_bool(entry.value().unwrap_or_default()).unwrap_or(false) {
+                names.insert(name.to_owned());
+            } else {
+                // Later entries override earlier ones
+                names.remove(name);
+            }
+        }
+        Ok(Self { names })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names.iter().map(|s| s.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Record that `branch` is waiting on a restack
+pub fn mark_needs_restack(repo: &git2::Repository, branch: &str) -> Result<(), git2::Error> {
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    config.set_bool(&format!("branch.{branch}.{NEEDS_RESTACK_BRANCH_KEY}"), true)
+}
+
+/// Forget that `branch` was waiting on a restack
+///
+/// Returns `false` if it wasn't.
+pub fn clear_needs_restack(repo: &git2::Repository, branch: &str) -> Result<bool, git2::Error> {
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    match config.remove(&format!("branch.{branch}.{NEEDS_RESTACK_BRANCH_KEY}")) {
+        Ok(()) => Ok(true),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
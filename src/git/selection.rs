@@ -0,0 +1,85 @@
+/// The stack chosen with `git stack select`, used in place of `--base`, `--onto`, and `--stack`
+///
+/// Revisions are kept as they were written, like `origin/main`, so they follow the branches they
+/// name.  The selection is kept per worktree, under `.git/git-stack/selection.json`.
+#[derive(Clone, Default, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StackSelection {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onto: Option<String>,
+    /// Which stacks to include or the name of a stack, like `--stack`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+}
+
+impl StackSelection {
+    /// Where the selection is kept within the worktree's `.git` directory
+    pub fn path(git_dir: &std::path::Path) -> std::path::PathBuf {
+        git_dir.join("git-stack").join("selection.json")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.base.is_none() && self.onto.is_none() && self.stack.is_none()
+    }
+
+    pub fn load(repo: &git2::Repository) -> eyre::Result<Option<Self>> {
+        let path = Self::path(repo.path());
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => eyre::bail!("could not read `{}`: {}", path.display(), err),
+        };
+        let selection = serde_json::from_str(&data)
+            .map_err(|err| eyre::eyre!("could not parse `{}`: {}", path.display(), err))?;
+        Ok(Some(selection))
+    }
+
+    pub fn save(&self, repo: &git2::Repository) -> eyre::Result<()> {
+        let path = Self::path(repo.path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, data)
+            .map_err(|err| eyre::eyre!("could not write `{}`: {}", path.display(), err))?;
+        Ok(())
+    }
+
+    /// Forget the selection
+    ///
+    /// Returns `false` if there wasn't one.
+    pub fn clear(repo: &git2::Repository) -> eyre::Result<bool> {
+        let path = Self::path(repo.path());
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => eyre::bail!("could not remove `{}`: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::fmt::Display for StackSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("base", self.base.as_deref()),
+            ("onto", self.onto.as_deref()),
+            ("stack", self.stack.as_deref()),
+        ];
+        let mut first = true;
+        for (name, value) in fields {
+            let Some(value) = value else {
+                continue;
+            };
+            if !first {
+                f.write_str(" ")?;
+            }
+            first = false;
+            write!(f, "--{name}={value}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+/// The version of the `git` command, for what `git-stack` leaves to it like `git push`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl GitVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Ask `git --version`, `None` if `git` can't be run
+    pub fn from_cli() -> Option<Self> {
+        let output = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse `git --version` output, like `git version 2.39.3 (Apple Git-146)`
+    ///
+    /// Vendor suffixes, like `.windows.1`, are ignored.
+    pub fn parse(output: &str) -> Option<Self> {
+        let version = output.trim().strip_prefix("git version ")?;
+        let version = version.split_whitespace().next()?;
+        let mut parts = version
+            .split('.')
+            .map(|part| part.parse::<u32>().ok())
+            .take(3);
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for GitVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
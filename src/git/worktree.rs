@@ -0,0 +1,176 @@
+/// Multivar of the worktrees `git stack worktree` created, by name
+pub const STACK_WORKTREE_FIELD: &str = "stack.worktree";
+
+/// Names of the worktrees created for the stack, see [`register_worktree`]
+pub fn stack_worktrees(repo: &git2::Repository) -> Result<Vec<String>, git2::Error> {
+    let config = repo.config()?;
+    let mut names = Vec::new();
+    let mut entries = config.multivar(STACK_WORKTREE_FIELD, None)?;
+    while let Some(entry) = entries.next() {
+        if let Some(name) = entry?.value() {
+            names.push(name.to_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Record a worktree as created for the stack, so it can be listed and cleaned up
+pub fn register_worktree(repo: &git2::Repository, name: &str) -> Result<(), git2::Error> {
+    if stack_worktrees(repo)?.iter().any(|n| n == name) {
+        return Ok(());
+    }
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    config.set_multivar(STACK_WORKTREE_FIELD, "^$", name)
+}
+
+/// Stop tracking a worktree created for the stack
+///
+/// Returns `false` if it wasn't registered.
+pub fn unregister_worktree(repo: &git2::Repository, name: &str) -> Result<bool, git2::Error> {
+    let names = stack_worktrees(repo)?;
+    if !names.iter().any(|n| n == name) {
+        return Ok(false);
+    }
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    config.remove_multivar(STACK_WORKTREE_FIELD, ".*")?;
+    for remaining in names.iter().filter(|n| *n != name) {
+        config.set_multivar(STACK_WORKTREE_FIELD, "^$", remaining)?;
+    }
+    Ok(true)
+}
+
+/// Local branches checked out in any worktree of the repo, with the worktree's path
+///
+/// This includes the main worktree, whichever worktree `repo` was opened from.
+pub fn checked_out_branches(
+    repo: &git2::Repository,
+) -> Result<Vec<(String, std::path::PathBuf)>, git2::Error> {
+    let main = open_main(repo)?;
+    let mut branches = Vec::new();
+    let mut add_head = |repo: &git2::Repository| {
+        let Some(workdir) = repo.workdir() else {
+            return;
+        };
+        let Ok(head) = repo.head() else {
+            return;
+        };
+        if head.is_branch() {
+            if let Some(name) = head.shorthand() {
+                branches.push((name.to_owned(), workdir.to_owned()));
+            }
+        }
+    };
+    add_head(&main);
+    for name in main.worktrees()?.iter().flatten() {
+        let Ok(worktree) = main.find_worktree(name) else {
+            continue;
+        };
+        if worktree.validate().is_err() {
+            continue;
+        }
+        if let Ok(repo) = git2::Repository::open_from_worktree(&worktree) {
+            add_head(&repo);
+        }
+    }
+    Ok(branches)
+}
+
+/// Local branches checked out in a worktree other than `repo`'s, with that worktree's path
+///
+/// Git won't update these from another worktree, so rewriting them is left to their worktree.
+pub fn branches_checked_out_elsewhere(
+    repo: &git2::Repository,
+) -> Result<std::collections::BTreeMap<String, std::path::PathBuf>, git2::Error> {
+    let canonical = |path: &std::path::Path| std::fs::canonicalize(path).unwrap_or(path.to_owned());
+    let workdir = repo.workdir().map(canonical);
+    Ok(checked_out_branches(repo)?
+        .into_iter()
+        .filter(|(_, path)| Some(canonical(path)) != workdir)
+        .collect())
+}
+
+/// Open the repo that `repo`'s worktrees belong to, which is `repo` unless it is a linked worktree
+pub fn open_main(repo: &git2::Repository) -> Result<git2::Repository, git2::Error> {
+    git2::Repository::open(common_dir(repo)?)
+}
+
+/// The git dir shared by all of `repo`'s worktrees, which is `repo`'s unless it is a linked
+/// worktree
+pub fn common_dir(repo: &git2::Repository) -> Result<std::path::PathBuf, git2::Error> {
+    let mut git_dir = repo.path().to_owned();
+    if repo.is_worktree() {
+        // Linked worktrees point to the shared git dir with a `commondir` file
+        let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+            .map_err(|err| git2::Error::from_str(&err.to_string()))?;
+        git_dir = git_dir.join(common_dir.trim());
+    }
+    Ok(git_dir)
+}
+
+/// What [`fast_forward_branch`] did
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FastForward {
+    /// The branch already points to the target
+    UpToDate,
+    /// The branch was (or, with `dry_run`, would be) moved to the target
+    Updated,
+    /// The target doesn't contain the branch, so it can't be fast-forwarded
+    Diverged,
+    /// The branch is checked out in this worktree, which has uncommitted changes
+    Dirty(std::path::PathBuf),
+}
+
+/// Move the local branch `name` forward to `target`, like `git merge --ff-only`
+///
+/// When the branch is checked out, in `repo`'s worktree or another one, that worktree is updated
+/// as well, so long as it has no uncommitted changes.
+pub fn fast_forward_branch(
+    repo: &git2::Repository,
+    name: &str,
+    target: git2::Oid,
+    message: &str,
+    dry_run: bool,
+) -> Result<FastForward, git2::Error> {
+    let mut branch = repo.find_branch(name, git2::BranchType::Local)?;
+    let current = branch
+        .get()
+        .target()
+        .ok_or_else(|| git2::Error::from_str(&format!("`{name}` is a symbolic reference")))?;
+    if current == target {
+        return Ok(FastForward::UpToDate);
+    }
+    if !repo.graph_descendant_of(target, current)? {
+        return Ok(FastForward::Diverged);
+    }
+
+    let worktree = checked_out_branches(repo)?
+        .into_iter()
+        .find(|(branch, _)| branch == name)
+        .map(|(_, path)| path);
+    if let Some(path) = worktree {
+        let worktree_repo = git2::Repository::open(&path)?;
+        let dirty = !worktree_repo
+            .statuses(Some(
+                git2::StatusOptions::new()
+                    .include_untracked(false)
+                    .include_ignored(false),
+            ))?
+            .is_empty();
+        if dirty {
+            return Ok(FastForward::Dirty(path));
+        }
+        if !dry_run {
+            let commit = worktree_repo.find_commit(target)?;
+            worktree_repo.checkout_tree(
+                commit.as_object(),
+                Some(git2::build::CheckoutBuilder::new().safe()),
+            )?;
+            worktree_repo
+                .find_reference(&format!("refs/heads/{name}"))?
+                .set_target(target, message)?;
+        }
+    } else if !dry_run {
+        branch.get_mut().set_target(target, message)?;
+    }
+    Ok(FastForward::Updated)
+}
@@ -12,10 +12,23 @@ impl BranchSet {
     ) -> crate::git::Result<Self> {
         let mut branches = Self::new();
         for mut branch in repo.local_branches().map(Branch::from) {
+            if repo.find_commit(branch.id()).is_none() {
+                // Like after an interrupted fetch or a pruned object database
+                log::warn!(
+                    "Skipping `{}`, its commit {} is missing (try `git fetch`)",
+                    branch.display_name(),
+                    branch.id()
+                );
+                continue;
+            }
             if protected.is_protected(branch.base_name()) {
                 log::trace!("Branch `{}` is protected", branch.display_name());
-                if let Some(remote) =
-                    repo.find_remote_branch(repo.pull_remote(), branch.base_name())
+                if let Some(remote) = repo
+                    .find_remote_branch(
+                        repo.pull_remote_for(branch.base_name()),
+                        branch.base_name(),
+                    )
+                    .filter(|remote| repo.find_commit(remote.id).is_some())
                 {
                     branch.set_kind(BranchKind::Mixed);
                     branch.set_pull_id(remote.id);
@@ -26,9 +39,7 @@ impl BranchSet {
                     branch.set_kind(BranchKind::Protected);
                 }
             } else {
-                if let Some(remote) =
-                    repo.find_remote_branch(repo.push_remote(), branch.base_name())
-                {
+                if let Some(remote) = repo.find_push_branch(branch.base_name()) {
                     branch.set_push_id(remote.id);
                 }
                 branch.set_kind(BranchKind::Mutable);
@@ -49,13 +60,13 @@ impl BranchSet {
             let new_branch = if let Some(mut new_branch) = new_branch.map(Branch::from) {
                 new_branch.kind = old_branch.kind;
                 new_branch.pull_id = old_branch.pull_id.and_then(|_| {
-                    repo.find_remote_branch(repo.pull_remote(), old_branch.base_name())
-                        .map(|b| b.id)
-                });
-                new_branch.push_id = old_branch.push_id.and_then(|_| {
-                    repo.find_remote_branch(repo.push_remote(), old_branch.base_name())
+                    let name = old_branch.base_name();
+                    repo.find_remote_branch(repo.pull_remote_for(name), name)
                         .map(|b| b.id)
                 });
+                new_branch.push_id = old_branch
+                    .push_id
+                    .and_then(|_| repo.find_push_branch(old_branch.base_name()).map(|b| b.id));
                 if new_branch.id() != old_branch.id() {
                     log::debug!(
                         "{} moved from {} to {}",
@@ -1,5 +1,6 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Action {
+    #[default]
     Pick,
     Fixup,
     Protected,
@@ -19,10 +20,4 @@ impl Action {
     }
 }
 
-impl Default for Action {
-    fn default() -> Self {
-        Self::Pick
-    }
-}
-
 impl crate::any::ResourceTag for Action {}
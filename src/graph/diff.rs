@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use super::Graph;
+
+/// What changed between two [`Graph`]s, see [`diff`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphDiff {
+    /// Commits only in the new graph
+    pub added: Vec<git2::Oid>,
+    /// Commits only in the old graph
+    pub removed: Vec<git2::Oid>,
+    /// Commits in both graphs whose parents changed, like when rebased onto a new base
+    pub moved: Vec<MovedCommit>,
+    /// Branches pointing somewhere else, including ones created or deleted
+    pub retargeted: Vec<BranchRetarget>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.retargeted.is_empty()
+    }
+}
+
+/// A commit with new parents, see [`GraphDiff::moved`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovedCommit {
+    pub id: git2::Oid,
+    /// Parents in the old graph, primary first
+    pub old_parents: Vec<git2::Oid>,
+    /// Parents in the new graph, primary first
+    pub new_parents: Vec<git2::Oid>,
+}
+
+/// A branch pointing somewhere else, see [`GraphDiff::retargeted`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchRetarget {
+    /// Name of the branch, including the remote for remote branches
+    pub name: String,
+    /// `None` if the branch didn't exist, or was deleted
+    pub old_id: Option<git2::Oid>,
+    /// `None` if the branch is deleted
+    pub new_id: Option<git2::Oid>,
+}
+
+/// Compare two graphs, like before and after planning changes to the stacks
+///
+/// Commits are reported in the order they are found walking each graph from its root, branches
+/// in name order.  Branches marked [`BranchKind::Deleted`][super::BranchKind::Deleted] are
+/// treated as not existing.
+pub fn diff(old: &Graph, new: &Graph) -> GraphDiff {
+    let mut diff = GraphDiff::default();
+
+    // Merges are reached through each parent
+    let mut seen = std::collections::HashSet::new();
+    for id in new.descendants() {
+        if !seen.insert(id) {
+            continue;
+        }
+        if !old.contains_id(id) {
+            diff.added.push(id);
+            continue;
+        }
+        let old_parents = parents(old, id);
+        let new_parents = parents(new, id);
+        if old_parents != new_parents {
+            diff.moved.push(MovedCommit {
+                id,
+                old_parents,
+                new_parents,
+            });
+        }
+    }
+    diff.removed = old
+        .descendants()
+        .filter(|id| !new.contains_id(*id) && seen.insert(*id))
+        .collect();
+
+    let old_branches = branch_ids(old);
+    let mut new_branches = branch_ids(new);
+    for (name, old_id) in old_branches {
+        let new_id = new_branches.remove(&name);
+        if new_id != Some(old_id) {
+            diff.retargeted.push(BranchRetarget {
+                name,
+                old_id: Some(old_id),
+                new_id,
+            });
+        }
+    }
+    diff.retargeted.extend(
+        new_branches
+            .into_iter()
+            .map(|(name, new_id)| BranchRetarget {
+                name,
+                old_id: None,
+                new_id: Some(new_id),
+            }),
+    );
+    diff.retargeted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    diff
+}
+
+fn parents(graph: &Graph, id: git2::Oid) -> Vec<git2::Oid> {
+    graph
+        .primary_parent_of(id)
+        .into_iter()
+        .chain(graph.merged_parents_of(id))
+        .collect()
+}
+
+fn branch_ids(graph: &Graph) -> BTreeMap<String, git2::Oid> {
+    graph
+        .branches
+        .iter()
+        .flat_map(|(_, branches)| branches.iter())
+        .filter(|b| b.kind() != super::BranchKind::Deleted)
+        .map(|b| (b.name(), b.id()))
+        .collect()
+}
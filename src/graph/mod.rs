@@ -1,9 +1,11 @@
 mod branch;
 mod commit;
+mod diff;
 mod ops;
 
 pub use branch::*;
 pub use commit::*;
+pub use diff::*;
 pub use ops::*;
 
 use std::collections::BTreeMap;
@@ -52,11 +54,10 @@ impl Graph {
 
         let mut graph = Graph::with_base_id(root_id);
         graph.branches = branches;
-        for branch_id in graph.branches.oids() {
-            for commit_id in crate::git::commit_range(repo, branch_id..root_id)? {
-                for (weight, parent_id) in repo.parent_ids(commit_id)?.into_iter().enumerate() {
-                    graph.graph.add_edge(commit_id, parent_id, weight);
-                }
+        let branch_ids = graph.branches.oids().collect::<Vec<_>>();
+        for (commit_id, parent_ids) in repo.parents_between(&branch_ids, root_id)? {
+            for (weight, parent_id) in parent_ids.into_iter().enumerate() {
+                graph.graph.add_edge(commit_id, parent_id, weight);
             }
         }
 
@@ -146,6 +147,18 @@ impl Graph {
             .find_map(|(_child, parent, weight)| (*weight == 0).then_some(parent))
     }
 
+    /// Parents other than the primary one, in merge order
+    pub fn merged_parents_of(&self, root_id: git2::Oid) -> Vec<git2::Oid> {
+        let mut parents = self
+            .graph
+            .edges_directed(root_id, petgraph::Direction::Outgoing)
+            .filter(|(_child, _parent, weight)| **weight != 0)
+            .map(|(_child, parent, weight)| (*weight, parent))
+            .collect::<Vec<_>>();
+        parents.sort_unstable();
+        parents.into_iter().map(|(_, parent)| parent).collect()
+    }
+
     pub fn parents_of(
         &self,
         root_id: git2::Oid,
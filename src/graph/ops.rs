@@ -12,12 +12,20 @@ pub fn protect_branches(graph: &mut Graph) {
             branches
                 .iter()
                 .find(|b| b.kind() == crate::graph::BranchKind::Protected)
-                .map(|_| oid)
+                .map(|b| (oid, b.name()))
+        })
+        .flat_map(|(protected_oid, name)| {
+            graph
+                .ancestors_of(protected_oid)
+                .map(move |oid| (oid, name.clone()))
         })
-        .flat_map(|protected_oid| graph.ancestors_of(protected_oid))
         .collect();
-    for protected_oid in protected_oids {
-        graph.commit_set(protected_oid, crate::graph::Action::Protected);
+    for (protected_oid, name) in protected_oids {
+        if graph.commit_set(protected_oid, crate::graph::Action::Protected) {
+            // Already explained by another protected branch
+            continue;
+        }
+        record_decision(graph, protected_oid, Decision::ProtectedBranch(name));
     }
 }
 
@@ -40,7 +48,6 @@ pub fn protect_large_branches(graph: &mut Graph, max: usize) -> Vec<String> {
                     }
                     crate::graph::BranchKind::Protected => {
                         ancestors.stop();
-                        continue;
                     }
                 }
             }
@@ -51,11 +58,10 @@ pub fn protect_large_branches(graph: &mut Graph, max: usize) -> Vec<String> {
                 .unwrap_or_default();
             if action.is_protected() {
                 ancestors.stop();
-                continue;
             }
         }
         if max <= count {
-            mark_branch_protected(graph, branch_id);
+            mark_branch_protected(graph, branch_id, Decision::LargeBranch(count));
             large_branches.extend(
                 graph
                     .branches
@@ -71,10 +77,12 @@ pub fn protect_large_branches(graph: &mut Graph, max: usize) -> Vec<String> {
     large_branches
 }
 
-fn mark_branch_protected(graph: &mut Graph, commit_id: git2::Oid) {
+fn mark_branch_protected(graph: &mut Graph, commit_id: git2::Oid, decision: Decision) {
     let protected_oids: Vec<_> = graph.ancestors_of(commit_id).collect();
     for protected_oid in protected_oids {
-        graph.commit_set(protected_oid, crate::graph::Action::Protected);
+        if !graph.commit_set(protected_oid, crate::graph::Action::Protected) {
+            record_decision(graph, protected_oid, decision.clone());
+        }
     }
 }
 
@@ -156,6 +164,11 @@ pub fn trim_tagged_branch<R: Resource + Eq>(
 }
 
 pub fn protect_tagged_branch<R: Resource + Eq>(graph: &mut Graph, template: R) {
+    let decision = Decision::Tagged(format!("{template:?}"));
+    protect_tagged_branch_as(graph, template, decision);
+}
+
+fn protect_tagged_branch_as<R: Resource + Eq>(graph: &mut Graph, template: R, decision: Decision) {
     let branches = graph
         .branches
         .iter()
@@ -163,7 +176,7 @@ pub fn protect_tagged_branch<R: Resource + Eq>(graph: &mut Graph, template: R) {
         .filter(|id| graph.commit_get::<R>(*id) == Some(&template))
         .collect::<Vec<_>>();
     for branch_id in branches {
-        mark_branch_protected(graph, branch_id);
+        mark_branch_protected(graph, branch_id, decision.clone());
     }
 }
 
@@ -199,7 +212,7 @@ pub fn protect_stale_branches(
     ignore: &[git2::Oid],
 ) {
     tag_stale_commits(graph, repo, earlier_than, ignore);
-    protect_tagged_branch(graph, StaleCommit);
+    protect_tagged_branch_as(graph, StaleCommit, Decision::StaleBranch);
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -240,7 +253,7 @@ pub fn protect_foreign_branches(
     ignore: &[git2::Oid],
 ) {
     tag_foreign_commits(graph, repo, user, ignore);
-    protect_tagged_branch(graph, ForeignCommit);
+    protect_tagged_branch_as(graph, ForeignCommit, Decision::ForeignBranch);
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -248,6 +261,162 @@ pub struct ForeignCommit;
 
 impl crate::any::ResourceTag for ForeignCommit {}
 
+/// Unprotected commits signed by someone other than `user`
+///
+/// Rewriting these would drop their signature.
+pub fn find_foreign_signed_commits(
+    graph: &Graph,
+    repo: &dyn crate::git::Repo,
+    user: &str,
+) -> Vec<git2::Oid> {
+    graph
+        .descendants()
+        .filter(|id| {
+            !graph
+                .commit_get::<crate::graph::Action>(*id)
+                .copied()
+                .unwrap_or_default()
+                .is_protected()
+        })
+        .filter(|id| {
+            repo.signed_by_committer(*id)
+                .map(|signer| *signer != *user)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Unprotected commits signed by `user`
+///
+/// Rewriting these without signing would drop their signature.
+pub fn find_own_signed_commits(
+    graph: &Graph,
+    repo: &dyn crate::git::Repo,
+    user: &str,
+) -> Vec<git2::Oid> {
+    graph
+        .descendants()
+        .filter(|id| {
+            !graph
+                .commit_get::<crate::graph::Action>(*id)
+                .copied()
+                .unwrap_or_default()
+                .is_protected()
+        })
+        .filter(|id| {
+            repo.signed_by_committer(*id)
+                .map(|signer| *signer == *user)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Protect branches with commits signed by someone other than `user`, preserving the signatures
+pub fn protect_foreign_signed_branches(
+    graph: &mut Graph,
+    repo: &dyn crate::git::Repo,
+    user: &str,
+) -> Vec<String> {
+    let signed: HashSet<_> = find_foreign_signed_commits(graph, repo, user)
+        .into_iter()
+        .collect();
+    if signed.is_empty() {
+        return Vec::new();
+    }
+
+    let mut signed_branches = Vec::new();
+    let branch_ids: Vec<_> = graph
+        .descendants()
+        .filter(|id| graph.branches.contains_oid(*id))
+        .collect();
+    for branch_id in branch_ids {
+        let mut signed_id = None;
+        let mut ancestors = graph.ancestors_of(branch_id).into_cursor();
+        while let Some(ancestor_id) = ancestors.next(graph) {
+            if graph
+                .commit_get::<crate::graph::Action>(ancestor_id)
+                .copied()
+                .unwrap_or_default()
+                .is_protected()
+            {
+                ancestors.stop();
+            } else if signed.contains(&ancestor_id) {
+                signed_id = Some(ancestor_id);
+                break;
+            }
+        }
+        if let Some(signed_id) = signed_id {
+            mark_branch_protected(graph, branch_id, Decision::ForeignSignature(signed_id));
+            signed_branches.extend(
+                graph
+                    .branches
+                    .get(branch_id)
+                    .unwrap_or(&[])
+                    .iter()
+                    .filter(|&branch| branch.kind().has_user_commits())
+                    .map(|branch| branch.name()),
+            );
+        }
+    }
+
+    signed_branches
+}
+
+/// Leave the stacks with branches checked out in other worktrees in place
+///
+/// `checked_out` maps branch names to their worktree, see
+/// [`branches_checked_out_elsewhere`][crate::git::branches_checked_out_elsewhere].  The whole
+/// stack is protected, as moving the rest of it would split it from those branches.
+///
+/// Returns the checked out branches found, with their worktree.
+pub fn protect_checked_out_branches(
+    graph: &mut Graph,
+    checked_out: &BTreeMap<String, std::path::PathBuf>,
+) -> Vec<(String, std::path::PathBuf)> {
+    let mut found = Vec::new();
+    let mut stack_roots = Vec::new();
+    for (branch_id, branches) in graph.branches.iter() {
+        let paths: Vec<_> = branches
+            .iter()
+            .filter_map(|b| {
+                let name = b.local_name()?;
+                let path = checked_out.get(name)?;
+                Some((name.to_owned(), path.clone()))
+            })
+            .collect();
+        let Some((_, path)) = paths.first() else {
+            continue;
+        };
+
+        let Some(stack_root) = graph
+            .ancestors_of(branch_id)
+            .take_while(|id| {
+                !graph
+                    .commit_get::<crate::graph::Action>(*id)
+                    .copied()
+                    .unwrap_or_default()
+                    .is_protected()
+            })
+            .last()
+        else {
+            // Already left in place
+            continue;
+        };
+        stack_roots.push((stack_root, path.clone()));
+        found.extend(paths);
+    }
+
+    for (stack_root, path) in stack_roots {
+        let stack: Vec<_> = graph.descendants_of(stack_root).collect();
+        for id in stack {
+            graph.commit_set(id, crate::graph::Action::Protected);
+            record_decision(graph, id, Decision::CheckedOut(path.clone()));
+        }
+    }
+
+    found
+}
+
 /// Pre-requisites:
 /// - Running `protect_branches`
 ///
@@ -321,11 +490,16 @@ pub fn mark_wip(graph: &mut Graph, repo: &dyn crate::git::Repo) {
             continue;
         }
 
+        if is_ignored(graph, current_id) {
+            continue;
+        }
+
         let commit = repo
             .find_commit(current_id)
             .expect("all commits in graph present in git");
         if commit.wip_summary().is_some() {
             graph.commit_set(current_id, Wip);
+            record_decision(graph, current_id, Decision::Wip);
         }
     }
 }
@@ -347,6 +521,10 @@ pub fn mark_fixup(graph: &mut Graph, repo: &dyn crate::git::Repo) {
             continue;
         }
 
+        if is_ignored(graph, current_id) {
+            continue;
+        }
+
         let commit = repo
             .find_commit(current_id)
             .expect("all commits in graph present in git");
@@ -361,6 +539,78 @@ pub struct Fixup;
 
 impl crate::any::ResourceTag for Fixup {}
 
+/// Tag the commits in `anchors`, for passes that reorder commits to leave alone
+pub fn mark_anchors(graph: &mut Graph, anchors: &crate::git::Anchors) {
+    for id in anchors.iter() {
+        if graph.contains_id(id) {
+            graph.commit_set(id, Anchor);
+            record_decision(graph, id, Decision::Anchor);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Anchor;
+
+impl crate::any::ResourceTag for Anchor {}
+
+fn is_anchor(graph: &Graph, id: git2::Oid) -> bool {
+    graph.commit_get::<Anchor>(id).is_some()
+}
+
+/// Tag the commits in `ignored`, for the WIP and fixup passes to leave alone
+pub fn mark_ignored(graph: &mut Graph, ignored: &crate::git::IgnoredCommits) {
+    for id in ignored.iter() {
+        if graph.contains_id(id) {
+            graph.commit_set(id, Ignored);
+            record_decision(graph, id, Decision::Ignored);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ignored;
+
+impl crate::any::ResourceTag for Ignored {}
+
+fn is_ignored(graph: &Graph, id: git2::Oid) -> bool {
+    graph.commit_get::<Ignored>(id).is_some()
+}
+
+/// Tag the commits that mark their branch as a draft, see [`crate::git::DraftBranches`]
+///
+/// These are commits with a [`crate::git::DRAFT_TRAILER`] and the tips of draft branches.
+pub fn mark_draft(graph: &mut Graph, repo: &git2::Repository, drafts: &crate::git::DraftBranches) {
+    let mut cursor = graph.descendants().into_cursor();
+    while let Some(current_id) = cursor.next(graph) {
+        if graph
+            .commit_get::<crate::graph::Action>(current_id)
+            .copied()
+            .unwrap_or_default()
+            .is_protected()
+        {
+            continue;
+        }
+
+        let draft_branch = graph
+            .branches
+            .get(current_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b.local_name())
+            .any(|name| drafts.contains(name));
+        if draft_branch || crate::git::is_draft_commit(repo, current_id) {
+            graph.commit_set(current_id, Draft);
+            record_decision(graph, current_id, Decision::Draft);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Draft;
+
+impl crate::any::ResourceTag for Draft {}
+
 pub fn pushable(graph: &mut Graph) {
     let branches = graph
         .branches
@@ -407,6 +657,30 @@ fn mark_push_status(graph: &mut Graph, branch_id: git2::Oid) -> Option<PushStatu
                     parent_id,
                 );
                 status = Some(PushStatus::Blocked("wip"));
+                record_decision(
+                    graph,
+                    branch_id,
+                    Decision::NotPushable {
+                        reason: "wip",
+                        blocked_by: parent_id,
+                    },
+                );
+                break;
+            } else if graph.commit_get::<Draft>(parent_id).is_some() {
+                log::debug!(
+                    "Branches at {} aren't pushable, commit {} is a draft",
+                    branch_id,
+                    parent_id,
+                );
+                status = Some(PushStatus::Draft);
+                record_decision(
+                    graph,
+                    branch_id,
+                    Decision::NotPushable {
+                        reason: "draft",
+                        blocked_by: parent_id,
+                    },
+                );
                 break;
             } else if branch_id != parent_id && graph.branches.contains_oid(parent_id) {
                 let parent_status = mark_push_status(graph, parent_id);
@@ -419,11 +693,44 @@ fn mark_push_status(graph: &mut Graph, branch_id: git2::Oid) -> Option<PushStatu
                             reason
                         );
                         status = Some(PushStatus::Blocked("parent branch"));
+                        record_decision(
+                            graph,
+                            branch_id,
+                            Decision::NotPushable {
+                                reason: "parent branch is blocked",
+                                blocked_by: parent_id,
+                            },
+                        );
+                        break;
+                    }
+                    Some(PushStatus::Draft) => {
+                        log::debug!(
+                            "Branches at {} aren't pushable, parent branch at {} is a draft",
+                            branch_id,
+                            parent_id
+                        );
+                        status = Some(PushStatus::Blocked("parent branch"));
+                        record_decision(
+                            graph,
+                            branch_id,
+                            Decision::NotPushable {
+                                reason: "parent branch is a draft",
+                                blocked_by: parent_id,
+                            },
+                        );
                         break;
                     }
                     Some(PushStatus::Pushed) | Some(PushStatus::Pushable) => {
                         log::debug!("Branches at {} aren't pushable, parent branch at {} should be pushed first", branch_id, parent_id);
                         status = Some(PushStatus::Blocked("parent branch"));
+                        record_decision(
+                            graph,
+                            branch_id,
+                            Decision::NotPushable {
+                                reason: "parent branch should be pushed first",
+                                blocked_by: parent_id,
+                            },
+                        );
                         break;
                     }
                     None => {
@@ -456,6 +763,8 @@ fn mark_push_status(graph: &mut Graph, branch_id: git2::Oid) -> Option<PushStatu
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PushStatus {
     Blocked(&'static str),
+    /// Not ready, only pushed with `--draft`
+    Draft,
     Pushed,
     Pushable,
 }
@@ -550,6 +859,7 @@ pub fn delete_squashed_branches_by_tree_id(
             continue;
         };
         let mut stop = false;
+        let mut squashed = false;
         for branch in branches {
             match branch.kind() {
                 crate::graph::BranchKind::Deleted => {
@@ -564,6 +874,7 @@ pub fn delete_squashed_branches_by_tree_id(
                     if pulled_tree_ids.contains(&commit.tree_id) {
                         branch.set_kind(crate::graph::BranchKind::Deleted);
                         removed.push(branch.clone());
+                        squashed = true;
                     }
                 }
                 crate::graph::BranchKind::Mixed | crate::graph::BranchKind::Protected => {
@@ -571,6 +882,9 @@ pub fn delete_squashed_branches_by_tree_id(
                 }
             }
         }
+        if squashed {
+            record_decision(graph, descendant_id, Decision::SquashMerged);
+        }
         if stop {
             descendants.stop();
         }
@@ -594,18 +908,39 @@ pub fn delete_merged_branches(
         } else {
             continue;
         };
+        let mut merged = false;
         for branch in branches {
             if branch.kind() == crate::graph::BranchKind::Mutable {
                 branch.set_kind(crate::graph::BranchKind::Deleted);
                 removed.push(branch.clone());
+                merged = true;
             }
         }
+        if merged {
+            record_decision(graph, pulled_id, Decision::Merged);
+        }
     }
     removed
 }
 
 pub fn fixup(graph: &mut Graph, repo: &dyn crate::git::Repo, effect: crate::config::Fixup) {
-    if effect == crate::config::Fixup::Ignore {
+    fixup_branches(graph, repo, effect, &Default::default());
+}
+
+/// Like [`fixup`], with `overrides` picking the effect for the fixups in particular branches
+///
+/// A fixup commit is in the nearest branch at or above it.
+pub fn fixup_branches(
+    graph: &mut Graph,
+    repo: &dyn crate::git::Repo,
+    effect: crate::config::Fixup,
+    overrides: &BTreeMap<String, crate::config::Fixup>,
+) {
+    if effect == crate::config::Fixup::Ignore
+        && overrides
+            .values()
+            .all(|e| *e == crate::config::Fixup::Ignore)
+    {
         return;
     }
 
@@ -617,7 +952,10 @@ pub fn fixup(graph: &mut Graph, repo: &dyn crate::git::Repo, effect: crate::conf
             .commit_get::<crate::graph::Action>(descendant_id)
             .copied()
             .unwrap_or_default();
-        if action.is_protected() {
+        if action.is_protected()
+            || is_anchor(graph, descendant_id)
+            || is_ignored(graph, descendant_id)
+        {
             continue;
         }
 
@@ -625,15 +963,24 @@ pub fn fixup(graph: &mut Graph, repo: &dyn crate::git::Repo, effect: crate::conf
             .find_commit(descendant_id)
             .expect("all commits in graph present in git");
         if let Some(summary) = commit.fixup_summary() {
-            fixups.push((descendant_id, summary.to_owned()));
+            let fixup_effect = graph
+                .descendants_of(descendant_id)
+                .find_map(|id| graph.branches.get(id)?.iter().find_map(|b| b.local_name()))
+                .and_then(|name| overrides.get(name).copied())
+                .unwrap_or(effect);
+            if fixup_effect != crate::config::Fixup::Ignore {
+                fixups.push((descendant_id, summary.to_owned(), fixup_effect));
+            }
         }
     }
 
-    for (fixup_id, summary) in fixups {
+    for (fixup_id, summary, effect) in fixups {
         let mut ancestors = graph.ancestors_of(fixup_id).into_cursor();
         let _self = ancestors.next(graph);
         assert_eq!(_self, Some(fixup_id));
-        let mut fixed = false;
+        let mut found = false;
+        // Keep looking past an anchor, but only to report why the fixup was left in place
+        let mut anchor_id = None;
         while let Some(ancestor_id) = ancestors.next(graph) {
             let action = graph
                 .commit_get::<crate::graph::Action>(ancestor_id)
@@ -644,23 +991,29 @@ pub fn fixup(graph: &mut Graph, repo: &dyn crate::git::Repo, effect: crate::conf
                 continue;
             }
 
+            if anchor_id.is_none() && is_anchor(graph, ancestor_id) {
+                anchor_id = Some(ancestor_id);
+            }
+
             let anc_commit = repo
                 .find_commit(ancestor_id)
                 .expect("all commits in graph present in git");
-            if let Some(anc_summary) = anc_commit.fixup_summary() {
-                if anc_summary == summary {
+            let is_target = anc_commit.summary == summary
+                || (!is_ignored(graph, ancestor_id)
+                    && anc_commit.fixup_summary() == Some(summary.as_ref()));
+            if is_target {
+                if let Some(anchor_id) = anchor_id {
+                    record_decision(graph, fixup_id, Decision::AnchoredFixup(anchor_id));
+                } else {
                     fixup_commit(graph, fixup_id, ancestor_id, effect);
-                    fixed = true;
-                    break;
+                    record_decision(graph, fixup_id, Decision::Fixup(ancestor_id));
                 }
-            }
-            if anc_commit.summary == summary {
-                fixup_commit(graph, fixup_id, ancestor_id, effect);
-                fixed = true;
+                found = true;
                 break;
             }
         }
-        if !fixed {
+        if !found {
+            record_decision(graph, fixup_id, Decision::NoFixupTarget);
             log::trace!(
                 "Could not find base commit for fixup {} ({})",
                 fixup_id,
@@ -718,6 +1071,76 @@ fn fixup_commit(
     }
 }
 
+/// Why planning treated a commit the way it did
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Decision {
+    /// Reachable from the named protected branch
+    ProtectedBranch(String),
+    /// Part of a branch with this many commits, too many to be under development
+    LargeBranch(usize),
+    /// Part of a branch where every commit is older than the cut-off
+    StaleBranch,
+    /// Part of a branch where no commit is by the current user
+    ForeignBranch,
+    /// Part of a branch with a commit signed by someone else, which rewriting would drop
+    ForeignSignature(git2::Oid),
+    /// Part of a stack with a branch checked out in the worktree at this path
+    CheckedOut(std::path::PathBuf),
+    /// Part of a branch protected by a custom tag
+    Tagged(String),
+    /// The commit is a work-in-progress
+    Wip,
+    /// The commit marks its branch as a draft
+    Draft,
+    /// Branches here aren't pushable because of the commit they are blocked by
+    NotPushable {
+        reason: &'static str,
+        blocked_by: git2::Oid,
+    },
+    /// Fixup commit, applied to the target commit
+    Fixup(git2::Oid),
+    /// Fixup commit without a matching commit to apply it to
+    NoFixupTarget,
+    /// Fixup commit left in place as applying it would rewrite or move it across this anchor
+    AnchoredFixup(git2::Oid),
+    /// Anchor, commits aren't reordered around it
+    Anchor,
+    /// Exempt from the WIP and fixup heuristics
+    Ignored,
+    /// Branches here were squash-merged, the tree matches a pulled commit
+    SquashMerged,
+    /// Branches here were merged into a pulled commit
+    Merged,
+}
+
+/// The [`Decision`]s recorded for a commit, in the order they were made
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Decisions(Vec<Decision>);
+
+impl Decisions {
+    pub fn iter(&self) -> impl Iterator<Item = &Decision> + '_ {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl crate::any::ResourceTag for Decisions {}
+
+/// Explain why `id` is being treated the way it is, for later reporting
+pub fn record_decision(graph: &mut Graph, id: git2::Oid, decision: Decision) {
+    if let Some(decisions) = graph.commit_get_mut::<Decisions>(id) {
+        if !decisions.0.contains(&decision) {
+            decisions.0.push(decision);
+        }
+    } else {
+        graph.commit_set(id, Decisions(vec![decision]));
+    }
+}
+
 /// When a branch has extra commits, update dependent branches to the latest
 pub fn realign_stacks(graph: &mut Graph, repo: &dyn crate::git::Repo) {
     let mut descendants = graph.descendants().into_cursor();
@@ -740,6 +1163,15 @@ fn realign_stack(graph: &mut Graph, repo: &dyn crate::git::Repo, base_id: git2::
 
     let mut current_id = base_id;
     loop {
+        if current_id != base_id && is_anchor(graph, current_id) {
+            // Moving the other stacks on top would reorder them around the anchor, so leave them
+            // and realign each side separately
+            for (_, child_id) in old_edges {
+                realign_stack(graph, repo, child_id);
+            }
+            realign_stack(graph, repo, current_id);
+            return;
+        }
         if graph
             .branches
             .get(current_id)
@@ -799,6 +1231,11 @@ pub fn merge_stacks_by_tree_id(graph: &mut Graph, repo: &dyn crate::git::Repo) {
                 continue;
             }
 
+            if is_anchor(graph, child_id) {
+                // Never dropped in favor of a duplicate
+                continue;
+            }
+
             let commit = repo
                 .find_commit(child_id)
                 .expect("all commits in graph present in git");
@@ -832,6 +1269,63 @@ pub fn merge_stacks_by_tree_id(graph: &mut Graph, repo: &dyn crate::git::Repo) {
 
 type CommitTimesByTreeId = BTreeMap<git2::Oid, Vec<(std::time::SystemTime, git2::Oid)>>;
 
+/// When commits were rewritten outside of `git-stack`, move what was stacked on the originals
+///
+/// A plain `git rebase` of a branch in the middle of a stack leaves the branches on top of it on
+/// the old commits.  Commits are matched by [`crate::git::patch_id`], taking the most recent as the
+/// rewrite, and what was on the original is moved on top of the rewrite.
+pub fn merge_stacks_by_patch_id(graph: &mut Graph, repo: &dyn crate::git::Repo) {
+    let mut by_patch_id = CommitTimesByPatchId::new();
+    for id in graph.descendants().collect::<Vec<_>>() {
+        let action = graph
+            .commit_get::<crate::graph::Action>(id)
+            .copied()
+            .unwrap_or_default();
+        if action.is_protected() || is_anchor(graph, id) {
+            continue;
+        }
+        let Some(patch_id) = repo.patch_id(id) else {
+            continue;
+        };
+        let commit = repo
+            .find_commit(id)
+            .expect("all commits in graph present in git");
+        by_patch_id
+            .entry(patch_id)
+            .or_default()
+            .push((commit.time, id));
+    }
+
+    for mut commits in by_patch_id.into_values() {
+        if commits.len() < 2 {
+            continue;
+        }
+
+        commits.sort_unstable();
+        let (_, winner_id) = commits.pop().expect("checked len earlier");
+
+        for (_, from_id) in commits {
+            if graph.descendants_of(from_id).any(|id| id == winner_id)
+                || graph.descendants_of(winner_id).any(|id| id == from_id)
+            {
+                // Re-applied within the same history, like after a revert
+                continue;
+            }
+            let rebased_children = graph.children_of(from_id).collect::<Vec<_>>();
+            for child_id in rebased_children {
+                graph.rebase(child_id, from_id, winner_id);
+            }
+            for mut branch in graph.branches.remove(from_id).into_iter().flatten() {
+                branch.set_id(winner_id);
+                graph.branches.insert(branch);
+            }
+            graph.remove(from_id);
+        }
+    }
+}
+
+type CommitTimesByPatchId = BTreeMap<git2::Oid, Vec<(std::time::SystemTime, git2::Oid)>>;
+
 pub fn reword_commit(
     graph: &mut Graph,
     repo: &dyn crate::git::Repo,
@@ -848,6 +1342,9 @@ pub fn reword_commit(
         .find_commit(id)
         .expect("graph.contains_id ensures commit exists");
     for descendant_id in graph.descendants_of(id) {
+        if is_ignored(graph, descendant_id) {
+            continue;
+        }
         let descendant_commit = repo
             .find_commit(descendant_id)
             .expect("graph.descendants_of ensures commit exists");
@@ -861,6 +1358,144 @@ pub fn reword_commit(
     Ok(())
 }
 
+/// Squash the commits unique to the branch at `tip_id` into its first commit
+///
+/// The branch's commits run from `tip_id` down to, but not including, the first protected commit
+/// or commit with another branch.  Branches stacked on `tip_id` are rebased onto the squashed
+/// commit.
+///
+/// Returns the branch's commits, oldest first.
+pub fn squash_branch(graph: &mut Graph, tip_id: git2::Oid) -> Result<Vec<git2::Oid>, eyre::Error> {
+    eyre::ensure!(
+        graph.contains_id(tip_id),
+        "cannot squash commit {}, not present",
+        tip_id
+    );
+
+    let mut ids = Vec::new();
+    let mut current_id = Some(tip_id);
+    while let Some(id) = current_id {
+        let action = graph
+            .commit_get::<crate::graph::Action>(id)
+            .copied()
+            .unwrap_or_default();
+        if action.is_protected() {
+            break;
+        }
+        let has_branch = graph
+            .branches
+            .get(id)
+            .into_iter()
+            .flatten()
+            .any(|b| b.kind().has_user_commits());
+        if id != tip_id && has_branch {
+            break;
+        }
+        eyre::ensure!(
+            graph.merged_parents_of(id).is_empty(),
+            "cannot squash merge commit {}",
+            id
+        );
+        eyre::ensure!(
+            id == tip_id || graph.children_of(id).count() == 1,
+            "cannot squash, commits are stacked on {}",
+            id
+        );
+        ids.push(id);
+        current_id = graph.primary_parent_of(id);
+    }
+    ids.reverse();
+
+    for id in ids.iter().skip(1) {
+        graph.commit_set(*id, crate::graph::Action::Fixup);
+    }
+
+    Ok(ids)
+}
+
+/// Re-parent the branch at `tip_id`, and everything stacked on it, onto `onto_id`
+///
+/// The branch's commits are found as in [`squash_branch`].  Everything else is protected, and
+/// dropped from the branches, so only the moved commits and branches are rewritten.
+///
+/// Returns the branch's first commit.
+pub fn move_branch(
+    graph: &mut Graph,
+    tip_id: git2::Oid,
+    onto_id: git2::Oid,
+) -> Result<git2::Oid, eyre::Error> {
+    eyre::ensure!(
+        graph.contains_id(tip_id),
+        "cannot move commit {}, not present",
+        tip_id
+    );
+    eyre::ensure!(
+        graph.contains_id(onto_id),
+        "cannot move onto commit {}, not present",
+        onto_id
+    );
+
+    let mut first_id = tip_id;
+    loop {
+        let action = graph
+            .commit_get::<crate::graph::Action>(first_id)
+            .copied()
+            .unwrap_or_default();
+        eyre::ensure!(
+            !action.is_protected(),
+            "cannot move protected commit {}",
+            first_id
+        );
+        let Some(parent_id) = graph.primary_parent_of(first_id) else {
+            eyre::bail!("cannot move commit {}, it has no parent", first_id);
+        };
+        let parent_action = graph
+            .commit_get::<crate::graph::Action>(parent_id)
+            .copied()
+            .unwrap_or_default();
+        let parent_has_branch = graph
+            .branches
+            .get(parent_id)
+            .into_iter()
+            .flatten()
+            .any(|b| b.kind().has_user_commits());
+        if parent_action.is_protected() || parent_has_branch || parent_id == graph.root_id() {
+            break;
+        }
+        first_id = parent_id;
+    }
+    eyre::ensure!(
+        graph.merged_parents_of(first_id).is_empty(),
+        "cannot move merge commit {}",
+        first_id
+    );
+
+    let moved: HashSet<_> = graph.descendants_of(first_id).collect();
+    eyre::ensure!(
+        !moved.contains(&onto_id),
+        "cannot move onto {}, it is stacked on the branch",
+        onto_id
+    );
+    let parent_id = graph
+        .primary_parent_of(first_id)
+        .expect("checked when finding the first commit");
+    if parent_id != onto_id {
+        graph.rebase(first_id, parent_id, onto_id);
+    }
+
+    let others: Vec<_> = graph
+        .descendants()
+        .filter(|id| !moved.contains(id))
+        .collect();
+    for id in others {
+        graph.commit_set(id, crate::graph::Action::Protected);
+        // Leave their branches be, rather than re-creating them where they are
+        graph.branches.remove(id);
+    }
+
+    Ok(first_id)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Reword(String);
 
@@ -980,7 +1615,12 @@ fn gather_script(
             .unwrap_or_default();
         match action {
             crate::graph::Action::Pick => {
-                batch.push(id, crate::rewrite::Command::CherryPick(id));
+                let merged_ids = graph.merged_parents_of(id);
+                if merged_ids.is_empty() {
+                    batch.push(id, crate::rewrite::Command::CherryPick(id));
+                } else {
+                    batch.push(id, crate::rewrite::Command::Merge(id, merged_ids));
+                }
                 if let Some(Reword(message)) = graph.commit_get::<Reword>(id) {
                     batch.push(id, crate::rewrite::Command::Reword(message.clone()));
                 }
@@ -1017,12 +1657,13 @@ fn gather_script(
         }
 
         current_id = None;
-        for (i, child_id) in graph.children_of(id).enumerate() {
+        // Merges are re-created from their primary parent
+        for (i, child_id) in graph
+            .children_of(id)
+            .filter(|c| graph.primary_parent_of(*c) == Some(id))
+            .enumerate()
+        {
             match i {
-                0 if 1 < graph.parents_of(child_id).count() => {
-                    current_id = None;
-                    gather_script(graph, id, child_id, dropped_branches, script);
-                }
                 0 => {
                     current_id = Some(child_id);
                 }
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use crate::rewrite::Id;
+
+/// Bumped whenever the layout changes, so older caches are ignored rather than misread
+const CACHE_VERSION: u32 = 2;
+
+/// What [`GitRepo`][super::GitRepo] learned about commits, kept between runs
+///
+/// Commits never change, so what was computed for them stays valid.  To keep the cache from
+/// growing without bound, entries are dropped once a branch tip they were computed for moves.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct GraphCache {
+    version: u32,
+    /// Where each branch pointed when the cache was saved
+    tips: BTreeMap<String, Id>,
+    commits: Vec<CachedCommit>,
+    bases: Vec<(Id, Id, Option<Id>)>,
+    counts: Vec<(Id, Id, Option<usize>)>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct CachedCommit {
+    id: Id,
+    tree_id: Id,
+    summary: String,
+    encoding: Option<String>,
+    time: u64,
+    author: Option<String>,
+    committer: Option<String>,
+}
+
+impl GraphCache {
+    /// Where the cache is kept within `git_dir`
+    pub fn path(git_dir: &std::path::Path) -> std::path::PathBuf {
+        git_dir.join("git-stack").join("graph-cache.json")
+    }
+
+    /// Read the cache, if there is a usable one
+    ///
+    /// A missing, unreadable, or outdated cache is treated as empty.
+    pub fn load(git_dir: &std::path::Path) -> Option<Self> {
+        let path = Self::path(git_dir);
+        let data = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<Self>(&data) {
+            Ok(cache) if cache.version == CACHE_VERSION => Some(cache),
+            Ok(cache) => {
+                log::debug!(
+                    "Ignoring `{}`, version {} is not {}",
+                    path.display(),
+                    cache.version,
+                    CACHE_VERSION
+                );
+                None
+            }
+            Err(err) => {
+                log::debug!("Ignoring `{}`: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, git_dir: &std::path::Path) -> eyre::Result<()> {
+        let path = Self::path(git_dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string(self)?;
+        std::fs::write(&path, data)
+            .map_err(|err| eyre::eyre!("could not write `{}`: {}", path.display(), err))?;
+        Ok(())
+    }
+
+    /// Branch tips the cache was computed against
+    pub fn tips(&self) -> impl Iterator<Item = (&str, git2::Oid)> + '_ {
+        self.tips.iter().map(|(name, id)| (name.as_str(), id.0))
+    }
+
+    pub(crate) fn commits(&self) -> impl Iterator<Item = super::Commit> + '_ {
+        self.commits.iter().map(|c| super::Commit {
+            id: c.id.0,
+            tree_id: c.tree_id.0,
+            summary: c.summary.clone().into(),
+            encoding: c.encoding.as_deref().map(Into::into),
+            time: std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(c.time),
+            author: c.author.as_deref().map(Into::into),
+            committer: c.committer.as_deref().map(Into::into),
+        })
+    }
+
+    /// Merge bases, keyed by the pair of commits
+    pub(crate) fn bases(
+        &self,
+    ) -> impl Iterator<Item = (git2::Oid, git2::Oid, Option<git2::Oid>)> + '_ {
+        self.bases
+            .iter()
+            .map(|(one, two, base)| (one.0, two.0, base.map(|b| b.0)))
+    }
+
+    /// Commit counts, keyed by base and head
+    pub(crate) fn counts(
+        &self,
+    ) -> impl Iterator<Item = (git2::Oid, git2::Oid, Option<usize>)> + '_ {
+        self.counts
+            .iter()
+            .map(|(base, head, count)| (base.0, head.0, *count))
+    }
+
+    pub(crate) fn new<'c>(
+        tips: impl IntoIterator<Item = (String, git2::Oid)>,
+        commits: impl IntoIterator<Item = &'c super::Commit>,
+        bases: impl IntoIterator<Item = (git2::Oid, git2::Oid, Option<git2::Oid>)>,
+        counts: impl IntoIterator<Item = (git2::Oid, git2::Oid, Option<usize>)>,
+    ) -> Self {
+        let tips = tips.into_iter().map(|(name, id)| (name, Id(id))).collect();
+        let commits = commits
+            .into_iter()
+            .filter_map(|c| {
+                // Summaries in other encodings aren't worth the trouble, they are re-read instead
+                let summary = String::from_utf8(c.summary.to_vec()).ok()?;
+                let time = c
+                    .time
+                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                Some(CachedCommit {
+                    id: Id(c.id),
+                    tree_id: Id(c.tree_id),
+                    summary,
+                    encoding: c.encoding.as_deref().map(ToOwned::to_owned),
+                    time,
+                    author: c.author.as_deref().map(ToOwned::to_owned),
+                    committer: c.committer.as_deref().map(ToOwned::to_owned),
+                })
+            })
+            .collect();
+        let bases = bases
+            .into_iter()
+            .map(|(one, two, base)| (Id(one), Id(two), base.map(Id)))
+            .collect();
+        let counts = counts
+            .into_iter()
+            .map(|(base, head, count)| (Id(base), Id(head), count))
+            .collect();
+        Self {
+            version: CACHE_VERSION,
+            tips,
+            commits,
+            bases,
+            counts,
+        }
+    }
+}
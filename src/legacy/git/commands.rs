@@ -51,6 +51,301 @@ impl Script {
     }
 }
 
+impl Script {
+    /// Find the scripts that would conflict, without touching the working tree or any branch
+    ///
+    /// See [`crate::rewrite::Script::dry_run_check`]
+    pub fn dry_run_check(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+    ) -> Result<Vec<Conflict>, git2::Error> {
+        let mut conflicts = Vec::new();
+        let mut marks = std::collections::HashMap::new();
+        self.check_single(repo, &mut marks, None, &mut Vec::new(), &mut conflicts)?;
+        Ok(conflicts)
+    }
+
+    /// Existing commits that applying the script replaces with new ones or squashes away
+    ///
+    /// See [`crate::rewrite::Script::rewritten_commits`]
+    pub fn rewritten_commits(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+    ) -> Result<Vec<git2::Oid>, git2::Error> {
+        let mut rewritten = Vec::new();
+        let mut marks = std::collections::HashMap::new();
+        self.rewritten_single(repo, &mut marks, None, &mut rewritten)?;
+        let mut seen = std::collections::HashSet::new();
+        rewritten.retain(|id| seen.insert(*id));
+        Ok(rewritten)
+    }
+
+    /// The branches the script would recreate as they already are, if it changes nothing
+    ///
+    /// See [`crate::rewrite::Script::skip_up_to_date`]
+    pub fn up_to_date_branches(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+    ) -> Result<Option<Vec<String>>, git2::Error> {
+        let mut branches = Vec::new();
+        let mut marks = std::collections::HashMap::new();
+        if self.up_to_date_single(repo, &mut marks, None, &mut branches)? {
+            Ok(Some(branches))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether the script re-stacks `branch`
+    pub fn creates_branch(&self, branch: &str) -> bool {
+        self.commands
+            .iter()
+            .any(|c| matches!(c, Command::CreateBranch(name) if name == branch))
+            || self.dependents.iter().any(|d| d.creates_branch(branch))
+    }
+
+    /// Narrow the script to re-stacking `branch`, leaving every other branch in place
+    ///
+    /// The commits `branch` is stacked on are still replayed beneath it.  With `descendants`, the
+    /// branches stacked on top of `branch` are re-stacked as well.
+    pub fn retain_branch(&mut self, branch: &str, descendants: bool) {
+        let is_other_branch = |c: &Command| match c {
+            Command::CreateBranch(name) | Command::DeleteBranch(name) => name != branch,
+            _ => false,
+        };
+        let position = self
+            .commands
+            .iter()
+            .position(|c| matches!(c, Command::CreateBranch(name) if name == branch));
+        if let Some(position) = position {
+            if !descendants {
+                self.commands.truncate(position + 1);
+                self.dependents.clear();
+            }
+            let mut i = 0;
+            self.commands.retain(|c| {
+                let keep = position < i || !is_other_branch(c);
+                i += 1;
+                keep
+            });
+        } else {
+            self.commands.retain(|c| !is_other_branch(c));
+            self.dependents.retain(|d| d.creates_branch(branch));
+            for dependent in &mut self.dependents {
+                dependent.retain_branch(branch, descendants);
+            }
+        }
+    }
+
+    /// Leave out the scripts found by [`Script::dry_run_check`], along with their dependents
+    pub fn skip_conflicts(&mut self, conflicts: &[Conflict]) {
+        let mut paths = conflicts.iter().map(|c| &c.path).collect::<Vec<_>>();
+        // Remove later siblings first so the earlier paths stay valid
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+        for path in paths {
+            let Some((last, parent_path)) = path.split_last() else {
+                *self = Self::new();
+                return;
+            };
+            let mut parent = &mut *self;
+            for i in parent_path {
+                parent = &mut parent.dependents[*i];
+            }
+            if *last < parent.dependents.len() {
+                parent.dependents.remove(*last);
+            }
+        }
+    }
+
+    fn rewritten_single(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+        marks: &mut std::collections::HashMap<git2::Oid, Option<git2::Oid>>,
+        mut head_id: Option<git2::Oid>,
+        rewritten: &mut Vec<git2::Oid>,
+    ) -> Result<(), git2::Error> {
+        for command in &self.commands {
+            match command {
+                Command::SwitchCommit(oid) => {
+                    head_id = Some(*oid);
+                }
+                Command::RegisterMark(mark_oid) => {
+                    marks.insert(*mark_oid, head_id);
+                }
+                Command::SwitchMark(mark_oid) => {
+                    head_id = marks.get(mark_oid).copied().unwrap_or(Some(*mark_oid));
+                }
+                Command::CherryPick(cherry_oid) => {
+                    let parent_ids = repo.parent_ids(*cherry_oid)?;
+                    if head_id.is_some() && head_id == parent_ids.first().copied() {
+                        head_id = Some(*cherry_oid);
+                    } else {
+                        rewritten.push(*cherry_oid);
+                        head_id = None;
+                    }
+                }
+                Command::Fixup(squash_oid) => {
+                    rewritten.extend(head_id);
+                    rewritten.push(*squash_oid);
+                    head_id = None;
+                }
+                Command::CreateBranch(_) | Command::DeleteBranch(_) => {}
+            }
+        }
+
+        for dependent in self.dependents.iter() {
+            dependent.rewritten_single(repo, marks, head_id, rewritten)?;
+        }
+
+        Ok(())
+    }
+
+    fn up_to_date_single(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+        marks: &mut std::collections::HashMap<git2::Oid, Option<git2::Oid>>,
+        mut head_id: Option<git2::Oid>,
+        branches: &mut Vec<String>,
+    ) -> Result<bool, git2::Error> {
+        for command in &self.commands {
+            match command {
+                Command::SwitchCommit(oid) => {
+                    head_id = Some(*oid);
+                }
+                Command::RegisterMark(mark_oid) => {
+                    if head_id != Some(*mark_oid) {
+                        return Ok(false);
+                    }
+                    marks.insert(*mark_oid, head_id);
+                }
+                Command::SwitchMark(mark_oid) => {
+                    head_id = marks.get(mark_oid).copied().unwrap_or(Some(*mark_oid));
+                }
+                Command::CherryPick(cherry_oid) => {
+                    let parent_ids = repo.parent_ids(*cherry_oid)?;
+                    if head_id.is_none() || head_id != parent_ids.first().copied() {
+                        return Ok(false);
+                    }
+                    head_id = Some(*cherry_oid);
+                }
+                Command::CreateBranch(name) => {
+                    let branch_id = repo.find_local_branch(name).map(|b| b.id);
+                    if head_id.is_none() || branch_id != head_id {
+                        return Ok(false);
+                    }
+                    branches.push(name.clone());
+                }
+                Command::Fixup(_) | Command::DeleteBranch(_) => {
+                    return Ok(false);
+                }
+            }
+        }
+
+        for dependent in self.dependents.iter() {
+            if !dependent.up_to_date_single(repo, marks, head_id, branches)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn check_single(
+        &self,
+        repo: &dyn crate::legacy::git::Repo,
+        marks: &mut std::collections::HashMap<git2::Oid, git2::Oid>,
+        mut tree_id: Option<git2::Oid>,
+        path: &mut Vec<usize>,
+        conflicts: &mut Vec<Conflict>,
+    ) -> Result<(), git2::Error> {
+        for command in &self.commands {
+            match command {
+                Command::SwitchCommit(oid) => {
+                    let commit = repo.find_commit(*oid).ok_or_else(|| {
+                        git2::Error::new(
+                            git2::ErrorCode::NotFound,
+                            git2::ErrorClass::Reference,
+                            format!("could not find commit {oid:?}"),
+                        )
+                    })?;
+                    tree_id = Some(commit.tree_id);
+                }
+                Command::RegisterMark(mark_oid) => {
+                    if let Some(tree_id) = tree_id {
+                        marks.insert(*mark_oid, tree_id);
+                    }
+                }
+                Command::SwitchMark(mark_oid) => {
+                    tree_id = marks.get(mark_oid).copied();
+                }
+                Command::CherryPick(cherry_oid) | Command::Fixup(cherry_oid) => {
+                    let Some(current_id) = tree_id else {
+                        continue;
+                    };
+                    match repo.apply_to_tree(current_id, *cherry_oid)? {
+                        Some(updated_id) => {
+                            tree_id = Some(updated_id);
+                        }
+                        None => {
+                            log::trace!(
+                                "`{}` will conflict on {}",
+                                self.branch().unwrap_or("detached"),
+                                cherry_oid
+                            );
+                            conflicts.push(Conflict {
+                                path: path.clone(),
+                                commit_id: *cherry_oid,
+                                branch: self.branch().map(ToOwned::to_owned),
+                                dependents: self
+                                    .dependent_branches()
+                                    .into_iter()
+                                    .map(ToOwned::to_owned)
+                                    .collect(),
+                            });
+                            return Ok(());
+                        }
+                    }
+                }
+                Command::CreateBranch(_) | Command::DeleteBranch(_) => {}
+            }
+        }
+
+        for (i, dependent) in self.dependents.iter().enumerate() {
+            path.push(i);
+            dependent.check_single(repo, marks, tree_id, path, conflicts)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// A script that [`Script::dry_run_check`] found won't apply cleanly
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    path: Vec<usize>,
+    commit_id: git2::Oid,
+    branch: Option<String>,
+    dependents: Vec<String>,
+}
+
+impl Conflict {
+    /// The commit that won't cherry-pick
+    pub fn commit_id(&self) -> git2::Oid {
+        self.commit_id
+    }
+
+    /// The branch holding the commit that conflicts
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// Branches stacked on top of the conflict that can't be re-stacked either
+    pub fn dependents(&self) -> &[String] {
+        &self.dependents
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Command {
     /// Switch to an existing commit
@@ -76,6 +371,7 @@ pub struct Executor {
     delete_branches: Vec<String>,
     post_rewrite: Vec<(git2::Oid, git2::Oid)>,
     dry_run: bool,
+    run_hooks: bool,
     detached: bool,
 }
 
@@ -89,10 +385,16 @@ impl Executor {
             delete_branches: Default::default(),
             post_rewrite: Default::default(),
             dry_run,
+            run_hooks: true,
             detached: false,
         }
     }
 
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    pub fn set_run_hooks(&mut self, run_hooks: bool) {
+        self.run_hooks = run_hooks;
+    }
+
     pub fn run_script<'s>(
         &mut self,
         repo: &mut dyn crate::legacy::git::Repo,
@@ -207,6 +509,11 @@ impl Executor {
                 self.head_oid = updated_oid;
             }
             Command::CreateBranch(name) => {
+                // Branches that already exist are only re-pointed, even if they clash by case
+                if repo.find_local_branch(name).is_none() {
+                    let existing: Vec<_> = repo.local_branches().map(|b| b.name).collect();
+                    crate::git::check_branch_case(name, existing.iter().map(|n| n.as_str()))?;
+                }
                 let branch_oid = self.head_oid;
                 self.branches.push((branch_oid, name.to_owned()));
             }
@@ -220,13 +527,10 @@ impl Executor {
 
     pub fn commit(&mut self, repo: &mut dyn crate::legacy::git::Repo) -> Result<(), git2::Error> {
         let hook_repo = repo.path().map(git2::Repository::open).transpose()?;
-        let hooks = if self.dry_run {
+        let hooks = if self.dry_run || !self.run_hooks {
             None
         } else {
-            hook_repo
-                .as_ref()
-                .map(git2_ext::hooks::Hooks::with_repo)
-                .transpose()?
+            hook_repo.as_ref().map(crate::git::hooks).transpose()?
         };
 
         log::trace!("Running reference-transaction hook");
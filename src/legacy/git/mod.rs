@@ -1,9 +1,11 @@
 mod branches;
+mod cache;
 mod commands;
 mod protect;
 mod repo;
 
 pub use branches::*;
+pub use cache::*;
 pub use commands::*;
 pub use protect::*;
 pub use repo::*;
@@ -9,6 +9,8 @@ pub trait Repo {
     fn merge_base(&self, one: git2::Oid, two: git2::Oid) -> Option<git2::Oid>;
 
     fn find_commit(&self, id: git2::Oid) -> Option<std::rc::Rc<Commit>>;
+    /// See [`GitRepo::signed_by_committer`]
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>>;
     fn head_commit(&self) -> std::rc::Rc<Commit>;
     fn head_branch(&self) -> Option<Branch>;
     fn resolve(&self, revspec: &str) -> Option<std::rc::Rc<Commit>>;
@@ -24,6 +26,12 @@ pub trait Repo {
         haystack_id: git2::Oid,
         needle_id: git2::Oid,
     ) -> Result<bool, git2::Error>;
+    /// See [`crate::git::apply_to_tree`]
+    fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>, git2::Error>;
     fn cherry_pick(
         &mut self,
         head_id: git2::Oid,
@@ -74,12 +82,18 @@ pub struct Commit {
     pub id: git2::Oid,
     pub tree_id: git2::Oid,
     pub summary: bstr::BString,
+    pub encoding: Option<std::rc::Rc<str>>,
     pub time: std::time::SystemTime,
     pub author: Option<std::rc::Rc<str>>,
     pub committer: Option<std::rc::Rc<str>>,
 }
 
 impl Commit {
+    /// The summary, decoded according to the commit's `encoding`
+    pub fn display_summary(&self) -> std::borrow::Cow<'_, str> {
+        crate::git::decode_message(&self.summary, self.encoding.as_deref())
+    }
+
     pub fn fixup_summary(&self) -> Option<&bstr::BStr> {
         self.summary
             .strip_prefix(b"fixup! ")
@@ -117,43 +131,164 @@ impl Commit {
 pub struct GitRepo {
     repo: git2::Repository,
     sign: Option<git2_ext::ops::UserSign>,
+    sign_policy: crate::config::Sign,
     push_remote: Option<String>,
+    branch_push_remotes: std::collections::HashMap<String, String>,
     pull_remote: Option<String>,
+    protected_remotes: crate::git::ProtectedRemotes,
     commits: std::cell::RefCell<std::collections::HashMap<git2::Oid, std::rc::Rc<Commit>>>,
     interned_strings: std::cell::RefCell<std::collections::HashSet<std::rc::Rc<str>>>,
     bases: std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<git2::Oid>>>,
     counts: std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<usize>>>,
+    signers: std::cell::RefCell<std::collections::HashMap<git2::Oid, Option<std::rc::Rc<str>>>>,
+    /// Results of [`GitRepo::apply_to_tree`], so cherry-picks after a check don't merge again
+    applied:
+        std::cell::RefCell<std::collections::HashMap<(git2::Oid, git2::Oid), Option<git2::Oid>>>,
+    /// Where checks write their objects, see [`GitRepo::keep_checks_in_memory`]
+    check_repo: Option<git2::Repository>,
+    /// Branch tips as of [`GitRepo::load_cache`], if the cache is in use
+    cache_tips: Option<std::collections::BTreeMap<String, git2::Oid>>,
 }
 
+/// Past this many entries, the cache is started over
+const MAX_CACHE_ENTRIES: usize = 100_000;
+
 impl GitRepo {
     pub fn new(repo: git2::Repository) -> Self {
         Self {
             repo,
             sign: None,
+            sign_policy: crate::config::Sign::Never,
             push_remote: None,
+            branch_push_remotes: Default::default(),
             pull_remote: None,
+            protected_remotes: Default::default(),
             commits: Default::default(),
             interned_strings: Default::default(),
             bases: Default::default(),
             counts: Default::default(),
+            signers: Default::default(),
+            applied: Default::default(),
+            check_repo: None,
+            cache_tips: None,
+        }
+    }
+
+    /// Reuse what earlier runs learned about commits, see [`GraphCache`][super::GraphCache]
+    ///
+    /// Merge bases and counts involving a commit a branch has since moved away from are dropped.
+    pub fn load_cache(&mut self) {
+        let tips = self.branch_tips();
+        if let Some(cache) = super::GraphCache::load(self.repo.path()) {
+            let stale = moved_tips(cache.tips(), &tips);
+            log::trace!(
+                "Loaded graph cache, {} branch tips moved since it was saved",
+                stale.len()
+            );
+            let mut commits = self.commits.borrow_mut();
+            for commit in cache.commits() {
+                commits.insert(commit.id, std::rc::Rc::new(commit));
+            }
+            let mut bases = self.bases.borrow_mut();
+            for (one, two, base) in cache.bases() {
+                if !stale.contains(&one) && !stale.contains(&two) {
+                    bases.insert((one, two), base);
+                }
+            }
+            let mut counts = self.counts.borrow_mut();
+            for (base, head, count) in cache.counts() {
+                if !stale.contains(&base) && !stale.contains(&head) {
+                    counts.insert((base, head), count);
+                }
+            }
         }
+        self.cache_tips = Some(tips);
+    }
+
+    /// Keep what was learned about commits for the next run, if [`GitRepo::load_cache`] was called
+    pub fn save_cache(&self) -> eyre::Result<()> {
+        let Some(loaded_tips) = &self.cache_tips else {
+            return Ok(());
+        };
+        let tips = self.branch_tips();
+        let stale = moved_tips(loaded_tips.iter().map(|(n, id)| (n.as_str(), *id)), &tips);
+        let commits = self.commits.borrow();
+        let bases = self.bases.borrow();
+        let counts = self.counts.borrow();
+        let cache = if MAX_CACHE_ENTRIES < commits.len() + bases.len() + counts.len() {
+            log::debug!("Starting the graph cache over, it has grown too large");
+            super::GraphCache::new(tips, None, None, None)
+        } else {
+            super::GraphCache::new(
+                tips,
+                commits.values().map(|c| c.as_ref()),
+                bases
+                    .iter()
+                    .filter(|((one, two), _)| !stale.contains(one) && !stale.contains(two))
+                    .map(|((one, two), base)| (*one, *two, *base)),
+                counts
+                    .iter()
+                    .filter(|((base, head), _)| !stale.contains(base) && !stale.contains(head))
+                    .map(|((base, head), count)| (*base, *head, *count)),
+            )
+        };
+        cache.save(self.repo.path())
+    }
+
+    fn branch_tips(&self) -> std::collections::BTreeMap<String, git2::Oid> {
+        let Ok(references) = self.repo.references() else {
+            return Default::default();
+        };
+        references
+            .filter_map(|r| r.ok())
+            .filter(|r| r.is_branch() || r.is_remote())
+            .filter_map(|r| Some((r.name()?.to_owned(), r.target()?)))
+            .collect()
     }
 
     pub fn set_sign(&mut self, yes: bool) -> Result<(), git2::Error> {
-        if yes {
-            let config = self.repo.config()?;
-            let sign = git2_ext::ops::UserSign::from_config(&self.repo, &config)?;
-            self.sign = Some(sign);
+        let policy = if yes {
+            crate::config::Sign::Always
         } else {
-            self.sign = None;
-        }
+            crate::config::Sign::Never
+        };
+        self.set_sign_policy(policy)
+    }
+
+    /// Choose which rewritten commits get signed
+    pub fn set_sign_policy(&mut self, policy: crate::config::Sign) -> Result<(), git2::Error> {
+        self.sign = match policy {
+            crate::config::Sign::Never => None,
+            crate::config::Sign::Always | crate::config::Sign::IfSigned => {
+                let config = self.repo.config()?;
+                Some(git2_ext::ops::UserSign::from_config(&self.repo, &config)?)
+            }
+        };
+        self.sign_policy = policy;
         Ok(())
     }
 
+    /// How to sign the commit rewritten from `original_id`, if at all
+    fn sign_for(&self, original_id: git2::Oid) -> Option<&dyn git2_ext::ops::Sign> {
+        let sign = self.sign.as_ref()?;
+        if self.sign_policy == crate::config::Sign::IfSigned
+            && self.repo.extract_signature(&original_id, None).is_err()
+        {
+            return None;
+        }
+        Some(sign)
+    }
+
     pub fn set_push_remote(&mut self, remote: &str) {
         self.push_remote = Some(remote.to_owned());
     }
 
+    /// Push `branch` to `remote`, rather than the [`GitRepo::push_remote`]
+    pub fn set_branch_push_remote(&mut self, branch: &str, remote: &str) {
+        self.branch_push_remotes
+            .insert(branch.to_owned(), remote.to_owned());
+    }
+
     pub fn set_pull_remote(&mut self, remote: &str) {
         self.pull_remote = Some(remote.to_owned());
     }
@@ -166,6 +301,34 @@ impl GitRepo {
         self.pull_remote.as_deref().unwrap_or("origin")
     }
 
+    pub fn set_protected_remotes(&mut self, remotes: crate::git::ProtectedRemotes) {
+        self.protected_remotes = remotes;
+    }
+
+    /// The remote to push the branch `name` to
+    pub fn push_remote_for(&self, name: &str) -> &str {
+        self.branch_push_remotes
+            .get(name)
+            .map(|r| r.as_str())
+            .unwrap_or_else(|| self.push_remote())
+    }
+
+    /// Push `refs` to `remote` in one go with libgit2, see [`crate::git::push`]
+    pub fn push_refs(
+        &self,
+        remote: &str,
+        refs: &[crate::git::PushRef],
+    ) -> Result<crate::git::PushReport, crate::git::PushError> {
+        crate::git::push(&self.repo, remote, refs)
+    }
+
+    /// The remote to pull the protected branch `name` from
+    pub fn pull_remote_for(&self, name: &str) -> &str {
+        self.protected_remotes
+            .remote(name)
+            .unwrap_or_else(|| self.pull_remote())
+    }
+
     pub fn raw(&self) -> &git2::Repository {
         &self.repo
     }
@@ -224,7 +387,8 @@ impl GitRepo {
             Some(std::rc::Rc::clone(commit))
         } else {
             let commit = self.repo.find_commit(id).ok()?;
-            let summary: bstr::BString = commit.summary_bytes().unwrap().into();
+            let summary: bstr::BString = commit.summary_bytes().unwrap_or_default().into();
+            let encoding = commit.message_encoding().map(|e| self.intern_string(e));
             let time = std::time::SystemTime::UNIX_EPOCH
                 + std::time::Duration::from_secs(commit.time().seconds().max(0) as u64);
 
@@ -234,6 +398,7 @@ impl GitRepo {
                 id: commit.id(),
                 tree_id: commit.tree_id(),
                 summary,
+                encoding,
                 time,
                 author,
                 committer,
@@ -243,6 +408,24 @@ impl GitRepo {
         }
     }
 
+    /// The committer of `id`, if the commit carries a signature
+    ///
+    /// The signature isn't verified, the committer is assumed to have made it.  Reading it means
+    /// parsing the commit again, so this is only looked up when signatures matter.
+    pub fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        if let Some(signer) = self.signers.borrow().get(&id) {
+            return signer.clone();
+        }
+        let signer = self
+            .repo
+            .extract_signature(&id, None)
+            .ok()
+            .and_then(|_| self.repo.find_commit(id).ok())
+            .and_then(|c| c.committer().name().map(|n| self.intern_string(n)));
+        self.signers.borrow_mut().insert(id, signer.clone());
+        signer
+    }
+
     pub fn head_commit(&self) -> std::rc::Rc<Commit> {
         let head_id = self
             .repo
@@ -265,18 +448,11 @@ impl GitRepo {
         let name = resolved.shorthand()?;
         let id = resolved.target()?;
 
-        let push_id = self
-            .repo
-            .find_branch(
-                &format!("{}/{}", self.push_remote(), name),
-                git2::BranchType::Remote,
-            )
-            .ok()
-            .and_then(|b| b.get().target());
+        let push_id = self.push_id(name);
         let pull_id = self
             .repo
             .find_branch(
-                &format!("{}/{}", self.pull_remote(), name),
+                &format!("{}/{}", self.pull_remote_for(name), name),
                 git2::BranchType::Remote,
             )
             .ok()
@@ -433,17 +609,61 @@ impl GitRepo {
         }
     }
 
+    /// Write the objects [`GitRepo::apply_to_tree`] creates to memory rather than the repository
+    ///
+    /// For checks in a dry run, which must not leave anything behind.
+    pub fn keep_checks_in_memory(&mut self) -> Result<(), git2::Error> {
+        let check_repo = git2::Repository::open(self.repo.path())?;
+        check_repo.odb()?.add_new_mempack_backend(1000)?;
+        self.check_repo = Some(check_repo);
+        self.applied.borrow_mut().clear();
+        Ok(())
+    }
+
+    pub fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>, git2::Error> {
+        if let Some(applied) = self.applied.borrow().get(&(tree_id, cherry_id)) {
+            return Ok(*applied);
+        }
+        let repo = self.check_repo.as_ref().unwrap_or(&self.repo);
+        let applied = crate::git::apply_to_tree(repo, tree_id, cherry_id)?;
+        self.applied
+            .borrow_mut()
+            .insert((tree_id, cherry_id), applied);
+        Ok(applied)
+    }
+
+    /// The tree an earlier check found cherry-picking `cherry_id` onto `head_id` gives
+    fn checked_tree(&self, head_id: git2::Oid, cherry_id: git2::Oid) -> Option<git2::Oid> {
+        if self.check_repo.is_some() {
+            return None;
+        }
+        let cherry_commit = self.repo.find_commit(cherry_id).ok()?;
+        if cherry_commit.parent_count() != 1 {
+            return None;
+        }
+        let head_tree_id = self.repo.find_commit(head_id).ok()?.tree_id();
+        self.applied
+            .borrow()
+            .get(&(head_tree_id, cherry_id))
+            .copied()?
+    }
+
     fn cherry_pick(
         &mut self,
         head_id: git2::Oid,
         cherry_id: git2::Oid,
     ) -> Result<git2::Oid, git2::Error> {
-        git2_ext::ops::cherry_pick(
-            &self.repo,
-            head_id,
-            cherry_id,
-            self.sign.as_ref().map(|s| s as &dyn git2_ext::ops::Sign),
-        )
+        let sign = self.sign_for(cherry_id);
+        match self.checked_tree(head_id, cherry_id) {
+            Some(tree_id) => {
+                crate::git::cherry_pick_tree(&self.repo, head_id, cherry_id, tree_id, sign)
+            }
+            None => crate::git::cherry_pick(&self.repo, head_id, cherry_id, sign),
+        }
     }
 
     pub fn squash(
@@ -451,12 +671,7 @@ impl GitRepo {
         head_id: git2::Oid,
         into_id: git2::Oid,
     ) -> Result<git2::Oid, git2::Error> {
-        git2_ext::ops::squash(
-            &self.repo,
-            head_id,
-            into_id,
-            self.sign.as_ref().map(|s| s as &dyn git2_ext::ops::Sign),
-        )
+        crate::git::squash(&self.repo, head_id, into_id, self.sign_for(into_id))
     }
 
     pub fn stash_push(&mut self, message: Option<&str>) -> Result<git2::Oid, git2::Error> {
@@ -560,18 +775,11 @@ impl GitRepo {
     ) -> Result<Branch, git2::Error> {
         let id = branch.get().target().unwrap();
 
-        let push_id = self
-            .repo
-            .find_branch(
-                &format!("{}/{}", self.push_remote(), name),
-                git2::BranchType::Remote,
-            )
-            .ok()
-            .and_then(|b| b.get().target());
+        let push_id = self.push_id(name);
         let pull_id = self
             .repo
             .find_branch(
-                &format!("{}/{}", self.pull_remote(), name),
+                &format!("{}/{}", self.pull_remote_for(name), name),
                 git2::BranchType::Remote,
             )
             .ok()
@@ -586,6 +794,17 @@ impl GitRepo {
         })
     }
 
+    /// Where the push remote has the local branch `name`, see [`crate::git::push_target`]
+    fn push_id(&self, name: &str) -> Option<git2::Oid> {
+        let remote = self.push_remote_for(name);
+        let target = crate::git::push_target(&self.repo, remote, name)?;
+        let tracking = crate::git::tracking_branch(&self.repo, remote, &target)?;
+        self.repo
+            .find_branch(&tracking, git2::BranchType::Remote)
+            .ok()
+            .and_then(|b| b.get().target())
+    }
+
     fn load_remote_branch(
         &self,
         branch: &git2::Branch<'_>,
@@ -594,8 +813,8 @@ impl GitRepo {
     ) -> Result<Branch, git2::Error> {
         let id = branch.get().target().unwrap();
 
-        let push_id = (remote == self.push_remote()).then_some(id);
-        let pull_id = (remote == self.pull_remote()).then_some(id);
+        let push_id = (remote == self.push_remote_for(name)).then_some(id);
+        let pull_id = (remote == self.pull_remote_for(name)).then_some(id);
 
         Ok(Branch {
             remote: Some(remote.to_owned()),
@@ -641,6 +860,17 @@ impl GitRepo {
     }
 }
 
+/// Commits that `old` branch tips pointed to and no current branch does
+fn moved_tips<'n>(
+    old: impl Iterator<Item = (&'n str, git2::Oid)>,
+    current: &std::collections::BTreeMap<String, git2::Oid>,
+) -> std::collections::HashSet<git2::Oid> {
+    let current_ids = current.values().collect::<std::collections::HashSet<_>>();
+    old.filter(|(name, id)| current.get(*name) != Some(id) && !current_ids.contains(id))
+        .map(|(_, id)| id)
+        .collect()
+}
+
 impl std::fmt::Debug for GitRepo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         f.debug_struct("GitRepo")
@@ -671,6 +901,10 @@ impl Repo for GitRepo {
         self.find_commit(id)
     }
 
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        self.signed_by_committer(id)
+    }
+
     fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.head_commit()
     }
@@ -707,6 +941,14 @@ impl Repo for GitRepo {
         self.contains_commit(haystack_id, needle_id)
     }
 
+    fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>, git2::Error> {
+        self.apply_to_tree(tree_id, cherry_id)
+    }
+
     fn cherry_pick(
         &mut self,
         head_id: git2::Oid,
@@ -834,6 +1076,11 @@ impl InMemoryRepo {
         self.commits.get(&id).map(|c| c.1.clone())
     }
 
+    /// In-memory commits are never signed
+    pub fn signed_by_committer(&self, _id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        None
+    }
+
     pub fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.commits.get(&self.head_id.unwrap()).cloned().unwrap().1
     }
@@ -926,6 +1173,22 @@ impl InMemoryRepo {
         Ok(false)
     }
 
+    /// Without file contents, nothing conflicts
+    pub fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>, git2::Error> {
+        self.find_commit(cherry_id).ok_or_else(|| {
+            git2::Error::new(
+                git2::ErrorCode::NotFound,
+                git2::ErrorClass::Reference,
+                format!("could not find commit {cherry_id:?}"),
+            )
+        })?;
+        Ok(Some(tree_id))
+    }
+
     pub fn cherry_pick(
         &mut self,
         head_id: git2::Oid,
@@ -1095,6 +1358,10 @@ impl Repo for InMemoryRepo {
         self.find_commit(id)
     }
 
+    fn signed_by_committer(&self, id: git2::Oid) -> Option<std::rc::Rc<str>> {
+        self.signed_by_committer(id)
+    }
+
     fn head_commit(&self) -> std::rc::Rc<Commit> {
         self.head_commit()
     }
@@ -1127,6 +1394,14 @@ impl Repo for InMemoryRepo {
         self.contains_commit(haystack_id, needle_id)
     }
 
+    fn apply_to_tree(
+        &self,
+        tree_id: git2::Oid,
+        cherry_id: git2::Oid,
+    ) -> Result<Option<git2::Oid>, git2::Error> {
+        self.apply_to_tree(tree_id, cherry_id)
+    }
+
     fn cherry_pick(
         &mut self,
         head_id: git2::Oid,
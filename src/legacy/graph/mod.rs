@@ -40,12 +40,12 @@ impl Graph {
         });
 
         let branch_id = branch_ids.remove(0);
-        let branch_commit = repo.find_commit(branch_id).unwrap();
+        let branch_commit = find_branch_commit(repo, &branches, branch_id)?;
         let root = Node::new(branch_commit).with_branches(&mut branches);
         let mut graph = Self::new(root);
 
         for branch_id in branch_ids {
-            let branch_commit = repo.find_commit(branch_id).unwrap();
+            let branch_commit = find_branch_commit(repo, &branches, branch_id)?;
             let node = Node::new(branch_commit).with_branches(&mut branches);
             graph.insert(repo, node)?;
         }
@@ -177,6 +177,23 @@ impl Graph {
     }
 }
 
+fn find_branch_commit(
+    repo: &dyn crate::legacy::git::Repo,
+    branches: &crate::legacy::git::Branches,
+    branch_id: git2::Oid,
+) -> eyre::Result<std::rc::Rc<crate::legacy::git::Commit>> {
+    repo.find_commit(branch_id).ok_or_else(|| {
+        let names = branches
+            .get(branch_id)
+            .into_iter()
+            .flatten()
+            .map(|b| format!("`{b}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        eyre::eyre!("commit {} for {} is missing", branch_id, names)
+    })
+}
+
 pub struct BreadthFirstIter<'g> {
     graph: &'g Graph,
     node_queue: VecDeque<git2::Oid>,
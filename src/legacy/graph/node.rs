@@ -6,6 +6,14 @@ pub struct Node {
     pub branches: Vec<crate::legacy::git::Branch>,
     pub action: crate::legacy::graph::Action,
     pub pushable: bool,
+    /// Commits aren't reordered around it, see [`crate::git::Anchors`]
+    pub anchor: bool,
+    /// Not treated as WIP or a fixup, see [`crate::git::IgnoredCommits`]
+    pub ignored: bool,
+    /// Marks its branch as not ready to push, see [`crate::git::DraftBranches`]
+    pub draft: bool,
+    /// Its branch is waiting on a restack, see [`crate::git::PendingRestack`]
+    pub needs_restack: bool,
     pub children: BTreeSet<git2::Oid>,
 }
 
@@ -18,6 +26,10 @@ impl Node {
             branches,
             action: crate::legacy::graph::Action::Pick,
             pushable: false,
+            anchor: false,
+            ignored: false,
+            draft: false,
+            needs_restack: false,
             children,
         }
     }
@@ -42,6 +54,22 @@ impl Node {
             self.pushable = true;
         }
 
+        if other.anchor {
+            self.anchor = true;
+        }
+
+        if other.ignored {
+            self.ignored = true;
+        }
+
+        if other.draft {
+            self.draft = true;
+        }
+
+        if other.needs_restack {
+            self.needs_restack = true;
+        }
+
         self.children.extend(other.children);
     }
 }
@@ -49,6 +49,61 @@ pub fn protect_commits(
     }
 }
 
+/// Flag the commits in `anchors`, for passes that reorder commits to leave alone
+pub fn mark_anchors(graph: &mut Graph, anchors: &crate::git::Anchors) {
+    for id in anchors.iter() {
+        if let Some(node) = graph.get_mut(id) {
+            node.anchor = true;
+        }
+    }
+}
+
+/// Flag the commits in `ignored`, for the WIP and fixup passes to leave alone
+pub fn mark_ignored(graph: &mut Graph, ignored: &crate::git::IgnoredCommits) {
+    for id in ignored.iter() {
+        if let Some(node) = graph.get_mut(id) {
+            node.ignored = true;
+        }
+    }
+}
+
+/// Flag the commits that mark their branch as a draft, see [`crate::git::DraftBranches`]
+pub fn mark_draft(graph: &mut Graph, repo: &git2::Repository, drafts: &crate::git::DraftBranches) {
+    let ids: Vec<_> = graph.breadth_first_iter().map(|n| n.commit.id).collect();
+    for id in ids {
+        let node = graph.get_mut(id).expect("all children exist");
+        if node.action.is_protected() {
+            continue;
+        }
+        let draft_branch = node
+            .branches
+            .iter()
+            .filter(|b| b.remote.is_none())
+            .any(|b| drafts.contains(&b.name));
+        if draft_branch || crate::git::is_draft_commit(repo, id) {
+            node.draft = true;
+        }
+    }
+}
+
+/// Flag the commits of branches waiting on a restack, see [`crate::git::PendingRestack`]
+pub fn mark_needs_restack(graph: &mut Graph, pending: &crate::git::PendingRestack) {
+    if pending.is_empty() {
+        return;
+    }
+    let ids: Vec<_> = graph.breadth_first_iter().map(|n| n.commit.id).collect();
+    for id in ids {
+        let node = graph.get_mut(id).expect("all children exist");
+        if node
+            .branches
+            .iter()
+            .any(|b| b.remote.is_none() && pending.contains(&b.name))
+        {
+            node.needs_restack = true;
+        }
+    }
+}
+
 pub fn protect_large_branches(graph: &mut Graph, max: usize) -> Vec<String> {
     let mut large_branches = Vec::new();
 
@@ -330,6 +385,259 @@ fn is_personal_branch(graph: &Graph, node_id: git2::Oid, user: &str, ignore: &[g
     false
 }
 
+/// Leave the stacks with branches checked out in other worktrees in place
+///
+/// Returns the checked out branches found, with their worktree.
+pub fn protect_checked_out_branches(
+    graph: &mut Graph,
+    checked_out: &BTreeMap<String, std::path::PathBuf>,
+) -> Vec<(String, std::path::PathBuf)> {
+    let mut found = Vec::new();
+
+    let mut protected_queue = VecDeque::new();
+    if graph.root().action.is_protected() {
+        protected_queue.push_back(graph.root_id());
+    }
+    while let Some(current_id) = protected_queue.pop_front() {
+        let current_children = graph
+            .get(current_id)
+            .expect("all children exist")
+            .children
+            .clone();
+
+        for child_id in current_children {
+            let child_action = graph.get(child_id).expect("all children exist").action;
+            if child_action.is_protected() {
+                protected_queue.push_back(child_id);
+                continue;
+            }
+
+            let stack: Vec<_> = crate::legacy::graph::BreadthFirstIter::new(graph, child_id)
+                .map(|n| n.commit.id)
+                .collect();
+            let stack_found: Vec<_> = stack
+                .iter()
+                .flat_map(|id| &graph.get(*id).expect("all children exist").branches)
+                .filter_map(|b| {
+                    let name = b.local_name()?;
+                    let path = checked_out.get(name)?;
+                    Some((name.to_owned(), path.clone()))
+                })
+                .collect();
+            if !stack_found.is_empty() {
+                // Moving the rest of the stack would split it from the checked out branches
+                for id in stack {
+                    graph.get_mut(id).expect("all children exist").action =
+                        crate::legacy::graph::Action::Protected;
+                }
+                found.extend(stack_found);
+            }
+        }
+    }
+
+    found
+}
+
+/// Stacks removed by [`trim_inactive_branches`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InactiveBranches {
+    pub old: Vec<String>,
+    pub foreign: Vec<String>,
+}
+
+/// Combined [`trim_old_branches`], [`trim_foreign_branches`], and [`protect_foreign_branches`]
+///
+/// Each stack is classified in a single walk, rather than once per pass.  Stacks kept only
+/// because they contain an `ignore` commit are protected if they are foreign.
+pub fn trim_inactive_branches(
+    graph: &mut Graph,
+    earlier_than: std::time::SystemTime,
+    user: Option<&str>,
+    ignore: &[git2::Oid],
+) -> InactiveBranches {
+    let mut inactive = InactiveBranches::default();
+
+    let mut protected_queue = VecDeque::new();
+    if graph.root().action.is_protected() {
+        protected_queue.push_back(graph.root_id());
+    }
+    while let Some(current_id) = protected_queue.pop_front() {
+        let current_children = graph
+            .get(current_id)
+            .expect("all children exist")
+            .children
+            .clone();
+
+        for child_id in current_children {
+            let child_action = graph.get(child_id).expect("all children exist").action;
+            if child_action.is_protected() {
+                protected_queue.push_back(child_id);
+                continue;
+            }
+
+            let activity = branch_activity(graph, child_id, earlier_than, user, ignore);
+            let trimmed = if !activity.recent && !activity.ignored {
+                Some(&mut inactive.old)
+            } else if user.is_some() && !activity.personal && !activity.ignored {
+                Some(&mut inactive.foreign)
+            } else {
+                None
+            };
+            if let Some(trimmed) = trimmed {
+                let removed = graph
+                    .remove_child(current_id, child_id)
+                    .expect("all children exist");
+                trimmed.extend(removed.breadth_first_iter().flat_map(|n| {
+                    n.branches
+                        .iter()
+                        // Remote branches are implicitly protected, so we don't need to record them
+                        .filter_map(|b| b.local_name().map(String::from))
+                }));
+            } else if user.is_some() && !activity.personal {
+                mark_branch_protected(graph, child_id, &mut Vec::new());
+            }
+        }
+    }
+
+    inactive
+}
+
+#[derive(Copy, Clone, Default)]
+struct BranchActivity {
+    recent: bool,
+    personal: bool,
+    ignored: bool,
+}
+
+fn branch_activity(
+    graph: &Graph,
+    node_id: git2::Oid,
+    earlier_than: std::time::SystemTime,
+    user: Option<&str>,
+    ignore: &[git2::Oid],
+) -> BranchActivity {
+    let mut activity = BranchActivity::default();
+
+    let mut queue = VecDeque::new();
+    queue.push_back(node_id);
+    while let Some(current_id) = queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        activity.ignored |= ignore.contains(&current_id);
+        activity.recent |= earlier_than < current.commit.time;
+        if let Some(user) = user {
+            activity.personal |= current.commit.committer.as_deref() == Some(user)
+                || current.commit.author.as_deref() == Some(user);
+        }
+        if (activity.recent || activity.ignored) && (activity.personal || user.is_none()) {
+            // Neither old nor foreign, the rest of the stack can't change that
+            break;
+        }
+        queue.extend(current.children.iter().copied());
+    }
+
+    activity
+}
+
+/// Unprotected commits signed by someone other than `user`
+///
+/// Rewriting these would drop their signature.
+pub fn find_foreign_signed_commits(
+    graph: &Graph,
+    repo: &dyn crate::legacy::git::Repo,
+    user: &str,
+) -> Vec<git2::Oid> {
+    let mut signed = Vec::new();
+
+    let mut queue = VecDeque::new();
+    queue.push_back(graph.root_id());
+    while let Some(current_id) = queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        if !current.action.is_protected() && is_foreign_signed(repo, current_id, user) {
+            signed.push(current_id);
+        }
+        queue.extend(current.children.iter().copied());
+    }
+
+    signed
+}
+
+/// Unprotected commits signed by `user`
+///
+/// Rewriting these without signing would drop their signature.
+pub fn find_own_signed_commits(
+    graph: &Graph,
+    repo: &dyn crate::legacy::git::Repo,
+    user: &str,
+) -> Vec<git2::Oid> {
+    let mut signed = Vec::new();
+
+    let mut queue = VecDeque::new();
+    queue.push_back(graph.root_id());
+    while let Some(current_id) = queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        if !current.action.is_protected()
+            && repo.signed_by_committer(current_id).as_deref() == Some(user)
+        {
+            signed.push(current_id);
+        }
+        queue.extend(current.children.iter().copied());
+    }
+
+    signed
+}
+
+/// Protect branches with commits signed by someone other than `user`, preserving the signatures
+pub fn protect_foreign_signed_branches(
+    graph: &mut Graph,
+    repo: &dyn crate::legacy::git::Repo,
+    user: &str,
+) -> Vec<String> {
+    let mut signed_branches = Vec::new();
+
+    // Track the unprotected commits leading up to each commit so they can be protected with it
+    let mut queue = VecDeque::new();
+    queue.push_back((graph.root_id(), Vec::new()));
+    while let Some((current_id, mut path)) = queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        if current.action.is_protected() {
+            path.clear();
+        } else {
+            path.push(current_id);
+            if is_foreign_signed(repo, current_id, user) {
+                for path_id in path.drain(..) {
+                    let node = graph.get_mut(path_id).expect("all children exist");
+                    node.action = crate::legacy::graph::Action::Protected;
+                    signed_branches.extend(
+                        node.branches
+                            .iter()
+                            .filter_map(|b| b.local_name().map(String::from)),
+                    );
+                }
+                mark_branch_protected(graph, current_id, &mut signed_branches);
+            }
+        }
+
+        let current = graph.get(current_id).expect("all children exist");
+        queue.extend(
+            current
+                .children
+                .iter()
+                .copied()
+                .map(|child_id| (child_id, path.clone())),
+        );
+    }
+
+    signed_branches.sort_unstable();
+    signed_branches.dedup();
+    signed_branches
+}
+
+fn is_foreign_signed(repo: &dyn crate::legacy::git::Repo, id: git2::Oid, user: &str) -> bool {
+    repo.signed_by_committer(id)
+        .map(|signer| *signer != *user)
+        .unwrap_or(false)
+}
+
 /// Pre-requisites:
 /// - Running `protect_branches`
 ///
@@ -378,7 +686,13 @@ pub fn rebase_development_branches(graph: &mut Graph, new_base_id: git2::Oid) {
 ///
 /// A normal `rebase_development_branches` only looks at development commits.  If `main` is pristine or if the
 /// user has branches on the same commit as `main`, we should also update these to what we pulled.
-pub fn fast_forward_pulled_branches(graph: &mut Graph, pull_start: git2::Oid, pull_end: git2::Oid) {
+/// Branches in `checked_out`, the ones checked out in other worktrees, are left for that worktree.
+pub fn fast_forward_pulled_branches(
+    graph: &mut Graph,
+    pull_start: git2::Oid,
+    pull_end: git2::Oid,
+    checked_out: &BTreeMap<String, std::path::PathBuf>,
+) {
     if pull_start == pull_end {
         return;
     }
@@ -389,7 +703,9 @@ pub fn fast_forward_pulled_branches(graph: &mut Graph, pull_start: git2::Oid, pu
             .expect("all children exist")
             .branches,
     );
-    let (start_branches, end_branches) = branches.into_iter().partition(|b| b.remote.is_some());
+    let (start_branches, end_branches) = branches
+        .into_iter()
+        .partition(|b| b.remote.is_some() || checked_out.contains_key(&b.name));
     graph
         .get_mut(pull_start)
         .expect("all children exist")
@@ -423,8 +739,10 @@ pub fn pushable(graph: &mut Graph) {
                 && current.branches.iter().all(|b| Some(b.id) == b.push_id)
             {
                 cause = Some("already pushed");
-            } else if current.commit.wip_summary().is_some() {
+            } else if !current.ignored && current.commit.wip_summary().is_some() {
                 cause = Some("contains WIP commit");
+            } else if current.draft {
+                cause = Some("is a draft");
             }
 
             if !current.branches.is_empty() {
@@ -538,6 +856,47 @@ fn drop_first_branch_by_tree_id(
     }
 }
 
+/// Drop commits whose changes are among the pulled IDs, going by [`crate::git::patch_id`]
+///
+/// Unlike [`drop_squashed_by_tree_id`], each commit is matched on its own, so this still catches
+/// commits that landed upstream on a different tree, like after resolving trivial conflicts.
+/// Commits that look like reverts are left alone.
+pub fn drop_by_patch_id(
+    graph: &mut Graph,
+    repo: &git2::Repository,
+    pulled_ids: impl Iterator<Item = git2::Oid>,
+) {
+    let pulled_patch_ids: HashSet<_> = pulled_ids
+        .filter_map(|id| crate::git::patch_id(repo, id).ok().flatten())
+        .collect();
+    if pulled_patch_ids.is_empty() {
+        return;
+    }
+
+    let ids: Vec<_> = graph.breadth_first_iter().map(|n| n.commit.id).collect();
+    for id in ids {
+        let node = graph.get_mut(id).expect("all children exist");
+        if node.action.is_protected() || node.action.is_delete() || node.anchor {
+            continue;
+        }
+        if node.commit.revert_summary().is_some() {
+            continue;
+        }
+        let patch_id = match crate::git::patch_id(repo, id) {
+            Ok(Some(patch_id)) => patch_id,
+            Ok(None) => continue,
+            Err(err) => {
+                log::debug!("Could not diff {}: {}", id, err);
+                continue;
+            }
+        };
+        if pulled_patch_ids.contains(&patch_id) {
+            log::trace!("Dropping {}, its changes were pulled", id);
+            node.action = crate::legacy::graph::Action::Delete;
+        }
+    }
+}
+
 /// Drop branches merged among the pulled IDs
 ///
 /// The removal in `graph` is purely superficial since nothing can act on it.  The returned branch
@@ -577,7 +936,22 @@ pub fn drop_merged_branches(
 }
 
 pub fn fixup(graph: &mut Graph, effect: crate::config::Fixup) {
-    if effect == crate::config::Fixup::Ignore {
+    fixup_branches(graph, effect, &BTreeMap::new());
+}
+
+/// Like [`fixup`], with `overrides` picking the effect for the fixups in particular branches
+///
+/// A fixup commit is in the nearest branch at or above it.
+pub fn fixup_branches(
+    graph: &mut Graph,
+    effect: crate::config::Fixup,
+    overrides: &BTreeMap<String, crate::config::Fixup>,
+) {
+    if effect == crate::config::Fixup::Ignore
+        && overrides
+            .values()
+            .all(|e| *e == crate::config::Fixup::Ignore)
+    {
         return;
     }
 
@@ -594,43 +968,51 @@ pub fn fixup(graph: &mut Graph, effect: crate::config::Fixup) {
             .clone();
 
         for child_id in current_children {
-            let child_action = graph.get(child_id).expect("all children exist").action;
-            if child_action.is_protected() || child_action.is_delete() {
+            let child = graph.get(child_id).expect("all children exist");
+            if child.action.is_protected() || child.action.is_delete() || child.anchor {
+                // Fixups aren't moved across an anchor, so each side is handled on its own
                 protected_queue.push_back(child_id);
             } else {
-                fixup_branch(graph, current_id, child_id, effect);
+                let anchors = fixup_branch(graph, current_id, child_id, effect, overrides);
+                protected_queue.extend(anchors);
             }
         }
     }
 }
 
+/// Fixup commits waiting for their target, by the summary they fix up, with each one's effect
+type Outstanding = BTreeMap<bstr::BString, Vec<(git2::Oid, crate::config::Fixup)>>;
+
+/// Returns the anchors found, to be handled as the base of their own branches
 fn fixup_branch(
     graph: &mut Graph,
     base_id: git2::Oid,
     mut node_id: git2::Oid,
     effect: crate::config::Fixup,
-) {
-    debug_assert_ne!(effect, crate::config::Fixup::Ignore);
-
-    let mut outstanding = BTreeMap::new();
+    overrides: &BTreeMap<String, crate::config::Fixup>,
+) -> Vec<git2::Oid> {
+    let mut outstanding = Outstanding::new();
+    let mut anchors = Vec::new();
     let node_children = graph
         .get(node_id)
         .expect("all children exist")
         .children
         .clone();
     for child_id in node_children {
-        fixup_node(graph, node_id, child_id, effect, &mut outstanding);
+        fixup_node(
+            graph,
+            node_id,
+            child_id,
+            effect,
+            overrides,
+            &mut outstanding,
+            &mut anchors,
+        );
     }
     if !outstanding.is_empty() {
         let node = graph.get_mut(node_id).expect("all children exist");
-        if let Some(fixup_ids) = outstanding.remove(&node.commit.summary) {
-            if effect == crate::config::Fixup::Squash {
-                for fixup_id in fixup_ids.iter().copied() {
-                    let fixup = graph.get_mut(fixup_id).expect("all children exist");
-                    assert!(fixup.action == crate::legacy::graph::Action::Pick);
-                    fixup.action = crate::legacy::graph::Action::Fixup;
-                }
-            }
+        if let Some(fixups) = outstanding.remove(&node.commit.summary) {
+            let fixup_ids = apply_fixup_effects(graph, fixups);
             splice_after(graph, node_id, fixup_ids);
         }
         debug_assert_ne!(
@@ -638,54 +1020,83 @@ fn fixup_branch(
             crate::legacy::graph::Action::Protected,
             "Unexpected result for {base_id}"
         );
-        for fixup_ids in outstanding.into_values() {
+        for fixups in outstanding.into_values() {
+            let fixup_ids = apply_fixup_effects(graph, fixups);
             node_id = splice_between(graph, base_id, node_id, fixup_ids);
         }
     }
+    anchors
 }
 
+/// Returns the effect for the nearest branch at or above `node_id`, if any
 fn fixup_node(
     graph: &mut Graph,
     base_id: git2::Oid,
     node_id: git2::Oid,
     effect: crate::config::Fixup,
-    outstanding: &mut BTreeMap<bstr::BString, Vec<git2::Oid>>,
-) {
-    debug_assert_ne!(effect, crate::config::Fixup::Ignore);
+    overrides: &BTreeMap<String, crate::config::Fixup>,
+    outstanding: &mut Outstanding,
+    anchors: &mut Vec<git2::Oid>,
+) -> Option<crate::config::Fixup> {
+    if graph.get(node_id).expect("all children exist").anchor {
+        anchors.push(node_id);
+        return None;
+    }
 
     let node_children = graph
         .get(node_id)
         .expect("all children exist")
         .children
         .clone();
+    let mut above = None;
     for child_id in node_children {
-        fixup_node(graph, node_id, child_id, effect, outstanding);
+        let child_effect = fixup_node(
+            graph,
+            node_id,
+            child_id,
+            effect,
+            overrides,
+            outstanding,
+            anchors,
+        );
+        above = above.or(child_effect);
     }
 
     let mut patch = None;
-    let mut fixup_ids = Vec::new();
+    let mut fixups = Vec::new();
+    let node_effect;
     {
         let node = graph.get_mut(node_id).expect("all children exist");
         debug_assert_ne!(node.action, crate::legacy::graph::Action::Protected);
         debug_assert_ne!(node.action, crate::legacy::graph::Action::Delete);
-        if let Some(summary) = node.commit.fixup_summary() {
+        node_effect = node
+            .branches
+            .iter()
+            .find(|b| b.remote.is_none())
+            .map(|b| overrides.get(&b.name).copied().unwrap_or(effect))
+            .or(above);
+        let fixup_summary = (!node.ignored)
+            .then(|| node.commit.fixup_summary())
+            .flatten()
+            .filter(|_| node_effect.unwrap_or(effect) != crate::config::Fixup::Ignore);
+        if let Some(summary) = fixup_summary {
             outstanding
                 .entry(summary.to_owned())
                 .or_default()
-                .push(node_id);
+                .push((node_id, node_effect.unwrap_or(effect)));
 
             let mut children = Default::default();
             std::mem::swap(&mut node.children, &mut children);
             let mut branches = Default::default();
             std::mem::swap(&mut node.branches, &mut branches);
             patch = Some((children, branches));
-        } else if let Some(ids) = outstanding.remove(&node.commit.summary) {
-            fixup_ids = ids;
+        } else if let Some(pending) = outstanding.remove(&node.commit.summary) {
+            fixups = pending;
         }
     }
 
     if let Some((children, branches)) = patch {
-        debug_assert!(fixup_ids.is_empty());
+        debug_assert!(fixups.is_empty());
 
         let base = graph.get_mut(base_id).expect("all children exist");
         debug_assert_ne!(base.action, crate::legacy::graph::Action::Protected);
@@ -693,16 +1104,31 @@ fn fixup_node(
         base.children.remove(&node_id);
         base.children.extend(children);
         base.branches.extend(branches);
-    } else if !fixup_ids.is_empty() {
-        if effect == crate::config::Fixup::Squash {
-            for fixup_id in fixup_ids.iter().copied() {
+    } else if !fixups.is_empty() {
+        let fixup_ids = apply_fixup_effects(graph, fixups);
+        splice_after(graph, node_id, fixup_ids);
+    }
+
+    node_effect
+}
+
+/// Mark the fixups to be squashed, returning the ids to splice in
+fn apply_fixup_effects(
+    graph: &mut Graph,
+    fixups: Vec<(git2::Oid, crate::config::Fixup)>,
+) -> Vec<git2::Oid> {
+    fixups
+        .into_iter()
+        .map(|(fixup_id, effect)| {
+            debug_assert_ne!(effect, crate::config::Fixup::Ignore);
+            if effect == crate::config::Fixup::Squash {
                 let fixup = graph.get_mut(fixup_id).expect("all children exist");
                 assert!(fixup.action == crate::legacy::graph::Action::Pick);
                 fixup.action = crate::legacy::graph::Action::Fixup;
             }
-        }
-        splice_after(graph, node_id, fixup_ids);
-    }
+            fixup_id
+        })
+        .collect()
 }
 
 // Does not update references
@@ -782,10 +1208,21 @@ pub fn realign_stacks(graph: &mut Graph) {
 
 fn realign_stack(graph: &mut Graph, node_id: git2::Oid) {
     let mut children = std::collections::BTreeSet::new();
+    let mut old_parents = Vec::new();
 
     let mut current_id = node_id;
     loop {
         let current = graph.get_mut(current_id).expect("all children exist");
+        if current_id != node_id && current.anchor {
+            // Moving the other stacks on top would reorder them around the anchor, so put them
+            // back and realign what is past the anchor on its own
+            for (parent_id, child_id) in old_parents {
+                let parent = graph.get_mut(parent_id).expect("all children exist");
+                parent.children.insert(child_id);
+            }
+            realign_stack(graph, current_id);
+            return;
+        }
         if current.branches.is_empty() {
             let mut current_children: Vec<_> = current.children.iter().copied().collect();
             match current_children.len() {
@@ -811,6 +1248,7 @@ fn realign_stack(graph: &mut Graph, node_id: git2::Oid) {
                             current.children.remove(child_id);
                         }
                     }
+                    old_parents.extend(current_children.iter().map(|id| (current_id, *id)));
                     children.extend(current_children);
                     current_id = newest;
                 }
@@ -824,6 +1262,83 @@ fn realign_stack(graph: &mut Graph, node_id: git2::Oid) {
     }
 }
 
+/// Branches that [`realign_stacks`] would move on top of a sibling stack
+///
+/// This leaves the graph untouched so callers can report rather than reorder history.
+pub fn find_misaligned_branches(graph: &Graph) -> Vec<String> {
+    let mut misaligned = Vec::new();
+
+    let mut protected_queue = VecDeque::new();
+    if graph.root().action.is_protected() {
+        protected_queue.push_back(graph.root_id());
+    }
+    while let Some(current_id) = protected_queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        for child_id in current.children.iter().copied() {
+            let child_action = graph.get(child_id).expect("all children exist").action;
+            if child_action.is_protected() || child_action.is_delete() {
+                protected_queue.push_back(child_id);
+            } else {
+                misaligned.extend(misaligned_stack(graph, child_id));
+            }
+        }
+    }
+
+    let mut names: Vec<_> = misaligned
+        .into_iter()
+        .flat_map(|id| descendant_branches(graph, id))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+fn misaligned_stack(graph: &Graph, node_id: git2::Oid) -> Vec<git2::Oid> {
+    let mut misaligned = Vec::new();
+
+    let mut current_id = node_id;
+    loop {
+        let current = graph.get(current_id).expect("all children exist");
+        if current_id != node_id && current.anchor {
+            // Mirror `realign_stack` leaving the other stacks in place
+            return misaligned_stack(graph, current_id);
+        }
+        if !current.branches.is_empty() {
+            return misaligned;
+        }
+        let mut current_children: Vec<_> = current.children.iter().copied().collect();
+        match current_children.len() {
+            0 => {
+                return misaligned;
+            }
+            1 => {
+                current_id = current_children.into_iter().next().unwrap();
+            }
+            _ => {
+                // Mirror `realign_stack`'s choice of the newest work as the continuation
+                current_children.sort_unstable_by_key(|id| {
+                    graph.get(*id).expect("all children exist").commit.time
+                });
+                let newest = current_children.pop().unwrap();
+                misaligned.extend(current_children);
+                current_id = newest;
+            }
+        }
+    }
+}
+
+fn descendant_branches(graph: &Graph, node_id: git2::Oid) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(node_id);
+    while let Some(current_id) = queue.pop_front() {
+        let current = graph.get(current_id).expect("all children exist");
+        names.extend(current.branches.iter().map(|b| b.name.clone()));
+        queue.extend(current.children.iter().copied());
+    }
+    names
+}
+
 /// When a rebase has split stack, re-combine them
 pub fn merge_stacks(graph: &mut Graph) {
     let mut protected_queue = VecDeque::new();
@@ -860,6 +1375,69 @@ pub fn merge_stacks(graph: &mut Graph) {
 
 type CommitTimesByTreeId = BTreeMap<git2::Oid, Vec<(std::time::SystemTime, git2::Oid)>>;
 
+/// When commits were rewritten outside of `git-stack`, move what was stacked on the originals
+///
+/// See [`crate::graph::merge_stacks_by_patch_id`]
+pub fn merge_stacks_by_patch_id(graph: &mut Graph, repo: &git2::Repository) {
+    let mut by_patch_id = CommitTimesByTreeId::new();
+    for node in graph.breadth_first_iter() {
+        if node.action.is_protected() || node.action.is_delete() || node.anchor {
+            continue;
+        }
+        let patch_id = match crate::git::patch_id(repo, node.commit.id) {
+            Ok(Some(patch_id)) => patch_id,
+            Ok(None) => continue,
+            Err(err) => {
+                log::debug!("Could not diff {}: {}", node.commit.id, err);
+                continue;
+            }
+        };
+        by_patch_id
+            .entry(patch_id)
+            .or_default()
+            .push((node.commit.time, node.commit.id));
+    }
+
+    for mut commits in by_patch_id.into_values() {
+        if commits.len() < 2 {
+            continue;
+        }
+
+        commits.sort_unstable();
+        let (_, winner_id) = commits.pop().expect("checked len earlier");
+
+        for (_, from_id) in commits {
+            let related = crate::legacy::graph::BreadthFirstIter::new(graph, from_id)
+                .any(|n| n.commit.id == winner_id)
+                || crate::legacy::graph::BreadthFirstIter::new(graph, winner_id)
+                    .any(|n| n.commit.id == from_id);
+            if related {
+                // Re-applied within the same history, like after a revert
+                continue;
+            }
+            let Some(parent_id) = graph
+                .breadth_first_iter()
+                .find(|n| n.children.contains(&from_id))
+                .map(|n| n.commit.id)
+            else {
+                continue;
+            };
+
+            let mut children = Default::default();
+            let mut branches = Default::default();
+            let from = graph.get_mut(from_id).expect("all children exist");
+            std::mem::swap(&mut from.branches, &mut branches);
+            std::mem::swap(&mut from.children, &mut children);
+
+            let winner = graph.get_mut(winner_id).expect("all children exist");
+            winner.branches.extend(branches);
+            winner.children.extend(children);
+
+            graph.remove_child(parent_id, from_id);
+        }
+    }
+}
+
 fn merge_child_stacks(graph: &mut Graph, node_id: git2::Oid, children: CommitTimesByTreeId) {
     let mut queue = VecDeque::new();
     queue.push_back((node_id, children));
@@ -870,6 +1448,15 @@ fn merge_child_stacks(graph: &mut Graph, node_id: git2::Oid, children: CommitTim
                 1 => {
                     enqueue_merge_stack(&mut queue, graph, children[0].1);
                 }
+                _ if children
+                    .iter()
+                    .any(|(_, id)| graph.get(*id).expect("all children exist").anchor) =>
+                {
+                    // An anchor is never dropped in favor of a duplicate
+                    for (_, child_id) in children {
+                        enqueue_merge_stack(&mut queue, graph, child_id);
+                    }
+                }
                 _ => {
                     children.sort_unstable();
                     let last_index = children.len() - 1;
@@ -11,5 +11,6 @@ pub mod config;
 pub mod git;
 pub mod graph;
 pub mod rewrite;
+pub mod stack;
 
 pub mod legacy;
@@ -0,0 +1,273 @@
+use super::Command;
+use super::Script;
+
+/// A batch that [`Script::dry_run_check`] found won't apply cleanly
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    batch: usize,
+    commit_id: git2::Oid,
+    branch: Option<String>,
+    blocked: Vec<usize>,
+    dependents: Vec<String>,
+}
+
+impl Conflict {
+    /// The commit that won't cherry-pick
+    pub fn commit_id(&self) -> git2::Oid {
+        self.commit_id
+    }
+
+    /// The branch holding the commit that conflicts
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// Branches stacked on top of the conflict that can't be re-stacked either
+    pub fn dependents(&self) -> &[String] {
+        &self.dependents
+    }
+}
+
+impl Script {
+    /// Find the batches that would conflict, without touching the working tree or any branch
+    ///
+    /// Each batch is replayed with in-memory merges of the trees, so nothing is committed.
+    /// Batches stacked on top of a conflict are reported as its
+    /// [`dependents`][Conflict::dependents] rather than being checked.
+    pub fn dry_run_check(&self, repo: &dyn crate::git::Repo) -> Result<Vec<Conflict>, git2::Error> {
+        let mut conflicts: Vec<Conflict> = Vec::new();
+        // Marks are tracked as the tree they will end up with
+        let mut marks = std::collections::HashMap::new();
+        // Marks of conflicting batches, to the index of the conflict that blocks them
+        let mut blocked_marks: std::collections::HashMap<git2::Oid, usize> = Default::default();
+        for (i, batch) in self.batches.iter().enumerate() {
+            let blocked_by = std::iter::once(&batch.onto_mark)
+                .chain(batch.merge_marks.iter())
+                .find_map(|mark| blocked_marks.get(mark).copied());
+            if let Some(conflict_index) = blocked_by {
+                let conflict = &mut conflicts[conflict_index];
+                conflict.blocked.push(i);
+                conflict
+                    .dependents
+                    .extend(batch.branch().map(ToOwned::to_owned));
+                for mark in &batch.marks {
+                    blocked_marks.insert(*mark, conflict_index);
+                }
+                continue;
+            }
+
+            let onto_mark = batch.onto_mark();
+            let mut tree_id = match marks.get(&onto_mark) {
+                Some(tree_id) => *tree_id,
+                None => find_tree_id(repo, onto_mark)?,
+            };
+            let mut commands = batch.commands.values().flatten();
+            let mut conflict_id = None;
+            for command in commands.by_ref() {
+                match command {
+                    Command::RegisterMark(mark_oid) => {
+                        marks.insert(*mark_oid, tree_id);
+                    }
+                    Command::CherryPick(cherry_oid)
+                    | Command::Merge(cherry_oid, _)
+                    | Command::Fixup(cherry_oid) => {
+                        match repo.apply_to_tree(tree_id, *cherry_oid)? {
+                            Some(updated_id) => {
+                                tree_id = updated_id;
+                            }
+                            None => {
+                                conflict_id = Some(*cherry_oid);
+                                break;
+                            }
+                        }
+                    }
+                    Command::Reword(_) | Command::CreateBranch(_) | Command::DeleteBranch(_) => {}
+                }
+            }
+
+            if let Some(commit_id) = conflict_id {
+                // The first branch after the conflict holds the commit, the rest are stacked on it
+                let mut branches = commands.filter_map(|c| match c {
+                    Command::CreateBranch(name) => Some(name.clone()),
+                    _ => None,
+                });
+                let branch = branches.next();
+                log::trace!(
+                    "`{}` will conflict on {}",
+                    branch.as_deref().unwrap_or("detached"),
+                    commit_id
+                );
+                for mark in &batch.marks {
+                    blocked_marks.insert(*mark, conflicts.len());
+                }
+                conflicts.push(Conflict {
+                    batch: i,
+                    commit_id,
+                    branch,
+                    blocked: Vec::new(),
+                    dependents: branches.collect(),
+                });
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Existing commits that applying the script replaces with new ones or squashes away
+    ///
+    /// Commits that are only replayed onto their current parent keep their id and aren't included.
+    pub fn rewritten_commits(
+        &self,
+        repo: &dyn crate::git::Repo,
+    ) -> Result<Vec<git2::Oid>, git2::Error> {
+        let mut rewritten = Vec::new();
+        // Marks are tracked as the existing commit they will still be, if unchanged
+        let mut marks: std::collections::HashMap<git2::Oid, Option<git2::Oid>> = Default::default();
+        let resolve = |marks: &std::collections::HashMap<_, Option<git2::Oid>>, mark| {
+            marks.get(&mark).copied().unwrap_or(Some(mark))
+        };
+        for batch in &self.batches {
+            let mut head_id = resolve(&marks, batch.onto_mark());
+            for command in batch.commands.values().flatten() {
+                match command {
+                    Command::RegisterMark(mark_oid) => {
+                        marks.insert(*mark_oid, head_id);
+                    }
+                    Command::CherryPick(cherry_oid) => {
+                        let parent_ids = repo.parent_ids(*cherry_oid)?;
+                        if head_id.is_some() && head_id == parent_ids.first().copied() {
+                            head_id = Some(*cherry_oid);
+                        } else {
+                            rewritten.push(*cherry_oid);
+                            head_id = None;
+                        }
+                    }
+                    Command::Merge(merge_oid, merge_marks) => {
+                        let parent_ids = repo.parent_ids(*merge_oid)?;
+                        let current_ids = std::iter::once(head_id)
+                            .chain(merge_marks.iter().map(|m| resolve(&marks, *m)))
+                            .collect::<Option<Vec<_>>>();
+                        if current_ids.as_ref() == Some(&parent_ids) {
+                            head_id = Some(*merge_oid);
+                        } else {
+                            rewritten.push(*merge_oid);
+                            head_id = None;
+                        }
+                    }
+                    Command::Reword(_) => {
+                        rewritten.extend(head_id);
+                        head_id = None;
+                    }
+                    Command::Fixup(squash_oid) => {
+                        rewritten.extend(head_id);
+                        rewritten.push(*squash_oid);
+                        head_id = None;
+                    }
+                    Command::CreateBranch(_) | Command::DeleteBranch(_) => {}
+                }
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        rewritten.retain(|id| seen.insert(*id));
+        Ok(rewritten)
+    }
+
+    /// Leave out the batches that would recreate their commits and branches as they already are
+    ///
+    /// A batch is up to date when each commit would be replayed onto its current parent and each
+    /// branch already points where it would be created.  Later batches stacked on a skipped one
+    /// are unaffected, as its marks resolve to the existing commits.
+    ///
+    /// Returns the branches of the skipped batches.
+    pub fn skip_up_to_date(
+        &mut self,
+        repo: &dyn crate::git::Repo,
+    ) -> Result<Vec<String>, git2::Error> {
+        let mut skipped = Vec::new();
+        // Marks are tracked as the existing commit they will still be, if unchanged
+        let mut marks: std::collections::HashMap<git2::Oid, Option<git2::Oid>> = Default::default();
+        let resolve = |marks: &std::collections::HashMap<_, Option<git2::Oid>>, mark| {
+            marks.get(&mark).copied().unwrap_or(Some(mark))
+        };
+        let mut up_to_date = Vec::with_capacity(self.batches.len());
+        for batch in &self.batches {
+            let mut head_id = resolve(&marks, batch.onto_mark());
+            let mut unchanged = true;
+            for command in batch.commands.values().flatten() {
+                match command {
+                    Command::RegisterMark(mark_oid) => {
+                        unchanged &= head_id == Some(*mark_oid);
+                        marks.insert(*mark_oid, head_id);
+                    }
+                    Command::CherryPick(cherry_oid) => {
+                        let parent_ids = repo.parent_ids(*cherry_oid)?;
+                        if head_id.is_some() && head_id == parent_ids.first().copied() {
+                            head_id = Some(*cherry_oid);
+                        } else {
+                            head_id = None;
+                        }
+                    }
+                    Command::Merge(merge_oid, merge_marks) => {
+                        let parent_ids = repo.parent_ids(*merge_oid)?;
+                        let current_ids = std::iter::once(head_id)
+                            .chain(merge_marks.iter().map(|m| resolve(&marks, *m)))
+                            .collect::<Option<Vec<_>>>();
+                        if current_ids.as_ref() == Some(&parent_ids) {
+                            head_id = Some(*merge_oid);
+                        } else {
+                            head_id = None;
+                        }
+                    }
+                    Command::Reword(_) | Command::Fixup(_) | Command::DeleteBranch(_) => {
+                        head_id = None;
+                        unchanged = false;
+                    }
+                    Command::CreateBranch(name) => {
+                        let branch_id = repo.find_local_branch(name).map(|b| b.id);
+                        unchanged &= head_id.is_some() && branch_id == head_id;
+                    }
+                }
+            }
+            unchanged &= head_id.is_some();
+            if unchanged {
+                skipped.extend(batch.commands.values().flatten().filter_map(|c| match c {
+                    Command::CreateBranch(name) => Some(name.clone()),
+                    _ => None,
+                }));
+            }
+            up_to_date.push(unchanged);
+        }
+
+        let mut up_to_date = up_to_date.into_iter();
+        self.batches
+            .retain(|_| !up_to_date.next().expect("one per batch"));
+        Ok(skipped)
+    }
+
+    /// Leave out the batches found by [`Script::dry_run_check`], along with their dependents
+    ///
+    /// Their branches are left where they are so the rest of the script can still apply.
+    pub fn skip_conflicts(&mut self, conflicts: &[Conflict]) {
+        let skipped = conflicts
+            .iter()
+            .flat_map(|c| std::iter::once(c.batch).chain(c.blocked.iter().copied()))
+            .collect::<std::collections::HashSet<_>>();
+        let mut i = 0;
+        self.batches.retain(|_| {
+            let keep = !skipped.contains(&i);
+            i += 1;
+            keep
+        });
+    }
+}
+
+fn find_tree_id(repo: &dyn crate::git::Repo, id: git2::Oid) -> Result<git2::Oid, git2::Error> {
+    repo.find_commit(id).map(|c| c.tree_id).ok_or_else(|| {
+        git2::Error::new(
+            git2::ErrorCode::NotFound,
+            git2::ErrorClass::Reference,
+            format!("could not find commit {id:?}"),
+        )
+    })
+}
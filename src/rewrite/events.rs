@@ -0,0 +1,71 @@
+use super::Batch;
+use super::Labels;
+use super::Script;
+
+/// Callbacks for the progress of an [`Executor`][super::Executor]
+///
+/// Register them with [`Executor::register`][super::Executor::register] to report progress,
+/// send notifications, or collect metrics without changing how the rewrite is carried out.  Every
+/// callback defaults to doing nothing.
+pub trait Events {
+    /// A script is about to be run
+    fn on_plan_ready(&mut self, _script: &Script) {}
+
+    /// A batch, re-stacking one branch, is about to be applied
+    fn on_batch_start(&mut self, _batch: &Batch, _labels: &dyn Labels) {}
+
+    /// A batch couldn't be applied
+    ///
+    /// Unless the rewrite stopped for the user to resolve the conflict, the batch and those
+    /// depending on it are left as they were.
+    fn on_conflict(&mut self, _failure: &BatchFailure<'_>) {}
+
+    /// Branches were moved, created, or deleted
+    fn on_refs_updated(&mut self, _updates: &[RefUpdate]) {}
+}
+
+/// A batch that failed to apply, see [`Events::on_conflict`]
+#[derive(Debug)]
+pub struct BatchFailure<'e> {
+    /// The branch being re-stacked, `detached` when the batch has none
+    pub branch: &'e str,
+    pub error: &'e git2::Error,
+    /// Branches left in place as they are stacked on `branch`
+    pub dependents: &'e [&'e str],
+    /// Whether the rewrite stopped at the conflict so it can be resolved and resumed
+    pub suspended: bool,
+}
+
+/// A branch changed by the rewrite, see [`Events::on_refs_updated`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub name: String,
+    /// Where the branch now points, `None` when it was deleted
+    pub id: Option<git2::Oid>,
+}
+
+/// Trace the rewrite in the log
+///
+/// Registered with every [`Executor`][super::Executor].
+#[derive(Copy, Clone, Default, Debug)]
+pub struct LogEvents;
+
+impl Events for LogEvents {
+    fn on_batch_start(&mut self, batch: &Batch, labels: &dyn Labels) {
+        log::trace!("Applying `{}`", batch.branch().unwrap_or("detached"));
+        log::trace!("Script:\n{}", batch.display(labels));
+    }
+
+    fn on_conflict(&mut self, failure: &BatchFailure<'_>) {
+        log::trace!("         `{}` failed: {}", failure.branch, failure.error);
+    }
+
+    fn on_refs_updated(&mut self, updates: &[RefUpdate]) {
+        for update in updates {
+            match update.id {
+                Some(id) => log::trace!("Updated {} to {}", update.name, id),
+                None => log::trace!("Deleted {}", update.name),
+            }
+        }
+    }
+}
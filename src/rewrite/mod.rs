@@ -1,4 +1,16 @@
-#[derive(Clone, Default, Debug)]
+mod check;
+mod events;
+mod resume;
+mod todo;
+
+pub use check::*;
+pub use events::*;
+pub(crate) use resume::oid;
+pub(crate) use resume::Id;
+pub use resume::Resume;
+pub use todo::*;
+
+#[derive(Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Script {
     batches: Vec<Batch>,
 }
@@ -8,6 +20,10 @@ impl Script {
         Default::default()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
     pub fn is_branch_deleted(&self, name: &str) -> bool {
         self.batches
             .iter()
@@ -37,7 +53,7 @@ impl Script {
         let expected_marks = self
             .batches
             .iter()
-            .map(|b| b.onto_mark())
+            .flat_map(|b| std::iter::once(b.onto_mark()).chain(b.merge_marks.iter().copied()))
             .collect::<Vec<_>>();
         for expected_mark in expected_marks {
             for batch in &mut self.batches {
@@ -107,11 +123,13 @@ impl std::fmt::Display for ScriptDisplay<'_> {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "resume::BatchRepr", into = "resume::BatchRepr")]
 pub struct Batch {
     onto_mark: git2::Oid,
     commands: indexmap::IndexMap<git2::Oid, indexmap::IndexSet<Command>>,
     marks: indexmap::IndexSet<git2::Oid>,
+    merge_marks: indexmap::IndexSet<git2::Oid>,
 }
 
 impl Batch {
@@ -120,6 +138,7 @@ impl Batch {
             onto_mark,
             commands: Default::default(),
             marks: Default::default(),
+            merge_marks: Default::default(),
         }
     }
 
@@ -147,6 +166,9 @@ impl Batch {
         if let Command::RegisterMark(mark) = command {
             self.marks.insert(mark);
         }
+        if let Command::Merge(_, parents) = &command {
+            self.merge_marks.extend(parents.iter().copied());
+        }
         self.commands.entry(id).or_default().insert(command);
         if let Some((last_key, _)) = self.commands.last() {
             assert_eq!(*last_key, id, "gaps aren't allowed between ids");
@@ -184,6 +206,11 @@ fn gen_graph(batches: &[Batch]) -> petgraph::graphmap::DiGraphMap<(git2::Oid, bo
         for mark in &batch.marks {
             graph.add_edge((batch.id(), true), (*mark, false), 0);
         }
+        for mark in &batch.merge_marks {
+            if !batch.commands.contains_key(mark) {
+                graph.add_edge((*mark, false), (batch.id(), true), 0);
+            }
+        }
     }
     graph
 }
@@ -226,6 +253,14 @@ impl std::fmt::Display for BatchDisplay<'_> {
                     Command::CherryPick(cherry_oid) => {
                         writeln!(f, "pick {cherry_oid}")?;
                     }
+                    Command::Merge(merge_oid, parents) => {
+                        write!(f, "merge -C {merge_oid}")?;
+                        for parent in parents {
+                            let label = self.labels.get(*parent);
+                            write!(f, " {label}")?;
+                        }
+                        writeln!(f)?;
+                    }
                     Command::Reword(_msg) => {
                         writeln!(f, "reword")?;
                     }
@@ -316,16 +351,23 @@ impl Labels for OidLabels {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
 pub enum Command {
     /// Mark the current commit with an `Oid` for future reference
-    RegisterMark(git2::Oid),
+    RegisterMark(#[serde(with = "resume::oid")] git2::Oid),
     /// Cherry-pick an existing commit
-    CherryPick(git2::Oid),
+    CherryPick(#[serde(with = "resume::oid")] git2::Oid),
+    /// Re-create a merge commit on the current commit, merging in the marked parents
+    Merge(
+        #[serde(with = "resume::oid")] git2::Oid,
+        #[serde(with = "resume::oid::vec")] Vec<git2::Oid>,
+    ),
     /// Change the wording of a commit message
     Reword(String),
     /// Squash a commit into prior commit, keeping the parent commits identity
-    Fixup(git2::Oid),
+    Fixup(#[serde(with = "resume::oid")] git2::Oid),
     /// Mark a branch for creation at the current commit
     CreateBranch(String),
     /// Mark a branch for deletion
@@ -338,8 +380,15 @@ pub struct Executor {
     delete_branches: Vec<String>,
     post_rewrite: Vec<(git2::Oid, git2::Oid)>,
     head_id: git2::Oid,
+    original_head_id: git2::Oid,
     dry_run: bool,
+    run_hooks: bool,
     detached: bool,
+    resumable: bool,
+    original_branches: Vec<(String, Option<git2::Oid>)>,
+    stopped: Option<(git2::Oid, git2::Oid, Vec<Command>)>,
+    suspended: Option<Resume>,
+    events: Vec<Box<dyn Events>>,
 }
 
 impl Executor {
@@ -350,11 +399,42 @@ impl Executor {
             delete_branches: Default::default(),
             post_rewrite: Default::default(),
             head_id: git2::Oid::zero(),
+            original_head_id: git2::Oid::zero(),
             dry_run,
+            run_hooks: true,
             detached: false,
+            resumable: false,
+            original_branches: Default::default(),
+            stopped: None,
+            suspended: None,
+            events: vec![Box::new(LogEvents)],
+        }
+    }
+
+    /// Report progress to `events`, in addition to those already registered
+    pub fn register(&mut self, events: impl Events + 'static) {
+        self.events.push(Box::new(events));
+    }
+
+    fn emit(&mut self, mut callback: impl FnMut(&mut dyn Events)) {
+        for events in self.events.iter_mut() {
+            callback(events.as_mut());
         }
     }
 
+    /// Skip the `reference-transaction` and `post-rewrite` hooks
+    pub fn set_run_hooks(&mut self, run_hooks: bool) {
+        self.run_hooks = run_hooks;
+    }
+
+    /// Stop at the first conflicting cherry-pick, rather than skipping its batch
+    ///
+    /// The progress is available from [`Executor::take_suspended`] so it can be finished with
+    /// [`Executor::resume`].
+    pub fn set_resumable(&mut self, resumable: bool) {
+        self.resumable = resumable;
+    }
+
     pub fn run<'s>(
         &mut self,
         repo: &mut dyn crate::git::Repo,
@@ -362,7 +442,14 @@ impl Executor {
     ) -> Vec<(git2::Error, &'s str, Vec<&'s str>)> {
         let mut failures = Vec::new();
 
-        self.head_id = repo.head_commit().id;
+        if self.head_id == git2::Oid::zero() {
+            self.head_id = repo.head_commit().id;
+            self.original_head_id = self.head_id;
+        }
+        if self.resumable && !self.dry_run {
+            self.record_branches(repo, script);
+        }
+        self.emit(|events| events.on_plan_ready(script));
 
         let onto_id = script.batches[0].onto_mark();
         let labels = NamedLabels::new();
@@ -375,20 +462,33 @@ impl Executor {
                 continue;
             }
 
-            log::trace!("Applying `{}`", branch_name);
-            log::trace!("Script:\n{}", batch.display(&labels));
+            self.emit(|events| events.on_batch_start(batch, &labels));
             let res = self.stage_batch(repo, batch);
             match res.and_then(|_| self.commit(repo)) {
                 Ok(()) => {
                     log::trace!("         `{}` succeeded", branch_name);
                 }
                 Err(err) => {
-                    log::trace!("         `{}` failed: {}", branch_name, err);
-                    self.abandon();
                     let dependent_branches = script.batches[(i + 1)..]
                         .iter()
                         .filter_map(|b| b.branch())
                         .collect::<Vec<_>>();
+                    let stopped = self.stopped.take();
+                    let failure = BatchFailure {
+                        branch: branch_name,
+                        error: &err,
+                        dependents: &dependent_branches,
+                        suspended: stopped.is_some(),
+                    };
+                    self.emit(|events| events.on_conflict(&failure));
+                    if let Some(stopped) = stopped {
+                        let remaining = Script {
+                            batches: script.batches[(i + 1)..].to_vec(),
+                        };
+                        self.suspend(stopped, batch.branch(), vec![remaining]);
+                        break;
+                    }
+                    self.abandon();
                     failures.push((err, branch_name, dependent_branches));
                 }
             }
@@ -397,6 +497,142 @@ impl Executor {
         failures
     }
 
+    /// Progress of the last [`Executor::run`], if it stopped on a conflict
+    pub fn take_suspended(&mut self) -> Option<Resume> {
+        self.suspended.take()
+    }
+
+    /// Finish a suspended rewrite, with `resolved_id` taking the place of the conflicting commit
+    pub fn resume<'s>(
+        &mut self,
+        repo: &mut dyn crate::git::Repo,
+        resume: &'s Resume,
+        resolved_id: git2::Oid,
+    ) -> Vec<(git2::Error, &'s str, Vec<&'s str>)> {
+        let mut failures = Vec::new();
+
+        self.head_id = resume.head_id.0;
+        self.original_head_id = resume.original_head_id.0;
+        self.run_hooks = resume.run_hooks;
+        self.detached = true;
+        self.marks = resume.marks.iter().map(|(k, v)| (k.0, v.0)).collect();
+        self.branches = resume
+            .branches
+            .iter()
+            .map(|(id, n)| (id.0, n.clone()))
+            .collect();
+        self.delete_branches = resume.delete_branches.clone();
+        self.post_rewrite = resume
+            .post_rewrite
+            .iter()
+            .map(|(o, n)| (o.0, n.0))
+            .collect();
+        self.original_branches = resume
+            .original_branches
+            .iter()
+            .map(|(n, id)| (n.clone(), id.map(|id| id.0)))
+            .collect();
+
+        let conflict_id = resume.conflict_id.0;
+        let branch_name = resume.branch().unwrap_or("detached");
+        log::trace!("Resuming `{}` at {}", branch_name, resolved_id);
+        self.update_head(conflict_id, resolved_id);
+        self.post_rewrite.push((conflict_id, resolved_id));
+        let commands = resume.commands.iter().collect::<Vec<_>>();
+        let res = self.stage_commands(repo, resolved_id, &commands);
+        if let Err(err) = res.and_then(|_| self.commit(repo)) {
+            let dependent_branches = resume
+                .scripts
+                .iter()
+                .flat_map(|s| s.iter())
+                .filter_map(|b| b.branch())
+                .collect::<Vec<_>>();
+            let stopped = self.stopped.take();
+            let failure = BatchFailure {
+                branch: branch_name,
+                error: &err,
+                dependents: &dependent_branches,
+                suspended: stopped.is_some(),
+            };
+            self.emit(|events| events.on_conflict(&failure));
+            if let Some(stopped) = stopped {
+                self.suspend(stopped, resume.branch(), resume.scripts.clone());
+            } else {
+                self.abandon();
+                failures.push((err, branch_name, dependent_branches));
+            }
+            return failures;
+        }
+
+        for (i, script) in resume.scripts.iter().enumerate() {
+            if script.is_empty() {
+                continue;
+            }
+            failures.extend(self.run(repo, script));
+            if let Some(suspended) = self.suspended.as_mut() {
+                suspended
+                    .scripts
+                    .extend(resume.scripts[(i + 1)..].iter().cloned());
+                break;
+            }
+        }
+
+        failures
+    }
+
+    fn record_branches(&mut self, repo: &dyn crate::git::Repo, script: &Script) {
+        for command in script
+            .batches
+            .iter()
+            .flat_map(|b| b.commands.values())
+            .flatten()
+        {
+            if let Command::CreateBranch(name) | Command::DeleteBranch(name) = command {
+                if !self.original_branches.iter().any(|(n, _)| n == name) {
+                    let id = repo.find_local_branch(name).map(|b| b.id);
+                    self.original_branches.push((name.clone(), id));
+                }
+            }
+        }
+    }
+
+    fn suspend(
+        &mut self,
+        (onto_id, conflict_id, commands): (git2::Oid, git2::Oid, Vec<Command>),
+        branch: Option<&str>,
+        scripts: Vec<Script>,
+    ) {
+        use resume::Id;
+
+        self.suspended = Some(Resume {
+            onto_id: Id(onto_id),
+            conflict_id: Id(conflict_id),
+            head_id: Id(self.head_id),
+            original_head_id: Id(self.original_head_id),
+            branch: branch.map(ToOwned::to_owned),
+            restore_branch: None,
+            stash_id: None,
+            run_hooks: self.run_hooks,
+            marks: self.marks.iter().map(|(k, v)| (Id(*k), Id(*v))).collect(),
+            branches: std::mem::take(&mut self.branches)
+                .into_iter()
+                .map(|(id, name)| (Id(id), name))
+                .collect(),
+            delete_branches: std::mem::take(&mut self.delete_branches),
+            post_rewrite: std::mem::take(&mut self.post_rewrite)
+                .into_iter()
+                .map(|(old, new)| (Id(old), Id(new)))
+                .collect(),
+            original_branches: self
+                .original_branches
+                .iter()
+                .map(|(name, id)| (name.clone(), id.map(Id)))
+                .collect(),
+            commands,
+            scripts,
+        });
+    }
+
     fn stage_batch(
         &mut self,
         repo: &mut dyn crate::git::Repo,
@@ -412,86 +648,140 @@ impl Executor {
             )
         })?;
         log::trace!("git checkout {}  # {}", onto_id, commit.summary);
-        let mut head_oid = onto_id;
-        for (_, commands) in &batch.commands {
-            for command in commands {
-                match command {
-                    Command::RegisterMark(mark_oid) => {
-                        let target_oid = head_oid;
-                        self.marks.insert(*mark_oid, target_oid);
-                    }
-                    Command::CherryPick(cherry_oid) => {
-                        let cherry_commit = repo.find_commit(*cherry_oid).ok_or_else(|| {
-                            git2::Error::new(
-                                git2::ErrorCode::NotFound,
-                                git2::ErrorClass::Reference,
-                                format!("could not find commit {cherry_oid:?}"),
-                            )
-                        })?;
-                        log::trace!(
-                            "git cherry-pick {}  # {}",
-                            cherry_oid,
-                            cherry_commit.summary
-                        );
-                        let updated_oid = if self.dry_run {
-                            *cherry_oid
-                        } else {
-                            repo.cherry_pick(head_oid, *cherry_oid)?
-                        };
-                        self.update_head(*cherry_oid, updated_oid);
-                        self.post_rewrite.push((*cherry_oid, updated_oid));
-                        head_oid = updated_oid;
-                    }
-                    Command::Reword(msg) => {
-                        log::trace!("git commit --amend");
-                        let updated_oid = if self.dry_run {
-                            head_oid
-                        } else {
-                            repo.reword(head_oid, msg)?
-                        };
-                        self.update_head(head_oid, updated_oid);
-                        for (_old_oid, new_oid) in &mut self.post_rewrite {
-                            if *new_oid == head_oid {
-                                *new_oid = updated_oid;
+        let commands = batch.commands.values().flatten().collect::<Vec<_>>();
+        self.stage_commands(repo, onto_id, &commands)
+    }
+
+    fn stage_commands(
+        &mut self,
+        repo: &mut dyn crate::git::Repo,
+        mut head_oid: git2::Oid,
+        commands: &[&Command],
+    ) -> Result<(), git2::Error> {
+        for (i, command) in commands.iter().enumerate() {
+            match command {
+                Command::RegisterMark(mark_oid) => {
+                    let target_oid = head_oid;
+                    self.marks.insert(*mark_oid, target_oid);
+                }
+                Command::CherryPick(cherry_oid) => {
+                    let cherry_commit = repo.find_commit(*cherry_oid).ok_or_else(|| {
+                        git2::Error::new(
+                            git2::ErrorCode::NotFound,
+                            git2::ErrorClass::Reference,
+                            format!("could not find commit {cherry_oid:?}"),
+                        )
+                    })?;
+                    log::trace!(
+                        "git cherry-pick {}  # {}",
+                        cherry_oid,
+                        cherry_commit.summary
+                    );
+                    let updated_oid = if self.dry_run {
+                        *cherry_oid
+                    } else {
+                        match repo.cherry_pick(head_oid, *cherry_oid) {
+                            Ok(updated_oid) => updated_oid,
+                            Err(err) => {
+                                if self.resumable && err.code() == git2::ErrorCode::Unmerged {
+                                    let remaining =
+                                        commands[(i + 1)..].iter().map(|c| (*c).clone());
+                                    self.stopped =
+                                        Some((head_oid, *cherry_oid, remaining.collect()));
+                                }
+                                return Err(err);
                             }
                         }
-                        head_oid = updated_oid;
-                    }
-                    Command::Fixup(squash_oid) => {
-                        let cherry_commit = repo.find_commit(*squash_oid).ok_or_else(|| {
-                            git2::Error::new(
-                                git2::ErrorCode::NotFound,
-                                git2::ErrorClass::Reference,
-                                format!("could not find commit {squash_oid:?}"),
-                            )
-                        })?;
-                        log::trace!(
-                            "git merge --squash {}  # {}",
-                            squash_oid,
-                            cherry_commit.summary
-                        );
-                        let updated_oid = if self.dry_run {
-                            *squash_oid
-                        } else {
-                            repo.squash(*squash_oid, head_oid)?
-                        };
-                        self.update_head(head_oid, updated_oid);
-                        self.update_head(*squash_oid, updated_oid);
-                        for (_old_oid, new_oid) in &mut self.post_rewrite {
-                            if *new_oid == head_oid {
-                                *new_oid = updated_oid;
-                            }
+                    };
+                    self.update_head(*cherry_oid, updated_oid);
+                    self.post_rewrite.push((*cherry_oid, updated_oid));
+                    head_oid = updated_oid;
+                }
+                Command::Merge(merge_oid, parents) => {
+                    let merge_commit = repo.find_commit(*merge_oid).ok_or_else(|| {
+                        git2::Error::new(
+                            git2::ErrorCode::NotFound,
+                            git2::ErrorClass::Reference,
+                            format!("could not find commit {merge_oid:?}"),
+                        )
+                    })?;
+                    let parent_oids = parents
+                        .iter()
+                        .map(|p| self.marks.get(p).copied().unwrap_or(*p))
+                        .collect::<Vec<_>>();
+                    log::trace!(
+                        "git merge -C {} {}  # {}",
+                        merge_oid,
+                        parent_oids
+                            .iter()
+                            .map(|p| p.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        merge_commit.summary
+                    );
+                    let updated_oid = if self.dry_run {
+                        *merge_oid
+                    } else {
+                        repo.merge(head_oid, *merge_oid, &parent_oids)?
+                    };
+                    self.update_head(*merge_oid, updated_oid);
+                    self.post_rewrite.push((*merge_oid, updated_oid));
+                    head_oid = updated_oid;
+                }
+                Command::Reword(msg) => {
+                    log::trace!("git commit --amend");
+                    let updated_oid = if self.dry_run {
+                        head_oid
+                    } else {
+                        repo.reword(head_oid, msg)?
+                    };
+                    self.update_head(head_oid, updated_oid);
+                    for (_old_oid, new_oid) in &mut self.post_rewrite {
+                        if *new_oid == head_oid {
+                            *new_oid = updated_oid;
                         }
-                        self.post_rewrite.push((*squash_oid, updated_oid));
-                        head_oid = updated_oid;
                     }
-                    Command::CreateBranch(name) => {
-                        let branch_oid = head_oid;
-                        self.branches.push((branch_oid, name.to_owned()));
+                    head_oid = updated_oid;
+                }
+                Command::Fixup(squash_oid) => {
+                    let cherry_commit = repo.find_commit(*squash_oid).ok_or_else(|| {
+                        git2::Error::new(
+                            git2::ErrorCode::NotFound,
+                            git2::ErrorClass::Reference,
+                            format!("could not find commit {squash_oid:?}"),
+                        )
+                    })?;
+                    log::trace!(
+                        "git merge --squash {}  # {}",
+                        squash_oid,
+                        cherry_commit.summary
+                    );
+                    let updated_oid = if self.dry_run {
+                        *squash_oid
+                    } else {
+                        repo.squash(*squash_oid, head_oid)?
+                    };
+                    self.update_head(head_oid, updated_oid);
+                    self.update_head(*squash_oid, updated_oid);
+                    for (_old_oid, new_oid) in &mut self.post_rewrite {
+                        if *new_oid == head_oid {
+                            *new_oid = updated_oid;
+                        }
                     }
-                    Command::DeleteBranch(name) => {
-                        self.delete_branches.push(name.to_owned());
+                    self.post_rewrite.push((*squash_oid, updated_oid));
+                    head_oid = updated_oid;
+                }
+                Command::CreateBranch(name) => {
+                    // Branches that already exist are only re-pointed, even if they clash by case
+                    if repo.find_local_branch(name).is_none() {
+                        let existing: Vec<_> = repo.local_branches().map(|b| b.name).collect();
+                        crate::git::check_branch_case(name, existing.iter().map(|n| n.as_str()))?;
                     }
+                    let branch_oid = head_oid;
+                    self.branches.push((branch_oid, name.to_owned()));
+                }
+                Command::DeleteBranch(name) => {
+                    self.delete_branches.push(name.to_owned());
                 }
             }
         }
@@ -508,13 +798,10 @@ impl Executor {
 
     pub fn commit(&mut self, repo: &mut dyn crate::git::Repo) -> Result<(), git2::Error> {
         let hook_repo = repo.path().map(git2::Repository::open).transpose()?;
-        let hooks = if self.dry_run {
+        let hooks = if self.dry_run || !self.run_hooks {
             None
         } else {
-            hook_repo
-                .as_ref()
-                .map(git2_ext::hooks::Hooks::with_repo)
-                .transpose()?
+            hook_repo.as_ref().map(crate::git::hooks).transpose()?
         };
 
         log::trace!("Running reference-transaction hook");
@@ -545,6 +832,7 @@ impl Executor {
                 None
             };
 
+        let mut updates = Vec::new();
         if !self.branches.is_empty() || !self.delete_branches.is_empty() {
             // In case we are changing the branch HEAD is attached to
             if !self.dry_run {
@@ -558,6 +846,10 @@ impl Executor {
                 log::trace!("git switch --force-create {}", name);
                 if !self.dry_run {
                     repo.branch(name, *oid)?;
+                    updates.push(RefUpdate {
+                        name: name.clone(),
+                        id: Some(*oid),
+                    });
                 }
             }
         }
@@ -567,6 +859,10 @@ impl Executor {
             log::trace!("git branch -D {}", name);
             if !self.dry_run {
                 repo.delete_branch(name)?;
+                updates.push(RefUpdate {
+                    name: name.clone(),
+                    id: None,
+                });
             }
         }
         self.delete_branches.clear();
@@ -574,6 +870,9 @@ impl Executor {
         if let Some(tx) = reference_transaction {
             tx.committed();
         }
+        if !updates.is_empty() {
+            self.emit(|events| events.on_refs_updated(&updates));
+        }
         self.post_rewrite.retain(|(old, new)| old != new);
         if !self.post_rewrite.is_empty() {
             log::trace!("Running post-rewrite hook");
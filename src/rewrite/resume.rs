@@ -0,0 +1,220 @@
+use super::Batch;
+use super::Command;
+use super::Script;
+
+/// Progress of an [`Executor`][super::Executor] that stopped on a conflicting cherry-pick
+///
+/// Everything needed to finish the rewrite once the user resolves the conflict, or to put the
+/// branches back the way they were.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Resume {
+    pub(super) onto_id: Id,
+    pub(super) conflict_id: Id,
+    pub(super) head_id: Id,
+    pub(super) original_head_id: Id,
+    pub(super) branch: Option<String>,
+    pub(super) restore_branch: Option<String>,
+    pub(super) stash_id: Option<Id>,
+    pub(super) run_hooks: bool,
+    pub(super) marks: Vec<(Id, Id)>,
+    pub(super) branches: Vec<(Id, String)>,
+    pub(super) delete_branches: Vec<String>,
+    pub(super) post_rewrite: Vec<(Id, Id)>,
+    pub(super) original_branches: Vec<(String, Option<Id>)>,
+    pub(super) commands: Vec<Command>,
+    pub(super) scripts: Vec<Script>,
+}
+
+impl Resume {
+    /// Where the state is kept within `git_dir`
+    pub fn path(git_dir: &std::path::Path) -> std::path::PathBuf {
+        git_dir.join("git-stack").join("resume.json")
+    }
+
+    /// Read the state left by a stopped rewrite, if any
+    pub fn load(git_dir: &std::path::Path) -> eyre::Result<Option<Self>> {
+        let path = Self::path(git_dir);
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(eyre::eyre!("could not read `{}`: {}", path.display(), err));
+            }
+        };
+        let resume = serde_json::from_str(&data)
+            .map_err(|err| eyre::eyre!("could not parse `{}`: {}", path.display(), err))?;
+        Ok(Some(resume))
+    }
+
+    pub fn save(&self, git_dir: &std::path::Path) -> eyre::Result<()> {
+        let path = Self::path(git_dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, data)
+            .map_err(|err| eyre::eyre!("could not write `{}`: {}", path.display(), err))?;
+        Ok(())
+    }
+
+    /// Forget the state once the rewrite is finished or aborted
+    pub fn clear(git_dir: &std::path::Path) -> eyre::Result<()> {
+        let path = Self::path(git_dir);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(eyre::eyre!(
+                "could not remove `{}`: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    /// The commit the conflicting cherry-pick was applied onto
+    pub fn onto_id(&self) -> git2::Oid {
+        self.onto_id.0
+    }
+
+    /// The commit that failed to cherry-pick
+    pub fn conflict_id(&self) -> git2::Oid {
+        self.conflict_id.0
+    }
+
+    /// The branch being rewritten when the conflict happened
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// What `HEAD` pointed to before the rewrite, as rewritten so far
+    pub fn head_id(&self) -> git2::Oid {
+        self.head_id.0
+    }
+
+    /// What `HEAD` pointed to before the rewrite
+    pub fn original_head_id(&self) -> git2::Oid {
+        self.original_head_id.0
+    }
+
+    /// The branch to switch back to once done
+    pub fn restore_branch(&self) -> Option<&str> {
+        self.restore_branch.as_deref()
+    }
+
+    pub fn set_restore_branch(&mut self, branch: Option<&str>) {
+        self.restore_branch = branch.map(ToOwned::to_owned);
+    }
+
+    /// Uncommitted changes stashed away before the rewrite
+    pub fn stash_id(&self) -> Option<git2::Oid> {
+        self.stash_id.map(|id| id.0)
+    }
+
+    pub fn set_stash_id(&mut self, stash_id: Option<git2::Oid>) {
+        self.stash_id = stash_id.map(Id);
+    }
+
+    /// Where each rewritten branch pointed before the rewrite, `None` if it didn't exist
+    pub fn original_branches(&self) -> impl Iterator<Item = (&str, Option<git2::Oid>)> + '_ {
+        self.original_branches
+            .iter()
+            .map(|(name, id)| (name.as_str(), id.map(|id| id.0)))
+    }
+
+    /// Queue another script to run after the stopped one
+    pub fn push_script(&mut self, script: Script) {
+        self.scripts.push(script);
+    }
+}
+
+/// [`git2::Oid`] as its hex representation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Id(pub(crate) git2::Oid);
+
+impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        git2::Oid::from_str(&s)
+            .map(Id)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+pub(crate) mod oid {
+    use serde::Deserialize as _;
+    use serde::Serialize as _;
+
+    pub(crate) fn serialize<S: serde::Serializer>(
+        id: &git2::Oid,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        super::Id(*id).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<git2::Oid, D::Error> {
+        super::Id::deserialize(deserializer).map(|id| id.0)
+    }
+
+    pub(crate) mod vec {
+        use serde::Deserialize as _;
+        use serde::Serialize as _;
+
+        pub(crate) fn serialize<S: serde::Serializer>(
+            ids: &[git2::Oid],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            ids.iter()
+                .copied()
+                .map(super::super::Id)
+                .collect::<Vec<_>>()
+                .serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<git2::Oid>, D::Error> {
+            let ids = Vec::<super::super::Id>::deserialize(deserializer)?;
+            Ok(ids.into_iter().map(|id| id.0).collect())
+        }
+    }
+}
+
+/// A [`Batch`] as its commands, the marks are re-derived when loading
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct BatchRepr {
+    onto_mark: Id,
+    commands: Vec<(Id, Vec<Command>)>,
+}
+
+impl From<Batch> for BatchRepr {
+    fn from(batch: Batch) -> Self {
+        Self {
+            onto_mark: Id(batch.onto_mark),
+            commands: batch
+                .commands
+                .into_iter()
+                .map(|(id, commands)| (Id(id), commands.into_iter().collect()))
+                .collect(),
+        }
+    }
+}
+
+impl From<BatchRepr> for Batch {
+    fn from(repr: BatchRepr) -> Self {
+        let mut batch = Batch::new(repr.onto_mark.0);
+        for (id, commands) in repr.commands {
+            for command in commands {
+                batch.push(id.0, command);
+            }
+        }
+        batch
+    }
+}
@@ -0,0 +1,297 @@
+use super::Batch;
+use super::Command;
+use super::Script;
+
+/// What a todo list refers to outside of its own labels
+pub trait TodoContext {
+    /// Resolve a commit reference, like an abbreviated id
+    fn resolve(&self, rev: &str) -> Option<git2::Oid>;
+
+    /// One-line description shown after each commit
+    fn summary(&self, id: git2::Oid) -> Option<String>;
+
+    /// Message for a commit marked with `reword`
+    fn reword(&mut self, id: git2::Oid) -> eyre::Result<String>;
+}
+
+impl Script {
+    /// Render as a `git rebase --interactive`-style todo list
+    ///
+    /// Labels are commit ids, so the result can be read back with [`Script::parse_todo`].
+    pub fn todo<'a>(&'a self, context: &'a dyn TodoContext) -> impl std::fmt::Display + 'a {
+        TodoDisplay {
+            script: self,
+            context,
+        }
+    }
+
+    /// Read back a todo list written by [`Script::todo`] after the user edited it
+    pub fn parse_todo(todo: &str, context: &mut dyn TodoContext) -> eyre::Result<Self> {
+        let lines = todo
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| parse_line(line).transpose().map(|l| (i + 1, l)))
+            .map(|(line_num, line)| {
+                line.map(|l| (line_num, l))
+                    .map_err(|err| eyre::eyre!("line {line_num}: {err}"))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        // Labels may be referenced by merges before they are defined
+        let mut labels = std::collections::HashMap::new();
+        let mut head_id = None;
+        for (line_num, line) in &lines {
+            match line {
+                TodoLine::Reset(target) => {
+                    head_id = Some(resolve(&labels, &*context, target, *line_num)?);
+                }
+                TodoLine::Pick(rev, _) | TodoLine::Merge(rev, _, _) | TodoLine::Fixup(rev) => {
+                    head_id = Some(resolve(&labels, &*context, rev, *line_num)?);
+                }
+                TodoLine::Label(label) => {
+                    let head_id = head_id
+                        .ok_or_else(|| eyre::eyre!("line {line_num}: `label` before `reset`"))?;
+                    if labels.insert(*label, head_id).is_some() {
+                        eyre::bail!("line {line_num}: label `{label}` is defined more than once");
+                    }
+                }
+                TodoLine::CreateBranch(_) | TodoLine::DeleteBranch(_) | TodoLine::Drop => {}
+            }
+        }
+
+        let mut batches = Vec::new();
+        let mut batch: Option<Batch> = None;
+        let mut last_id = None;
+        let mut pending = Vec::new();
+        let mut picked = std::collections::HashSet::new();
+        for (line_num, line) in lines {
+            if let TodoLine::Reset(target) = line {
+                if !pending.is_empty() {
+                    eyre::bail!("line {line_num}: branch commands need a commit before them");
+                }
+                batches.extend(batch.take());
+                let onto_id = resolve(&labels, &*context, target, line_num)?;
+                batch = Some(Batch::new(onto_id));
+                last_id = None;
+                continue;
+            }
+            let Some(current) = batch.as_mut() else {
+                if line == TodoLine::Drop {
+                    continue;
+                }
+                eyre::bail!("line {line_num}: expected `reset` first");
+            };
+            match line {
+                TodoLine::Reset(_) => unreachable!("handled above"),
+                TodoLine::Pick(rev, reword) => {
+                    let id = resolve(&labels, &*context, rev, line_num)?;
+                    start_commit(current, &mut pending, &mut picked, id, rev, line_num)?;
+                    last_id = Some(id);
+                    current.push(id, Command::CherryPick(id));
+                    if reword {
+                        current.push(id, Command::Reword(context.reword(id)?));
+                    }
+                }
+                TodoLine::Merge(rev, reword, parents) => {
+                    let id = resolve(&labels, &*context, rev, line_num)?;
+                    let parent_ids = parents
+                        .iter()
+                        .map(|p| resolve(&labels, &*context, p, line_num))
+                        .collect::<eyre::Result<Vec<_>>>()?;
+                    start_commit(current, &mut pending, &mut picked, id, rev, line_num)?;
+                    last_id = Some(id);
+                    current.push(id, Command::Merge(id, parent_ids));
+                    if reword {
+                        current.push(id, Command::Reword(context.reword(id)?));
+                    }
+                }
+                TodoLine::Fixup(rev) => {
+                    let id = resolve(&labels, &*context, rev, line_num)?;
+                    if last_id.is_none() {
+                        eyre::bail!("line {line_num}: `fixup` needs a commit before it");
+                    }
+                    start_commit(current, &mut pending, &mut picked, id, rev, line_num)?;
+                    last_id = Some(id);
+                    current.push(id, Command::Fixup(id));
+                }
+                TodoLine::Label(_) => {
+                    // Labels before any commit are aliases for the `reset` target
+                    if let Some(id) = last_id {
+                        current.push(id, Command::RegisterMark(id));
+                    }
+                }
+                TodoLine::CreateBranch(name) => {
+                    let command = Command::CreateBranch(name.to_owned());
+                    match last_id {
+                        Some(id) => current.push(id, command),
+                        None => pending.push(command),
+                    }
+                }
+                TodoLine::DeleteBranch(name) => {
+                    let command = Command::DeleteBranch(name.to_owned());
+                    match last_id {
+                        Some(id) => current.push(id, command),
+                        None => pending.push(command),
+                    }
+                }
+                TodoLine::Drop => {}
+            }
+        }
+        if !pending.is_empty() {
+            eyre::bail!("branch commands need a commit before them");
+        }
+        batches.extend(batch);
+        batches.retain(|b| !b.is_empty());
+
+        Ok(Self::from(batches))
+    }
+}
+
+struct TodoDisplay<'a> {
+    script: &'a Script,
+    context: &'a dyn TodoContext,
+}
+
+impl TodoDisplay<'_> {
+    fn summary(&self, id: git2::Oid) -> String {
+        self.context
+            .summary(id)
+            .map(|s| format!("  # {s}"))
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for TodoDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, batch) in self.script.batches.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            let onto_id = batch.onto_mark();
+            writeln!(f, "reset {onto_id}{}", self.summary(onto_id))?;
+            for (_, commands) in &batch.commands {
+                let reword = commands.iter().any(|c| matches!(c, Command::Reword(_)));
+                for command in commands {
+                    match command {
+                        Command::RegisterMark(mark_oid) => {
+                            writeln!(f, "label {mark_oid}")?;
+                        }
+                        Command::CherryPick(cherry_oid) => {
+                            let verb = if reword { "reword" } else { "pick" };
+                            writeln!(f, "{verb} {cherry_oid}{}", self.summary(*cherry_oid))?;
+                        }
+                        Command::Merge(merge_oid, parents) => {
+                            let flag = if reword { "-c" } else { "-C" };
+                            write!(f, "merge {flag} {merge_oid}")?;
+                            for parent in parents {
+                                write!(f, " {parent}")?;
+                            }
+                            writeln!(f, "{}", self.summary(*merge_oid))?;
+                        }
+                        Command::Reword(_) => {}
+                        Command::Fixup(squash_oid) => {
+                            writeln!(f, "fixup {squash_oid}{}", self.summary(*squash_oid))?;
+                        }
+                        Command::CreateBranch(name) => {
+                            writeln!(f, "exec git switch --force-create {name}")?;
+                        }
+                        Command::DeleteBranch(name) => {
+                            writeln!(f, "exec git branch -D {name}")?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TodoLine<'t> {
+    Reset(&'t str),
+    Pick(&'t str, bool),
+    Merge(&'t str, bool, Vec<&'t str>),
+    Fixup(&'t str),
+    Label(&'t str),
+    CreateBranch(&'t str),
+    DeleteBranch(&'t str),
+    Drop,
+}
+
+fn parse_line(line: &str) -> eyre::Result<Option<TodoLine<'_>>> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = line.split_once(" #").map(|(l, _)| l).unwrap_or(line);
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(None);
+    };
+    let args = words.collect::<Vec<_>>();
+    let line = match (command, args.as_slice()) {
+        ("reset" | "t", [target]) => TodoLine::Reset(target),
+        ("pick" | "p", [rev]) => TodoLine::Pick(rev, false),
+        ("reword" | "r", [rev]) => TodoLine::Pick(rev, true),
+        ("fixup" | "f", [rev]) => TodoLine::Fixup(rev),
+        ("label" | "l", [label]) => TodoLine::Label(label),
+        ("drop" | "d", [_]) => TodoLine::Drop,
+        ("merge" | "m", [flag @ ("-C" | "-c"), rev, parents @ ..]) if !parents.is_empty() => {
+            TodoLine::Merge(rev, *flag == "-c", parents.to_vec())
+        }
+        ("merge" | "m", _) => {
+            eyre::bail!("expected `merge -C <commit> <label>...`");
+        }
+        ("exec" | "x", ["git", "switch", "--force-create" | "-C", name]) => {
+            TodoLine::CreateBranch(name)
+        }
+        ("exec" | "x", ["git", "branch", "-D", name]) => TodoLine::DeleteBranch(name),
+        ("exec" | "x", _) => {
+            eyre::bail!(
+                "only `exec git switch --force-create <branch>` and `exec git branch -D <branch>` are supported"
+            );
+        }
+        (
+            "reset" | "t" | "pick" | "p" | "reword" | "r" | "fixup" | "f" | "label" | "l" | "drop"
+            | "d",
+            _,
+        ) => {
+            eyre::bail!("`{command}` expects one argument");
+        }
+        (_, _) => {
+            eyre::bail!("unknown command `{command}`");
+        }
+    };
+    Ok(Some(line))
+}
+
+/// Begin the commands for `id`, attaching branch commands that came before it
+fn start_commit(
+    batch: &mut Batch,
+    pending: &mut Vec<Command>,
+    picked: &mut std::collections::HashSet<git2::Oid>,
+    id: git2::Oid,
+    rev: &str,
+    line_num: usize,
+) -> eyre::Result<()> {
+    if !picked.insert(id) {
+        eyre::bail!("line {line_num}: `{rev}` is used more than once");
+    }
+    for command in pending.drain(..) {
+        batch.push(id, command);
+    }
+    Ok(())
+}
+
+fn resolve(
+    labels: &std::collections::HashMap<&str, git2::Oid>,
+    context: &dyn TodoContext,
+    target: &str,
+    line_num: usize,
+) -> eyre::Result<git2::Oid> {
+    labels
+        .get(target)
+        .copied()
+        .or_else(|| context.resolve(target))
+        .ok_or_else(|| eyre::eyre!("line {line_num}: could not find `{target}`"))
+}
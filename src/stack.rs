@@ -0,0 +1,336 @@
+//! Plan stack changes without the `git-stack` command line
+//!
+//! [`Builder`] runs the same planning passes as `git stack --rebase`, including leaving alone the
+//! branches it would (large, stale, foreign, signed by others, or checked out in another
+//! worktree), and returns the
+//! [`rewrite::Script`][crate::rewrite::Script]s to apply, along with a plain-text rendering of the
+//! planned stacks.  Nothing is printed and nothing is changed until [`Plan::execute`].
+//!
+//! ```no_run
+//! let repo = git2::Repository::discover(".")?;
+//! let mut repo = git_stack::git::GitRepo::new(repo);
+//!
+//! let plan = git_stack::stack::Builder::new(&repo)
+//!     .protected_branches(["main"])
+//!     .rebase(true)
+//!     .fixup(git_stack::config::Fixup::Squash)
+//!     .build()?;
+//! println!("{}", plan.tree());
+//!
+//! let mut executor = git_stack::rewrite::Executor::new(false);
+//! let failures = plan.execute(&mut repo, &mut executor, None)?;
+//! for (err, branch, _dependents) in failures {
+//!     eprintln!("could not re-stack `{branch}`: {err}");
+//! }
+//! # Ok::<(), eyre::Error>(())
+//! ```
+
+use std::fmt::Write as _;
+
+/// Plan changes to the stacks on top of `HEAD`'s protected base
+pub struct Builder<'r> {
+    repo: &'r dyn crate::git::Repo,
+    protected_branches: Vec<String>,
+    anchors: crate::git::Anchors,
+    ignored: crate::git::IgnoredCommits,
+    rebase: bool,
+    fixup: crate::config::Fixup,
+    repair: bool,
+    protect_commit_count: Option<usize>,
+    protect_commit_age: std::time::Duration,
+    user: Option<String>,
+    resign: crate::config::Resign,
+    protect_checked_out: bool,
+    force: bool,
+}
+
+impl<'r> Builder<'r> {
+    /// Defaults to leaving the stacks as they are, protecting branches like `git stack` does
+    pub fn new(repo: &'r dyn crate::git::Repo) -> Self {
+        let config = crate::config::RepoConfig::default();
+        Self {
+            repo,
+            protected_branches: Vec::new(),
+            anchors: Default::default(),
+            ignored: Default::default(),
+            rebase: false,
+            fixup: crate::config::Fixup::Ignore,
+            repair: false,
+            protect_commit_count: config.protect_commit_count(),
+            protect_commit_age: config.protect_commit_age(),
+            user: repo.user().map(|u| u.to_string()),
+            resign: config.resign(),
+            protect_checked_out: true,
+            force: false,
+        }
+    }
+
+    /// Globs (`.gitignore` syntax) for branches that must not be rewritten
+    pub fn protected_branches(
+        mut self,
+        globs: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.protected_branches = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Commits to not reorder around, see [`crate::git::Anchors`]
+    pub fn anchors(mut self, anchors: crate::git::Anchors) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Commits exempt from the WIP and fixup heuristics, see [`crate::git::IgnoredCommits`]
+    pub fn ignored(mut self, ignored: crate::git::IgnoredCommits) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Move the development branches onto the latest commit of their protected base
+    pub fn rebase(mut self, yes: bool) -> Self {
+        self.rebase = yes;
+        self
+    }
+
+    /// How to handle `fixup!` commits
+    pub fn fixup(mut self, effect: crate::config::Fixup) -> Self {
+        self.fixup = effect;
+        self
+    }
+
+    /// Re-combine stacks that were split and realign branches on top of each other
+    pub fn repair(mut self, yes: bool) -> Self {
+        self.repair = yes;
+        self
+    }
+
+    /// Leave branches with more commits than this in place, like `stack.protect-commit-count`
+    pub fn protect_commit_count(mut self, max: Option<usize>) -> Self {
+        self.protect_commit_count = max;
+        self
+    }
+
+    /// Leave branches without newer commits in place, like `stack.protect-commit-age`
+    pub fn protect_commit_age(mut self, age: std::time::Duration) -> Self {
+        self.protect_commit_age = age;
+        self
+    }
+
+    /// Leave branches without commits by `user` in place (default: the repo's `user.name`)
+    ///
+    /// `None` treats every branch as yours.
+    pub fn user(mut self, user: Option<impl Into<String>>) -> Self {
+        self.user = user.map(Into::into);
+        self
+    }
+
+    /// How to treat commits signed by others, like `stack.resign`
+    pub fn resign(mut self, resign: crate::config::Resign) -> Self {
+        self.resign = resign;
+        self
+    }
+
+    /// Leave stacks with branches checked out in other worktrees in place
+    pub fn protect_checked_out(mut self, yes: bool) -> Self {
+        self.protect_checked_out = yes;
+        self
+    }
+
+    /// Rewrite tagged commits rather than failing, like `git stack --force`
+    pub fn force(mut self, yes: bool) -> Self {
+        self.force = yes;
+        self
+    }
+
+    pub fn build(self) -> eyre::Result<Plan> {
+        let repo = self.repo;
+        let protected =
+            crate::git::ProtectedBranches::new(self.protected_branches.iter().map(|s| s.as_str()))?;
+        let branches = crate::graph::BranchSet::from_repo(repo, &protected)?;
+
+        let head_id = repo.head_commit().id;
+        let onto = crate::graph::find_protected_base(repo, &branches, head_id);
+        let onto_id = onto
+            .map(|b| b.id())
+            .or_else(|| crate::graph::infer_base(repo, head_id))
+            .unwrap_or(head_id);
+        // The local branch is what the commits were last based on
+        let base_id = onto
+            .filter(|b| b.remote().is_some())
+            .and_then(|b| repo.find_local_branch(b.base_name()))
+            .map(|b| b.id)
+            .unwrap_or(onto_id);
+        let merge_base_id = repo
+            .merge_base(base_id, head_id)
+            .ok_or_else(|| eyre::eyre!("could not find base between {base_id} and HEAD"))?;
+        let branches = branches.descendants(repo, merge_base_id);
+
+        let mut graph = crate::graph::Graph::from_branches(repo, branches)?;
+        crate::graph::protect_branches(&mut graph);
+        crate::graph::mark_anchors(&mut graph, &self.anchors);
+        crate::graph::mark_ignored(&mut graph, &self.ignored);
+        if let Some(max) = self.protect_commit_count {
+            crate::graph::protect_large_branches(&mut graph, max);
+        }
+        let earlier_than = std::time::SystemTime::now() - self.protect_commit_age;
+        crate::graph::protect_stale_branches(&mut graph, repo, earlier_than, &[head_id]);
+        if let Some(user) = self.user.as_deref() {
+            crate::graph::protect_foreign_branches(&mut graph, repo, user, &[]);
+            match self.resign {
+                crate::config::Resign::Strip => {}
+                crate::config::Resign::Block | crate::config::Resign::ResignMine => {
+                    crate::graph::protect_foreign_signed_branches(&mut graph, repo, user);
+                }
+            }
+        }
+        let git_repo = repo.path().map(git2::Repository::open).transpose()?;
+        if let Some(git_repo) = git_repo.as_ref().filter(|_| self.protect_checked_out) {
+            let checked_out = crate::git::branches_checked_out_elsewhere(git_repo)?;
+            crate::graph::protect_checked_out_branches(&mut graph, &checked_out);
+        }
+
+        let mut dropped_branches = Vec::new();
+        if self.rebase {
+            let pull_start_id = repo.merge_base(base_id, onto_id).unwrap_or(onto_id);
+            crate::graph::rebase_development_branches(&mut graph, onto_id);
+            crate::graph::fast_forward_pulled_branches(&mut graph, pull_start_id, onto_id);
+
+            let pull_range = crate::git::commit_range(repo, onto_id..pull_start_id)?
+                .into_iter()
+                .filter_map(|id| repo.find_commit(id))
+                .collect::<Vec<_>>();
+            dropped_branches.extend(crate::graph::delete_squashed_branches_by_tree_id(
+                &mut graph,
+                repo,
+                pull_start_id,
+                pull_range.iter().map(|c| c.tree_id),
+            ));
+            dropped_branches.extend(crate::graph::delete_merged_branches(
+                &mut graph,
+                pull_range.iter().map(|c| c.id),
+            ));
+        }
+        crate::graph::fixup(&mut graph, repo, self.fixup);
+        if self.repair {
+            crate::graph::merge_stacks_by_tree_id(&mut graph, repo);
+            crate::graph::merge_stacks_by_patch_id(&mut graph, repo);
+            crate::graph::realign_stacks(&mut graph, repo);
+        }
+        crate::graph::mark_wip(&mut graph, repo);
+        crate::graph::pushable(&mut graph);
+
+        let tree = render_tree(&graph, repo);
+        let scripts = crate::graph::to_scripts(&graph, dropped_branches);
+        if let Some(git_repo) = git_repo.as_ref().filter(|_| !self.force) {
+            let tags = crate::git::tagged_commits(git_repo)?;
+            for script in &scripts {
+                if let Some(id) = script
+                    .rewritten_commits(repo)?
+                    .into_iter()
+                    .find(|id| tags.contains_key(id))
+                {
+                    eyre::bail!(
+                        "refusing to rewrite {}, tagged {}",
+                        &id.to_string()[..7],
+                        tags[&id].join(", ")
+                    );
+                }
+            }
+        }
+        Ok(Plan {
+            graph,
+            scripts,
+            tree,
+        })
+    }
+}
+
+/// The result of [`Builder::build`]
+pub struct Plan {
+    graph: crate::graph::Graph,
+    scripts: Vec<crate::rewrite::Script>,
+    tree: String,
+}
+
+impl Plan {
+    /// The planned stacks, with the resources the planning passes tagged commits with
+    pub fn graph(&self) -> &crate::graph::Graph {
+        &self.graph
+    }
+
+    pub fn scripts(&self) -> &[crate::rewrite::Script] {
+        &self.scripts
+    }
+
+    pub fn into_scripts(self) -> Vec<crate::rewrite::Script> {
+        self.scripts
+    }
+
+    /// The planned stacks, one commit per line, with each side of a fork indented
+    pub fn tree(&self) -> &str {
+        &self.tree
+    }
+
+    /// Apply each script, switching to `restore_branch` (default: what `HEAD` was) when done
+    ///
+    /// Returns the branches that failed to re-stack along with the branches stacked on them.
+    pub fn execute<'s>(
+        &'s self,
+        repo: &mut dyn crate::git::Repo,
+        executor: &mut crate::rewrite::Executor,
+        restore_branch: Option<&str>,
+    ) -> Result<Vec<Failure<'s>>, git2::Error> {
+        let mut failures = Vec::new();
+        for script in &self.scripts {
+            failures.extend(executor.run(repo, script));
+        }
+        executor.close(repo, restore_branch)?;
+        Ok(failures)
+    }
+}
+
+/// A branch that failed to re-stack, with the error and the branches stacked on it
+pub type Failure<'s> = (git2::Error, &'s str, Vec<&'s str>);
+
+fn render_tree(graph: &crate::graph::Graph, repo: &dyn crate::git::Repo) -> String {
+    let mut tree = String::new();
+    // Each side of a fork is indented, with its first commit marked by `- `
+    let mut pending = vec![(graph.root_id(), 0, false)];
+    while let Some((id, depth, fork)) = pending.pop() {
+        let indent = if fork {
+            format!("{}- ", "  ".repeat(depth - 1))
+        } else {
+            "  ".repeat(depth)
+        };
+        let summary = repo
+            .find_commit(id)
+            .map(|c| c.summary.to_string())
+            .unwrap_or_default();
+        let _ = write!(tree, "{indent}{} {summary}", &id.to_string()[..7]);
+        let mut names = graph
+            .branches
+            .get(id)
+            .unwrap_or_default()
+            .iter()
+            .map(|b| b.display_name().to_string())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            names.sort();
+            let _ = write!(tree, " ({})", names.join(", "));
+        }
+        tree.push('\n');
+
+        let mut children = graph.children_of(id).collect::<Vec<_>>();
+        children.sort_unstable_by_key(|id| (repo.find_commit(*id).map(|c| c.time), *id));
+        let fork = 2 <= children.len();
+        let depth = if fork { depth + 1 } else { depth };
+        // Reversed so they pop off in order
+        pending.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|child_id| (child_id, depth, fork)),
+        );
+    }
+    tree
+}
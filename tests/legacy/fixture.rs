@@ -29,6 +29,7 @@ pub(crate) fn populate_repo(
                     id: commit_id,
                     tree_id: commit_id,
                     summary: bstr::BString::from(summary),
+                    encoding: None,
                     time: std::time::SystemTime::now(),
                     author: Some(std::rc::Rc::from(
                         tree.author.as_deref().unwrap_or("fixture"),
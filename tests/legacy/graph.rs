@@ -360,3 +360,49 @@ fn overflow() {
     assert_eq!(result, vec![]);
     executor.close(&mut repo, "master").unwrap();
 }
+
+#[test]
+fn trim_inactive_matches_separate_passes() {
+    let mut repo = git_stack::legacy::git::InMemoryRepo::new();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    fixture::populate_repo(&mut repo, plan);
+
+    let mut protected_branches = git_stack::legacy::git::Branches::default();
+    protected_branches.insert(repo.find_local_branch("master").unwrap());
+
+    let mut graphed_branches = git_stack::legacy::git::Branches::default();
+    for name in ["master", "off_master", "feature1", "feature2"] {
+        graphed_branches.insert(repo.find_local_branch(name).unwrap());
+    }
+    let head_id = repo.find_local_branch("feature1").unwrap().id;
+
+    let now = std::time::SystemTime::now();
+    let past = now - std::time::Duration::from_secs(60 * 60);
+    let future = now + std::time::Duration::from_secs(60 * 60);
+    for (earlier_than, user) in [
+        (past, Some("fixture")),
+        (past, Some("someone else")),
+        (past, None),
+        (future, Some("someone else")),
+        (future, None),
+    ] {
+        let mut graph = Graph::from_branches(&repo, graphed_branches.clone()).unwrap();
+        protect_branches(&mut graph, &repo, &protected_branches);
+        let mut expected_graph = graph.clone();
+
+        let expected_old = trim_old_branches(&mut expected_graph, earlier_than, &[head_id]);
+        let mut expected_foreign = Vec::new();
+        if let Some(user) = user {
+            expected_foreign = trim_foreign_branches(&mut expected_graph, user, &[head_id]);
+            protect_foreign_branches(&mut expected_graph, user, &[]);
+        }
+
+        let actual = trim_inactive_branches(&mut graph, earlier_than, user, &[head_id]);
+        assert_eq!(actual.old, expected_old, "{user:?}");
+        assert_eq!(actual.foreign, expected_foreign, "{user:?}");
+        let actual_nodes: Vec<_> = graph.breadth_first_iter().cloned().collect();
+        let expected_nodes: Vec<_> = expected_graph.breadth_first_iter().cloned().collect();
+        assert_eq!(actual_nodes, expected_nodes, "{user:?}");
+    }
+}
@@ -0,0 +1,164 @@
+use bstr::ByteSlice;
+use snapbox::assert_data_eq;
+use snapbox::prelude::*;
+use snapbox::str;
+
+fn fixture(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a1\na2\na3\n")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a1\na2\na3\n"), ("b", "b1\nb2\nb3\n")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [
+                    ("a", "a1\na2\na3\n"),
+                    ("b", "b1\nb2\nb3\nc1\nc2\n"),
+                    ("c", "c1\n"),
+                ]
+                .into_iter()
+                .map(|(p, c)| (p.into(), c.into()))
+                .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("C".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    std::fs::write(root_path.join("a"), "a1\nA2\na3\n").unwrap();
+    std::fs::write(root_path.join("b"), "b1\nB2\nb3\nC1\nc2\n").unwrap();
+    std::fs::write(root_path.join("c"), "c1\nc1.5\n").unwrap();
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["a", "b", "c"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+}
+
+fn file_at(repo: &git2::Repository, rev: &str, path: &str) -> String {
+    let tree = repo.revparse_single(rev).unwrap().peel_to_tree().unwrap();
+    let blob = tree
+        .get_path(std::path::Path::new(path))
+        .unwrap()
+        .to_object(repo)
+        .unwrap()
+        .peel_to_blob()
+        .unwrap();
+    blob.content().to_str().unwrap().to_owned()
+}
+
+#[test]
+fn absorb_creates_fixups() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("absorb")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(
+            str![[r#"
+Absorbing 1 hunk into [..]: B
+Absorbing 2 hunks into [..]: C
+WARN: Leaving 1 hunk staged, they don't belong to a single commit in the stack
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let summaries = ["feature", "feature~1", "feature~2", "feature~3"]
+        .map(|rev| {
+            repo.revparse_single(rev)
+                .unwrap()
+                .peel_to_commit()
+                .unwrap()
+                .summary()
+                .unwrap()
+                .to_owned()
+        })
+        .join("\n");
+    assert_data_eq!(
+        summaries,
+        str![[r#"
+fixup! C
+fixup! B
+C
+B
+"#]]
+        .raw()
+    );
+    assert_eq!(file_at(&repo, "feature~1", "b"), "b1\nB2\nb3\nc1\nc2\n");
+    assert_eq!(file_at(&repo, "feature", "b"), "b1\nB2\nb3\nC1\nc2\n");
+    assert_eq!(file_at(&repo, "feature", "c"), "c1\nc1.5\n");
+    assert_eq!(file_at(&repo, "feature", "a"), "a1\na2\na3\n");
+
+    let statuses = repo.statuses(None).unwrap();
+    let staged = statuses
+        .iter()
+        .filter(|s| s.status().is_index_modified())
+        .map(|s| s.path().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(staged, ["a"]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn absorb_and_rebase() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("absorb")
+        .arg("--and-rebase")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(
+            str![[r#"
+Absorbing 1 hunk into [..]: B
+Absorbing 2 hunks into [..]: C
+WARN: Leaving 1 hunk staged, they don't belong to a single commit in the stack
+Saved working directory and index state WIP on feature (absorb): [..]
+Dropped refs/stash [..]
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let feature = repo.revparse_single("feature").unwrap().peel_to_commit().unwrap();
+    assert_eq!(feature.summary(), Some("C"));
+    assert_eq!(feature.parent(0).unwrap().summary(), Some("B"));
+    assert_eq!(file_at(&repo, "feature~1", "b"), "b1\nB2\nb3\n");
+    assert_eq!(file_at(&repo, "feature", "b"), "b1\nB2\nb3\nC1\nc2\n");
+    assert_eq!(file_at(&repo, "feature", "c"), "c1\nc1.5\n");
+    assert_eq!(file_at(&repo, "feature", "a"), "a1\na2\na3\n");
+    assert_eq!(
+        std::fs::read_to_string(root_path.join("a")).unwrap(),
+        "a1\nA2\na3\n"
+    );
+
+    root.close().unwrap();
+}
@@ -365,3 +365,98 @@ note: To register, pass `--register`
 
     root.close().unwrap();
 }
+
+#[test]
+fn user_defined_alias() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+
+    let home_root = root_path.join("home");
+    std::fs::create_dir_all(&home_root).unwrap();
+    std::fs::write(
+        home_root.join(".gitconfig"),
+        "
+[stack \"alias\"]
+  explain = why
+",
+    )
+    .unwrap();
+
+    let repo_root = root_path.join("repo");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a".into(), "a".into())].into_iter().collect(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&repo_root).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.alias.where", "status --porcelain"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["explain", "main"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+main: A
+...
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["alias", "--list"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+explain = why
+where = status --porcelain
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["alias", "--unset", "explain"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Unsetting: stack.alias.explain="why"
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["alias", "--unset", "explain"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+`stack.alias.explain` is not set
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["alias", "--list"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+where = status --porcelain
+
+"#]]);
+
+    root.close().unwrap();
+}
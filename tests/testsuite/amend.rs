@@ -177,7 +177,7 @@ fn reword() {
 Saved working directory and index state WIP on target (amend): [..]
 Amended to [..]: C
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -249,7 +249,7 @@ fn reword_rebases() {
 Saved working directory and index state WIP on local (amend): [..]
 Amended to [..]: B
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -325,7 +325,7 @@ fn amend_add() {
         .stderr_eq(str![[r#"
 Adding c
 Amended to [..]: C
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -396,7 +396,7 @@ fn amend_staged() {
 Saved working directory and index state WIP on target (amend): [..]
 Amended to [..]: C
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -473,7 +473,7 @@ fn amend_detached() {
 Saved working directory and index state WIP on HEAD (amend): [..]
 Amended to [..]: B
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -557,7 +557,7 @@ fn amend_explicit_head() {
 Saved working directory and index state WIP on target (amend): [..]
 Amended to [..]: C
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -635,7 +635,7 @@ fn amend_ancestor() {
 Saved working directory and index state WIP on local (amend): [..]
 Amended to [..]: B
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -722,7 +722,7 @@ ERROR: Failed to re-stack branch `local`: squash conflicts:
   c
 ; class=Index (10); code=Unmerged (-10)
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -748,3 +748,222 @@ note: to undo, run `git branch-stash pop git-stack`
 
     root.close().unwrap();
 }
+
+#[test]
+#[cfg(unix)]
+fn amend_edit_todo() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("target".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b"), ("c", "c")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("C".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("local".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let a_id = repo.find_local_branch("main").unwrap().id;
+    let b_id = repo.find_local_branch("target").unwrap().id;
+    let c_id = repo.find_local_branch("local").unwrap().id;
+
+    // Drop `C` from the plan, keeping everything else
+    let editor_path = root_path.join(".git/editor.sh");
+    std::fs::write(
+        &editor_path,
+        format!(
+            "#!/bin/sh
+cp \"$1\" '{}'
+grep -v '^pick {c_id}' '{}' > \"$1\"
+",
+            root_path.join(".git/old-todo").display(),
+            root_path.join(".git/old-todo").display(),
+        ),
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&editor_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&editor_path, permissions).unwrap();
+
+    std::fs::write(root_path.join("b"), "new b").unwrap();
+    snapbox::cmd::Command::new("git")
+        .arg("add")
+        .arg("b")
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--edit-todo", "target"])
+        .env("GIT_EDITOR", &editor_path)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let old_todo = std::fs::read_to_string(root_path.join(".git/old-todo")).unwrap();
+    let old_todo = old_todo
+        .replace(&a_id.to_string(), "[A]")
+        .replace(&b_id.to_string(), "[B]")
+        .replace(&c_id.to_string(), "[C]");
+    assert_data_eq!(
+        old_todo,
+        str![[r#"
+reset [A]  # A
+pick [B]  # B
+fixup [..]  # fixup! B
+exec git switch --force-create target
+pick [C]  # C
+exec git switch --force-create local
+...
+"#]]
+    );
+
+    let local_branch = repo.find_local_branch("local").unwrap();
+    let local_commit = repo.find_commit(local_branch.id).unwrap();
+    assert_data_eq!(
+        local_commit.summary.to_str_lossy().into_owned(),
+        str!["B"].raw()
+    );
+    assert_eq!(
+        repo.find_local_branch("target").unwrap().id,
+        local_branch.id
+    );
+    assert_data_eq!(
+        std::fs::read(root_path.join("b")).unwrap(),
+        str!["new b"].raw()
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn amend_no_rebase() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b"), ("c", "c")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("C".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("child".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    std::fs::write(root_path.join("b"), "amended b").unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_child_id = repo.find_local_branch("child").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--all", "--no-rebase"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+Adding b
+Amended to [..]: B
+note: `child`, `feature` need restacking, run `git stack --rebase`
+
+"#]]);
+
+    assert_eq!(repo.find_local_branch("child").unwrap().id, old_child_id);
+    assert_eq!(
+        repo.head_commit().summary.to_str().unwrap(),
+        "fixup! B"
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+main (no remote) A
+⌽ [..] B
+├─┐
+│ ⌽ child (needs restack) C
+│ 
+⌽ feature (needs restack) fixup! B
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--rebase")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+main (no remote) A
+⌽ feature (ready) B
+⌽ child C
+
+"#]]);
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let child_id = repo.find_local_branch("child").unwrap().id;
+    let child = repo.raw().find_commit(child_id).unwrap();
+    assert_eq!(child.parent_id(0).unwrap(), feature_id);
+    assert_eq!(repo.head_commit().summary.to_str().unwrap(), "B");
+    assert!(git_stack::git::PendingRestack::from_repo(repo.raw())
+        .unwrap()
+        .is_empty());
+
+    root.close().unwrap();
+}
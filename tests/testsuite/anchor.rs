@@ -0,0 +1,124 @@
+use crate::fixture::{summaries, tree};
+use snapbox::prelude::*;
+use snapbox::str;
+
+fn anchored_fixup(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            tree(&[("a", "a"), ("b", "b2"), ("c", "c")], "fixup! B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+fn list_and_remove() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    anchored_fixup(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "HEAD~1"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Anchored [..]
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+[..] C
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "--remove", "HEAD~1"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Removed anchor [..]
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn fixup_not_moved_across_anchor() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    anchored_fixup(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "HEAD~1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--fixup=squash", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(summaries(root_path), "fixup! B\nC\nB\n");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "--remove", "HEAD~1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--fixup=squash", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(summaries(root_path), "C\nB\n");
+
+    root.close().unwrap();
+}
+
+#[test]
+fn amend_refuses_anchor() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    anchored_fixup(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "HEAD"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--message", "D"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+cannot amend an anchor, run `git stack anchor --remove` first
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,48 @@
+use crate::fixture::tree;
+
+#[test]
+fn annotates_stack_lines() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("f", "one\ntwo\nthree\n")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("f", "one\n2\nthree\n")], "B"),
+            tree(&[("f", "one\n2\nthree\nfour\n")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    std::fs::create_dir_all(root_path.join("sub")).unwrap();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let short = |rev: &str| repo.revparse_single(rev).unwrap().id().to_string()[..7].to_owned();
+    let expected = format!(
+        "\
+upstream 1) one
+{}  2) 2
+upstream 3) three
+{}  4) four
+",
+        short("feature~"),
+        short("feature"),
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["blame", "../f"])
+        .current_dir(root_path.join("sub"))
+        .assert()
+        .success()
+        .stdout_eq(expected);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["blame", "missing"])
+        .current_dir(root_path)
+        .assert()
+        .failure();
+
+    root.close().unwrap();
+}
@@ -0,0 +1,106 @@
+use crate::fixture;
+
+fn populate() -> git_stack::git::InMemoryRepo {
+    let mut repo = git_stack::git::InMemoryRepo::new();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    fixture::populate_repo(&mut repo, plan);
+    repo
+}
+
+#[test]
+fn show_without_rebase() {
+    let repo = populate();
+
+    let plan = git_stack::stack::Builder::new(&repo)
+        .protected_branches(["master"])
+        .build()
+        .unwrap();
+    snapbox::assert_data_eq!(plan.tree(), snapbox::str![[r#"
+0000000 3 (base)
+- 0000000 4
+  0000000 5 (master)
+  0000000 6 (off_master)
+- 0000000 7 (feature1)
+  0000000 8
+  0000000 9
+  0000000 10 (feature2)
+
+"#]]);
+}
+
+#[test]
+fn rebase() {
+    let mut repo = populate();
+    let master_id = repo.find_local_branch("master").unwrap().id;
+
+    let plan = git_stack::stack::Builder::new(&repo)
+        .protected_branches(["master"])
+        .rebase(true)
+        .build()
+        .unwrap();
+    snapbox::assert_data_eq!(plan.tree(), snapbox::str![[r#"
+0000000 3 (base)
+0000000 4
+0000000 5 (master)
+- 0000000 6 (off_master)
+- 0000000 7 (feature1)
+  0000000 8
+  0000000 9
+  0000000 10 (feature2)
+
+"#]]);
+
+    let mut executor = git_stack::rewrite::Executor::new(false);
+    let failures = plan.execute(&mut repo, &mut executor, None).unwrap();
+    assert_eq!(failures, vec![]);
+
+    let feature2_id = repo.find_local_branch("feature2").unwrap().id;
+    let ancestors = git_stack::git::commit_range(&repo, feature2_id..).unwrap();
+    assert!(ancestors.contains(&master_id));
+}
+
+#[test]
+fn rebase_leaves_foreign_branches() {
+    let tree = |message: &str, author: &str| {
+        git_fixture::Command::Tree(git_fixture::Tree {
+            files: Default::default(),
+            message: Some(message.to_owned()),
+            author: Some(author.to_owned()),
+        })
+    };
+    let mut repo = git_stack::git::InMemoryRepo::new();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree("A", "someone"),
+            git_fixture::Command::Label("base".into()),
+            tree("B", "someone"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree("C", "me"),
+            git_fixture::Command::Branch("mine".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree("D", "someone"),
+            git_fixture::Command::Branch("theirs".into()),
+            git_fixture::Command::Head,
+        ],
+        ..Default::default()
+    };
+    fixture::populate_repo(&mut repo, plan);
+    let old_theirs_id = repo.find_local_branch("theirs").unwrap().id;
+
+    let plan = git_stack::stack::Builder::new(&repo)
+        .protected_branches(["main"])
+        .user(Some("me"))
+        .rebase(true)
+        .build()
+        .unwrap();
+    let mut executor = git_stack::rewrite::Executor::new(false);
+    let failures = plan.execute(&mut repo, &mut executor, None).unwrap();
+    assert_eq!(failures, vec![]);
+
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let mine_id = repo.find_local_branch("mine").unwrap().id;
+    assert_eq!(repo.parent_ids(mine_id).unwrap(), vec![main_id]);
+    assert_eq!(repo.find_local_branch("theirs").unwrap().id, old_theirs_id);
+}
@@ -0,0 +1,170 @@
+use crate::fixture::tree;
+
+#[test]
+fn round_trip() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("first".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let first_id = repo.find_local_branch("first").unwrap().id;
+    let second_id = repo.find_local_branch("second").unwrap().id;
+    let bundle_path = root_path.join("stack.bundle");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("export-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Bundled first, second onto main to [..]stack.bundle
+
+"#]]);
+
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "--quiet", "-D", "first", "second"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("import-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Imported first, second onto [..] (A)
+
+"#]]);
+
+    assert_eq!(repo.find_local_branch("first").unwrap().id, first_id);
+    assert_eq!(repo.find_local_branch("second").unwrap().id, second_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn import_keeps_existing_branches() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let bundle_path = root_path.join("stack.bundle");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("export-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "--quiet", "--force", "feature", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("import-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+warning: skipping feature, it already exists (use `--force` to overwrite)
+Imported no branches onto [..] (A)
+
+"#]]);
+    assert_eq!(repo.find_local_branch("feature").unwrap().id, main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn import_rejects_case_conflicts() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("Feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    let bundle_path = root_path.join("stack.bundle");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("export-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "--quiet", "-D", "Feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "--quiet", "feature", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("import-bundle")
+        .arg(&bundle_path)
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(snapbox::str![[r#"
+cannot create branch `Feature`, it differs from `feature` only by case which corrupts refs on case-insensitive filesystems
+
+"#]]);
+
+    root.close().unwrap();
+}
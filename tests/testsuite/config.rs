@@ -0,0 +1,432 @@
+use crate::fixture::{git_config, tree};
+
+fn profiles(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("release".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    git_config(root_path, "stack.protected-branch", "main");
+    git_config(root_path, "stack-profile.work.protected-branch", "release");
+    git_config(root_path, "stack-profile.work.push-remote", "fork");
+    git_config(root_path, "stack-profile.oss.pull-remote", "upstream");
+}
+
+#[test]
+fn profile_flag() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--protected"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--protected", "--profile", "work"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+release
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--dump-config", "-", "--profile", "work"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+[core]
+	editor=true
+[stack]
+	protected-branch=release
+	protect-remote-default-branch=true
+	protect-commit-count=50
+	protect-commit-age=14days
+	auto-base-commit-count=500
+	stack=all
+	push-remote=fork
+	pull-remote=origin
+	show-format=graph
+	show-icons=unicode
+	palette=default
+	show-commits=unprotected
+	show-stacked=true
+	show-signatures=false
+	show-base-history=0
+	auto-fixup=move
+	auto-repair=true
+	preserve-order=false
+	dropByPatchId=false
+	resign=strip
+	gitlab-mr=false
+	pushStyle=plain
+	forge=none
+	default-command=show
+	network-retries=3
+	network-retry-delay=1s
+	fresh-max-age=1day
+	profile=work
+	read-only=false
+	auto-stash=false
+[branch-stash]
+	capacity=30
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn profile_default() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+    git_config(root_path, "stack.profile", "oss");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--dump-config", "-"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+[core]
+	editor=true
+[stack]
+	protected-branch=main
+	protected-branch=main
+	protected-branch=master
+	protected-branch=dev
+	protected-branch=stable
+	protected-branch=main
+	protect-remote-default-branch=true
+	protect-commit-count=50
+	protect-commit-age=14days
+	auto-base-commit-count=500
+	stack=all
+	push-remote=origin
+	pull-remote=upstream
+	show-format=graph
+	show-icons=unicode
+	palette=default
+	show-commits=unprotected
+	show-stacked=true
+	show-signatures=false
+	show-base-history=0
+	auto-fixup=move
+	auto-repair=true
+	preserve-order=false
+	dropByPatchId=false
+	resign=strip
+	gitlab-mr=false
+	pushStyle=plain
+	forge=none
+	default-command=show
+	network-retries=3
+	network-retry-delay=1s
+	fresh-max-age=1day
+	profile=oss
+	read-only=false
+	auto-stash=false
+[branch-stash]
+	capacity=30
+
+"#]]);
+
+    // The flag wins over the repo's default
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["why", "release", "--profile", "work"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+release: B
+  planned against release onto release
+  branch release is protected
+  protected, reachable from protected branch `release`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--protected", "--profile", "missing"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+unknown profile `missing`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn config_sources() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+    git_config(root_path, "stack.auto-fixup", "squash");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config", "--profile", "work"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+core.editor=true (env)
+stack.protected-branch=release (profile)
+stack.push-remote=fork (profile)
+stack.auto-fixup=squash (repo)
+stack.profile=work (profile)
+
+"#]]);
+
+    let output = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config", "--effective", "--json"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let settings: Vec<serde_json::Value> = serde_json::from_slice(&output).unwrap();
+    let source_of = |key: &str| {
+        settings
+            .iter()
+            .find(|s| s["key"] == key)
+            .map(|s| s["source"].as_str().unwrap().to_owned())
+    };
+    assert_eq!(source_of("stack.auto-fixup").as_deref(), Some("repo"));
+    assert_eq!(source_of("stack.show-format").as_deref(), Some("default"));
+    assert_eq!(source_of("stack.profile"), None);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config", "stack.auto-fixup", "stack.show-format"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+stack.show-format=graph (default)
+stack.auto-fixup=squash (repo)
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn protects_remote_default_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("trunk".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    for args in [
+        &["update-ref", "refs/remotes/origin/trunk", "trunk"][..],
+        &[
+            "symbolic-ref",
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/trunk",
+        ][..],
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(args)
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+core.editor=true (env)
+stack.protected-branch=trunk (remote)
+
+"#]]);
+
+    git_config(root_path, "stack.protect-remote-default-branch", "false");
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+core.editor=true (env)
+stack.protect-remote-default-branch=false (repo)
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn warns_of_missing_remotes_and_branches() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+    git_config(root_path, "remote.upstream.url", "https://example.com/upstream.git");
+    git_config(root_path, "stack.protected-branch", "release-*");
+    git_config(root_path, "stack.protected-remote", "main=origin");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--protected"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main
+
+"#]])
+        .stderr_eq(snapbox::str![[r#"
+WARN: `stack.push-remote` is `origin` but there is no such remote (have upstream)
+WARN: `stack.protected-remote=main=origin` names `origin` but there is no such remote
+WARN: `stack.protected-branch=release-*` doesn't match any branch
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["status", "--porcelain"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![""]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn validate_reports_unknown_keys_and_invalid_values() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+    git_config(root_path, "stack.pushstyl", "plain");
+    git_config(root_path, "stack.show-format", "tree");
+    git_config(root_path, "stack.auto-repair", "true");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config", "--validate"])
+        .current_dir(root_path)
+        .assert()
+        .code(78)
+        .stderr_eq(snapbox::str![[r#"
+error: [..]config:[..]: unknown key `stack.pushstyl`, did you mean `stack.pushStyle`?
+error: [..]config:[..]: invalid `stack.show-format=tree`, expected one of silent, list, list-verbose, graph, debug
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["config", "--schema"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+...
+    "stack.show-format": {
+      "description": "[..]",
+      "enum": [
+        "silent",
+        "list",
+        "list-verbose",
+        "graph",
+        "debug"
+      ],
+      "type": "string"
+    },
+...
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn read_only_refuses_changes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+    git_config(root_path, "stack.read-only", "true");
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let old_feature_id = repo.revparse_single("feature").unwrap().id();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--onto", "release"])
+        .current_dir(root_path)
+        .assert()
+        .code(1)
+        .stderr_eq(snapbox::str![[r#"
+refusing to make changes, `stack.read-only` is set
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--onto", "release", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["anchor", "main"])
+        .current_dir(root_path)
+        .assert()
+        .code(1);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format", "silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_eq!(
+        repo.revparse_single("feature").unwrap().id(),
+        old_feature_id
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn read_only_flag() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    profiles(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--read-only", "--protect", "release"])
+        .current_dir(root_path)
+        .assert()
+        .code(1)
+        .stderr_eq(snapbox::str![[r#"
+refusing to make changes, `stack.read-only` is set
+
+"#]]);
+
+    root.close().unwrap();
+}
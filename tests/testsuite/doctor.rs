@@ -0,0 +1,61 @@
+use crate::fixture::{git, tree};
+use snapbox::str;
+
+#[test]
+fn doctor_reports_fixes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("trunk".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git(root_path, &["switch", "--quiet", "--detach", "trunk"]);
+    git(root_path, &["remote", "add", "origin", "https://example.com/repo.git"]);
+    git(root_path, &["config", "branch.feature.remote", "origin"]);
+    git(root_path, &["config", "branch.feature.merge", "refs/heads/feature"]);
+    let hook = root_path.join(".git/hooks/pre-push");
+    std::fs::write(&hook, "#!/bin/sh\nexit 0\n").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("doctor")
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stdout_eq(str![])
+        .stderr_eq(str![[r#"
+warning: `HEAD` is detached at [..], so `git stack` can't tell which stack you are on
+  fix: `git switch <branch>`, or `git switch -c <branch>` to keep the commits you made
+warning: no branches are protected, so `git stack` treats every branch as yours to rewrite
+  fix: `git stack --protect <branch>` for the branch you merge into, like `git stack --protect main`
+warning: upstreams are gone for `feature` (`origin/feature`)
+  fix: `git stack tidy` to stop tracking them
+warning: the `pre-push` hook isn't executable, so it is skipped
+  fix: `chmod +x [..]pre-push`
+found 4 problems
+
+"#]]);
+
+    git(root_path, &["switch", "--quiet", "trunk"]);
+    git(root_path, &["branch", "--unset-upstream", "feature"]);
+    git(root_path, &["config", "stack.protected-branch", "trunk"]);
+    std::fs::remove_file(&hook).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("doctor")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![])
+        .stderr_eq(str![[r#"
+note: no problems found
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,163 @@
+use crate::fixture::{git, tree};
+use snapbox::assert_data_eq;
+use snapbox::str;
+
+fn fixture(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("base".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+fn edit_amends_staged_changes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "feature~1"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![])
+        .stderr_eq(str![[r#"
+Stopped at [..]: B
+note: stage your changes or `git commit --amend`, then run `git stack edit --continue`
+
+"#]]);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    assert!(repo.raw().head_detached().unwrap());
+    assert_eq!(repo.head_commit().summary, "B");
+
+    std::fs::write(root_path.join("b"), "edited").unwrap();
+    git(root_path, &["add", "b"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "--continue"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![])
+        .stderr_eq(str![[r#"
+Edited [..]: B
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    assert_eq!(repo.head_branch().unwrap().name, "feature");
+    let base = repo.find_local_branch("base").unwrap();
+    let feature = repo.find_local_branch("feature").unwrap();
+    assert_eq!(repo.parent_ids(feature.id).unwrap(), vec![base.id]);
+    let base_commit = repo.raw().find_commit(base.id).unwrap();
+    assert_eq!(base_commit.summary(), Some("B"));
+    assert_eq!(std::fs::read_to_string(root_path.join("b")).unwrap(), "edited");
+    assert_eq!(std::fs::read_to_string(root_path.join("c")).unwrap(), "c");
+    assert!(!git_stack::git::EditStop::path(repo.raw().path()).exists());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn edit_rebases_onto_amended_commit() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "base"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    git(root_path, &["commit", "--quiet", "--amend", "-m", "new B"]);
+    std::fs::write(root_path.join("d"), "d").unwrap();
+    git(root_path, &["add", "d"]);
+    git(root_path, &["commit", "--quiet", "-m", "D"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "--continue"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    assert_eq!(repo.head_branch().unwrap().name, "feature");
+    let feature = repo.raw().find_commit(repo.head_commit().id).unwrap();
+    let summaries = std::iter::successors(Some(feature), |c| c.parent(0).ok())
+        .map(|c| c.summary().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(summaries, ["C", "D", "new B", "A"]);
+    let base = repo.find_local_branch("base").unwrap();
+    assert_eq!(
+        repo.raw().find_commit(base.id).unwrap().summary(),
+        Some("D")
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn edit_abort() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_feature = repo.find_local_branch("feature").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "base"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "main"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+already editing a commit, run `git stack edit --continue` or `git stack edit --abort`
+
+"#]]);
+    git(root_path, &["commit", "--quiet", "--amend", "-m", "new B"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "--abort"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![])
+        .stderr_eq(str![]);
+
+    assert_eq!(repo.head_branch().unwrap().name, "feature");
+    let feature = repo.find_local_branch("feature").unwrap();
+    assert_eq!(feature.id, old_feature.id);
+    assert_data_eq!(
+        repo.raw().find_commit(feature.id).unwrap().summary().unwrap(),
+        str!["C"]
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["edit", "main"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+cannot edit protected commits
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,81 @@
+use crate::fixture::{git, tree};
+use snapbox::prelude::*;
+
+#[test]
+fn fetch_all_stacks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let repo_path = root_path.join("repo");
+    std::fs::create_dir_all(&repo_path).unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("other".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&repo_path).unwrap();
+    git(&repo_path, &["switch", "--quiet", "feature"]);
+    for remote in ["upstream", "origin", "fork"] {
+        let remote_path = root_path.join(format!("{remote}.git"));
+        git(
+            root_path,
+            &["init", "--quiet", "--bare", remote_path.to_str().unwrap()],
+        );
+        git(
+            &repo_path,
+            &["push", "--quiet", remote_path.to_str().unwrap(), "main"],
+        );
+        git(
+            &repo_path,
+            &["remote", "add", remote, remote_path.to_str().unwrap()],
+        );
+    }
+    git(&repo_path, &["config", "stack.pull-remote", "upstream"]);
+    git(&repo_path, &["config", "branch.other.stack-push-remote", "fork"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["fetch", "--dry-run"])
+        .current_dir(&repo_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+Would fetch and prune `origin`
+Would fetch and prune `upstream`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["fetch", "--all-stacks", "--jobs", "2"])
+        .current_dir(&repo_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(
+            snapbox::str![[r#"
+Fetched `fork` ([..]/3)
+Fetched `origin` ([..]/3)
+Fetched `upstream` ([..]/3)
+...
+"#]]
+            .unordered(),
+        );
+
+    let repo = git2::Repository::open(&repo_path).unwrap();
+    for remote in ["upstream", "origin", "fork"] {
+        assert!(
+            repo.find_branch(&format!("{remote}/main"), git2::BranchType::Remote)
+                .is_ok(),
+            "{remote}/main wasn't fetched"
+        );
+    }
+
+    root.close().unwrap();
+}
@@ -26,6 +26,7 @@ pub(crate) fn populate_repo(repo: &mut git_stack::git::InMemoryRepo, fixture: gi
                     id: commit_id,
                     tree_id: commit_id,
                     summary: bstr::BString::from(summary),
+                    encoding: None,
                     time: std::time::SystemTime::now(),
                     author: Some(std::rc::Rc::from(
                         tree.author.as_deref().unwrap_or("fixture"),
@@ -59,3 +60,97 @@ pub(crate) fn populate_repo(repo: &mut git_stack::git::InMemoryRepo, fixture: gi
         }
     }
 }
+
+pub(crate) fn tree(files: &[(&str, &str)], message: &str) -> git_fixture::Command {
+    git_fixture::Command::Tree(git_fixture::Tree {
+        files: files
+            .iter()
+            .map(|(p, c)| ((*p).into(), (*c).into()))
+            .collect::<std::collections::HashMap<_, _>>(),
+        message: Some(message.to_owned()),
+        author: None,
+    })
+}
+
+/// Stand in for `gh`, answering from `prs.txt` and logging changes to `changes.txt`
+#[cfg(unix)]
+pub(crate) fn fake_gh(bin_path: &std::path::Path) {
+    std::fs::create_dir_all(bin_path).unwrap();
+    let gh_path = bin_path.join("gh");
+    std::fs::write(
+        &gh_path,
+        r#"#!/bin/sh
+case "$1 $2" in
+"pr list")
+    head=$6
+    grep "^$head " prs.txt | cut -d' ' -f2-
+    ;;
+"pr edit")
+    echo "edit $3 $5" >> changes.txt
+    ;;
+"api --method")
+    echo "create $6 $8 ${10} token=$GH_TOKEN" >> changes.txt
+    echo "14 ${8#base=} https://example.com/pull/14"
+    ;;
+esac
+"#,
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&gh_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&gh_path, permissions).unwrap();
+}
+
+/// `feature`, and `child` stacked on it, conflict with `main`; `other`, checked out, doesn't
+pub(crate) fn conflicting_branches(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "feature")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("b", "feature"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("child".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("other".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("b", "main")], "E"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "other"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+/// Summaries of the commits on `feature` but not `main`, newest first
+pub(crate) fn summaries(root_path: &std::path::Path) -> String {
+    let output = snapbox::cmd::Command::new("git")
+        .args(["log", "--format=%s", "main..feature"])
+        .current_dir(root_path)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Add `value` for `key`, on top of any it already has, like `git config --add`
+pub(crate) fn git_config(root_path: &std::path::Path, key: &str, value: &str) {
+    snapbox::cmd::Command::new("git")
+        .args(["config", "--add", key, value])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+pub(crate) fn git(root_path: &std::path::Path, args: &[&str]) {
+    snapbox::cmd::Command::new("git")
+        .args(args)
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
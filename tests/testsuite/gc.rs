@@ -0,0 +1,113 @@
+use crate::fixture::tree;
+use snapbox::str;
+
+fn snapshot_count(root_path: &std::path::Path) -> usize {
+    std::fs::read_dir(root_path.join(".git/branch-stash/git-stack"))
+        .unwrap()
+        .count()
+}
+
+#[test]
+fn gc_expire_now_keeps_latest() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    for message in ["first", "second", "third"] {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg("reword")
+            .arg(format!("--message={message}"))
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+    assert_eq!(snapshot_count(root_path), 3);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["gc", "--expire=now", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Would remove 2 snapshots older than 0s
+
+"#]]);
+    assert_eq!(snapshot_count(root_path), 3);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["gc", "--expire=now"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Removed 2 snapshots older than 0s
+
+"#]]);
+    assert_eq!(snapshot_count(root_path), 1);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("gc")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Removed 0 snapshots older than 30days
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn prune_defaults_to_max_age() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    for message in ["first", "second"] {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg("reword")
+            .arg(format!("--message={message}"))
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+    assert_eq!(snapshot_count(root_path), 2);
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("branch-stash.max-age", "0s")
+        .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("prune")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Removed 1 snapshot older than 0s
+
+"#]]);
+    assert_eq!(snapshot_count(root_path), 1);
+
+    root.close().unwrap();
+}
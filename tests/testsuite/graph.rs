@@ -346,3 +346,85 @@ fn remove_base() {
         .collect::<std::collections::HashSet<_>>();
     assert_eq!(actual_children, expected_children);
 }
+
+#[test]
+fn diff_unchanged() {
+    let mut repo = git_stack::git::InMemoryRepo::new();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    fixture::populate_repo(&mut repo, plan);
+
+    let protect = protect();
+    let branches = BranchSet::from_repo(&repo, &protect).unwrap();
+    let graph = Graph::from_branches(&repo, branches).unwrap();
+
+    let actual = diff(&graph, &graph.clone());
+    assert!(actual.is_empty(), "{actual:?}");
+}
+
+#[test]
+fn diff_rebase_development_branches() {
+    let mut repo = git_stack::git::InMemoryRepo::new();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    fixture::populate_repo(&mut repo, plan);
+
+    let protect = protect();
+    let branches = BranchSet::from_repo(&repo, &protect).unwrap();
+    let mut graph = Graph::from_branches(&repo, branches).unwrap();
+    protect_branches(&mut graph);
+    let old = graph.clone();
+    let base = repo.find_local_branch("base").unwrap().id;
+    let master = repo.find_local_branch("master").unwrap().id;
+    rebase_development_branches(&mut graph, master);
+    fast_forward_pulled_branches(&mut graph, base, master);
+
+    let actual = diff(&old, &graph);
+    let expected = GraphDiff {
+        moved: vec![MovedCommit {
+            id: repo.find_local_branch("feature1").unwrap().id,
+            old_parents: vec![base],
+            new_parents: vec![master],
+        }],
+        retargeted: vec![BranchRetarget {
+            name: "base".to_owned(),
+            old_id: Some(base),
+            new_id: Some(master),
+        }],
+        ..Default::default()
+    };
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn diff_remove() {
+    let mut repo = git_stack::git::InMemoryRepo::new();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    fixture::populate_repo(&mut repo, plan);
+
+    let protect = protect();
+    let branches = BranchSet::from_repo(&repo, &protect).unwrap();
+    let mut graph = Graph::from_branches(&repo, branches).unwrap();
+    let old = graph.clone();
+    let feature1 = repo.find_local_branch("feature1").unwrap().id;
+    let base = repo.find_local_branch("base").unwrap().id;
+    graph.remove(feature1);
+
+    let actual = diff(&old, &graph);
+    let expected = GraphDiff {
+        removed: vec![feature1],
+        moved: vec![MovedCommit {
+            id: to_oid(8),
+            old_parents: vec![feature1],
+            new_parents: vec![base],
+        }],
+        retargeted: vec![BranchRetarget {
+            name: "feature1".to_owned(),
+            old_id: Some(feature1),
+            new_id: None,
+        }],
+        ..Default::default()
+    };
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,29 @@
+use snapbox::str;
+
+#[test]
+fn workflows() {
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["help", "workflows"])
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+Reviewing your stacks
+  See how your development branches relate to each other and what is ready
+
+  # Show all development branches on top of their protected bases
+  $ git stack
+...
+"#]]);
+}
+
+#[test]
+fn unknown_topic() {
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["help", "frobnicate"])
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+unrecognized command or topic `frobnicate`, see `git stack help`
+
+"#]]);
+}
@@ -0,0 +1,381 @@
+#![cfg(unix)]
+
+use crate::fixture::tree;
+use snapbox::assert_data_eq;
+use snapbox::str;
+
+/// `main` moved ahead of a two-branch stack, with HEAD at the top of the stack
+fn stack_fixture(path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("m", "m")], "M"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature-1".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature-2".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(path).unwrap();
+}
+
+/// Install a hook that records its name, arguments, and how many lines it was fed
+fn install_hook(git_dir: &std::path::Path, name: &str, log: &std::path::Path) {
+    let hook_path = git_dir.join("hooks").join(name);
+    std::fs::create_dir_all(hook_path.parent().unwrap()).unwrap();
+    std::fs::write(
+        &hook_path,
+        format!(
+            "#!/bin/sh
+echo \"{name} $* $(wc -l | tr -d ' ')\" >> '{}'
+",
+            log.display()
+        ),
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&hook_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&hook_path, permissions).unwrap();
+}
+
+fn read_log(log: &std::path::Path) -> String {
+    std::fs::read_to_string(log).unwrap_or_default()
+}
+
+#[test]
+fn rebase_runs_post_rewrite() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        read_log(&log),
+        str![[r#"
+post-rewrite rebase 1
+post-rewrite rebase 1
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_no_hooks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--no-hooks", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(read_log(&log), str![""]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_relative_hooks_path() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join("tools"), "post-rewrite", &log);
+    snapbox::cmd::Command::new("git")
+        .args(["config", "core.hooksPath", "tools/hooks"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    // Relative to the top of the worktree, not where `git stack` is run from
+    let subdir = root_path.join("tools");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(&subdir)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        read_log(&log),
+        str![[r#"
+post-rewrite rebase 1
+post-rewrite rebase 1
+
+"#]]
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync", "--no-verify"])
+        .current_dir(&subdir)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--no-verify", "--message=new C", "feature-2"])
+        .current_dir(&subdir)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        read_log(&log),
+        str![[r#"
+post-rewrite rebase 1
+post-rewrite rebase 1
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_runs_post_rewrite() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--message=new B", "feature-1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        read_log(&log),
+        str![[r#"
+post-rewrite rebase 2
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_no_hooks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--no-hooks", "--message=new B", "feature-1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(read_log(&log), str![""]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn amend_runs_post_rewrite() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--message=new B", "feature-1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        read_log(&log),
+        str![[r#"
+post-rewrite rebase 2
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn amend_no_hooks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    let log = root_path.join(".git/hooks.log");
+    install_hook(&root_path.join(".git"), "post-rewrite", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["amend", "--no-hooks", "--message=new B", "feature-1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(read_log(&log), str![""]);
+
+    root.close().unwrap();
+}
+
+fn push_fixture(root_path: &std::path::Path) -> std::path::PathBuf {
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    local_path
+}
+
+#[test]
+fn push_runs_pre_push() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = push_fixture(root_path);
+    let log = root_path.join("hooks.log");
+    install_hook(&local_path.join(".git"), "pre-push", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let log = read_log(&log).replace(&root_path.join("remote.git").display().to_string(), "[REMOTE]");
+    assert_data_eq!(
+        log,
+        str![[r#"
+pre-push origin [REMOTE] 1
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_no_hooks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = push_fixture(root_path);
+    let log = root_path.join("hooks.log");
+    install_hook(&local_path.join(".git"), "pre-push", &log);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--no-hooks", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(read_log(&log), str![""]);
+    assert!(git2::Repository::open(root_path.join("remote.git"))
+        .unwrap()
+        .find_branch("feature", git2::BranchType::Local)
+        .is_ok());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_pre_push_per_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = push_fixture(root_path);
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "--create", "other", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["commit", "--quiet", "--allow-empty", "-m", "O"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    let log = root_path.join("hooks.log");
+    let hook_path = local_path.join(".git/hooks/pre-push");
+    std::fs::create_dir_all(hook_path.parent().unwrap()).unwrap();
+    std::fs::write(
+        &hook_path,
+        format!(
+            "#!/bin/sh
+read local_ref local_id remote_ref remote_id
+echo \"pre-push $1 $local_ref $remote_ref $remote_id\" >> '{}'
+test \"$local_ref\" != refs/heads/feature
+",
+            log.display()
+        ),
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&hook_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&hook_path, permissions).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+...
+WARN: `feature` was rejected by the `pre-push` hook
+...
+"#]]);
+
+    // Sibling branches are pushed in commit order, which depends on the commit times
+    let mut lines = read_log(&log)
+        .lines()
+        .map(|l| format!("{l}\n"))
+        .collect::<Vec<_>>();
+    lines.sort();
+    assert_data_eq!(
+        lines.concat(),
+        str![[r#"
+pre-push origin refs/heads/feature refs/heads/feature 0000000000000000000000000000000000000000
+pre-push origin refs/heads/other refs/heads/other 0000000000000000000000000000000000000000
+
+"#]]
+    );
+    let remote = git2::Repository::open(root_path.join("remote.git")).unwrap();
+    assert!(remote
+        .find_branch("feature", git2::BranchType::Local)
+        .is_err());
+    assert!(remote.find_branch("other", git2::BranchType::Local).is_ok());
+
+    root.close().unwrap();
+}
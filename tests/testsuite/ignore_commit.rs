@@ -0,0 +1,151 @@
+use crate::fixture::{summaries, tree};
+use snapbox::str;
+
+#[test]
+fn ignored_fixup_is_not_squashed() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b2")], "fixup! B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["ignore-commit", "HEAD"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Ignored [..]
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["ignore-commit"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+[..] fixup! B
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--fixup=squash", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(summaries(root_path), "fixup! B\nB\n");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["ignore-commit", "--remove", "HEAD"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Unignored [..]
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--fixup=squash", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(summaries(root_path), "B\n");
+
+    root.close().unwrap();
+}
+
+#[test]
+fn ignored_wip_is_pushable() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "WIP: B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+main (no remote) A
+⌽ feature WIP: B
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["ignore-commit", "HEAD"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+main (no remote) A
+⌽ feature (ready) WIP: B
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn ignored_wip_is_pushed() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "WIP: B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["ignore-commit", "HEAD"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert!(remote.find_reference("refs/heads/feature").is_ok());
+
+    root.close().unwrap();
+}
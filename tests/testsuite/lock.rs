@@ -0,0 +1,91 @@
+use snapbox::prelude::*;
+use snapbox::str;
+
+fn fixture(root_path: &std::path::Path) -> std::path::PathBuf {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let lock_path = git_stack::git::RepoLock::path(repo.path());
+    std::fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+    lock_path
+}
+
+#[test]
+fn rebase_fails_while_locked() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let lock_path = fixture(root_path);
+    // This test is still running, so the lock isn't stale
+    let since = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    std::fs::write(&lock_path, format!("{}\n{}\n", std::process::id(), since)).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--rebase")
+        .current_dir(root_path)
+        .assert()
+        .code(75)
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+another git-stack (pid [..]) has been running for [..], holding `[..]lock`, pass `--wait` to wait for it
+
+"#]]);
+
+    assert!(lock_path.exists());
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn rebase_takes_over_stale_lock() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let lock_path = fixture(root_path);
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    std::fs::write(&lock_path, format!("{}\n0\n", exited.id())).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--rebase")
+        .arg("--wait")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert!(!lock_path.exists());
+    // Nothing is left behind from taking it over
+    let leftovers = std::fs::read_dir(lock_path.parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("lock"))
+        .collect::<Vec<_>>();
+    assert_eq!(leftovers, Vec::<String>::new());
+
+    root.close().unwrap();
+}
@@ -0,0 +1,141 @@
+// Not correctly overriding on Windows
+#![cfg(target_os = "linux")]
+
+use snapbox::str;
+
+/// A `crontab` that keeps the table in `$HOME/crontab`
+fn fake_crontab(bin_path: &std::path::Path) {
+    std::fs::create_dir_all(bin_path).unwrap();
+    let crontab_path = bin_path.join("crontab");
+    std::fs::write(
+        &crontab_path,
+        r#"#!/bin/sh
+case "$1" in
+-l)
+    [ -e "$HOME/crontab.deny" ] && { echo "crontab: your UID isn't in the passwd file" >&2; exit 1; }
+    cat "$HOME/crontab" 2>/dev/null || { echo "no crontab for $USER" >&2; exit 1; }
+    ;;
+-)
+    cat > "$HOME/crontab"
+    ;;
+esac
+"#,
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&crontab_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&crontab_path, permissions).unwrap();
+}
+
+#[test]
+fn register_schedules_and_unregister_unschedules() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+
+    let home_root = root_path.join("home");
+    std::fs::create_dir_all(&home_root).unwrap();
+    std::fs::write(home_root.join("crontab"), "@reboot true\n").unwrap();
+    let bin_path = root_path.join("bin");
+    fake_crontab(&bin_path);
+    let path = std::env::join_paths(
+        std::iter::once(bin_path).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let repo_root = root_path.join("repo");
+    git2::Repository::init(&repo_root).unwrap();
+
+    for _ in 0..2 {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .args(["maintenance", "register"])
+            .current_dir(&repo_root)
+            .env("HOME", &home_root)
+            .env("PATH", &path)
+            .assert()
+            .success();
+    }
+    let crontab = std::fs::read_to_string(home_root.join("crontab")).unwrap();
+    let lines: Vec<_> = crontab.lines().collect();
+    assert_eq!(lines.len(), 2, "{crontab}");
+    assert_eq!(lines[0], "@reboot true");
+    assert!(
+        lines[1].starts_with("0 4 * * * ")
+            && lines[1].ends_with(" maintenance run # git-stack maintenance"),
+        "{crontab}"
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("maintenance")
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .env("PATH", &path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+[..]/repo
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["maintenance", "unregister"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .env("PATH", &path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Unregistering: [..]/repo
+Unscheduled: `git stack maintenance run` from `crontab`
+
+"#]]);
+    let crontab = std::fs::read_to_string(home_root.join("crontab")).unwrap();
+    assert_eq!(crontab, "@reboot true\n");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("maintenance")
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .env("PATH", &path)
+        .assert()
+        .success()
+        .stdout_eq(str![""]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn register_keeps_an_unreadable_crontab() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+
+    let home_root = root_path.join("home");
+    std::fs::create_dir_all(&home_root).unwrap();
+    std::fs::write(home_root.join("crontab"), "@reboot true\n").unwrap();
+    std::fs::write(home_root.join("crontab.deny"), "").unwrap();
+    let bin_path = root_path.join("bin");
+    fake_crontab(&bin_path);
+    let path = std::env::join_paths(
+        std::iter::once(bin_path).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let repo_root = root_path.join("repo");
+    git2::Repository::init(&repo_root).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["maintenance", "register"])
+        .current_dir(&repo_root)
+        .env("HOME", &home_root)
+        .env("PATH", &path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+...
+`crontab -l` failed with exit status: 1: crontab: your UID isn't in the passwd file
+
+"#]]);
+    let crontab = std::fs::read_to_string(home_root.join("crontab")).unwrap();
+    assert_eq!(crontab, "@reboot true\n");
+
+    root.close().unwrap();
+}
@@ -0,0 +1,112 @@
+use crate::fixture::tree;
+use snapbox::str;
+
+fn branch_id(repo: &git2::Repository, name: &str) -> git2::Oid {
+    repo.find_branch(name, git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .target()
+        .unwrap()
+}
+
+#[test]
+fn move_onto_sibling() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature-1".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature-2".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("other".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let old_feature_1 = branch_id(&repo, "feature-1");
+    let old_other = branch_id(&repo, "other");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["move", "--branch=feature-2", "--onto=other"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Moved feature-2 onto other
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    assert_eq!(branch_id(&repo, "feature-1"), old_feature_1);
+    assert_eq!(branch_id(&repo, "other"), old_other);
+    let feature_2 = repo.find_commit(branch_id(&repo, "feature-2")).unwrap();
+    assert_eq!(feature_2.summary(), Some("C"));
+    assert_eq!(feature_2.parent_id(0).unwrap(), old_other);
+    assert_eq!(repo.head().unwrap().shorthand(), Some("other"));
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["move", "--branch=feature-2", "--onto=other"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+`feature-2` is already on `other`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["move", "--branch=main", "--onto=other"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+cannot move protected branch `main`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn move_quietly() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("other".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["move", "-q", "--branch=feature", "--onto=other"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Moved feature onto other
+
+"#]]);
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let feature = repo.find_commit(branch_id(&repo, "feature")).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), branch_id(&repo, "other"));
+
+    root.close().unwrap();
+}
@@ -0,0 +1,184 @@
+/// `main` with `feature` forking into `left` and `right`
+use crate::fixture::tree;
+
+fn forked_stack(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Label("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("left".into()),
+            git_fixture::Command::Reset("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("e", "e")], "E"),
+            git_fixture::Command::Branch("right".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+fn head_branch(root_path: &std::path::Path) -> String {
+    let repo = git2::Repository::open(root_path).unwrap();
+    let head = repo.head().unwrap();
+    head.shorthand().unwrap().to_owned()
+}
+
+#[test]
+fn next_branch_into_sibling() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    forked_stack(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next", "--branch=left"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(head_branch(root_path), "left");
+
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next", "--branch=right"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(head_branch(root_path), "right");
+
+    // Without a terminal to ask on, `--branch` still counts branches
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["prev", "--branch", "1"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(head_branch(root_path), "feature");
+
+    root.close().unwrap();
+}
+
+#[test]
+fn next_branch_not_on_top() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    forked_stack(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next", "--branch=main"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+`main` is not on top of `HEAD`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["prev", "--branch=left"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+`left` is not beneath `HEAD`
+
+"#]]);
+    assert_eq!(head_branch(root_path), "feature");
+
+    root.close().unwrap();
+}
+
+fn stash_count(root_path: &std::path::Path) -> usize {
+    let mut repo = git2::Repository::open(root_path).unwrap();
+    let mut count = 0;
+    repo.stash_foreach(|_, _, _| {
+        count += 1;
+        true
+    })
+    .unwrap();
+    count
+}
+
+#[test]
+fn stash_restores_changes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    forked_stack(root_path);
+    std::fs::write(root_path.join("a"), "dirty").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next", "--branch=right"])
+        .current_dir(root_path)
+        .assert()
+        .failure();
+    assert_eq!(head_branch(root_path), "feature");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next", "--branch=right", "--stash"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(head_branch(root_path), "right");
+    assert_eq!(std::fs::read_to_string(root_path.join("a")).unwrap(), "dirty");
+    assert_eq!(stash_count(root_path), 0);
+
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.auto-stash", "true"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["prev"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(head_branch(root_path), "feature");
+    assert_eq!(std::fs::read_to_string(root_path.join("a")).unwrap(), "dirty");
+    assert_eq!(stash_count(root_path), 0);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn stash_conflict_is_kept() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "1")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "2")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    std::fs::write(root_path.join("a"), "3").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["prev", "--stash"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+...
+Switching to main: A
+error: could not restore your changes, the stashed changes conflict
+note: resolve the conflicts, then drop [..] from `git stash list`
+
+"#]]);
+    assert_eq!(head_branch(root_path), "main");
+    assert_eq!(stash_count(root_path), 1);
+
+    root.close().unwrap();
+}
@@ -88,6 +88,72 @@ mod test_rebase {
         dbg!(&feature1_branch.id);
         assert!(ancestors.contains(&feature1_branch.id));
     }
+
+    #[derive(Clone, Default)]
+    struct RecordEvents(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl git_stack::rewrite::Events for RecordEvents {
+        fn on_plan_ready(&mut self, _script: &git_stack::rewrite::Script) {
+            self.0.borrow_mut().push("plan".to_owned());
+        }
+
+        fn on_batch_start(
+            &mut self,
+            batch: &git_stack::rewrite::Batch,
+            _labels: &dyn git_stack::rewrite::Labels,
+        ) {
+            self.0
+                .borrow_mut()
+                .push(format!("batch {}", batch.branch().unwrap_or("detached")));
+        }
+
+        fn on_refs_updated(&mut self, updates: &[git_stack::rewrite::RefUpdate]) {
+            for update in updates {
+                self.0.borrow_mut().push(format!("ref {}", update.name));
+            }
+        }
+    }
+
+    #[test]
+    fn rebase_events() {
+        let mut repo = git_stack::git::InMemoryRepo::new();
+        let plan = git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml"))
+            .unwrap();
+        fixture::populate_repo(&mut repo, plan);
+
+        let protect = protect();
+        let branches = BranchSet::from_repo(&repo, &protect).unwrap();
+
+        let master_branch = repo.find_local_branch("master").unwrap();
+        let master_commit = repo.find_commit(master_branch.id).unwrap();
+
+        let mut graph = Graph::from_branches(&repo, branches).unwrap();
+        protect_branches(&mut graph);
+        rebase_development_branches(&mut graph, master_commit.id);
+        let scripts = to_scripts(&graph, vec![]);
+
+        let events = RecordEvents::default();
+        let mut executor = git_stack::rewrite::Executor::new(false);
+        executor.register(events.clone());
+        for script in scripts {
+            let result = executor.run(&mut repo, &script);
+            assert_eq!(result, vec![]);
+        }
+        executor.close(&mut repo, Some("off_master")).unwrap();
+
+        let events = events.0.borrow();
+        dbg!(&events);
+        assert_eq!(events.first().map(|e| e.as_str()), Some("plan"));
+        // `feature1` is re-stacked in the same batch as `feature2`, which is stacked on it
+        let batch = events.iter().position(|e| e == "batch feature2").unwrap();
+        for branch in ["feature1", "feature2"] {
+            let updated = events
+                .iter()
+                .position(|e| *e == format!("ref {branch}"))
+                .unwrap();
+            assert!(batch < updated);
+        }
+    }
 }
 
 mod test_fixup {
@@ -199,6 +265,75 @@ mod test_fixup {
         assert_eq!(feature2_commit.summary.to_str(), Ok("feature2 commit"));
     }
 
+    #[test]
+    fn fixup_stops_at_anchor() {
+        let mut repo = git_stack::git::InMemoryRepo::new();
+        let plan =
+            git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/fixup.yml")).unwrap();
+        fixture::populate_repo(&mut repo, plan);
+
+        let protect = protect();
+        let branches = BranchSet::from_repo(&repo, &protect).unwrap();
+
+        let feature2_branch = repo.find_local_branch("feature2").unwrap();
+        let ids = git_stack::git::commit_range(&repo, feature2_branch.id..).unwrap();
+        let anchor_index = ids
+            .iter()
+            .position(|id| repo.find_commit(*id).unwrap().summary == "feature1 commit 3")
+            .unwrap();
+        let anchor_id = ids[anchor_index];
+        let blocked_id = ids[anchor_index - 1];
+        let anchors = git_stack::git::Anchors::new([anchor_id]);
+
+        let mut graph = Graph::from_branches(&repo, branches).unwrap();
+        protect_branches(&mut graph);
+        mark_anchors(&mut graph, &anchors);
+        fixup(&mut graph, &repo, git_stack::config::Fixup::Move);
+        let blocked = graph
+            .commit_get::<Decisions>(blocked_id)
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(blocked, vec![Decision::AnchoredFixup(anchor_id)]);
+        let scripts = to_scripts(&graph, vec![]);
+        dbg!(&scripts);
+
+        let mut executor = git_stack::rewrite::Executor::new(false);
+        for script in scripts {
+            let result = executor.run(&mut repo, &script);
+            assert_eq!(result, vec![]);
+        }
+        executor.close(&mut repo, Some("master")).unwrap();
+        dbg!(&repo);
+
+        let feature2_branch = repo.find_local_branch("feature2").unwrap();
+        let mut commits: Vec<_> = git_stack::git::commit_range(&repo, feature2_branch.id..)
+            .unwrap()
+            .into_iter()
+            .map(|id| repo.find_commit(id).unwrap())
+            .map(|c| c.summary.to_str_lossy().into_owned())
+            .collect();
+        commits.reverse();
+        // Fixups past the anchor only move among themselves
+        assert_eq!(
+            commits,
+            &[
+                "commit 1",
+                "commit 2",
+                "master commit",
+                "feature1 commit 1",
+                "fixup! feature1 commit 1",
+                "feature1 commit 2",
+                "feature1 commit 3",
+                "fixup! feature1 commit 1",
+                "fixup! feature1 commit 1",
+                "fixup! feature1 commit 2",
+                "feature2 commit",
+            ]
+        );
+    }
+
     #[test]
     fn stray_fixups() {
         fn protect() -> git_stack::git::ProtectedBranches {
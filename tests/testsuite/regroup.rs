@@ -0,0 +1,188 @@
+use crate::fixture::tree;
+use snapbox::str;
+
+/// Three independent branches on `main`, with HEAD on `feature-c`
+fn fixture(path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature-a".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature-b".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("feature-c".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(path).unwrap();
+}
+
+#[test]
+fn regroup_and_list() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "ui", "feature-a", "feature-b"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Adding feature-a to ui
+Adding feature-b to ui
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "backend"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "backend", "feature-b"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Moving feature-b from ui to backend
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+backend
+  feature-b
+  feature-c
+ui
+  feature-a
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn regroup_reserved_name() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "current"])
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+`current` is reserved for `--stack current`, pick another name
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rename_stack() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "ui", "feature-a"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "backend", "feature-b"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename-stack", "ui", "backend"])
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+stack `backend` already exists, use `git stack regroup` to move branches into it
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename-stack", "ui", "frontend"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Renamed ui to frontend
+
+"#]]);
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let config = repo.config().unwrap();
+    assert_eq!(
+        config.get_string("branch.feature-a.stack").unwrap(),
+        "frontend"
+    );
+    assert_eq!(config.get_string("branch.feature-b.stack").unwrap(), "backend");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename-stack", "ui", "other"])
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+no stack named `ui`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn select_named_stack() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "ui", "feature-a", "feature-b"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    // Sibling branches aren't listed in a stable order
+    let output = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack", "ui", "--format", "list"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let mut listed: Vec<_> = std::str::from_utf8(&output).unwrap().lines().collect();
+    listed.sort();
+    assert_eq!(listed, ["feature-a", "feature-b"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack", "missing"])
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(str![[r#"
+no stack named `missing`, see `git stack regroup`
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,123 @@
+use crate::fixture::tree;
+
+#[test]
+fn rename_carries_over_metadata() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "--set-upstream", "origin", "main", "feature"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["regroup", "ui", "feature"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let stash_repo = git_branch_stash::GitRepo::new(git2::Repository::open(&local_path).unwrap());
+    let mut snapshots = git_branch_stash::Stack::new("git-stack", &stash_repo);
+    let snapshot_path = snapshots
+        .push(git_branch_stash::Snapshot::from_repo(&stash_repo).unwrap())
+        .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename", "feature", "frobnicator"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Renamed feature to frobnicator
+`origin/feature` is left on the remote, the next push creates `origin/frobnicator`
+
+"#]]);
+
+    let repo = git2::Repository::open(&local_path).unwrap();
+    assert_eq!(repo.head().unwrap().shorthand(), Some("frobnicator"));
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert_eq!(config.get_str("branch.frobnicator.stack").unwrap(), "ui");
+    assert_eq!(
+        config.get_str("branch.frobnicator.merge").unwrap(),
+        "refs/heads/frobnicator"
+    );
+    let main_merge = config.get_str("branch.main.merge").unwrap();
+    assert_eq!(main_merge, "refs/heads/main");
+
+    let snapshot = git_branch_stash::Snapshot::load(&snapshot_path).unwrap();
+    let names = snapshot
+        .branches
+        .iter()
+        .map(|b| b.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["frobnicator", "main"]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rename_refuses_existing() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename", "feature", "main"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+branch `main` already exists
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename", "feature", "Main"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+cannot create branch `Main`, it differs from `main` only by case which corrupts refs on case-insensitive filesystems
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["rename", "missing", "other"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+could not find branch `missing`
+
+"#]]);
+
+    root.close().unwrap();
+}
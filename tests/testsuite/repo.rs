@@ -107,6 +107,30 @@ fn shared_branches_fixture() {
         }
     }
 
+    // parents_between
+    {
+        {
+            let base = repo.find_local_branch("base").unwrap();
+            let heads = ["feature1", "feature2", "master"]
+                .map(|name| repo.find_local_branch(name).unwrap().id);
+            let actual = repo.parents_between(&heads, base.id).unwrap();
+
+            let mut expected = Vec::new();
+            for head_id in heads {
+                for id in commit_range(&repo, head_id..base.id).unwrap() {
+                    expected.push((id, repo.parent_ids(id).unwrap()));
+                }
+            }
+            expected.sort_unstable();
+            expected.dedup();
+            let mut actual_sorted = actual.clone();
+            actual_sorted.sort_unstable();
+            assert_eq!(actual_sorted, expected);
+            // Shared history is only walked once
+            assert_eq!(actual.len(), expected.len());
+        }
+    }
+
     // local_branches
     {
         let mut actual: Vec<_> = repo.local_branches().map(|b| b.to_string()).collect();
@@ -413,3 +437,219 @@ fn switch() {
 
     temp.close().unwrap();
 }
+
+fn commit_encoded(
+    repo: &git2::Repository,
+    parent_id: git2::Oid,
+    path: &str,
+    encoding: &str,
+    message: &[u8],
+) -> git2::Oid {
+    let parent = repo.find_commit(parent_id).unwrap();
+    let blob_id = repo.blob(path.as_bytes()).unwrap();
+    let mut builder = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+    builder.insert(path, blob_id, 0o100644).unwrap();
+    let tree_id = builder.write().unwrap();
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(format!("tree {tree_id}\nparent {parent_id}\n").as_bytes());
+    buffer.extend_from_slice(b"author Fixture <fixture@example.com> 1700000000 +0100\n");
+    buffer.extend_from_slice(b"committer Fixture <fixture@example.com> 1700000000 +0100\n");
+    buffer.extend_from_slice(format!("encoding {encoding}\n\n").as_bytes());
+    buffer.extend_from_slice(message);
+    repo.odb()
+        .unwrap()
+        .write(git2::ObjectType::Commit, &buffer)
+        .unwrap()
+}
+
+#[test]
+fn cherry_pick_preserves_encoding() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    plan.run(temp.path()).unwrap();
+
+    let repo = git2::Repository::discover(temp.path()).unwrap();
+    let mut repo = GitRepo::new(repo);
+
+    let cases: &[(&str, &[u8], &str)] = &[
+        ("ISO-8859-1", b"Caf\xe9 au lait\n\nD\xe9tails\n", "Café au lait"),
+        (
+            "Shift_JIS",
+            b"\x93\xfa\x96\x7b\x8c\xea\n",
+            "日本語",
+        ),
+    ];
+    for (i, (encoding, message, summary)) in cases.iter().enumerate() {
+        let base = repo.find_local_branch("master").unwrap();
+        let onto = repo.find_local_branch("off_master").unwrap();
+        let source_id = commit_encoded(repo.raw(), base.id, &format!("encoded{i}"), encoding, message);
+
+        let source = repo.find_commit(source_id).unwrap();
+        assert_eq!(source.display_summary(), *summary);
+
+        let same_id = repo.cherry_pick(base.id, source_id).unwrap();
+        assert_eq!(same_id, source_id);
+
+        let dest_id = repo.cherry_pick(onto.id, source_id).unwrap();
+        assert_ne!(dest_id, source_id);
+        {
+            let dest = repo.raw().find_commit(dest_id).unwrap();
+            assert_eq!(dest.message_encoding(), Some(*encoding));
+            assert_eq!(dest.message_raw_bytes(), *message);
+            assert_eq!(dest.parent_id(0).unwrap(), onto.id);
+        }
+        assert_eq!(repo.find_commit(dest_id).unwrap().display_summary(), *summary);
+
+        let squashed_id = repo.squash(dest_id, source_id).unwrap();
+        let squashed = repo.raw().find_commit(squashed_id).unwrap();
+        assert_eq!(squashed.message_encoding(), Some(*encoding));
+        assert_eq!(squashed.message_raw_bytes(), *message);
+        let squashed_parent_ids = squashed.parent_ids().collect::<Vec<_>>();
+        drop(squashed);
+
+        let onto_tree_id = repo.find_commit(onto.id).unwrap().tree_id;
+        let amended_id = repo.amend_tree(squashed_id, onto_tree_id).unwrap();
+        assert_ne!(amended_id, squashed_id);
+        let amended = repo.raw().find_commit(amended_id).unwrap();
+        assert_eq!(amended.message_encoding(), Some(*encoding));
+        assert_eq!(amended.message_raw_bytes(), *message);
+        assert_eq!(amended.tree_id(), onto_tree_id);
+        assert_eq!(amended.parent_ids().collect::<Vec<_>>(), squashed_parent_ids);
+    }
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn cherry_pick_leaves_non_utf8_unsigned() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let key_path = temp.path().join("id_ed25519");
+    if std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+        .arg(&key_path)
+        .output()
+        .is_err()
+    {
+        return;
+    }
+    let repo_path = temp.path().join("repo");
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    plan.run(&repo_path).unwrap();
+
+    let repo = git2::Repository::discover(&repo_path).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("gpg.format", "ssh").unwrap();
+    config
+        .set_str("user.signingkey", key_path.to_str().unwrap())
+        .unwrap();
+    let mut repo = GitRepo::new(repo);
+    repo.set_sign(true).unwrap();
+
+    let base = repo.find_local_branch("master").unwrap();
+    let onto = repo.find_local_branch("off_master").unwrap();
+    let cases: &[(&str, &[u8], bool)] = &[
+        ("UTF-8", b"Caf\xc3\xa9 au lait\n", true),
+        ("ISO-8859-1", b"Caf\xe9 au lait\n", false),
+    ];
+    for (i, (encoding, message, signed)) in cases.iter().enumerate() {
+        let source_id = commit_encoded(repo.raw(), base.id, &format!("encoded{i}"), encoding, message);
+
+        let dest_id = repo.cherry_pick(onto.id, source_id).unwrap();
+        let dest = repo.raw().find_commit(dest_id).unwrap();
+        assert_eq!(dest.message_raw_bytes(), *message);
+        assert_eq!(
+            repo.raw().extract_signature(&dest_id, None).is_ok(),
+            *signed,
+            "{encoding}"
+        );
+    }
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn cherry_pick_encoded_merge() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let plan =
+        git_fixture::TodoList::load(std::path::Path::new("tests/fixtures/branches.yml")).unwrap();
+    plan.run(temp.path()).unwrap();
+
+    let repo = git2::Repository::discover(temp.path()).unwrap();
+    let mut repo = GitRepo::new(repo);
+
+    let base = repo.find_local_branch("master").unwrap();
+    let onto = repo.find_local_branch("off_master").unwrap();
+    let side_id = commit_encoded(repo.raw(), base.id, "side", "ISO-8859-1", b"C\xf4t\xe9\n");
+
+    let message = b"Fusion d\xe9j\xe0 faite\n";
+    let merge_id = {
+        let raw = repo.raw();
+        let side = raw.find_commit(side_id).unwrap();
+        let onto_commit = raw.find_commit(onto.id).unwrap();
+        let mut index = raw.merge_commits(&side, &onto_commit, None).unwrap();
+        assert!(!index.has_conflicts());
+        let tree_id = index.write_tree_to(raw).unwrap();
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(
+            format!("tree {tree_id}\nparent {side_id}\nparent {}\n", onto.id).as_bytes(),
+        );
+        buffer.extend_from_slice(b"author Fixture <fixture@example.com> 1700000000 +0100\n");
+        buffer.extend_from_slice(b"committer Fixture <fixture@example.com> 1700000000 +0100\n");
+        buffer.extend_from_slice(b"encoding ISO-8859-1\n\n");
+        buffer.extend_from_slice(message);
+        raw.odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, &buffer)
+            .unwrap()
+    };
+
+    // Already on top of its first parent
+    let same_id = repo.cherry_pick(side_id, merge_id).unwrap();
+    assert_eq!(same_id, merge_id);
+
+    let dest_id = repo.cherry_pick(base.id, merge_id).unwrap();
+    assert_ne!(dest_id, merge_id);
+    let dest = repo.raw().find_commit(dest_id).unwrap();
+    assert_eq!(dest.message_encoding(), Some("ISO-8859-1"));
+    assert_eq!(dest.message_raw_bytes(), message);
+    assert_eq!(dest.parent_ids().collect::<Vec<_>>(), vec![base.id]);
+    assert_eq!(dest.tree_id(), repo.find_commit(onto.id).unwrap().tree_id);
+    drop(dest);
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn case_conflicting_branch_names() {
+    assert!(is_case_conflict("Feature-X", "feature-x"));
+    assert!(is_case_conflict("Team/a", "team/b"));
+    assert!(is_case_conflict("team", "Team/b"));
+    assert!(!is_case_conflict("feature-x", "feature-x"));
+    assert!(!is_case_conflict("team/a", "team/b"));
+    assert!(!is_case_conflict("feature", "bugfix"));
+
+    assert!(check_branch_case("Feature-X", ["main", "feature-x"]).is_err());
+    assert!(check_branch_case("feature-x", ["main", "feature-x"]).is_ok());
+}
+
+#[test]
+fn parse_git_version() {
+    assert_eq!(
+        GitVersion::parse("git version 2.43.0\n"),
+        Some(GitVersion::new(2, 43, 0))
+    );
+    assert_eq!(
+        GitVersion::parse("git version 2.39.3 (Apple Git-146)"),
+        Some(GitVersion::new(2, 39, 3))
+    );
+    assert_eq!(
+        GitVersion::parse("git version 2.45.1.windows.1"),
+        Some(GitVersion::new(2, 45, 1))
+    );
+    assert_eq!(GitVersion::parse("hub version 2.14.2"), None);
+    assert!(GitVersion::new(2, 9, 5) < GitVersion::new(2, 10, 0));
+}
@@ -0,0 +1,210 @@
+use bstr::ByteSlice as _;
+use snapbox::assert_data_eq;
+use snapbox::prelude::*;
+use snapbox::str;
+
+fn conflicting_stack(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "c")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("C".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    std::fs::write(root_path.join("b"), "amended").unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["add", "b"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+fn amend_conflict(root_path: &std::path::Path) {
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("amend")
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+error: could not apply [..] (target): C
+note: resolve the conflicts and `git add` them, then run `git stack --continue`
+note: to put the branches back, run `git stack --abort`
+
+"#]]);
+}
+
+#[test]
+fn continue_after_conflict() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_stack(root_path);
+    amend_conflict(root_path);
+
+    assert!(root_path.join(".git/git-stack/resume.json").exists());
+    let conflicted = std::fs::read_to_string(root_path.join("b")).unwrap();
+    assert!(conflicted.contains("<<<<<<<"), "{conflicted}");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("sync")
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+a rewrite is stopped on a conflict, run `git stack --continue` or `git stack --abort`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--continue")
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+conflicts are unresolved, `git add` the resolved files, resolve the conflict or run `git stack --abort`
+
+"#]]);
+
+    std::fs::write(root_path.join("b"), "resolved").unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["add", "b"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--continue")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![].raw());
+
+    assert!(!root_path.join(".git/git-stack/resume.json").exists());
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    assert_eq!(repo.head_branch().unwrap().name, "feature");
+    let feature = repo.find_local_branch("feature").unwrap();
+    let target = repo.find_local_branch("target").unwrap();
+    let target_commit = repo.find_commit(target.id).unwrap();
+    assert_data_eq!(target_commit.summary.to_str().unwrap(), str!["C"].raw());
+    assert_eq!(
+        repo.raw()
+            .find_commit(target.id)
+            .unwrap()
+            .parent_id(0)
+            .unwrap(),
+        feature.id
+    );
+    let blob = repo
+        .raw()
+        .find_commit(target.id)
+        .unwrap()
+        .tree()
+        .unwrap()
+        .get_name("b")
+        .unwrap()
+        .to_object(repo.raw())
+        .unwrap()
+        .peel_to_blob()
+        .unwrap();
+    assert_data_eq!(blob.content(), str!["resolved"].raw());
+    assert_data_eq!(
+        std::fs::read(root_path.join("b")).unwrap(),
+        str!["amended"].raw()
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn abort_after_conflict() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_stack(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_feature_id = repo.find_local_branch("feature").unwrap().id;
+    let old_target_id = repo.find_local_branch("target").unwrap().id;
+
+    amend_conflict(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--abort")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![].raw());
+
+    assert!(!root_path.join(".git/git-stack/resume.json").exists());
+    assert_eq!(repo.head_branch().unwrap().name, "feature");
+    // The amended changes stay behind as a fixup, like any other failed amend
+    let feature = repo.find_local_branch("feature").unwrap();
+    let feature_commit = repo.find_commit(feature.id).unwrap();
+    assert_data_eq!(
+        feature_commit.summary.to_str().unwrap(),
+        str!["fixup! B"].raw()
+    );
+    assert_eq!(
+        repo.raw()
+            .find_commit(feature.id)
+            .unwrap()
+            .parent_id(0)
+            .unwrap(),
+        old_feature_id
+    );
+    assert_eq!(repo.find_local_branch("target").unwrap().id, old_target_id);
+    assert_data_eq!(
+        std::fs::read(root_path.join("b")).unwrap(),
+        str!["amended"].raw()
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--abort")
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+no rewrite in progress
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,135 @@
+#[cfg(unix)]
+use crate::fixture::fake_gh;
+use crate::fixture::tree;
+
+#[test]
+#[cfg(unix)]
+fn retarget_after_bottom_landed() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    // `feature` was stacked on the now-deleted `bottom`
+    std::fs::write(root_path.join("prs.txt"), "feature me 12 bottom\nsecond me 13 feature\n").unwrap();
+    let bin_path = root_path.join("bin");
+    fake_gh(&bin_path);
+    let path = std::env::join_paths(
+        std::iter::once(bin_path).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("retarget")
+        .env("PATH", &path)
+        .env("GIT_CONFIG_PARAMETERS", "'stack.forge'='github'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Retargeting #12 (feature) from bottom to main
+
+"#]]);
+
+    let changes = std::fs::read_to_string(root_path.join("changes.txt")).unwrap();
+    assert_eq!(changes, "edit 12 main\n");
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn retarget_fork_pull_requests() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    for args in [
+        &["remote", "add", "fork", "git@github.com:me/repo.git"][..],
+        &["config", "remote.fork.push", "refs/heads/*:refs/heads/me/*"],
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(args)
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+
+    // Branches are pushed to `me/<branch>` on a fork, where someone else has a same-named branch
+    std::fs::write(
+        root_path.join("prs.txt"),
+        "feature me 11 bottom\nme/feature other 12 bottom\nme/feature me 13 bottom\n",
+    )
+    .unwrap();
+    let bin_path = root_path.join("bin");
+    fake_gh(&bin_path);
+    let path = std::env::join_paths(
+        std::iter::once(bin_path).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("retarget")
+        .env("PATH", &path)
+        .env(
+            "GIT_CONFIG_PARAMETERS",
+            "'stack.forge'='github' 'stack.push-remote'='fork' 'stack.pull-remote'='fork'",
+        )
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Retargeting #13 (feature) from bottom to main
+
+"#]]);
+
+    let changes = std::fs::read_to_string(root_path.join("changes.txt")).unwrap();
+    assert_eq!(changes, "edit 13 main\n");
+
+    root.close().unwrap();
+}
+
+#[test]
+fn retarget_requires_forge() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("retarget")
+        .current_dir(root_path)
+        .assert()
+        .code(78)
+        .stderr_eq(snapbox::str![[r#"
+no forge configured, set `stack.forge=github`
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -102,7 +102,7 @@ fn reword_implicit_head() {
         .stdout_eq(str![].raw())
         .stderr_eq(
             str![[r#"
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]
             .raw(),
@@ -174,7 +174,7 @@ fn reword_explicit_head() {
         .stdout_eq(str![].raw())
         .stderr_eq(
             str![[r#"
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]
             .raw(),
@@ -250,7 +250,7 @@ fn reword_branch() {
         .stderr_eq(str![[r#"
 Saved working directory and index state WIP on local (reword): [..]
 Dropped refs/stash [..]
-note: to undo, run `git branch-stash pop git-stack`
+note: to undo, run `git stack undo`
 
 "#]]);
 
@@ -275,3 +275,540 @@ note: to undo, run `git branch-stash pop git-stack`
 
     root.close().unwrap();
 }
+
+#[test]
+fn reword_expires_old_snapshots() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    for message in ["first", "second", "third"] {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg("reword")
+            .arg(format!("--message={message}"))
+            .env("GIT_CONFIG_PARAMETERS", "'branch-stash.git-stack.max-age'='0s'")
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+
+    let snapshots = std::fs::read_dir(root_path.join(".git/branch-stash/git-stack"))
+        .unwrap()
+        .count();
+    assert_eq!(snapshots, 1);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_recreates_merge() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let tree = |files: &[(&str, &str)], message: &str| {
+        git_fixture::Command::Tree(git_fixture::Tree {
+            files: files
+                .iter()
+                .map(|(p, c)| ((*p).into(), (*c).into()))
+                .collect::<std::collections::HashMap<_, _>>(),
+            message: Some(message.to_owned()),
+            author: None,
+        })
+    };
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("side".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    std::fs::write(root_path.join("d"), "d").unwrap();
+    for args in [
+        &["switch", "--quiet", "--create", "feature", "target"][..],
+        &["merge", "--quiet", "--no-ff", "--message=Merge side", "side"][..],
+        &["add", "d"][..],
+        &["commit", "--quiet", "--message=D"][..],
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "fixture")
+            .env("GIT_AUTHOR_EMAIL", "fixture@example.com")
+            .env("GIT_COMMITTER_NAME", "fixture")
+            .env("GIT_COMMITTER_EMAIL", "fixture@example.com")
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let old_merge = repo
+        .revparse_single("feature~")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let side_id = old_merge.parent_id(1).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("reword")
+        .arg("--message=new B")
+        .arg("target")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let target = repo
+        .revparse_single("target")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(target.summary(), Some("new B"));
+    let merge = repo
+        .revparse_single("feature~")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(merge.summary(), Some("Merge side"));
+    assert_eq!(
+        merge.parent_ids().collect::<Vec<_>>(),
+        vec![target.id(), side_id]
+    );
+    assert_eq!(merge.tree_id(), old_merge.tree_id());
+
+    root.close().unwrap();
+}
+
+/// Write an editor that saves the todo list it was given and replaces it with `todo`
+#[cfg(unix)]
+fn todo_editor(root_path: &std::path::Path, todo: Option<&str>, message: &str) -> std::path::PathBuf {
+    let todo_path = root_path.join(".git/new-todo");
+    let replace = if let Some(todo) = todo {
+        std::fs::write(&todo_path, todo).unwrap();
+        format!("cp '{}' \"$1\"", todo_path.display())
+    } else {
+        "true".to_owned()
+    };
+    let editor_path = root_path.join(".git/editor.sh");
+    std::fs::write(
+        &editor_path,
+        format!(
+            "#!/bin/sh
+case \"$1\" in
+  *git-stack-todo) cp \"$1\" '{}'; {replace};;
+  *) echo '{message}' > \"$1\";;
+esac
+",
+            root_path.join(".git/old-todo").display(),
+        ),
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&editor_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&editor_path, permissions).unwrap();
+    editor_path
+}
+
+fn interactive_fixture(root_path: &std::path::Path) {
+    let tree = |files: &[(&str, &str)], message: &str| {
+        git_fixture::Command::Tree(git_fixture::Tree {
+            files: files
+                .iter()
+                .map(|(p, c)| ((*p).into(), (*c).into()))
+                .collect::<std::collections::HashMap<_, _>>(),
+            message: Some(message.to_owned()),
+            author: None,
+        })
+    };
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn reword_interactive_reorders() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let id = |rev: &str| repo.revparse_single(rev).unwrap().id();
+    let (a_id, b_id, c_id) = (id("main"), id("feature~"), id("feature"));
+    let editor = todo_editor(
+        root_path,
+        Some(&format!(
+            "reset {a_id}
+pick {c_id}
+pick {b_id}
+exec git switch --force-create feature
+"
+        )),
+        "unused",
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--interactive"])
+        .env("GIT_EDITOR", &editor)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let old_todo = std::fs::read_to_string(root_path.join(".git/old-todo")).unwrap();
+    let old_todo = old_todo
+        .replace(&a_id.to_string(), "[A]")
+        .replace(&b_id.to_string(), "[B]")
+        .replace(&c_id.to_string(), "[C]");
+    assert_data_eq!(
+        old_todo,
+        str![[r#"
+reset [A]  # A
+pick [B]  # B
+pick [C]  # C
+exec git switch --force-create feature
+
+# Commands:
+# p, pick <commit> = use commit
+# r, reword <commit> = use commit, but edit the commit message
+# f, fixup <commit> = meld into the previous commit, keeping its message
+# d, drop <commit> = remove commit
+# l, label <label> = label the current commit
+# t, reset <label> = start again from a label or commit
+# m, merge -C <commit> <label>... = re-create a merge commit, `-c` to edit its message
+# x, exec git switch --force-create <branch> = point a branch at the current commit
+# x, exec git branch -D <branch> = delete a branch
+#
+# These lines can be re-ordered; they are executed from top to bottom.
+# If you remove everything, nothing will be changed.
+
+"#]]
+    );
+
+    let feature = repo
+        .revparse_single("feature")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(feature.summary(), Some("B"));
+    let parent = feature.parent(0).unwrap();
+    assert_eq!(parent.summary(), Some("C"));
+    assert_eq!(parent.parent_id(0).unwrap(), a_id);
+    assert_eq!(feature.tree_id(), repo.find_commit(c_id).unwrap().tree_id());
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn reword_interactive_rewords_and_drops() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let id = |rev: &str| repo.revparse_single(rev).unwrap().id();
+    let (a_id, b_id, c_id) = (id("main"), id("feature~"), id("feature"));
+    let editor = todo_editor(
+        root_path,
+        Some(&format!(
+            "reset {a_id}
+reword {b_id}
+drop {c_id}
+exec git switch --force-create feature
+"
+        )),
+        "new B",
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--interactive"])
+        .env("GIT_EDITOR", &editor)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature = repo
+        .revparse_single("feature")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(feature.summary(), Some("new B"));
+    assert_eq!(feature.parent_id(0).unwrap(), a_id);
+    assert_eq!(feature.tree_id(), repo.find_commit(b_id).unwrap().tree_id());
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn reword_interactive_invalid_todo() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let old_feature_id = repo.revparse_single("feature").unwrap().id();
+    let editor = todo_editor(root_path, Some("reset main\nsquash feature\n"), "unused");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--interactive"])
+        .env("GIT_EDITOR", &editor)
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+line 2: unknown command `squash`
+
+"#]]);
+
+    assert_eq!(repo.revparse_single("feature").unwrap().id(), old_feature_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_snapshot_records_head() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    std::fs::write(root_path.join("a"), "dirty").unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let mut repo = git_stack::git::GitRepo::new(repo);
+    let old_head_id = repo.head_commit().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("reword")
+        .arg("--message=new B")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let stash_repo = git_branch_stash::GitRepo::new(git2::Repository::open(root_path).unwrap());
+    let mut snapshots = git_branch_stash::Stack::new("git-stack", &stash_repo);
+    let snapshot = git_branch_stash::Snapshot::load(&snapshots.peek().unwrap()).unwrap();
+    let head = git_stack::git::HeadState::from_metadata(&snapshot.metadata).unwrap();
+    assert_eq!(head.branch.as_deref(), Some("target"));
+    assert_eq!(head.id, old_head_id);
+    assert!(head.dirty);
+
+    // Restoring the branches alone leaves HEAD wherever the user went since
+    std::fs::write(root_path.join("a"), "a").unwrap();
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    repo.switch_commit(main_id).unwrap();
+    repo.branch("target", old_head_id).unwrap();
+    head.restore(&mut repo).unwrap();
+    assert_eq!(
+        repo.head_branch().and_then(|b| b.local_name().map(ToOwned::to_owned)),
+        Some("target".to_owned())
+    );
+    assert_eq!(repo.head_commit().id, old_head_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_file() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+    std::fs::write(
+        root_path.join(".git/rewords"),
+        "# release notes
+feature~ feat: Add b
+
+feature fix: Add c\\n\\nCloses #1
+",
+    )
+    .unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let a_id = repo.revparse_single("main").unwrap().id();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--file=.git/rewords"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature = repo
+        .revparse_single("feature")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(feature.message(), Some("fix: Add c\n\nCloses #1"));
+    let parent = feature.parent(0).unwrap();
+    assert_eq!(parent.summary(), Some("feat: Add b"));
+    assert_eq!(parent.parent_id(0).unwrap(), a_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_file_fails_atomically() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let old_feature_id = repo.revparse_single("feature").unwrap().id();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--file=-"])
+        .stdin("feature new C\nmain new A\n")
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+line 2: `main`: cannot reword protected commits
+
+"#]]);
+
+    assert_eq!(repo.revparse_single("feature").unwrap().id(), old_feature_id);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--file=-"])
+        .stdin("feature new C\nmissing new B\nfeature~0 newer C\n")
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+line 2: could not find `missing`: [..]
+line 3: `feature~0` is the same commit as `feature` on line 1
+
+"#]]);
+
+    assert_eq!(repo.revparse_single("feature").unwrap().id(), old_feature_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn reword_verbose_editor() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    interactive_fixture(root_path);
+    for (key, value) in [("core.commentChar", ";"), ("commit.verbose", "true")] {
+        snapbox::cmd::Command::new("git")
+            .args(["config", key, value])
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+
+    let seen_path = root_path.join(".git/seen-msg");
+    let editor_path = root_path.join(".git/editor.sh");
+    std::fs::write(
+        &editor_path,
+        format!(
+            "#!/bin/sh
+cp \"$1\" '{seen}'
+sed '1s/.*/new C/' '{seen}' > \"$1\"
+",
+            seen = seen_path.display(),
+        ),
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&editor_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&editor_path, permissions).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("reword")
+        .env("GIT_EDITOR", &editor_path)
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    assert_data_eq!(
+        std::fs::read_to_string(&seen_path).unwrap(),
+        str![[r#"
+C
+
+; Please enter the commit message for your changes. Lines starting
+; with ';' will be ignored, and an empty message aborts the commit.
+;
+; On branch feature
+; ------------------------ >8 ------------------------
+; Do not modify or remove the line above.
+; Everything below it will be ignored.
+diff --git a/c b/c
+new file mode 100755
+index 0000000..[..]
+--- /dev/null
++++ b/c
+@@ -0,0 +1 @@
++c
+/ No newline at end of file
+
+"#]]
+    );
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let commit = repo.head_commit();
+    assert_eq!(commit.summary.to_str().unwrap(), "new C");
+    let message = repo.raw().find_commit(commit.id).unwrap();
+    assert_eq!(message.message().unwrap(), "new C");
+
+    root.close().unwrap();
+}
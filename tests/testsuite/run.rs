@@ -0,0 +1,160 @@
+use crate::fixture::tree;
+
+#[test]
+#[cfg(unix)]
+fn run_per_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("first".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("second".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["run", "--branch", "--no-fail-fast", "--", "test", "!", "-f", "d"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+Switching to first: C
+Success with first
+Switching to second: D
+Failed with second: exit code 1
+  passed [..]s  first
+  failed [..]s  second
+Failed starting at second
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+fn stack(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("first".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("third".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn run_fail_fast_skips_dependents() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["run", "--branch", "--fail-fast", "--", "test", "!", "-f", "c"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+Switching to first: B
+Success with first
+Switching to second: C
+Failed with second: exit code 1
+  passed [..]s  first
+  failed [..]s  second
+  skipped           third
+Failed starting at second
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn run_from_to() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args([
+            "run", "--branch", "--from", "second", "--to", "second", "--", "true",
+        ])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Switching to second: C
+Success with second
+  passed [..]s  second
+...
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["run", "--from", "missing", "--", "true"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+could not find `missing`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn run_json() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args([
+            "run", "--branch", "--json", "--to", "second", "--", "test", "!", "-f", "c",
+        ])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stdout_eq(snapbox::str![[r#"
+[
+  {
+    "branches": [
+      "first"
+    ],
+    "commit": "[..]",
+    "duration_secs": [..],
+    "exit_code": 0,
+    "status": "passed",
+    "summary": "B"
+  },
+  {
+    "branches": [
+      "second"
+    ],
+    "commit": "[..]",
+    "duration_secs": [..],
+    "exit_code": 1,
+    "status": "failed",
+    "summary": "C"
+  }
+]
+
+"#]]);
+
+    root.close().unwrap();
+}
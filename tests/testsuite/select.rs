@@ -0,0 +1,151 @@
+use crate::fixture::tree;
+use snapbox::prelude::*;
+
+#[test]
+fn select_stack() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Branch("release".into()),
+            git_fixture::Command::Label("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("other".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+note: no stack is selected
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select", "--stack", "current", "--base", "release"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+Selected --base=release --stack=current
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+--base=release --stack=current
+
+"#]])
+        .stderr_eq(snapbox::str![]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format", "list"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+feature
+
+"#]])
+        .stderr_eq(snapbox::str![]);
+
+    // Flags still win over the selection
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format", "list", "--stack", "all"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+other
+feature
+
+"#]]
+        .unordered())
+        .stderr_eq(snapbox::str![]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select", "--stack", "frontend"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+no stack named `frontend`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn select_validated_on_load() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Branch("release".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select", "--onto", "release"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "--quiet", "-D", "release"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+selected stack is invalid, could not find `release`: revspec 'release' not found; update it with `git stack select` or reset it with `git stack select --clear`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["select", "--clear"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+Cleared the selected stack
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["next"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    root.close().unwrap();
+}
@@ -0,0 +1,120 @@
+use crate::fixture::tree;
+
+#[test]
+fn rehearsal_success_applies() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("self-test")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rehearsal_failure_leaves_repo_untouched() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "main")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "feature")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_feature_id = repo.find_local_branch("feature").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("self-test")
+        .current_dir(root_path)
+        .assert()
+        .failure();
+
+    let new_feature_id = repo.find_local_branch("feature").unwrap().id;
+    assert_eq!(old_feature_id, new_feature_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rehearsal_does_not_push() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    for args in [
+        &["remote", "add", "origin", remote_path.to_str().unwrap()][..],
+        &["push", "--quiet", "origin", "main", "feature~1:refs/heads/feature"],
+        &["fetch", "--quiet", "origin"],
+        &["branch", "--quiet", "--set-upstream-to=origin/feature", "feature"],
+        // Sneak `--push` past `self-test`
+        &["config", "stack.alias.ship", "--push"],
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(args)
+            .current_dir(&local_path)
+            .assert()
+            .success();
+    }
+
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    let old_feature_id = remote.refname_to_id("refs/heads/feature").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["self-test", "--dry-run", "ship"])
+        .current_dir(&local_path)
+        .assert();
+
+    let new_feature_id = remote.refname_to_id("refs/heads/feature").unwrap();
+    assert_eq!(old_feature_id, new_feature_id);
+
+    root.close().unwrap();
+}
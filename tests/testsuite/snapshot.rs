@@ -0,0 +1,125 @@
+use crate::fixture::tree;
+use snapbox::str;
+
+#[test]
+fn snapshot_diff() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("old".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--message=new C"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    repo.find_branch("old", git2::BranchType::Local)
+        .unwrap()
+        .delete()
+        .unwrap();
+    let main = repo
+        .find_branch("main", git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .peel_to_commit()
+        .unwrap();
+    repo.branch("new", &main, false).unwrap();
+    repo.set_head("refs/heads/new").unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["snapshot", "--diff=0"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+created new [..]
+deleted old [..]
+moved target [..] -> [..] (+1 -1)
+HEAD target -> new
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["snapshot", "--diff=1"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+no snapshot at index 1
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn snapshot_apply_by_message() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let old_target_id = repo.revparse_single("target").unwrap().id();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--message=new B"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_ne!(repo.revparse_single("target").unwrap().id(), old_target_id);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["snapshot"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![[r#"
+0: 2 branches, on `target`, [..] ago (reword)
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["snapshot", "--apply=reword"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Restored 2 branches
+
+"#]]);
+    assert_eq!(repo.revparse_single("target").unwrap().id(), old_target_id);
+    assert_eq!(repo.head().unwrap().shorthand(), Some("target"));
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["snapshot", "--apply=rebase"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+no snapshot `rebase`
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,98 @@
+use bstr::ByteSlice;
+use snapbox::assert_data_eq;
+use snapbox::prelude::*;
+use snapbox::str;
+
+#[test]
+fn squash_rebases_dependent_branches() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("A".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("B".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b"), ("c", "c")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("C".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Tree(git_fixture::Tree {
+                files: [("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")]
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect::<std::collections::HashMap<_, _>>(),
+                message: Some("D".to_owned()),
+                author: None,
+            }),
+            git_fixture::Command::Branch("child".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("squash")
+        .arg("feature")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(
+            str![[r#"
+note: to undo, run `git stack undo`
+
+"#]]
+            .raw(),
+        );
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+    assert_data_eq!(
+        feature.message_bytes().to_str().unwrap(),
+        str![[r#"
+B
+
+C
+"#]]
+        .raw()
+    );
+    let tree = feature.tree().unwrap();
+    assert!(tree.get_name("b").is_some());
+    assert!(tree.get_name("c").is_some());
+
+    let child_id = repo.find_local_branch("child").unwrap().id;
+    let child = repo.find_commit(child_id).unwrap();
+    assert_data_eq!(child.summary.to_str().unwrap(), str!["D"].raw());
+    assert_eq!(
+        repo.raw().find_commit(child_id).unwrap().parent_id(0).unwrap(),
+        feature_id
+    );
+    assert_eq!(repo.head_commit().id, child_id);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,2926 @@
+use crate::fixture::{conflicting_branches, git, git_config, tree};
+use snapbox::prelude::*;
+
+#[test]
+fn rebase_ignore_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("ignored".into()),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("ignored-child".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("e", "e")], "E"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let old_ignored_id = repo.find_local_branch("ignored").unwrap().id;
+    let old_child_id = repo.find_local_branch("ignored-child").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args([
+            "--rebase",
+            "--ignore-branch=ignored",
+            "--ignore-descendants",
+            "--format=silent",
+        ])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+    assert_eq!(
+        repo.find_local_branch("ignored").unwrap().id,
+        old_ignored_id
+    );
+    assert_eq!(
+        repo.find_local_branch("ignored-child").unwrap().id,
+        old_child_id
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_existing_case_variant_branches() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("Feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    for name in ["feature", "Feature"] {
+        let id = repo.find_local_branch(name).unwrap().id;
+        let commit = repo.raw().find_commit(id).unwrap();
+        assert_eq!(commit.parent_id(0).unwrap(), main_id, "{name}");
+    }
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_skips_branch_checked_out_elsewhere() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("elsewhere".into()),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("elsewhere-child".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("e", "e")], "E"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["worktree", "add", "--quiet", "../other", "elsewhere"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::discover(&local_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let old_elsewhere_id = repo.find_local_branch("elsewhere").unwrap().id;
+    let old_child_id = repo.find_local_branch("elsewhere-child").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Leaving the stack with `elsewhere` in place, it is checked out in `[..]other/`
+...
+"#]]);
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+    assert_eq!(
+        repo.find_local_branch("elsewhere").unwrap().id,
+        old_elsewhere_id
+    );
+    assert_eq!(
+        repo.find_local_branch("elsewhere-child").unwrap().id,
+        old_child_id
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn ignore_unknown_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--ignore-branch=missing"])
+        .current_dir(root_path)
+        .assert()
+        .code(64)
+        .stderr_eq(snapbox::str![[r#"
+could not find branch `missing`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn default_command_pipeline() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.default-command'='rebase'")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn default_command_skipped_for_display_flags() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format", "list"])
+        .env("GIT_CONFIG_PARAMETERS", "'stack.default-command'='push'")
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn default_command_sync() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.default-command'='sync'")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn default_command_invalid() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.default-command'='pull+rebsae'")
+        .current_dir(root_path)
+        .assert()
+        .code(78)
+        .stderr_eq(
+            "\
+invalid `stack.default-command=pull+rebsae`: expected `show`, `sync`, or `+`-separated `pull`, `rebase`, `push`, got `rebsae`
+",
+        );
+
+    root.close().unwrap();
+}
+
+/// A branch handed off by a co-worker: their signed commit with one of ours on top
+fn handed_off_branch(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let base = repo.head().unwrap().peel_to_commit().unwrap();
+    let coworker = git2::Signature::now("Co Worker", "coworker@example.com").unwrap();
+
+    let mut index = repo.index().unwrap();
+    std::fs::write(root_path.join("c"), "c").unwrap();
+    index.add_path(std::path::Path::new("c")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let buffer = repo
+        .commit_create_buffer(&coworker, &coworker, "C", &tree, &[&base])
+        .unwrap();
+    let signed_id = repo
+        .commit_signed(
+            buffer.as_str().unwrap(),
+            "-----BEGIN PGP SIGNATURE-----\n\nfake\n-----END PGP SIGNATURE-----",
+            None,
+        )
+        .unwrap();
+    let signed = repo.find_commit(signed_id).unwrap();
+
+    std::fs::write(root_path.join("d"), "d").unwrap();
+    index.add_path(std::path::Path::new("d")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let me = repo.signature().unwrap();
+    let mine_id = repo.commit(None, &me, &me, "D", &tree, &[&signed]).unwrap();
+    let mine = repo.find_commit(mine_id).unwrap();
+    repo.branch("feature", &mine, false).unwrap();
+    repo.set_head("refs/heads/feature").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+}
+
+#[test]
+fn resign_strip_rewrites_signed_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    handed_off_branch(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.parent(0).unwrap().parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn resign_block_keeps_signed_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    handed_off_branch(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_feature_id = repo.find_local_branch("feature").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .env("GIT_CONFIG_PARAMETERS", "'stack.resign'='block'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(
+            "\
+WARN: Leaving branches with commits signed by others in place (`stack.resign=block`): feature
+2 branches already up to date
+note: to undo, run `git stack undo`
+",
+        );
+
+    assert_eq!(
+        repo.find_local_branch("feature").unwrap().id,
+        old_feature_id
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn show_base_history() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--base-history=1")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) C
+⌽ feature (ready) D
+┊ [..] B
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn show_max_width() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(
+                &[("a", "a"), ("b", "b")],
+                "A subject too long to fit on a narrow terminal",
+            ),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--max-width=30")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+⌽ feature (ready) A subject t…
+
+"#]]);
+
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.show-max-width", "24"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--icons=ascii")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+* feature (ready) A s...
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--full")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+⌽ feature (ready) A subject too long to fit on a narrow terminal
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn show_as_of_snapshot() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["reword", "--message=Reworded B"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    git(root_path, &["branch", "-D", "main"]);
+    git(root_path, &["branch", "other", "feature"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--as-of=0", "--format=list"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+feature
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--as-of=0"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+⌽ feature (ready) B
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--as-of=1h"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+no snapshot from before `1h`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn show_skips_branch_missing_commit() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    // Like an interrupted fetch, the ref was written but not the objects
+    std::fs::write(
+        root_path.join(".git/refs/heads/broken"),
+        "1234567890123456789012345678901234567890\n",
+    )
+    .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+⌽ feature (ready) B
+
+"#]])
+        .stderr_eq(snapbox::str![[r#"
+WARN: Skipping `broken`, its commit 1234567890123456789012345678901234567890 is missing (try `git fetch`)
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["why", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Skipping `broken`, its commit 1234567890123456789012345678901234567890 is missing (try `git fetch`)
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+fn diverged_stacks(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Label("shared".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("first".into()),
+            git_fixture::Command::Reset("shared".into()),
+            tree(&[("a", "a"), ("b", "b"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("second".into()),
+        ],
+        sleep: Some(std::time::Duration::from_secs(1)),
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+fn preserve_order_reports_instead_of_realigning() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    diverged_stacks(root_path);
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let old_first_id = repo.find_local_branch("first").unwrap().id;
+    let second_id = repo.find_local_branch("second").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--repair", "--format=silent"])
+        .env("GIT_CONFIG_PARAMETERS", "'stack.preserve-order'='true'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Leaving diverged stacks in place (`stack.preserve-order=true`): first
+2 branches already up to date
+note: to undo, run `git stack undo`
+
+"#]]);
+    assert_eq!(repo.find_local_branch("first").unwrap().id, old_first_id);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--repair", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    let first_id = repo.find_local_branch("first").unwrap().id;
+    let first = repo.raw().find_commit(first_id).unwrap();
+    assert_eq!(first.parent_id(0).unwrap(), second_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn repair_after_external_rebase() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    // `first` was rebased onto `main` with plain `git rebase`, leaving `second` on the original
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("m", "m")], "M"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b"), ("m", "m")], "B"),
+            git_fixture::Command::Branch("first".into()),
+        ],
+        sleep: Some(std::time::Duration::from_secs(1)),
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let first_id = repo.find_local_branch("first").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(repo.find_local_branch("first").unwrap().id, first_id);
+    let second_id = repo.find_local_branch("second").unwrap().id;
+    let second = repo.raw().find_commit(second_id).unwrap();
+    assert_eq!(second.parent_id(0).unwrap(), first_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_drop_by_patch_id() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    // Upstream applied `B` on top of other changes, so no pulled tree matches the local one
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("x", "x")], "X"),
+            tree(&[("a", "a"), ("b", "b"), ("x", "x")], "B"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--base=main~2", "--onto=main", "--format=silent"])
+        .env("GIT_CONFIG_PARAMETERS", "'stack.dropByPatchId'='true'")
+        .current_dir(root_path)
+        .assert()
+        .success();
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let feature = repo.raw().find_commit(feature_id).unwrap();
+    assert_eq!(feature.summary(), Some("C"));
+    assert_eq!(feature.parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+fn sibling_stacks(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("main".into()),
+            tree(&[("a", "a"), ("z", "z")], "Z"),
+            git_fixture::Command::Branch("zeta".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("m", "m")], "M"),
+            git_fixture::Command::Branch("mu".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("beta".into()),
+            git_fixture::Command::Reset("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn sibling_stacks_sort_by_name() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    sibling_stacks(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.show-stacked'='true'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+├─┐
+│ ⌽ beta (ready) B
+├─┐
+│ ⌽ mu (ready) M
+│ 
+⌽ zeta (ready) Z
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.show-stacked'='false'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+├─┐
+│ ⌽ beta (ready) B
+├─┐
+│ ⌽ mu (ready) M
+│ 
+⌽ zeta (ready) Z
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn icon_themes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    sibling_stacks(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--icons=unicode")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+├─┐
+│ ⌽ beta (ready) B
+├─┐
+│ ⌽ mu (ready) M
+│ 
+⌽ zeta (ready) Z
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--icons=ascii")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+|-+
+| * beta (ready) B
+|-+
+| * mu (ready) M
+| 
+* zeta (ready) Z
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .env("GIT_CONFIG_PARAMETERS", "'stack.show-icons'='emoji'")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main 🔌 no remote A
+├─┐
+│ ⌽ beta 🚀 ready B
+├─┐
+│ ⌽ mu 🚀 ready M
+│ 
+⌽ zeta 🚀 ready Z
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn color_palettes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    sibling_stacks(root_path);
+
+    let render = |config: &str| {
+        let output = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg("--color=always")
+            .env("GIT_CONFIG_PARAMETERS", config)
+            .current_dir(root_path)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    // Branches ready to push are `good`, remote status is `warn`
+    let default = render("");
+    assert!(default.contains("\u{1b}[1m\u{1b}[36mbeta"), "{default:?}");
+    let colorblind = render("'stack.palette'='colorblind'");
+    assert!(colorblind.contains("\u{1b}[1m\u{1b}[36mbeta"), "{colorblind:?}");
+    assert!(!colorblind.contains("\u{1b}[32m"), "{colorblind:?}");
+    let monochrome = render("'stack.palette'='monochrome-bold' 'color.stack.warn'='magenta ul'");
+    assert!(!monochrome.contains("\u{1b}[36m"), "{monochrome:?}");
+    assert!(
+        monochrome.contains("\u{1b}[4m\u{1b}[35m(no remote)"),
+        "{monochrome:?}"
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn ascii_base_history() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--icons=ascii", "--base-history=1"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) B
+* feature (ready) C
+: [..] A
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn graph_cache() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    let cache_path = root_path.join(".git/git-stack/graph-cache.json");
+
+    let run = |args: &[&str]| {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .args(args)
+            .current_dir(root_path)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    let uncached = run(&["--format=graph"]);
+    assert!(cache_path.exists());
+    let cached = run(&["--format=graph"]);
+    assert_eq!(cached, uncached);
+
+    // Moving a branch is picked up
+    snapbox::cmd::Command::new("git")
+        .args(["commit", "--quiet", "--allow-empty", "-m", "C"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    let moved = String::from_utf8(run(&["--format=graph"])).unwrap();
+    assert!(moved.contains("C"), "{moved}");
+
+    std::fs::remove_file(&cache_path).unwrap();
+    run(&["--format=graph", "--no-cache"]);
+    assert!(!cache_path.exists());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn no_color_plain_output() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    // `--pull` without a remote warns through the logger, covering it along with the graph and
+    // the notes
+    let forced = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--pull")
+        .env("CLICOLOR_FORCE", "1")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert!(forced.stdout.contains(&b'\x1b'));
+    assert!(forced.stderr.contains(&b'\x1b'));
+
+    for (arg, env) in [
+        (None, Some(("NO_COLOR", "1"))),
+        (Some("--color=never"), None),
+    ] {
+        let mut cmd = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg("--pull")
+            .args(arg)
+            .env("CLICOLOR_FORCE", "1")
+            .current_dir(root_path);
+        if let Some((key, value)) = env {
+            cmd = cmd.env(key, value);
+        }
+        let output = cmd.assert().success().get_output().clone();
+        assert!(!output.stdout.contains(&b'\x1b'), "{arg:?} {env:?}");
+        assert!(!output.stderr.contains(&b'\x1b'), "{arg:?} {env:?}");
+        snapbox::assert_data_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            snapbox::str![[r#"
+WARN: Skipping pull of `main` local branch
+1 branch already up to date
+note: to undo, run `git stack undo`
+
+"#]]
+        );
+    }
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn push_create_mr_targets_parent() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+
+    // Record push options like GitLab would act on them
+    let remote = git2::Repository::init_bare(&remote_path).unwrap();
+    remote
+        .config()
+        .unwrap()
+        .set_bool("receive.advertisePushOptions", true)
+        .unwrap();
+    let hook_path = remote_path.join("hooks/pre-receive");
+    std::fs::write(
+        &hook_path,
+        "#!/bin/sh
+i=0
+while [ $i -lt \"$GIT_PUSH_OPTION_COUNT\" ]; do
+    eval \"echo \\$GIT_PUSH_OPTION_$i\" >> options.txt
+    i=$((i + 1))
+done
+",
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&hook_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&hook_path, permissions).unwrap();
+
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--create-mr", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let options = std::fs::read_to_string(remote_path.join("options.txt")).unwrap();
+    snapbox::assert_data_eq!(
+        options,
+        snapbox::str![[r#"
+merge_request.create
+merge_request.target=main
+
+"#]]
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_gerrit_refs_for_protected_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.pushStyle", "gerrit-refs-for"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    // The remote's `HEAD` is left on the missing `master`, which libgit2 doesn't need
+    let output = snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--format=silent", "-v"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("Falling back to `git push`"), "{stderr}");
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let feature_id = local
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .target()
+        .unwrap();
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    let review_id = remote
+        .find_reference("refs/for/main")
+        .unwrap()
+        .target()
+        .unwrap();
+    assert_eq!(review_id, feature_id);
+    assert!(remote
+        .find_reference("refs/heads/feature")
+        .is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn push_gerrit_refs_for_sibling_stacks() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("first".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+
+    // Like Gerrit, record what was sent for review without keeping `refs/for/main`
+    git2::Repository::init_bare(&remote_path).unwrap();
+    let hook_path = remote_path.join("hooks/post-receive");
+    std::fs::write(
+        &hook_path,
+        "#!/bin/sh
+while read old new ref; do
+    case \"$ref\" in
+    refs/for/*)
+        echo \"$new $ref\" >> reviews.txt
+        git update-ref -d \"$ref\"
+        ;;
+    esac
+done
+",
+    )
+    .unwrap();
+    let mut permissions = std::fs::metadata(&hook_path).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&hook_path, permissions).unwrap();
+
+    // libgit2 pushes to a local remote without running its hooks, so go through `git push`
+    git(
+        &local_path,
+        &[
+            "remote",
+            "add",
+            "origin",
+            &format!("ext::git %s {}", remote_path.display()),
+        ],
+    );
+    git(&local_path, &["config", "protocol.ext.allow", "always"]);
+    git(&local_path, &["push", "--quiet", "origin", "main"]);
+    git(&local_path, &["config", "stack.pushStyle", "gerrit-refs-for"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let mut expected: Vec<_> = ["first", "second"]
+        .into_iter()
+        .map(|branch| {
+            let id = local.revparse_single(branch).unwrap().id();
+            format!("{id} refs/for/main")
+        })
+        .collect();
+    expected.sort();
+    let reviews = std::fs::read_to_string(remote_path.join("reviews.txt")).unwrap();
+    let mut reviews: Vec<_> = reviews.lines().map(ToOwned::to_owned).collect();
+    reviews.sort();
+    assert_eq!(reviews, expected);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_until_commit() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "WIP: C"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--push-until-commit", "HEAD~", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+cannot push WIP commit [..] (WIP: C)
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--push-until-commit", "HEAD~2", "--format=list"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+...
+Pushed [..] (B) to origin/feature, leaving 2 commits on `feature` unpushed
+
+"#]]);
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let feature = local
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap();
+    let feature_id = feature.get().target().unwrap();
+    let until_id = local
+        .revparse_single("feature~2")
+        .unwrap()
+        .id();
+    assert_eq!(
+        feature.upstream().unwrap().get().target().unwrap(),
+        until_id
+    );
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    let remote_id = remote
+        .find_reference("refs/heads/feature")
+        .unwrap()
+        .target()
+        .unwrap();
+    assert_eq!(remote_id, until_id);
+    assert_ne!(remote_id, feature_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_until_commit_push_remote() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+    let fork_path = root_path.join("fork.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    git2::Repository::init_bare(&fork_path).unwrap();
+    git(
+        &local_path,
+        &["remote", "add", "origin", remote_path.to_str().unwrap()],
+    );
+    git(
+        &local_path,
+        &["remote", "add", "fork", fork_path.to_str().unwrap()],
+    );
+    git(&local_path, &["push", "--quiet", "origin", "main"]);
+    git(&local_path, &["config", "branch.feature.pushRemote", "fork"]);
+    git(
+        &local_path,
+        &["config", "remote.fork.push", "refs/heads/*:refs/heads/me/*"],
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--push-until-commit", "HEAD~", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+...
+Pushed [..] (B) to fork/me/feature, leaving 1 commit on `feature` unpushed
+
+"#]]);
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let until_id = local.revparse_single("feature~").unwrap().id();
+    let fork = git2::Repository::open(&fork_path).unwrap();
+    assert_eq!(fork.refname_to_id("refs/heads/me/feature").unwrap(), until_id);
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_until_commit_gerrit_refs_for() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    git(
+        &local_path,
+        &["remote", "add", "origin", remote_path.to_str().unwrap()],
+    );
+    git(&local_path, &["push", "--quiet", "origin", "main"]);
+    git(&local_path, &["config", "stack.pushStyle", "gerrit-refs-for"]);
+    git(&local_path, &["config", "branch.feature.stack-draft", "true"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--push-until-commit", "HEAD~", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+cannot push draft `feature`, pass `--draft` to push it anyway
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args([
+            "--push",
+            "--draft",
+            "--push-until-commit",
+            "HEAD~",
+            "--format=silent",
+        ])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+...
+Pushed [..] (B) to origin refs/for/main%wip, leaving 1 commit on `feature` unpushed
+
+"#]]);
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let until_id = local.revparse_single("feature~").unwrap().id();
+    assert!(local
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .upstream()
+        .is_err());
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert_eq!(remote.refname_to_id("refs/for/main%wip").unwrap(), until_id);
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn pull_fast_forwards_protected_branch_checked_out_elsewhere() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let other_path = root_path.join("other");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("upstream".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("f", "f")], "F"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    for args in [
+        &["remote", "add", "origin", remote_path.to_str().unwrap()][..],
+        &["push", "--quiet", "origin", "upstream:main"],
+        &["branch", "--quiet", "-D", "upstream"],
+        &["worktree", "add", "--quiet", other_path.to_str().unwrap(), "main"],
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(args)
+            .current_dir(&local_path)
+            .assert()
+            .success();
+    }
+
+    // Uncommitted changes keep `main` where it is
+    std::fs::write(other_path.join("a"), "changed").unwrap();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--pull", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+...
+WARN: Leaving `main` in place, it is checked out in `[..]other/` with uncommitted changes
+...
+"#]]);
+    let local = git2::Repository::open(&local_path).unwrap();
+    let main_id = |local: &git2::Repository| {
+        local
+            .find_branch("main", git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .target()
+            .unwrap()
+    };
+    let remote_id = local
+        .find_reference("refs/remotes/origin/main")
+        .unwrap()
+        .target()
+        .unwrap();
+    assert_ne!(main_id(&local), remote_id);
+
+    std::fs::write(other_path.join("a"), "a").unwrap();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--pull", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+...
+Fast-forwarding `main` to `origin/main`
+...
+"#]]);
+    assert_eq!(main_id(&local), remote_id);
+    assert_eq!(
+        std::fs::read_to_string(other_path.join("b")).unwrap(),
+        "b"
+    );
+    let other = git2::Repository::open(&other_path).unwrap();
+    assert!(other
+        .statuses(Some(git2::StatusOptions::new().include_untracked(false)))
+        .unwrap()
+        .is_empty());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_warns_of_conflicts() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_branches(root_path);
+    let objects = root_path.join(".git/objects");
+    let count_objects = || {
+        std::fs::read_dir(&objects)
+            .unwrap()
+            .map(|dir| std::fs::read_dir(dir.unwrap().path()).unwrap().count())
+            .sum::<usize>()
+    };
+    let before = count_objects();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--dry-run", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .stderr_eq(snapbox::str![[r#"
+...
+WARN: Branch `feature` will conflict on [..] (B)
+WARN:   Blocked dependents: child
+...
+"#]]);
+    // The check merges in memory
+    assert_eq!(count_objects(), before);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_skip_conflicts() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_branches(root_path);
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::legacy::git::GitRepo::new(repo);
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let child_id = repo.find_local_branch("child").unwrap().id;
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--skip-conflicts", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Leaving `feature` in place, it will conflict on [..] (B)
+WARN:   Blocked dependents: child
+...
+"#]]);
+
+    assert_eq!(repo.find_local_branch("feature").unwrap().id, feature_id);
+    assert_eq!(repo.find_local_branch("child").unwrap().id, child_id);
+    let other_id = repo.find_local_branch("other").unwrap().id;
+    let other = repo.raw().find_commit(other_id).unwrap();
+    assert_eq!(other.parent_id(0).unwrap(), main_id);
+
+    root.close().unwrap();
+}
+
+fn diverged_remotes(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("origin-main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("upstream-main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("f", "f")], "F"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    for (remote, branch) in [("origin", "origin-main"), ("upstream", "upstream-main")] {
+        snapbox::cmd::Command::new("git")
+            .args([
+                "update-ref",
+                &format!("refs/remotes/{remote}/main"),
+                branch,
+            ])
+            .current_dir(root_path)
+            .assert()
+            .success();
+        snapbox::cmd::Command::new("git")
+            .args(["branch", "--quiet", "-D", branch])
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+}
+
+#[test]
+fn warn_diverged_protected_remotes() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    diverged_remotes(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: `upstream/main` has diverged from `origin/main` (1 ahead, 1 behind), using `origin/main`
+WARN:   To pick the remote, set `stack.protected-remote` to `main=upstream`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn protected_remote_per_pattern() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    diverged_remotes(root_path);
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.protected-remote", "ma*=upstream"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: `origin/main` has diverged from `upstream/main` (1 ahead, 1 behind), using `upstream/main`
+WARN:   To pick the remote, set `stack.protected-remote` to `main=origin`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    let repo = git2::Repository::open(root_path).unwrap();
+    let upstream_id = repo
+        .find_reference("refs/remotes/upstream/main")
+        .unwrap()
+        .target()
+        .unwrap();
+    let feature = repo
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(feature.parent_id(0).unwrap(), upstream_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_refuses_tagged_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Tag("v1".into()),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+ERROR: Cannot rewrite [..] (C), tagged `v1`
+ERROR:   Use `--force` to rewrite anyway, leaving the tags on the original commits
+refusing to rewrite tagged commits
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--force", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Rewriting [..] (C), leaving `v1` on the original commit
+...
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn onto_any_revspec() {
+    for (onto, expected) in [
+        ("upstream/HEAD", "upstream"),
+        (":/^B", "origin"),
+        ("v1", "upstream"),
+        ("@{-1}", "origin"),
+    ] {
+        let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+        let root_path = root.path().unwrap();
+        diverged_remotes(root_path);
+        snapbox::cmd::Command::new("git")
+            .args([
+                "symbolic-ref",
+                "refs/remotes/upstream/HEAD",
+                "refs/remotes/upstream/main",
+            ])
+            .current_dir(root_path)
+            .assert()
+            .success();
+        snapbox::cmd::Command::new("git")
+            .args(["tag", "-a", "v1", "-m", "v1", "upstream/main"])
+            .current_dir(root_path)
+            .assert()
+            .success();
+        // Leave `main` at `origin/main`, as the previously checked out branch
+        for args in [
+            &["branch", "--quiet", "-f", "main", "origin/main"][..],
+            &["switch", "--quiet", "main"],
+            &["switch", "--quiet", "feature"],
+        ] {
+            snapbox::cmd::Command::new("git")
+                .args(args)
+                .current_dir(root_path)
+                .assert()
+                .success();
+        }
+
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .args(["--rebase", "--format=silent", "--onto", onto])
+            .current_dir(root_path)
+            .assert()
+            .success();
+        let repo = git2::Repository::open(root_path).unwrap();
+        let expected_id = repo
+            .find_reference(&format!("refs/remotes/{expected}/main"))
+            .unwrap()
+            .target()
+            .unwrap();
+        let feature = repo
+            .find_branch("feature", git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(feature.parent_id(0).unwrap(), expected_id, "{onto}");
+
+        root.close().unwrap();
+    }
+}
+
+#[test]
+fn onto_unknown_revspec() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    diverged_remotes(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format=silent", "--onto", "no-such-rev"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+...
+could not find `no-such-rev`: revspec 'no-such-rev' not found
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+fn stale_stack(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature1".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature2".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("feature3".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("m", "m")], "M"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature3"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn rebase_only_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stale_stack(root_path);
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let feature1_id = repo.find_local_branch("feature1").unwrap().id;
+    let feature3_id = repo.find_local_branch("feature3").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent", "--only", "feature2"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(repo.find_local_branch("feature1").unwrap().id, feature1_id);
+    assert_eq!(repo.find_local_branch("feature3").unwrap().id, feature3_id);
+    let feature2_id = repo.find_local_branch("feature2").unwrap().id;
+    let feature2_base_id = repo.raw().find_commit(feature2_id).unwrap().parent_id(0).unwrap();
+    let feature2_base = repo.raw().find_commit(feature2_base_id).unwrap();
+    assert_eq!(feature2_base.summary(), Some("B"));
+    assert_eq!(feature2_base.parent_id(0).unwrap(), main_id);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent", "--only", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+could not find branch `feature`
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_only_branch_with_descendants() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stale_stack(root_path);
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let main_id = repo.find_local_branch("main").unwrap().id;
+    let feature1_id = repo.find_local_branch("feature1").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args([
+            "--rebase",
+            "--format=silent",
+            "--only",
+            "feature2",
+            "--with-descendants",
+        ])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(repo.find_local_branch("feature1").unwrap().id, feature1_id);
+    let feature2_id = repo.find_local_branch("feature2").unwrap().id;
+    let feature3_id = repo.find_local_branch("feature3").unwrap().id;
+    assert_eq!(
+        repo.raw().find_commit(feature3_id).unwrap().parent_id(0).unwrap(),
+        feature2_id
+    );
+    assert_eq!(
+        repo.raw().merge_base(feature2_id, main_id).unwrap(),
+        main_id
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_draft() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("main".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B\n\nStack-Draft: true\n"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("other".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("ready".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["config", "branch.other.stack-draft", "true"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack=all"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+├─┐
+│ ⌽ feature (draft) B
+├─┐
+│ ⌽ other (draft) C
+│ 
+⌽ ready (ready) D
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert!(remote.find_reference("refs/heads/ready").is_ok());
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+    assert!(remote.find_reference("refs/heads/other").is_err());
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--draft", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    assert!(remote.find_reference("refs/heads/feature").is_ok());
+    assert!(remote.find_reference("refs/heads/other").is_ok());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_default_upstream() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    git(
+        &local_path,
+        &["remote", "add", "origin", remote_path.to_str().unwrap()],
+    );
+    git(&local_path, &["push", "--quiet", "origin", "main"]);
+    git(
+        &local_path,
+        &["push", "--quiet", "-u", "origin", "feature:review/feature"],
+    );
+    git(&local_path, &["config", "push.default", "upstream"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main A
+⌽ feature (pushed) B
+
+"#]]);
+
+    git(&local_path, &["commit", "--quiet", "--allow-empty", "-m", "C"]);
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let feature_id = local.revparse_single("feature").unwrap().id();
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert_eq!(
+        remote.revparse_single("review/feature").unwrap().id(),
+        feature_id
+    );
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_refspec() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    git(
+        &local_path,
+        &["remote", "add", "origin", remote_path.to_str().unwrap()],
+    );
+    git(&local_path, &["push", "--quiet", "origin", "main"]);
+    git(
+        &local_path,
+        &["config", "remote.origin.push", "refs/heads/*:refs/heads/me/*"],
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main A
+⌽ feature (pushed) B
+
+"#]]);
+
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    assert!(remote.find_reference("refs/heads/me/feature").is_ok());
+    assert!(remote.find_reference("refs/heads/feature").is_err());
+
+    root.close().unwrap();
+}
+
+#[test]
+fn separate_households() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("main".into()),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("r", "r")], "R"),
+            git_fixture::Command::Branch("release".into()),
+            tree(&[("a", "a"), ("r", "r"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("fix".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git(root_path, &["config", "stack.protected-branch", "release"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack=all", "--separate-households"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+Stacks onto main:
+main (no remote) A
+⌽ feature (ready) B
+
+Stacks onto release:
+release (no remote) R
+⌽ fix (ready) C
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_stale_base() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main", "feature:refs/tmp/feature"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["config", "stack.fresh-max-age", "0s"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    // Squash-merge `feature` on the remote, without the local repo noticing
+    let remote = git2::Repository::open(&remote_path).unwrap();
+    let main = remote
+        .find_reference("refs/heads/main")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let feature = remote
+        .find_reference("refs/tmp/feature")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    remote
+        .commit(
+            Some("refs/heads/main"),
+            &feature.author(),
+            &feature.committer(),
+            "B (#1)",
+            &feature.tree().unwrap(),
+            &[&main],
+        )
+        .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+WARN: `origin/main` was last fetched [..] ago, squash-merged branches may be missed; run `git fetch --prune origin` or pass `--auto-fresh`
+1 branch already up to date
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--auto-fresh", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+From [..]
+   [..]  main       -> origin/main
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    let local = git2::Repository::open(&local_path).unwrap();
+    let feature_id = local
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .target();
+    let main_id = local
+        .find_reference("refs/remotes/origin/main")
+        .unwrap()
+        .target();
+    assert_eq!(feature_id, main_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn list_verbose() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("first".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("third".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main", "first", "first:second"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--format=list-verbose"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+first	main	0	0	pushed
+second	first	1	0	ahead
+third	second	-	-	-
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn branch_push_remote_override() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("forked".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("plain".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    for remote in ["origin", "fork"] {
+        let remote_path = root_path.join(format!("{remote}.git"));
+        git2::Repository::init_bare(&remote_path).unwrap();
+        snapbox::cmd::Command::new("git")
+            .args(["remote", "add", remote])
+            .arg(&remote_path)
+            .current_dir(&local_path)
+            .assert()
+            .success();
+        snapbox::cmd::Command::new("git")
+            .args(["push", "--quiet", remote, "main"])
+            .current_dir(&local_path)
+            .assert()
+            .success();
+    }
+    snapbox::cmd::Command::new("git")
+        .args(["config", "branch.forked.stack-push-remote", "fork"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let pushed = |remote: &str| {
+        let repo = git2::Repository::open(root_path.join(format!("{remote}.git"))).unwrap();
+        let mut names: Vec<_> = repo
+            .branches(Some(git2::BranchType::Local))
+            .unwrap()
+            .map(|b| b.unwrap().0.name().unwrap().unwrap().to_owned())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(pushed("origin"), ["main", "plain"]);
+    assert_eq!(pushed("fork"), ["forked", "main"]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn git_push_remote_config() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("forked".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("plain".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    for remote in ["upstream", "origin", "fork"] {
+        let remote_path = root_path.join(format!("{remote}.git"));
+        git2::Repository::init_bare(&remote_path).unwrap();
+        git(
+            &local_path,
+            &["remote", "add", remote, remote_path.to_str().unwrap()],
+        );
+        git(&local_path, &["push", "--quiet", remote, "main"]);
+    }
+    // Triangular workflow: pull from `upstream`, push to `origin`, except `forked`
+    git(&local_path, &["config", "stack.pull-remote", "upstream"]);
+    git(&local_path, &["config", "remote.pushDefault", "origin"]);
+    git(&local_path, &["config", "branch.forked.pushRemote", "fork"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack=all", "--icons=ascii"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main A
+|-+
+| * forked (ready to fork) B
+| 
+* plain (ready) C
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    let pushed = |remote: &str| {
+        let repo = git2::Repository::open(root_path.join(format!("{remote}.git"))).unwrap();
+        let mut names: Vec<_> = repo
+            .branches(Some(git2::BranchType::Local))
+            .unwrap()
+            .map(|b| b.unwrap().0.name().unwrap().unwrap().to_owned())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(pushed("upstream"), ["main"]);
+    assert_eq!(pushed("origin"), ["main", "plain"]);
+    assert_eq!(pushed("fork"), ["forked", "main"]);
+
+    git(&local_path, &["switch", "--quiet", "forked"]);
+    git(&local_path, &["commit", "--quiet", "--allow-empty", "-m", "D"]);
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--stack=all", "--icons=ascii"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main A
+|-+
+| * plain (pushed) C
+| 
+* [..] B
+* forked (1 ahead of fork) D
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+/// `feature` to push and `other` amended after someone else pushed to it
+fn push_lease_fixture(root_path: &std::path::Path) -> std::path::PathBuf {
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("other".into()),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("theirs".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    git2::Repository::init_bare(&remote_path).unwrap();
+    git(
+        &local_path,
+        &["remote", "add", "origin", remote_path.to_str().unwrap()],
+    );
+    git(&local_path, &["push", "--quiet", "origin", "main", "other"]);
+    // Someone else pushes to `other` without the local repo noticing
+    git(&local_path, &["push", "--quiet", "origin", "theirs:other"]);
+    git(&local_path, &["update-ref", "refs/remotes/origin/other", "other"]);
+    git(&local_path, &["switch", "--quiet", "other"]);
+    git(&local_path, &["branch", "--quiet", "-D", "theirs"]);
+    git(&local_path, &["commit", "--quiet", "--amend", "-m", "C2"]);
+
+    local_path
+}
+
+fn remote_summary(root_path: &std::path::Path, branch: &str) -> Option<String> {
+    let remote = git2::Repository::open(root_path.join("remote.git")).unwrap();
+    let commit = remote
+        .find_reference(&format!("refs/heads/{branch}"))
+        .ok()?
+        .peel_to_commit()
+        .unwrap();
+    commit.summary().map(ToOwned::to_owned)
+}
+
+#[test]
+fn push_lease_rejects_deleted_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = push_lease_fixture(root_path);
+    git(&local_path, &["push", "--quiet", "--force", "origin", "feature"]);
+    git(&local_path, &["fetch", "--quiet", "origin"]);
+    git(&local_path, &["reset", "--quiet", "--hard", "origin/other"]);
+    // Someone else deletes `feature` without the local repo noticing
+    git(
+        &root_path.join("remote.git"),
+        &["update-ref", "-d", "refs/heads/feature"],
+    );
+    git(&local_path, &["switch", "--quiet", "feature"]);
+    git(&local_path, &["commit", "--quiet", "--amend", "-m", "B2"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent", "--no-hooks"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+...
+WARN: `feature` was rejected by `origin`, stale info
+Could not push feature
+
+"#]]);
+
+    assert_eq!(remote_summary(root_path, "feature"), None);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn push_batch_keeps_lease_atomically() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = push_lease_fixture(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent", "--no-hooks"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(
+            snapbox::str![[r#"
+...
+WARN: `other` was rejected by `origin`, stale info
+WARN: `feature` was rejected by `origin`, atomic push failed
+Could not push [..]
+
+"#]]
+            .unordered(),
+        );
+
+    assert_eq!(remote_summary(root_path, "other").as_deref(), Some("D"));
+    assert_eq!(remote_summary(root_path, "feature"), None);
+
+    // Without the stale branch, the rest go in one push
+    git(&local_path, &["fetch", "--quiet", "origin"]);
+    git(&local_path, &["reset", "--quiet", "--hard", "origin/other"]);
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--push", "--stack=all", "--format=silent", "--no-hooks"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![])
+        .stderr_eq(snapbox::str![[r#"
+Pushed feature to origin/feature
+
+"#]]);
+    assert_eq!(remote_summary(root_path, "feature").as_deref(), Some("B"));
+    let local = git2::Repository::open(&local_path).unwrap();
+    let config = local.config().unwrap().snapshot().unwrap();
+    assert_eq!(
+        config.get_str("branch.feature.merge").unwrap(),
+        "refs/heads/feature"
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn branch_auto_fixup_and_protected_overrides() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            tree(&[("a", "a"), ("b", "bb")], "fixup! B"),
+            git_fixture::Command::Branch("squashed".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            tree(&[("a", "a"), ("c", "cc")], "fixup! C"),
+            git_fixture::Command::Branch("kept".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    for (key, value) in [
+        ("stack.auto-fixup", "squash"),
+        ("branch.kept.stack-auto-fixup", "ignore"),
+        ("branch.release.stack-protected", "true"),
+    ] {
+        snapbox::cmd::Command::new("git")
+            .args(["config", key, value])
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+    snapbox::cmd::Command::new("git")
+        .args(["branch", "release", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--protected"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main
+release
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--stack=all", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let count = |branch: &str| {
+        let mut revwalk = repo.revwalk().unwrap();
+        revwalk.push_range(&format!("main..{branch}")).unwrap();
+        revwalk.count()
+    };
+    assert_eq!(count("squashed"), 1);
+    assert_eq!(count("kept"), 2);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn rebase_autosquash_only() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            tree(&[("a", "a"), ("c", "cc"), ("d", "d")], "fixup! C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::discover(root_path).unwrap();
+    let base_id = repo.revparse_single("main~").unwrap().id();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--autosquash-only", "--format=silent"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let feature = repo.revparse_single("feature").unwrap().peel_to_commit().unwrap();
+    assert_eq!(feature.summary(), Some("D"));
+    let squashed = feature.parent(0).unwrap();
+    assert_eq!(squashed.summary(), Some("C"));
+    assert_eq!(squashed.parent_id(0).unwrap(), base_id);
+    let blob = squashed.tree().unwrap().get_name("c").unwrap().id();
+    assert_eq!(repo.find_blob(blob).unwrap().content(), b"cc");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--autosquash-only", "--pull"])
+        .current_dir(root_path)
+        .assert()
+        .failure();
+
+    root.close().unwrap();
+}
+
+/// Create an SSH key to sign with, if `ssh-keygen` is available
+fn ssh_signing_key(dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    if std::process::Command::new("ssh-keygen")
+        .arg("-?")
+        .output()
+        .is_err()
+    {
+        return None;
+    }
+
+    let key_path = dir.join("id_ed25519");
+    snapbox::cmd::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+        .arg(&key_path)
+        .assert()
+        .success();
+    Some(key_path)
+}
+
+fn is_signed(root_path: &std::path::Path, rev: &str) -> bool {
+    let repo = git2::Repository::open(root_path).unwrap();
+    let id = repo.revparse_single(rev).unwrap().id();
+    repo.extract_signature(&id, None).is_ok()
+}
+
+#[test]
+fn rebase_signs_with_ssh() {
+    let keys = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let Some(key_path) = ssh_signing_key(keys.path().unwrap()) else {
+        return;
+    };
+
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git_config(root_path, "gpg.format", "ssh");
+    git_config(root_path, "user.signingkey", key_path.to_str().unwrap());
+    git_config(root_path, "stack.gpgSign", "true");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--rebase")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let feature_id = repo.revparse_single("feature").unwrap().id();
+    let main_id = repo.revparse_single("main").unwrap().id();
+    assert_eq!(
+        repo.find_commit(feature_id).unwrap().parent_id(0).unwrap(),
+        main_id
+    );
+    let (signature, data) = repo.extract_signature(&feature_id, None).unwrap();
+    let signature = signature.as_str().unwrap();
+    assert!(
+        signature.starts_with("-----BEGIN SSH SIGNATURE-----"),
+        "{signature}"
+    );
+    let signature_path = keys.path().unwrap().join("commit.sig");
+    std::fs::write(&signature_path, signature).unwrap();
+    snapbox::cmd::Command::new("ssh-keygen")
+        .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+        .arg(&signature_path)
+        .stdin(data.to_vec())
+        .assert()
+        .success();
+
+    root.close().unwrap();
+    keys.close().unwrap();
+}
+
+#[test]
+fn rebase_sign_if_signed() {
+    let keys = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let Some(key_path) = ssh_signing_key(keys.path().unwrap()) else {
+        return;
+    };
+
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("unsigned".into()),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("signed".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git_config(root_path, "gpg.format", "ssh");
+    git_config(root_path, "user.signingkey", key_path.to_str().unwrap());
+    snapbox::cmd::Command::new("git")
+        .args(["commit", "--quiet", "--amend", "--no-edit", "-S"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert!(is_signed(root_path, "signed"));
+
+    // Without signing, the signature is reported before it is lost
+    git_config(root_path, "stack.sign", "never");
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--rebase", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+WARN: Rewriting may drop your signatures (set `stack.sign=if-signed` to re-sign them): [..]
+
+"#]]);
+
+    git(root_path, &["config", "stack.sign", "if-signed"]);
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("--rebase")
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let main_id = repo.revparse_single("main").unwrap().id();
+    let unsigned_id = repo.revparse_single("unsigned").unwrap().id();
+    assert_eq!(
+        repo.find_commit(unsigned_id).unwrap().parent_id(0).unwrap(),
+        main_id
+    );
+    assert!(!is_signed(root_path, "unsigned"));
+    assert!(is_signed(root_path, "signed"));
+
+    root.close().unwrap();
+    keys.close().unwrap();
+}
+
+#[test]
+fn show_signatures() {
+    let keys = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let keys_path = keys.path().unwrap();
+    let Some(key_path) = ssh_signing_key(keys_path) else {
+        return;
+    };
+    let other_keys_path = keys_path.join("other");
+    std::fs::create_dir_all(&other_keys_path).unwrap();
+    let other_key_path = ssh_signing_key(&other_keys_path).unwrap();
+    let public_key = std::fs::read_to_string(key_path.with_extension("pub")).unwrap();
+    let allowed_signers_path = keys_path.join("allowed_signers");
+    std::fs::write(&allowed_signers_path, format!("* {public_key}")).unwrap();
+
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git_config(root_path, "gpg.format", "ssh");
+    git_config(root_path, "user.signingkey", key_path.to_str().unwrap());
+    git_config(
+        root_path,
+        "gpg.ssh.allowedSignersFile",
+        allowed_signers_path.to_str().unwrap(),
+    );
+    for (message, key) in [("C", &key_path), ("D", &other_key_path)] {
+        snapbox::cmd::Command::new("git")
+            .args(["commit", "--quiet", "--allow-empty", "-m", message])
+            .arg(format!("--gpg-sign={}", key.display()))
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "-c", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["--show-signatures", "--show-commits=all"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main (no remote) A
+⌽ [..] (unsigned) B
+⌽ [..] (signed) C
+⌽ feature (ready) (unknown key) D
+
+"#]])
+        .stderr_eq(snapbox::str![]);
+
+    root.close().unwrap();
+    keys.close().unwrap();
+}
@@ -0,0 +1,84 @@
+use crate::fixture::tree;
+
+fn stack_fixture(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Label("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("first".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("third".into()),
+            git_fixture::Command::Reset("main".into()),
+            tree(&[("a", "a"), ("x", "x")], "X"),
+            git_fixture::Command::Branch("other".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+}
+
+#[test]
+fn porcelain() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "second"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["status", "--porcelain"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(
+            "\
+second 2/3 2 0 clean main
+",
+        );
+
+    std::fs::write(root_path.join("a"), "dirty").unwrap();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["status"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(
+            "\
+second 2/3 on main (2 ahead, 0 behind) dirty
+",
+        );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn porcelain_detached_on_base() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    stack_fixture(root_path);
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "--detach", "main"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["status", "--porcelain"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(
+            "\
+HEAD 0/4 0 0 clean main
+",
+        );
+
+    root.close().unwrap();
+}
@@ -0,0 +1,112 @@
+#[cfg(unix)]
+use crate::fixture::fake_gh;
+use crate::fixture::tree;
+
+#[test]
+#[cfg(unix)]
+fn submit_creates_and_retargets() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let remote_path = root_path.join("remote.git");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("second".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("unpushed".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+
+    git2::Repository::init_bare(&remote_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["remote", "add", "origin"])
+        .arg(&remote_path)
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["push", "--quiet", "origin", "main", "feature", "second"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    // `second` was stacked on the now-deleted `bottom`
+    std::fs::write(
+        local_path.join("prs.txt"),
+        "second me 13 bottom https://example.com/pull/13\n",
+    )
+    .unwrap();
+    let bin_path = root_path.join("bin");
+    fake_gh(&bin_path);
+    let path = std::env::join_paths(
+        std::iter::once(bin_path).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("submit")
+        .env("PATH", &path)
+        .env(
+            "GIT_CONFIG_PARAMETERS",
+            "'stack.forge'='github' 'stack.github-token'='secret'",
+        )
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+main
+└── feature https://example.com/pull/14
+    └── second https://example.com/pull/13
+        └── unpushed (not pushed)
+
+"#]])
+        .stderr_eq(snapbox::str![[r#"
+Creating pull request for feature onto main
+Retargeting #13 (second) from bottom to feature
+warning: skipping unpushed, push it first with `git stack --push`
+
+"#]]);
+
+    let changes = std::fs::read_to_string(local_path.join("changes.txt")).unwrap();
+    assert_eq!(
+        changes,
+        "create head=feature base=main title=B token=secret\nedit 13 feature\n"
+    );
+
+    root.close().unwrap();
+}
+
+#[test]
+fn submit_requires_forge() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("submit")
+        .current_dir(root_path)
+        .assert()
+        .code(78)
+        .stderr_eq(snapbox::str![[r#"
+no forge configured, set `stack.forge=github`
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,223 @@
+use crate::fixture::{conflicting_branches, tree};
+use snapbox::prelude::*;
+use snapbox::str;
+
+#[test]
+fn dry_run_warns_of_conflicts() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_branches(root_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+Would move feature from [..] onto main
+Would move other from [..] onto main
+WARN: Branch `feature` will conflict on [..] (B)
+WARN:   Blocked dependents: child
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn skip_stack_checked_out_elsewhere() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("main".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("c", "c"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("child".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(&local_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new("git")
+        .args(["worktree", "add", "--quiet", "../other", "child"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    let repo = git2::Repository::open(&local_path).unwrap();
+    let branch_id = |name| {
+        repo.find_branch(name, git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .target()
+            .unwrap()
+    };
+    let feature_id = branch_id("feature");
+    let child_id = branch_id("child");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+WARN: Leaving the stack with `child` in place, it is checked out in `[..]other/`
+...
+"#]]);
+
+    assert_eq!(branch_id("feature"), feature_id);
+    assert_eq!(branch_id("child"), child_id);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn skip_conflicts() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    conflicting_branches(root_path);
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let child_id = repo.find_local_branch("child").unwrap().id;
+    let main_id = repo.find_local_branch("main").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync", "--skip-conflicts"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+WARN: Leaving `feature` in place, it will conflict on [..] (B)
+WARN:   Blocked dependents: child
+...
+"#]]);
+
+    assert_eq!(repo.find_local_branch("feature").unwrap().id, feature_id);
+    assert_eq!(repo.find_local_branch("child").unwrap().id, child_id);
+    let other_id = repo.find_local_branch("other").unwrap().id;
+    let other = repo.raw().find_commit(other_id).unwrap();
+    assert_eq!(other.parent_id(0).unwrap(), main_id);
+    assert!(!git_stack::rewrite::Resume::path(repo.raw().path()).exists());
+
+    root.close().unwrap();
+}
+
+fn tagged_branch(root_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Tag("v1".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("d", "d")], "D"),
+            git_fixture::Command::Branch("main".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "feature"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn refuse_rewriting_tagged_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    tagged_branch(root_path);
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync"])
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+ERROR: Cannot rewrite [..] (B), tagged `v1`
+ERROR:   Use `--force` to rewrite anyway, leaving the tags on the original commits
+refusing to rewrite tagged commits
+
+"#]]);
+    assert_eq!(repo.find_local_branch("feature").unwrap().id, feature_id);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync", "--force"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+WARN: Rewriting [..] (B), leaving `v1` on the original commit
+...
+"#]]);
+    assert_ne!(repo.find_local_branch("feature").unwrap().id, feature_id);
+
+    // Already up to date, so the tagged commits are replayed in place
+    snapbox::cmd::Command::new("git")
+        .args(["tag", "v2", "feature~"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync"])
+        .current_dir(root_path)
+        .assert()
+        .success();
+
+    root.close().unwrap();
+}
+
+#[test]
+fn skip_up_to_date_branches() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("child".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    let repo = git2::Repository::open(root_path).unwrap();
+    let repo = git_stack::git::GitRepo::new(repo);
+    let feature_id = repo.find_local_branch("feature").unwrap().id;
+    let child_id = repo.find_local_branch("child").unwrap().id;
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["sync"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+2 branches already up to date
+note: to undo, run `git stack undo`
+
+"#]]);
+    assert_eq!(repo.find_local_branch("feature").unwrap().id, feature_id);
+    assert_eq!(repo.find_local_branch("child").unwrap().id, child_id);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,87 @@
+use crate::fixture::{git, tree};
+use snapbox::str;
+
+#[test]
+fn tidy_plans_then_cleans_up() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Label("base".into()),
+            git_fixture::Command::Branch("done".into()),
+            tree(&[("a", "a"), ("s", "s")], "S"),
+            git_fixture::Command::Branch("squashed".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("s", "s")], "Squashed S"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("s", "s"), ("f", "f")], "F"),
+            git_fixture::Command::Branch("feature".into()),
+            git_fixture::Command::Reset("base".into()),
+            tree(&[("a", "a"), ("o", "o")], "O"),
+            git_fixture::Command::Branch("other".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+    git(root_path, &["switch", "--quiet", "feature"]);
+    git(root_path, &["remote", "add", "origin", "https://example.com/repo.git"]);
+    git(root_path, &["update-ref", "refs/remotes/origin/other", "other"]);
+    git(root_path, &["config", "branch.feature.remote", "origin"]);
+    git(root_path, &["config", "branch.feature.merge", "refs/heads/feature"]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["tidy", "--no-fetch", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Would delete done (merged into `main`)
+Would delete squashed (squashed into `main`)
+Would untrack `origin/feature` for feature, it is gone
+Would track `origin/other` for other
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["tidy", "--no-fetch"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Delete done (merged into `main`)
+Delete squashed (squashed into `main`)
+Untrack `origin/feature` for feature, it is gone
+Track `origin/other` for other
+Tidied: deleted 2 branches, updated 2 upstreams
+note: to undo, run `git stack undo`
+
+"#]]);
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let mut branches: Vec<_> = repo
+        .branches(Some(git2::BranchType::Local))
+        .unwrap()
+        .map(|b| b.unwrap().0.name().unwrap().unwrap().to_owned())
+        .collect();
+    branches.sort();
+    assert_eq!(branches, ["feature", "main", "other"]);
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert!(config.get_str("branch.feature.merge").is_err());
+    assert_eq!(
+        config.get_str("branch.other.merge").unwrap(),
+        "refs/heads/other"
+    );
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["tidy", "--no-fetch"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+note: nothing to tidy up
+
+"#]]);
+
+    root.close().unwrap();
+}
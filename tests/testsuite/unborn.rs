@@ -0,0 +1,54 @@
+use snapbox::prelude::*;
+use snapbox::str;
+
+#[test]
+fn show_without_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    git2::Repository::init(root_path).unwrap();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+note: no commits yet, nothing to do
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("sync")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stdout_eq(str![].raw())
+        .stderr_eq(str![[r#"
+note: no commits yet, nothing to do
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn reword_without_commits() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    git2::Repository::init(root_path).unwrap();
+
+    for command in ["reword", "amend", "next", "prev", "why"] {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .arg(command)
+            .current_dir(root_path)
+            .assert()
+            .code(64)
+            .stdout_eq(str![].raw())
+            .stderr_eq(str![[r#"
+no commits yet, create one with `git commit` first
+
+"#]]);
+    }
+
+    root.close().unwrap();
+}
@@ -0,0 +1,82 @@
+use crate::fixture::tree;
+use snapbox::str;
+
+#[test]
+fn undo_walks_back_operations() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("target".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(root_path).unwrap();
+
+    let repo = git2::Repository::open(root_path).unwrap();
+    let summary = |repo: &git2::Repository| {
+        repo.revparse_single("target")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap()
+            .summary()
+            .unwrap()
+            .to_owned()
+    };
+
+    for message in ["first", "second"] {
+        snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+            .args(["reword", &format!("--message={message}")])
+            .current_dir(root_path)
+            .assert()
+            .success();
+    }
+    assert_eq!(summary(&repo), "second");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["undo", "--dry-run"])
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Would move target [..] -> [..]
+`reword` would be undone
+
+"#]]);
+    assert_eq!(summary(&repo), "second");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("undo")
+        .current_dir(root_path)
+        .assert()
+        .success()
+        .stderr_eq(str![[r#"
+Moving target [..] -> [..]
+Undid `reword`
+
+"#]]);
+    assert_eq!(summary(&repo), "first");
+    assert_eq!(repo.head().unwrap().shorthand(), Some("target"));
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("undo")
+        .current_dir(root_path)
+        .assert()
+        .success();
+    assert_eq!(summary(&repo), "B");
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .arg("undo")
+        .current_dir(root_path)
+        .assert()
+        .failure()
+        .stderr_eq(str![[r#"
+nothing to undo
+
+"#]]);
+
+    root.close().unwrap();
+}
@@ -0,0 +1,109 @@
+use crate::fixture::tree;
+
+fn stack(local_path: &std::path::Path) {
+    let plan = git_fixture::TodoList {
+        commands: vec![
+            tree(&[("a", "a")], "A"),
+            git_fixture::Command::Branch("main".into()),
+            tree(&[("a", "a"), ("b", "b")], "B"),
+            git_fixture::Command::Branch("feature".into()),
+            tree(&[("a", "a"), ("b", "b"), ("c", "c")], "C"),
+            git_fixture::Command::Branch("user/child".into()),
+        ],
+        ..Default::default()
+    };
+    plan.run(local_path).unwrap();
+}
+
+#[test]
+fn worktree_add_list_clean() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    stack(&local_path);
+    snapbox::cmd::Command::new("git")
+        .args(["switch", "--quiet", "main"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree", "feature"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+[..]/local.worktrees/feature
+
+"#]])
+        .stderr_eq(snapbox::str![[r#"
+Checked out `feature` at [..]/local.worktrees/feature
+
+"#]]);
+    let feature_path = root_path.join("local.worktrees/feature");
+    let worktree_repo = git2::Repository::open(&feature_path).unwrap();
+    assert_eq!(worktree_repo.head().unwrap().shorthand(), Some("feature"));
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree", "user/child"])
+        .current_dir(&local_path)
+        .assert()
+        .success();
+    std::fs::write(root_path.join("local.worktrees/user/child/d"), "d").unwrap();
+
+    // Listing works from any worktree
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree"])
+        .current_dir(&feature_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+[..]/local.worktrees/feature feature
+[..]/local.worktrees/user/child user/child
+
+"#]]);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree", "--clean"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stderr_eq(snapbox::str![[r#"
+Removed [..]/local.worktrees/feature
+WARN: Leaving `[..]/local.worktrees/user/child`, it has uncommitted changes
+
+"#]]);
+    assert!(!feature_path.exists());
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree"])
+        .current_dir(&local_path)
+        .assert()
+        .success()
+        .stdout_eq(snapbox::str![[r#"
+[..]/local.worktrees/user/child user/child
+
+"#]]);
+
+    root.close().unwrap();
+}
+
+#[test]
+fn worktree_refuses_checked_out_branch() {
+    let root = snapbox::dir::DirRoot::mutable_temp().unwrap();
+    let root_path = root.path().unwrap();
+    let local_path = root_path.join("local");
+    stack(&local_path);
+
+    snapbox::cmd::Command::new(snapbox::cmd::cargo_bin!("git-stack"))
+        .args(["worktree", "user/child"])
+        .current_dir(&local_path)
+        .assert()
+        .failure()
+        .stderr_eq(snapbox::str![[r#"
+`user/child` is already checked out at `[..]/local/`
+
+"#]]);
+
+    root.close().unwrap();
+}